@@ -1,47 +1,181 @@
 use crate::{
-    config::Config,
-    editor::Editor,
+    config::{manager::ConfigManager, Config, ThemeMode},
+    editor::{
+        autocomplete::Autocomplete, highlighter::MarkdownHighlighter, snippets::SnippetLibrary, Editor,
+    },
+    i18n::t,
+    keymap::{Action, Keymap},
     markdown::MarkdownRenderer,
+    platform::clipboard::ClipboardHistory,
     preview::Preview,
-    theme::Theme,
-    ui::layouts::EditorLayout,
+    startup::{Loaded, Settings, StartupLoad},
+    stats::{document::DocumentStats, WritingStats},
+    theme::{Chrome, Theme},
+    ui::{
+        breadcrumbs::Breadcrumbs, browser_preview::BrowserPreview, changes::ChangesView, chrome::PaneBackdrop, clipboard::ClipboardPicker, color_swatches::ColorSwatches, consolidate::ConsolidateDialog, document_settings::DocumentSettingsState, indentation::IndentationState, export::ExportDialog, find::FindBar, focus::PaneFocus, gutter::GutterDrag, heading_picker::HeadingPicker,
+        history::FileHistoryDialog, insert_image::InsertImageDialog, layouts::EditorLayout,
+        outline::PendingSectionMove, palette::CommandPalette, preview_header::PreviewHeader, problems::Problems, rename_heading::RenameHeadingDialog, scale::WindowScale, scratchpad::Scratchpad, settings_bundle::SettingsImport, sidebar::Sidebar, split::{MergeDialog, SplitDialog}, switcher::QuickSwitcher,
+        theme_switch::ThemeFade, toasts::{Severity, Toasts}, typing_aids::TypingAidsState, unsaved::Replacement, updates::Updates, watch::FileWatch, zoom::ZoomIndicator,
+    },
+    utils::{self, timing::{self, Phase}},
+    workspace::{export::FolderExport, links::BacklinkIndex, search::WorkspaceSearch},
 };
 use eframe::egui;
 use std::path::PathBuf;
+use std::time::Instant;
 
 pub struct RmdApp {
     // Core components
     pub editor: Editor,
     pub preview: Preview,
     pub markdown_renderer: MarkdownRenderer,
+    pub highlighter: MarkdownHighlighter,
 
     // UI state
     pub layout: EditorLayout,
     pub theme: Theme,
+    /// Light or Dark chosen with Toggle Theme, until restart
+    pub theme_override: Option<ThemeMode>,
+    /// Blend into a new theme in progress
+    pub theme_fade: Option<ThemeFade>,
     pub config: Config,
+    pub config_manager: ConfigManager,
+    pub autocomplete: Autocomplete,
+    pub snippets: SnippetLibrary,
+    /// Auto-correct table, and the substitution just made while typing
+    pub typing_aids: TypingAidsState,
+    pub keymap: Keymap,
+    /// Action whose shortcut is being recorded in Preferences
+    pub rebinding: Option<Action>,
+    pub breadcrumbs: Breadcrumbs,
+    pub sidebar: Sidebar,
+    pub changes: ChangesView,
+    /// Colors in the editor text and the picker open on one
+    pub color_swatches: ColorSwatches,
+    /// Pane styling from `theme.toml`, put on every theme
+    pub theme_chrome: Chrome,
+    /// Its background image
+    pub pane_backdrop: PaneBackdrop,
+    /// Update check in progress, and the new release it found
+    pub updates: Updates,
+    /// Page of the document opened in the browser
+    pub browser_preview: BrowserPreview,
+    /// Overrides from the open document's front matter
+    pub document_settings: DocumentSettingsState,
+    /// Indentation of the open document, detected or chosen in the status bar
+    pub indentation: IndentationState,
+    /// Where each pane is, for F6 and Ctrl+1..9 to move focus between them
+    pub pane_focus: PaneFocus,
+    /// Lines being selected by dragging in the editor gutter
+    pub gutter: GutterDrag,
+    pub writing_stats: WritingStats,
+    /// Quick notes kept apart from the document
+    pub scratchpad: Scratchpad,
+    /// Title, facts, and tags shown above the preview
+    pub preview_header: PreviewHeader,
+    /// What was copied or cut in the app, for Paste from History
+    pub clipboard: ClipboardHistory,
+    /// Text typed into the sidebar outline's filter box
+    pub outline_filter: String,
+    /// Section dropped in the outline where its levels don't fit
+    pub section_move: Option<PendingSectionMove>,
+    /// Scale factor last drawn at, and whether the window fits its monitor
+    pub window_scale: WindowScale,
+    /// Heading being renamed, with the links to it
+    pub rename_heading: Option<RenameHeadingDialog>,
+    /// Word counts of the document and the revision they were counted at
+    pub document_stats: Option<(u64, DocumentStats)>,
+
+    /// Settings and the first document loading in the background
+    pub startup: Option<StartupLoad>,
+    /// Document being read before it is shown, with a placeholder meanwhile
+    pub loading_document: Option<PathBuf>,
 
     // File state
     pub current_file: Option<PathBuf>,
     pub has_unsaved_changes: bool,
+    /// The current document is locked against edits and saving
+    pub readonly: bool,
+    /// When the "document is read-only" hint was last shown
+    pub readonly_hint: Option<Instant>,
+    /// Title last sent to the window
+    pub window_title: String,
+    /// Reload the document when it changes on disk, in any layout
+    pub watch_mode: bool,
+    pub file_watch: Option<FileWatch>,
+    /// The file changed on disk while the buffer had unsaved edits
+    pub disk_conflict: bool,
+    /// Asking whether to save before the window closes
+    pub close_prompt: bool,
+    /// The user chose to close the window despite unsaved changes
+    pub close_confirmed: bool,
+    /// Document to open once the user decides about unsaved changes
+    pub pending_replacement: Option<Replacement>,
+    /// Import Settings dialog
+    pub settings_import: SettingsImport,
+
+    // Workspace state
+    /// Folder opened in the sidebar
+    pub workspace_root: Option<PathBuf>,
+    /// Workspace to switch to once the user decides about unsaved changes
+    pub workspace_switch: Option<PathBuf>,
+    pub workspace_search: WorkspaceSearch,
+    pub backlinks: BacklinkIndex,
+    /// Missing note a wiki link pointed to, waiting for confirmation to create it
+    pub create_note_prompt: Option<PathBuf>,
+    /// Link with an unusual scheme, waiting for confirmation to open it
+    pub link_prompt: Option<String>,
+
+    // Dialogs
+    pub file_history: Option<FileHistoryDialog>,
+    /// Folder export in progress, or finished and showing its summary
+    pub folder_export: Option<FolderExport>,
+    /// Page export waiting on its options
+    pub export_dialog: Option<ExportDialog>,
+    pub split_dialog: Option<SplitDialog>,
+    pub merge_dialog: Option<MergeDialog>,
+    /// Images being gathered into the assets folder, or the summary of it
+    pub consolidate_dialog: Option<ConsolidateDialog>,
+    pub insert_image: Option<InsertImageDialog>,
+    pub palette: CommandPalette,
+    pub heading_picker: HeadingPicker,
+    pub clipboard_picker: ClipboardPicker,
+    pub find: FindBar,
+    pub switcher: QuickSwitcher,
+    /// Links found by Check Links
+    pub problems: Problems,
+    /// Zoom level flashed next to the pointer after Ctrl+wheel or a pinch
+    pub zoom_indicator: Option<ZoomIndicator>,
+    /// Notifications in the bottom-right corner
+    pub toasts: Toasts,
 
     // Panel visibility
     pub show_sidebar: bool,
     pub show_toolbar: bool,
     pub show_status_bar: bool,
+    pub show_search_panel: bool,
+    pub show_problems: bool,
+    pub show_preferences: bool,
+    /// Extension being typed into the Preferences list
+    pub new_extension: String,
+    pub show_shortcuts: bool,
+    /// Search text in the Keyboard Shortcuts window
+    pub shortcuts_filter: String,
+    pub show_about: bool,
+    /// Show the diff against the saved file in place of the preview
+    pub show_changes: bool,
 }
 
 impl RmdApp {
     pub fn new(cc: &eframe::CreationContext<'_>, config: Config) -> Self {
         // Initialize theme
-        let theme = Theme::from_mode(config.theme_mode);
+        let theme = Theme::from_mode(config.theme_mode, cc.egui_ctx.system_theme());
 
         // Apply theme to egui context
         theme.apply(&cc.egui_ctx);
 
-        // Load custom fonts if available
-        Self::load_custom_fonts(&cc.egui_ctx);
-
         let markdown_renderer = MarkdownRenderer::new(&theme);
+        let highlighter = MarkdownHighlighter::new(theme.clone());
         let editor = Editor::new();
         let preview = Preview::new();
 
@@ -50,26 +184,113 @@ impl RmdApp {
             config.split_direction,
         );
 
-        Self {
+        let mut app = Self {
             editor,
             preview,
             markdown_renderer,
+            highlighter,
             layout,
             theme,
+            theme_override: None,
+            theme_fade: None,
+            config_manager: ConfigManager::open_default(&config),
             config,
+            autocomplete: Autocomplete::new(),
+            snippets: SnippetLibrary::new(),
+            typing_aids: TypingAidsState::default(),
+            keymap: Keymap::new(),
+            rebinding: None,
+            breadcrumbs: Breadcrumbs::default(),
+            sidebar: Sidebar::default(),
+            changes: ChangesView::default(),
+            color_swatches: ColorSwatches::default(),
+            theme_chrome: Chrome::default(),
+            pane_backdrop: PaneBackdrop::default(),
+            updates: Updates::default(),
+            browser_preview: BrowserPreview::default(),
+            document_settings: DocumentSettingsState::default(),
+            indentation: IndentationState::default(),
+            pane_focus: PaneFocus::default(),
+            gutter: GutterDrag::default(),
+            writing_stats: WritingStats::load_default(),
+            scratchpad: Scratchpad::default(),
+            preview_header: PreviewHeader::default(),
+            clipboard: ClipboardHistory::load_default(),
+            outline_filter: String::new(),
+            section_move: None,
+            window_scale: WindowScale::default(),
+            rename_heading: None,
+            document_stats: None,
+            startup: None,
+            loading_document: None,
             current_file: None,
             has_unsaved_changes: false,
+            readonly: false,
+            readonly_hint: None,
+            watch_mode: false,
+            file_watch: None,
+            disk_conflict: false,
+            close_prompt: false,
+            close_confirmed: false,
+            pending_replacement: None,
+            settings_import: SettingsImport::default(),
+            window_title: String::new(),
+            workspace_root: None,
+            workspace_switch: None,
+            workspace_search: WorkspaceSearch::new(),
+            backlinks: BacklinkIndex::default(),
+            create_note_prompt: None,
+            link_prompt: None,
+            file_history: None,
+            folder_export: None,
+            export_dialog: None,
+            split_dialog: None,
+            merge_dialog: None,
+            consolidate_dialog: None,
+            insert_image: None,
+            palette: CommandPalette::default(),
+            heading_picker: HeadingPicker::default(),
+            clipboard_picker: ClipboardPicker::default(),
+            find: FindBar::default(),
+            switcher: QuickSwitcher::default(),
+            problems: Problems::default(),
+            zoom_indicator: None,
+            toasts: Toasts::default(),
             show_sidebar: true,
             show_toolbar: true,
             show_status_bar: true,
-        }
+            show_search_panel: false,
+            show_problems: false,
+            show_preferences: false,
+            new_extension: String::new(),
+            show_shortcuts: false,
+            shortcuts_filter: String::new(),
+            show_about: false,
+            show_changes: false,
+        };
+        app.apply_markdown_settings();
+        app
     }
 
-    fn load_custom_fonts(ctx: &egui::Context) {
-        let mut fonts = egui::FontDefinitions::default();
+    /// Push Markdown parsing settings from the config to the renderer and highlighter
+    pub fn apply_markdown_settings(&mut self) {
+        self.markdown_renderer.set_wiki_links(self.config.wiki_links);
+        self.markdown_renderer
+            .set_smart_punctuation(self.config.writing.smart_punctuation);
+        self.markdown_renderer
+            .set_emoji_shortcodes(self.config.preview.emoji_shortcodes);
+        self.markdown_renderer.set_render_html(self.config.preview.render_html);
+        let flavor = self.active_document_settings().and_then(|settings| settings.flavor);
+        self.markdown_renderer.set_flavor(flavor.unwrap_or(self.config.preview.flavor));
+        self.markdown_renderer.set_number_headings(self.config.preview.number_headings);
+        self.highlighter.set_wiki_links(self.config.wiki_links);
+    }
 
-        // Add Fira Code for monospace (code blocks)
-        if let Ok(font_data) = std::fs::read("assets/fonts/FiraCode-Regular.ttf") {
+    /// Use Fira Code for monospace (code blocks), and the fallback fonts
+    /// after the built-in ones in every family
+    fn set_custom_fonts(ctx: &egui::Context, font_data: Option<Vec<u8>>, fallbacks: Vec<(String, Vec<u8>)>) {
+        let mut fonts = egui::FontDefinitions::default();
+        if let Some(font_data) = font_data {
             fonts.font_data.insert(
                 "FiraCode".to_owned(),
                 egui::FontData::from_owned(font_data),
@@ -80,25 +301,106 @@ impl RmdApp {
                 .or_default()
                 .push("FiraCode".to_owned());
         }
-
+        for (name, data) in fallbacks {
+            fonts.font_data.insert(name.clone(), egui::FontData::from_owned(data));
+            for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+                fonts.families.entry(family).or_default().push(name.clone());
+            }
+        }
         ctx.set_fonts(fonts);
     }
+
+    /// Apply what the startup thread loaded since the last frame
+    fn poll_startup(&mut self, ctx: &egui::Context) {
+        let Some(startup) = &mut self.startup else {
+            return;
+        };
+        let (loaded, finished) = startup.poll();
+        for item in loaded {
+            match item {
+                Loaded::Settings(settings) => self.apply_startup_settings(ctx, *settings),
+                Loaded::Document(path, text) => {
+                    self.loading_document = None;
+                    if self.finish_open(&path, text) {
+                        self.readonly |= self.startup.as_ref().is_some_and(|startup| startup.readonly);
+                    }
+                }
+            }
+        }
+        if finished {
+            self.startup = None;
+            self.loading_document = None;
+        }
+    }
+
+    /// Swap the defaults the window started with for the loaded settings,
+    /// all in one frame
+    fn apply_startup_settings(&mut self, ctx: &egui::Context, settings: Settings) {
+        let Settings { config, keymap, snippets, corrections, font, fallback_fonts, chrome } = settings;
+        if font.is_some() || !fallback_fonts.is_empty() {
+            Self::set_custom_fonts(ctx, font, fallback_fonts);
+        }
+        self.config_manager = ConfigManager::open_default(&config);
+        self.layout = EditorLayout::new(config.layout_mode, config.split_direction);
+        self.config = config;
+        self.keymap = keymap;
+        self.snippets = snippets;
+        self.typing_aids.corrections = corrections;
+        self.theme_chrome = chrome;
+        crate::i18n::set_language(&self.config.language);
+        self.apply_theme_mode(ctx);
+        self.apply_markdown_settings();
+        if let Some(startup) = &mut self.startup {
+            startup.settings_applied = true;
+            if let Some(folder) = startup.workspace.take() {
+                self.open_workspace(folder);
+            }
+        }
+        self.check_for_updates_if_due();
+    }
 }
 
 impl eframe::App for RmdApp {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
-        // Handle file drops
-        ctx.input(|i| {
-            if !i.raw.dropped_files.is_empty() {
-                if let Some(file) = i.raw.dropped_files.first() {
-                    if let Some(ref path) = file.path {
-                        let _ = self.editor.open_file(path);
-                        self.current_file = Some(path.clone());
-                        self.has_unsaved_changes = false;
-                    }
+        let _frame = timing::span(Phase::Frame);
+        // Handle file drops: open the first Markdown file dropped
+        let dropped: Vec<PathBuf> = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|f| f.path.clone()).collect());
+        if !dropped.is_empty() {
+            let extensions = &self.config.markdown_extensions;
+            match dropped.iter().find(|path| utils::is_markdown_file(path, extensions)) {
+                Some(path) => {
+                    self.open_path(path);
+                }
+                None => {
+                    let message = t!("toast.not_markdown", extensions = extensions.join(", "));
+                    self.notify(Severity::Warning, message);
                 }
             }
-        });
+        }
+
+        // Settings and the document loaded in the background at startup
+        self.poll_startup(ctx);
+
+        // Window size and scale factor
+        self.follow_window_scale(ctx);
+
+        // Pick up changes other programs made to the document
+        self.poll_file_watch();
+
+        // Result of checking for a new release
+        self.poll_update_check(ctx);
+
+        // Settings the document overrides in its front matter
+        self.update_document_settings();
+
+        // Indentation of a document that just opened
+        self.update_indentation();
+
+        // Global keyboard shortcuts
+        self.handle_shortcuts(ctx);
+
+        // Follow the system light/dark setting, fading between themes
+        self.update_theme(ctx);
 
         // Top menu bar
         self.ui_menu_bar(ctx, frame);
@@ -108,6 +410,37 @@ impl eframe::App for RmdApp {
             self.ui_toolbar(ctx);
         }
 
+        // Heading path of the caret
+        if self.config.show_breadcrumbs {
+            self.ui_breadcrumbs(ctx);
+        }
+
+        // Workspace file tree
+        if self.show_sidebar {
+            self.ui_sidebar(ctx);
+        }
+
+        // Workspace search panel
+        if self.show_search_panel {
+            self.ui_search_panel(ctx);
+        }
+
+        // Offer to reload a document changed on disk under unsaved edits
+        self.ui_disk_conflict(ctx);
+
+        // Find in the editor or the preview
+        self.ui_find_bar(ctx);
+
+        // Broken links found by Check Links
+        if self.show_problems {
+            self.ui_problems_panel(ctx);
+        }
+
+        // Quick notes kept apart from the document
+        if self.config.window.scratchpad_open {
+            self.ui_scratchpad(ctx);
+        }
+
         // Main content area with sidebar and editor/preview
         egui::CentralPanel::default()
             .frame(egui::Frame::central_panel(&ctx.style()).inner_margin(0.0))
@@ -120,6 +453,106 @@ impl eframe::App for RmdApp {
             self.ui_status_bar(ctx);
         }
 
+        // Original text of a clicked change marker
+        self.ui_change_popup(ctx);
+
+        // Picking a new color for a clicked gutter swatch
+        self.ui_color_picker(ctx);
+
+        // Putting back what a typing aid changed
+        self.ui_substitution_popup(ctx);
+
+        // Confirmation for creating a note from a wiki link
+        self.ui_create_note_prompt(ctx);
+
+        // Confirmation for opening a link with an unusual scheme
+        self.ui_link_prompt(ctx);
+
+        // Whether a section dragged in the outline changes levels
+        self.ui_section_move_prompt(ctx);
+        self.ui_rename_heading(ctx);
+
+        // Unsaved changes when replacing the document, switching
+        // workspaces, or closing the window
+        self.ui_replace_prompt(ctx);
+        self.ui_workspace_switch_prompt(ctx);
+        self.intercept_close(ctx);
+        self.ui_close_prompt(ctx);
+        self.ui_settings_import(ctx);
+
+        // Preferences dialog
+        if self.show_preferences {
+            self.ui_preferences(ctx);
+        }
+
+        // File History dialog
+        self.ui_file_history(ctx);
+
+        // Options for exporting a page
+        self.ui_export_dialog(ctx);
+        self.ui_split_dialog(ctx);
+        self.ui_merge_dialog(ctx);
+        self.ui_consolidate_dialog(ctx);
+
+        // Folder export progress and summary
+        self.ui_folder_export(ctx);
+
+        // Insert Image dialog
+        self.ui_insert_image(ctx);
+
+        // Keyboard Shortcuts help and About, closed by Escape
+        self.close_help_on_escape(ctx);
+        if self.show_shortcuts {
+            self.ui_shortcuts_help(ctx);
+        }
+        if self.show_about {
+            self.ui_about(ctx);
+        }
+        self.ui_update_dialog(ctx);
+
+        // Command palette
+        self.ui_command_palette(ctx);
+        self.ui_heading_picker(ctx);
+        self.ui_clipboard_picker(ctx);
+
+        // Quick file switcher
+        self.ui_quick_switcher(ctx);
+
+        // Hint shown when editing a read-only document
+        self.ui_readonly_hint(ctx);
+
+        // Notifications, including failures to open, save, or export
+        self.ui_toasts(ctx);
+
+        // Zoom level after Ctrl+wheel or a pinch
+        self.ui_zoom_indicator(ctx);
+
+        // Frame timings and cache use, when turned on
+        self.ui_diagnostics(ctx);
+
+        // Keep the page opened in the browser up to date as the document changes
+        self.update_browser_preview(ctx);
+
+        // After everything that can rename the document or change its dirty state
+        self.update_window_title(ctx);
+
+        // Persist settings changed this frame, including the layout
+        self.config.layout_mode = self.layout.mode;
+        self.config.split_direction = self.layout.split_direction;
+        // Not before the settings are in, so the defaults are never saved
+        if self.startup.as_ref().is_none_or(|startup| startup.settings_applied) {
+            self.config_manager.update(&self.config);
+            self.clipboard.set_persist(self.config.editor.keep_clipboard_history);
+        }
+        // Everything copied through egui this frame, the editor's copies
+        // and cuts included
+        let copied = ctx.output(|o| o.copied_text.clone());
+        if !copied.is_empty() {
+            self.clipboard.record(&copied);
+        }
+        self.writing_stats.save_if_due();
+        self.save_scratchpad(false);
+
         // Auto-save check (stub)
         if self.config.auto_save && self.has_unsaved_changes {
             // TODO: implement auto-save
@@ -130,9 +563,15 @@ impl eframe::App for RmdApp {
     }
 
     fn on_exit(&mut self, _ctx: Option<&eframe::glow::Context>) {
-        // Save config on exit
-        if let Err(e) = self.config.save() {
-            eprintln!("Failed to save config: {}", e);
+        // Write any settings changed since the last save before quitting
+        self.config_manager.flush(&self.config);
+        self.writing_stats.save();
+        self.save_reading_position();
+        self.save_position();
+        self.save_workspace_state();
+        if let Err(e) = self.scratchpad.save_if_due(true) {
+            log::warn!("Failed to save the scratchpad: {}", e);
         }
+        self.browser_preview.clean_up();
     }
 }
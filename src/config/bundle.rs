@@ -0,0 +1,253 @@
+//! Settings bundles: the config, keyboard shortcuts, snippets and
+//! auto-correct table in one zip file, for moving settings to another
+//! computer. Importing one first backs up the files it replaces, so the
+//! import can be undone.
+
+use super::{Config, SETTINGS_VERSION};
+use crate::utils::zip::{self, ZipWriter};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Files of the settings folder a bundle holds, the config first
+pub const BUNDLE_FILES: [&str; 4] = ["config.toml", "keybindings.toml", "snippets.toml", "autocorrect.toml"];
+
+/// What made the bundle, stored in it beside the settings
+const MANIFEST: &str = "manifest.toml";
+
+/// Folder in the settings folder holding what the last import replaced
+const BACKUP_FOLDER: &str = "settings-backup";
+
+/// Names of the files in the backup the import added rather than
+/// replaced, to remove when restoring
+const ADDED_LIST: &str = "added.txt";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    settings_version: u32,
+    app_version: String,
+}
+
+/// What importing a bundle does to one file of the settings folder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Replaces,
+    Adds,
+    Unchanged,
+}
+
+/// The bundle of the settings in `folder`, with `config` as the config
+pub fn export(folder: &Path, config: &Config) -> anyhow::Result<Vec<u8>> {
+    let manifest = Manifest { settings_version: SETTINGS_VERSION, app_version: env!("CARGO_PKG_VERSION").to_string() };
+    let mut zip = ZipWriter::new();
+    zip.add(MANIFEST, toml::to_string_pretty(&manifest)?.as_bytes());
+    zip.add(BUNDLE_FILES[0], toml::to_string_pretty(config)?.as_bytes());
+    for name in &BUNDLE_FILES[1..] {
+        match std::fs::read(folder.join(name)) {
+            Ok(contents) => zip.add(name, &contents),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(anyhow::anyhow!("Failed to read {}: {}", name, e)),
+        }
+    }
+    Ok(zip.finish())
+}
+
+/// A bundle read and checked, ready to import
+#[derive(Debug)]
+pub struct Bundle {
+    /// Version of the app that made it
+    pub app_version: String,
+    /// Each file, its name and contents, with the config brought up to
+    /// the current layout
+    files: Vec<(String, Vec<u8>)>,
+}
+
+impl Bundle {
+    /// Read a bundle, refusing one from a newer version or holding a file
+    /// that doesn't parse
+    pub fn read(archive: &[u8]) -> anyhow::Result<Self> {
+        let files = zip::read(archive)?;
+        let file = |name: &str| files.iter().find(|(file, _)| file == name).map(|(_, contents)| contents);
+        let manifest = file(MANIFEST).ok_or_else(|| anyhow::anyhow!("This is not a settings bundle"))?;
+        let manifest: Manifest = toml::from_str(std::str::from_utf8(manifest)?)?;
+        anyhow::ensure!(
+            manifest.settings_version <= SETTINGS_VERSION,
+            "These settings were exported by a newer version of rmd ({}) and can't be read by this one",
+            manifest.app_version
+        );
+
+        let mut checked = Vec::new();
+        for name in BUNDLE_FILES {
+            let Some(contents) = file(name) else {
+                anyhow::ensure!(name != BUNDLE_FILES[0], "The bundle has no {}", name);
+                continue;
+            };
+            let parse = || -> anyhow::Result<Vec<u8>> {
+                let mut value: toml::Value = toml::from_str(std::str::from_utf8(contents)?)?;
+                if name != BUNDLE_FILES[0] {
+                    return Ok(contents.clone());
+                }
+                Config::migrate(&mut value);
+                let config: Config = value.try_into()?;
+                Ok(toml::to_string_pretty(&config)?.into_bytes())
+            };
+            let contents = parse().map_err(|e| anyhow::anyhow!("{} in the bundle is not valid: {}", name, e))?;
+            checked.push((name.to_string(), contents));
+        }
+        Ok(Self { app_version: manifest.app_version, files: checked })
+    }
+
+    /// What importing does to each file of the bundle in `folder`
+    pub fn changes(&self, folder: &Path) -> Vec<(&str, Change)> {
+        self.files
+            .iter()
+            .map(|(name, contents)| {
+                let change = match std::fs::read(folder.join(name)) {
+                    Ok(current) if current == *contents => Change::Unchanged,
+                    Ok(_) => Change::Replaces,
+                    Err(_) => Change::Adds,
+                };
+                (name.as_str(), change)
+            })
+            .collect()
+    }
+
+    /// Write the bundle's files into `folder`, after backing up those it
+    /// replaces. Every file is written beside its target before any is
+    /// moved into place, and a failure part way puts back what was there.
+    pub fn import(&self, folder: &Path) -> anyhow::Result<()> {
+        std::fs::create_dir_all(folder)?;
+        let backup = folder.join(BACKUP_FOLDER);
+        if backup.exists() {
+            std::fs::remove_dir_all(&backup)?;
+        }
+        std::fs::create_dir_all(&backup)?;
+        let mut added = String::new();
+        for (name, _) in &self.files {
+            let path = folder.join(name);
+            if path.exists() {
+                std::fs::copy(&path, backup.join(name))?;
+            } else {
+                added.push_str(name);
+                added.push('\n');
+            }
+        }
+        std::fs::write(backup.join(ADDED_LIST), added)?;
+
+        let staged = |name: &str| folder.join(format!("{}.import", name));
+        for (name, contents) in &self.files {
+            std::fs::write(staged(name), contents)?;
+        }
+        for (name, _) in &self.files {
+            if let Err(e) = std::fs::rename(staged(name), folder.join(name)) {
+                for (name, _) in &self.files {
+                    let _ = std::fs::remove_file(staged(name));
+                }
+                restore_backup(folder)?;
+                return Err(anyhow::anyhow!("Failed to write {}: {}", name, e));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether the last import's backup is still in `folder`
+pub fn has_backup(folder: &Path) -> bool {
+    folder.join(BACKUP_FOLDER).join(ADDED_LIST).exists()
+}
+
+/// Put back the settings the last import replaced, and remove the files
+/// it added
+pub fn restore_backup(folder: &Path) -> anyhow::Result<()> {
+    let backup = folder.join(BACKUP_FOLDER);
+    let added = std::fs::read_to_string(backup.join(ADDED_LIST))?;
+    for name in BUNDLE_FILES {
+        let saved = backup.join(name);
+        if saved.exists() {
+            std::fs::copy(&saved, folder.join(name))?;
+        } else if added.lines().any(|line| line == name) {
+            let path = folder.join(name);
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+    }
+    std::fs::remove_dir_all(&backup)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    #[test]
+    fn test_round_trip() {
+        let folder = test_dir("bundle-round-trip");
+        let mut config = Config { wiki_links: true, ..Default::default() };
+        config.writing.auto_dashes = true;
+        std::fs::write(folder.join("config.toml"), toml::to_string_pretty(&config).unwrap()).unwrap();
+        std::fs::write(folder.join("keybindings.toml"), "[bindings]\nbold = \"Ctrl+Shift+B\"\n").unwrap();
+        std::fs::write(folder.join("autocorrect.toml"), "[replacements]\nteh = \"the\"\n").unwrap();
+        let archive = export(&folder, &config).unwrap();
+
+        // Wipe the settings and bring them back from the bundle
+        std::fs::remove_dir_all(&folder).unwrap();
+        let bundle = Bundle::read(&archive).unwrap();
+        assert_eq!(bundle.app_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(
+            bundle.changes(&folder),
+            [("config.toml", Change::Adds), ("keybindings.toml", Change::Adds), ("autocorrect.toml", Change::Adds)]
+        );
+        bundle.import(&folder).unwrap();
+        assert_eq!(Config::load_from(&folder.join("config.toml")), config);
+        assert_eq!(std::fs::read_to_string(folder.join("autocorrect.toml")).unwrap(), "[replacements]\nteh = \"the\"\n");
+        assert!(bundle.changes(&folder).iter().all(|(_, change)| *change == Change::Unchanged));
+
+        // Undoing it removes what it added
+        assert!(has_backup(&folder));
+        restore_backup(&folder).unwrap();
+        assert!(!folder.join("config.toml").exists() && !has_backup(&folder));
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_import_backs_up_and_restores() {
+        let folder = test_dir("bundle-restore");
+        std::fs::write(folder.join("config.toml"), "wiki_links = true\n").unwrap();
+        std::fs::write(folder.join("snippets.toml"), "mine = 1\n").unwrap();
+        let archive = export(&folder, &Config::default()).unwrap();
+
+        std::fs::write(folder.join("config.toml"), "auto_save = true\n").unwrap();
+        let bundle = Bundle::read(&archive).unwrap();
+        assert_eq!(bundle.changes(&folder), [("config.toml", Change::Replaces), ("snippets.toml", Change::Unchanged)]);
+        bundle.import(&folder).unwrap();
+        assert!(!Config::load_from(&folder.join("config.toml")).auto_save);
+        restore_backup(&folder).unwrap();
+        assert_eq!(std::fs::read_to_string(folder.join("config.toml")).unwrap(), "auto_save = true\n");
+        assert_eq!(std::fs::read_to_string(folder.join("snippets.toml")).unwrap(), "mine = 1\n");
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_versions_and_validation() {
+        let bundle = |manifest: &str, config: &str| {
+            let mut zip = ZipWriter::new();
+            zip.add(MANIFEST, manifest.as_bytes());
+            zip.add("config.toml", config.as_bytes());
+            Bundle::read(&zip.finish())
+        };
+        // A bundle from before settings had a version is brought up to date
+        let old = bundle("settings_version = 0\napp_version = \"0.1.0\"\n", "wiki_links = true\n").unwrap();
+        let config: Config = toml::from_str(std::str::from_utf8(&old.files[0].1).unwrap()).unwrap();
+        assert!(config.wiki_links);
+        assert_eq!(config.settings_version, SETTINGS_VERSION);
+
+        let newer = bundle("settings_version = 99\napp_version = \"9.0.0\"\n", "").unwrap_err();
+        assert!(newer.to_string().contains("newer version of rmd (9.0.0)"));
+        let invalid = bundle("settings_version = 1\napp_version = \"0.1.0\"\n", "wiki_links = \"yes\"\n").unwrap_err();
+        assert!(invalid.to_string().starts_with("config.toml in the bundle is not valid"));
+        let mut zip = ZipWriter::new();
+        zip.add("config.toml", b"");
+        assert!(Bundle::read(&zip.finish()).is_err());
+    }
+}
@@ -0,0 +1,229 @@
+//! Saving the config in the background whenever it changes. Every window
+//! is its own process with its own copy of the config, so each write keeps
+//! what other windows wrote to settings this one left alone.
+
+use super::Config;
+use crate::editor::save;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How long the config must stay unchanged before it is written, so
+/// dragging a slider doesn't write the file every frame
+pub const SAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// Watches the config for changes and writes it on a background thread
+pub struct ConfigManager {
+    /// Config as last sent to the writer
+    saved: Config,
+    /// When the config last differed from `saved`, if it still does
+    changed_at: Option<Instant>,
+    writer: Option<Writer>,
+    /// Failures to report to the user, from the writer thread or setup
+    errors: Receiver<String>,
+}
+
+/// Background thread writing configs to one file in order
+struct Writer {
+    sender: Sender<toml::Value>,
+    thread: JoinHandle<()>,
+}
+
+impl ConfigManager {
+    /// Manage `config`, as loaded from `path`
+    pub fn new(path: PathBuf, config: &Config) -> Self {
+        let (sender, receiver) = mpsc::channel::<toml::Value>();
+        let (error_sender, errors) = mpsc::channel::<String>();
+        // The config as this window last knew it, to tell its own changes
+        // from another window's
+        let mut base = toml::Value::try_from(config).ok();
+        let thread = std::thread::spawn(move || {
+            for ours in receiver {
+                let merged = match (&base, read_value(&path)) {
+                    (Some(base), Some(theirs)) => merge(base, &ours, &theirs),
+                    _ => ours.clone(),
+                };
+                let result = toml::to_string_pretty(&merged)
+                    .map_err(|e| e.to_string())
+                    .and_then(|contents| write(&path, &contents));
+                if let Err(e) = result {
+                    log::error!("Failed to save config to {}: {}", path.display(), e);
+                    let _ = error_sender.send(format!("{}: {}", path.display(), e));
+                }
+                base = Some(ours);
+            }
+        });
+        Self {
+            saved: config.clone(),
+            changed_at: None,
+            writer: Some(Writer { sender, thread }),
+            errors,
+        }
+    }
+
+    /// Manage `config` in the default config file
+    pub fn open_default(config: &Config) -> Self {
+        match Config::config_path() {
+            Ok(path) => Self::new(path, config),
+            Err(e) => {
+                log::warn!("Settings will not be saved: {}", e);
+                let (error_sender, errors) = mpsc::channel();
+                let _ = error_sender.send(format!("Settings will not be saved: {}", e));
+                Self {
+                    saved: config.clone(),
+                    changed_at: None,
+                    writer: None,
+                    errors,
+                }
+            }
+        }
+    }
+
+    /// Note any change to `config` since the last call, and queue a write
+    /// once it has stopped changing for a moment
+    pub fn update(&mut self, config: &Config) {
+        let now = Instant::now();
+        if *config != self.saved {
+            self.saved = config.clone();
+            self.changed_at = Some(now);
+        }
+        if self.changed_at.is_some_and(|at| now - at >= SAVE_DELAY) {
+            self.write();
+        }
+    }
+
+    /// Queue any pending change and wait until everything is on disk
+    pub fn flush(&mut self, config: &Config) {
+        if *config != self.saved {
+            self.saved = config.clone();
+            self.changed_at = Some(Instant::now());
+        }
+        if self.changed_at.is_some() {
+            self.write();
+        }
+        if let Some(writer) = self.writer.take() {
+            drop(writer.sender);
+            if writer.thread.join().is_err() {
+                log::error!("Config writer thread panicked");
+            }
+        }
+    }
+
+    /// The next failure to write the config that the user hasn't been told of
+    pub fn take_error(&self) -> Option<String> {
+        self.errors.try_recv().ok()
+    }
+
+    fn write(&mut self) {
+        self.changed_at = None;
+        let Some(writer) = &self.writer else {
+            return;
+        };
+        match toml::Value::try_from(&self.saved) {
+            Ok(value) => {
+                if writer.sender.send(value).is_err() {
+                    log::error!("Config writer thread has stopped");
+                }
+            }
+            Err(e) => log::error!("Failed to serialize config: {}", e),
+        }
+    }
+}
+
+/// The config file at `path` as it is now, if it can be read
+fn read_value(path: &Path) -> Option<toml::Value> {
+    toml::from_str(&std::fs::read_to_string(path).ok()?).ok()
+}
+
+/// `theirs`, the file as another window left it, with the settings changed
+/// from `base` to `ours` in this window written over it. Tables merge key
+/// by key; any other value is replaced whole, the last writer winning.
+fn merge(base: &toml::Value, ours: &toml::Value, theirs: &toml::Value) -> toml::Value {
+    let (toml::Value::Table(base), toml::Value::Table(ours), toml::Value::Table(theirs)) = (base, ours, theirs) else {
+        return if ours == base { theirs.clone() } else { ours.clone() };
+    };
+    let mut merged = theirs.clone();
+    for (key, value) in ours {
+        let merged_value = match (base.get(key), theirs.get(key)) {
+            (Some(base), Some(theirs)) => merge(base, value, theirs),
+            (Some(base), None) if base == value => continue,
+            _ => value.clone(),
+        };
+        merged.insert(key.clone(), merged_value);
+    }
+    // Settings this window removed
+    for key in base.keys().filter(|key| !ours.contains_key(*key)) {
+        merged.remove(key);
+    }
+    toml::Value::Table(merged)
+}
+
+/// Replace `path` with `contents` the way documents are saved, creating
+/// its folder the first time
+fn write(path: &Path, contents: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    save::write_atomic(path, contents.as_bytes()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_are_written_after_a_quiet_period() {
+        let dir = std::env::temp_dir().join(format!("rmd-config-manager-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        let mut config = Config::default();
+        let mut manager = ConfigManager::new(path.clone(), &config);
+
+        config.wiki_links = true;
+        manager.update(&config);
+        assert!(manager.changed_at.is_some());
+
+        // Pretend the change happened long enough ago
+        manager.changed_at = Some(Instant::now() - SAVE_DELAY);
+        manager.update(&config);
+        assert!(manager.changed_at.is_none());
+
+        config.show_breadcrumbs = false;
+        manager.flush(&config);
+        let saved = Config::load_from(&path);
+        assert!(saved.wiki_links);
+        assert!(!saved.show_breadcrumbs);
+        // Nothing is left beside it from writing
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_windows_keep_each_others_changes() {
+        let dir = std::env::temp_dir().join(format!("rmd-config-windows-{}", std::process::id()));
+        let path = dir.join("config.toml");
+        let mut first = Config::default();
+        let mut second = Config::default();
+        let mut first_manager = ConfigManager::new(path.clone(), &first);
+        let mut second_manager = ConfigManager::new(path.clone(), &second);
+
+        first.wiki_links = true;
+        first_manager.flush(&first);
+        // The second window never saw the first's change, and doesn't undo it
+        second.show_breadcrumbs = false;
+        second_manager.flush(&second);
+        let saved = Config::load_from(&path);
+        assert!(saved.wiki_links);
+        assert!(!saved.show_breadcrumbs);
+
+        // The same setting changed in both: the last write wins
+        let mut third_manager = ConfigManager::new(path.clone(), &saved);
+        let mut third = saved.clone();
+        third.wiki_links = false;
+        third_manager.flush(&third);
+        assert!(!Config::load_from(&path).wiki_links);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
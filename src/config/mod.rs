@@ -0,0 +1,816 @@
+pub mod bundle;
+pub mod manager;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::markdown::cleanup::Cleanup;
+use crate::markdown::colors::ColorRules;
+use crate::markdown::export::{ExportStyle, PageSize};
+use crate::markdown::flavor::Flavor;
+use crate::utils;
+use crate::ui::layouts::{LayoutMode, SplitDirection};
+
+/// Version of the settings' layout, raised whenever settings written by
+/// an older version need converting; see `Config::migrate`
+pub const SETTINGS_VERSION: u32 = 1;
+
+/// Application configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    /// Layout version the settings were written with, 0 from before it
+    /// was recorded
+    #[serde(default)]
+    pub settings_version: u32,
+
+    /// Theme mode (light, dark, or system)
+    #[serde(default)]
+    pub theme_mode: ThemeMode,
+
+    /// Editor layout mode
+    #[serde(default)]
+    pub layout_mode: LayoutMode,
+
+    /// Split direction for split layouts
+    #[serde(default)]
+    pub split_direction: SplitDirection,
+
+    /// Font settings
+    #[serde(default)]
+    pub font: FontConfig,
+
+    /// Editor behavior settings
+    #[serde(default)]
+    pub editor: EditorConfig,
+
+    /// Auto-save settings
+    #[serde(default)]
+    pub auto_save: bool,
+
+    /// Window state
+    #[serde(default)]
+    pub window: WindowConfig,
+
+    /// Show the heading breadcrumb bar above the editor
+    #[serde(default = "default_true")]
+    pub show_breadcrumbs: bool,
+
+    /// Parse `[[Note Name]]` as links to other documents in the workspace
+    #[serde(default)]
+    pub wiki_links: bool,
+
+    /// File extensions treated as Markdown, lowercase without the dot
+    #[serde(default = "default_markdown_extensions")]
+    pub markdown_extensions: Vec<String>,
+
+    /// Recently opened or saved files, most recent first
+    #[serde(default)]
+    pub recent_files: Vec<PathBuf>,
+
+    /// Recently opened workspace folders, most recent first
+    #[serde(default)]
+    pub recent_folders: Vec<PathBuf>,
+
+    /// Workspace folders kept at the top of the recent folders, in the
+    /// order they were pinned
+    #[serde(default)]
+    pub pinned_folders: Vec<PathBuf>,
+
+    /// Typing aids and typography
+    #[serde(default)]
+    pub writing: WritingConfig,
+
+    /// Local snapshots of saved files
+    #[serde(default)]
+    pub history: HistoryConfig,
+
+    /// Rendering options for the preview
+    #[serde(default)]
+    pub preview: PreviewConfig,
+
+    /// Local writing statistics
+    #[serde(default)]
+    pub stats: StatsConfig,
+
+    /// HTML export options
+    #[serde(default)]
+    pub export: ExportConfig,
+
+    /// Swatches beside color literals
+    #[serde(default)]
+    pub colors: ColorConfig,
+
+    /// Checking for a newer release
+    #[serde(default)]
+    pub updates: UpdateConfig,
+
+    /// Interface language, such as `de`; empty follows the system
+    #[serde(default)]
+    pub language: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeMode {
+    Light,
+    Dark,
+    System,
+}
+
+impl Default for ThemeMode {
+    fn default() -> Self {
+        ThemeMode::System
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FontConfig {
+    #[serde(default = "default_editor_font")]
+    pub editor_font: String,
+    #[serde(default = "default_editor_font_size")]
+    pub editor_font_size: f32,
+    #[serde(default = "default_ui_font")]
+    pub ui_font: String,
+    #[serde(default = "default_ui_font_size")]
+    pub ui_font_size: f32,
+    #[serde(default)]
+    pub line_height: f32,
+    /// Font files tried for characters the built-in fonts lack, such as a
+    /// CJK font, in order
+    #[serde(default)]
+    pub fallback_fonts: Vec<String>,
+}
+
+fn default_editor_font() -> String {
+    "Fira Code".to_string()
+}
+
+fn default_editor_font_size() -> f32 {
+    14.0
+}
+
+fn default_ui_font() -> String {
+    "Segoe UI".to_string()
+}
+
+fn default_ui_font_size() -> f32 {
+    14.0
+}
+
+impl Default for FontConfig {
+    fn default() -> Self {
+        Self {
+            editor_font: default_editor_font(),
+            editor_font_size: default_editor_font_size(),
+            ui_font: default_ui_font(),
+            ui_font_size: default_ui_font_size(),
+            line_height: 1.5,
+            fallback_fonts: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditorConfig {
+    #[serde(default = "default_true")]
+    pub word_wrap: bool,
+    #[serde(default = "default_true")]
+    pub show_line_numbers: bool,
+    #[serde(default = "default_true")]
+    pub highlight_current_line: bool,
+    #[serde(default = "default_true")]
+    pub auto_indent: bool,
+    #[serde(default = "default_true")]
+    pub use_spaces_for_tabs: bool,
+    #[serde(default = "default_tab_size")]
+    pub tab_size: usize,
+    /// On open, indent as the file already is rather than as set above
+    #[serde(default = "default_true")]
+    pub detect_indentation: bool,
+    #[serde(default = "default_auto_save_interval")]
+    pub auto_save_interval_seconds: u64,
+    /// Column of the line-length guide and hard wrapping; 0 hides the guide
+    #[serde(default = "default_ruler_column")]
+    pub ruler_column: usize,
+    /// Faint lines at each tab stop within leading whitespace
+    #[serde(default)]
+    pub show_indent_guides: bool,
+    /// Marks for spaces, tabs and line ends in leading and trailing whitespace
+    #[serde(default)]
+    pub show_whitespace: bool,
+    /// Mark whitespace between words as well
+    #[serde(default)]
+    pub whitespace_everywhere: bool,
+    /// On save, trim whitespace at line ends outside code, keeping
+    /// two-space hard breaks
+    #[serde(default)]
+    pub trim_trailing_whitespace: bool,
+    /// On save, end the file with exactly one line break
+    #[serde(default)]
+    pub ensure_final_newline: bool,
+    /// On save, collapse three or more blank lines in a row into one
+    #[serde(default)]
+    pub collapse_blank_lines: bool,
+    /// Keep the history of what was copied in the settings folder between
+    /// sessions, rather than only in memory
+    #[serde(default)]
+    pub keep_clipboard_history: bool,
+}
+
+impl EditorConfig {
+    /// The cleanups to make on save
+    pub fn save_cleanup(&self) -> Cleanup {
+        Cleanup {
+            trim_trailing_whitespace: self.trim_trailing_whitespace,
+            final_newline: self.ensure_final_newline,
+            collapse_blank_lines: self.collapse_blank_lines,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_markdown_extensions() -> Vec<String> {
+    utils::DEFAULT_MARKDOWN_EXTENSIONS.map(String::from).to_vec()
+}
+
+fn default_tab_size() -> usize {
+    4
+}
+
+fn default_auto_save_interval() -> u64 {
+    30
+}
+
+fn default_ruler_column() -> usize {
+    80
+}
+
+impl Default for EditorConfig {
+    fn default() -> Self {
+        Self {
+            word_wrap: true,
+            show_line_numbers: true,
+            highlight_current_line: true,
+            auto_indent: true,
+            use_spaces_for_tabs: true,
+            tab_size: default_tab_size(),
+            detect_indentation: true,
+            auto_save_interval_seconds: 30,
+            ruler_column: default_ruler_column(),
+            show_indent_guides: false,
+            show_whitespace: false,
+            whitespace_everywhere: false,
+            trim_trailing_whitespace: false,
+            ensure_final_newline: false,
+            collapse_blank_lines: false,
+            keep_clipboard_history: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WritingConfig {
+    /// Show curly quotes, en/em dashes, and ellipses in the preview
+    #[serde(default)]
+    pub smart_punctuation: bool,
+    /// Rewrite straight punctuation in the source itself when saving
+    #[serde(default)]
+    pub smart_punctuation_on_save: bool,
+    /// Deepest heading level listed in a table of contents
+    #[serde(default = "default_toc_depth")]
+    pub toc_depth: u8,
+    /// Regenerate the table of contents and list of figures when saving
+    #[serde(default)]
+    pub update_toc_on_save: bool,
+    /// Capitalize the first letter of a sentence while typing
+    #[serde(default)]
+    pub auto_capitalize: bool,
+    /// Correct typos from `autocorrect.toml` while typing
+    #[serde(default)]
+    pub auto_correct: bool,
+    /// Turn `--` and `---` into en and em dashes while typing
+    #[serde(default)]
+    pub auto_dashes: bool,
+    /// Renumber an ordered list after an edit to it
+    #[serde(default)]
+    pub renumber_lists: bool,
+    /// Add the closing fence of a code block as its opening one is typed,
+    /// and keep Enter and pastes after the opening fence inside the block
+    #[serde(default = "default_true")]
+    pub auto_pair: bool,
+    /// How many times each code block language was picked, to suggest the
+    /// common ones first
+    #[serde(default)]
+    pub code_languages: BTreeMap<String, u32>,
+}
+
+impl Default for WritingConfig {
+    fn default() -> Self {
+        Self {
+            smart_punctuation: false,
+            smart_punctuation_on_save: false,
+            toc_depth: default_toc_depth(),
+            update_toc_on_save: false,
+            auto_capitalize: false,
+            auto_correct: false,
+            auto_dashes: false,
+            renumber_lists: false,
+            auto_pair: true,
+            code_languages: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_toc_depth() -> u8 {
+    3
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreviewConfig {
+    /// Render `:shortcode:`s such as `:rocket:` as emoji
+    #[serde(default = "default_true")]
+    pub emoji_shortcodes: bool,
+
+    /// Render supported HTML tags; when off, HTML blocks show as source
+    #[serde(default = "default_true")]
+    pub render_html: bool,
+
+    /// Code blocks longer than this many lines start collapsed; 0 never collapses
+    #[serde(default = "default_max_code_lines")]
+    pub max_code_lines: usize,
+
+    /// Render profile for documents without `rmd_flavor` in their front matter
+    #[serde(default)]
+    pub flavor: Flavor,
+
+    /// Widest the text column grows, in pixels before zoom; 0 fills the panel
+    #[serde(default = "default_max_content_width")]
+    pub max_content_width: f32,
+
+    /// Number headings 1., 1.1, 1.1.1 in the preview and in exports
+    #[serde(default)]
+    pub number_headings: bool,
+
+    /// Scroll the preview to the caret's element as it moves, and move the
+    /// caret to elements clicked in the preview
+    #[serde(default = "default_true")]
+    pub follow_caret: bool,
+
+    /// Rewrite the page opened with Open Preview in Browser as the document
+    /// changes, and have the browser reload it
+    #[serde(default)]
+    pub browser_live_reload: bool,
+
+    /// In Preview Only, show how far through the document the view is and
+    /// the reading time left, and offer to resume there next time
+    #[serde(default = "default_true")]
+    pub reading_progress: bool,
+
+    /// Schemes besides http, https, and mailto that links open without
+    /// asking, such as `zotero`
+    #[serde(default)]
+    pub open_link_schemes: Vec<String>,
+
+    /// Language tag for documents without `lang` in their front matter,
+    /// such as `ja`; empty leaves it unset
+    #[serde(default)]
+    pub document_language: String,
+
+    /// Show the title, reading time, word count, date, and tags above the
+    /// preview, folding away once it is scrolled down
+    #[serde(default)]
+    pub show_header: bool,
+
+    /// Lay the preview out on pages of the export's page size and margins
+    #[serde(default)]
+    pub paginated: bool,
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            emoji_shortcodes: true,
+            render_html: true,
+            max_code_lines: default_max_code_lines(),
+            flavor: Flavor::default(),
+            max_content_width: default_max_content_width(),
+            number_headings: false,
+            follow_caret: true,
+            browser_live_reload: false,
+            reading_progress: true,
+            open_link_schemes: Vec::new(),
+            document_language: String::new(),
+            show_header: false,
+            paginated: false,
+        }
+    }
+}
+
+fn default_max_code_lines() -> usize {
+    40
+}
+
+fn default_max_content_width() -> f32 {
+    760.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatsConfig {
+    /// Record typing and active editing time per day; nothing leaves the machine
+    #[serde(default)]
+    pub enabled: bool,
+    /// Words to add per day; 0 hides the progress bar
+    #[serde(default = "default_daily_word_goal")]
+    pub daily_word_goal: usize,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            daily_word_goal: default_daily_word_goal(),
+        }
+    }
+}
+
+fn default_daily_word_goal() -> usize {
+    500
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportConfig {
+    /// Look of exported pages
+    #[serde(default)]
+    pub style: ExportStyle,
+    /// Stylesheet used with `ExportStyle::Custom`
+    #[serde(default)]
+    pub custom_css: Option<PathBuf>,
+    /// Write the stylesheet to a `.css` file next to the page and link it
+    /// instead of putting it in the page
+    #[serde(default)]
+    pub link_css: bool,
+    /// Start pages with a table of contents, as deep as `writing.toc_depth`
+    #[serde(default)]
+    pub include_toc: bool,
+    /// Put local images into the page as `data:` URIs
+    #[serde(default)]
+    pub embed_images: bool,
+    /// Start pages with the header shown above the preview
+    #[serde(default)]
+    pub include_header: bool,
+    /// Paper pages print on, also used by the paginated preview
+    #[serde(default)]
+    pub page_size: PageSize,
+    /// Space around the text of a printed page, in millimetres
+    #[serde(default = "default_page_margin")]
+    pub page_margin: f32,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            style: ExportStyle::default(),
+            custom_css: None,
+            link_css: false,
+            include_toc: false,
+            embed_images: false,
+            include_header: false,
+            page_size: PageSize::default(),
+            page_margin: default_page_margin(),
+        }
+    }
+}
+
+fn default_page_margin() -> f32 {
+    20.0
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorConfig {
+    /// Show a swatch beside colors in the preview
+    #[serde(default = "default_true")]
+    pub preview: bool,
+    /// Show a swatch in the editor gutter for lines with a color, which
+    /// opens a color picker
+    #[serde(default)]
+    pub editor: bool,
+    /// Which literals count as colors
+    #[serde(default)]
+    pub rules: ColorRules,
+}
+
+impl Default for ColorConfig {
+    fn default() -> Self {
+        Self {
+            preview: true,
+            editor: false,
+            rules: ColorRules::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct UpdateConfig {
+    /// Ask GitHub for the latest release at startup, once a week
+    #[serde(default)]
+    pub check_weekly: bool,
+    /// When it last asked, in seconds since the Unix epoch
+    #[serde(default)]
+    pub last_check: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryConfig {
+    /// Keep a snapshot of each file every time it is saved
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Snapshots kept per file
+    #[serde(default = "default_max_snapshots")]
+    pub max_snapshots: usize,
+    /// Combined size in bytes of the snapshots kept per file
+    #[serde(default = "default_max_history_size")]
+    pub max_total_size: u64,
+    /// Files larger than this many bytes are never snapshotted
+    #[serde(default = "default_max_snapshot_file_size")]
+    pub max_file_size: u64,
+}
+
+fn default_max_snapshots() -> usize {
+    20
+}
+
+fn default_max_history_size() -> u64 {
+    50 * 1024 * 1024
+}
+
+fn default_max_snapshot_file_size() -> u64 {
+    5 * 1024 * 1024
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_snapshots: default_max_snapshots(),
+            max_total_size: default_max_history_size(),
+            max_file_size: default_max_snapshot_file_size(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowConfig {
+    /// Inner size in logical points, so it means the same at any scale
+    /// factor; 0 until the window was first sized
+    #[serde(default)]
+    pub width: f32,
+    #[serde(default)]
+    pub height: f32,
+    #[serde(default)]
+    pub maximized: bool,
+    #[serde(default)]
+    pub sidebar_width: f32,
+    #[serde(default)]
+    pub editor_ratio: f32,
+    #[serde(default)]
+    pub scratchpad_open: bool,
+    #[serde(default = "default_scratchpad_height")]
+    pub scratchpad_height: f32,
+}
+
+fn default_scratchpad_height() -> f32 {
+    160.0
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: 0.0,
+            height: 0.0,
+            maximized: false,
+            sidebar_width: 250.0,
+            editor_ratio: 0.5,
+            scratchpad_open: false,
+            scratchpad_height: default_scratchpad_height(),
+        }
+    }
+}
+
+/// How many recent files are remembered
+const MAX_RECENT_FILES: usize = 8;
+
+/// How many recent workspace folders are remembered, besides pinned ones
+const MAX_RECENT_FOLDERS: usize = 8;
+
+impl Config {
+    /// Move `path` to the front of the recent files
+    pub fn add_recent_file(&mut self, path: &Path) {
+        self.recent_files.retain(|recent| recent != path);
+        self.recent_files.insert(0, path.to_path_buf());
+        self.recent_files.truncate(MAX_RECENT_FILES);
+    }
+
+    /// Move the workspace folder `path` to the front of the recent folders
+    pub fn add_recent_folder(&mut self, path: &Path) {
+        self.recent_folders.retain(|recent| recent != path);
+        self.recent_folders.insert(0, path.to_path_buf());
+        self.recent_folders.truncate(MAX_RECENT_FOLDERS);
+    }
+
+    pub fn is_pinned_folder(&self, path: &Path) -> bool {
+        self.pinned_folders.iter().any(|pinned| pinned == path)
+    }
+
+    /// Pin `path`, or unpin it if it is pinned
+    pub fn toggle_pinned_folder(&mut self, path: &Path) {
+        if self.is_pinned_folder(path) {
+            self.pinned_folders.retain(|pinned| pinned != path);
+        } else {
+            self.pinned_folders.push(path.to_path_buf());
+        }
+    }
+
+    /// Pinned folders, then the other recent ones
+    pub fn workspace_folders(&self) -> Vec<&Path> {
+        let recent = self.recent_folders.iter().filter(|path| !self.is_pinned_folder(path));
+        self.pinned_folders.iter().chain(recent).map(PathBuf::as_path).collect()
+    }
+
+    /// Use `extensions` as the Markdown file extensions, normalized, without
+    /// duplicates or invalid entries. An empty list means the defaults.
+    pub fn set_markdown_extensions(&mut self, extensions: Vec<String>) {
+        let mut normalized: Vec<String> = Vec::new();
+        for extension in extensions.iter().filter_map(|e| utils::normalize_extension(e)) {
+            if !normalized.contains(&extension) {
+                normalized.push(extension);
+            }
+        }
+        self.markdown_extensions = if normalized.is_empty() { default_markdown_extensions() } else { normalized };
+    }
+
+    /// Load configuration from the default config file, or the defaults if
+    /// there is none or it can't be read
+    pub fn load_or_default() -> Self {
+        match Self::config_path() {
+            Ok(path) => Self::load_from(&path),
+            Err(e) => {
+                log::warn!("Using default settings: {}", e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Load configuration from `path`. Unknown keys are ignored; a file that
+    /// doesn't parse is moved to `config.toml.bad` so it isn't overwritten,
+    /// and the defaults are used instead.
+    pub fn load_from(path: &Path) -> Self {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::error!("Failed to read {}: {}", path.display(), e);
+                }
+                return Self::default();
+            }
+        };
+        let parsed = toml::from_str::<toml::Value>(&contents).and_then(|mut value| {
+            Self::migrate(&mut value);
+            value.try_into::<Self>()
+        });
+        match parsed {
+            Ok(mut config) => {
+                let extensions = std::mem::take(&mut config.markdown_extensions);
+                config.set_markdown_extensions(extensions);
+                config
+            }
+            Err(e) => {
+                let backup = path.with_extension("toml.bad");
+                log::error!(
+                    "{} is not valid, starting with default settings and keeping it as {}: {}",
+                    path.display(),
+                    backup.display(),
+                    e
+                );
+                if let Err(e) = std::fs::rename(path, &backup) {
+                    log::error!("Failed to back up {}: {}", path.display(), e);
+                }
+                Self::default()
+            }
+        }
+    }
+
+    /// Bring settings written by an older version up to the current
+    /// layout. Settings from a newer version are left as they are.
+    pub fn migrate(value: &mut toml::Value) {
+        let Some(table) = value.as_table_mut() else {
+            return;
+        };
+        let version = table.get("settings_version").and_then(toml::Value::as_integer).unwrap_or(0);
+        if version >= i64::from(SETTINGS_VERSION) {
+            return;
+        }
+        // Version 0 had the same layout, only without its version
+        table.insert("settings_version".to_string(), toml::Value::Integer(i64::from(SETTINGS_VERSION)));
+    }
+
+    /// Get the path to the config file
+    pub fn config_path() -> anyhow::Result<PathBuf> {
+        Ok(Self::config_dir()?.join("config.toml"))
+    }
+
+    /// Get the RMD directory inside the platform config directory
+    pub fn config_dir() -> anyhow::Result<PathBuf> {
+        let config_dir = dirs::config_dir()
+            .ok_or_else(|| anyhow::anyhow!("Could not find config directory"))?;
+        Ok(config_dir.join("rmd"))
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            settings_version: SETTINGS_VERSION,
+            theme_mode: ThemeMode::default(),
+            layout_mode: LayoutMode::default(),
+            split_direction: SplitDirection::default(),
+            font: FontConfig::default(),
+            editor: EditorConfig::default(),
+            auto_save: false,
+            window: WindowConfig::default(),
+            show_breadcrumbs: true,
+            wiki_links: false,
+            markdown_extensions: default_markdown_extensions(),
+            recent_files: Vec::new(),
+            recent_folders: Vec::new(),
+            pinned_folders: Vec::new(),
+            writing: WritingConfig::default(),
+            history: HistoryConfig::default(),
+            preview: PreviewConfig::default(),
+            stats: StatsConfig::default(),
+            export: ExportConfig::default(),
+            colors: ColorConfig::default(),
+            updates: UpdateConfig::default(),
+            language: String::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_from_tolerates_unknown_keys_and_backs_up_corrupt_files() {
+        let dir = std::env::temp_dir().join(format!("rmd-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+
+        assert_eq!(Config::load_from(&path), Config::default());
+
+        std::fs::write(&path, "wiki_links = true\nfrom_a_newer_version = 1\n").unwrap();
+        let config = Config::load_from(&path);
+        assert!(config.wiki_links);
+        // Written before settings had a version
+        assert_eq!(config.settings_version, SETTINGS_VERSION);
+
+        std::fs::write(&path, "wiki_links = [not toml").unwrap();
+        assert_eq!(Config::load_from(&path), Config::default());
+        assert!(!path.exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("config.toml.bad")).unwrap(),
+            "wiki_links = [not toml"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_workspace_folders_put_pinned_first() {
+        let mut config = Config::default();
+        for folder in ["/a", "/b", "/c", "/b"] {
+            config.add_recent_folder(Path::new(folder));
+        }
+        assert_eq!(config.workspace_folders(), [Path::new("/b"), Path::new("/c"), Path::new("/a")]);
+        config.toggle_pinned_folder(Path::new("/a"));
+        config.toggle_pinned_folder(Path::new("/pinned/elsewhere"));
+        assert_eq!(
+            config.workspace_folders(),
+            [Path::new("/a"), Path::new("/pinned/elsewhere"), Path::new("/b"), Path::new("/c")]
+        );
+        config.toggle_pinned_folder(Path::new("/a"));
+        assert_eq!(config.workspace_folders()[..2], [Path::new("/pinned/elsewhere"), Path::new("/b")]);
+    }
+
+    #[test]
+    fn test_markdown_extensions_are_normalized() {
+        let mut config = Config::default();
+        config.set_markdown_extensions(vec![".MDX".into(), "".into(), "md".into(), "mdx".into(), "a/b".into()]);
+        assert_eq!(config.markdown_extensions, ["mdx", "md"]);
+        config.set_markdown_extensions(vec![" ".into()]);
+        assert_eq!(config.markdown_extensions, Config::default().markdown_extensions);
+    }
+}
@@ -0,0 +1,416 @@
+//! Completion popup state and suggestion providers for links, anchors,
+//! emoji, and code block languages
+
+use super::fences::{self, COMMON_LANGUAGES};
+use crate::markdown::outline;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::Path;
+
+/// Maximum number of suggestions shown at once
+const MAX_SUGGESTIONS: usize = 50;
+
+/// A single completion candidate
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+    /// Text shown in the popup
+    pub label: String,
+    /// Secondary, muted text shown next to the label
+    pub detail: Option<String>,
+    /// Byte range of the document replaced when accepted
+    pub replace: Range<usize>,
+    /// Text inserted when accepted
+    pub insert: String,
+}
+
+/// What a provider gets to look at when asked for suggestions
+pub struct CompletionContext<'a> {
+    /// Full document text
+    pub text: &'a str,
+    /// Byte offset of the caret
+    pub cursor: usize,
+    /// Directory of the current document, if it has been saved
+    pub doc_dir: Option<&'a Path>,
+    /// How many times each code block language was picked
+    pub used_languages: &'a BTreeMap<String, u32>,
+}
+
+impl CompletionContext<'_> {
+    /// Text of the caret's line up to the caret
+    pub fn line_before_cursor(&self) -> &str {
+        let before = &self.text[..self.cursor];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        &before[line_start..]
+    }
+}
+
+/// A source of completions. Returns `None` when the text before the caret
+/// is not something this provider completes.
+pub trait SuggestionProvider {
+    fn suggest(&self, ctx: &CompletionContext) -> Option<Vec<Suggestion>>;
+}
+
+/// Completes `[text` and `](#frag` with headings of the current document
+pub struct HeadingProvider;
+
+impl SuggestionProvider for HeadingProvider {
+    fn suggest(&self, ctx: &CompletionContext) -> Option<Vec<Suggestion>> {
+        let line = ctx.line_before_cursor();
+
+        // `](#par` — complete the fragment itself
+        if let Some(start) = line.rfind("](#") {
+            let query = &line[start + 3..];
+            if query.contains([')', ' ']) {
+                return None;
+            }
+            let replace = ctx.cursor - query.len()..ctx.cursor;
+            let items = outline::headings(ctx.text)
+                .into_iter()
+                .filter(|h| matches_query(&h.slug, query) || matches_query(&h.text, query))
+                .map(|h| Suggestion {
+                    label: h.text,
+                    detail: Some(format!("#{}", h.slug)),
+                    replace: replace.clone(),
+                    insert: format!("{})", h.slug),
+                })
+                .collect();
+            return Some(items);
+        }
+
+        // `[text` — append a link to the matching heading
+        let start = line.rfind('[')?;
+        let query = &line[start + 1..];
+        if query.is_empty() || query.contains([']', '[']) || line[..start].ends_with(['!', '[']) {
+            return None;
+        }
+        let items = outline::headings(ctx.text)
+            .into_iter()
+            .filter(|h| matches_query(&h.text, query))
+            .map(|h| Suggestion {
+                label: h.text,
+                detail: Some(format!("#{}", h.slug)),
+                replace: ctx.cursor..ctx.cursor,
+                insert: format!("](#{})", h.slug),
+            })
+            .collect();
+        Some(items)
+    }
+}
+
+/// Completes `](./path` with files next to the current document
+pub struct FileProvider;
+
+impl SuggestionProvider for FileProvider {
+    fn suggest(&self, ctx: &CompletionContext) -> Option<Vec<Suggestion>> {
+        let doc_dir = ctx.doc_dir?;
+        let line = ctx.line_before_cursor();
+        let start = line.rfind("](./")?;
+        let path = &line[start + 4..];
+        if path.contains([')', ' ']) {
+            return None;
+        }
+
+        let (subdir, prefix) = match path.rfind('/') {
+            Some(i) => (&path[..i], &path[i + 1..]),
+            None => ("", path),
+        };
+        let dir = doc_dir.join(subdir);
+        let replace = ctx.cursor - prefix.len()..ctx.cursor;
+
+        let mut items: Vec<Suggestion> = std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(Result::ok)
+            .filter(|entry| !crate::utils::is_hidden_file(&entry.path()))
+            .filter_map(|entry| {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if !matches_query(&name, prefix) {
+                    return None;
+                }
+                let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                Some(Suggestion {
+                    label: if is_dir { format!("{}/", name) } else { name.clone() },
+                    detail: None,
+                    replace: replace.clone(),
+                    insert: if is_dir { format!("{}/", name) } else { format!("{})", name) },
+                })
+            })
+            .collect();
+        items.sort_by_key(|item| item.label.to_lowercase());
+        Some(items)
+    }
+}
+
+/// Completes `:shortcode` with the matching emoji character
+pub struct EmojiProvider;
+
+impl SuggestionProvider for EmojiProvider {
+    fn suggest(&self, ctx: &CompletionContext) -> Option<Vec<Suggestion>> {
+        let line = ctx.line_before_cursor();
+        let start = line.rfind(':')?;
+        let query = &line[start + 1..];
+        let bounded = line[..start].chars().next_back().is_none_or(char::is_whitespace);
+        let valid = query
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-'));
+        if !bounded || !valid || query.len() < 2 {
+            return None;
+        }
+
+        let replace = ctx.cursor - query.len() - 1..ctx.cursor;
+        let query = query.to_lowercase();
+        let mut items: Vec<(bool, Suggestion)> = emojis::iter()
+            .flat_map(|emoji| emoji.shortcodes().map(move |code| (emoji, code)))
+            .filter(|(_, code)| code.contains(&query))
+            .map(|(emoji, code)| {
+                let item = Suggestion {
+                    label: format!("{} :{}:", emoji.as_str(), code),
+                    detail: Some(emoji.name().to_string()),
+                    replace: replace.clone(),
+                    insert: emoji.as_str().to_string(),
+                };
+                (!code.starts_with(&query), item)
+            })
+            .collect();
+        // Prefix matches first, then alphabetical
+        items.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.label.cmp(&b.1.label)));
+        Some(items.into_iter().map(|(_, item)| item).collect())
+    }
+}
+
+/// Completes the language after an opening code fence, the ones picked
+/// most often first and then common ones
+pub struct LanguageProvider;
+
+impl SuggestionProvider for LanguageProvider {
+    fn suggest(&self, ctx: &CompletionContext) -> Option<Vec<Suggestion>> {
+        let query = fences::language_query(ctx.text, ctx.cursor)?;
+        if query.is_empty() {
+            return None;
+        }
+        let mut used: Vec<(&String, &u32)> = ctx.used_languages.iter().collect();
+        used.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        let mut languages: Vec<&str> = used.into_iter().map(|(language, _)| language.as_str()).collect();
+        for language in COMMON_LANGUAGES {
+            if !languages.contains(&language) {
+                languages.push(language);
+            }
+        }
+
+        let replace = ctx.cursor - query.len()..ctx.cursor;
+        let lower = query.to_lowercase();
+        let (mut prefixed, others): (Vec<&str>, Vec<&str>) = languages
+            .into_iter()
+            .filter(|language| matches_query(language, query) && *language != query)
+            .partition(|language| language.to_lowercase().starts_with(&lower));
+        prefixed.extend(others);
+        let items = prefixed
+            .into_iter()
+            .map(|language| Suggestion {
+                label: language.to_string(),
+                detail: None,
+                replace: replace.clone(),
+                insert: language.to_string(),
+            })
+            .collect();
+        Some(items)
+    }
+}
+
+fn matches_query(candidate: &str, query: &str) -> bool {
+    candidate.to_lowercase().contains(&query.to_lowercase())
+}
+
+/// State of the completion popup
+pub struct Autocomplete {
+    providers: Vec<Box<dyn SuggestionProvider>>,
+    items: Vec<Suggestion>,
+    selected: usize,
+    /// Start of the replace range the user dismissed with Escape, so the
+    /// popup stays closed until they start completing something else
+    dismissed_at: Option<usize>,
+}
+
+impl Autocomplete {
+    pub fn new() -> Self {
+        let mut autocomplete = Self {
+            providers: Vec::new(),
+            items: Vec::new(),
+            selected: 0,
+            dismissed_at: None,
+        };
+        autocomplete.register(Box::new(LanguageProvider));
+        autocomplete.register(Box::new(FileProvider));
+        autocomplete.register(Box::new(HeadingProvider));
+        autocomplete.register(Box::new(EmojiProvider));
+        autocomplete
+    }
+
+    /// Add another suggestion source; earlier providers take precedence
+    pub fn register(&mut self, provider: Box<dyn SuggestionProvider>) {
+        self.providers.push(provider);
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.items.is_empty()
+    }
+
+    pub fn items(&self) -> &[Suggestion] {
+        &self.items
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Recompute suggestions after the user typed
+    pub fn update(&mut self, ctx: &CompletionContext) {
+        let items = self
+            .providers
+            .iter()
+            .find_map(|provider| provider.suggest(ctx).filter(|items| !items.is_empty()))
+            .unwrap_or_default();
+
+        let start = items.first().map(|item| item.replace.start);
+        if start.is_none() || start != self.dismissed_at {
+            self.dismissed_at = None;
+        }
+        if self.dismissed_at.is_some() {
+            self.items.clear();
+            return;
+        }
+
+        self.items = items;
+        self.items.truncate(MAX_SUGGESTIONS);
+        self.selected = self.selected.min(self.items.len().saturating_sub(1));
+    }
+
+    pub fn select_next(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + 1) % self.items.len();
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+        }
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.items.len() {
+            self.selected = index;
+        }
+    }
+
+    /// Close the popup without inserting anything
+    pub fn close(&mut self) {
+        self.items.clear();
+        self.selected = 0;
+    }
+
+    /// Close the popup and keep it closed for the current completion
+    pub fn dismiss(&mut self) {
+        self.dismissed_at = self.items.first().map(|item| item.replace.start);
+        self.close();
+    }
+
+    /// Take the selected suggestion and close the popup
+    pub fn accept(&mut self) -> Option<Suggestion> {
+        let item = self.items.get(self.selected).cloned();
+        self.close();
+        item
+    }
+}
+
+impl Default for Autocomplete {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suggest(provider: &dyn SuggestionProvider, text: &str) -> Option<Vec<Suggestion>> {
+        provider.suggest(&CompletionContext {
+            text,
+            cursor: text.len(),
+            doc_dir: None,
+            used_languages: &BTreeMap::new(),
+        })
+    }
+
+    #[test]
+    fn test_heading_provider_appends_anchor() {
+        let text = "# Getting Started\n\n## Install\n\nSee [inst";
+        let items = suggest(&HeadingProvider, text).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].insert, "](#install)");
+        assert_eq!(items[0].replace, text.len()..text.len());
+    }
+
+    #[test]
+    fn test_heading_provider_completes_fragment() {
+        let text = "# Getting Started\n\n[go](#get";
+        let items = suggest(&HeadingProvider, text).unwrap();
+        assert_eq!(items[0].insert, "getting-started)");
+        assert_eq!(items[0].replace, text.len() - 3..text.len());
+    }
+
+    #[test]
+    fn test_heading_provider_ignores_images_and_tasks() {
+        assert!(suggest(&HeadingProvider, "![alt").is_none());
+        assert!(suggest(&HeadingProvider, "- [ ] task").is_none());
+    }
+
+    #[test]
+    fn test_emoji_provider() {
+        let items = suggest(&EmojiProvider, "launch :rock").unwrap();
+        assert!(items.iter().any(|item| item.insert == "🚀"));
+        assert_eq!(items[0].replace, 7..12);
+
+        // Needs a word boundary before the colon and at least two characters
+        assert!(suggest(&EmojiProvider, "time 10:30").is_none());
+        assert!(suggest(&EmojiProvider, "hi :r").is_none());
+    }
+
+    #[test]
+    fn test_language_provider() {
+        let used = BTreeMap::from([("python".to_string(), 2), ("pony".to_string(), 5)]);
+        let text = "```p\n```";
+        let items = LanguageProvider
+            .suggest(&CompletionContext { text, cursor: 4, doc_dir: None, used_languages: &used })
+            .unwrap();
+        let labels: Vec<&str> = items.iter().map(|item| item.label.as_str()).collect();
+        // Most used first, then common ones starting with the query, then
+        // the ones with it further in
+        assert_eq!(labels, ["pony", "python", "cpp"]);
+        assert_eq!(items[0].replace, 3..4);
+
+        // Not without a letter typed, nor on a closing fence
+        assert!(suggest(&LanguageProvider, "```").is_none());
+        assert!(suggest(&LanguageProvider, "```\ncode\n```").is_none());
+    }
+
+    #[test]
+    fn test_dismiss_keeps_popup_closed_until_context_changes() {
+        let mut autocomplete = Autocomplete::new();
+        let update = |ac: &mut Autocomplete, text: &str| {
+            ac.update(&CompletionContext {
+                text,
+                cursor: text.len(),
+                doc_dir: None,
+                used_languages: &BTreeMap::new(),
+            })
+        };
+
+        update(&mut autocomplete, ":smi");
+        assert!(autocomplete.is_open());
+        autocomplete.dismiss();
+        update(&mut autocomplete, ":smil");
+        assert!(!autocomplete.is_open());
+        update(&mut autocomplete, ":smile :hea");
+        assert!(autocomplete.is_open());
+    }
+}
@@ -0,0 +1,143 @@
+//! Bookmarked lines, kept on the same text as edits shift lines around
+
+use super::Editor;
+
+impl Editor {
+    /// Bookmarked lines, zero-based and in order
+    pub fn bookmarks(&self) -> &[usize] {
+        &self.bookmarks
+    }
+
+    /// Replace the bookmarks, e.g. with ones restored for a reopened file
+    pub fn set_bookmarks(&mut self, mut lines: Vec<usize>) {
+        let line_count = self.buffer.line_count();
+        lines.retain(|&line| line < line_count);
+        lines.sort_unstable();
+        lines.dedup();
+        self.bookmarks = lines;
+    }
+
+    /// Add or remove a bookmark on the caret line
+    pub fn toggle_bookmark(&mut self) {
+        let line = self.cursor_position.0;
+        match self.bookmarks.binary_search(&line) {
+            Ok(index) => {
+                self.bookmarks.remove(index);
+            }
+            Err(index) => self.bookmarks.insert(index, line),
+        }
+    }
+
+    /// Move the caret to the next (or previous) bookmark, wrapping around
+    /// the document. Returns whether there was one to go to.
+    pub fn next_bookmark(&mut self, backwards: bool) -> bool {
+        let line = self.cursor_position.0;
+        let target = if backwards {
+            self.bookmarks.iter().rev().find(|&&b| b < line).or(self.bookmarks.last())
+        } else {
+            self.bookmarks.iter().find(|&&b| b > line).or(self.bookmarks.first())
+        };
+        match target.copied() {
+            Some(target) => {
+                self.go_to_line(target);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Keep bookmarks on their lines across replacing `old_text` at
+    /// `position` with `new_text`. Must run before the buffer changes.
+    pub(super) fn shift_bookmarks(&mut self, position: usize, old_text: &str, new_text: &str) {
+        if self.bookmarks.is_empty() {
+            return;
+        }
+        let (line, col) = self.buffer.line_col_from_byte_index(position);
+        self.bookmarks = shift_lines(
+            &self.bookmarks,
+            line,
+            col == 0 && old_text.is_empty(),
+            old_text.matches('\n').count(),
+            new_text.matches('\n').count(),
+        );
+    }
+}
+
+/// Bookmarks after an edit on `line` that removed `removed` and inserted
+/// `inserted` line breaks. Lines whose breaks were removed merge into the
+/// edited line; a pure insertion at the start of a line pushes it down.
+fn shift_lines(bookmarks: &[usize], line: usize, at_line_start: bool, removed: usize, inserted: usize) -> Vec<usize> {
+    let mut shifted: Vec<usize> = bookmarks
+        .iter()
+        .map(|&b| {
+            if b < line || (b == line && !at_line_start) {
+                b
+            } else if b <= line + removed && b != line {
+                line
+            } else {
+                b - removed + inserted
+            }
+        })
+        .collect();
+    shifted.dedup();
+    shifted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_and_navigate() {
+        let mut editor = Editor::new();
+        editor.set_text("a\nb\nc\nd\n");
+        assert!(!editor.next_bookmark(false));
+
+        editor.go_to_line(3);
+        editor.toggle_bookmark();
+        editor.go_to_line(1);
+        editor.toggle_bookmark();
+        assert_eq!(editor.bookmarks(), &[1, 3]);
+
+        assert!(editor.next_bookmark(false));
+        assert_eq!(editor.cursor_position().0, 3);
+        editor.next_bookmark(false);
+        assert_eq!(editor.cursor_position().0, 1);
+        editor.next_bookmark(true);
+        assert_eq!(editor.cursor_position().0, 3);
+
+        editor.toggle_bookmark();
+        assert_eq!(editor.bookmarks(), &[1]);
+    }
+
+    #[test]
+    fn test_bookmarks_follow_edits() {
+        let mut editor = Editor::new();
+        editor.set_text("one\ntwo\nthree\nfour\n");
+        editor.set_bookmarks(vec![1, 3, 9]);
+        assert_eq!(editor.bookmarks(), &[1, 3]);
+
+        // New lines typed at the start of "two" push both bookmarks down
+        editor.set_cursor(4);
+        editor.insert_text("new\n\n");
+        assert_eq!(editor.bookmarks(), &[3, 5]);
+
+        editor.undo();
+        assert_eq!(editor.bookmarks(), &[1, 3]);
+        editor.redo();
+        assert_eq!(editor.bookmarks(), &[3, 5]);
+
+        // Typing inside a bookmarked line keeps it in place
+        editor.set_cursor(editor.text().find("two").unwrap() + 1);
+        editor.insert_text("\n");
+        assert_eq!(editor.bookmarks(), &[3, 6]);
+    }
+
+    #[test]
+    fn test_shift_lines_merges_deleted_lines() {
+        // Deleting the breaks after line 1 pulls lines 2 and 3 into it
+        assert_eq!(shift_lines(&[0, 2, 3, 6], 1, false, 2, 0), vec![0, 1, 4]);
+        assert_eq!(shift_lines(&[1], 1, true, 0, 1), vec![2]);
+        assert_eq!(shift_lines(&[1], 1, false, 0, 1), vec![1]);
+    }
+}
@@ -0,0 +1,195 @@
+//! Line-based diff between the saved file and the buffer
+
+use similar::{ChangeTag, DiffOp, TextDiff};
+use std::ops::Range;
+
+/// A run of consecutive diff lines, as shown in the changes view
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffRow {
+    /// Lines present in both versions
+    Unchanged {
+        old_start: usize,
+        new_start: usize,
+        lines: Vec<String>,
+    },
+    /// Lines removed from and/or added to the saved version
+    Changed(Hunk),
+}
+
+/// A contiguous change: `removed` lines of the old text replaced by `added` lines
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    /// Zero-based first line of `removed` in the old text
+    pub old_start: usize,
+    pub removed: Vec<String>,
+    /// Zero-based first line of `added` in the new text
+    pub new_start: usize,
+    pub added: Vec<String>,
+}
+
+/// How a hunk is marked in the editor gutter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    /// Lines removed with nothing in their place
+    Deleted,
+}
+
+impl Hunk {
+    pub fn kind(&self) -> ChangeKind {
+        match (self.removed.is_empty(), self.added.is_empty()) {
+            (true, _) => ChangeKind::Added,
+            (false, true) => ChangeKind::Deleted,
+            (false, false) => ChangeKind::Modified,
+        }
+    }
+
+    /// Zero-based lines of the new text the hunk covers
+    pub fn new_lines(&self) -> Range<usize> {
+        self.new_start..self.new_start + self.added.len()
+    }
+
+    /// The hunk's lines of the old text, as they were
+    pub fn original_text(&self) -> String {
+        self.removed.join("\n")
+    }
+}
+
+/// Diff `old` against `new` line by line
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffRow> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let owned = |lines: &[&str]| lines.iter().map(|line| line.to_string()).collect::<Vec<_>>();
+
+    let diff = TextDiff::from_slices(&old_lines, &new_lines);
+    let mut rows: Vec<DiffRow> = Vec::new();
+    for op in diff.ops() {
+        let old_range = op.old_range();
+        let new_range = op.new_range();
+        if let DiffOp::Equal { .. } = op {
+            rows.push(DiffRow::Unchanged {
+                old_start: old_range.start,
+                new_start: new_range.start,
+                lines: owned(&new_lines[new_range]),
+            });
+            continue;
+        }
+
+        // Merge deletes and inserts that follow each other into one hunk
+        if let Some(DiffRow::Changed(hunk)) = rows.last_mut() {
+            hunk.removed.extend(owned(&old_lines[old_range]));
+            hunk.added.extend(owned(&new_lines[new_range]));
+            continue;
+        }
+        rows.push(DiffRow::Changed(Hunk {
+            old_start: old_range.start,
+            removed: owned(&old_lines[old_range]),
+            new_start: new_range.start,
+            added: owned(&new_lines[new_range]),
+        }));
+    }
+    rows
+}
+
+/// Byte range of the zero-based `lines` of `text`, each with its newline.
+/// Lines past the end are an empty range at the end.
+pub fn line_byte_range(text: &str, lines: Range<usize>) -> Range<usize> {
+    let line_start = |line: usize| {
+        if line == 0 {
+            return 0;
+        }
+        text.match_indices('\n').nth(line - 1).map_or(text.len(), |(i, _)| i + 1)
+    };
+    line_start(lines.start)..line_start(lines.end)
+}
+
+/// The edit that puts back the lines `hunk` replaced: the byte range of its
+/// lines in `new`, and the text they had in `old`. Taken from the texts
+/// rather than the hunk's lines so a missing final newline comes back too.
+pub fn revert_hunk(old: &str, new: &str, hunk: &Hunk) -> (Range<usize>, String) {
+    let original = line_byte_range(old, hunk.old_start..hunk.old_start + hunk.removed.len());
+    (line_byte_range(new, hunk.new_lines()), old[original].to_string())
+}
+
+/// `old` split into words and the whitespace between them, each marked
+/// true when it is gone from `new`
+pub fn removed_words(old: &str, new: &str) -> Vec<(String, bool)> {
+    let diff = TextDiff::from_words(old, new);
+    let mut words: Vec<(String, bool)> = Vec::new();
+    for change in diff.iter_all_changes() {
+        let removed = match change.tag() {
+            ChangeTag::Equal => false,
+            ChangeTag::Delete => true,
+            ChangeTag::Insert => continue,
+        };
+        // Join runs so the text is drawn in as few pieces as possible
+        match words.last_mut() {
+            Some((text, last)) if *last == removed => text.push_str(change.value()),
+            _ => words.push((change.value().to_string(), removed)),
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_lines() {
+        let old = "a\nb\nc\nd\n";
+        let new = "a\nB\nc\nd\ne\n";
+        let rows = diff_lines(old, new);
+        assert_eq!(rows.len(), 4);
+        assert_eq!(
+            rows[1],
+            DiffRow::Changed(Hunk {
+                old_start: 1,
+                removed: vec!["b".to_string()],
+                new_start: 1,
+                added: vec!["B".to_string()],
+            })
+        );
+        assert!(matches!(&rows[2], DiffRow::Unchanged { lines, .. } if lines.len() == 2));
+        assert!(matches!(&rows[3], DiffRow::Changed(hunk) if hunk.removed.is_empty() && hunk.new_start == 4));
+    }
+
+    #[test]
+    fn test_diff_without_saved_file() {
+        let rows = diff_lines("", "new\nfile");
+        assert_eq!(rows.len(), 1);
+        assert!(matches!(&rows[0], DiffRow::Changed(hunk) if hunk.added.len() == 2 && hunk.removed.is_empty()));
+        assert!(diff_lines("same\n", "same\n").iter().all(|row| matches!(row, DiffRow::Unchanged { .. })));
+    }
+
+    #[test]
+    fn test_revert_hunk() {
+        let revert = |old: &str, new: &str| {
+            let rows = diff_lines(old, new);
+            let Some(DiffRow::Changed(hunk)) = rows.iter().find(|row| matches!(row, DiffRow::Changed(_))) else {
+                panic!("no hunk");
+            };
+            let (range, original) = revert_hunk(old, new, hunk);
+            let mut text = new.to_string();
+            text.replace_range(range, &original);
+            (hunk.kind(), text)
+        };
+        assert_eq!(revert("a\nb\nc\n", "a\nB\nc\n"), (ChangeKind::Modified, "a\nb\nc\n".to_string()));
+        assert_eq!(revert("a\nc\n", "a\nb\nc\n"), (ChangeKind::Added, "a\nc\n".to_string()));
+        assert_eq!(revert("a\nb\n", "a\n"), (ChangeKind::Deleted, "a\nb\n".to_string()));
+        assert_eq!(revert("a\nb", "a\nc\n"), (ChangeKind::Modified, "a\nb".to_string()));
+
+        assert_eq!(line_byte_range("ab\ncd\nef", 1..2), 3..6);
+        assert_eq!(line_byte_range("ab\ncd\nef", 2..5), 6..8);
+    }
+
+    #[test]
+    fn test_removed_words() {
+        let words = removed_words("the quick fox", "the slow fox");
+        assert_eq!(
+            words,
+            [("the ".to_string(), false), ("quick".to_string(), true), (" fox".to_string(), false)]
+        );
+    }
+}
@@ -0,0 +1,86 @@
+//! Why opening, reloading, or saving a document failed, with the file and
+//! what was being done to it, so the UI can say what went wrong and offer
+//! what can be done about it
+
+use std::fmt;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// What was being done to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Open,
+    Reload,
+    Save,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Operation::Open => "open",
+            Operation::Reload => "reload",
+            Operation::Save => "save",
+        })
+    }
+}
+
+/// A file operation that failed, worded for a notification
+#[derive(Debug, thiserror::Error)]
+pub enum EditorError {
+    #[error("{} doesn't exist", .path.display())]
+    NotFound { path: PathBuf, operation: Operation },
+    #[error("Not allowed to {operation} {}", .path.display())]
+    PermissionDenied { path: PathBuf, operation: Operation },
+    #[error("{} is read-only", .path.display())]
+    ReadOnly { path: PathBuf, operation: Operation },
+    /// Not UTF-8; `lossy_available` when it still looks like text, so
+    /// opening it with the bad bytes replaced is worth offering
+    #[error("{} isn't UTF-8 text", .path.display())]
+    InvalidUtf8 { path: PathBuf, operation: Operation, lossy_available: bool },
+    #[error("{} is {size} bytes, more than the {limit} that can be opened", .path.display())]
+    TooLarge { path: PathBuf, operation: Operation, size: u64, limit: u64 },
+    #[error("The disk is full")]
+    DiskFull { path: PathBuf, operation: Operation },
+    #[error("Couldn't {operation} {}: {source}", .path.display())]
+    Io { path: PathBuf, operation: Operation, source: io::Error },
+}
+
+impl EditorError {
+    /// The variant `error` amounts to, from `operation` on `path`
+    pub fn from_io(operation: Operation, path: &Path, error: io::Error) -> Self {
+        let path = path.to_path_buf();
+        match error.kind() {
+            ErrorKind::NotFound => EditorError::NotFound { path, operation },
+            ErrorKind::PermissionDenied => EditorError::PermissionDenied { path, operation },
+            ErrorKind::ReadOnlyFilesystem => EditorError::ReadOnly { path, operation },
+            ErrorKind::StorageFull => EditorError::DiskFull { path, operation },
+            _ => EditorError::Io { path, operation, source: error },
+        }
+    }
+
+    /// The file the operation was on
+    pub fn path(&self) -> &Path {
+        match self {
+            EditorError::NotFound { path, .. }
+            | EditorError::PermissionDenied { path, .. }
+            | EditorError::ReadOnly { path, .. }
+            | EditorError::InvalidUtf8 { path, .. }
+            | EditorError::TooLarge { path, .. }
+            | EditorError::DiskFull { path, .. }
+            | EditorError::Io { path, .. } => path,
+        }
+    }
+
+    /// What was being done to the file
+    pub fn operation(&self) -> Operation {
+        match self {
+            EditorError::NotFound { operation, .. }
+            | EditorError::PermissionDenied { operation, .. }
+            | EditorError::ReadOnly { operation, .. }
+            | EditorError::InvalidUtf8 { operation, .. }
+            | EditorError::TooLarge { operation, .. }
+            | EditorError::DiskFull { operation, .. }
+            | EditorError::Io { operation, .. } => *operation,
+        }
+    }
+}
@@ -0,0 +1,261 @@
+//! Fenced code blocks as they are typed: typing an opening fence adds the
+//! closing one, Enter where the language goes moves into the block, and a
+//! multi-line paste there lands inside the block without ending it early
+
+use super::Editor;
+use crate::markdown::fence_marker;
+use std::ops::Range;
+
+/// Languages suggested after an opening fence, after the ones already used
+pub const COMMON_LANGUAGES: [&str; 20] = [
+    "rust", "python", "js", "bash", "json", "toml", "yaml", "html", "css", "ts", "c", "cpp", "go", "java", "sql", "sh",
+    "diff", "markdown", "text", "xml",
+];
+
+/// A fenced code block, by the byte ranges of its fence lines without their
+/// newlines
+struct Block {
+    open: Range<usize>,
+    /// `None` while the block runs to the end of the document
+    close: Option<Range<usize>>,
+    marker: char,
+    len: usize,
+}
+
+impl Block {
+    /// Where the opening fence ends and the language starts
+    fn fence_end(&self, text: &str) -> usize {
+        let line = &text[self.open.clone()];
+        self.open.start + line.len() - line.trim_start_matches(' ').len() + self.len
+    }
+
+    /// Whether the block is closed with nothing but blank lines inside
+    fn is_empty(&self, text: &str) -> bool {
+        self.close.as_ref().is_some_and(|close| text[self.open.end..close.start].trim().is_empty())
+    }
+
+    /// Whether `pos` is where the language goes
+    fn at_language(&self, text: &str, pos: usize) -> bool {
+        (self.fence_end(text)..=self.open.end).contains(&pos)
+    }
+}
+
+/// The fenced code blocks of `text`, in order
+fn blocks(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut open: Option<Block> = None;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let range = offset..offset + content.len();
+        offset += line.len();
+        let Some((marker, len)) = fence_marker(content) else {
+            continue;
+        };
+        match open.take() {
+            None => open = Some(Block { open: range, close: None, marker, len }),
+            // Only a fence as long, with nothing after it, closes a block
+            Some(mut block) if marker == block.marker && len >= block.len && content.trim().len() == len => {
+                block.close = Some(range);
+                blocks.push(block);
+            }
+            Some(block) => open = Some(block),
+        }
+    }
+    blocks.extend(open);
+    blocks
+}
+
+/// The language typed so far when `caret` is after an opening fence
+pub fn language_query(text: &str, caret: usize) -> Option<&str> {
+    let block = blocks(text).into_iter().find(|block| block.at_language(text, caret))?;
+    let query = text[block.fence_end(text)..caret].trim_start();
+    query.chars().all(|c| c.is_alphanumeric() || matches!(c, '+' | '-' | '_' | '#' | '.')).then_some(query)
+}
+
+impl Editor {
+    /// Now that an opening fence was typed at the end of its line, add the
+    /// closing fence below as an edit of its own, leaving the caret where
+    /// the language goes. A closing fence already there is lengthened to
+    /// match instead. Returns whether anything changed.
+    pub fn close_fence(&mut self) -> bool {
+        if self.selection().is_some() {
+            return false;
+        }
+        let text = self.text();
+        let caret = self.cursor;
+        let Some(block) = blocks(&text).into_iter().find(|block| block.open.end == caret) else {
+            return false;
+        };
+        if text[block.open.clone()].trim().len() != block.len {
+            return false;
+        }
+        let indent = &text[block.open.start..block.fence_end(&text) - block.len];
+        let fence = block.marker.to_string().repeat(block.len);
+
+        let next_start = text[caret..].find('\n').map(|i| caret + i + 1);
+        let next = next_start.map(|start| start..text[start..].find(['\r', '\n']).map_or(text.len(), |i| start + i));
+        if let Some(next) = next {
+            let line = &text[next.clone()];
+            let shorter = fence_marker(line).is_some_and(|(marker, len)| marker == block.marker && line.trim().len() == len);
+            if shorter && line.trim().len() < block.len {
+                self.replace_range_in_place(next, &format!("{}{}", indent, fence));
+                return true;
+            }
+            if shorter {
+                return false;
+            }
+        }
+        let newline = if text[caret..].starts_with("\r\n") { "\r\n" } else { "\n" };
+        self.replace_range_in_place(caret..caret, &format!("{}{}{}", newline, indent, fence));
+        true
+    }
+
+    /// Enter where the language of an empty block goes: move into the block
+    /// rather than breaking the line, adding a line to it when it has none.
+    /// Returns the block's language, empty when it has none, or `None` when
+    /// the caret isn't there.
+    pub fn enter_fence(&mut self) -> Option<String> {
+        if self.selection().is_some() {
+            return None;
+        }
+        let text = self.text();
+        let caret = self.cursor;
+        let block = blocks(&text).into_iter().find(|block| block.at_language(&text, caret) && block.is_empty(&text))?;
+        let language = text[block.fence_end(&text)..block.open.end].split_whitespace().next().unwrap_or_default().to_string();
+
+        let close = block.close.as_ref().map_or(text.len(), |close| close.start);
+        let body = text[block.open.end..close].find('\n').map(|i| block.open.end + i + 1).filter(|&start| start < close);
+        match body {
+            Some(start) => {
+                let end = start + text[start..close].find(['\r', '\n']).unwrap_or(0);
+                self.set_cursor(end);
+            }
+            None => {
+                let newline = if text[block.open.end..].starts_with("\r\n") { "\r\n" } else { "\n" };
+                self.replace_range(block.open.end..block.open.end, newline);
+            }
+        }
+        Some(language)
+    }
+
+    /// Paste `pasted`, several lines, with the caret where the language of
+    /// an empty block goes, or on a blank line inside it when `pasted` has a
+    /// fence that would end the block. It goes inside the block, with the
+    /// fences made longer than any in `pasted`, as one undoable edit.
+    /// Returns whether it was pasted that way.
+    pub fn paste_into_fence(&mut self, pasted: &str) -> bool {
+        if !pasted.contains('\n') || self.selection().is_some() {
+            return false;
+        }
+        let text = self.text();
+        let caret = self.cursor;
+        let Some(block) = blocks(&text).into_iter().find(|block| {
+            let close = block.close.as_ref().map_or(0, |close| close.start);
+            block.is_empty(&text) && (block.at_language(&text, caret) || (block.open.end < caret && caret < close))
+        }) else {
+            return false;
+        };
+        let Some(close) = block.close.clone() else {
+            return false;
+        };
+
+        let longest = pasted
+            .lines()
+            .filter_map(fence_marker)
+            .filter(|&(marker, _)| marker == block.marker)
+            .map(|(_, len)| len)
+            .max();
+        let conflicts = longest.is_some_and(|len| len >= block.len);
+        if !block.at_language(&text, caret) && !conflicts {
+            return false;
+        }
+        let len = longest.map_or(block.len, |longest| block.len.max(longest + 1));
+        let fence = block.marker.to_string().repeat(len);
+
+        let fence_end = block.fence_end(&text);
+        let indent = &text[block.open.start..fence_end - block.len];
+        let info = &text[fence_end..block.open.end];
+        let close_line = &text[close.clone()];
+        let close_indent = &close_line[..close_line.len() - close_line.trim_start_matches(' ').len()];
+        let body = pasted.strip_suffix('\n').unwrap_or(pasted);
+
+        let opening = format!("{}{}{}\n", indent, fence, info);
+        let replacement = format!("{}{}\n{}{}", opening, body, close_indent, fence);
+        self.replace_range(block.open.start..close.end, &replacement);
+        self.set_cursor(block.open.start + opening.len() + body.len());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::test_editor;
+
+    #[test]
+    fn test_close_fence() {
+        let mut e = test_editor("Intro\n```", 9, 9);
+        assert!(e.close_fence());
+        assert_eq!(e.text(), "Intro\n```\n```");
+        assert_eq!(e.cursor(), 9);
+        e.undo();
+        assert_eq!(e.text(), "Intro\n```");
+
+        // A fourth backtick lengthens the closing fence rather than adding one
+        let mut e = test_editor("````\n```\n", 4, 4);
+        assert!(e.close_fence());
+        assert_eq!(e.text(), "````\n````\n");
+        assert!(!e.close_fence());
+
+        // Not when the fence ends a block, or has a language after it
+        assert!(!test_editor("```\ncode\n```", 12, 12).close_fence());
+        assert!(!test_editor("```rust", 7, 7).close_fence());
+    }
+
+    #[test]
+    fn test_language_query() {
+        assert_eq!(language_query("```ru", 5), Some("ru"));
+        assert_eq!(language_query("  ~~~ py\n~~~", 8), Some("py"));
+        assert_eq!(language_query("```\ncode", 8), None);
+        assert_eq!(language_query("```\ncode\n```", 12), None);
+        assert_eq!(language_query("```a b", 6), None);
+    }
+
+    #[test]
+    fn test_enter_fence() {
+        let mut e = test_editor("```rust\n```", 5, 5);
+        assert_eq!(e.enter_fence().as_deref(), Some("rust"));
+        assert_eq!(e.text(), "```rust\n\n```");
+        assert_eq!(e.cursor(), 8);
+
+        // A blank line already inside is used as it is
+        let mut e = test_editor("```\n\n```", 3, 3);
+        assert_eq!(e.enter_fence().as_deref(), Some(""));
+        assert_eq!(e.text(), "```\n\n```");
+        assert_eq!(e.cursor(), 4);
+
+        // Only in an empty block
+        assert!(test_editor("```\ncode\n```", 3, 3).enter_fence().is_none());
+    }
+
+    #[test]
+    fn test_paste_into_fence() {
+        let mut e = test_editor("```rust\n```\nAfter", 7, 7);
+        assert!(e.paste_into_fence("fn main() {\n}\n"));
+        assert_eq!(e.text(), "```rust\nfn main() {\n}\n```\nAfter");
+        assert_eq!(e.cursor(), 21);
+        e.undo();
+        assert_eq!(e.text(), "```rust\n```\nAfter");
+
+        // Fences in what is pasted make the block's longer
+        let mut e = test_editor("```\n\n```", 4, 4);
+        assert!(e.paste_into_fence("```md\nx\n```"));
+        assert_eq!(e.text(), "````\n```md\nx\n```\n````");
+
+        // Elsewhere, and without a fence to clash, the paste is left alone
+        assert!(!test_editor("```\n\n```", 4, 4).paste_into_fence("a\nb"));
+        assert!(!test_editor("```rust\n```", 7, 7).paste_into_fence("one line"));
+        assert!(!test_editor("Text\n", 5, 5).paste_into_fence("```\nx\n```"));
+    }
+}
@@ -1,9 +1,16 @@
+use crate::markdown::{autolink, fence_marker, include, outline};
 use crate::theme::Theme;
+use crate::utils::timing::{self, Cache, Phase};
 use eframe::egui;
 
 /// Syntax highlighter for Markdown
 pub struct MarkdownHighlighter {
     theme: Theme,
+    /// Highlight `[[Note Name]]` wiki links
+    wiki_links: bool,
+    /// Lines of the text last laid out, reused while they and what their
+    /// neighbours make of them stay the same
+    lines: Vec<CachedLine>,
 }
 
 /// A highlighted token
@@ -13,9 +20,52 @@ pub struct Token {
     pub style: TokenStyle,
 }
 
+/// Semantic kind of a token, used by themes to pick colors
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Plain text
+    #[default]
+    Text,
+    /// An ATX heading line with its level (1-6)
+    Heading(u8),
+    /// Emphasized (italic) text
+    Emphasis,
+    /// Strong (bold) text
+    Strong,
+    /// Struck-through text
+    Strikethrough,
+    /// Inline code span, including its backticks
+    Code,
+    /// A line of a fenced code block, fences included
+    CodeBlock,
+    /// Visible text of a link
+    LinkText,
+    /// Destination of a link
+    LinkUrl,
+    /// Bare URL or email address that the preview turns into a link
+    AutoLink,
+    /// Alt text of an image
+    ImageAlt,
+    /// Source of an image
+    ImageUrl,
+    /// List item marker (`-`, `*`, `+`, `1.`, `1)`)
+    ListMarker,
+    /// Block quote marker (`>`)
+    QuoteMarker,
+    /// Backslash escape such as `\*`
+    Escape,
+    /// Markdown punctuation that is not content (`**`, `[`, `](`, ...)
+    Syntax,
+    /// A line including another file, `<!-- include: part.md -->`
+    Include,
+    /// A thematic break such as `---` or `* * *`
+    Rule,
+}
+
 /// Style for a token
 #[derive(Clone, Debug, Default)]
 pub struct TokenStyle {
+    pub kind: TokenKind,
     pub color: Option<egui::Color32>,
     pub background: Option<egui::Color32>,
     pub bold: bool,
@@ -31,111 +81,443 @@ pub struct Line {
     pub tokens: Vec<Token>,
 }
 
+/// What the lines around a line make of it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct LineContext {
+    /// Fence of the code block the line is inside, if any
+    fence: Option<(char, usize)>,
+    /// The line above is paragraph text, which a `===` or `---` line underlines
+    after_paragraph: bool,
+    /// Level of the setext heading the line below underlines this one as
+    underline: Option<u8>,
+}
+
+/// A highlighted line, with what it was highlighted from
+struct CachedLine {
+    text: String,
+    context: LineContext,
+    line: Line,
+}
+
+/// Inline formatting state while scanning a line
+#[derive(Clone, Copy, Default)]
+struct InlineState {
+    bold: bool,
+    italic: bool,
+    strikethrough: bool,
+}
+
 impl MarkdownHighlighter {
     /// Create a new highlighter with the given theme
     pub fn new(theme: Theme) -> Self {
-        Self { theme }
+        Self {
+            theme,
+            wiki_links: false,
+            lines: Vec::new(),
+        }
     }
 
     /// Update the theme
     pub fn set_theme(&mut self, theme: Theme) {
         self.theme = theme;
+        self.lines.clear();
+    }
+
+    /// Enable or disable `[[wiki link]]` highlighting
+    pub fn set_wiki_links(&mut self, enabled: bool) {
+        self.wiki_links = enabled;
+        self.lines.clear();
     }
 
     /// Highlight a line of text
     pub fn highlight_line(&self, line: &str) -> Line {
         let mut tokens = Vec::new();
-        let mut chars = line.chars().peekable();
+
+        if include::directive_target(line, self.wiki_links).is_some() {
+            tokens.push(Token {
+                text: line.to_string(),
+                style: self.kind_style(TokenKind::Include),
+            });
+            return Line { tokens };
+        }
+
+        if let Some(level) = heading_level(line) {
+            return self.heading_line(line, level);
+        }
+
+        let rest = self.highlight_block_prefix(line, &mut tokens);
+        self.highlight_inline(rest, &mut tokens);
+
+        // If no tokens were created, create an empty one
+        if tokens.is_empty() {
+            tokens.push(Token {
+                text: String::new(),
+                style: TokenStyle::default(),
+            });
+        }
+
+        Line { tokens }
+    }
+
+    /// A heading line, with an explicit `{#id}` anchor at its end dimmed
+    fn heading_line(&self, line: &str, level: u8) -> Line {
+        let (text, anchor) = match outline::explicit_anchor(line) {
+            Some((start, _)) => line.split_at(start),
+            None => (line, ""),
+        };
+        let mut tokens = Vec::new();
+        push_token(&mut tokens, text, self.kind_style(TokenKind::Heading(level)));
+        push_token(&mut tokens, anchor, self.kind_style(TokenKind::Syntax));
+        Line { tokens }
+    }
+
+    /// Highlight a line as the lines around it make it: inside a code
+    /// block, a setext heading or its underline, or a thematic break
+    fn highlight_in(&self, line: &str, context: LineContext) -> Line {
+        let whole = |kind| Line {
+            tokens: vec![Token {
+                text: line.to_string(),
+                style: self.kind_style(kind),
+            }],
+        };
+        if context.fence.is_some() || fence_marker(line).is_some() {
+            return whole(TokenKind::CodeBlock);
+        }
+        if let Some(level) = context.underline {
+            return self.heading_line(line, level);
+        }
+        if let Some(level) = setext_level(line).filter(|_| context.after_paragraph) {
+            return whole(TokenKind::Heading(level));
+        }
+        if is_thematic_break(line) {
+            return whole(TokenKind::Rule);
+        }
+        self.highlight_line(line)
+    }
+
+    /// Bring the cached lines up to date with `text`. Only lines that were
+    /// edited, or whose context an edit changed, such as the line above a
+    /// new `---` or every line after a new fence, are highlighted again.
+    /// Returns how many were.
+    fn update(&mut self, text: &str) -> usize {
+        let texts: Vec<&str> = text.split('\n').collect();
+        let contexts = line_contexts(&texts);
+        let mut old: Vec<Option<CachedLine>> = std::mem::take(&mut self.lines).into_iter().map(Some).collect();
+
+        // Lines before and after the edited ones line up with the old lines
+        let prefix = old
+            .iter()
+            .zip(&texts)
+            .take_while(|(cached, text)| cached.as_ref().is_some_and(|cached| cached.text == **text))
+            .count();
+        let suffix = old
+            .iter()
+            .rev()
+            .zip(texts.iter().rev())
+            .take(old.len().min(texts.len()) - prefix)
+            .take_while(|(cached, text)| cached.as_ref().is_some_and(|cached| cached.text == **text))
+            .count();
+
+        let (new_len, old_len) = (texts.len(), old.len());
+        let mut highlighted = 0;
+        let lines = texts
+            .into_iter()
+            .zip(contexts)
+            .enumerate()
+            .map(|(i, (text, context))| {
+                let old_index = if i < prefix {
+                    Some(i)
+                } else if i >= new_len - suffix {
+                    Some(old_len - (new_len - i))
+                } else {
+                    None
+                };
+                let cached = old_index.and_then(|j| old[j].take()).filter(|cached| cached.context == context);
+                cached.unwrap_or_else(|| {
+                    highlighted += 1;
+                    CachedLine {
+                        text: text.to_string(),
+                        context,
+                        line: self.highlight_in(text, context),
+                    }
+                })
+            })
+            .collect();
+        self.lines = lines;
+        highlighted
+    }
+
+    /// Build an egui layout job for a whole document, one line at a time
+    pub fn layout_job(&mut self, text: &str, font_id: egui::FontId) -> egui::text::LayoutJob {
+        let _span = timing::span(Phase::Highlight);
+        let highlighted = self.update(text);
+        timing::count(Cache::HighlightLines, self.lines.len() - highlighted, highlighted);
+        let mut job = egui::text::LayoutJob::default();
+
+        for (i, cached) in self.lines.iter().enumerate() {
+            if i > 0 {
+                job.append("\n", 0.0, self.text_format(&TokenStyle::default(), font_id.clone()));
+            }
+            for token in &cached.line.tokens {
+                if !token.text.is_empty() {
+                    job.append(&token.text, 0.0, self.text_format(&token.style, font_id.clone()));
+                }
+            }
+        }
+
+        job
+    }
+
+    /// Map a token kind to the theme color used for it
+    pub fn kind_color(&self, kind: TokenKind) -> Option<egui::Color32> {
+        match kind {
+            TokenKind::Text | TokenKind::Emphasis | TokenKind::Strong | TokenKind::Strikethrough => None,
+            TokenKind::Heading(_) => Some(self.theme.accent),
+            TokenKind::Code | TokenKind::CodeBlock => Some(self.theme.text),
+            TokenKind::LinkText | TokenKind::AutoLink | TokenKind::ImageAlt => Some(self.theme.link),
+            TokenKind::LinkUrl | TokenKind::ImageUrl => Some(self.theme.text_muted),
+            TokenKind::ListMarker | TokenKind::QuoteMarker => Some(self.theme.warning),
+            TokenKind::Escape | TokenKind::Syntax | TokenKind::Rule => Some(self.theme.text_muted),
+            TokenKind::Include => Some(self.theme.link),
+        }
+    }
+
+    fn text_format(&self, style: &TokenStyle, font_id: egui::FontId) -> egui::TextFormat {
+        let color = style.color.unwrap_or(self.theme.text);
+        egui::TextFormat {
+            font_id,
+            color,
+            background: style.background.unwrap_or(egui::Color32::TRANSPARENT),
+            italics: style.italic,
+            underline: if style.underline {
+                egui::Stroke::new(1.0, color)
+            } else {
+                egui::Stroke::NONE
+            },
+            strikethrough: if style.strikethrough {
+                egui::Stroke::new(1.0, color)
+            } else {
+                egui::Stroke::NONE
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Emit tokens for leading indentation, quote markers, and a list marker.
+    /// Returns the remainder of the line to be scanned for inline syntax.
+    fn highlight_block_prefix<'a>(&self, line: &'a str, tokens: &mut Vec<Token>) -> &'a str {
+        let mut rest = line;
+
+        loop {
+            let trimmed = rest.trim_start_matches([' ', '\t']);
+            let indent = &rest[..rest.len() - trimmed.len()];
+
+            if let Some(after) = trimmed.strip_prefix('>') {
+                push_token(tokens, indent, self.kind_style(TokenKind::Text));
+                let marker_len = if after.starts_with(' ') { 2 } else { 1 };
+                push_token(tokens, &trimmed[..marker_len], self.kind_style(TokenKind::QuoteMarker));
+                rest = &trimmed[marker_len..];
+                continue;
+            }
+
+            if let Some(marker_len) = list_marker_len(trimmed) {
+                push_token(tokens, indent, self.kind_style(TokenKind::Text));
+                push_token(tokens, &trimmed[..marker_len], self.kind_style(TokenKind::ListMarker));
+                rest = &trimmed[marker_len..];
+            }
+
+            return rest;
+        }
+    }
+
+    /// Scan inline syntax: escapes, code spans, emphasis, strikethrough, links, and images
+    fn highlight_inline(&self, text: &str, tokens: &mut Vec<Token>) {
+        let chars: Vec<char> = text.chars().collect();
+        let mut state = InlineState::default();
         let mut current_text = String::new();
-        let mut in_code_span = false;
-        let mut in_bold = false;
-        let mut in_italic = false;
-        let mut in_strikethrough = false;
+        let mut i = 0;
 
-        while let Some(c) = chars.next() {
+        while i < chars.len() {
+            let c = chars[i];
             match c {
+                '\\' if chars.get(i + 1).is_some_and(|n| n.is_ascii_punctuation()) => {
+                    self.flush(tokens, &mut current_text, state);
+                    let escaped: String = chars[i..i + 2].iter().collect();
+                    push_token(tokens, &escaped, self.kind_style(TokenKind::Escape));
+                    i += 2;
+                }
                 '`' => {
-                    if !current_text.is_empty() {
-                        tokens.push(Token {
-                            text: current_text.clone(),
-                            style: self.create_style(in_bold, in_italic, in_strikethrough, in_code_span),
-                        });
-                        current_text.clear();
+                    let run = run_length(&chars, i, '`');
+                    match find_closing_backticks(&chars, i + run, run) {
+                        Some(end) => {
+                            self.flush(tokens, &mut current_text, state);
+                            let code: String = chars[i..end + run].iter().collect();
+                            push_token(tokens, &code, self.kind_style(TokenKind::Code));
+                            i = end + run;
+                        }
+                        None => {
+                            current_text.extend(&chars[i..i + run]);
+                            i += run;
+                        }
                     }
-                    in_code_span = !in_code_span;
                 }
                 '*' | '_' => {
-                    let next_is_same = chars.peek() == Some(&c);
-
-                    if next_is_same && !in_code_span {
-                        // Bold
-                        chars.next(); // Consume second char
-                        if !current_text.is_empty() {
-                            tokens.push(Token {
-                                text: current_text.clone(),
-                                style: self.create_style(in_bold, in_italic, in_strikethrough, in_code_span),
-                            });
-                            current_text.clear();
+                    let run = run_length(&chars, i, c);
+                    let before = if i == 0 { None } else { Some(chars[i - 1]) };
+                    let after = chars.get(i + run).copied();
+
+                    let intraword = c == '_'
+                        && before.is_some_and(char::is_alphanumeric)
+                        && after.is_some_and(char::is_alphanumeric);
+                    let flanking = can_open(before, after) || can_close(before, after);
+
+                    if intraword || !flanking || run > 3 {
+                        current_text.extend(&chars[i..i + run]);
+                    } else {
+                        self.flush(tokens, &mut current_text, state);
+                        let marker: String = chars[i..i + run].iter().collect();
+                        push_token(tokens, &marker, self.kind_style(TokenKind::Syntax));
+                        if run >= 2 {
+                            state.bold = !state.bold;
                         }
-                        in_bold = !in_bold;
-                    } else if !in_code_span {
-                        // Italic
-                        if !current_text.is_empty() {
-                            tokens.push(Token {
-                                text: current_text.clone(),
-                                style: self.create_style(in_bold, in_italic, in_strikethrough, in_code_span),
-                            });
-                            current_text.clear();
+                        if run != 2 {
+                            state.italic = !state.italic;
                         }
-                        in_italic = !in_italic;
-                    } else {
-                        current_text.push(c);
                     }
+                    i += run;
+                }
+                '~' if chars.get(i + 1) == Some(&'~') => {
+                    self.flush(tokens, &mut current_text, state);
+                    push_token(tokens, "~~", self.kind_style(TokenKind::Syntax));
+                    state.strikethrough = !state.strikethrough;
+                    i += 2;
                 }
-                '~' => {
-                    let next_is_same = chars.peek() == Some(&'~');
-                    if next_is_same && !in_code_span {
-                        chars.next();
-                        if !current_text.is_empty() {
-                            tokens.push(Token {
-                                text: current_text.clone(),
-                                style: self.create_style(in_bold, in_italic, in_strikethrough, in_code_span),
-                            });
-                            current_text.clear();
+                '!' if chars.get(i + 1) == Some(&'[') => {
+                    match parse_link(&chars, i + 1) {
+                        Some(link) => {
+                            self.flush(tokens, &mut current_text, state);
+                            self.push_link(tokens, &chars, &link, true);
+                            i = link.end;
+                        }
+                        None => {
+                            current_text.push(c);
+                            i += 1;
                         }
-                        in_strikethrough = !in_strikethrough;
-                    } else {
-                        current_text.push(c);
                     }
                 }
+                '[' if self.wiki_links && chars.get(i + 1) == Some(&'[') => match parse_wiki_link(&chars, i) {
+                    Some(end) => {
+                        self.flush(tokens, &mut current_text, state);
+                        self.push_wiki_link(tokens, &chars[i + 2..end - 2]);
+                        i = end;
+                    }
+                    None => {
+                        current_text.push(c);
+                        i += 1;
+                    }
+                },
+                '[' => match parse_link(&chars, i) {
+                    Some(link) => {
+                        self.flush(tokens, &mut current_text, state);
+                        self.push_link(tokens, &chars, &link, false);
+                        i = link.end;
+                    }
+                    None => {
+                        current_text.push(c);
+                        i += 1;
+                    }
+                },
                 _ => {
                     current_text.push(c);
+                    i += 1;
                 }
             }
         }
 
         // Add remaining text
-        if !current_text.is_empty() {
-            tokens.push(Token {
-                text: current_text,
-                style: self.create_style(in_bold, in_italic, in_strikethrough, in_code_span),
-            });
+        self.flush(tokens, &mut current_text, state);
+    }
+
+    fn push_link(&self, tokens: &mut Vec<Token>, chars: &[char], link: &LinkSpan, image: bool) {
+        let (text_kind, url_kind, open) = if image {
+            (TokenKind::ImageAlt, TokenKind::ImageUrl, "![")
+        } else {
+            (TokenKind::LinkText, TokenKind::LinkUrl, "[")
+        };
+        let label: String = chars[link.text.clone()].iter().collect();
+        let url: String = chars[link.url.clone()].iter().collect();
+
+        push_token(tokens, open, self.kind_style(TokenKind::Syntax));
+        push_token(tokens, &label, self.kind_style(text_kind));
+        push_token(tokens, "](", self.kind_style(TokenKind::Syntax));
+        push_token(tokens, &url, self.kind_style(url_kind));
+        push_token(tokens, ")", self.kind_style(TokenKind::Syntax));
+    }
+
+    /// `[[target]]` or `[[target|label]]`: the label is styled as link text
+    /// and an aliased target like a link destination
+    fn push_wiki_link(&self, tokens: &mut Vec<Token>, inner: &[char]) {
+        let inner: String = inner.iter().collect();
+        push_token(tokens, "[[", self.kind_style(TokenKind::Syntax));
+        match inner.split_once('|') {
+            Some((target, label)) => {
+                push_token(tokens, target, self.kind_style(TokenKind::LinkUrl));
+                push_token(tokens, "|", self.kind_style(TokenKind::Syntax));
+                push_token(tokens, label, self.kind_style(TokenKind::LinkText));
+            }
+            None => push_token(tokens, &inner, self.kind_style(TokenKind::LinkText)),
         }
+        push_token(tokens, "]]", self.kind_style(TokenKind::Syntax));
+    }
 
-        // If no tokens were created, create an empty one
-        if tokens.is_empty() {
-            tokens.push(Token {
-                text: String::new(),
-                style: TokenStyle::default(),
-            });
+    fn flush(&self, tokens: &mut Vec<Token>, current_text: &mut String, state: InlineState) {
+        if current_text.is_empty() {
+            return;
+        }
+        let text = std::mem::take(current_text);
+        let style = self.create_style(state.bold, state.italic, state.strikethrough, false);
+        let mut last = 0;
+        for link in autolink::find_autolinks(&text) {
+            push_token(tokens, &text[last..link.range.start], style.clone());
+            push_token(tokens, &text[link.range.clone()], self.kind_style(TokenKind::AutoLink));
+            last = link.range.end;
         }
+        push_token(tokens, &text[last..], style);
+    }
 
-        Line { tokens }
+    fn kind_style(&self, kind: TokenKind) -> TokenStyle {
+        TokenStyle {
+            kind,
+            color: self.kind_color(kind),
+            background: match kind {
+                TokenKind::Code | TokenKind::CodeBlock => Some(self.theme.code_bg),
+                TokenKind::Include => Some(self.theme.link.gamma_multiply(0.12)),
+                _ => None,
+            },
+            bold: matches!(kind, TokenKind::Heading(_)),
+            italic: false,
+            underline: matches!(kind, TokenKind::LinkText | TokenKind::AutoLink),
+            strikethrough: false,
+            code: matches!(kind, TokenKind::Code | TokenKind::CodeBlock),
+        }
     }
 
     fn create_style(&self, bold: bool, italic: bool, strikethrough: bool, code: bool) -> TokenStyle {
+        let kind = if code {
+            TokenKind::Code
+        } else if strikethrough {
+            TokenKind::Strikethrough
+        } else if bold {
+            TokenKind::Strong
+        } else if italic {
+            TokenKind::Emphasis
+        } else {
+            TokenKind::Text
+        };
+
         TokenStyle {
-            color: None,
+            kind,
+            color: self.kind_color(kind),
             background: if code { Some(self.theme.code_bg) } else { None },
             bold,
             italic,
@@ -151,3 +533,354 @@ impl Default for MarkdownHighlighter {
         Self::new(Theme::default())
     }
 }
+
+/// Character ranges of a parsed `[text](url)` construct
+struct LinkSpan {
+    text: std::ops::Range<usize>,
+    url: std::ops::Range<usize>,
+    /// Index just past the closing parenthesis
+    end: usize,
+}
+
+fn push_token(tokens: &mut Vec<Token>, text: &str, style: TokenStyle) {
+    if !text.is_empty() {
+        tokens.push(Token {
+            text: text.to_string(),
+            style,
+        });
+    }
+}
+
+/// Return the level of an ATX heading line (`# Title`), if the line is one
+fn heading_level(line: &str) -> Option<u8> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+    if !(1..=6).contains(&hashes) {
+        return None;
+    }
+    match trimmed[hashes..].chars().next() {
+        None | Some(' ') | Some('\t') => Some(hashes as u8),
+        _ => None,
+    }
+}
+
+/// Level of a setext heading a line of `=` (1) or `-` (2) underlines
+fn setext_level(line: &str) -> Option<u8> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let marker = trimmed.trim_end();
+    match marker.chars().next()? {
+        '=' if marker.chars().all(|c| c == '=') => Some(1),
+        '-' if marker.chars().all(|c| c == '-') => Some(2),
+        _ => None,
+    }
+}
+
+/// Whether a line is a thematic break: three or more of one of `-`, `*`,
+/// `_`, with spaces between allowed
+fn is_thematic_break(line: &str) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return false;
+    }
+    let mut marks = trimmed.chars().filter(|c| !matches!(c, ' ' | '\t'));
+    let Some(mark) = marks.next().filter(|c| matches!(c, '-' | '*' | '_')) else {
+        return false;
+    };
+    let rest: Vec<char> = marks.collect();
+    rest.len() >= 2 && rest.iter().all(|&c| c == mark)
+}
+
+/// Whether a line outside code blocks is paragraph text, which a setext
+/// underline below makes a heading
+fn is_paragraph_text(line: &str) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    !trimmed.trim().is_empty()
+        && line.len() - trimmed.len() <= 3
+        && heading_level(line).is_none()
+        && fence_marker(line).is_none()
+        && !is_thematic_break(line)
+        && !trimmed.starts_with('>')
+        && list_marker_len(trimmed).is_none()
+}
+
+/// The context of each line: fence state flows down through the text, and
+/// a setext underline reaches back to the line above it
+fn line_contexts(lines: &[&str]) -> Vec<LineContext> {
+    let mut contexts = vec![LineContext::default(); lines.len()];
+    let mut fence = None;
+    let mut paragraph = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        contexts[i].fence = fence;
+        contexts[i].after_paragraph = paragraph;
+        let marker = fence_marker(line);
+        match (fence, marker) {
+            (None, Some(marker)) => fence = Some(marker),
+            (Some((open, open_len)), Some((marker, len))) if open == marker && len >= open_len => fence = None,
+            _ => {}
+        }
+
+        let underline = if paragraph { setext_level(line) } else { None };
+        if let Some(level) = underline {
+            contexts[i - 1].underline = Some(level);
+        }
+        paragraph = contexts[i].fence.is_none() && underline.is_none() && is_paragraph_text(line);
+    }
+    contexts
+}
+
+/// Length in bytes of a list marker (including the following space) at the start of `text`
+fn list_marker_len(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let marker_len = match bytes.first()? {
+        b'-' | b'*' | b'+' => 1,
+        b'0'..=b'9' => {
+            let digits = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+            if digits > 9 || !matches!(bytes.get(digits), Some(b'.') | Some(b')')) {
+                return None;
+            }
+            digits + 1
+        }
+        _ => return None,
+    };
+    match bytes.get(marker_len) {
+        Some(b' ') | Some(b'\t') => Some(marker_len + 1),
+        _ => None,
+    }
+}
+
+fn run_length(chars: &[char], start: usize, c: char) -> usize {
+    chars[start..].iter().take_while(|&&ch| ch == c).count()
+}
+
+fn find_closing_backticks(chars: &[char], from: usize, run: usize) -> Option<usize> {
+    let mut i = from;
+    while i < chars.len() {
+        if chars[i] == '`' {
+            let len = run_length(chars, i, '`');
+            if len == run {
+                return Some(i);
+            }
+            i += len;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// A delimiter run can open emphasis when it is not followed by whitespace
+fn can_open(_before: Option<char>, after: Option<char>) -> bool {
+    after.is_some_and(|c| !c.is_whitespace())
+}
+
+/// A delimiter run can close emphasis when it is not preceded by whitespace
+fn can_close(before: Option<char>, _after: Option<char>) -> bool {
+    before.is_some_and(|c| !c.is_whitespace())
+}
+
+/// Find the end (just past `]]`) of a wiki link whose `[[` is at `start`
+fn parse_wiki_link(chars: &[char], start: usize) -> Option<usize> {
+    let inner_start = start + 2;
+    let close = (inner_start..chars.len().saturating_sub(1))
+        .find(|&i| chars[i] == ']' && chars[i + 1] == ']')?;
+    let inner = &chars[inner_start..close];
+    let valid = inner.iter().any(|c| !c.is_whitespace()) && !inner.iter().any(|c| matches!(c, '[' | ']'));
+    valid.then_some(close + 2)
+}
+
+/// Parse `[text](url)` starting at the `[` at index `start`
+fn parse_link(chars: &[char], start: usize) -> Option<LinkSpan> {
+    let mut depth = 0;
+    let mut i = start;
+    let close_bracket = loop {
+        match chars.get(i)? {
+            '\\' => i += 1,
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    break i;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    };
+
+    if chars.get(close_bracket + 1) != Some(&'(') {
+        return None;
+    }
+
+    let url_start = close_bracket + 2;
+    let mut parens = 0;
+    let mut j = url_start;
+    let close_paren = loop {
+        match chars.get(j)? {
+            '\\' => j += 1,
+            '(' => parens += 1,
+            ')' if parens == 0 => break j,
+            ')' => parens -= 1,
+            _ => {}
+        }
+        j += 1;
+    };
+
+    Some(LinkSpan {
+        text: start + 1..close_bracket,
+        url: url_start..close_paren,
+        end: close_paren + 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TokenKind::*;
+
+    fn kinds(line: &str) -> Vec<(TokenKind, String)> {
+        MarkdownHighlighter::default()
+            .highlight_line(line)
+            .tokens
+            .into_iter()
+            .filter(|t| !t.text.is_empty())
+            .map(|t| (t.style.kind, t.text))
+            .collect()
+    }
+
+    #[test]
+    fn test_highlight_table() {
+        let cases: &[(&str, &[(TokenKind, &str)])] = &[
+            ("plain text", &[(Text, "plain text")]),
+            ("# Title", &[(Heading(1), "# Title")]),
+            ("### Deep *title*", &[(Heading(3), "### Deep *title*")]),
+            ("## Install {#setup}", &[(Heading(2), "## Install "), (Syntax, "{#setup}")]),
+            ("## Odd {#}", &[(Heading(2), "## Odd {#}")]),
+            ("#hashtag", &[(Text, "#hashtag")]),
+            ("####### seven", &[(Text, "####### seven")]),
+            ("snake_case_identifier", &[(Text, "snake_case_identifier")]),
+            ("an _emph_ word", &[(Text, "an "), (Syntax, "_"), (Emphasis, "emph"), (Syntax, "_"), (Text, " word")]),
+            ("**bold**", &[(Syntax, "**"), (Strong, "bold"), (Syntax, "**")]),
+            ("a * b", &[(Text, "a * b")]),
+            ("\\*not italic\\*", &[(Escape, "\\*"), (Text, "not italic"), (Escape, "\\*")]),
+            ("`co*de`", &[(Code, "`co*de`")]),
+            ("``a ` b``", &[(Code, "``a ` b``")]),
+            ("~~gone~~", &[(Syntax, "~~"), (Strikethrough, "gone"), (Syntax, "~~")]),
+            (
+                "see [docs](https://x.y)",
+                &[(Text, "see "), (Syntax, "["), (LinkText, "docs"), (Syntax, "]("), (LinkUrl, "https://x.y"), (Syntax, ")")],
+            ),
+            (
+                "![alt](img.png)",
+                &[(Syntax, "!["), (ImageAlt, "alt"), (Syntax, "]("), (ImageUrl, "img.png"), (Syntax, ")")],
+            ),
+            ("[not a link]", &[(Text, "[not a link]")]),
+            ("(see https://x.y).", &[(Text, "(see "), (AutoLink, "https://x.y"), (Text, ").")]),
+            ("mail me@example.com", &[(Text, "mail "), (AutoLink, "me@example.com")]),
+            ("- item", &[(ListMarker, "- "), (Text, "item")]),
+            ("* item", &[(ListMarker, "* "), (Text, "item")]),
+            ("  12. item", &[(Text, "  "), (ListMarker, "12. "), (Text, "item")]),
+            ("> quote", &[(QuoteMarker, "> "), (Text, "quote")]),
+            ("> - nested", &[(QuoteMarker, "> "), (ListMarker, "- "), (Text, "nested")]),
+            ("-not a list", &[(Text, "-not a list")]),
+            ("2024. was a year", &[(ListMarker, "2024. "), (Text, "was a year")]),
+            ("<!-- include: ./part1.md -->", &[(Include, "<!-- include: ./part1.md -->")]),
+            ("<!-- note -->", &[(Text, "<!-- note -->")]),
+        ];
+
+        for (line, expected) in cases {
+            let expected: Vec<(TokenKind, String)> =
+                expected.iter().map(|(k, t)| (*k, t.to_string())).collect();
+            assert_eq!(kinds(line), expected, "line: {:?}", line);
+        }
+    }
+
+    #[test]
+    fn test_wiki_links_only_when_enabled() {
+        assert_eq!(kinds("[[Note]]"), vec![(Text, "[[Note]]".to_string())]);
+
+        let mut highlighter = MarkdownHighlighter::default();
+        highlighter.set_wiki_links(true);
+        let tokens: Vec<_> = highlighter
+            .highlight_line("see [[Note Name|the note]]")
+            .tokens
+            .into_iter()
+            .map(|t| (t.style.kind, t.text))
+            .collect();
+        let expected = [
+            (Text, "see "),
+            (Syntax, "[["),
+            (LinkUrl, "Note Name"),
+            (Syntax, "|"),
+            (LinkText, "the note"),
+            (Syntax, "]]"),
+        ];
+        let expected: Vec<_> = expected.iter().map(|(k, t)| (*k, t.to_string())).collect();
+        assert_eq!(tokens, expected);
+    }
+
+    #[test]
+    fn test_empty_line() {
+        let line = MarkdownHighlighter::default().highlight_line("");
+        assert_eq!(line.tokens.len(), 1);
+        assert_eq!(line.tokens[0].style.kind, Text);
+    }
+
+    /// First token kind of each cached line
+    fn line_kinds(highlighter: &MarkdownHighlighter) -> Vec<TokenKind> {
+        highlighter.lines.iter().map(|cached| cached.line.tokens[0].style.kind).collect()
+    }
+
+    #[test]
+    fn test_edits_update_neighbouring_lines() {
+        let mut highlighter = MarkdownHighlighter::default();
+        assert_eq!(highlighter.update("Title\n\nbody"), 3);
+        assert_eq!(highlighter.update("Title\n\nbody!"), 1);
+
+        // Underlining a line makes the line above a heading
+        assert_eq!(highlighter.update("Title\n---\nbody!"), 2);
+        assert_eq!(line_kinds(&highlighter), [Heading(2), Heading(2), Text]);
+        assert_eq!(highlighter.update("Title\n===\nbody!"), 2);
+        assert_eq!(line_kinds(&highlighter), [Heading(1), Heading(1), Text]);
+
+        // After a blank line the same `---` is a rule, and the title text again
+        assert_eq!(highlighter.update("Title\n\n---\nbody!"), 3);
+        assert_eq!(line_kinds(&highlighter), [Text, Text, Rule, Text]);
+
+        // Typing at line starts applies at once
+        highlighter.update("# Title\n\n> body!");
+        assert_eq!(line_kinds(&highlighter), [Heading(1), Text, QuoteMarker]);
+    }
+
+    #[test]
+    fn test_fences_update_following_lines() {
+        let mut highlighter = MarkdownHighlighter::default();
+        highlighter.update("a\n# b\nc\n");
+        assert_eq!(highlighter.update("```\n# b\nc\n"), 4);
+        assert_eq!(line_kinds(&highlighter), [CodeBlock, CodeBlock, CodeBlock, CodeBlock]);
+
+        // Closing the fence gives the lines after it back
+        assert_eq!(highlighter.update("```\n# b\n```\n"), 2);
+        assert_eq!(line_kinds(&highlighter), [CodeBlock, CodeBlock, CodeBlock, Text]);
+
+        // Editing inside the block leaves the rest alone
+        assert_eq!(highlighter.update("```\n# b!\n```\n"), 1);
+
+        // A paragraph line ends at a fence, so a `---` after one is a rule
+        highlighter.update("```\n```\n---");
+        assert_eq!(line_kinds(&highlighter), [CodeBlock, CodeBlock, Rule]);
+    }
+
+    #[test]
+    fn test_layout_job_preserves_text() {
+        let text = "# Title\n\nSome **bold** and [link](x)\n- item";
+        let job = MarkdownHighlighter::default().layout_job(text, egui::FontId::monospace(14.0));
+        assert_eq!(job.text, text);
+    }
+}
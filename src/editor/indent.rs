@@ -0,0 +1,231 @@
+//! Indentation as a document uses it: Tab and Shift+Tab indent with spaces
+//! or tabs as set, the indentation a file already uses is read from its
+//! leading whitespace, and a document converts between tabs and spaces
+
+use super::Editor;
+use crate::markdown::fence_marker;
+
+/// How a document is indented
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Indentation {
+    /// Indent with spaces rather than tabs
+    pub spaces: bool,
+    /// Columns per level, and between tab stops
+    pub size: usize,
+}
+
+impl Indentation {
+    /// One level of indentation
+    pub fn unit(self) -> String {
+        if self.spaces {
+            " ".repeat(self.size)
+        } else {
+            "\t".to_string()
+        }
+    }
+}
+
+/// Columns `text` takes from the start of a line, with tab stops every
+/// `size` columns
+pub fn columns(text: &str, size: usize) -> usize {
+    let size = size.max(1);
+    text.chars().fold(0, |column, c| if c == '\t' { column + size - column % size } else { column + 1 })
+}
+
+/// Leading spaces and tabs of `line`
+fn leading_whitespace(line: &str) -> &str {
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// The indentation `text` uses, from the leading whitespace of its lines:
+/// tabs or spaces by which more lines start with, and the size from the
+/// step most often taken from one line's indentation to a deeper one, the
+/// smaller on a tie. A size that can't be told is `default_size`. `None`
+/// when no line is indented.
+pub fn detect(text: &str, default_size: usize) -> Option<Indentation> {
+    let (mut tabs, mut spaces) = (0, 0);
+    let mut steps = [0usize; 9];
+    let mut previous = 0;
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let indent = leading_whitespace(line);
+        if indent.starts_with('\t') {
+            tabs += 1;
+            continue;
+        }
+        if indent.contains('\t') {
+            continue;
+        }
+        if indent.len() > 1 {
+            spaces += 1;
+        }
+        if let Some(step) = indent.len().checked_sub(previous).filter(|step| (2..=8).contains(step)) {
+            steps[step] += 1;
+        }
+        previous = indent.len();
+    }
+    if tabs == 0 && spaces == 0 {
+        return None;
+    }
+    if tabs > spaces {
+        return Some(Indentation { spaces: false, size: default_size });
+    }
+    let size = (2..=8).filter(|&size| steps[size] > 0).max_by_key(|&size| (steps[size], std::cmp::Reverse(size)));
+    Some(Indentation { spaces: true, size: size.unwrap_or(default_size) })
+}
+
+/// `text` with the leading whitespace of each line redone in `to`'s tabs
+/// or spaces, keeping its width. Lines inside fenced code blocks are left
+/// as they are when `skip_code` is set.
+pub fn convert(text: &str, to: Indentation, skip_code: bool) -> String {
+    let size = to.size.max(1);
+    let mut out = String::with_capacity(text.len());
+    // Marker and length of the fence of the code block the line is in
+    let mut fence: Option<(char, usize)> = None;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let in_code = fence.is_some();
+        match (fence, fence_marker(content)) {
+            (None, Some(marker)) => fence = Some(marker),
+            (Some((open, open_len)), Some((marker, len))) if marker == open && len >= open_len && content.trim().len() == len => {
+                fence = None
+            }
+            _ => {}
+        }
+        let indent = leading_whitespace(line);
+        if (skip_code && in_code && fence.is_some()) || indent.is_empty() {
+            out.push_str(line);
+            continue;
+        }
+        let width = columns(indent, size);
+        if to.spaces {
+            out.push_str(&" ".repeat(width));
+        } else {
+            out.push_str(&"\t".repeat(width / size));
+            out.push_str(&" ".repeat(width % size));
+        }
+        out.push_str(&line[indent.len()..]);
+    }
+    out
+}
+
+impl Editor {
+    /// Tab: indent the selected lines when the selection spans more than
+    /// one, otherwise put indentation at the caret in place of the
+    /// selection. Spaces go as far as the next tab stop.
+    pub fn tab(&mut self, indentation: Indentation) {
+        if self.selection().is_some() && self.selected_lines().len() > 1 {
+            self.shift_lines(indentation, false);
+            return;
+        }
+        let range = self.selection_range();
+        let (line, _) = self.buffer.line_col_from_byte_index(range.start);
+        let line_start = self.buffer.line_range(line).start;
+        let unit = if indentation.spaces {
+            let size = indentation.size.max(1);
+            let column = columns(&self.buffer.substring(line_start, range.start), size);
+            " ".repeat(size - column % size)
+        } else {
+            "\t".to_string()
+        };
+        self.insert_text(&unit);
+    }
+
+    /// Indent the selected lines (or the caret line) by one level, or take
+    /// one level off with `outdent`, keeping the selection on the same
+    /// text. Blank lines aren't indented. Returns whether anything changed.
+    pub fn shift_lines(&mut self, indentation: Indentation, outdent: bool) -> bool {
+        let lines = self.selected_lines();
+        let span = self.lines_span(lines.clone());
+        let text = self.buffer.substring(span.start, span.end);
+        let unit = indentation.unit();
+
+        // Start of each line in the span, with what was taken off and put on there
+        let mut changes: Vec<(usize, usize, usize)> = Vec::new();
+        let mut replacement = String::with_capacity(text.len() + unit.len() * lines.len());
+        let mut offset = span.start;
+        for line in text.split_inclusive('\n') {
+            let removed = if !outdent {
+                0
+            } else if line.starts_with('\t') {
+                1
+            } else {
+                line.chars().take(indentation.size.max(1)).take_while(|&c| c == ' ').count()
+            };
+            let inserted = if outdent || line.trim().is_empty() { "" } else { unit.as_str() };
+            replacement.push_str(inserted);
+            replacement.push_str(&line[removed..]);
+            changes.push((offset, removed, inserted.len()));
+            offset += line.len();
+        }
+        if replacement == text {
+            return false;
+        }
+
+        let moved = |pos: usize| {
+            let mut shift = 0isize;
+            for &(start, removed, inserted) in &changes {
+                if pos < start {
+                    break;
+                }
+                let column = pos - start;
+                if column > 0 || removed > 0 {
+                    shift += inserted as isize - column.min(removed) as isize;
+                }
+            }
+            pos.saturating_add_signed(shift)
+        };
+        let selection = (moved(self.anchor), moved(self.cursor));
+        self.replace_keeping_selection(span, &replacement, selection);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::test_editor;
+
+    const SPACES: Indentation = Indentation { spaces: true, size: 4 };
+    const TABS: Indentation = Indentation { spaces: false, size: 4 };
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("# Title\n\nText\n", 4), None);
+        let two = "- a\n  - b\n    - c\n  - d\n- e\n";
+        assert_eq!(detect(two, 4), Some(Indentation { spaces: true, size: 2 }));
+        assert_eq!(detect("- a\n\t- b\n\t\t- c\n", 3), Some(Indentation { spaces: false, size: 3 }));
+        // A tie goes to the smaller step
+        assert_eq!(detect("a\n    b\nc\n  d\n", 4), Some(Indentation { spaces: true, size: 2 }));
+    }
+
+    #[test]
+    fn test_convert() {
+        let text = "- a\n\t- b\n  \t- c\n```\n\tcode\n```\n";
+        assert_eq!(convert(text, SPACES, false), "- a\n    - b\n    - c\n```\n    code\n```\n");
+        assert_eq!(convert(text, SPACES, true), "- a\n    - b\n    - c\n```\n\tcode\n```\n");
+        assert_eq!(convert("      x\n", TABS, false), "\t  x\n");
+    }
+
+    #[test]
+    fn test_tab_and_shift_lines() {
+        let mut e = test_editor("ab", 1, 1);
+        e.tab(SPACES);
+        assert_eq!((e.text().as_str(), e.cursor()), ("a   b", 4));
+        e.tab(TABS);
+        assert_eq!(e.text(), "a   \tb");
+
+        // Whole lines selected stay selected, blank lines stay blank
+        let mut e = test_editor("one\n\ntwo\nthree", 0, 9);
+        e.tab(SPACES);
+        assert_eq!(e.text(), "    one\n\n    two\nthree");
+        assert_eq!(e.selection(), Some(0..17));
+        assert!(e.shift_lines(SPACES, true));
+        assert_eq!(e.text(), "one\n\ntwo\nthree");
+        assert_eq!(e.selection(), Some(0..9));
+        assert!(!e.shift_lines(SPACES, true));
+
+        let mut e = test_editor("\t  x", 4, 4);
+        assert!(e.shift_lines(TABS, true));
+        assert_eq!((e.text().as_str(), e.cursor()), ("  x", 3));
+    }
+}
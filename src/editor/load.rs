@@ -0,0 +1,88 @@
+//! Reading a document: files too large to edit are refused before they are
+//! read, and text that isn't UTF-8 is told apart from other failures, so it
+//! can be opened with its bad bytes replaced instead
+
+use super::error::{EditorError, Operation};
+use std::fs;
+use std::path::Path;
+
+/// Largest file that is opened, in bytes
+pub const MAX_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The text of `path`, read for `operation`
+pub fn read(path: &Path, operation: Operation) -> Result<String, EditorError> {
+    read_with_limit(path, operation, MAX_FILE_SIZE, false)
+}
+
+/// The text of `path` with anything that isn't UTF-8 replaced by `�`
+pub fn read_lossy(path: &Path, operation: Operation) -> Result<String, EditorError> {
+    read_with_limit(path, operation, MAX_FILE_SIZE, true)
+}
+
+fn read_with_limit(path: &Path, operation: Operation, limit: u64, lossy: bool) -> Result<String, EditorError> {
+    let io_error = |e| EditorError::from_io(operation, path, e);
+    let size = fs::metadata(path).map_err(io_error)?.len();
+    if size > limit {
+        return Err(EditorError::TooLarge { path: path.to_path_buf(), operation, size, limit });
+    }
+    let bytes = fs::read(path).map_err(io_error)?;
+    if lossy {
+        return Ok(String::from_utf8_lossy(&bytes).into_owned());
+    }
+    String::from_utf8(bytes).map_err(|e| EditorError::InvalidUtf8 {
+        path: path.to_path_buf(),
+        operation,
+        // A NUL byte means a binary file, not text in another encoding
+        lossy_available: !e.as_bytes().contains(&0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+    use std::io;
+
+    #[test]
+    fn test_read_errors() {
+        let dir = test_dir("load-errors");
+        let missing = dir.join("missing.md");
+        assert!(matches!(read(&missing, Operation::Open), Err(EditorError::NotFound { operation: Operation::Open, .. })));
+
+        // Latin-1 text can be opened lossily; a binary file isn't offered
+        let latin1 = dir.join("latin1.md");
+        fs::write(&latin1, b"caf\xe9\n").unwrap();
+        let error = read(&latin1, Operation::Open).unwrap_err();
+        assert!(matches!(error, EditorError::InvalidUtf8 { lossy_available: true, .. }));
+        assert_eq!(error.path(), latin1);
+        assert_eq!(read_lossy(&latin1, Operation::Open).unwrap(), "caf\u{fffd}\n");
+        let binary = dir.join("image.md");
+        fs::write(&binary, b"\x89PNG\0\0\xff").unwrap();
+        assert!(matches!(read(&binary, Operation::Reload), Err(EditorError::InvalidUtf8 { lossy_available: false, .. })));
+
+        let large = dir.join("large.md");
+        fs::write(&large, "# Notes\n").unwrap();
+        let error = read_with_limit(&large, Operation::Open, 4, false).unwrap_err();
+        assert!(matches!(error, EditorError::TooLarge { size: 8, limit: 4, .. }));
+        assert_eq!(read(&large, Operation::Open).unwrap(), "# Notes\n");
+
+        // A folder can't be read as a document
+        let error = read(&dir, Operation::Open).unwrap_err();
+        assert!(matches!(error, EditorError::Io { .. }));
+        assert!(error.to_string().starts_with(&format!("Couldn't open {}: ", dir.display())));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_permission_denied() {
+        // Made up rather than set on a file, which an administrator could
+        // read anyway
+        let path = Path::new("/docs/private.md");
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        let error = EditorError::from_io(Operation::Save, path, denied);
+        assert!(matches!(error, EditorError::PermissionDenied { .. }));
+        assert_eq!(error.operation(), Operation::Save);
+        assert_eq!(error.to_string(), "Not allowed to save /docs/private.md");
+    }
+}
@@ -1,21 +1,70 @@
+pub mod autocomplete;
+mod bookmarks;
+pub mod diff;
+pub mod error;
+mod fences;
 pub mod highlighter;
+pub mod indent;
+pub mod load;
+pub mod save;
+pub mod search;
+mod selection;
+pub mod snippets;
+mod structure;
+mod tables;
 pub mod text_buffer;
+pub mod typing_aids;
+pub mod view_state;
+pub mod words;
 
 use crate::config::EditorConfig;
+use crate::markdown::figures::{self, Figure};
+use crate::markdown::outline::{self, HeadingEntry};
+use crate::utils::timing::{self, Phase};
 use egui::{text_edit::TextEditState, *};
+use snippets::{ExpandedSnippet, SnippetSession};
+use std::ops::Range;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use text_buffer::TextBuffer;
 
+/// Source of the ids that keep each editor's widget state apart
+static NEXT_EDITOR_ID: AtomicU64 = AtomicU64::new(0);
+
 /// A rich text editor for Markdown
 pub struct Editor {
+    /// Tells this document's widget state apart from other documents'
+    id: u64,
     buffer: TextBuffer,
     config: EditorConfig,
     has_focus: bool,
+    /// Caret line and column, derived from `cursor`
     cursor_position: (usize, usize),
-    selection: Option<(usize, usize)>,
+    /// Byte offset of the caret
+    cursor: usize,
+    /// Byte offset of the selection anchor (equal to `cursor` when nothing is selected)
+    anchor: usize,
+    /// Selection (anchor, caret) to push into the text widget on the next frame
+    pending_selection: Option<(usize, usize)>,
+    /// Screen rectangle of the caret from the last frame
+    caret_rect: Option<Rect>,
+    /// Tab stops of a snippet being filled in
+    snippet: Option<SnippetSession>,
     history: EditHistory,
     dirty: bool,
+    /// Text as last opened or saved, which changes are measured against
+    saved_text: String,
+    /// Bumped on every change to the text
+    revision: u64,
+    /// Headings of the current text, computed on demand
+    outline: Option<Vec<HeadingEntry>>,
+    /// Figures of the current text, computed on demand
+    figures: Option<Vec<Figure>>,
+    /// Bookmarked lines, zero-based and in order
+    bookmarks: Vec<usize>,
     scroll_offset: Vec2,
+    /// Scroll offset to push to the text widget on the next frame
+    pending_scroll: Option<f32>,
     text_edit_state: Option<TextEditState>,
 }
 
@@ -30,21 +79,34 @@ struct Edit {
     old_text: String,
     new_text: String,
     position: usize,
+    /// Selection (anchor, caret) in bytes before the edit
     cursor_before: (usize, usize),
+    /// Selection (anchor, caret) in bytes after the edit
     cursor_after: (usize, usize),
 }
 
 impl Editor {
     pub fn new() -> Self {
         Self {
+            id: NEXT_EDITOR_ID.fetch_add(1, Ordering::Relaxed),
             buffer: TextBuffer::new(),
             config: EditorConfig::default(),
             has_focus: false,
             cursor_position: (0, 0),
-            selection: None,
+            cursor: 0,
+            anchor: 0,
+            pending_selection: None,
+            caret_rect: None,
+            snippet: None,
             history: EditHistory::new(1000),
             dirty: false,
+            saved_text: String::new(),
+            revision: 0,
+            outline: None,
+            figures: None,
+            bookmarks: Vec::new(),
             scroll_offset: Vec2::ZERO,
+            pending_scroll: None,
             text_edit_state: None,
         }
     }
@@ -59,51 +121,338 @@ impl Editor {
 
     pub fn set_text(&mut self, text: impl Into<String>) {
         let text = text.into();
+        self.saved_text = text.clone();
         self.buffer = TextBuffer::from(text);
+        self.mark_changed();
         self.dirty = false;
         self.history.clear();
+        self.snippet = None;
+        self.bookmarks.clear();
+        self.set_cursor(0);
     }
 
     pub fn cursor_position(&self) -> (usize, usize) {
         self.cursor_position
     }
 
+    /// Counter that changes whenever the text does
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Headings of the document, cached until the text changes
+    pub fn headings(&mut self) -> &[HeadingEntry] {
+        let buffer = &self.buffer;
+        self.outline.get_or_insert_with(|| outline::headings(&buffer.as_str()))
+    }
+
+    /// Captioned images of the document, cached until the text changes
+    pub fn figures(&mut self) -> &[Figure] {
+        let buffer = &self.buffer;
+        self.figures.get_or_insert_with(|| figures::find_figures(&buffer.as_str()))
+    }
+
+    fn mark_changed(&mut self) {
+        self.dirty = true;
+        self.revision += 1;
+        self.outline = None;
+        self.figures = None;
+    }
+
+    /// Id of the text widget backing this editor, different for each
+    /// editor so their cursors and selections in egui memory stay apart
+    pub fn widget_id(&self) -> Id {
+        Id::new(("rmd_editor_text", self.id))
+    }
+
+    pub fn has_focus(&self) -> bool {
+        self.has_focus
+    }
+
+    pub fn set_focus(&mut self, has_focus: bool) {
+        self.has_focus = has_focus;
+    }
+
+    /// Byte offset of the caret
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Selected byte range, or `None` when the selection is empty
+    pub fn selection(&self) -> Option<Range<usize>> {
+        let range = self.selection_range();
+        (!range.is_empty()).then_some(range)
+    }
+
+    /// Selected byte range, collapsed to the caret when nothing is selected
+    pub fn selection_range(&self) -> Range<usize> {
+        self.anchor.min(self.cursor)..self.anchor.max(self.cursor)
+    }
+
+    /// Move the caret, clearing the selection
+    pub fn set_cursor(&mut self, pos: usize) {
+        self.set_selection(pos, pos);
+    }
+
+    /// Move the caret to the start of a zero-based line
+    pub fn go_to_line(&mut self, line: usize) {
+        let pos = self.buffer.byte_index_from_line_col(line, 0);
+        self.set_cursor(pos);
+    }
+
+    /// Select from `anchor` to `cursor` (byte offsets) and push it to the text widget
+    pub fn set_selection(&mut self, anchor: usize, cursor: usize) {
+        self.sync_selection(anchor, cursor);
+        self.pending_selection = Some((self.anchor, self.cursor));
+    }
+
+    /// Record the selection reported by the text widget
+    pub fn sync_selection(&mut self, anchor: usize, cursor: usize) {
+        let len = self.buffer.len();
+        self.anchor = anchor.min(len);
+        self.cursor = cursor.min(len);
+        self.cursor_position = self.buffer.line_col_from_byte_index(self.cursor);
+    }
+
+    /// Take the selection that should be applied to the text widget this frame
+    pub fn take_pending_selection(&mut self) -> Option<(usize, usize)> {
+        self.pending_selection.take()
+    }
+
+    pub fn caret_rect(&self) -> Option<Rect> {
+        self.caret_rect
+    }
+
+    pub fn set_caret_rect(&mut self, rect: Option<Rect>) {
+        self.caret_rect = rect;
+    }
+
+    /// Replace a byte range with new text as a single undoable edit,
+    /// leaving the caret after the inserted text
+    pub fn replace_range(&mut self, range: Range<usize>, text: &str) {
+        let end = range.start + text.len();
+        self.record_edit(range, text, (end, end), false);
+        self.set_cursor(end);
+    }
+
+    /// Replace a byte range as a single undoable edit, keeping the caret and
+    /// selection on the same text where they fall outside the range
+    pub fn replace_range_in_place(&mut self, range: Range<usize>, text: &str) {
+        let shift = |pos: usize| {
+            if pos <= range.start {
+                pos
+            } else if pos >= range.end {
+                pos - range.len() + text.len()
+            } else {
+                range.start
+            }
+        };
+        let selection = (shift(self.anchor), shift(self.cursor));
+        self.record_edit(range, text, selection, false);
+        self.set_selection(selection.0, selection.1);
+    }
+
+    /// Replace a byte range like `replace_range_in_place`, but as part of
+    /// the last undoable edit, so one undo takes back both
+    pub fn amend_last_edit(&mut self, range: Range<usize>, text: &str) {
+        let Some(last) = self.history.undo_stack.pop() else {
+            self.replace_range_in_place(range, text);
+            return;
+        };
+        let shift = |pos: usize| {
+            if pos <= range.start {
+                pos
+            } else if pos >= range.end {
+                pos - range.len() + text.len()
+            } else {
+                range.start
+            }
+        };
+        let selection = (shift(self.anchor), shift(self.cursor));
+        if let Some(session) = self.snippet.as_mut() {
+            session.adjust(range.start, range.len(), text.len());
+        }
+        let old_range_text = self.buffer.substring(range.start, range.end);
+        self.shift_bookmarks(range.start, &old_range_text, text);
+
+        // The region both edits cover, in the text between them
+        let last_end = last.position + last.new_text.len();
+        let start = last.position.min(range.start);
+        let end = last_end.max(range.end);
+        let mut old_text = self.buffer.substring(start, last.position);
+        old_text.push_str(&last.old_text);
+        old_text.push_str(&self.buffer.substring(last_end, end));
+        self.buffer.replace_range(range.clone(), text);
+        let new_end = end + text.len() - range.len();
+        let edit = Edit {
+            old_text,
+            new_text: self.buffer.substring(start, new_end),
+            position: start,
+            cursor_before: last.cursor_before,
+            cursor_after: selection,
+        };
+        self.mark_changed();
+        self.history.push(edit);
+        self.set_selection(selection.0, selection.1);
+    }
+
+    /// Apply the full text reported by the text widget after the user typed,
+    /// recording only the changed region in the undo history
+    pub fn apply_widget_text(&mut self, new_text: &str) {
+        let old_text = self.buffer.as_str();
+        let Some((range, replacement)) = diff_region(&old_text, new_text) else {
+            return;
+        };
+        let end = range.start + replacement.len();
+        self.record_edit(range, replacement, (end, end), true);
+    }
+
+    /// Replace the whole text as a single undoable edit, keeping the caret
+    /// as close to where it was as the new text allows
+    pub fn replace_text(&mut self, new_text: &str) {
+        let old_text = self.buffer.as_str();
+        let Some((range, replacement)) = diff_region(&old_text, new_text) else {
+            return;
+        };
+        let clamp = |pos: usize| floor_char_boundary(new_text, pos);
+        let selection = (clamp(self.anchor), clamp(self.cursor));
+        self.record_edit(range, replacement, selection, false);
+        self.set_selection(selection.0, selection.1);
+    }
+
+    /// Word immediately before the caret that could be a snippet trigger
+    pub fn snippet_trigger(&self) -> Option<(Range<usize>, String)> {
+        if self.selection().is_some() {
+            return None;
+        }
+        let text = self.buffer.substring(0, self.cursor);
+        let start = text
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphanumeric() || *c == '_' || *c == '-')
+            .last()
+            .map(|(i, _)| i)?;
+        Some((start..self.cursor, text[start..].to_string()))
+    }
+
+    /// Replace `range` (the trigger word) with a snippet as one undoable edit
+    /// and select its first tab stop
+    pub fn expand_snippet(&mut self, range: Range<usize>, snippet: &ExpandedSnippet) {
+        let start = range.start;
+        self.replace_range(range, &snippet.text);
+        let session = SnippetSession::new(start, snippet);
+        let stop = session.current_stop();
+        self.set_selection(stop.start, stop.end);
+        self.snippet = (!session.is_last()).then_some(session);
+    }
+
+    pub fn in_snippet(&self) -> bool {
+        self.snippet.is_some()
+    }
+
+    /// Select the next (or previous) tab stop, leaving snippet mode at `$0`
+    pub fn jump_tab_stop(&mut self, backwards: bool) {
+        let Some(session) = self.snippet.as_mut() else {
+            return;
+        };
+        let stop = if backwards {
+            session.previous_stop()
+        } else {
+            session.next_stop()
+        };
+        if let Some(stop) = stop {
+            let is_last = session.is_last();
+            self.set_selection(stop.start, stop.end);
+            if is_last {
+                self.snippet = None;
+            }
+        }
+    }
+
+    /// Leave snippet mode, keeping the text as it is
+    pub fn exit_snippet(&mut self) {
+        self.snippet = None;
+    }
+
+    fn record_edit(&mut self, range: Range<usize>, text: &str, cursor_after: (usize, usize), coalesce: bool) {
+        if let Some(session) = self.snippet.as_mut() {
+            session.adjust(range.start, range.len(), text.len());
+        }
+
+        let edit = Edit {
+            old_text: self.buffer.substring(range.start, range.end),
+            new_text: text.to_string(),
+            position: range.start,
+            cursor_before: (self.anchor, self.cursor),
+            cursor_after,
+        };
+        self.shift_bookmarks(edit.position, &edit.old_text, &edit.new_text);
+        self.buffer.replace_range(range, text);
+        self.mark_changed();
+        if coalesce {
+            self.history.push_coalescing(edit);
+        } else {
+            self.history.push(edit);
+        }
+    }
+
     pub fn is_dirty(&self) -> bool {
         self.dirty
     }
 
-    pub fn open_file(&mut self, path: &Path) -> Result<(), std::io::Error> {
-        let content = std::fs::read_to_string(path)?;
+    /// Show `content`, as just read from disk
+    pub fn open_text(&mut self, content: String) {
         self.set_text(content);
         self.dirty = false;
-        Ok(())
     }
 
-    pub fn save_file(&mut self, path: &Path) -> Result<(), std::io::Error> {
-        std::fs::write(path, self.buffer.as_str())?;
+    /// Write the buffer to `path` without ever leaving it half-written
+    pub fn save_file(&mut self, path: &Path) -> Result<(), error::EditorError> {
+        let _span = timing::span(Phase::Save);
+        let text = self.buffer.as_str();
+        save::write_atomic(path, text.as_bytes())?;
+        self.saved_text = text;
         self.dirty = false;
         Ok(())
     }
 
+    /// Bytes held for the text: the buffer and the copy as last saved
+    pub fn allocated_bytes(&self) -> usize {
+        self.buffer.allocated_bytes() + self.saved_text.capacity()
+    }
+
+    /// Text as last opened or saved
+    pub fn saved_text(&self) -> &str {
+        &self.saved_text
+    }
+
+    /// Take the current text as the saved one, e.g. after reloading it
+    pub fn mark_saved(&mut self) {
+        self.saved_text = self.buffer.as_str();
+        self.dirty = false;
+    }
+
     pub fn undo(&mut self) {
         if let Some(edit) = self.history.undo() {
+            self.shift_bookmarks(edit.position, &edit.new_text, &edit.old_text);
             self.buffer.replace_range(
                 edit.position..edit.position + edit.new_text.len(),
                 &edit.old_text,
             );
-            self.cursor_position = edit.cursor_before;
-            self.dirty = true;
+            self.mark_changed();
+            self.set_selection(edit.cursor_before.0, edit.cursor_before.1);
         }
     }
 
     pub fn redo(&mut self) {
         if let Some(edit) = self.history.redo() {
+            self.shift_bookmarks(edit.position, &edit.old_text, &edit.new_text);
             self.buffer.replace_range(
                 edit.position..edit.position + edit.old_text.len(),
                 &edit.new_text,
             );
-            self.cursor_position = edit.cursor_after;
-            self.dirty = true;
+            self.mark_changed();
+            self.set_selection(edit.cursor_after.0, edit.cursor_after.1);
         }
     }
 
@@ -115,19 +464,65 @@ impl Editor {
         !self.history.redo_stack.is_empty()
     }
 
+    /// Insert text at the caret, replacing the selection
     pub fn insert_text(&mut self, text: &str) {
-        let cursor_byte = 0; // Simplified
-        self.buffer.insert(cursor_byte, text);
-        self.dirty = true;
+        self.replace_range(self.selection_range(), text);
     }
 
+    /// Delete the selection, or the character before the caret
     pub fn backspace(&mut self) {
-        let cursor_byte = 0; // Simplified
-        if cursor_byte > 0 {
-            self.buffer.delete_range(cursor_byte - 1..cursor_byte);
-            self.dirty = true;
+        if let Some(range) = self.selection() {
+            self.replace_range(range, "");
+        } else if self.cursor > 0 {
+            let text = self.buffer.as_str();
+            let prev = text[..self.cursor].chars().next_back().map_or(0, char::len_utf8);
+            self.replace_range(self.cursor - prev..self.cursor, "");
+        }
+    }
+}
+
+/// Largest char boundary of `text` at or before `pos`
+fn floor_char_boundary(text: &str, pos: usize) -> usize {
+    let mut pos = pos.min(text.len());
+    while !text.is_char_boundary(pos) {
+        pos -= 1;
+    }
+    pos
+}
+
+/// Start of the line `pos` is on
+fn line_start(text: &str, pos: usize) -> usize {
+    text[..pos].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// End of the line `pos` is on, before its line break
+fn line_end(text: &str, pos: usize) -> usize {
+    text[pos..].find('\n').map_or(text.len(), |i| pos + i)
+}
+
+/// Find the smallest byte range of `old` that must be replaced to produce `new`
+pub(crate) fn diff_region<'a>(old: &str, new: &'a str) -> Option<(Range<usize>, &'a str)> {
+    if old == new {
+        return None;
+    }
+
+    let prefix = old
+        .char_indices()
+        .zip(new.chars())
+        .find(|((_, a), b)| a != b)
+        .map_or(old.len().min(new.len()), |((i, _), _)| i);
+
+    // Zipping the reversed remainders stops at the shorter one, so the
+    // suffix never overlaps the prefix
+    let mut suffix = 0;
+    for (a, b) in old[prefix..].chars().rev().zip(new[prefix..].chars().rev()) {
+        if a != b {
+            break;
         }
+        suffix += a.len_utf8();
     }
+
+    Some((prefix..old.len() - suffix, &new[prefix..new.len() - suffix]))
 }
 
 impl EditHistory {
@@ -147,6 +542,34 @@ impl EditHistory {
         self.redo_stack.clear();
     }
 
+    /// Push an edit, merging runs of typed characters or backspaces into one entry
+    fn push_coalescing(&mut self, edit: Edit) {
+        if let Some(last) = self.undo_stack.last_mut() {
+            let typing = last.old_text.is_empty()
+                && edit.old_text.is_empty()
+                && last.position + last.new_text.len() == edit.position
+                && !edit.new_text.contains('\n');
+            let deleting = last.new_text.is_empty()
+                && edit.new_text.is_empty()
+                && edit.position + edit.old_text.len() == last.position;
+
+            if typing {
+                last.new_text.push_str(&edit.new_text);
+                last.cursor_after = edit.cursor_after;
+                self.redo_stack.clear();
+                return;
+            }
+            if deleting {
+                last.old_text.insert_str(0, &edit.old_text);
+                last.position = edit.position;
+                last.cursor_after = edit.cursor_after;
+                self.redo_stack.clear();
+                return;
+            }
+        }
+        self.push(edit);
+    }
+
     fn undo(&mut self) -> Option<Edit> {
         if let Some(edit) = self.undo_stack.pop() {
             self.redo_stack.push(edit.clone());
@@ -176,3 +599,12 @@ impl Default for Editor {
         Self::new()
     }
 }
+
+/// An editor holding `text` with the selection from `anchor` to `cursor`
+#[cfg(test)]
+pub(crate) fn test_editor(text: &str, anchor: usize, cursor: usize) -> Editor {
+    let mut editor = Editor::new();
+    editor.set_text(text);
+    editor.set_selection(anchor, cursor);
+    editor
+}
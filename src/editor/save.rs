@@ -0,0 +1,164 @@
+//! Writing a document so a crash or a full disk mid-save never leaves a
+//! truncated file: the text goes to a temporary file next to it, which
+//! then replaces the original in one rename
+
+use super::error::{EditorError, Operation};
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// The error for writing `path` failing with `error`
+fn save_error(path: &Path, error: io::Error) -> EditorError {
+    EditorError::from_io(Operation::Save, path, error)
+}
+
+/// Replace the contents of `path` with `contents`. A symlink is followed,
+/// so the file it points at is written and the link stays. The file keeps
+/// its permissions; a read-only file is refused.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), EditorError> {
+    write_atomic_with(path, contents, |file, contents| file.write_all(contents))
+}
+
+/// `write_atomic`, with `write` filling the temporary file
+fn write_atomic_with(
+    path: &Path,
+    contents: &[u8],
+    write: impl FnOnce(&mut File, &[u8]) -> io::Result<()>,
+) -> Result<(), EditorError> {
+    let path = match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_symlink() => fs::canonicalize(path).map_err(|e| save_error(path, e))?,
+        _ => path.to_path_buf(),
+    };
+    let existing = match fs::metadata(&path) {
+        Ok(meta) => Some(meta),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(save_error(&path, e)),
+    };
+    if existing.as_ref().is_some_and(|meta| meta.permissions().readonly()) {
+        return Err(EditorError::ReadOnly { path, operation: Operation::Save });
+    }
+
+    let temp = temp_path(&path);
+    let mut file = match File::options().write(true).create_new(true).open(&temp) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+            // Some network shares let the file be written but not a new one
+            // created beside it
+            log::warn!("Writing {} in place, no temporary file: {}", path.display(), e);
+            return write_in_place(&path, contents, write);
+        }
+        Err(e) => return Err(save_error(&path, e)),
+    };
+    let written = write(&mut file, contents)
+        .and_then(|()| file.sync_all())
+        .and_then(|()| match &existing {
+            Some(meta) => fs::set_permissions(&temp, meta.permissions()),
+            None => Ok(()),
+        });
+    drop(file);
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(save_error(&path, e));
+    }
+    if let Err(e) = fs::rename(&temp, &path) {
+        let _ = fs::remove_file(&temp);
+        log::warn!("Writing {} in place, the temporary file can't replace it: {}", path.display(), e);
+        return write_in_place(&path, contents, |file, contents| file.write_all(contents));
+    }
+    sync_parent(&path);
+    Ok(())
+}
+
+/// Overwrite `path` directly, for when a temporary file can't be used
+fn write_in_place(
+    path: &Path,
+    contents: &[u8],
+    write: impl FnOnce(&mut File, &[u8]) -> io::Result<()>,
+) -> Result<(), EditorError> {
+    let mut file = File::create(path).map_err(|e| save_error(path, e))?;
+    write(&mut file, contents)
+        .and_then(|()| file.sync_all())
+        .map_err(|e| save_error(path, e))
+}
+
+/// A hidden name beside `path` for the new contents
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.rmd-save-{}", name, std::process::id()))
+}
+
+/// Make the rename itself durable; only possible, and needed, on Unix
+fn sync_parent(path: &Path) {
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().and_then(|dir| File::open(dir).ok()) {
+        let _ = dir.sync_all();
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = test_dir("save-write");
+        let path = dir.join("notes.md");
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        // A failure halfway leaves the old contents and no temporary file
+        let failed = write_atomic_with(&path, b"third", |file, contents| {
+            file.write_all(&contents[..2])?;
+            Err(io::Error::new(ErrorKind::StorageFull, "injected"))
+        });
+        assert!(matches!(failed, Err(EditorError::DiskFull { .. })));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_read_only_is_refused() {
+        let dir = test_dir("save-readonly");
+        let path = dir.join("locked.md");
+        fs::write(&path, "kept").unwrap();
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions.clone()).unwrap();
+
+        let error = write_atomic(&path, b"lost").unwrap_err();
+        assert!(matches!(error, EditorError::ReadOnly { .. }));
+        assert_eq!(error.to_string(), format!("{} is read-only", path.display()));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "kept");
+
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        fs::set_permissions(&path, permissions).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_and_permissions_are_kept() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = test_dir("save-symlink");
+        let target = dir.join("target.md");
+        let link = dir.join("link.md");
+        fs::write(&target, "old").unwrap();
+        fs::set_permissions(&target, fs::Permissions::from_mode(0o640)).unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_atomic(&link, b"new").unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+        assert_eq!(fs::metadata(&target).unwrap().permissions().mode() & 0o777, 0o640);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,76 @@
+//! Text matching shared by in-document and workspace search
+
+use regex::{Regex, RegexBuilder};
+use std::ops::Range;
+
+/// How a search query is interpreted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+    /// Treat the query as a regular expression instead of literal text
+    pub regex: bool,
+}
+
+/// A compiled search query
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    regex: Regex,
+}
+
+impl Matcher {
+    /// Compile `query`; fails only for an invalid regular expression
+    pub fn new(query: &str, options: SearchOptions) -> Result<Self, regex::Error> {
+        let mut pattern = if options.regex {
+            query.to_string()
+        } else {
+            regex::escape(query)
+        };
+        if options.whole_word {
+            pattern = format!(r"\b(?:{})\b", pattern);
+        }
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(!options.case_sensitive)
+            .multi_line(true)
+            .build()?;
+        Ok(Self { regex })
+    }
+
+    /// Byte ranges of all non-empty matches in `text`
+    pub fn find_all(&self, text: &str) -> Vec<Range<usize>> {
+        self.regex
+            .find_iter(text)
+            .filter(|m| !m.is_empty())
+            .map(|m| m.range())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(query: &str, options: SearchOptions, text: &str) -> Vec<Range<usize>> {
+        Matcher::new(query, options).unwrap().find_all(text)
+    }
+
+    #[test]
+    fn test_matcher_options() {
+        let text = "Rust rusty rust. (rust)";
+        let literal = SearchOptions::default();
+        assert_eq!(find("rust", literal, text).len(), 4);
+
+        let case = SearchOptions { case_sensitive: true, ..literal };
+        assert_eq!(find("Rust", case, text), vec![0..4]);
+
+        let word = SearchOptions { whole_word: true, ..literal };
+        assert_eq!(find("rust", word, text), vec![0..4, 11..15, 18..22]);
+
+        // Literal queries escape regex syntax
+        assert_eq!(find("(rust)", literal, text), vec![17..23]);
+
+        let regex = SearchOptions { regex: true, ..literal };
+        assert_eq!(find(r"rust\w+", regex, text), vec![5..10]);
+        assert!(Matcher::new("(", regex).is_err());
+    }
+}
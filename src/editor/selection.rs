@@ -0,0 +1,181 @@
+//! Selection commands: selecting the word or line at the caret, jumping to
+//! the next occurrence, and growing the selection word → line → paragraph
+//! → section
+
+use super::words::word_at;
+use super::{line_end, line_start, Editor};
+use std::ops::Range;
+
+impl Editor {
+    /// With nothing selected, select the word under the caret. With a
+    /// selection, move it to the next occurrence of the selected text,
+    /// wrapping around at the end. Returns whether the selection changed.
+    pub fn select_word_or_next(&mut self) -> bool {
+        let text = self.buffer.as_str();
+        let target = match self.selection() {
+            None => word_at(&text, self.cursor),
+            Some(range) => {
+                let needle = &text[range.clone()];
+                text[range.end..]
+                    .find(needle)
+                    .map(|i| range.end + i)
+                    .or_else(|| text.find(needle))
+                    .map(|start| start..start + needle.len())
+                    .filter(|next| *next != range)
+            }
+        };
+        match target {
+            Some(range) => {
+                self.set_selection(range.start, range.end);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Select the lines the selection touches, line breaks included. When
+    /// whole lines are selected already, take in the next one as well.
+    /// Returns whether the selection changed.
+    pub fn select_line(&mut self) -> bool {
+        let text = self.buffer.as_str();
+        let range = self.selection_range();
+        let at_line_start = |pos: usize| pos == 0 || text[..pos].ends_with('\n');
+        let whole_lines = !range.is_empty() && at_line_start(range.start) && at_line_start(range.end);
+
+        let start = line_start(&text, range.start);
+        if whole_lines && range.end == text.len() {
+            return false;
+        }
+        // Where the last line to select starts from: past a selection
+        // of whole lines, otherwise its last character
+        let last_line_from = match text[..range.end].char_indices().next_back() {
+            Some((last_char, _)) if !whole_lines && !range.is_empty() => last_char,
+            _ => range.end,
+        };
+        let end = text[last_line_from..].find('\n').map_or(text.len(), |i| last_line_from + i + 1);
+        if (start..end) == range {
+            return false;
+        }
+        self.set_selection(start, end);
+        true
+    }
+
+    /// Grow the selection to the smallest word, line, paragraph, or section
+    /// around it, and finally the whole document. Returns whether it grew.
+    pub fn expand_selection(&mut self) -> bool {
+        let text = self.buffer.as_str();
+        let range = self.selection_range();
+        let headings: Vec<(usize, u8)> = self.headings().iter().map(|h| (h.offset, h.level)).collect();
+
+        let mut candidates = vec![line_at(&text, &range), paragraph_at(&text, &range), 0..text.len()];
+        candidates.extend(word_at(&text, range.start));
+        candidates.extend(sections(&text, &headings));
+        let grown = candidates
+            .into_iter()
+            .filter(|c| c.start <= range.start && c.end >= range.end && c.len() > range.len())
+            .min_by_key(|c| c.len());
+        match grown {
+            Some(grown) => {
+                self.set_selection(grown.start, grown.end);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// The lines `range` touches, without the final line break
+fn line_at(text: &str, range: &Range<usize>) -> Range<usize> {
+    line_start(text, range.start)..line_end(text, range.end)
+}
+
+/// The run of non-blank lines around `range`, without the final line break
+fn paragraph_at(text: &str, range: &Range<usize>) -> Range<usize> {
+    let lines = line_at(text, range);
+    let mut start = lines.start;
+    while start > 0 {
+        let previous = line_start(text, start - 1);
+        if text[previous..start - 1].trim().is_empty() {
+            break;
+        }
+        start = previous;
+    }
+    let mut end = lines.end;
+    while end < text.len() {
+        let next = line_end(text, end + 1);
+        if text[end + 1..next].trim().is_empty() {
+            break;
+        }
+        end = next;
+    }
+    start..end
+}
+
+/// Each heading's section: from the heading up to the next one of the
+/// same or a higher level, trailing blank lines left out
+fn sections(text: &str, headings: &[(usize, u8)]) -> Vec<Range<usize>> {
+    headings
+        .iter()
+        .enumerate()
+        .map(|(i, &(offset, level))| {
+            let end = headings[i + 1..]
+                .iter()
+                .find(|&&(_, next_level)| next_level <= level)
+                .map_or(text.len(), |&(next_offset, _)| next_offset);
+            offset..text[..end].trim_end().len().max(offset)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::test_editor;
+
+    #[test]
+    fn test_select_word_or_next() {
+        let text = "naïve café, naïve";
+        let mut ed = test_editor(text, "naï".len(), "naï".len());
+        assert!(ed.select_word_or_next());
+        assert_eq!(ed.selection(), Some(0.."naïve".len()));
+        assert!(ed.select_word_or_next());
+        let second = text.rfind("naïve").unwrap();
+        assert_eq!(ed.selection(), Some(second..text.len()));
+        // Wraps back to the first
+        assert!(ed.select_word_or_next());
+        assert_eq!(ed.selection(), Some(0.."naïve".len()));
+
+        let mut ed = test_editor("a  b", 2, 2);
+        assert!(!ed.select_word_or_next());
+    }
+
+    #[test]
+    fn test_select_line() {
+        let mut ed = test_editor("one\ntwo\nthree", 5, 5);
+        assert!(ed.select_line());
+        assert_eq!(ed.selection(), Some(4..8));
+        assert!(ed.select_line());
+        assert_eq!(ed.selection(), Some(4..13));
+        assert!(!ed.select_line());
+    }
+
+    #[test]
+    fn test_expand_selection() {
+        let text = "# Intro\n\nFirst wörd here\nsecond line\n\n## Sub\n\nbody\n\n# Next\n";
+        let caret = text.find("rd here").unwrap();
+        let mut ed = test_editor(text, caret, caret);
+        let mut steps = Vec::new();
+        while ed.expand_selection() {
+            steps.push(&text[ed.selection_range()]);
+        }
+        assert_eq!(
+            steps,
+            [
+                "wörd",
+                "First wörd here",
+                "First wörd here\nsecond line",
+                "# Intro\n\nFirst wörd here\nsecond line\n\n## Sub\n\nbody",
+                text,
+            ]
+        );
+    }
+}
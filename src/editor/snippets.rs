@@ -0,0 +1,305 @@
+//! User-definable snippets with `$1`, `${2:default}`, `$0` tab stops
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::Path;
+
+/// A snippet: typing `trigger` and pressing Tab inserts `body`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snippet {
+    pub trigger: String,
+    pub body: String,
+    #[serde(default)]
+    pub description: String,
+}
+
+/// Layout of `snippets.toml`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SnippetFile {
+    #[serde(default, rename = "snippet")]
+    snippets: Vec<Snippet>,
+}
+
+/// A snippet body with placeholders resolved
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpandedSnippet {
+    /// Text to insert, with placeholder syntax removed
+    pub text: String,
+    /// Tab stop ranges within `text`, in visiting order, ending with `$0`
+    pub stops: Vec<Range<usize>>,
+}
+
+/// All snippets available to the editor, keyed by trigger
+pub struct SnippetLibrary {
+    snippets: BTreeMap<String, Snippet>,
+}
+
+impl SnippetLibrary {
+    /// Built-in snippets only
+    pub fn new() -> Self {
+        let snippets = builtin_snippets()
+            .into_iter()
+            .map(|s| (s.trigger.clone(), s))
+            .collect();
+        Self { snippets }
+    }
+
+    /// Built-in snippets overridden by the user's `snippets.toml`
+    pub fn load_or_default() -> Self {
+        let mut library = Self::new();
+        match crate::config::Config::config_dir() {
+            Ok(dir) => {
+                if let Err(e) = library.merge_file(&dir.join("snippets.toml")) {
+                    log::warn!("Failed to load snippets: {}", e);
+                }
+            }
+            Err(e) => log::warn!("Failed to locate snippets: {}", e),
+        }
+        library
+    }
+
+    /// Add the snippets from a TOML file, replacing ones with the same trigger
+    pub fn merge_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let contents = std::fs::read_to_string(path)?;
+        let file: SnippetFile = toml::from_str(&contents)?;
+        for snippet in file.snippets {
+            self.snippets.insert(snippet.trigger.clone(), snippet);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, trigger: &str) -> Option<&Snippet> {
+        self.snippets.get(trigger)
+    }
+}
+
+impl Default for SnippetLibrary {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn builtin_snippets() -> Vec<Snippet> {
+    let snippet = |trigger: &str, body: &str, description: &str| Snippet {
+        trigger: trigger.to_string(),
+        body: body.to_string(),
+        description: description.to_string(),
+    };
+
+    vec![
+        snippet("code", "```${1:lang}\n$2\n```\n$0", "Fenced code block"),
+        snippet("link", "[${1:text}](${2:url})$0", "Link"),
+        snippet("img", "![${1:alt}](${2:path})$0", "Image"),
+        snippet(
+            "tbl",
+            "| ${1:Header} | ${2:Header} |\n| --- | --- |\n| $3 | $4 |\n| $5 | $6 |\n$0",
+            "2×2 table",
+        ),
+        snippet("fm", "---\ntitle: ${1:Title}\ndate: $2\ntags: [$3]\n---\n\n$0", "Front matter"),
+    ]
+}
+
+/// Resolve placeholders in a snippet body.
+///
+/// `$1`..`$9` and `${1:default}` are tab stops visited in numeric order, `$0`
+/// is the final caret position (end of the snippet if absent), and `\$` is a
+/// literal dollar sign.
+pub fn expand(body: &str) -> ExpandedSnippet {
+    let mut text = String::with_capacity(body.len());
+    let mut numbered: Vec<(usize, Range<usize>)> = Vec::new();
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'$') => {
+                chars.next();
+                text.push('$');
+            }
+            '$' => match chars.peek().copied() {
+                Some(d) if d.is_ascii_digit() => {
+                    chars.next();
+                    let index = d.to_digit(10).unwrap_or(0) as usize;
+                    numbered.push((index, text.len()..text.len()));
+                }
+                Some('{') => {
+                    let rest: String = chars.clone().collect();
+                    match parse_braced(&rest) {
+                        Some((index, default, consumed)) => {
+                            for _ in 0..consumed {
+                                chars.next();
+                            }
+                            let start = text.len();
+                            text.push_str(&default);
+                            numbered.push((index, start..text.len()));
+                        }
+                        None => text.push('$'),
+                    }
+                }
+                _ => text.push('$'),
+            },
+            _ => text.push(c),
+        }
+    }
+
+    // Visit 1, 2, ... in order, then $0 (or the end of the snippet)
+    numbered.sort_by_key(|(index, _)| if *index == 0 { usize::MAX } else { *index });
+    numbered.dedup_by_key(|(index, _)| *index);
+    let mut stops: Vec<Range<usize>> = numbered.iter().map(|(_, range)| range.clone()).collect();
+    if !numbered.iter().any(|(index, _)| *index == 0) {
+        stops.push(text.len()..text.len());
+    }
+
+    ExpandedSnippet { text, stops }
+}
+
+/// Parse `{1:default}` at the start of `rest`, returning the index, the
+/// default text, and the number of chars consumed
+fn parse_braced(rest: &str) -> Option<(usize, String, usize)> {
+    let inner = rest.strip_prefix('{')?;
+    let close = inner.find('}')?;
+    let inner = &inner[..close];
+    let (index, default) = match inner.split_once(':') {
+        Some((index, default)) => (index, default),
+        None => (inner, ""),
+    };
+    let index = index.parse().ok()?;
+    let consumed = rest[..close + 2].chars().count();
+    Some((index, default.replace("\\$", "$"), consumed))
+}
+
+/// An expanded snippet whose tab stops are still being visited
+#[derive(Debug, Clone)]
+pub struct SnippetSession {
+    /// Tab stops as absolute byte ranges in the document
+    stops: Vec<Range<usize>>,
+    current: usize,
+}
+
+impl SnippetSession {
+    /// Start a session for a snippet inserted at byte offset `start`
+    pub fn new(start: usize, snippet: &ExpandedSnippet) -> Self {
+        let stops = snippet
+            .stops
+            .iter()
+            .map(|range| start + range.start..start + range.end)
+            .collect();
+        Self { stops, current: 0 }
+    }
+
+    /// Range of the tab stop the caret is at
+    pub fn current_stop(&self) -> Range<usize> {
+        self.stops[self.current].clone()
+    }
+
+    /// Whether the current stop is the final one (`$0`)
+    pub fn is_last(&self) -> bool {
+        self.current + 1 >= self.stops.len()
+    }
+
+    /// Advance to the next stop, returning its range
+    pub fn next_stop(&mut self) -> Option<Range<usize>> {
+        if self.is_last() {
+            return None;
+        }
+        self.current += 1;
+        Some(self.current_stop())
+    }
+
+    /// Go back to the previous stop, returning its range
+    pub fn previous_stop(&mut self) -> Option<Range<usize>> {
+        self.current = self.current.checked_sub(1)?;
+        Some(self.current_stop())
+    }
+
+    /// Shift the stops to account for `old_len` bytes at `position` being
+    /// replaced by `new_len` bytes. Typing inside a stop grows that stop.
+    pub fn adjust(&mut self, position: usize, old_len: usize, new_len: usize) {
+        let edit_end = position + old_len;
+        let shift = |offset: usize| (offset + new_len).saturating_sub(old_len);
+
+        for stop in &mut self.stops {
+            if stop.start <= position && edit_end <= stop.end {
+                // Edit inside the stop
+                stop.end = shift(stop.end);
+            } else if edit_end <= stop.start {
+                // Edit before the stop
+                stop.start = shift(stop.start);
+                stop.end = shift(stop.end);
+            } else if position < stop.end {
+                // Edit overlapping the stop: keep the union of both
+                stop.start = stop.start.min(position);
+                stop.end = shift(stop.end.max(edit_end)).max(stop.start);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_simple_stops() {
+        let snippet = expand("[$1]($2)$0");
+        assert_eq!(snippet.text, "[]()");
+        assert_eq!(snippet.stops, vec![1..1, 3..3, 4..4]);
+    }
+
+    #[test]
+    fn test_expand_defaults_and_order() {
+        let snippet = expand("${2:b} ${1:a}");
+        assert_eq!(snippet.text, "b a");
+        // $1 first, $2 second, then the implicit final stop at the end
+        assert_eq!(snippet.stops, vec![2..3, 0..1, 3..3]);
+    }
+
+    #[test]
+    fn test_expand_escaped_dollar() {
+        let snippet = expand("costs \\$5 and ${1:\\$10}$0");
+        assert_eq!(snippet.text, "costs $5 and $10");
+        assert_eq!(snippet.stops, vec![13..16, 16..16]);
+    }
+
+    #[test]
+    fn test_expand_lone_dollar_is_literal() {
+        let snippet = expand("$ and ${x} and $");
+        assert_eq!(snippet.text, "$ and ${x} and $");
+        assert_eq!(snippet.stops, vec![16..16]);
+    }
+
+    #[test]
+    fn test_builtin_table() {
+        let library = SnippetLibrary::new();
+        let snippet = expand(&library.get("tbl").unwrap().body);
+        assert!(snippet.text.starts_with("| Header | Header |\n| --- | --- |"));
+        assert_eq!(&snippet.text[snippet.stops[0].clone()], "Header");
+        assert_eq!(snippet.stops.len(), 7);
+    }
+
+    #[test]
+    fn test_session_tracks_typing() {
+        // "[](url)" with stops at 1..1 and 3..6, inserted at offset 10
+        let snippet = ExpandedSnippet {
+            text: "[](url)".to_string(),
+            stops: vec![1..1, 3..6, 7..7],
+        };
+        let mut session = SnippetSession::new(10, &snippet);
+        assert_eq!(session.current_stop(), 11..11);
+
+        // Type "docs" in the first stop
+        session.adjust(11, 0, 4);
+        assert_eq!(session.current_stop(), 11..15);
+        assert_eq!(session.next_stop(), Some(17..20));
+
+        // Replace the "url" default with a longer one
+        session.adjust(17, 3, 10);
+        assert_eq!(session.current_stop(), 17..27);
+        assert_eq!(session.next_stop(), Some(28..28));
+        assert!(session.is_last());
+        assert_eq!(session.next_stop(), None);
+    }
+}
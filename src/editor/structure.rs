@@ -0,0 +1,478 @@
+//! Structural editing: moving, duplicating and deleting lines, shifting heading
+//! levels, toggling inline emphasis markers, text transforms, reflowing
+//! paragraphs, and renumbering lists
+
+use super::Editor;
+use super::text_buffer::TextBuffer;
+use crate::markdown::{lists, wrap};
+use std::ops::Range;
+
+impl Editor {
+    /// Move the selected lines (or the caret line) up or down past their
+    /// neighbour. Returns whether anything moved.
+    pub fn move_lines(&mut self, up: bool) -> bool {
+        let lines = self.selected_lines();
+        if (up && lines.start == 0) || (!up && lines.end >= self.buffer.line_count()) {
+            return false;
+        }
+
+        let block = self.lines_span(lines.clone());
+        let block_text = self.buffer.substring(block.start, block.end);
+        let (span, replacement, shift) = if up {
+            let above = self.buffer.line_range(lines.start - 1);
+            let above_text = self.buffer.substring(above.start, above.end);
+            let shift = -(above_text.len() as isize + 1);
+            (above.start..block.end, format!("{}\n{}", block_text, above_text), shift)
+        } else {
+            let below = self.buffer.line_range(lines.end);
+            let below_text = self.buffer.substring(below.start, below.end);
+            let shift = below_text.len() as isize + 1;
+            (block.start..below.end, format!("{}\n{}", below_text, block_text), shift)
+        };
+
+        let moved = |pos: usize| pos.saturating_add_signed(shift);
+        let selection = (moved(self.anchor), moved(self.cursor));
+        self.replace_keeping_selection(span, &replacement, selection);
+        true
+    }
+
+    /// Duplicate the selected lines (or the caret line). The selection stays
+    /// on the upper copy when `above`, and follows the lower copy otherwise.
+    pub fn duplicate_lines(&mut self, above: bool) {
+        let lines = self.selected_lines();
+        let block = self.lines_span(lines);
+        let text = self.buffer.substring(block.start, block.end);
+        let shift = if above { 0 } else { text.len() + 1 };
+        let selection = (self.anchor + shift, self.cursor + shift);
+        self.replace_keeping_selection(block.end..block.end, &format!("\n{}", text), selection);
+    }
+
+    /// Delete the selected lines (or the caret line) with their line
+    /// breaks. The caret keeps its column on the line that takes their
+    /// place, as far as that line reaches. Returns whether anything was deleted.
+    pub fn delete_lines(&mut self) -> bool {
+        if self.buffer.is_empty() {
+            return false;
+        }
+        let lines = self.selected_lines();
+        let (_, col) = self.buffer.line_col_from_byte_index(self.cursor);
+        let column = self.buffer.substring(self.cursor - col, self.cursor).chars().count();
+
+        let span = self.lines_span(lines.clone());
+        let (range, next) = if lines.end < self.buffer.line_count() {
+            // The line break after the last line goes too
+            (span.start..span.end + 1, Some(lines.end))
+        } else if lines.start > 0 {
+            // The last line has no break after it; take the one before
+            (span.start - 1..span.end, Some(lines.start - 1))
+        } else {
+            (span, None)
+        };
+
+        let caret = match next {
+            Some(next) => {
+                let next_line = self.buffer.line_range(next);
+                let next_text = self.buffer.substring(next_line.start, next_line.end);
+                let offset: usize = next_text.chars().take(column).map(char::len_utf8).sum();
+                let start = if next_line.start >= range.end { next_line.start - range.len() } else { next_line.start };
+                start + offset
+            }
+            None => 0,
+        };
+        self.replace_keeping_selection(range, "", (caret, caret));
+        true
+    }
+
+    /// Add (`deeper`) or remove one `#` on every ATX heading in the selected
+    /// lines, or on the heading under the caret, keeping levels within 1–6.
+    /// Returns whether any heading changed.
+    pub fn shift_headings(&mut self, deeper: bool) -> bool {
+        let lines = self.selected_lines();
+        let offsets: Vec<usize> = self.headings().iter().map(|heading| heading.offset).collect();
+        let heading_lines: Vec<usize> = offsets
+            .into_iter()
+            .map(|offset| self.buffer.line_col_from_byte_index(offset).0)
+            .filter(|line| lines.contains(line))
+            .collect();
+
+        // (byte offset, bytes inserted (+1) or removed (-1)) for each heading
+        let mut changes: Vec<(usize, isize)> = Vec::new();
+        for line in heading_lines {
+            let range = self.buffer.line_range(line);
+            let text = self.buffer.substring(range.start, range.end);
+            let Some((indent, level)) = atx_level(&text) else {
+                continue;
+            };
+            match (deeper, level) {
+                (true, 1..=5) => changes.push((range.start + indent, 1)),
+                (false, 2..=6) => changes.push((range.start + indent, -1)),
+                _ => {}
+            }
+        }
+        if changes.is_empty() {
+            return false;
+        }
+
+        let span = self.lines_span(lines);
+        let mut replacement = self.buffer.substring(span.start, span.end);
+        for &(at, change) in changes.iter().rev() {
+            let at = at - span.start;
+            if change > 0 {
+                replacement.insert(at, '#');
+            } else {
+                replacement.remove(at);
+            }
+        }
+
+        let shifted = |pos: usize| {
+            let mut new = pos as isize;
+            for &(at, change) in &changes {
+                if change > 0 && at < pos {
+                    new += change;
+                } else if change < 0 && pos > at {
+                    new -= 1;
+                }
+            }
+            new as usize
+        };
+        let selection = (shifted(self.anchor), shifted(self.cursor));
+        self.replace_keeping_selection(span, &replacement, selection);
+        true
+    }
+
+    /// Wrap the selection in `marker` (`**` for bold, `*` for italic), or
+    /// unwrap it when it is already wrapped. With nothing selected the caret
+    /// ends up between the two markers.
+    pub fn toggle_inline_marker(&mut self, marker: &str) {
+        let range = self.selection_range();
+        let len = marker.len();
+        let text = self.buffer.as_str();
+        let selected = &text[range.clone()];
+
+        let wrapped_outside = text[..range.start].ends_with(marker) && text[range.end..].starts_with(marker);
+        let wrapped_inside = selected.len() >= 2 * len && selected.starts_with(marker) && selected.ends_with(marker);
+
+        if wrapped_outside {
+            let selection = (self.anchor - len, self.cursor - len);
+            self.replace_keeping_selection(range.start - len..range.end + len, selected, selection);
+        } else if wrapped_inside {
+            let inner = &selected[len..selected.len() - len];
+            let shrink = |pos: usize| if pos == range.end { pos - 2 * len } else { pos };
+            let selection = (shrink(self.anchor), shrink(self.cursor));
+            self.replace_keeping_selection(range.clone(), inner, selection);
+        } else {
+            let wrapped = format!("{}{}{}", marker, selected, marker);
+            let selection = (self.anchor + len, self.cursor + len);
+            self.replace_keeping_selection(range, &wrapped, selection);
+        }
+    }
+
+    /// Replace the selection (or the caret line when nothing is selected)
+    /// with `transform` of it as one undoable edit, selecting the result.
+    /// `whole_lines` widens a selection to the lines it touches. Returns
+    /// whether the text changed.
+    pub fn transform_selection(&mut self, transform: fn(&str) -> String, whole_lines: bool) -> bool {
+        let range = match self.selection() {
+            Some(range) if !whole_lines => range,
+            _ => {
+                let lines = self.selected_lines();
+                self.lines_span(lines)
+            }
+        };
+        let text = self.buffer.substring(range.start, range.end);
+        let replacement = transform(&text);
+        if replacement == text {
+            return false;
+        }
+        let selection = (range.start, range.start + replacement.len());
+        self.replace_keeping_selection(range, &replacement, selection);
+        true
+    }
+
+    /// Hard-wrap the paragraphs on the selected lines at `column`, or join
+    /// each onto one line when `column` is `None`, as one undoable edit.
+    /// Returns whether the text changed.
+    pub fn reflow_selection(&mut self, column: Option<usize>) -> bool {
+        let lines = self.selected_lines();
+        let span = self.lines_span(lines.clone());
+        let replacement = wrap::reflow(&self.buffer.as_str(), lines, column);
+        if replacement == self.buffer.substring(span.start, span.end) {
+            return false;
+        }
+        let selection = (span.start, span.start + replacement.len());
+        self.replace_keeping_selection(span, &replacement, selection);
+        true
+    }
+
+    /// Renumber every ordered list of the document as one undoable edit.
+    /// Returns whether any number changed.
+    pub fn renumber_lists(&mut self) -> bool {
+        let text = self.buffer.as_str();
+        let Some((range, replacement)) = lists::combine(&text, &lists::renumber(&text)) else {
+            return false;
+        };
+        self.replace_range_in_place(range, &replacement);
+        true
+    }
+
+    /// Renumber the lists the last edit touched, at `touched`, as part of
+    /// that edit so one undo takes back both. Returns whether any number
+    /// changed.
+    pub fn renumber_lists_around(&mut self, touched: Range<usize>) -> bool {
+        let text = self.buffer.as_str();
+        let Some((range, replacement)) = lists::combine(&text, &lists::renumber_around(&text, touched)) else {
+            return false;
+        };
+        self.amend_last_edit(range, &replacement);
+        true
+    }
+
+    /// Select whole lines from `anchor_line` to `cursor_line`, taking in
+    /// the last line's terminator so line commands act on exactly these
+    /// lines. The caret ends on the side of `cursor_line`.
+    pub fn select_lines(&mut self, anchor_line: usize, cursor_line: usize) {
+        let start = |buffer: &mut TextBuffer, line| buffer.byte_index_from_line_col(line, 0);
+        let (anchor, cursor) = if cursor_line >= anchor_line {
+            (start(&mut self.buffer, anchor_line), start(&mut self.buffer, cursor_line + 1))
+        } else {
+            (start(&mut self.buffer, anchor_line + 1), start(&mut self.buffer, cursor_line))
+        };
+        self.set_selection(anchor, cursor);
+    }
+
+    /// Line the selection is anchored on. An anchor at the very start of a
+    /// line, after the caret, belongs to the line before, as in
+    /// `selected_lines`.
+    pub fn anchor_line(&mut self) -> usize {
+        let (line, col) = self.buffer.line_col_from_byte_index(self.anchor);
+        if col == 0 && line > 0 && self.anchor > self.cursor {
+            line - 1
+        } else {
+            line
+        }
+    }
+
+    /// Lines touched by the selection. A selection that ends at the very
+    /// start of a line doesn't include that line.
+    pub(super) fn selected_lines(&mut self) -> Range<usize> {
+        let range = self.selection_range();
+        let (first, _) = self.buffer.line_col_from_byte_index(range.start);
+        let (mut last, col) = self.buffer.line_col_from_byte_index(range.end);
+        if col == 0 && last > first {
+            last -= 1;
+        }
+        first..last + 1
+    }
+
+    /// Byte range from the start of the first line to the end of the last,
+    /// excluding the last line's terminator
+    pub(super) fn lines_span(&mut self, lines: Range<usize>) -> Range<usize> {
+        self.buffer.line_range(lines.start).start..self.buffer.line_range(lines.end - 1).end
+    }
+
+    /// Replace `range` as one undoable edit and select `selection` afterwards
+    pub(super) fn replace_keeping_selection(&mut self, range: Range<usize>, text: &str, selection: (usize, usize)) {
+        self.record_edit(range, text, selection, false);
+        self.set_selection(selection.0, selection.1);
+    }
+}
+
+/// Indentation and level of an ATX heading line such as `  ## Title`
+fn atx_level(line: &str) -> Option<(usize, usize)> {
+    let content = line.trim_start_matches(' ');
+    let indent = line.len() - content.len();
+    let level = content.chars().take_while(|&c| c == '#').count();
+    let rest = &content[level..];
+    let valid = indent <= 3
+        && (1..=6).contains(&level)
+        && (rest.is_empty() || rest.starts_with([' ', '\t']));
+    valid.then_some((indent, level))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::editor::test_editor;
+
+    #[test]
+    fn test_transform_selection() {
+        let upper: fn(&str) -> String = |text| text.to_uppercase();
+        let sort: fn(&str) -> String = |text| {
+            let mut lines: Vec<&str> = text.split('\n').collect();
+            lines.sort();
+            lines.join("\n")
+        };
+
+        // Nothing selected: the caret line
+        let mut e = test_editor("one\ntwo\n", 5, 5);
+        assert!(e.transform_selection(upper, false));
+        assert_eq!(e.text(), "one\nTWO\n");
+        assert_eq!(e.selection(), Some(4..7));
+
+        // Whole lines touched by a partial selection, as one undo entry
+        let mut e = test_editor("c\nb\na\n", 1, 5);
+        assert!(e.transform_selection(sort, true));
+        assert_eq!(e.text(), "a\nb\nc\n");
+        e.undo();
+        assert_eq!(e.text(), "c\nb\na\n");
+
+        let mut e = test_editor("ABC", 0, 3);
+        assert!(!e.transform_selection(upper, false));
+    }
+
+    #[test]
+    fn test_select_lines() {
+        let mut e = test_editor("one
+two
+three
+four", 0, 0);
+        e.select_lines(1, 2);
+        assert_eq!(e.selection(), Some(4..14));
+        assert_eq!(e.selected_lines(), 1..3);
+        // Dragged upwards, the caret is at the top and the anchor line stays
+        e.select_lines(2, 0);
+        assert_eq!((e.anchor, e.cursor), (14, 0));
+        assert_eq!(e.anchor_line(), 2);
+        // The last line has no terminator to take
+        e.select_lines(3, 3);
+        assert_eq!(e.selection(), Some(14..18));
+        assert!(e.delete_lines());
+        assert_eq!(e.text(), "one
+two
+three");
+    }
+
+    #[test]
+    fn test_reflow_selection() {
+        let mut e = test_editor("# Title\n\none two three four\n", 12, 12);
+        assert!(e.reflow_selection(Some(9)));
+        assert_eq!(e.text(), "# Title\n\none two\nthree\nfour\n");
+        assert_eq!(e.selection(), Some(9..27));
+        assert!(e.reflow_selection(None));
+        assert_eq!(e.text(), "# Title\n\none two three four\n");
+        assert!(!e.reflow_selection(None));
+    }
+
+    #[test]
+    fn test_renumber_lists_around() {
+        // Typing a new item, renumbered as part of the same undo entry
+        let mut e = test_editor("1. a\n2. b\n", 5, 5);
+        e.apply_widget_text("1. a\n2. new\n2. b\n");
+        e.set_cursor(11);
+        assert!(e.renumber_lists_around(5..11));
+        assert_eq!(e.text(), "1. a\n2. new\n3. b\n");
+        assert_eq!(e.cursor(), 11);
+        e.undo();
+        assert_eq!(e.text(), "1. a\n2. b\n");
+        e.redo();
+        assert_eq!(e.text(), "1. a\n2. new\n3. b\n");
+
+        // An edit away from the list leaves it as it is
+        let mut e = test_editor("1. a\n3. b\n\nText\n", 15, 15);
+        e.apply_widget_text("1. a\n3. b\n\nTexts\n");
+        assert!(!e.renumber_lists_around(15..16));
+        assert!(e.renumber_lists());
+        assert_eq!(e.text(), "1. a\n2. b\n\nTexts\n");
+    }
+
+    #[test]
+    fn test_move_lines() {
+        let mut ed = test_editor("one\ntwo\nthree", 5, 5);
+        assert!(ed.move_lines(true));
+        assert_eq!(ed.text(), "two\none\nthree");
+        assert_eq!(ed.cursor(), 1);
+        assert!(!ed.move_lines(true));
+
+        // Two selected lines move together, past the last line without a newline
+        let mut ed = test_editor("one\ntwo\nthree", 0, 8);
+        assert!(ed.move_lines(false));
+        assert_eq!(ed.text(), "three\none\ntwo");
+        assert_eq!(ed.selection_range(), 6..13);
+        ed.undo();
+        assert_eq!(ed.text(), "one\ntwo\nthree");
+    }
+
+    #[test]
+    fn test_duplicate_lines() {
+        let mut ed = test_editor("a\nbc\n", 3, 3);
+        ed.duplicate_lines(false);
+        assert_eq!(ed.text(), "a\nbc\nbc\n");
+        assert_eq!(ed.cursor(), 6);
+
+        let mut ed = test_editor("a\nbc\n", 3, 3);
+        ed.duplicate_lines(true);
+        assert_eq!(ed.text(), "a\nbc\nbc\n");
+        assert_eq!(ed.cursor(), 3);
+    }
+
+    #[test]
+    fn test_delete_lines() {
+        // The caret keeps its column, counted in characters
+        let mut ed = test_editor("héllo\nwörld\n", 3, 3);
+        assert!(ed.delete_lines());
+        assert_eq!(ed.text(), "wörld\n");
+        assert_eq!(ed.cursor(), "wö".len());
+        ed.undo();
+        assert_eq!(ed.text(), "héllo\nwörld\n");
+
+        // Every line the selection touches; the last line takes the break before it
+        let mut ed = test_editor("a\nb\nc", 2, 5);
+        assert!(ed.delete_lines());
+        assert_eq!(ed.text(), "a");
+        assert_eq!(ed.cursor(), 1);
+
+        let mut ed = test_editor("", 0, 0);
+        assert!(!ed.delete_lines());
+    }
+
+    #[test]
+    fn test_toggle_inline_marker() {
+        let mut ed = test_editor("make it bold", 8, 12);
+        ed.toggle_inline_marker("**");
+        assert_eq!(ed.text(), "make it **bold**");
+        assert_eq!(ed.selection_range(), 10..14);
+        ed.toggle_inline_marker("**");
+        assert_eq!(ed.text(), "make it bold");
+        assert_eq!(ed.selection_range(), 8..12);
+
+        let mut ed = test_editor("*word*", 0, 6);
+        ed.toggle_inline_marker("*");
+        assert_eq!(ed.text(), "word");
+        assert_eq!(ed.selection_range(), 0..4);
+
+        let mut ed = test_editor("ab", 1, 1);
+        ed.toggle_inline_marker("*");
+        assert_eq!(ed.text(), "a**b");
+        assert_eq!(ed.cursor(), 2);
+
+        // Multi-byte characters before the caret
+        let mut ed = test_editor("café", 5, 5);
+        ed.toggle_inline_marker("*");
+        assert_eq!(ed.text(), "café**");
+        assert_eq!(ed.cursor(), 6);
+        let mut ed = test_editor("日本", 6, 6);
+        ed.toggle_inline_marker("**");
+        assert_eq!(ed.text(), "日本****");
+        let mut ed = test_editor("**日本**", 2, 8);
+        ed.toggle_inline_marker("**");
+        assert_eq!(ed.text(), "日本");
+    }
+
+    #[test]
+    fn test_shift_headings() {
+        let text = "# Top\n\n## Sub\ntext\n```\n# not a heading\n```\n###### Deep\n";
+        let mut ed = test_editor(text, 0, text.len());
+        assert!(ed.shift_headings(true));
+        assert_eq!(
+            ed.text(),
+            "## Top\n\n### Sub\ntext\n```\n# not a heading\n```\n###### Deep\n"
+        );
+        assert_eq!(ed.selection_range(), 0..text.len() + 2);
+
+        // Only the heading under the caret, clamped at level 1
+        let mut ed = test_editor("# A\n## B\n", 6, 6);
+        assert!(ed.shift_headings(false));
+        assert_eq!(ed.text(), "# A\n# B\n");
+        assert_eq!(ed.cursor(), 5);
+        assert!(!ed.shift_headings(false));
+    }
+}
@@ -0,0 +1,348 @@
+//! Editing GFM tables: Tab and Shift+Tab move between cells, Enter adds a
+//! row below the caret's, and Format Table lines the columns up
+
+use super::Editor;
+use crate::markdown::fence_marker;
+use std::ops::Range;
+
+/// A table around the caret: the byte range of each row's line, without
+/// its newline, header first and the delimiter row second
+struct Table {
+    rows: Vec<Range<usize>>,
+    /// Row the caret is on
+    current: usize,
+}
+
+/// Offsets of the pipes in `line` that separate cells, leaving out `\|`
+fn pipes(line: &str) -> Vec<usize> {
+    let mut pipes = Vec::new();
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        if c == '|' && !escaped {
+            pipes.push(i);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    pipes
+}
+
+/// Byte ranges of the cells of a row, within `line`, spaces around the
+/// content included. Pipes at either end of the row don't start a cell.
+fn cells(line: &str) -> Vec<Range<usize>> {
+    let mut cells = Vec::new();
+    let mut start = 0;
+    for pipe in pipes(line) {
+        cells.push(start..pipe);
+        start = pipe + 1;
+    }
+    cells.push(start..line.len());
+    if line.trim_start().starts_with('|') {
+        cells.remove(0);
+    }
+    let trailing = line.trim_end().ends_with('|') && !line.trim_end().ends_with("\\|");
+    if trailing && !cells.is_empty() {
+        cells.pop();
+    }
+    cells
+}
+
+/// Whether `line` is a delimiter row such as `| :--- | ---: |`
+fn is_delimiter_row(line: &str) -> bool {
+    if !line.contains('-') {
+        return false;
+    }
+    let cells = cells(line);
+    !cells.is_empty()
+        && cells.iter().all(|cell| {
+            let cell = line[cell.clone()].trim();
+            let dashes = cell.strip_prefix(':').unwrap_or(cell);
+            let dashes = dashes.strip_suffix(':').unwrap_or(dashes);
+            !dashes.is_empty() && dashes.chars().all(|c| c == '-')
+        })
+}
+
+/// The table the line holding `pos` is a row of, if it is outside code
+fn table_at(text: &str, pos: usize) -> Option<Table> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for line in text.split('\n') {
+        lines.push(offset..offset + line.len());
+        offset += line.len() + 1;
+    }
+    let current = lines.iter().position(|line| line.contains(&pos) || line.end == pos)?;
+
+    let mut fence: Option<(char, usize)> = None;
+    for line in &lines[..current] {
+        if let Some((marker, len)) = fence_marker(&text[line.clone()]) {
+            match fence {
+                None => fence = Some((marker, len)),
+                Some((open, open_len)) if open == marker && len >= open_len => fence = None,
+                Some(_) => {}
+            }
+        }
+    }
+    let row = |index: usize| {
+        let line = &text[lines[index].clone()];
+        !line.trim().is_empty() && !pipes(line).is_empty() && fence_marker(line).is_none()
+    };
+    if fence.is_some() || !row(current) {
+        return None;
+    }
+    let mut first = current;
+    while first > 0 && row(first - 1) {
+        first -= 1;
+    }
+    let mut last = current;
+    while last + 1 < lines.len() && row(last + 1) {
+        last += 1;
+    }
+    // The header is the line above the first delimiter row
+    let delimiter = (first + 1..=last).find(|&index| is_delimiter_row(&text[lines[index].clone()]))?;
+    let first = delimiter - 1;
+    (current >= first).then(|| Table { rows: lines[first..=last].to_vec(), current: current - first })
+}
+
+/// Selection for moving into `cell` of the row at `line`: its content, or
+/// a caret after the space that opens an empty cell
+fn cell_target(text: &str, line: &Range<usize>, cell: &Range<usize>) -> (usize, usize) {
+    let content = &text[line.start + cell.start..line.start + cell.end];
+    let trimmed = content.trim();
+    if trimmed.is_empty() {
+        let caret = line.start + (cell.start + 1).min(cell.end);
+        return (caret, caret);
+    }
+    let start = line.start + cell.start + (content.len() - content.trim_start().len());
+    (start, start + trimmed.len())
+}
+
+/// A row of `count` empty cells, indented and piped like `like`
+fn empty_row(like: &str, count: usize) -> String {
+    let indent = &like[..like.len() - like.trim_start().len()];
+    let leading = if like.trim_start().starts_with('|') { "|" } else { "" };
+    let trailing = if like.trim_end().ends_with('|') { "|" } else { "" };
+    format!("{}{}{}{}", indent, leading, vec!["   "; count.max(1)].join("|"), trailing)
+}
+
+/// Column alignment of a delimiter cell, as the cell it's written back as
+fn delimiter_cell(cell: &str, width: usize) -> String {
+    let cell = cell.trim();
+    match (cell.starts_with(':'), cell.ends_with(':') && cell.len() > 1) {
+        (true, true) => format!(":{}:", "-".repeat(width.saturating_sub(2).max(1))),
+        (true, false) => format!(":{}", "-".repeat(width.saturating_sub(1).max(2))),
+        (false, true) => format!("{}:", "-".repeat(width.saturating_sub(1).max(2))),
+        (false, false) => "-".repeat(width.max(3)),
+    }
+}
+
+/// `rows` with every column padded to its widest cell
+fn format_rows(rows: &[&str]) -> Vec<String> {
+    let indent = &rows[0][..rows[0].len() - rows[0].trim_start().len()];
+    let table: Vec<Vec<&str>> = rows.iter().map(|row| cells(row).into_iter().map(|cell| row[cell].trim()).collect()).collect();
+    let columns = table.iter().map(Vec::len).max().unwrap_or(0);
+    let mut widths = vec![3; columns];
+    for row in table.iter().enumerate().filter(|(index, _)| *index != 1).map(|(_, row)| row) {
+        for (column, cell) in row.iter().enumerate() {
+            widths[column] = widths[column].max(cell.chars().count());
+        }
+    }
+    table
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let cells: Vec<String> = (0..columns)
+                .map(|column| {
+                    let cell = row.get(column).copied().unwrap_or("");
+                    if index == 1 {
+                        delimiter_cell(if cell.is_empty() { "---" } else { cell }, widths[column])
+                    } else {
+                        format!("{}{}", cell, " ".repeat(widths[column] - cell.chars().count()))
+                    }
+                })
+                .collect();
+            format!("{}| {} |", indent, cells.join(" | "))
+        })
+        .collect()
+}
+
+impl Editor {
+    /// Tab in a table: select the next cell, or with `backwards` the
+    /// previous one, going on to the row below or above. Tab in the last
+    /// cell adds a row. Returns whether the caret was in a table.
+    pub fn table_tab(&mut self, backwards: bool) -> bool {
+        let text = self.text();
+        let Some(table) = table_at(&text, self.cursor) else {
+            return false;
+        };
+        let line = &table.rows[table.current];
+        let row_cells = cells(&text[line.clone()]);
+        let column = self.cursor - line.start;
+        let index = row_cells.iter().position(|cell| column <= cell.end).unwrap_or(row_cells.len().saturating_sub(1));
+        let on_delimiter = table.current == 1;
+
+        let target = if backwards {
+            if index > 0 && !on_delimiter {
+                Some((table.current, index - 1))
+            } else {
+                let row = match table.current {
+                    0 => None,
+                    1 | 2 => Some(0),
+                    row => Some(row - 1),
+                };
+                row.map(|row| (row, cells(&text[table.rows[row].clone()]).len().saturating_sub(1)))
+            }
+        } else if index + 1 < row_cells.len() && !on_delimiter {
+            Some((table.current, index + 1))
+        } else {
+            let row = if table.current == 0 { 2 } else { table.current + 1 };
+            if row >= table.rows.len() {
+                self.insert_table_row(&text, &table, table.rows.len() - 1);
+                return true;
+            }
+            Some((row, 0))
+        };
+        if let Some((row, cell)) = target {
+            let line = &table.rows[row];
+            let row_cells = cells(&text[line.clone()]);
+            if let Some(cell) = row_cells.get(cell).or(row_cells.last()) {
+                let (anchor, cursor) = cell_target(&text, line, cell);
+                self.set_selection(anchor, cursor);
+            }
+        }
+        true
+    }
+
+    /// Enter in a table: add an empty row below the caret's, below the
+    /// delimiter row when on the header. Returns whether the caret was in
+    /// a table.
+    pub fn table_new_row(&mut self) -> bool {
+        let text = self.text();
+        let Some(table) = table_at(&text, self.cursor) else {
+            return false;
+        };
+        self.insert_table_row(&text, &table, table.current.max(1));
+        true
+    }
+
+    /// Line the caret's table up in columns, as one undoable edit.
+    /// Returns whether anything changed.
+    pub fn format_table(&mut self) -> bool {
+        let text = self.text();
+        let Some(table) = table_at(&text, self.cursor) else {
+            return false;
+        };
+        let rows: Vec<&str> = table.rows.iter().map(|row| &text[row.clone()]).collect();
+        let line = &table.rows[table.current];
+        let column = self.cursor - line.start;
+        let cell = cells(rows[table.current]).iter().position(|cell| column <= cell.end).unwrap_or(0);
+
+        let formatted = format_rows(&rows);
+        let span = table.rows[0].start..table.rows[table.rows.len() - 1].end;
+        let replacement = formatted.join("\n");
+        if replacement == text[span.clone()] {
+            return false;
+        }
+        // Keep the caret in the same cell
+        let start = span.start + formatted[..table.current].iter().map(|row| row.len() + 1).sum::<usize>();
+        let line = start..start + formatted[table.current].len();
+        let new_cells = cells(&formatted[table.current]);
+        let caret = new_cells.get(cell).map_or(line.start, |cell| {
+            let content = &formatted[table.current][cell.clone()];
+            line.start + cell.start + (content.len() - content.trim_start().len())
+        });
+        self.record_edit(span, &replacement, (caret, caret), false);
+        self.set_selection(caret, caret);
+        true
+    }
+
+    /// Add an empty row after row `after`, with as many cells as the
+    /// header, and put the caret in its first cell
+    fn insert_table_row(&mut self, text: &str, table: &Table, after: usize) {
+        let header = &text[table.rows[0].clone()];
+        let row = empty_row(header, cells(header).len());
+        let at = table.rows[after].end;
+        let line = at + 1..at + 1 + row.len();
+        let caret = line.start + cells(&row).first().map_or(0, |cell| (cell.start + 1).min(cell.end));
+        self.record_edit(at..at, &format!("\n{}", row), (caret, caret), false);
+        self.set_selection(caret, caret);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::test_editor;
+
+    const TABLE: &str = "Intro\n\n| Name | Age |\n| :--- | --: |\n| Ann | 30 |\n| Bo \\| Cy | 4 |\n\nAfter";
+
+    fn selected(editor: &Editor) -> String {
+        let range = editor.selection_range();
+        editor.text()[range].to_string()
+    }
+
+    #[test]
+    fn test_cells() {
+        let line = "| Bo \\| Cy | 4 |";
+        let texts: Vec<_> = cells(line).into_iter().map(|cell| line[cell].trim()).collect();
+        assert_eq!(texts, ["Bo \\| Cy", "4"]);
+        let texts: Vec<_> = cells("a | b").into_iter().map(|cell| "a | b"[cell].trim()).collect();
+        assert_eq!(texts, ["a", "b"]);
+        assert!(is_delimiter_row("| :--- | --: |"));
+        assert!(is_delimiter_row("---|:-:"));
+        assert!(!is_delimiter_row("| Ann | 30 |"));
+        assert!(table_at(TABLE, 2).is_none());
+        assert!(table_at("```\n| a | b |\n| - | - |\n```", 6).is_none());
+        assert_eq!(table_at(TABLE, TABLE.find("Ann").unwrap()).unwrap().current, 2);
+    }
+
+    #[test]
+    fn test_tab_moves_between_cells() {
+        let at = TABLE.find("Name").unwrap();
+        let mut editor = test_editor(TABLE, at, at);
+        assert!(editor.table_tab(false));
+        assert_eq!(selected(&editor), "Age");
+        // On past the delimiter row to the next row
+        editor.table_tab(false);
+        assert_eq!(selected(&editor), "Ann");
+        editor.table_tab(false);
+        editor.table_tab(false);
+        assert_eq!(selected(&editor), "Bo \\| Cy");
+        editor.table_tab(true);
+        assert_eq!(selected(&editor), "30");
+        editor.table_tab(true);
+        editor.table_tab(true);
+        assert_eq!(selected(&editor), "Age");
+        // Outside a table Tab is left alone
+        let mut outside = test_editor(TABLE, 1, 1);
+        assert!(!outside.table_tab(false));
+    }
+
+    #[test]
+    fn test_new_rows() {
+        // Tab in the last cell adds a row, as one undoable edit
+        let at = TABLE.find("4 |").unwrap();
+        let mut editor = test_editor(TABLE, at, at);
+        editor.table_tab(false);
+        assert!(editor.text().contains("| Bo \\| Cy | 4 |\n|   |   |\n\nAfter"));
+        assert_eq!(editor.cursor(), editor.text().find("|   |   |").unwrap() + 2);
+        editor.undo();
+        assert_eq!(editor.text(), TABLE);
+
+        // Enter on the header adds a row below the delimiter row
+        let at = TABLE.find("Age").unwrap();
+        let mut editor = test_editor(TABLE, at, at);
+        assert!(editor.table_new_row());
+        assert!(editor.text().contains("| :--- | --: |\n|   |   |\n| Ann"));
+        assert!(!test_editor(TABLE, 0, 0).table_new_row());
+    }
+
+    #[test]
+    fn test_format_table() {
+        let at = TABLE.find("30").unwrap();
+        let mut editor = test_editor(TABLE, at, at);
+        assert!(editor.format_table());
+        let formatted = "| Name     | Age |\n| :------- | --: |\n| Ann      | 30  |\n| Bo \\| Cy | 4   |";
+        assert_eq!(editor.text(), format!("Intro\n\n{}\n\nAfter", formatted));
+        assert_eq!(editor.cursor(), editor.text().find("30").unwrap());
+        assert!(!editor.format_table());
+    }
+}
@@ -50,6 +50,11 @@ impl TextBuffer {
         self.storage.len() - (self.gap_end - self.gap_start)
     }
 
+    /// Bytes allocated for the text, the gap, and the line starts
+    pub fn allocated_bytes(&self) -> usize {
+        self.storage.capacity() + self.line_starts.capacity() * std::mem::size_of::<usize>()
+    }
+
     /// Check if the buffer is empty
     pub fn is_empty(&self) -> bool {
         self.len() == 0
@@ -174,6 +179,20 @@ impl TextBuffer {
         Some(text.trim_end_matches('\n').trim_end_matches('\r').to_string())
     }
 
+    /// Byte range of a line's content, excluding its `\n` terminator
+    pub fn line_range(&mut self, line: usize) -> Range<usize> {
+        self.rebuild_line_cache_if_needed();
+
+        let Some(&start) = self.line_starts.get(line) else {
+            return self.len()..self.len();
+        };
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .map_or(self.len(), |next| next - 1);
+        start..end
+    }
+
     /// Get the line and column from a byte offset (mutable version for cache rebuild)
     pub fn line_col_from_byte_index_mut(&mut self, byte_index: usize) -> (usize, usize) {
         self.rebuild_line_cache_if_needed();
@@ -198,18 +217,16 @@ impl TextBuffer {
 
     /// Move the gap to a specific position
     fn move_gap(&mut self, pos: usize) {
+        let pos = pos.min(self.len());
         if pos == self.gap_start {
             return;
         }
 
-        let len = self.len();
-        let pos = pos.min(len);
-
         if pos < self.gap_start {
-            // Move gap left: copy content from left of gap to right
+            // Move gap left: copy content from left of gap to right.
+            // Copy back to front since the ranges overlap when the gap is small.
             let move_len = self.gap_start - pos;
-            // Manual copy
-            for i in 0..move_len {
+            for i in (0..move_len).rev() {
                 self.storage[self.gap_end - move_len + i] = self.storage[pos + i];
             }
             self.gap_start -= move_len;
@@ -228,20 +245,20 @@ impl TextBuffer {
 
     /// Grow the gap by a specific amount
     fn grow_gap(&mut self, additional: usize) {
-        let new_gap_end = self.gap_end + additional;
-        self.storage.resize(new_gap_end, 0);
-        // Manual copy to avoid copy_within issue
+        let old_len = self.storage.len();
+        self.storage.resize(old_len + additional, 0);
+        // Shift the text after the gap to the new end of storage
         let src_start = self.gap_end;
         let dst_start = self.gap_end + additional;
-        let len = self.storage.len() - src_start;
+        let len = old_len - src_start;
         for i in (0..len).rev() {
             self.storage[dst_start + i] = self.storage[src_start + i];
         }
-        self.gap_end = new_gap_end;
+        self.gap_end += additional;
     }
 
     /// Get a substring from the buffer
-    fn substring(&self, start: usize, end: usize) -> String {
+    pub fn substring(&self, start: usize, end: usize) -> String {
         let mut result = String::with_capacity(end - start);
 
         if end <= self.gap_start || start >= self.gap_start {
@@ -349,15 +366,36 @@ mod tests {
         assert_eq!(buffer.as_str(), "HelloWorld!");
     }
 
+    #[test]
+    fn test_edits_across_gap() {
+        let mut buffer = TextBuffer::from("abcdef");
+        buffer.insert(3, "XYZ");
+        buffer.insert(1, "12345678");
+        assert_eq!(buffer.as_str(), "a12345678bcXYZdef");
+
+        buffer.replace_range(0..9, "A");
+        assert_eq!(buffer.as_str(), "AbcXYZdef");
+        assert_eq!(buffer.substring(3, 6), "XYZ");
+    }
+
+    #[test]
+    fn test_line_range() {
+        let mut buffer = TextBuffer::from("ab\n\ncd");
+        assert_eq!(buffer.line_range(0), 0..2);
+        assert_eq!(buffer.line_range(1), 3..3);
+        assert_eq!(buffer.line_range(2), 4..6);
+        assert_eq!(buffer.line_range(3), 6..6);
+    }
+
     #[test]
     fn test_line_count() {
-        let buffer = TextBuffer::from("Line 1\nLine 2\nLine 3");
+        let mut buffer = TextBuffer::from("Line 1\nLine 2\nLine 3");
         assert_eq!(buffer.line_count(), 3);
     }
 
     #[test]
     fn test_line_col_conversion() {
-        let buffer = TextBuffer::from("Hello\nWorld\n!");
+        let mut buffer = TextBuffer::from("Hello\nWorld\n!");
 
         // Line 0, Col 0 -> Byte 0
         assert_eq!(buffer.byte_index_from_line_col(0, 0), 0);
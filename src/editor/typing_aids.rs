@@ -0,0 +1,370 @@
+//! Typing aids, each one optional: capitalizing the first letter of a
+//! sentence, correcting words from a replacement table, and turning `--`
+//! and `---` into en and em dashes. None of them apply in code or front
+//! matter, and each substitution is an edit of its own, so undoing it
+//! gives back exactly what was typed.
+
+use super::words::code_spans;
+use crate::markdown::{fence_marker, flavor};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Words ending in a period that doesn't end the sentence. Any word with
+/// a period inside, such as `e.g.`, counts as well.
+const ABBREVIATIONS: [&str; 14] = ["cf", "dr", "etc", "fig", "jr", "mr", "mrs", "ms", "no", "pp", "prof", "sr", "st", "vs"];
+
+/// Which aids are on
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TypingAids {
+    pub capitalize: bool,
+    pub correct: bool,
+    pub dashes: bool,
+}
+
+/// What made a substitution, to turn it off from there
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rule {
+    Capitalize,
+    /// The table entry, by what it replaces
+    Correction(String),
+    Dash,
+}
+
+/// Text to put in place of what was just typed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Substitution {
+    pub range: Range<usize>,
+    pub replacement: String,
+    pub rule: Rule,
+}
+
+/// Layout of `autocorrect.toml`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CorrectionFile {
+    #[serde(default)]
+    replacements: BTreeMap<String, String>,
+}
+
+/// The replacement table: the user's `autocorrect.toml` once there is
+/// one, or else a few common fixes
+pub struct Corrections {
+    replacements: BTreeMap<String, String>,
+    path: Option<PathBuf>,
+    /// When the file was modified as last read, to notice edits to it
+    loaded: Option<SystemTime>,
+}
+
+impl Corrections {
+    /// Built-in corrections only
+    pub fn new() -> Self {
+        let replacements = [
+            ("adn", "and"),
+            ("definately", "definitely"),
+            ("hte", "the"),
+            ("occured", "occurred"),
+            ("recieve", "receive"),
+            ("seperate", "separate"),
+            ("taht", "that"),
+            ("teh", "the"),
+            ("untill", "until"),
+            ("wich", "which"),
+            ("(c)", "©"),
+            ("(r)", "®"),
+            ("(tm)", "™"),
+        ];
+        let replacements = replacements.iter().map(|(from, to)| (from.to_string(), to.to_string())).collect();
+        Self { replacements, path: None, loaded: None }
+    }
+
+    /// The table in the settings folder's `autocorrect.toml`
+    pub fn load_or_default() -> Self {
+        let mut corrections = Self::new();
+        match crate::config::Config::config_dir() {
+            Ok(dir) => corrections.path = Some(dir.join("autocorrect.toml")),
+            Err(e) => log::warn!("Failed to locate the auto-correct list: {}", e),
+        }
+        corrections.reload_if_changed();
+        corrections
+    }
+
+    /// Read the file again if it was edited since it was last read
+    pub fn reload_if_changed(&mut self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let modified = std::fs::metadata(path).and_then(|meta| meta.modified()).ok();
+        if modified.is_none() || modified == self.loaded {
+            return;
+        }
+        self.loaded = modified;
+        match read_file(path) {
+            Ok(replacements) => self.replacements = replacements,
+            Err(e) => log::warn!("Failed to load {}: {}", path.display(), e),
+        }
+    }
+
+    /// Stop correcting `from`, saving the table without it
+    pub fn remove(&mut self, from: &str) -> anyhow::Result<()> {
+        self.replacements.remove(from);
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = CorrectionFile { replacements: self.replacements.clone() };
+        std::fs::write(path, toml::to_string(&file)?)?;
+        self.loaded = std::fs::metadata(path).and_then(|meta| meta.modified()).ok();
+        Ok(())
+    }
+
+    /// The replacement for `word`, capitalized like it is
+    fn correct(&self, word: &str) -> Option<(String, String)> {
+        if let Some(replacement) = self.replacements.get(word) {
+            return Some((word.to_string(), replacement.clone()));
+        }
+        let mut chars = word.chars();
+        let first = chars.next().filter(|c| c.is_uppercase())?;
+        let lower: String = first.to_lowercase().chain(chars).collect();
+        let replacement = self.replacements.get(&lower)?;
+        let mut chars = replacement.chars();
+        let capitalized = chars.next().map(|c| c.to_uppercase().chain(chars).collect())?;
+        Some((lower, capitalized))
+    }
+}
+
+impl Default for Corrections {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn read_file(path: &Path) -> anyhow::Result<BTreeMap<String, String>> {
+    let file: CorrectionFile = toml::from_str(&std::fs::read_to_string(path)?)?;
+    Ok(file.replacements)
+}
+
+/// The character `new` has that `old` doesn't, if the only change between
+/// them is that one character typed just before `caret`
+pub fn typed_char(old: &str, new: &str, caret: usize) -> Option<char> {
+    let typed = new.get(..caret)?.chars().next_back()?;
+    let at = caret - typed.len_utf8();
+    let inserted = new.len() == old.len() + typed.len_utf8() && old.get(..at) == Some(&new[..at]) && old.get(at..) == Some(&new[caret..]);
+    inserted.then_some(typed)
+}
+
+/// Whether `pos` is in prose: not in front matter, a code block, or a code
+/// span, including ones whose closing line or backticks aren't typed yet
+pub fn in_prose(text: &str, pos: usize) -> bool {
+    let front_matter_end = flavor::front_matter(text)
+        .map(|range| range.end)
+        .or_else(|| (text.starts_with("---\n") || text.starts_with("---\r\n")).then_some(text.len()));
+    if front_matter_end.is_some_and(|end| pos <= end) {
+        return false;
+    }
+    let mut fence: Option<(char, usize)> = None;
+    let mut code_above = true;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let indented = content.starts_with('\t') || content.starts_with("    ");
+        if offset + line.len() > pos || !line.ends_with('\n') {
+            let in_code = fence.is_some() || fence_marker(content).is_some() || (indented && code_above);
+            let at = (pos - offset).min(content.len());
+            let spans = code_spans(content);
+            let after_spans = spans.last().map_or(0, |span| span.end);
+            let in_span = spans.iter().any(|span| span.start < at && at < span.end) || content[after_spans.min(at)..at].contains('`');
+            return !in_code && !in_span;
+        }
+        if let Some((marker, len)) = fence_marker(content) {
+            match fence {
+                None => fence = Some((marker, len)),
+                Some((open, open_len)) if open == marker && len >= open_len => fence = None,
+                Some(_) => {}
+            }
+        }
+        code_above = content.trim().is_empty() || (indented && code_above);
+        offset += line.len();
+    }
+    true
+}
+
+/// The substitution to make now that `typed` was typed just before `caret`
+pub fn substitution(text: &str, caret: usize, typed: char, aids: TypingAids, corrections: &Corrections) -> Option<Substitution> {
+    let at = caret.checked_sub(typed.len_utf8())?;
+    if !in_prose(text, at) {
+        return None;
+    }
+    let before = &text[..at];
+    let dash = || dash(before, typed).filter(|_| aids.dashes);
+    let correction = || correction(&text[..caret], typed, corrections).filter(|_| aids.correct);
+    let capital = || capitalization(before, typed).filter(|_| aids.capitalize);
+    dash().or_else(correction).or_else(capital)
+}
+
+/// `--` or `---` turned into an en or em dash once something other than a
+/// dash follows, unless it is a rule, a table row, or part of a comment
+fn dash(before: &str, typed: char) -> Option<Substitution> {
+    if !(typed.is_whitespace() || typed.is_alphanumeric()) {
+        return None;
+    }
+    let line = &before[before.rfind('\n').map_or(0, |i| i + 1)..];
+    let rest = line.trim_end_matches('-');
+    let replacement = match line.len() - rest.len() {
+        2 => "–",
+        3 => "—",
+        _ => return None,
+    };
+    if rest.trim().is_empty() || rest.ends_with(['!', '<', '|', ':']) || line.contains("<!--") || line.trim_start().starts_with('|') {
+        return None;
+    }
+    Some(Substitution { range: rest.len() + before.len() - line.len()..before.len(), replacement: replacement.to_string(), rule: Rule::Dash })
+}
+
+/// A table entry the text just typed completes: a word once a character
+/// after it ends it, or an entry such as `(c)` once its last character is in
+fn correction(text: &str, typed: char, corrections: &Corrections) -> Option<Substitution> {
+    let symbol = corrections.replacements.iter().find(|(from, _)| {
+        let ends_in_word = from.chars().next_back().is_some_and(char::is_alphanumeric);
+        let start = text.len().saturating_sub(from.len());
+        !ends_in_word
+            && text.ends_with(from.as_str())
+            && !text[..start].chars().next_back().is_some_and(char::is_alphanumeric)
+    });
+    if let Some((from, to)) = symbol {
+        let range = text.len() - from.len()..text.len();
+        return Some(Substitution { range, replacement: to.clone(), rule: Rule::Correction(from.clone()) });
+    }
+
+    if typed.is_alphanumeric() || matches!(typed, '\'' | '’' | '-' | '_') {
+        return None;
+    }
+    let before = &text[..text.len() - typed.len_utf8()];
+    let is_word = |c: char| c.is_alphanumeric() || c == '\'';
+    let start = before.rfind(|c| !is_word(c)).map_or(0, |i| i + before[i..].chars().next().map_or(1, char::len_utf8));
+    // Part of a path, address, or name rather than a word of its own
+    if before[..start].ends_with(['_', '.', '/', '\\', '@', ':', '#', '-']) {
+        return None;
+    }
+    let (from, to) = corrections.correct(&before[start..])?;
+    Some(Substitution { range: start..before.len(), replacement: to, rule: Rule::Correction(from) })
+}
+
+/// A lowercase letter typed after a sentence's end and a space, made a
+/// capital unless the period ends an abbreviation, a number, or an ellipsis
+fn capitalization(before: &str, typed: char) -> Option<Substitution> {
+    if !typed.is_lowercase() {
+        return None;
+    }
+    let spaced = before.trim_end_matches(' ');
+    if spaced.len() == before.len() {
+        return None;
+    }
+    let closed = spaced.trim_end_matches(['"', '\'', '”', '’', ')', '*', '_']);
+    let stop = closed.chars().next_back().filter(|c| matches!(c, '.' | '!' | '?'))?;
+    let sentence = &closed[..closed.len() - 1];
+    let word = sentence.rsplit(char::is_whitespace).next().unwrap_or_default();
+    let word = word.trim_start_matches(['(', '"', '\'', '“', '‘', '*', '_']);
+    let not_an_end = word.is_empty()
+        || word.contains(['.', '/', '@'])
+        || word.chars().count() == 1
+        || word.chars().all(|c| c.is_ascii_digit())
+        || ABBREVIATIONS.contains(&word.to_lowercase().as_str());
+    if stop == '.' && not_an_end {
+        return None;
+    }
+    let at = before.len();
+    Some(Substitution { range: at..at + typed.len_utf8(), replacement: typed.to_uppercase().collect(), rule: Rule::Capitalize })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: TypingAids = TypingAids { capitalize: true, correct: true, dashes: true };
+
+    /// `text` after typing its last character, with any substitution made
+    fn typed(text: &str) -> String {
+        let typed = text.chars().next_back().unwrap();
+        match substitution(text, text.len(), typed, ALL, &Corrections::new()) {
+            Some(substitution) => {
+                let mut result = text.to_string();
+                result.replace_range(substitution.range, &substitution.replacement);
+                result
+            }
+            None => text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_capitalize() {
+        assert_eq!(typed("One. t"), "One. T");
+        assert_eq!(typed("Really? y"), "Really? Y");
+        assert_eq!(typed("He said \"stop.\" t"), "He said \"stop.\" T");
+        assert_eq!(typed("*Done.*  n"), "*Done.*  N");
+        // Only the first letter, after a space
+        assert_eq!(typed("One. Tw"), "One. Tw");
+        assert_eq!(typed("One.t"), "One.t");
+        // Abbreviations, initials, numbers, ellipses, and addresses
+        assert_eq!(typed("See e.g. t"), "See e.g. t");
+        assert_eq!(typed("Cats vs. d"), "Cats vs. d");
+        assert_eq!(typed("J. s"), "J. s");
+        assert_eq!(typed("1. i"), "1. i");
+        assert_eq!(typed("Wait... t"), "Wait... t");
+        assert_eq!(typed("at example.com. t"), "at example.com. t");
+    }
+
+    #[test]
+    fn test_corrections() {
+        assert_eq!(typed("I saw teh "), "I saw the ");
+        assert_eq!(typed("Teh,"), "The,");
+        assert_eq!(typed("Copyright (c)"), "Copyright ©");
+        // Only once the word ends, and only whole words
+        assert_eq!(typed("teh"), "teh");
+        assert_eq!(typed("tehran "), "tehran ");
+        assert_eq!(typed("file_teh "), "file_teh ");
+        assert_eq!(typed("call f(c)"), "call f(c)");
+
+        let mut corrections = Corrections::new();
+        corrections.remove("teh").unwrap();
+        assert!(substitution("teh ", 4, ' ', ALL, &corrections).is_none());
+        let made = substitution("hte ", 4, ' ', ALL, &corrections).unwrap();
+        assert_eq!(made.rule, Rule::Correction("hte".to_string()));
+        assert_eq!(made.range, 0..3);
+    }
+
+    #[test]
+    fn test_dashes() {
+        assert_eq!(typed("pages 4--5"), "pages 4–5");
+        assert_eq!(typed("wait -- "), "wait – ");
+        assert_eq!(typed("and---t"), "and—t");
+        assert_eq!(typed("one-two"), "one-two");
+        assert_eq!(typed("a----b"), "a----b");
+        // Rules, front matter, table rows, and comments
+        assert_eq!(typed("---\n"), "---\n");
+        assert_eq!(typed("Title\n--- "), "Title\n--- ");
+        assert_eq!(typed("| a |--- "), "| a |--- ");
+        assert_eq!(typed("<!--a"), "<!--a");
+        assert_eq!(typed("a<!-- note --b"), "a<!-- note --b");
+    }
+
+    #[test]
+    fn test_not_in_code() {
+        assert_eq!(typed("`teh "), "`teh ");
+        assert_eq!(typed("`a` and `b-- "), "`a` and `b-- ");
+        assert_eq!(typed("`a` teh "), "`a` the ");
+        assert_eq!(typed("```\nOne. t"), "```\nOne. t");
+        assert_eq!(typed("```\ncode\n```\nteh "), "```\ncode\n```\nthe ");
+        assert_eq!(typed("Text\n\n    teh "), "Text\n\n    teh ");
+        assert_eq!(typed("---\ntitle: teh "), "---\ntitle: teh ");
+        assert_eq!(typed("---\ntitle: x\n---\nteh "), "---\ntitle: x\n---\nthe ");
+
+        assert_eq!(typed_char("ab", "axb", 2), Some('x'));
+        assert_eq!(typed_char("ab", "axb", 3), None);
+        assert_eq!(typed_char("ab", "axyb", 3), None);
+        assert_eq!(typed_char("ab", "a", 1), None);
+    }
+}
@@ -0,0 +1,89 @@
+//! Where a document was being viewed, so it can be shown again as it was
+
+use super::Editor;
+use serde::{Deserialize, Serialize};
+
+/// The caret, selection, scroll position, and bookmarks of a document.
+/// The undo history stays with the document's `Editor`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ViewState {
+    /// Selection anchor and caret, as byte offsets
+    pub anchor: usize,
+    pub cursor: usize,
+    /// Vertical scroll offset of the text, in points
+    pub scroll_y: f32,
+    /// Bookmarked lines, zero-based
+    pub bookmarks: Vec<usize>,
+}
+
+impl Editor {
+    pub fn view_state(&self) -> ViewState {
+        ViewState {
+            anchor: self.anchor,
+            cursor: self.cursor,
+            scroll_y: self.scroll_offset.y,
+            bookmarks: self.bookmarks.clone(),
+        }
+    }
+
+    /// Put the caret, scroll position, and bookmarks back where `state`
+    /// had them, as far as the current text allows
+    pub fn restore_view_state(&mut self, state: &ViewState) {
+        let text = self.buffer.as_str();
+        let clamp = |pos: usize| super::floor_char_boundary(&text, pos);
+        self.set_selection(clamp(state.anchor), clamp(state.cursor));
+        self.set_bookmarks(state.bookmarks.clone());
+        self.scroll_offset.y = state.scroll_y;
+        self.pending_scroll = Some(state.scroll_y);
+    }
+
+    /// Record how far the text widget is scrolled
+    pub fn set_scroll_y(&mut self, scroll_y: f32) {
+        self.scroll_offset.y = scroll_y;
+    }
+
+    /// Scroll offset to apply to the text widget, once after a restore
+    pub fn take_pending_scroll(&mut self) -> Option<f32> {
+        self.pending_scroll.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_documents_keep_their_own_state() {
+        let mut a = Editor::new();
+        let mut b = Editor::new();
+        assert_ne!(a.widget_id(), b.widget_id());
+        a.set_text("# A\n");
+        b.set_text("# B\n");
+
+        // Type in A, switch to B and type there, then back to A and undo
+        a.set_cursor(4);
+        a.insert_text("alpha");
+        let saved = a.view_state();
+        b.set_cursor(4);
+        b.insert_text("beta");
+
+        a.restore_view_state(&saved);
+        assert_eq!(a.cursor(), 9);
+        a.undo();
+        assert_eq!(a.text(), "# A\n");
+        assert_eq!(a.cursor(), 4);
+        assert_eq!(b.text(), "# B\nbeta");
+    }
+
+    #[test]
+    fn test_restore_clamps_to_text() {
+        let mut editor = Editor::new();
+        editor.set_text("short\nlines\n");
+        let state = ViewState { anchor: 2, cursor: 400, scroll_y: 80.0, bookmarks: vec![1, 9] };
+        editor.restore_view_state(&state);
+        assert_eq!(editor.selection_range(), 2..12);
+        assert_eq!(editor.bookmarks(), [1]);
+        assert_eq!(editor.take_pending_scroll(), Some(80.0));
+        assert_eq!(editor.take_pending_scroll(), None);
+    }
+}
@@ -0,0 +1,315 @@
+//! Word boundaries that know Markdown, for double-click selection and
+//! Ctrl+arrow movement: URLs and file paths are one word, emphasis markers
+//! are not part of the word they wrap, link text selects as a whole, and
+//! an inline code span is a single hop
+
+use super::{line_end, line_start, Editor};
+use std::ops::Range;
+
+/// What a character counts as between word boundaries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CharClass {
+    Space,
+    Word,
+    Punctuation,
+}
+
+pub fn classify(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Space
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+/// Inline code spans of `line`, backticks included: a run of backticks up
+/// to the next run of the same length
+pub(super) fn code_spans(line: &str) -> Vec<Range<usize>> {
+    let mut runs = Vec::new();
+    let mut at = 0;
+    while let Some(i) = line[at..].find('`') {
+        let start = at + i;
+        let len = line[start..].len() - line[start..].trim_start_matches('`').len();
+        runs.push((start, len));
+        at = start + len;
+    }
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < runs.len() {
+        let (start, len) = runs[i];
+        match runs[i + 1..].iter().position(|&(_, other)| other == len) {
+            Some(offset) => {
+                let (close, _) = runs[i + 1 + offset];
+                spans.push(start..close + len);
+                i += offset + 2;
+            }
+            None => i += 1,
+        }
+    }
+    spans
+}
+
+/// Characters that end a URL or path: space, and what wraps one in
+/// Markdown
+fn ends_path(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '<' | '>' | '(' | ')' | '[' | ']' | '{' | '}' | '"' | '\'' | '`' | '|')
+}
+
+/// Whether `token` reads as a URL, a file path, or a dotted name such as
+/// `main.rs`
+fn is_path(token: &str) -> bool {
+    let alphanumeric = |c: Option<char>| c.is_some_and(char::is_alphanumeric);
+    let dotted = token.match_indices('.').any(|(i, _)| alphanumeric(token[..i].chars().next_back()) && alphanumeric(token[i + 1..].chars().next()));
+    token.contains("://")
+        || token.starts_with("www.")
+        || token.starts_with("mailto:")
+        || (token.contains(['/', '\\']) && token.chars().any(char::is_alphanumeric))
+        || dotted
+}
+
+/// URLs and paths in `line`, without emphasis markers around them or
+/// sentence punctuation after
+fn paths(line: &str) -> Vec<Range<usize>> {
+    let mut paths = Vec::new();
+    let mut at = 0;
+    while at < line.len() {
+        let start = at + line[at..].find(|c| !ends_path(c)).unwrap_or(line.len() - at);
+        let end = start + line[start..].find(ends_path).unwrap_or(line.len() - start);
+        let token = &line[start..end];
+        let trimmed = token.trim_start_matches(['*', '_', '~']);
+        let start_trimmed = start + token.len() - trimmed.len();
+        let trimmed = trimmed.trim_end_matches(['*', '_', '~', '.', ',', ';', ':', '!', '?']);
+        if is_path(trimmed) {
+            paths.push(start_trimmed..start_trimmed + trimmed.len());
+        }
+        at = end;
+    }
+    paths
+}
+
+/// Text between the brackets around `pos`, trimmed, as in `[link text]`,
+/// `![alt]` or `[[Page]]`
+fn bracketed_at(line: &str, pos: usize) -> Option<Range<usize>> {
+    let open = line[..pos].rfind(['[', ']']).filter(|&i| line[i..].starts_with('['))? + 1;
+    let close = pos + line[pos..].find(['[', ']']).filter(|&i| line[pos + i..].starts_with(']'))?;
+    let inner = &line[open..close];
+    let start = open + inner.len() - inner.trim_start().len();
+    let end = open + inner.trim_end().len();
+    (start < end).then_some(start..end)
+}
+
+/// The run of word characters touching `pos`
+fn word_run(line: &str, pos: usize) -> Range<usize> {
+    let is_word = |c: char| classify(c) == CharClass::Word;
+    let start = line[..pos].rfind(|c| !is_word(c)).map_or(0, |i| i + line[i..].chars().next().map_or(1, char::len_utf8));
+    let end = line[pos..].find(|c| !is_word(c)).map_or(line.len(), |i| pos + i);
+    start..end
+}
+
+/// What double-clicking at `pos` selects: the text of the link there, the
+/// URL or path, or else the word, without the `_` of emphasis around it.
+/// Inside a code span only paths and plain words count. `None` when `pos`
+/// touches none of these.
+pub fn word_at(text: &str, pos: usize) -> Option<Range<usize>> {
+    let offset = line_start(text, pos);
+    let line = &text[offset..line_end(text, pos)];
+    let pos = pos - offset;
+    let in_code = code_spans(line).iter().any(|span| span.start < pos && pos < span.end);
+
+    let bracketed = || bracketed_at(line, pos).filter(|_| !in_code);
+    let path = || paths(line).into_iter().find(|path| path.start <= pos && pos <= path.end);
+    let word = || {
+        let run = word_run(line, pos);
+        let word = &line[run.clone()];
+        let inner = word.trim_matches('_');
+        let range = if in_code || inner.is_empty() {
+            run
+        } else {
+            let start = run.start + word.len() - word.trim_start_matches('_').len();
+            start..start + inner.len()
+        };
+        (!range.is_empty()).then_some(range)
+    };
+    let range = bracketed().or_else(path).or_else(word)?;
+    Some(offset + range.start..offset + range.end)
+}
+
+/// Where Ctrl+Right goes from `pos`: past any spaces, then to the end of
+/// the word, run of punctuation, path, or code span. At the end of a line,
+/// to the start of the next.
+pub fn next_boundary(text: &str, pos: usize) -> usize {
+    let end = line_end(text, pos);
+    if pos == end {
+        return (pos + 1).min(text.len());
+    }
+    let offset = line_start(text, pos);
+    let line = &text[offset..end];
+    let at = line.len() - line[pos - offset..].trim_start().len();
+    let Some(c) = line[at..].chars().next() else {
+        return end;
+    };
+    let unit = code_spans(line)
+        .into_iter()
+        .find(|span| span.start == at)
+        .or_else(|| paths(line).into_iter().find(|path| path.start <= at && at < path.end));
+    let stop = match unit {
+        Some(unit) => unit.end,
+        None => line[at..].find(|next| classify(next) != classify(c)).map_or(line.len(), |i| at + i),
+    };
+    offset + stop
+}
+
+/// Where Ctrl+Left goes from `pos`, the mirror of `next_boundary`
+pub fn previous_boundary(text: &str, pos: usize) -> usize {
+    let offset = line_start(text, pos);
+    if pos == offset {
+        let before = pos.saturating_sub(1);
+        return if text[..before].ends_with('\r') { before - 1 } else { before };
+    }
+    let line = &text[offset..line_end(text, pos)];
+    let at = line[..pos - offset].trim_end().len();
+    let Some(c) = line[..at].chars().next_back() else {
+        return offset;
+    };
+    let unit = code_spans(line)
+        .into_iter()
+        .find(|span| span.end == at)
+        .or_else(|| paths(line).into_iter().find(|path| path.start < at && at <= path.end));
+    let stop = match unit {
+        Some(unit) => unit.start,
+        None => line[..at].rfind(|previous| classify(previous) != classify(c)).map_or(0, |i| i + line[i..].chars().next().map_or(1, char::len_utf8)),
+    };
+    offset + stop
+}
+
+impl Editor {
+    /// Move the caret a word forward or back, keeping the anchor to grow
+    /// the selection when `extend`. Returns the new anchor and caret.
+    pub fn step_word(&mut self, forward: bool, extend: bool) -> (usize, usize) {
+        let text = self.buffer.as_str();
+        let cursor = if forward { next_boundary(&text, self.cursor) } else { previous_boundary(&text, self.cursor) };
+        let anchor = if extend { self.anchor } else { cursor };
+        self.sync_selection(anchor, cursor);
+        (self.anchor, self.cursor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The text `word_at` selects at each `|` in `marked`
+    fn selected(marked: &str) -> Option<String> {
+        let pos = marked.find('|').unwrap();
+        let text = marked.replacen('|', "", 1);
+        word_at(&text, pos).map(|range| text[range].to_string())
+    }
+
+    /// Every stop of Ctrl+Right from the start, and of Ctrl+Left from the end
+    fn stops(text: &str) -> (Vec<&str>, Vec<&str>) {
+        let mut forward = Vec::new();
+        let mut pos = 0;
+        while pos < text.len() {
+            let next = next_boundary(text, pos);
+            assert!(next > pos);
+            forward.push(&text[pos..next]);
+            pos = next;
+        }
+        let mut backward = Vec::new();
+        while pos > 0 {
+            let previous = previous_boundary(text, pos);
+            assert!(previous < pos);
+            backward.push(&text[previous..pos]);
+            pos = previous;
+        }
+        (forward, backward)
+    }
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify('a'), CharClass::Word);
+        assert_eq!(classify('é'), CharClass::Word);
+        assert_eq!(classify('7'), CharClass::Word);
+        assert_eq!(classify('_'), CharClass::Word);
+        assert_eq!(classify(' '), CharClass::Space);
+        assert_eq!(classify('\t'), CharClass::Space);
+        assert_eq!(classify('*'), CharClass::Punctuation);
+        assert_eq!(classify('/'), CharClass::Punctuation);
+        assert_eq!(code_spans("a `b` and ``c ` d`` ``` e"), [2..5, 10..19]);
+        assert!(is_path("https://example.com/a?b=c"));
+        assert!(is_path("www.example.com"));
+        assert!(is_path("src/editor/mod.rs"));
+        assert!(is_path(r"C:\notes\today.md"));
+        assert!(is_path("main.rs"));
+        assert!(!is_path("end."));
+        assert!(!is_path("/"));
+        assert!(!is_path("word"));
+    }
+
+    #[test]
+    fn test_word_at() {
+        // Snake case and plain words
+        assert_eq!(selected("call some_sn|ake_case_word now").as_deref(), Some("some_snake_case_word"));
+        assert_eq!(selected("naï|ve café").as_deref(), Some("naïve"));
+        assert_eq!(selected("word|, next").as_deref(), Some("word"));
+        assert_eq!(selected("|word").as_deref(), Some("word"));
+        assert_eq!(selected("a | b"), None);
+        assert_eq!(selected("a *|* b"), None);
+        // Emphasis markers are left out
+        assert_eq!(selected("**bo|ld** text").as_deref(), Some("bold"));
+        assert_eq!(selected("_emph|asis_ text").as_deref(), Some("emphasis"));
+        assert_eq!(selected("__strong_wo|rd__").as_deref(), Some("strong_word"));
+        assert_eq!(selected("~~str|uck~~").as_deref(), Some("struck"));
+        // URLs and paths select whole
+        assert_eq!(selected("see https://exa|mple.com/a/b?c=d.").as_deref(), Some("https://example.com/a/b?c=d"));
+        assert_eq!(selected("edit src/edi|tor/mod.rs, then").as_deref(), Some("src/editor/mod.rs"));
+        assert_eq!(selected("in **docs/gu|ide.md**").as_deref(), Some("docs/guide.md"));
+        assert_eq!(selected("<https://exa|mple.com>").as_deref(), Some("https://example.com"));
+        assert_eq!(selected("open C:\\no|tes\\a.md").as_deref(), Some("C:\\notes\\a.md"));
+        assert_eq!(selected("run main|.rs").as_deref(), Some("main.rs"));
+        // Link text without its brackets, and the destination on its own
+        assert_eq!(selected("a [link te|xt](https://example.com) b").as_deref(), Some("link text"));
+        assert_eq!(selected("[link text](https://exa|mple.com)").as_deref(), Some("https://example.com"));
+        assert_eq!(selected("![An ima|ge](a.png)").as_deref(), Some("An image"));
+        assert_eq!(selected("see [[Wiki Pa|ge]]").as_deref(), Some("Wiki Page"));
+        assert_eq!(selected("[ref|][1]").as_deref(), Some("ref"));
+        assert_eq!(selected("- [ |] task"), None);
+        // Inside code, underscores and brackets are the code's
+        assert_eq!(selected("`__in|it__`").as_deref(), Some("__init__"));
+        assert_eq!(selected("`a[in|dex]`").as_deref(), Some("index"));
+        assert_eq!(selected("`src/ma|in.rs`").as_deref(), Some("src/main.rs"));
+        // Only the line of `pos` counts
+        assert_eq!(selected("[one\ntw|o]").as_deref(), Some("two"));
+    }
+
+    #[test]
+    fn test_boundaries() {
+        let (forward, backward) = stops("Edit `let x = 1;` in src/editor/mod.rs now");
+        assert_eq!(forward, ["Edit", " `let x = 1;`", " in", " src/editor/mod.rs", " now"]);
+        assert_eq!(backward, ["now", "src/editor/mod.rs ", "in ", "`let x = 1;` ", "Edit "]);
+
+        let (forward, backward) = stops("**bold** word, then https://example.com.");
+        assert_eq!(forward, ["**", "bold", "**", " word", ",", " then", " https://example.com", "."]);
+        assert_eq!(backward, [".", "https://example.com", "then ", ", ", "word", "** ", "bold", "**"]);
+
+        // Line ends are a stop of their own
+        let (forward, backward) = stops("one  \ntwo");
+        assert_eq!(forward, ["one", "  ", "\n", "two"]);
+        assert_eq!(backward, ["two", "\n", "one  "]);
+        assert_eq!(previous_boundary("a\r\nb", 3), 1);
+
+        // Inside a code span, words are separate again
+        let text = "`one two`";
+        assert_eq!(next_boundary(text, 1), 4);
+        assert_eq!(previous_boundary(text, 8), 5);
+
+        let mut editor = Editor::new();
+        editor.set_text("one two three");
+        editor.set_selection(4, 4);
+        assert_eq!(editor.step_word(true, true), (4, 7));
+        assert_eq!(editor.step_word(true, false), (13, 13));
+        assert_eq!(editor.step_word(false, false), (8, 8));
+    }
+}
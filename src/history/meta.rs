@@ -0,0 +1,211 @@
+//! Per-document scratch data such as bookmarks, kept in the config folder
+//! rather than next to the document. Only the most recently used documents
+//! keep theirs.
+
+use super::fnv1a;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Documents whose data is kept; saving for another drops the one used
+/// longest ago
+const MAX_DOCUMENTS: usize = 200;
+
+/// What is remembered about a document between sessions
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DocumentMeta {
+    /// Document the data belongs to, for finding it by hand
+    #[serde(default)]
+    pub path: PathBuf,
+    /// Bookmarked lines, zero-based
+    #[serde(default)]
+    pub bookmarks: Vec<usize>,
+    /// How far down the preview was read, from 0 to 1
+    #[serde(default)]
+    pub reading_position: Option<f32>,
+    /// When the data was last saved, in seconds since the Unix epoch
+    #[serde(default)]
+    pub last_used: u64,
+    /// Where the document was left
+    #[serde(default)]
+    pub position: Option<Position>,
+}
+
+/// The selection and how far each pane was scrolled when a document was
+/// left
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    /// Selection anchor and caret, as byte offsets
+    pub anchor: usize,
+    pub cursor: usize,
+    /// Vertical scroll offset of the editor, in points
+    pub editor_scroll: f32,
+    /// How far down the preview was scrolled, from 0 to 1
+    pub preview_scroll: f32,
+}
+
+impl DocumentMeta {
+    fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty() && self.reading_position.is_none() && self.position.is_none()
+    }
+}
+
+/// One `.rmd-meta` file per document, named by a hash of its path
+pub struct MetaStore {
+    root: PathBuf,
+}
+
+impl MetaStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Store under `config_dir/rmd/meta`
+    pub fn open_default() -> anyhow::Result<Self> {
+        Ok(Self::new(crate::config::Config::config_dir()?.join("meta")))
+    }
+
+    fn path_for(&self, file: &Path) -> PathBuf {
+        self.root
+            .join(format!("{:016x}.rmd-meta", fnv1a(file.to_string_lossy().as_bytes())))
+    }
+
+    /// Data for `file`, or empty data if none was saved or it can't be read
+    pub fn load(&self, file: &Path) -> DocumentMeta {
+        std::fs::read_to_string(self.path_for(file))
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    /// Save data for `file`, removing the sidecar once there is nothing left in it
+    pub fn save(&self, file: &Path, meta: &DocumentMeta) -> anyhow::Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.save_at(file, meta, now)
+    }
+
+    /// Save data for `file` as used at `last_used`, then drop what is over
+    /// the limit
+    fn save_at(&self, file: &Path, meta: &DocumentMeta, last_used: u64) -> anyhow::Result<()> {
+        let path = self.path_for(file);
+        if meta.is_empty() {
+            match std::fs::remove_file(&path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => return Ok(()),
+            }
+        }
+        std::fs::create_dir_all(&self.root)?;
+        let meta = DocumentMeta {
+            path: file.to_path_buf(),
+            last_used,
+            ..meta.clone()
+        };
+        std::fs::write(&path, toml::to_string(&meta)?)?;
+        self.prune(&path)
+    }
+
+    /// Remove the sidecars used longest ago, past `MAX_DOCUMENTS`, sparing
+    /// `just_saved`. Ones that can't be read go first.
+    fn prune(&self, just_saved: &Path) -> anyhow::Result<()> {
+        let sidecars: Vec<PathBuf> = std::fs::read_dir(&self.root)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "rmd-meta"))
+            .collect();
+        if sidecars.len() <= MAX_DOCUMENTS {
+            return Ok(());
+        }
+        let mut by_use: Vec<(u64, PathBuf)> = sidecars
+            .into_iter()
+            .filter(|path| path != just_saved)
+            .map(|path| {
+                let last_used = std::fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|contents| toml::from_str::<DocumentMeta>(&contents).ok())
+                    .map_or(0, |meta| meta.last_used);
+                (last_used, path)
+            })
+            .collect();
+        by_use.sort();
+        let excess = by_use.len() + 1 - MAX_DOCUMENTS;
+        for (_, path) in by_use.into_iter().take(excess) {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_load_and_clear() {
+        let root = std::env::temp_dir().join(format!("rmd-meta-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let store = MetaStore::new(&root);
+        let file = Path::new("/docs/notes.md");
+
+        assert_eq!(store.load(file), DocumentMeta::default());
+        let meta = DocumentMeta {
+            bookmarks: vec![2, 40],
+            ..DocumentMeta::default()
+        };
+        store.save(file, &meta).unwrap();
+        assert_eq!(store.load(file).bookmarks, vec![2, 40]);
+        assert_eq!(store.load(file).path, file);
+        assert!(store.load(Path::new("/docs/other.md")).bookmarks.is_empty());
+
+        // A reading position alone keeps the sidecar
+        let meta = DocumentMeta {
+            reading_position: Some(0.5),
+            ..DocumentMeta::default()
+        };
+        store.save(file, &meta).unwrap();
+        assert_eq!(store.load(file).reading_position, Some(0.5));
+        assert!(store.load(file).bookmarks.is_empty());
+
+        store.save(file, &DocumentMeta::default()).unwrap();
+        assert_eq!(store.load(file), DocumentMeta::default());
+        assert_eq!(std::fs::read_dir(&root).unwrap().count(), 0);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_position_round_trip() {
+        let meta = DocumentMeta {
+            path: PathBuf::from("/docs/notes.md"),
+            bookmarks: vec![3],
+            reading_position: None,
+            last_used: 1_700_000_000,
+            position: Some(Position { anchor: 12, cursor: 40, editor_scroll: 320.5, preview_scroll: 0.25 }),
+        };
+        let toml = toml::to_string(&meta).unwrap();
+        assert_eq!(toml::from_str::<DocumentMeta>(&toml).unwrap(), meta);
+        // Sidecars from before positions were kept still load
+        let old: DocumentMeta = toml::from_str("path = \"/docs/notes.md\"\nbookmarks = [3]\n").unwrap();
+        assert_eq!(old.position, None);
+        assert_eq!(old.last_used, 0);
+    }
+
+    #[test]
+    fn test_least_recently_used_are_dropped() {
+        let root = std::env::temp_dir().join(format!("rmd-meta-lru-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let store = MetaStore::new(&root);
+        let meta = DocumentMeta { bookmarks: vec![1], ..DocumentMeta::default() };
+        let file = |i: usize| PathBuf::from(format!("/docs/{}.md", i));
+        for i in 0..MAX_DOCUMENTS {
+            store.save_at(&file(i), &meta, 1000 + i as u64).unwrap();
+        }
+        // Using the oldest again makes the second oldest the one to go
+        store.save_at(&file(0), &meta, 5000).unwrap();
+        store.save_at(&file(MAX_DOCUMENTS), &meta, 5001).unwrap();
+        assert_eq!(std::fs::read_dir(&root).unwrap().count(), MAX_DOCUMENTS);
+        assert_eq!(store.load(&file(0)).bookmarks, [1]);
+        assert_eq!(store.load(&file(1)), DocumentMeta::default());
+        assert_eq!(store.load(&file(2)).bookmarks, [1]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,169 @@
+//! Local snapshots of saved documents, independent of undo history
+
+pub mod meta;
+
+use crate::config::HistoryConfig;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A saved copy of a document
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// Location of the snapshot file
+    pub path: PathBuf,
+    pub timestamp: SystemTime,
+    pub size: u64,
+}
+
+/// Snapshots of every document, stored in one folder per document
+pub struct SnapshotStore {
+    root: PathBuf,
+    config: HistoryConfig,
+}
+
+impl SnapshotStore {
+    pub fn new(root: impl Into<PathBuf>, config: HistoryConfig) -> Self {
+        Self {
+            root: root.into(),
+            config,
+        }
+    }
+
+    /// Store under `config_dir/rmd/history`
+    pub fn open_default(config: HistoryConfig) -> anyhow::Result<Self> {
+        Ok(Self::new(crate::config::Config::config_dir()?.join("history"), config))
+    }
+
+    /// Folder holding the snapshots of `file`
+    fn dir_for(&self, file: &Path) -> PathBuf {
+        self.root.join(format!("{:016x}", fnv1a(file.to_string_lossy().as_bytes())))
+    }
+
+    /// Save `contents` as the newest snapshot of `file`, then prune old ones.
+    /// Nothing is written when the file is too large or unchanged since the
+    /// last snapshot.
+    pub fn record(&self, file: &Path, contents: &str) -> anyhow::Result<()> {
+        if !self.config.enabled || contents.len() as u64 > self.config.max_file_size {
+            return Ok(());
+        }
+        if let Some(latest) = self.list(file).first() {
+            if self.read(latest).ok().as_deref() == Some(contents) {
+                return Ok(());
+            }
+        }
+
+        let dir = self.dir_for(file);
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("source.txt"), file.to_string_lossy().as_bytes())?;
+
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        std::fs::write(dir.join(format!("{}.md", millis)), contents)?;
+        self.prune(file)
+    }
+
+    /// Snapshots of `file`, newest first
+    pub fn list(&self, file: &Path) -> Vec<Snapshot> {
+        let Ok(entries) = std::fs::read_dir(self.dir_for(file)) else {
+            return Vec::new();
+        };
+
+        let mut snapshots: Vec<Snapshot> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let path = entry.path();
+                let millis: u64 = path.file_stem()?.to_str()?.parse().ok()?;
+                Some(Snapshot {
+                    timestamp: UNIX_EPOCH + Duration::from_millis(millis),
+                    size: entry.metadata().ok()?.len(),
+                    path,
+                })
+            })
+            .collect();
+        snapshots.sort_by_key(|s| std::cmp::Reverse(s.timestamp));
+        snapshots
+    }
+
+    pub fn read(&self, snapshot: &Snapshot) -> std::io::Result<String> {
+        std::fs::read_to_string(&snapshot.path)
+    }
+
+    /// Delete the oldest snapshots beyond the count and total size limits
+    fn prune(&self, file: &Path) -> anyhow::Result<()> {
+        let mut total = 0;
+        for (index, snapshot) in self.list(file).iter().enumerate() {
+            total += snapshot.size;
+            // Always keep the newest snapshot
+            let over_limit = index >= self.config.max_snapshots || total > self.config.max_total_size;
+            if index > 0 && over_limit {
+                std::fs::remove_file(&snapshot.path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// FNV-1a, a hash that stays stable across builds for naming folders
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_list_and_prune() {
+        let root = std::env::temp_dir().join(format!("rmd-history-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let config = HistoryConfig {
+            max_snapshots: 3,
+            ..HistoryConfig::default()
+        };
+        let store = SnapshotStore::new(&root, config);
+        let file = Path::new("/docs/notes.md");
+
+        for version in 0..5 {
+            store.record(file, &format!("version {}", version)).unwrap();
+            // Snapshot names have millisecond resolution
+            std::thread::sleep(Duration::from_millis(2));
+        }
+        // Saving unchanged contents does not add a snapshot
+        store.record(file, "version 4").unwrap();
+
+        let snapshots = store.list(file);
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(store.read(&snapshots[0]).unwrap(), "version 4");
+        assert_eq!(store.read(&snapshots[2]).unwrap(), "version 2");
+        assert!(store.list(Path::new("/docs/other.md")).is_empty());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let root = std::env::temp_dir().join(format!("rmd-history-size-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let config = HistoryConfig {
+            max_total_size: 10,
+            max_file_size: 8,
+            ..HistoryConfig::default()
+        };
+        let store = SnapshotStore::new(&root, config);
+        let file = Path::new("/docs/big.md");
+
+        store.record(file, "too large!").unwrap();
+        assert!(store.list(file).is_empty());
+
+        store.record(file, "aaaaaa").unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        store.record(file, "bbbbbb").unwrap();
+        // 12 bytes total exceeds the limit, so only the newest survives
+        let snapshots = store.list(file);
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(store.read(&snapshots[0]).unwrap(), "bbbbbb");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
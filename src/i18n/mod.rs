@@ -0,0 +1,170 @@
+//! Translations of the interface. Each language is a TOML file of strings
+//! built into the binary, with keys grouped into tables: `[menu] file =
+//! "File"` is the key `menu.file`. English is complete and the fallback
+//! for anything another language lacks.
+//!
+//! ```ignore
+//! ui.menu_button(t!("menu.file"), |ui| …);
+//! self.notify_error(t!("error.open_failed", path = path.display()), e);
+//! ```
+//!
+//! `{name}` in a string is replaced by the argument of that name.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+/// Languages shipped: id, name in that language, and strings. English
+/// comes first.
+const SHIPPED: [(&str, &str, &str); 2] = [
+    ("en", "English", include_str!("en.toml")),
+    ("de", "Deutsch", include_str!("de.toml")),
+];
+
+/// A shipped language with its strings by key
+struct Language {
+    id: &'static str,
+    name: &'static str,
+    strings: HashMap<String, String>,
+}
+
+fn languages() -> &'static [Language] {
+    static LANGUAGES: OnceLock<Vec<Language>> = OnceLock::new();
+    LANGUAGES.get_or_init(|| {
+        SHIPPED
+            .iter()
+            .map(|&(id, name, source)| {
+                let mut strings = HashMap::new();
+                match source.parse::<toml::Table>() {
+                    Ok(table) => flatten("", &table, &mut strings),
+                    Err(e) => log::error!("The {} strings don't parse: {}", name, e),
+                }
+                Language { id, name, strings }
+            })
+            .collect()
+    })
+}
+
+/// Add the strings of `table` to `strings`, keyed by their path from the top
+fn flatten(prefix: &str, table: &toml::Table, strings: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match value {
+            toml::Value::String(text) => {
+                strings.insert(key, text.clone());
+            }
+            toml::Value::Table(table) => flatten(&key, table, strings),
+            other => log::warn!("Ignoring {} = {}: not a string", key, other),
+        }
+    }
+}
+
+/// Index of the language in use among `languages()`
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+/// The ids and names of the languages shipped, English first
+pub fn available() -> impl Iterator<Item = (&'static str, &'static str)> {
+    languages().iter().map(|language| (language.id, language.name))
+}
+
+/// Switch the interface to the language `setting` names, or to the
+/// system's when it is empty. Languages not shipped fall back to English.
+pub fn set_language(setting: &str) {
+    let wanted = if setting.is_empty() { system_language() } else { Some(setting.to_string()) };
+    let index = wanted
+        .and_then(|wanted| languages().iter().position(|language| language.id == primary_subtag(&wanted)))
+        .unwrap_or(0);
+    CURRENT.store(index, Ordering::Relaxed);
+}
+
+/// `de` of `de_DE.UTF-8` or `de-AT`
+fn primary_subtag(locale: &str) -> String {
+    locale.split(['_', '-', '.', '@']).next().unwrap_or_default().to_ascii_lowercase()
+}
+
+/// The language the environment asks for, as `LC_ALL`, `LC_MESSAGES` or
+/// `LANG` name it
+fn system_language() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty() && value != "C" && value != "POSIX")
+}
+
+/// The string for `key` in the current language, or in English when that
+/// lacks it
+pub fn lookup(key: &str) -> Option<&'static str> {
+    let languages = languages();
+    let current = &languages[CURRENT.load(Ordering::Relaxed).min(languages.len() - 1)];
+    if let Some(text) = current.strings.get(key) {
+        return Some(text);
+    }
+    note_missing(current.id, key);
+    languages[0].strings.get(key).map(String::as_str)
+}
+
+/// Log each missing key once, rather than every frame it is drawn
+fn note_missing(language: &str, key: &str) {
+    static LOGGED: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+    let Ok(mut logged) = LOGGED.lock() else {
+        return;
+    };
+    if logged.get_or_insert_with(HashSet::new).insert(format!("{}:{}", language, key)) {
+        log::debug!("No {} string for {}", language, key);
+    }
+}
+
+/// The string for `key`, or the key itself when no language has it
+pub fn tr(key: &'static str) -> &'static str {
+    lookup(key).unwrap_or(key)
+}
+
+/// The string for `key` with each `{name}` replaced by its argument
+pub fn tr_args(key: &'static str, args: &[(&str, &dyn Display)]) -> String {
+    let mut text = tr(key).to_string();
+    for (name, value) in args {
+        text = text.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    text
+}
+
+/// The interface string for a key, with `name = value` arguments for the
+/// `{name}` placeholders in it
+macro_rules! t {
+    ($key:expr) => {
+        $crate::i18n::tr($key)
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::tr_args($key, &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+])
+    };
+}
+pub(crate) use t;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keymap::Action;
+
+    #[test]
+    fn test_every_language_is_complete() {
+        let english = &languages()[0];
+        assert!(!english.strings.is_empty());
+        for language in &languages()[1..] {
+            let mut missing: Vec<&String> = english.strings.keys().filter(|key| !language.strings.contains_key(*key)).collect();
+            missing.sort();
+            assert!(missing.is_empty(), "{} lacks {:?}", language.name, missing);
+        }
+        for action in Action::ALL {
+            assert!(english.strings.contains_key(&format!("action.{}", action.id())), "no label for {}", action.id());
+        }
+    }
+
+    #[test]
+    fn test_placeholders_and_fallback() {
+        assert_eq!(primary_subtag("de_DE.UTF-8"), "de");
+        assert_eq!(primary_subtag("en-GB"), "en");
+        assert_eq!(t!("status.position", line = 3, column = 14), "Ln 3, Col 14");
+        assert_eq!(t!("no.such.key"), "no.such.key");
+    }
+}
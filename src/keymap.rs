@@ -0,0 +1,775 @@
+//! Keyboard shortcuts: the actions they trigger, their default chords, and
+//! user overrides from `keybindings.toml`
+
+use eframe::egui::{self, Key, Modifiers};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+/// Something the user can trigger from a shortcut, a menu, or the palette
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Action {
+    NewFile,
+    NewWindow,
+    OpenFile,
+    OpenFolder,
+    Save,
+    SaveAs,
+    ToggleReadonly,
+    FileHistory,
+    ExportHtml,
+    ExportFolderHtml,
+    ExportSelectionHtml,
+    ExportDocx,
+    QuickOpen,
+    GoToHeading,
+    Undo,
+    Redo,
+    Bold,
+    Italic,
+    Find,
+    PasteFromHistory,
+    FindInFolder,
+    Preferences,
+    MoveLineUp,
+    MoveLineDown,
+    DuplicateLineUp,
+    DuplicateLineDown,
+    DeleteLines,
+    PromoteHeading,
+    DemoteHeading,
+    ToggleSplit,
+    ToggleSidebar,
+    ToggleChanges,
+    ToggleScratchpad,
+    ToggleDiagnostics,
+    ShowShortcuts,
+    CommandPalette,
+    PinPreview,
+    OpenInBrowser,
+    FocusEditor,
+    FocusPreview,
+    FocusFiles,
+    FocusOutline,
+    FocusBookmarks,
+    FocusNotifications,
+    FocusSearch,
+    FocusProblems,
+    FocusScratchpad,
+    FocusNextPane,
+    FocusPreviousPane,
+    ToggleTheme,
+    InsertToc,
+    UpdateToc,
+    RenameHeading,
+    InsertFigureList,
+    UpdateFigureList,
+    InsertImage,
+    ToggleBookmark,
+    NextBookmark,
+    PreviousBookmark,
+    TransformUpper,
+    TransformLower,
+    TransformTitleCase,
+    SortLinesAscending,
+    SortLinesDescending,
+    RemoveDuplicateLines,
+    JoinLines,
+    ToggleComment,
+    LinksToReferences,
+    InlineReferenceLinks,
+    CheckLinks,
+    SplitDocument,
+    MergeFiles,
+    ConsolidateImages,
+    CheckForUpdates,
+    HardWrap,
+    UnwrapParagraph,
+    FormatTable,
+    RenumberLists,
+    CopySection,
+    SelectWordOrNext,
+    SelectLine,
+    ExpandSelection,
+}
+
+/// Where a shortcut is listened for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scope {
+    /// Anywhere in the window
+    Global,
+    /// Only while the editor has keyboard focus
+    Editor,
+}
+
+impl Action {
+    pub const ALL: [Action; 82] = [
+        Action::NewFile,
+        Action::NewWindow,
+        Action::OpenFile,
+        Action::OpenFolder,
+        Action::Save,
+        Action::SaveAs,
+        Action::ToggleReadonly,
+        Action::FileHistory,
+        Action::ExportHtml,
+        Action::ExportFolderHtml,
+        Action::ExportSelectionHtml,
+        Action::ExportDocx,
+        Action::QuickOpen,
+        Action::GoToHeading,
+        Action::Undo,
+        Action::Redo,
+        Action::Bold,
+        Action::Italic,
+        Action::Find,
+        Action::PasteFromHistory,
+        Action::FindInFolder,
+        Action::Preferences,
+        Action::MoveLineUp,
+        Action::MoveLineDown,
+        Action::DuplicateLineUp,
+        Action::DuplicateLineDown,
+        Action::DeleteLines,
+        Action::PromoteHeading,
+        Action::DemoteHeading,
+        Action::ToggleSplit,
+        Action::ToggleSidebar,
+        Action::ToggleChanges,
+        Action::ToggleScratchpad,
+        Action::ToggleDiagnostics,
+        Action::ShowShortcuts,
+        Action::CommandPalette,
+        Action::PinPreview,
+        Action::OpenInBrowser,
+        Action::FocusEditor,
+        Action::FocusPreview,
+        Action::FocusFiles,
+        Action::FocusOutline,
+        Action::FocusBookmarks,
+        Action::FocusNotifications,
+        Action::FocusSearch,
+        Action::FocusProblems,
+        Action::FocusScratchpad,
+        Action::FocusNextPane,
+        Action::FocusPreviousPane,
+        Action::ToggleTheme,
+        Action::InsertToc,
+        Action::UpdateToc,
+        Action::RenameHeading,
+        Action::InsertFigureList,
+        Action::UpdateFigureList,
+        Action::InsertImage,
+        Action::ToggleBookmark,
+        Action::NextBookmark,
+        Action::PreviousBookmark,
+        Action::TransformUpper,
+        Action::TransformLower,
+        Action::TransformTitleCase,
+        Action::SortLinesAscending,
+        Action::SortLinesDescending,
+        Action::RemoveDuplicateLines,
+        Action::JoinLines,
+        Action::ToggleComment,
+        Action::LinksToReferences,
+        Action::InlineReferenceLinks,
+        Action::CheckLinks,
+        Action::SplitDocument,
+        Action::MergeFiles,
+        Action::ConsolidateImages,
+        Action::CheckForUpdates,
+        Action::HardWrap,
+        Action::UnwrapParagraph,
+        Action::FormatTable,
+        Action::RenumberLists,
+        Action::CopySection,
+        Action::SelectWordOrNext,
+        Action::SelectLine,
+        Action::ExpandSelection,
+    ];
+
+    /// Identifier used in `keybindings.toml`
+    pub fn id(self) -> &'static str {
+        match self {
+            Action::NewFile => "new_file",
+            Action::NewWindow => "new_window",
+            Action::OpenFile => "open_file",
+            Action::OpenFolder => "open_folder",
+            Action::Save => "save",
+            Action::SaveAs => "save_as",
+            Action::ToggleReadonly => "toggle_readonly",
+            Action::FileHistory => "file_history",
+            Action::ExportHtml => "export_html",
+            Action::ExportFolderHtml => "export_folder_html",
+            Action::ExportSelectionHtml => "export_selection_html",
+            Action::ExportDocx => "export_docx",
+            Action::QuickOpen => "quick_open",
+            Action::GoToHeading => "go_to_heading",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+            Action::Bold => "bold",
+            Action::Italic => "italic",
+            Action::Find => "find",
+            Action::PasteFromHistory => "paste_from_history",
+            Action::FindInFolder => "find_in_folder",
+            Action::Preferences => "preferences",
+            Action::MoveLineUp => "move_line_up",
+            Action::MoveLineDown => "move_line_down",
+            Action::DuplicateLineUp => "duplicate_line_up",
+            Action::DuplicateLineDown => "duplicate_line_down",
+            Action::DeleteLines => "delete_lines",
+            Action::PromoteHeading => "promote_heading",
+            Action::DemoteHeading => "demote_heading",
+            Action::ToggleSplit => "toggle_split",
+            Action::ToggleSidebar => "toggle_sidebar",
+            Action::ToggleChanges => "toggle_changes",
+            Action::ToggleScratchpad => "toggle_scratchpad",
+            Action::ToggleDiagnostics => "toggle_diagnostics",
+            Action::ShowShortcuts => "show_shortcuts",
+            Action::CommandPalette => "command_palette",
+            Action::PinPreview => "pin_preview",
+            Action::OpenInBrowser => "open_in_browser",
+            Action::FocusEditor => "focus_editor",
+            Action::FocusPreview => "focus_preview",
+            Action::FocusFiles => "focus_files",
+            Action::FocusOutline => "focus_outline",
+            Action::FocusBookmarks => "focus_bookmarks",
+            Action::FocusNotifications => "focus_notifications",
+            Action::FocusSearch => "focus_search",
+            Action::FocusProblems => "focus_problems",
+            Action::FocusScratchpad => "focus_scratchpad",
+            Action::FocusNextPane => "focus_next_pane",
+            Action::FocusPreviousPane => "focus_previous_pane",
+            Action::ToggleTheme => "toggle_theme",
+            Action::InsertToc => "insert_toc",
+            Action::UpdateToc => "update_toc",
+            Action::RenameHeading => "rename_heading",
+            Action::InsertFigureList => "insert_figure_list",
+            Action::UpdateFigureList => "update_figure_list",
+            Action::InsertImage => "insert_image",
+            Action::ToggleBookmark => "toggle_bookmark",
+            Action::NextBookmark => "next_bookmark",
+            Action::PreviousBookmark => "previous_bookmark",
+            Action::TransformUpper => "transform_upper",
+            Action::TransformLower => "transform_lower",
+            Action::TransformTitleCase => "transform_title_case",
+            Action::SortLinesAscending => "sort_lines_ascending",
+            Action::SortLinesDescending => "sort_lines_descending",
+            Action::RemoveDuplicateLines => "remove_duplicate_lines",
+            Action::JoinLines => "join_lines",
+            Action::ToggleComment => "toggle_comment",
+            Action::LinksToReferences => "links_to_references",
+            Action::InlineReferenceLinks => "inline_reference_links",
+            Action::CheckLinks => "check_links",
+            Action::SplitDocument => "split_document",
+            Action::MergeFiles => "merge_files",
+            Action::ConsolidateImages => "consolidate_images",
+            Action::CheckForUpdates => "check_for_updates",
+            Action::HardWrap => "hard_wrap",
+            Action::UnwrapParagraph => "unwrap_paragraph",
+            Action::FormatTable => "format_table",
+            Action::RenumberLists => "renumber_lists",
+            Action::CopySection => "copy_section",
+            Action::SelectWordOrNext => "select_word_or_next",
+            Action::SelectLine => "select_line",
+            Action::ExpandSelection => "expand_selection",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.id() == id)
+    }
+
+    /// Name shown in menus, the help dialog, and the command palette, in
+    /// the interface language
+    pub fn label(self) -> &'static str {
+        crate::i18n::lookup(&format!("action.{}", self.id())).unwrap_or(self.id())
+    }
+
+    /// Heading the action is grouped under in the help dialog
+    pub fn category(self) -> &'static str {
+        match self {
+            Action::NewFile
+            | Action::OpenFile
+            | Action::OpenFolder
+            | Action::Save
+            | Action::SaveAs
+            | Action::ToggleReadonly
+            | Action::FileHistory
+            | Action::ExportFolderHtml
+            | Action::QuickOpen
+            | Action::ExportSelectionHtml
+            | Action::ExportHtml
+            | Action::ExportDocx
+            | Action::NewWindow => "File",
+            Action::Undo
+            | Action::Redo
+            | Action::Bold
+            | Action::Italic
+            | Action::FindInFolder
+            | Action::Preferences
+            | Action::ToggleBookmark
+            | Action::NextBookmark
+            | Action::PreviousBookmark
+            | Action::HardWrap
+            | Action::UnwrapParagraph
+            | Action::CopySection
+            | Action::SelectWordOrNext
+            | Action::SelectLine
+            | Action::ExpandSelection
+            | Action::GoToHeading
+            | Action::Find
+            | Action::PasteFromHistory
+            | Action::RenameHeading => "Edit",
+            Action::MoveLineUp
+            | Action::MoveLineDown
+            | Action::DuplicateLineUp
+            | Action::DuplicateLineDown
+            | Action::PromoteHeading
+            | Action::DemoteHeading
+            | Action::DeleteLines
+            | Action::FormatTable
+            | Action::RenumberLists => "Structure",
+            Action::ToggleSplit
+            | Action::ToggleSidebar
+            | Action::ToggleChanges
+            | Action::ShowShortcuts
+            | Action::CommandPalette
+            | Action::PinPreview
+            | Action::ToggleTheme
+            | Action::OpenInBrowser
+            | Action::FocusEditor
+            | Action::FocusPreview
+            | Action::FocusFiles
+            | Action::FocusOutline
+            | Action::FocusBookmarks
+            | Action::FocusNotifications
+            | Action::FocusSearch
+            | Action::FocusProblems
+            | Action::FocusScratchpad
+            | Action::FocusNextPane
+            | Action::FocusPreviousPane
+            | Action::ToggleScratchpad
+            | Action::ToggleDiagnostics => "View",
+            Action::InsertToc
+            | Action::UpdateToc
+            | Action::InsertFigureList
+            | Action::UpdateFigureList
+            | Action::InsertImage => "Insert",
+            Action::TransformUpper
+            | Action::TransformLower
+            | Action::TransformTitleCase
+            | Action::SortLinesAscending
+            | Action::SortLinesDescending
+            | Action::RemoveDuplicateLines
+            | Action::JoinLines
+            | Action::ToggleComment
+            | Action::LinksToReferences
+            | Action::InlineReferenceLinks => "Transform",
+            Action::CheckLinks
+            | Action::CheckForUpdates
+            | Action::SplitDocument
+            | Action::MergeFiles
+            | Action::ConsolidateImages => "Tools",
+        }
+    }
+
+    pub fn scope(self) -> Scope {
+        match self {
+            Action::Undo
+            | Action::Redo
+            | Action::Bold
+            | Action::Italic
+            | Action::MoveLineUp
+            | Action::MoveLineDown
+            | Action::DuplicateLineUp
+            | Action::DuplicateLineDown
+            | Action::PromoteHeading
+            | Action::DemoteHeading
+            | Action::InsertToc
+            | Action::UpdateToc
+            | Action::TransformUpper
+            | Action::TransformLower
+            | Action::TransformTitleCase
+            | Action::SortLinesAscending
+            | Action::SortLinesDescending
+            | Action::RemoveDuplicateLines
+            | Action::JoinLines
+            | Action::ToggleComment
+            | Action::HardWrap
+            | Action::UnwrapParagraph
+            | Action::LinksToReferences
+            | Action::InlineReferenceLinks
+            | Action::DeleteLines
+            | Action::SelectWordOrNext
+            | Action::SelectLine
+            | Action::ExpandSelection
+            | Action::InsertFigureList
+            | Action::UpdateFigureList
+            | Action::FormatTable
+            | Action::RenumberLists
+            | Action::PasteFromHistory
+            | Action::RenameHeading => Scope::Editor,
+            _ => Scope::Global,
+        }
+    }
+
+    /// Whether the action edits the document, so is off in read-only mode
+    pub fn edits_text(self) -> bool {
+        self.scope() == Scope::Editor
+            && !matches!(self, Action::SelectWordOrNext | Action::SelectLine | Action::ExpandSelection)
+    }
+
+    fn default_chord(self) -> Option<KeyChord> {
+        let ctrl = Modifiers::COMMAND;
+        let ctrl_shift = Modifiers::COMMAND.plus(Modifiers::SHIFT);
+        let ctrl_alt = Modifiers::COMMAND.plus(Modifiers::ALT);
+        let alt_shift = Modifiers::ALT.plus(Modifiers::SHIFT);
+        let (modifiers, key) = match self {
+            Action::NewFile => (ctrl, Key::N),
+            Action::OpenFile => (ctrl, Key::O),
+            Action::Save => (ctrl, Key::S),
+            Action::SaveAs => (ctrl_shift, Key::S),
+            Action::ToggleReadonly => (ctrl_shift, Key::L),
+            Action::Undo => (ctrl, Key::Z),
+            Action::Redo => (ctrl, Key::Y),
+            Action::Bold => (ctrl, Key::B),
+            Action::Italic => (ctrl, Key::I),
+            Action::FindInFolder => (ctrl_shift, Key::F),
+            Action::Preferences => (ctrl, Key::Comma),
+            Action::MoveLineUp => (ctrl_shift, Key::ArrowUp),
+            Action::MoveLineDown => (ctrl_shift, Key::ArrowDown),
+            Action::DuplicateLineUp => (alt_shift, Key::ArrowUp),
+            Action::DuplicateLineDown => (alt_shift, Key::ArrowDown),
+            Action::PromoteHeading => (ctrl_shift, Key::Period),
+            Action::DemoteHeading => (ctrl_shift, Key::Comma),
+            Action::ToggleSplit => (ctrl, Key::Backslash),
+            Action::ToggleSidebar => (ctrl_shift, Key::E),
+            Action::ShowShortcuts => (Modifiers::NONE, Key::F1),
+            Action::CommandPalette => (ctrl_shift, Key::P),
+            Action::ToggleBookmark => (ctrl, Key::F2),
+            Action::NextBookmark => (Modifiers::NONE, Key::F2),
+            Action::PreviousBookmark => (Modifiers::SHIFT, Key::F2),
+            Action::HardWrap => (Modifiers::ALT, Key::Q),
+            Action::QuickOpen => (ctrl, Key::P),
+            Action::ToggleTheme => (ctrl_shift, Key::D),
+            Action::DeleteLines => (ctrl_shift, Key::K),
+            Action::SelectWordOrNext => (ctrl, Key::D),
+            Action::SelectLine => (ctrl, Key::L),
+            Action::ExpandSelection => (alt_shift, Key::ArrowRight),
+            Action::GoToHeading => (ctrl_shift, Key::O),
+            Action::OpenInBrowser => (ctrl_shift, Key::B),
+            Action::FocusEditor => (ctrl, Key::Num1),
+            Action::FocusPreview => (ctrl, Key::Num2),
+            Action::FocusFiles => (ctrl, Key::Num3),
+            Action::FocusOutline => (ctrl, Key::Num4),
+            Action::FocusBookmarks => (ctrl, Key::Num5),
+            Action::FocusNotifications => (ctrl, Key::Num6),
+            Action::FocusSearch => (ctrl, Key::Num7),
+            Action::FocusProblems => (ctrl, Key::Num8),
+            Action::FocusScratchpad => (ctrl, Key::Num9),
+            Action::FocusNextPane => (Modifiers::NONE, Key::F6),
+            Action::FocusPreviousPane => (Modifiers::SHIFT, Key::F6),
+            Action::NewWindow => (ctrl_shift, Key::N),
+            Action::Find => (ctrl, Key::F),
+            Action::PasteFromHistory => (ctrl_shift, Key::V),
+            Action::ToggleScratchpad => (ctrl_alt, Key::N),
+            Action::ToggleDiagnostics => (ctrl_alt, Key::D),
+            Action::OpenFolder
+            | Action::FileHistory
+            | Action::ToggleChanges
+            | Action::InsertToc
+            | Action::UpdateToc
+            | Action::TransformUpper
+            | Action::TransformLower
+            | Action::TransformTitleCase
+            | Action::SortLinesAscending
+            | Action::SortLinesDescending
+            | Action::RemoveDuplicateLines
+            | Action::JoinLines
+            | Action::ToggleComment
+            | Action::ExportFolderHtml
+            | Action::UnwrapParagraph
+            | Action::CheckLinks
+            | Action::PinPreview
+            | Action::ExportSelectionHtml
+            | Action::CopySection
+            | Action::InsertImage
+            | Action::LinksToReferences
+            | Action::InlineReferenceLinks
+            | Action::ExportHtml
+            | Action::ExportDocx
+            | Action::InsertFigureList
+            | Action::UpdateFigureList
+            | Action::FormatTable
+            | Action::RenumberLists
+            | Action::CheckForUpdates
+            | Action::SplitDocument
+            | Action::MergeFiles
+            | Action::ConsolidateImages
+            | Action::RenameHeading => return None,
+        };
+        Some(KeyChord { modifiers, key })
+    }
+}
+
+/// A key with the exact modifiers that must be held, e.g. `Ctrl+Shift+P`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyChord {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl KeyChord {
+    /// A chord as reported by a key event, with Ctrl/Cmd folded into the
+    /// platform command modifier
+    pub fn new(modifiers: Modifiers, key: Key) -> Self {
+        let command = modifiers.command || modifiers.ctrl || modifiers.mac_cmd;
+        let mut modifiers = Modifiers {
+            alt: modifiers.alt,
+            shift: modifiers.shift,
+            ..Modifiers::NONE
+        };
+        if command {
+            modifiers = modifiers.plus(Modifiers::COMMAND);
+        }
+        KeyChord { modifiers, key }
+    }
+
+    /// Parse `Ctrl+Shift+P`, `Alt+Up`, `Ctrl++`, ... (case-insensitive modifiers)
+    pub fn parse(text: &str) -> Option<KeyChord> {
+        let text = text.trim();
+        let (modifier_part, key_name) = match text.strip_suffix("++") {
+            Some(rest) => (rest, "+"),
+            None => match text.rsplit_once('+') {
+                Some((modifiers, key)) => (modifiers, key),
+                None => ("", text),
+            },
+        };
+
+        let mut modifiers = Modifiers::NONE;
+        for name in modifier_part.split('+').filter(|name| !name.is_empty()) {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "ctrl" | "cmd" | "command" => modifiers = modifiers.plus(Modifiers::COMMAND),
+                "alt" | "option" => modifiers = modifiers.plus(Modifiers::ALT),
+                "shift" => modifiers = modifiers.plus(Modifiers::SHIFT),
+                _ => return None,
+            }
+        }
+        let key = Key::from_name(key_name.trim())?;
+        Some(KeyChord { modifiers, key })
+    }
+
+    /// Whether `modifiers` + `key` is exactly this chord
+    pub fn matches(&self, key: Key, modifiers: Modifiers) -> bool {
+        self.key == key && modifiers.matches_exact(self.modifiers)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.command || self.modifiers.ctrl || self.modifiers.mac_cmd {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "Shift+")?;
+        }
+        match self.key {
+            Key::Period | Key::Comma | Key::Backslash | Key::Slash | Key::Minus | Key::Plus | Key::Equals => {
+                write!(f, "{}", self.key.symbol_or_name())
+            }
+            key => write!(f, "{}", key.name()),
+        }
+    }
+}
+
+/// Shortcut table: every action with the chord bound to it, if any
+pub struct Keymap {
+    bindings: BTreeMap<Action, Option<KeyChord>>,
+}
+
+impl Keymap {
+    /// Default shortcuts only
+    pub fn new() -> Self {
+        let bindings = Action::ALL
+            .into_iter()
+            .map(|action| (action, action.default_chord()))
+            .collect();
+        Self { bindings }
+    }
+
+    /// Default shortcuts overridden by the user's `keybindings.toml`
+    pub fn load_or_default() -> Self {
+        let mut keymap = Self::new();
+        match crate::config::Config::config_dir() {
+            Ok(dir) => {
+                if let Err(e) = keymap.merge_file(&dir.join("keybindings.toml")) {
+                    log::warn!("Failed to load keybindings: {}", e);
+                }
+            }
+            Err(e) => log::warn!("Failed to locate keybindings: {}", e),
+        }
+        for (chord, actions) in keymap.conflicts() {
+            let ids: Vec<&str> = actions.iter().map(|action| action.id()).collect();
+            log::warn!("Shortcut {} is bound to more than one action: {}", chord, ids.join(", "));
+        }
+        keymap
+    }
+
+    /// Apply `action = "Chord"` overrides from a TOML file; an empty string unbinds
+    pub fn merge_file(&mut self, path: &Path) -> anyhow::Result<()> {
+        if !path.exists() {
+            return Ok(());
+        }
+        let contents = std::fs::read_to_string(path)?;
+        let overrides: BTreeMap<String, String> = toml::from_str(&contents)?;
+        for (id, chord) in overrides {
+            let Some(action) = Action::from_id(&id) else {
+                log::warn!("Unknown action in keybindings: {}", id);
+                continue;
+            };
+            if chord.trim().is_empty() {
+                self.bindings.insert(action, None);
+            } else if let Some(chord) = KeyChord::parse(&chord) {
+                self.bindings.insert(action, Some(chord));
+            } else {
+                log::warn!("Invalid shortcut for {}: {:?}", id, chord);
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the bindings that differ from the defaults to `keybindings.toml`
+    pub fn save(&self) -> anyhow::Result<()> {
+        let dir = crate::config::Config::config_dir()?;
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("keybindings.toml"), self.overrides_toml()?)?;
+        Ok(())
+    }
+
+    fn overrides_toml(&self) -> anyhow::Result<String> {
+        let overrides: BTreeMap<&str, String> = self
+            .bindings
+            .iter()
+            .filter(|(action, chord)| **chord != action.default_chord())
+            .map(|(action, chord)| (action.id(), chord.map(|c| c.to_string()).unwrap_or_default()))
+            .collect();
+        Ok(toml::to_string(&overrides)?)
+    }
+
+    pub fn chord(&self, action: Action) -> Option<KeyChord> {
+        self.bindings.get(&action).copied().flatten()
+    }
+
+    pub fn set(&mut self, action: Action, chord: Option<KeyChord>) {
+        self.bindings.insert(action, chord);
+    }
+
+    pub fn is_default(&self, action: Action) -> bool {
+        self.chord(action) == action.default_chord()
+    }
+
+    pub fn reset(&mut self, action: Action) {
+        self.bindings.insert(action, action.default_chord());
+    }
+
+    /// The chord for `action` as text, or an empty string when unbound
+    pub fn shortcut_text(&self, action: Action) -> String {
+        self.chord(action).map(|chord| chord.to_string()).unwrap_or_default()
+    }
+
+    /// Chords bound to more than one action
+    pub fn conflicts(&self) -> Vec<(KeyChord, Vec<Action>)> {
+        let mut conflicts: Vec<(KeyChord, Vec<Action>)> = Vec::new();
+        for (&action, chord) in &self.bindings {
+            let Some(chord) = *chord else {
+                continue;
+            };
+            match conflicts.iter_mut().find(|(c, _)| *c == chord) {
+                Some((_, actions)) => actions.push(action),
+                None => conflicts.push((chord, vec![action])),
+            }
+        }
+        conflicts.retain(|(_, actions)| actions.len() > 1);
+        conflicts
+    }
+
+    /// Take the first key press this frame that triggers an action in `scope`
+    pub fn consume(&self, input: &mut egui::InputState, scope: Scope) -> Option<Action> {
+        let mut triggered = None;
+        input.events.retain(|event| {
+            let egui::Event::Key { key, pressed: true, modifiers, .. } = event else {
+                return true;
+            };
+            if triggered.is_some() {
+                return true;
+            }
+            triggered = self
+                .bindings
+                .iter()
+                .filter(|(action, _)| action.scope() == scope)
+                .find(|(_, chord)| chord.is_some_and(|chord| chord.matches(*key, *modifiers)))
+                .map(|(&action, _)| action);
+            triggered.is_none()
+        });
+        triggered
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chord() {
+        let chord = KeyChord::parse("Ctrl+Shift+P").unwrap();
+        assert_eq!(chord.modifiers, Modifiers::COMMAND.plus(Modifiers::SHIFT));
+        assert_eq!(chord.key, Key::P);
+        assert_eq!(chord.to_string(), "Ctrl+Shift+P");
+        assert_eq!(KeyChord::parse("ctrl++").unwrap().key, Key::Plus);
+        assert_eq!(KeyChord::parse("Alt+Up").unwrap().to_string(), "Alt+Up");
+        assert_eq!(KeyChord::parse("Ctrl+Shift+.").unwrap().to_string(), "Ctrl+Shift+.");
+        assert!(KeyChord::parse("Hyper+P").is_none());
+        assert!(KeyChord::parse("Ctrl+NotAKey").is_none());
+        assert_eq!(KeyChord::new(Modifiers::CTRL, Key::P), KeyChord::parse("Ctrl+P").unwrap());
+    }
+
+    #[test]
+    fn test_overrides_and_conflicts() {
+        let path = std::env::temp_dir().join(format!("rmd-keybindings-{}.toml", std::process::id()));
+        std::fs::write(&path, "save = \"Ctrl+Shift+L\"\nredo = \"\"\nnot_an_action = \"Ctrl+K\"\n").unwrap();
+
+        let mut keymap = Keymap::new();
+        assert!(keymap.conflicts().is_empty());
+        keymap.merge_file(&path).unwrap();
+        assert_eq!(keymap.shortcut_text(Action::Save), "Ctrl+Shift+L");
+        assert_eq!(keymap.chord(Action::Redo), None);
+        assert_eq!(
+            keymap.conflicts(),
+            vec![(KeyChord::parse("Ctrl+Shift+L").unwrap(), vec![Action::Save, Action::ToggleReadonly])]
+        );
+
+        let toml = keymap.overrides_toml().unwrap();
+        assert!(toml.contains("save = \"Ctrl+Shift+L\""));
+        assert!(toml.contains("redo = \"\""));
+        assert!(!toml.contains("undo"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_ids_round_trip() {
+        for action in Action::ALL {
+            assert_eq!(Action::from_id(action.id()), Some(action));
+        }
+    }
+}
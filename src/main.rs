@@ -4,27 +4,75 @@
 mod app;
 mod config;
 mod editor;
+mod history;
+mod i18n;
+mod keymap;
 mod markdown;
+mod platform;
 mod preview;
+mod startup;
+mod stats;
 mod theme;
 mod ui;
+mod update;
 mod utils;
+mod workspace;
+
+#[cfg(test)]
+#[path = "../tests/commonmark/harness.rs"]
+mod commonmark;
 
 use eframe::NativeOptions;
+use startup::StartupLoad;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Command-line arguments: `rmd [--readonly] [--watch] [--debug-hud] [FILE]`, or
+/// `rmd --new [FOLDER]` from File → New Window
+#[derive(Default)]
+struct Args {
+    file: Option<PathBuf>,
+    /// Started by File → New Window: an empty document, in the other
+    /// window's folder if it had one open
+    new_window: bool,
+    readonly: bool,
+    /// Reload the file whenever it changes on disk
+    watch: bool,
+    /// Start with the performance overlay shown
+    debug_hud: bool,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let mut args = Args::default();
+        for arg in std::env::args().skip(1) {
+            match arg.as_str() {
+                "--new" => args.new_window = true,
+                "--readonly" => args.readonly = true,
+                "--watch" => args.watch = true,
+                "--debug-hud" => args.debug_hud = true,
+                _ if arg.starts_with("--") => eprintln!("Unknown option: {}", arg),
+                _ => args.file = Some(PathBuf::from(arg)),
+            }
+        }
+        args
+    }
+}
 
 fn main() -> eframe::Result {
+    let started = Instant::now();
     // Initialize logging
     env_logger::init();
 
-    // Load configuration
-    let config = config::Config::load_or_default();
+    let args = Args::parse();
+    utils::timing::set_enabled(args.debug_hud);
 
     // Create native options with custom window settings
     let native_options = NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1400.0, 900.0])
-            .with_min_inner_size([800.0, 600.0])
-            .with_title("RMD - Markdown Editor"),
+            .with_inner_size(ui::scale::INITIAL_SIZE)
+            .with_min_inner_size(ui::scale::MIN_SIZE)
+            .with_title("Untitled — RMD"),
         ..Default::default()
     };
 
@@ -32,6 +80,21 @@ fn main() -> eframe::Result {
     eframe::run_native(
         "RMD",
         native_options,
-        Box::new(|cc| Ok(Box::new(app::RmdApp::new(cc, config)))),
+        Box::new(move |cc| {
+            // The window opens with the defaults; the settings and the
+            // document follow from a background thread
+            let mut app = app::RmdApp::new(cc, config::Config::default());
+            let document = args.file.clone().filter(|_| !args.new_window);
+            let mut startup = StartupLoad::spawn(started, document.clone());
+            if args.new_window {
+                startup.workspace = args.file.clone();
+            }
+            // Files that are read-only on disk stay locked even without the flag
+            startup.readonly = args.readonly;
+            app.loading_document = document;
+            app.startup = Some(startup);
+            app.watch_mode = args.watch;
+            Ok(Box::new(app))
+        }),
     )
 }
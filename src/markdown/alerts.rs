@@ -0,0 +1,115 @@
+//! GitHub alerts: block quotes whose first line is a marker such as
+//! `[!NOTE]` or `[!WARNING]`, shown as callouts rather than quotes. Any
+//! other `[!...]` leaves the quote a quote.
+
+use super::{ElementKind, RenderedElement};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlertKind {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+}
+
+impl AlertKind {
+    pub const ALL: [AlertKind; 5] = [AlertKind::Note, AlertKind::Tip, AlertKind::Important, AlertKind::Warning, AlertKind::Caution];
+
+    /// Name in the marker and in the exported class, lowercase
+    pub fn id(self) -> &'static str {
+        match self {
+            AlertKind::Note => "note",
+            AlertKind::Tip => "tip",
+            AlertKind::Important => "important",
+            AlertKind::Warning => "warning",
+            AlertKind::Caution => "caution",
+        }
+    }
+
+    /// Title of exported alerts, which GitHub shows in English
+    pub fn title(self) -> &'static str {
+        match self {
+            AlertKind::Note => "Note",
+            AlertKind::Tip => "Tip",
+            AlertKind::Important => "Important",
+            AlertKind::Warning => "Warning",
+            AlertKind::Caution => "Caution",
+        }
+    }
+
+    pub fn icon(self) -> &'static str {
+        match self {
+            AlertKind::Note => "ℹ",
+            AlertKind::Tip => "💡",
+            AlertKind::Important => "❗",
+            AlertKind::Warning => "⚠",
+            AlertKind::Caution => "🛑",
+        }
+    }
+}
+
+/// The alert `text` starts with, and the length of its marker. The marker
+/// must be followed by whitespace or nothing.
+pub fn parse_marker(text: &str) -> Option<(AlertKind, usize)> {
+    let name = text.strip_prefix("[!")?;
+    let end = name.find(']')?;
+    let len = 2 + end + 1;
+    if !text[len..].chars().next().is_none_or(char::is_whitespace) {
+        return None;
+    }
+    let kind = AlertKind::ALL.into_iter().find(|kind| kind.id().eq_ignore_ascii_case(&name[..end]))?;
+    Some((kind, len))
+}
+
+/// The alert of the block quote whose source is `quote`, with the length
+/// of its marker, from the quote's first line
+pub fn quote_alert(quote: &str) -> Option<(AlertKind, usize)> {
+    let line = quote.lines().next()?.trim_start().strip_prefix('>')?;
+    parse_marker(line.trim_start())
+}
+
+/// Take the marker off the first text of a block quote's `items`, giving
+/// the alert it names. The text is dropped when the marker was all of it.
+pub(super) fn take_marker(items: &mut Vec<RenderedElement>) -> Option<AlertKind> {
+    let Some(RenderedElement { kind: ElementKind::Paragraph(text), .. }) = items.first_mut() else {
+        return None;
+    };
+    let (kind, len) = parse_marker(text)?;
+    let rest = text[len..].trim_start();
+    if rest.is_empty() {
+        items.remove(0);
+        if matches!(items.first(), Some(RenderedElement { kind: ElementKind::LineBreak, .. })) {
+            items.remove(0);
+        }
+    } else {
+        *text = rest.to_string();
+    }
+    Some(kind)
+}
+
+/// Start of an exported alert, in GitHub's markup
+pub fn html_start(kind: AlertKind) -> String {
+    format!(
+        "<div class=\"markdown-alert markdown-alert-{}\">\n<p class=\"markdown-alert-title\">{}</p>\n",
+        kind.id(),
+        kind.title()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_marker() {
+        assert_eq!(parse_marker("[!NOTE]"), Some((AlertKind::Note, 7)));
+        assert_eq!(parse_marker("[!warning] Careful"), Some((AlertKind::Warning, 10)));
+        assert_eq!(parse_marker("[!FOO]"), None);
+        assert_eq!(parse_marker("[!TIP]s"), None);
+        assert_eq!(parse_marker("Note [!TIP]"), None);
+        assert_eq!(quote_alert("> [!CAUTION]\n> Hot"), Some((AlertKind::Caution, 10)));
+        // The marker of a nested quote isn't the outer quote's
+        assert_eq!(quote_alert("> > [!TIP]\n"), None);
+    }
+}
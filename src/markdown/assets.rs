@@ -0,0 +1,273 @@
+//! Gathering the images a document shows into the `assets` folder next to
+//! it: local files are copied, web images optionally downloaded, and files
+//! with the same contents stored once. The document's links are rewritten
+//! separately, so the files can be gathered away from the editor.
+
+use super::embed;
+use super::flavor::{self, Flavor};
+use super::linkcheck::{relative_link, url_range};
+use crate::utils::sanitize_filename;
+use crate::workspace::files::unique_path;
+use crate::workspace::links::normalize;
+use anyhow::Context;
+use pulldown_cmark::{Event, Parser, Tag};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Folder next to the document that images are copied into
+pub const ASSETS_FOLDER: &str = "assets";
+
+/// Largest image downloaded
+const MAX_DOWNLOAD: u64 = 32 * 1024 * 1024;
+
+/// How long to wait for a web server to send an image
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where an image of the document is now
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImageLocation {
+    /// A file elsewhere on disk
+    Local(PathBuf),
+    /// Already in the assets folder
+    InAssets(PathBuf),
+    /// A file that doesn't exist
+    Missing(PathBuf),
+    /// A web address
+    Remote,
+}
+
+/// An image the document shows, once per URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentImage {
+    /// The URL as written in the document
+    pub url: String,
+    pub location: ImageLocation,
+}
+
+/// What gathering did with an image
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    /// Copied or downloaded, or found already there, at this URL
+    Moved(String),
+    /// Left alone as its file is missing
+    Skipped,
+    /// Copying or downloading failed, and why
+    Failed(String),
+}
+
+/// The images of `markdown`, saved in `folder`, each URL once. `data:`
+/// URIs and other schemes are left out.
+pub fn document_images(markdown: &str, flavor: Flavor, folder: &Path) -> Vec<DocumentImage> {
+    let flavor = flavor::front_matter_flavor(markdown).unwrap_or(flavor);
+    let assets = normalize(&folder.join(ASSETS_FOLDER));
+    let mut images: Vec<DocumentImage> = Vec::new();
+    for event in Parser::new_ext(markdown, flavor.options()) {
+        let Event::Start(Tag::Image { dest_url, .. }) = event else {
+            continue;
+        };
+        if images.iter().any(|image| image.url == *dest_url) {
+            continue;
+        }
+        let location = if dest_url.starts_with("http://") || dest_url.starts_with("https://") {
+            ImageLocation::Remote
+        } else {
+            let Some(path) = embed::resolve(&dest_url, Some(folder)) else {
+                continue;
+            };
+            let path = normalize(&path);
+            if !path.is_file() {
+                ImageLocation::Missing(path)
+            } else if path.parent() == Some(assets.as_path()) {
+                ImageLocation::InAssets(path)
+            } else {
+                ImageLocation::Local(path)
+            }
+        };
+        images.push(DocumentImage { url: dest_url.to_string(), location });
+    }
+    images
+}
+
+/// Files of the assets folder by the hash of their contents
+struct Store {
+    folder: PathBuf,
+    files: HashMap<u64, Vec<PathBuf>>,
+}
+
+fn content_hash(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Store {
+    fn open(folder: PathBuf) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&folder)?;
+        let mut store = Self { folder, files: HashMap::new() };
+        for entry in std::fs::read_dir(&store.folder)?.filter_map(Result::ok) {
+            let path = entry.path();
+            if let Ok(bytes) = std::fs::read(&path) {
+                store.files.entry(content_hash(&bytes)).or_default().push(path);
+            }
+        }
+        Ok(store)
+    }
+
+    /// The file holding `bytes`, written as `name` unless a file with the
+    /// same contents is already there
+    fn put(&mut self, name: &str, bytes: &[u8]) -> std::io::Result<PathBuf> {
+        let hash = content_hash(bytes);
+        let same = self.files.get(&hash).into_iter().flatten().find(|path| std::fs::read(path).is_ok_and(|existing| existing == bytes));
+        if let Some(path) = same {
+            return Ok(path.clone());
+        }
+        let name = sanitize_filename(name);
+        let name = if name.trim().is_empty() { "image".to_string() } else { name };
+        let path = unique_path(&self.folder, &name);
+        std::fs::write(&path, bytes)?;
+        self.files.entry(hash).or_default().push(path.clone());
+        Ok(path)
+    }
+}
+
+/// Copy `images` into the assets folder of the document saved in `folder`,
+/// downloading the web ones too when `download` is set. Images already
+/// there, and web ones not downloaded, have no outcome.
+pub fn gather(images: &[DocumentImage], folder: &Path, download: bool) -> Vec<(String, Outcome)> {
+    let mut store = match Store::open(folder.join(ASSETS_FOLDER)) {
+        Ok(store) => store,
+        Err(e) => return images.iter().map(|image| (image.url.clone(), Outcome::Failed(e.to_string()))).collect(),
+    };
+    let agent = ureq::AgentBuilder::new().timeout(DOWNLOAD_TIMEOUT).redirects(5).build();
+    let mut outcomes = Vec::new();
+    for image in images {
+        let stored = match &image.location {
+            ImageLocation::InAssets(_) => continue,
+            ImageLocation::Remote if !download => continue,
+            ImageLocation::Missing(_) => {
+                outcomes.push((image.url.clone(), Outcome::Skipped));
+                continue;
+            }
+            ImageLocation::Local(path) => std::fs::read(path).map_err(anyhow::Error::from).and_then(|bytes| {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                Ok(store.put(&name, &bytes)?)
+            }),
+            ImageLocation::Remote => fetch(&agent, &image.url).and_then(|(name, bytes)| Ok(store.put(&name, &bytes)?)),
+        };
+        let outcome = match stored {
+            Ok(path) => Outcome::Moved(relative_link(folder, &path)),
+            Err(e) => Outcome::Failed(format!("{:#}", e)),
+        };
+        outcomes.push((image.url.clone(), outcome));
+    }
+    outcomes
+}
+
+/// The image at `url` with a file name for it, from the URL's last segment
+/// and the type the server gives when that has no extension
+fn fetch(agent: &ureq::Agent, url: &str) -> anyhow::Result<(String, Vec<u8>)> {
+    let response = agent.get(url).call().with_context(|| format!("downloading {}", url))?;
+    let extension = match response.content_type() {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/svg+xml" => "svg",
+        "image/webp" => "webp",
+        "image/bmp" => "bmp",
+        "image/avif" => "avif",
+        _ => "",
+    };
+    let segment = url.split(['?', '#']).next().unwrap_or_default().rsplit('/').next().unwrap_or_default();
+    let stem = segment.replace("%20", " ");
+    let name = match (Path::new(&stem).extension(), extension) {
+        (None, extension) if !extension.is_empty() => format!("{}.{}", if stem.is_empty() { "image" } else { &stem }, extension),
+        _ => stem,
+    };
+    let mut bytes = Vec::new();
+    response.into_reader().take(MAX_DOWNLOAD + 1).read_to_end(&mut bytes)?;
+    anyhow::ensure!(bytes.len() as u64 <= MAX_DOWNLOAD, "larger than {} MB", MAX_DOWNLOAD / (1024 * 1024));
+    Ok((name, bytes))
+}
+
+/// `markdown` with the URL of each image in `moved` replaced by where it
+/// was moved to, titles and all else left as they are. Returns `None` when
+/// there is nothing to change.
+pub fn rewrite_images(markdown: &str, flavor: Flavor, moved: &HashMap<String, String>) -> Option<String> {
+    let flavor = flavor::front_matter_flavor(markdown).unwrap_or(flavor);
+    let mut edits: Vec<(Range<usize>, &str)> = Vec::new();
+    for (event, range) in Parser::new_ext(markdown, flavor.options()).into_offset_iter() {
+        let Event::Start(Tag::Image { dest_url, .. }) = event else {
+            continue;
+        };
+        let Some(new_url) = moved.get(&*dest_url) else {
+            continue;
+        };
+        // Images sharing a reference definition rewrite it once
+        if let Some(url_range) = url_range(markdown, &range, &dest_url).filter(|r| !edits.iter().any(|(e, _)| e == r)) {
+            edits.push((url_range, new_url));
+        }
+    }
+    if edits.is_empty() {
+        return None;
+    }
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    let mut text = markdown.to_string();
+    for (range, new_url) in edits {
+        text.replace_range(range, new_url);
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gather() {
+        let root = std::env::temp_dir().join(format!("rmd-assets-{}", std::process::id()));
+        let folder = root.join("doc");
+        std::fs::create_dir_all(root.join("elsewhere")).unwrap();
+        std::fs::create_dir_all(folder.join(ASSETS_FOLDER)).unwrap();
+        std::fs::write(root.join("elsewhere/cat.png"), b"meow").unwrap();
+        std::fs::write(root.join("copy.png"), b"meow").unwrap();
+        std::fs::write(folder.join("assets/dog.png"), b"woof").unwrap();
+        std::fs::write(folder.join("assets/cat.png"), b"other").unwrap();
+
+        let markdown = "![cat](../elsewhere/cat.png \"A cat\") and ![copy][c], ![dog](assets/dog.png)\n\
+                        ![gone](gone.png) ![web](https://example.com/x.png)\n\n[c]: ../copy.png\n";
+        let images = document_images(markdown, Flavor::Gfm, &folder);
+        assert_eq!(images.len(), 5);
+        assert_eq!(images[2].location, ImageLocation::InAssets(folder.join("assets/dog.png")));
+        assert_eq!(images[4].location, ImageLocation::Remote);
+
+        // The same contents are stored once, beside a different file of the same name
+        let outcomes = gather(&images, &folder, false);
+        assert_eq!(
+            outcomes,
+            [
+                ("../elsewhere/cat.png".to_string(), Outcome::Moved("assets/cat-1.png".to_string())),
+                ("../copy.png".to_string(), Outcome::Moved("assets/cat-1.png".to_string())),
+                ("gone.png".to_string(), Outcome::Skipped),
+            ]
+        );
+
+        let moved: HashMap<String, String> = outcomes
+            .into_iter()
+            .filter_map(|(url, outcome)| match outcome {
+                Outcome::Moved(new_url) => Some((url, new_url)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            rewrite_images(markdown, Flavor::Gfm, &moved).unwrap(),
+            "![cat](assets/cat-1.png \"A cat\") and ![copy][c], ![dog](assets/dog.png)\n\
+             ![gone](gone.png) ![web](https://example.com/x.png)\n\n[c]: assets/cat-1.png\n"
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+//! Bare URLs and email addresses that render as links, as in GFM
+
+use std::ops::Range;
+
+/// A bare URL or email address found in text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Autolink {
+    /// Byte range of the link text
+    pub range: Range<usize>,
+    /// Destination, with a scheme added for `www.` and email links
+    pub url: String,
+}
+
+const URL_PREFIXES: [&str; 3] = ["https://", "http://", "www."];
+
+/// Find bare `http(s)://` and `www.` URLs and email addresses in `text`.
+/// Trailing punctuation and unbalanced closing parentheses are left out.
+pub fn find_autolinks(text: &str) -> Vec<Autolink> {
+    let mut links = Vec::new();
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        let starts_word = text[..i]
+            .chars()
+            .next_back()
+            .is_none_or(|c| c.is_whitespace() || "*_~(\"'".contains(c));
+        let prefix = URL_PREFIXES.iter().find(|prefix| {
+            rest.get(..prefix.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+        });
+
+        if let Some(prefix) = prefix.filter(|_| starts_word) {
+            let end = rest.find(|c: char| c.is_whitespace() || c == '<').unwrap_or(rest.len());
+            let candidate = trim_trailing(&rest[..end]);
+            let host = candidate.get(prefix.len()..).unwrap_or("").split(['/', '?', '#']).next().unwrap_or("");
+            if host.chars().any(char::is_alphanumeric) && (*prefix != "www." || host.contains('.')) {
+                let url = if *prefix == "www." {
+                    format!("http://{}", candidate)
+                } else {
+                    candidate.to_string()
+                };
+                links.push(Autolink { range: i..i + candidate.len(), url });
+                i += candidate.len();
+                continue;
+            }
+        }
+        i += rest.chars().next().map_or(1, char::len_utf8);
+    }
+
+    for (at, _) in text.match_indices('@') {
+        if links.iter().any(|link| link.range.contains(&at)) {
+            continue;
+        }
+        let start = text[..at]
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || ".+-_".contains(c)))
+            .map_or(0, |index| index + text[index..].chars().next().map_or(1, char::len_utf8));
+        let domain_len = text[at + 1..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || ".-_".contains(c)))
+            .unwrap_or(text.len() - at - 1);
+        let domain = text[at + 1..at + 1 + domain_len].trim_end_matches('.');
+        let valid_domain = domain.contains('.')
+            && !domain.starts_with('.')
+            && domain.ends_with(|c: char| c.is_ascii_alphanumeric());
+        if start < at && valid_domain {
+            let range = start..at + 1 + domain.len();
+            let url = format!("mailto:{}", &text[range.clone()]);
+            links.push(Autolink { range, url });
+        }
+    }
+
+    links.sort_by_key(|link| link.range.start);
+    links
+}
+
+/// Drop punctuation that more likely ends the sentence than the URL
+fn trim_trailing(mut url: &str) -> &str {
+    loop {
+        let Some(last) = url.chars().next_back() else {
+            return url;
+        };
+        let unbalanced_paren = last == ')' && url.matches(')').count() > url.matches('(').count();
+        if "?!.,:;*_~'\"".contains(last) || unbalanced_paren {
+            url = &url[..url.len() - 1];
+        } else {
+            return url;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(text: &str) -> Vec<(&str, String)> {
+        find_autolinks(text)
+            .into_iter()
+            .map(|link| (&text[link.range], link.url))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_urls() {
+        assert_eq!(
+            found("Visit https://example.com."),
+            vec![("https://example.com", "https://example.com".to_string())]
+        );
+        assert_eq!(
+            found("(see https://x.y)"),
+            vec![("https://x.y", "https://x.y".to_string())]
+        );
+        assert_eq!(
+            found("https://en.wikipedia.org/wiki/Rust_(language), ok"),
+            vec![(
+                "https://en.wikipedia.org/wiki/Rust_(language)",
+                "https://en.wikipedia.org/wiki/Rust_(language)".to_string()
+            )]
+        );
+        assert_eq!(
+            found("go to www.rust-lang.org!"),
+            vec![("www.rust-lang.org", "http://www.rust-lang.org".to_string())]
+        );
+        assert!(found("nohttps://x.y and https:// and www.").is_empty());
+    }
+
+    #[test]
+    fn test_find_emails() {
+        assert_eq!(
+            found("Mail me@example.com."),
+            vec![("me@example.com", "mailto:me@example.com".to_string())]
+        );
+        assert_eq!(
+            found("https://user@host.com and a.b+c@d.org"),
+            vec![
+                ("https://user@host.com", "https://user@host.com".to_string()),
+                ("a.b+c@d.org", "mailto:a.b+c@d.org".to_string()),
+            ]
+        );
+        assert!(found("@handle and user@localhost").is_empty());
+    }
+
+    #[test]
+    fn test_find_emails_after_non_ascii() {
+        // The local part stops at the accented letter rather than inside it
+        assert!(found("café@example.com").is_empty());
+        assert_eq!(
+            found("日本 jo@example.jp"),
+            vec![("jo@example.jp", "mailto:jo@example.jp".to_string())]
+        );
+        assert_eq!(
+            found("Grüße—ana@example.org"),
+            vec![("ana@example.org", "mailto:ana@example.org".to_string())]
+        );
+    }
+}
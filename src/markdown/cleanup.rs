@@ -0,0 +1,206 @@
+//! Tidying the whitespace of a document as it is saved
+
+use super::fence_marker;
+
+/// Which cleanups to make, or which of them changed something
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Cleanup {
+    /// Trim spaces and tabs at line ends, except inside fenced code and
+    /// two-space hard breaks
+    pub trim_trailing_whitespace: bool,
+    /// End the document with exactly one line break
+    pub final_newline: bool,
+    /// Collapse three or more blank lines in a row into one
+    pub collapse_blank_lines: bool,
+}
+
+impl Cleanup {
+    pub fn any(self) -> bool {
+        self.trim_trailing_whitespace || self.final_newline || self.collapse_blank_lines
+    }
+
+    /// What was done, such as "trimmed trailing whitespace and fixed the
+    /// final newline"
+    pub fn describe(self) -> String {
+        let done: Vec<&str> = [
+            (self.trim_trailing_whitespace, "trimmed trailing whitespace"),
+            (self.final_newline, "fixed the final newline"),
+            (self.collapse_blank_lines, "collapsed blank lines"),
+        ]
+        .into_iter()
+        .filter_map(|(done, what)| done.then_some(what))
+        .collect();
+        match done.split_last() {
+            None => String::new(),
+            Some((last, [])) => last.to_string(),
+            Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+        }
+    }
+}
+
+struct Line<'a> {
+    /// The line without its line break
+    body: &'a str,
+    ending: &'a str,
+    /// Inside a fenced code block, where whitespace is content
+    code: bool,
+    /// Opens or closes one
+    fence: bool,
+}
+
+impl Line<'_> {
+    fn is_blank(&self) -> bool {
+        !self.code && self.body.trim().is_empty()
+    }
+}
+
+/// `markdown` with the cleanups of `cleanup` made, and which of them
+/// changed it
+pub fn clean(markdown: &str, cleanup: Cleanup) -> (String, Cleanup) {
+    let mut fence: Option<(char, usize)> = None;
+    let lines: Vec<Line> = markdown
+        .split_inclusive('\n')
+        .map(|raw| {
+            let body = raw.strip_suffix('\n').map_or(raw, |body| body.strip_suffix('\r').unwrap_or(body));
+            let code = fence.is_some();
+            let marker = fence_marker(body);
+            if let Some((marker, len)) = marker {
+                match fence {
+                    None => fence = Some((marker, len)),
+                    Some((open, open_len)) if open == marker && len >= open_len => fence = None,
+                    Some(_) => {}
+                }
+            }
+            // The fence lines themselves aren't code
+            let fence_line = marker.is_some() && (!code || fence.is_none());
+            Line { body, ending: &raw[body.len()..], code: code && !fence_line, fence: fence_line }
+        })
+        .collect();
+    let newline = lines.iter().map(|line| line.ending).find(|ending| !ending.is_empty()).unwrap_or("\n");
+    let mut done = Cleanup::default();
+
+    // Lines kept at the end: up to the last one with text
+    let mut end = lines.len();
+    if cleanup.final_newline {
+        end = lines.iter().rposition(|line| !line.is_blank()).map_or(0, |last| last + 1);
+        done.final_newline = end < lines.len() || lines.get(end.wrapping_sub(1)).is_some_and(|last| last.ending != newline);
+    }
+
+    // Blank lines dropped from runs of three or more
+    let mut skip = vec![false; end];
+    if cleanup.collapse_blank_lines {
+        let mut start = 0;
+        while start < end {
+            if !lines[start].is_blank() {
+                start += 1;
+                continue;
+            }
+            let run_end = (start..end).find(|&index| !lines[index].is_blank()).unwrap_or(end);
+            if run_end - start >= 3 {
+                skip[start + 1..run_end].fill(true);
+                done.collapse_blank_lines = true;
+            }
+            start = run_end;
+        }
+    }
+
+    let mut output = String::with_capacity(markdown.len());
+    for (index, line) in lines[..end].iter().enumerate() {
+        if skip[index] {
+            continue;
+        }
+        let mut body = line.body;
+        if cleanup.trim_trailing_whitespace && !line.code {
+            let trimmed = body.trim_end_matches([' ', '\t']);
+            // Two spaces before the line break end the line there, unless
+            // nothing follows in the paragraph
+            let next_is_text = lines.get(index + 1).is_some_and(|next| !next.is_blank() && !next.code && !next.fence);
+            let hard_break = !trimmed.is_empty() && body.ends_with("  ") && next_is_text && !line.fence;
+            if trimmed.len() != body.len() && !hard_break {
+                body = trimmed;
+                done.trim_trailing_whitespace = true;
+            }
+        }
+        output.push_str(body);
+        if cleanup.final_newline && index + 1 == end {
+            output.push_str(newline);
+        } else {
+            output.push_str(line.ending);
+        }
+    }
+    (output, done)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIM: Cleanup = Cleanup { trim_trailing_whitespace: true, final_newline: false, collapse_blank_lines: false };
+    const NEWLINE: Cleanup = Cleanup { trim_trailing_whitespace: false, final_newline: true, collapse_blank_lines: false };
+    const COLLAPSE: Cleanup = Cleanup { trim_trailing_whitespace: false, final_newline: false, collapse_blank_lines: true };
+    const ALL: Cleanup = Cleanup { trim_trailing_whitespace: true, final_newline: true, collapse_blank_lines: true };
+
+    #[test]
+    fn test_trim_keeps_hard_breaks() {
+        let (text, done) = clean("One  \ntwo \t\nthree   \nfour\n\nLast  \n", TRIM);
+        // Two or more spaces before more text in the paragraph stay; at the
+        // end of a paragraph or after a tab they are only whitespace
+        assert_eq!(text, "One  \ntwo\nthree   \nfour\n\nLast\n");
+        assert!(done.trim_trailing_whitespace);
+
+        assert_eq!(clean("One  \n   \nTwo", TRIM).0, "One\n\nTwo");
+        assert_eq!(clean("One  \r\nTwo  \r\n", TRIM).0, "One  \r\nTwo\r\n");
+        // A single space is never a hard break
+        assert_eq!(clean("One \nTwo", TRIM).0, "One\nTwo");
+        // Nor are spaces on their own
+        assert_eq!(clean("  \nTwo", TRIM).0, "\nTwo");
+
+        let (text, done) = clean("Tidy  \ntext\n", TRIM);
+        assert_eq!(text, "Tidy  \ntext\n");
+        assert!(!done.any());
+    }
+
+    #[test]
+    fn test_trim_skips_fenced_code() {
+        let text = "Text \n\n```py  \nx = 1   \n\n   \n```\nAfter \n";
+        assert_eq!(clean(text, TRIM).0, "Text\n\n```py\nx = 1   \n\n   \n```\nAfter\n");
+        // A fence ends the paragraph, so there is no break to keep
+        assert_eq!(clean("Text  \n```\ncode\n```\n", TRIM).0, "Text\n```\ncode\n```\n");
+        // An unclosed fence runs to the end
+        assert_eq!(clean("~~~\ncode  \n", TRIM).0, "~~~\ncode  \n");
+    }
+
+    #[test]
+    fn test_final_newline() {
+        assert_eq!(clean("Text", NEWLINE), ("Text\n".to_string(), NEWLINE));
+        assert_eq!(clean("Text\n\n\n  \n", NEWLINE), ("Text\n".to_string(), NEWLINE));
+        assert_eq!(clean("A\r\nText", NEWLINE).0, "A\r\nText\r\n");
+        // Trailing spaces on the last line are left to the trim option
+        assert_eq!(clean("Text  ", NEWLINE).0, "Text  \n");
+        assert_eq!(clean("Text\n", NEWLINE), ("Text\n".to_string(), Cleanup::default()));
+        assert_eq!(clean("", NEWLINE), (String::new(), Cleanup::default()));
+        assert_eq!(clean("\n\n", NEWLINE).0, "");
+        // Blank lines closing a code block are part of it
+        assert_eq!(clean("```\ncode\n\n", NEWLINE).0, "```\ncode\n\n");
+    }
+
+    #[test]
+    fn test_collapse_blank_lines() {
+        assert_eq!(clean("A\n\n\nB\n", COLLAPSE), ("A\n\n\nB\n".to_string(), Cleanup::default()));
+        assert_eq!(clean("A\n\n \n\t\n\nB\n", COLLAPSE), ("A\n\nB\n".to_string(), COLLAPSE));
+        assert_eq!(clean("```\n\n\n\n\n```\n", COLLAPSE).0, "```\n\n\n\n\n```\n");
+        // Blank lines at the end go with the final newline, not here
+        assert_eq!(clean("A\n\n\n\n", COLLAPSE).0, "A\n\n");
+    }
+
+    #[test]
+    fn test_all_together() {
+        let text = "# Title  \n\n\n\n\nLine one  \nline two \n\n```\nkeep  \n```\n\n\n";
+        let (cleaned, done) = clean(text, ALL);
+        assert_eq!(cleaned, "# Title\n\nLine one  \nline two\n\n```\nkeep  \n```\n");
+        assert_eq!(done, ALL);
+        assert_eq!(done.describe(), "trimmed trailing whitespace, fixed the final newline and collapsed blank lines");
+        assert_eq!(clean(&cleaned, ALL), (cleaned.clone(), Cleanup::default()));
+        assert_eq!(NEWLINE.describe(), "fixed the final newline");
+    }
+}
@@ -0,0 +1,340 @@
+//! Color literals in text — `#ff8800`, `rgb(24, 100, 200)`, `hsl(30 100% 50%)`
+//! — for showing swatches beside them, and writing a color back in the way
+//! the literal was written
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Which literals count as colors. Code is taken at its word; prose is
+/// full of things like issue references (`#123`) and hashtags (`#fad`),
+/// so there a literal has to look more like a color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColorRules {
+    /// `rgb()`, `rgba()`, `hsl()` and `hsla()`
+    #[serde(default = "default_true")]
+    pub functions: bool,
+    /// Three and four digit hex like `#f80` outside code
+    #[serde(default)]
+    pub short_hex_in_prose: bool,
+    /// Hex with no letters like `#123456` outside code
+    #[serde(default)]
+    pub numeric_hex_in_prose: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for ColorRules {
+    fn default() -> Self {
+        Self { functions: true, short_hex_in_prose: false, numeric_hex_in_prose: false }
+    }
+}
+
+impl ColorRules {
+    /// Whether `literal`, found in code, also counts as a color in prose
+    pub fn allows_in_prose(&self, literal: &ColorLiteral) -> bool {
+        let Format::Hex { digits, .. } = literal.format else {
+            return true;
+        };
+        let hex = &literal.text[1..];
+        (self.short_hex_in_prose || digits > 4) && (self.numeric_hex_in_prose || hex.bytes().any(|b| b.is_ascii_alphabetic()))
+    }
+}
+
+/// How a color literal was written, to write a new color the same way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`
+    Hex { digits: usize, uppercase: bool },
+    /// `rgb()` or `hsl()`, possibly with `a`: whether `hsl`, whether its
+    /// name is uppercase, whether it has an alpha, whether its values are
+    /// separated by commas, and for `rgb` whether channels are percentages
+    Function { hsl: bool, uppercase: bool, alpha: bool, commas: bool, percent: bool },
+}
+
+impl Format {
+    /// Whether colors written this way have an alpha
+    pub fn has_alpha(self) -> bool {
+        match self {
+            Format::Hex { digits, .. } => digits == 4 || digits == 8,
+            Format::Function { alpha, .. } => alpha,
+        }
+    }
+
+    /// `rgba` written this way. Short hex is kept where the color fits it.
+    pub fn write(self, rgba: [u8; 4]) -> String {
+        match self {
+            Format::Hex { digits, uppercase } => {
+                let channels = if self.has_alpha() { &rgba[..] } else { &rgba[..3] };
+                let short = digits <= 4 && channels.iter().all(|c| c >> 4 == c & 0xf);
+                let hex: String = if short {
+                    channels.iter().map(|c| format!("{:x}", c & 0xf)).collect()
+                } else {
+                    channels.iter().map(|c| format!("{:02x}", c)).collect()
+                };
+                let hex = if uppercase { hex.to_uppercase() } else { hex };
+                format!("#{}", hex)
+            }
+            Format::Function { hsl, uppercase, alpha, commas, percent } => {
+                let mut values: Vec<String> = if hsl {
+                    let (h, s, l) = rgb_to_hsl(rgba);
+                    vec![format!("{}", h.round() as u32 % 360), format!("{}%", s.round()), format!("{}%", l.round())]
+                } else if percent {
+                    rgba[..3].iter().map(|&c| format!("{}%", (c as f32 / 2.55).round())).collect()
+                } else {
+                    rgba[..3].iter().map(u8::to_string).collect()
+                };
+                let name = if hsl { "hsl" } else { "rgb" };
+                let name = if uppercase { name.to_uppercase() } else { name.to_string() };
+                let alpha_value = format!("{}", (rgba[3] as f32 / 255.0 * 100.0).round() / 100.0);
+                if !commas {
+                    let alpha = if alpha { format!(" / {}", alpha_value) } else { String::new() };
+                    return format!("{}({}{})", name, values.join(" "), alpha);
+                }
+                if alpha {
+                    values.push(alpha_value);
+                }
+                let a = if alpha { if uppercase { "A" } else { "a" } } else { "" };
+                format!("{}{}({})", name, a, values.join(", "))
+            }
+        }
+    }
+}
+
+/// A color written in the text
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorLiteral {
+    /// Byte range of the literal
+    pub range: Range<usize>,
+    pub text: String,
+    /// Red, green, blue and alpha
+    pub rgba: [u8; 4],
+    pub format: Format,
+}
+
+/// Color literals of `text`, as code when `code`, or else as prose
+pub fn find_colors(text: &str, rules: &ColorRules, code: bool) -> Vec<ColorLiteral> {
+    let bytes = text.as_bytes();
+    let mut found = Vec::new();
+    let mut at = 0;
+    while at < text.len() {
+        // Literals start after something that isn't part of a word, or an
+        // HTML entity like `&#123;`
+        let boundary = at == 0 || !(bytes[at - 1].is_ascii_alphanumeric() || matches!(bytes[at - 1], b'_' | b'&' | b'#'));
+        let literal = if !boundary {
+            None
+        } else if bytes[at] == b'#' {
+            hex(text, at)
+        } else if rules.functions && bytes[at].is_ascii_alphabetic() {
+            function(text, at)
+        } else {
+            None
+        };
+        match literal.filter(|literal| code || rules.allows_in_prose(literal)) {
+            Some(literal) => {
+                at = literal.range.end;
+                found.push(literal);
+            }
+            None => at += text[at..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    found
+}
+
+fn hex(text: &str, start: usize) -> Option<ColorLiteral> {
+    let rest = &text[start + 1..];
+    let len = rest.bytes().take_while(|b| b.is_ascii_alphanumeric() || *b == b'_').count();
+    let hex = &rest[..len];
+    if !matches!(len, 3 | 4 | 6 | 8) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) || rest[len..].starts_with('-') {
+        return None;
+    }
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).unwrap_or(0);
+    let mut rgba = [0, 0, 0, 255];
+    for (i, channel) in rgba.iter_mut().enumerate().take(len / if len <= 4 { 1 } else { 2 }) {
+        *channel = if len <= 4 { digit(i) * 17 } else { digit(2 * i) * 16 + digit(2 * i + 1) };
+    }
+    let uppercase = hex.bytes().any(|b| b.is_ascii_uppercase());
+    Some(ColorLiteral {
+        range: start..start + 1 + len,
+        text: text[start..start + 1 + len].to_string(),
+        rgba,
+        format: Format::Hex { digits: len, uppercase },
+    })
+}
+
+fn function(text: &str, start: usize) -> Option<ColorLiteral> {
+    let rest = &text[start..];
+    let name_len = rest.bytes().take_while(u8::is_ascii_alphabetic).count();
+    let name = &rest[..name_len];
+    let (hsl, named_alpha) = match name.len() {
+        3 | 4 if name[..3].eq_ignore_ascii_case("rgb") => (false, name.len() == 4),
+        3 | 4 if name[..3].eq_ignore_ascii_case("hsl") => (true, name.len() == 4),
+        _ => return None,
+    };
+    if name.len() == 4 && !name[3..].eq_ignore_ascii_case("a") {
+        return None;
+    }
+    let args_end = rest[name_len..].strip_prefix('(')?.find(')')?;
+    let args = &rest[name_len + 1..name_len + 1 + args_end];
+    if args.len() > 64 || args.contains('\n') {
+        return None;
+    }
+
+    let commas = args.contains(',');
+    let (values, alpha) = if commas {
+        let mut values: Vec<&str> = args.split(',').map(str::trim).collect();
+        let alpha = (values.len() == 4).then(|| values.pop()).flatten();
+        (values, alpha)
+    } else {
+        let (values, alpha) = args.split_once('/').map_or((args, None), |(values, alpha)| (values, Some(alpha.trim())));
+        (values.split_whitespace().collect(), alpha)
+    };
+    if values.len() != 3 || (commas && named_alpha != alpha.is_some()) {
+        return None;
+    }
+    let alpha_value = match alpha {
+        Some(alpha) => match alpha.strip_suffix('%') {
+            Some(percent) => percent.parse::<f32>().ok()? / 100.0,
+            None => alpha.parse::<f32>().ok()?,
+        },
+        None => 1.0,
+    };
+
+    let percent = values.iter().all(|value| value.ends_with('%'));
+    let rgb = if hsl {
+        let hue: f32 = values[0].trim_end_matches("deg").parse().ok()?;
+        let saturation: f32 = values[1].strip_suffix('%')?.parse().ok()?;
+        let lightness: f32 = values[2].strip_suffix('%')?.parse().ok()?;
+        hsl_to_rgb(hue, saturation, lightness)
+    } else {
+        let mut rgb = [0u8; 3];
+        for (channel, value) in rgb.iter_mut().zip(&values) {
+            let value: f32 = match value.strip_suffix('%') {
+                Some(percent) => percent.parse::<f32>().ok()? * 2.55,
+                None => value.parse().ok()?,
+            };
+            *channel = value.round().clamp(0.0, 255.0) as u8;
+        }
+        rgb
+    };
+    let end = start + name_len + 1 + args_end + 1;
+    Some(ColorLiteral {
+        range: start..end,
+        text: text[start..end].to_string(),
+        rgba: [rgb[0], rgb[1], rgb[2], (alpha_value.clamp(0.0, 1.0) * 255.0).round() as u8],
+        format: Format::Function {
+            hsl,
+            uppercase: name.bytes().all(|b| b.is_ascii_uppercase()),
+            alpha: alpha.is_some(),
+            commas,
+            percent: !hsl && percent,
+        },
+    })
+}
+
+/// Hue in degrees, saturation and lightness in percent, to red, green and blue
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> [u8; 3] {
+    let s = (saturation / 100.0).clamp(0.0, 1.0);
+    let l = (lightness / 100.0).clamp(0.0, 1.0);
+    let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+    let h = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = l - chroma / 2.0;
+    [r, g, b].map(|c| ((c + m) * 255.0).round().clamp(0.0, 255.0) as u8)
+}
+
+/// Red, green and blue to hue in degrees, saturation and lightness in percent
+fn rgb_to_hsl(rgba: [u8; 4]) -> (f32, f32, f32) {
+    let [r, g, b] = [rgba[0], rgba[1], rgba[2]].map(|c| c as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let l = (max + min) / 2.0;
+    let delta = max - min;
+    if delta == 0.0 {
+        return (0.0, 0.0, l * 100.0);
+    }
+    let s = delta / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    (h, s * 100.0, l * 100.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colors(text: &str, code: bool) -> Vec<(String, [u8; 4])> {
+        find_colors(text, &ColorRules::default(), code).into_iter().map(|c| (c.text, c.rgba)).collect()
+    }
+
+    /// Colors expected in a case, with their RGBA
+    type Expected = &'static [(&'static str, [u8; 4])];
+
+    #[test]
+    fn test_find_colors() {
+        let cases: [(&str, bool, Expected); 12] = [
+            ("color: #ff8800;", true, &[("#ff8800", [255, 136, 0, 255])]),
+            ("#F80 and #f808", true, &[("#F80", [255, 136, 0, 255]), ("#f808", [255, 136, 0, 136])]),
+            ("rgb(24, 100, 200)", true, &[("rgb(24, 100, 200)", [24, 100, 200, 255])]),
+            ("RGBA(0,0,0,0.5)", true, &[("RGBA(0,0,0,0.5)", [0, 0, 0, 128])]),
+            ("rgb(100% 0% 50% / 25%)", true, &[("rgb(100% 0% 50% / 25%)", [255, 0, 128, 64])]),
+            ("hsl(30, 100%, 50%)", true, &[("hsl(30, 100%, 50%)", [255, 128, 0, 255])]),
+            ("hsla(120deg 100% 25% / 1)", true, &[("hsla(120deg 100% 25% / 1)", [0, 128, 0, 255])]),
+            // Too long, inside a word, an entity, or not hex at all
+            ("#deadbeefcafe a#fff &#123; #ggg #fff-1 rgb(1, 2)", true, &[]),
+            // Short and all-digit hex are colors in code only
+            ("See #123 and #fad", false, &[]),
+            ("#123 #fad", true, &[("#123", [17, 34, 51, 255]), ("#fad", [255, 170, 221, 255])]),
+            ("Issue #123456 and color #ff8800.", false, &[("#ff8800", [255, 136, 0, 255])]),
+            ("Try hsl(0, 0%, 100%) here", false, &[("hsl(0, 0%, 100%)", [255, 255, 255, 255])]),
+        ];
+        for (text, code, expected) in cases {
+            let expected: Vec<(String, [u8; 4])> = expected.iter().map(|(t, c)| (t.to_string(), *c)).collect();
+            assert_eq!(colors(text, code), expected, "colors in {:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_rules() {
+        let rules = ColorRules { functions: false, short_hex_in_prose: true, numeric_hex_in_prose: true };
+        let found: Vec<String> = find_colors("#123 #fad rgb(1, 2, 3)", &rules, false).into_iter().map(|c| c.text).collect();
+        assert_eq!(found, ["#123", "#fad"]);
+    }
+
+    #[test]
+    fn test_write_in_same_format() {
+        let cases = [
+            ("#ff8800", [0, 17, 34, 255], "#001122"),
+            ("#F80", [170, 187, 204, 255], "#ABC"),
+            ("#f80", [0, 18, 34, 255], "#001222"),
+            ("#f808", [0, 17, 34, 51], "#0123"),
+            ("rgb(24, 100, 200)", [1, 2, 3, 255], "rgb(1, 2, 3)"),
+            ("RGBA(0,0,0,0.5)", [1, 2, 3, 64], "RGBA(1, 2, 3, 0.25)"),
+            ("rgb(0 0 0 / 1)", [255, 0, 0, 128], "rgb(255 0 0 / 0.5)"),
+            ("rgb(100% 0% 0%)", [0, 255, 128, 255], "rgb(0% 100% 50%)"),
+            ("hsl(30, 100%, 50%)", [0, 128, 0, 255], "hsl(120, 100%, 25%)"),
+            ("hsl(0 0% 0%)", [255, 255, 255, 255], "hsl(0 0% 100%)"),
+        ];
+        for (literal, rgba, written) in cases {
+            let found = &find_colors(literal, &ColorRules::default(), true)[0];
+            assert_eq!(found.format.write(rgba), written, "writing over {:?}", literal);
+            // What is written reads back, with or without alpha as before
+            let again = &find_colors(written, &ColorRules::default(), true)[0];
+            assert_eq!(again.format.has_alpha(), found.format.has_alpha());
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Rendered blocks as text for the clipboard: tables as TSV or CSV for
+//! spreadsheets, and lists and quotes as plain text that keeps its bullets
+
+use super::export::escape_html;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+
+/// Rows of a table separated by tabs, for pasting into a spreadsheet
+pub fn tsv(rows: &[Vec<String>]) -> String {
+    delimited(rows, '\t')
+}
+
+/// Rows of a table as comma-separated values
+pub fn csv(rows: &[Vec<String>]) -> String {
+    delimited(rows, ',')
+}
+
+/// One line per row. A cell holding the delimiter, a quote or a line break
+/// is quoted, with its quotes doubled, the way spreadsheets read it.
+fn delimited(rows: &[Vec<String>], delimiter: char) -> String {
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| {
+                    if cell.contains([delimiter, '"', '\n', '\r']) {
+                        format!("\"{}\"", cell.replace('"', "\"\""))
+                    } else {
+                        cell.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(&delimiter.to_string())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A table as an HTML `<table>`, header row first
+pub fn table_html(rows: &[Vec<String>]) -> String {
+    let mut html = String::from("<table>\n");
+    for (index, row) in rows.iter().enumerate() {
+        let tag = if index == 0 { "th" } else { "td" };
+        html.push_str("<tr>");
+        for cell in row {
+            html.push_str(&format!("<{tag}>{}</{tag}>", escape_html(cell)));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+/// Plain text of the Markdown of a list or quote, with `-` before list
+/// items, numbers before ordered ones, and nested lists indented under
+/// their item
+pub fn block_text(markdown: &str) -> String {
+    let mut text = String::new();
+    // Next number of each open list, `None` for bullets
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    for event in Parser::new_ext(markdown, Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS) {
+        match event {
+            Event::Start(Tag::List(start)) => lists.push(start),
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+            }
+            Event::Start(Tag::Item) => {
+                end_line(&mut text);
+                text.push_str(&"   ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        text.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => text.push_str("- "),
+                }
+            }
+            Event::TaskListMarker(done) => text.push_str(if done { "[x] " } else { "[ ] " }),
+            Event::Text(part) | Event::Code(part) => text.push_str(&part),
+            Event::SoftBreak => text.push(' '),
+            Event::HardBreak | Event::End(TagEnd::Paragraph | TagEnd::Heading(_) | TagEnd::CodeBlock) => end_line(&mut text),
+            _ => {}
+        }
+    }
+    text.trim_end().to_string()
+}
+
+fn end_line(text: &mut String) {
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::{ElementKind, MarkdownRenderer};
+
+    fn rows(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter().map(|row| row.iter().map(|cell| cell.to_string()).collect()).collect()
+    }
+
+    #[test]
+    fn test_tsv_and_csv() {
+        let table = rows(&[
+            &["Name", "Note"],
+            &["Smith, J.", "says \"hi\""],
+            &["a | b", "tab\there"],
+            &["two\nlines", ""],
+        ]);
+        assert_eq!(tsv(&table), "Name\tNote\nSmith, J.\t\"says \"\"hi\"\"\"\na | b\t\"tab\there\"\n\"two\nlines\"\t");
+        assert_eq!(csv(&table), "Name,Note\n\"Smith, J.\",\"says \"\"hi\"\"\"\na | b,tab\there\n\"two\nlines\",");
+        assert_eq!(table_html(&rows(&[&["A<B"], &["&"]])), "<table>\n<tr><th>A&lt;B</th></tr>\n<tr><td>&amp;</td></tr>\n</table>\n");
+    }
+
+    #[test]
+    fn test_table_from_markdown() {
+        let markdown = "| Item | Price |\n|---|---|\n| Tea, green | 3 |\n| Pipe \\| escaped | \"4\" |\n";
+        let elements = MarkdownRenderer::default().render(markdown);
+        let Some(ElementKind::Table(table)) = elements.iter().map(|e| &e.kind).find(|kind| matches!(kind, ElementKind::Table(_))) else {
+            panic!("no table in {:?}", elements);
+        };
+        assert_eq!(csv(table), "Item,Price\n\"Tea, green\",3\nPipe | escaped,\"\"\"4\"\"\"");
+    }
+
+    #[test]
+    fn test_block_text() {
+        let list = "- One\n- Two **bold**\n  1. Nested\n  2. `More`\n- [x] Three\n  wrapped\n";
+        assert_eq!(block_text(list), "- One\n- Two bold\n   1. Nested\n   2. More\n- [x] Three wrapped");
+        // Numbers go on from where the list starts
+        assert_eq!(block_text("3. Three\n1. Four\n"), "3. Three\n4. Four");
+        assert_eq!(block_text("> Quoted\n>\n> - a\n> - b\n"), "Quoted\n- a\n- b");
+    }
+}
@@ -0,0 +1,804 @@
+//! Word documents from Markdown. Headings take Word's heading styles, lists
+//! its numbering, and local images are embedded; what Word has no place for
+//! is written as plain text and reported.
+
+use super::embed;
+use super::export::escape_html;
+use super::flavor::{self, Flavor};
+use super::pagebreak::{BreakEvent, PageBreaks};
+use crate::utils::zip::ZipWriter;
+use pulldown_cmark::{Alignment, Event, Parser, Tag, TagEnd};
+use std::path::{Path, PathBuf};
+
+/// Width of the text between the page margins, in twentieths of a point
+const TEXT_WIDTH: u32 = 11906 - 2 * 1440;
+/// English Metric Units in a twentieth of a point, and in a pixel at 96 DPI
+const EMU_PER_TWIP: u64 = 635;
+const EMU_PER_PIXEL: u64 = 9525;
+/// Indentation per list level, in twentieths of a point
+const LIST_INDENT: usize = 720;
+/// Deepest list level Word's numbering has
+const LIST_LEVELS: usize = 9;
+/// Image formats Word shows, by extension
+const IMAGE_TYPES: [(&str, &str); 5] = [
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("bmp", "image/bmp"),
+];
+
+const NAMESPACES: &str = "xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\" \
+xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\" \
+xmlns:wp=\"http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing\" \
+xmlns:a=\"http://schemas.openxmlformats.org/drawingml/2006/main\" \
+xmlns:pic=\"http://schemas.openxmlformats.org/drawingml/2006/picture\"";
+const XML_DECLARATION: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+const RELATIONSHIP_TYPES: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+const PACKAGE_RELATIONSHIPS: &str = "<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+<Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"word/document.xml\"/>\
+</Relationships>";
+
+const STYLES: &str = "<w:styles xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">\
+<w:docDefaults><w:rPrDefault><w:rPr><w:rFonts w:ascii=\"Calibri\" w:hAnsi=\"Calibri\" w:eastAsia=\"Calibri\" w:cs=\"Calibri\"/>\
+<w:sz w:val=\"22\"/><w:szCs w:val=\"22\"/></w:rPr></w:rPrDefault>\
+<w:pPrDefault><w:pPr><w:spacing w:after=\"160\" w:line=\"264\" w:lineRule=\"auto\"/></w:pPr></w:pPrDefault></w:docDefaults>\
+<w:style w:type=\"paragraph\" w:default=\"1\" w:styleId=\"Normal\"><w:name w:val=\"Normal\"/><w:qFormat/></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Heading1\"><w:name w:val=\"heading 1\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>\
+<w:pPr><w:keepNext/><w:spacing w:before=\"360\" w:after=\"120\"/><w:outlineLvl w:val=\"0\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"36\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Heading2\"><w:name w:val=\"heading 2\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>\
+<w:pPr><w:keepNext/><w:spacing w:before=\"320\" w:after=\"120\"/><w:outlineLvl w:val=\"1\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"30\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Heading3\"><w:name w:val=\"heading 3\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>\
+<w:pPr><w:keepNext/><w:spacing w:before=\"280\" w:after=\"80\"/><w:outlineLvl w:val=\"2\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"26\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Heading4\"><w:name w:val=\"heading 4\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>\
+<w:pPr><w:keepNext/><w:spacing w:before=\"240\" w:after=\"80\"/><w:outlineLvl w:val=\"3\"/></w:pPr><w:rPr><w:b/><w:i/><w:sz w:val=\"24\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Heading5\"><w:name w:val=\"heading 5\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>\
+<w:pPr><w:keepNext/><w:spacing w:before=\"200\" w:after=\"40\"/><w:outlineLvl w:val=\"4\"/></w:pPr><w:rPr><w:b/><w:sz w:val=\"22\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Heading6\"><w:name w:val=\"heading 6\"/><w:basedOn w:val=\"Normal\"/><w:next w:val=\"Normal\"/><w:qFormat/>\
+<w:pPr><w:keepNext/><w:spacing w:before=\"200\" w:after=\"40\"/><w:outlineLvl w:val=\"5\"/></w:pPr><w:rPr><w:b/><w:i/><w:color w:val=\"595959\"/><w:sz w:val=\"22\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Quote\"><w:name w:val=\"Quote\"/><w:basedOn w:val=\"Normal\"/><w:qFormat/>\
+<w:pPr><w:pBdr><w:left w:val=\"single\" w:sz=\"18\" w:space=\"8\" w:color=\"BFBFBF\"/></w:pBdr><w:ind w:left=\"360\"/></w:pPr><w:rPr><w:i/><w:color w:val=\"595959\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"Code\"><w:name w:val=\"Code\"/><w:basedOn w:val=\"Normal\"/>\
+<w:pPr><w:shd w:val=\"clear\" w:color=\"auto\" w:fill=\"F2F2F2\"/><w:spacing w:after=\"0\" w:line=\"240\" w:lineRule=\"auto\"/></w:pPr>\
+<w:rPr><w:rFonts w:ascii=\"Consolas\" w:hAnsi=\"Consolas\" w:cs=\"Consolas\"/><w:sz w:val=\"20\"/></w:rPr></w:style>\
+<w:style w:type=\"paragraph\" w:styleId=\"ListParagraph\"><w:name w:val=\"List Paragraph\"/><w:basedOn w:val=\"Normal\"/><w:qFormat/>\
+<w:pPr><w:spacing w:after=\"60\"/><w:ind w:left=\"720\"/></w:pPr></w:style>\
+<w:style w:type=\"character\" w:styleId=\"CodeChar\"><w:name w:val=\"Code Char\"/>\
+<w:rPr><w:rFonts w:ascii=\"Consolas\" w:hAnsi=\"Consolas\" w:cs=\"Consolas\"/><w:sz w:val=\"20\"/><w:shd w:val=\"clear\" w:color=\"auto\" w:fill=\"F2F2F2\"/></w:rPr></w:style>\
+<w:style w:type=\"character\" w:styleId=\"Hyperlink\"><w:name w:val=\"Hyperlink\"/><w:rPr><w:color w:val=\"0563C1\"/><w:u w:val=\"single\"/></w:rPr></w:style>\
+<w:style w:type=\"table\" w:styleId=\"TableGrid\"><w:name w:val=\"Table Grid\"/><w:pPr><w:spacing w:after=\"0\"/></w:pPr>\
+<w:tblPr><w:tblBorders><w:top w:val=\"single\" w:sz=\"4\" w:color=\"auto\"/><w:left w:val=\"single\" w:sz=\"4\" w:color=\"auto\"/>\
+<w:bottom w:val=\"single\" w:sz=\"4\" w:color=\"auto\"/><w:right w:val=\"single\" w:sz=\"4\" w:color=\"auto\"/>\
+<w:insideH w:val=\"single\" w:sz=\"4\" w:color=\"auto\"/><w:insideV w:val=\"single\" w:sz=\"4\" w:color=\"auto\"/></w:tblBorders>\
+<w:tblCellMar><w:left w:w=\"108\" w:type=\"dxa\"/><w:right w:w=\"108\" w:type=\"dxa\"/></w:tblCellMar></w:tblPr></w:style>\
+</w:styles>";
+
+/// A Word document made from Markdown
+pub struct Docx {
+    /// The `.docx` file's contents
+    pub bytes: Vec<u8>,
+    /// Kinds of content written as plain text or left out, for telling the
+    /// user, such as "footnotes"
+    pub simplified: Vec<&'static str>,
+}
+
+/// A list being written, with the Word numbering instance its items use
+struct List {
+    num_id: usize,
+}
+
+/// A picture to store in the package
+struct Media {
+    path: PathBuf,
+    name: String,
+    rel_id: String,
+}
+
+/// How a run of text looks besides the current formatting
+#[derive(Clone, Copy, PartialEq)]
+enum RunStyle {
+    Plain,
+    Code,
+    Superscript,
+}
+
+/// Position in a table being written
+struct Table {
+    alignments: Vec<Alignment>,
+    cell: usize,
+}
+
+#[derive(Default)]
+struct Writer<'a> {
+    folder: Option<&'a Path>,
+    body: String,
+    /// Runs of the paragraph being written, after its properties
+    paragraph: Option<String>,
+    bold: usize,
+    italic: usize,
+    strike: usize,
+    /// Relationship id of the link being written
+    link: Option<String>,
+    quotes: usize,
+    lists: Vec<List>,
+    /// Numbering instances: whether each is ordered, and where it starts
+    numbering: Vec<Option<u64>>,
+    /// Set by an item's start until its first paragraph takes the number
+    item_pending: bool,
+    code_block: Option<String>,
+    /// Alt text of the image being read, with its URL
+    image: Option<(String, String)>,
+    table: Option<Table>,
+    /// Label of a footnote definition waiting for its first paragraph
+    footnote: Option<String>,
+    metadata: bool,
+    /// Relationships after styles and numbering: id, type, target, external
+    relationships: Vec<(String, &'static str, String, bool)>,
+    media: Vec<Media>,
+    /// Pictures placed so far, for their ids in the document
+    drawings: usize,
+    simplified: Vec<&'static str>,
+}
+
+/// `markdown` as a Word document, using the profile set in its front matter
+/// or else `flavor`. Local images are read relative to `folder`.
+pub fn markdown_to_docx(markdown: &str, flavor: Flavor, folder: Option<&Path>) -> Docx {
+    let flavor = flavor::front_matter_flavor(markdown).unwrap_or(flavor);
+    let mut writer = Writer { folder, ..Writer::default() };
+    let mut page_breaks = PageBreaks::new(markdown);
+    for (event, range) in Parser::new_ext(markdown, flavor.options()).into_offset_iter() {
+        match page_breaks.event(&range) {
+            BreakEvent::Outside => writer.event(event),
+            BreakEvent::Start => {
+                writer.close_paragraph();
+                writer.body.push_str("<w:p><w:r><w:br w:type=\"page\"/></w:r></w:p>");
+            }
+            BreakEvent::Inside => {}
+        }
+    }
+    writer.close_paragraph();
+    writer.finish()
+}
+
+impl Writer<'_> {
+    fn event(&mut self, event: Event) {
+        if self.metadata && !matches!(event, Event::End(TagEnd::MetadataBlock(_))) {
+            return;
+        }
+        if let Some((alt, _)) = self.image.as_mut() {
+            match event {
+                Event::Text(text) | Event::Code(text) => alt.push_str(&text),
+                Event::End(TagEnd::Image) => self.end_image(),
+                _ => {}
+            }
+            return;
+        }
+        if let Some(code) = self.code_block.as_mut() {
+            match event {
+                Event::Text(text) => code.push_str(&text),
+                Event::End(TagEnd::CodeBlock) => self.end_code_block(),
+                _ => {}
+            }
+            return;
+        }
+
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => self.text(&text, RunStyle::Plain),
+            Event::Code(text) => self.text(&text, RunStyle::Code),
+            Event::InlineMath(text) | Event::DisplayMath(text) => {
+                self.simplify("math");
+                self.text(&text, RunStyle::Code);
+            }
+            Event::Html(html) | Event::InlineHtml(html) => {
+                if html.trim_start().starts_with("<!--") {
+                    return;
+                }
+                self.simplify("HTML");
+                let text = strip_tags(&html).replace('\n', " ");
+                if !text.trim().is_empty() {
+                    self.text(&text, RunStyle::Plain);
+                }
+            }
+            Event::FootnoteReference(label) => {
+                self.simplify("footnotes");
+                self.text(&format!("[{}]", label), RunStyle::Superscript);
+            }
+            Event::SoftBreak => self.text(" ", RunStyle::Plain),
+            Event::HardBreak => {
+                self.ensure_paragraph();
+                self.push_run("<w:br/>");
+            }
+            Event::Rule => {
+                self.close_paragraph();
+                self.body.push_str(
+                    "<w:p><w:pPr><w:pBdr><w:bottom w:val=\"single\" w:sz=\"6\" w:space=\"1\" w:color=\"auto\"/></w:pBdr></w:pPr></w:p>",
+                );
+            }
+            Event::TaskListMarker(checked) => self.text(if checked { "☒ " } else { "☐ " }, RunStyle::Plain),
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => {
+                self.close_paragraph();
+                self.open_paragraph(None);
+            }
+            Tag::Heading { level, .. } => {
+                self.close_paragraph();
+                self.open_paragraph(Some(&format!("Heading{}", level as u8)));
+            }
+            Tag::BlockQuote(_) => {
+                self.close_paragraph();
+                self.quotes += 1;
+            }
+            Tag::CodeBlock(_) => {
+                self.close_paragraph();
+                self.code_block = Some(String::new());
+            }
+            Tag::HtmlBlock => self.close_paragraph(),
+            Tag::List(start) => {
+                self.close_paragraph();
+                self.numbering.push(start);
+                self.lists.push(List { num_id: self.numbering.len() });
+            }
+            Tag::Item => {
+                self.close_paragraph();
+                self.item_pending = true;
+            }
+            Tag::FootnoteDefinition(label) => {
+                self.close_paragraph();
+                self.simplify("footnotes");
+                self.footnote = Some(label.to_string());
+            }
+            Tag::Table(alignments) => {
+                self.close_paragraph();
+                let width = TEXT_WIDTH / alignments.len().max(1) as u32;
+                self.body.push_str(
+                    "<w:tbl><w:tblPr><w:tblStyle w:val=\"TableGrid\"/><w:tblW w:w=\"0\" w:type=\"auto\"/></w:tblPr><w:tblGrid>",
+                );
+                for _ in &alignments {
+                    self.body.push_str(&format!("<w:gridCol w:w=\"{}\"/>", width));
+                }
+                self.body.push_str("</w:tblGrid>");
+                self.table = Some(Table { alignments, cell: 0 });
+            }
+            Tag::TableHead => {
+                self.body.push_str("<w:tr><w:trPr><w:tblHeader/></w:trPr>");
+                self.bold += 1;
+            }
+            Tag::TableRow => self.body.push_str("<w:tr>"),
+            Tag::TableCell => {
+                self.body.push_str("<w:tc>");
+                let alignment = self.table.as_ref().and_then(|table| table.alignments.get(table.cell)).copied();
+                let justify = match alignment {
+                    Some(Alignment::Center) => "<w:jc w:val=\"center\"/>",
+                    Some(Alignment::Right) => "<w:jc w:val=\"right\"/>",
+                    _ => "",
+                };
+                self.paragraph = Some(format!("<w:p><w:pPr>{}</w:pPr>", justify));
+            }
+            Tag::Emphasis => self.italic += 1,
+            Tag::Strong => self.bold += 1,
+            Tag::Strikethrough => self.strike += 1,
+            Tag::Link { dest_url, .. } => {
+                self.ensure_paragraph();
+                if dest_url.is_empty() || dest_url.starts_with('#') {
+                    // Headings have no bookmarks to jump to
+                    self.simplify("links within the document");
+                    return;
+                }
+                let id = self.relationship("hyperlink", dest_url.to_string(), true);
+                if let Some(paragraph) = self.paragraph.as_mut() {
+                    paragraph.push_str(&format!("<w:hyperlink r:id=\"{}\">", id));
+                }
+                self.link = Some(id);
+            }
+            Tag::Image { dest_url, .. } => self.image = Some((String::new(), dest_url.to_string())),
+            Tag::MetadataBlock(_) => self.metadata = true,
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::Heading(_) => self.close_paragraph(),
+            TagEnd::BlockQuote(_) => {
+                self.close_paragraph();
+                self.quotes -= 1;
+            }
+            TagEnd::HtmlBlock => self.close_paragraph(),
+            TagEnd::List(_) => {
+                self.close_paragraph();
+                self.lists.pop();
+            }
+            TagEnd::Item | TagEnd::FootnoteDefinition => {
+                self.close_paragraph();
+                self.item_pending = false;
+            }
+            TagEnd::Table => {
+                self.body.push_str("</w:tbl>");
+                self.table = None;
+            }
+            TagEnd::TableHead => {
+                self.body.push_str("</w:tr>");
+                self.bold -= 1;
+                if let Some(table) = self.table.as_mut() {
+                    table.cell = 0;
+                }
+            }
+            TagEnd::TableRow => {
+                self.body.push_str("</w:tr>");
+                if let Some(table) = self.table.as_mut() {
+                    table.cell = 0;
+                }
+            }
+            TagEnd::TableCell => {
+                self.close_paragraph();
+                self.body.push_str("</w:tc>");
+                if let Some(table) = self.table.as_mut() {
+                    table.cell += 1;
+                }
+            }
+            TagEnd::Emphasis => self.italic -= 1,
+            TagEnd::Strong => self.bold -= 1,
+            TagEnd::Strikethrough => self.strike -= 1,
+            TagEnd::Link if self.link.take().is_some() => {
+                if let Some(paragraph) = self.paragraph.as_mut() {
+                    paragraph.push_str("</w:hyperlink>");
+                }
+            }
+            TagEnd::MetadataBlock(_) => self.metadata = false,
+            _ => {}
+        }
+    }
+
+    /// Start a paragraph in `style`, or in the style of the block it is in.
+    /// The first paragraph of a list item carries the item's number.
+    fn open_paragraph(&mut self, style: Option<&str>) {
+        let style = style.or(if self.quotes > 0 {
+            Some("Quote")
+        } else if self.lists.is_empty() {
+            None
+        } else {
+            Some("ListParagraph")
+        });
+        let mut properties = String::new();
+        if let Some(style) = style {
+            properties.push_str(&format!("<w:pStyle w:val=\"{}\"/>", style));
+        }
+        if let Some(list) = self.lists.last() {
+            let level = (self.lists.len() - 1).min(LIST_LEVELS - 1);
+            if std::mem::take(&mut self.item_pending) {
+                properties.push_str(&format!(
+                    "<w:numPr><w:ilvl w:val=\"{}\"/><w:numId w:val=\"{}\"/></w:numPr>",
+                    level, list.num_id
+                ));
+            } else {
+                // Later paragraphs of an item line up with its text
+                properties.push_str(&format!("<w:ind w:left=\"{}\"/>", LIST_INDENT * (level + 1)));
+            }
+        }
+        self.paragraph = Some(format!("<w:p><w:pPr>{}</w:pPr>", properties));
+        if let Some(label) = self.footnote.take() {
+            self.text(&format!("[{}] ", label), RunStyle::Superscript);
+        }
+    }
+
+    /// Open a paragraph for text outside one, as in a tight list item
+    fn ensure_paragraph(&mut self) {
+        if self.paragraph.is_none() {
+            self.open_paragraph(None);
+        }
+    }
+
+    fn close_paragraph(&mut self) {
+        if let Some(paragraph) = self.paragraph.take() {
+            if self.link.take().is_some() {
+                self.body.push_str(&paragraph);
+                self.body.push_str("</w:hyperlink>");
+            } else {
+                self.body.push_str(&paragraph);
+            }
+            self.body.push_str("</w:p>");
+        }
+    }
+
+    /// A run of `text` in the current formatting
+    fn text(&mut self, text: &str, style: RunStyle) {
+        if text.is_empty() {
+            return;
+        }
+        self.ensure_paragraph();
+        // In the order Word's schema wants them
+        let mut properties = String::new();
+        if self.link.is_some() {
+            properties.push_str("<w:rStyle w:val=\"Hyperlink\"/>");
+            if style == RunStyle::Code {
+                properties.push_str("<w:rFonts w:ascii=\"Consolas\" w:hAnsi=\"Consolas\"/>");
+            }
+        } else if style == RunStyle::Code {
+            properties.push_str("<w:rStyle w:val=\"CodeChar\"/>");
+        }
+        if self.bold > 0 {
+            properties.push_str("<w:b/>");
+        }
+        if self.italic > 0 {
+            properties.push_str("<w:i/>");
+        }
+        if self.strike > 0 {
+            properties.push_str("<w:strike/>");
+        }
+        if style == RunStyle::Superscript {
+            properties.push_str("<w:vertAlign w:val=\"superscript\"/>");
+        }
+        self.push_run(&format!(
+            "<w:rPr>{}</w:rPr><w:t xml:space=\"preserve\">{}</w:t>",
+            properties,
+            escape_xml(text)
+        ));
+    }
+
+    fn push_run(&mut self, content: &str) {
+        if let Some(paragraph) = self.paragraph.as_mut() {
+            paragraph.push_str(&format!("<w:r>{}</w:r>", content));
+        }
+    }
+
+    /// Each line of the code block as a shaded paragraph
+    fn end_code_block(&mut self) {
+        let code = self.code_block.take().unwrap_or_default();
+        let code = code.strip_suffix('\n').unwrap_or(&code);
+        for line in code.split('\n') {
+            self.open_paragraph(Some("Code"));
+            if !line.is_empty() {
+                let run = format!("<w:t xml:space=\"preserve\">{}</w:t>", escape_xml(&line.replace('\t', "    ")));
+                self.push_run(&run);
+            }
+            self.close_paragraph();
+        }
+    }
+
+    /// Embed the image just read, or write its alt text when it isn't a
+    /// local file Word can show
+    fn end_image(&mut self) {
+        let Some((alt, url)) = self.image.take() else {
+            return;
+        };
+        let path = embed::resolve(&url, self.folder).filter(|path| image_type(path).is_some());
+        let size = path.as_deref().and_then(|path| image::image_dimensions(path).ok());
+        let (Some(path), Some((width, height))) = (path, size) else {
+            self.simplify("images that couldn't be embedded");
+            self.text(&format!("[{}]", if alt.is_empty() { &url } else { &alt }), RunStyle::Plain);
+            return;
+        };
+
+        let rel_id = match self.media.iter().find(|media| media.path == path) {
+            Some(media) => media.rel_id.clone(),
+            None => {
+                let extension = path.extension().unwrap_or_default().to_string_lossy().to_lowercase();
+                let name = format!("image{}.{}", self.media.len() + 1, extension);
+                let rel_id = self.relationship("image", format!("media/{}", name), false);
+                self.media.push(Media { path, name, rel_id: rel_id.clone() });
+                rel_id
+            }
+        };
+        // At its size on a 96 DPI screen, shrunk to fit the page
+        let max_width = u64::from(TEXT_WIDTH) * EMU_PER_TWIP;
+        let (mut cx, mut cy) = (u64::from(width) * EMU_PER_PIXEL, u64::from(height) * EMU_PER_PIXEL);
+        if cx > max_width {
+            cy = cy * max_width / cx;
+            cx = max_width;
+        }
+        self.drawings += 1;
+        let id = self.drawings;
+        self.ensure_paragraph();
+        self.push_run(&format!(
+            "<w:drawing><wp:inline distT=\"0\" distB=\"0\" distL=\"0\" distR=\"0\"><wp:extent cx=\"{cx}\" cy=\"{cy}\"/>\
+             <wp:docPr id=\"{id}\" name=\"Picture {id}\" descr=\"{alt}\"/>\
+             <a:graphic><a:graphicData uri=\"http://schemas.openxmlformats.org/drawingml/2006/picture\">\
+             <pic:pic><pic:nvPicPr><pic:cNvPr id=\"{id}\" name=\"Picture {id}\"/><pic:cNvPicPr/></pic:nvPicPr>\
+             <pic:blipFill><a:blip r:embed=\"{rel_id}\"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill>\
+             <pic:spPr><a:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"{cx}\" cy=\"{cy}\"/></a:xfrm>\
+             <a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></pic:spPr></pic:pic></a:graphicData></a:graphic></wp:inline></w:drawing>",
+            alt = escape_xml(&alt),
+        ));
+    }
+
+    /// Add a relationship of the document, returning its id. The first two
+    /// ids are the styles' and the numbering's.
+    fn relationship(&mut self, kind: &'static str, target: String, external: bool) -> String {
+        let id = format!("rId{}", self.relationships.len() + 3);
+        self.relationships.push((id.clone(), kind, target, external));
+        id
+    }
+
+    fn simplify(&mut self, kind: &'static str) {
+        if !self.simplified.contains(&kind) {
+            self.simplified.push(kind);
+        }
+    }
+
+    /// Package the parts into the `.docx` archive
+    fn finish(mut self) -> Docx {
+        let mut zip = ZipWriter::new();
+        let mut types = String::from(XML_DECLARATION);
+        types.push_str("<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">");
+        types.push_str("<Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>");
+        types.push_str("<Default Extension=\"xml\" ContentType=\"application/xml\"/>");
+        for (extension, mime) in IMAGE_TYPES {
+            types.push_str(&format!("<Default Extension=\"{}\" ContentType=\"{}\"/>", extension, mime));
+        }
+        for (part, kind) in [("document", "document.main"), ("styles", "styles"), ("numbering", "numbering")] {
+            types.push_str(&format!(
+                "<Override PartName=\"/word/{}.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.wordprocessingml.{}+xml\"/>",
+                part, kind
+            ));
+        }
+        types.push_str("</Types>");
+        zip.add("[Content_Types].xml", types.as_bytes());
+        zip.add("_rels/.rels", format!("{}{}", XML_DECLARATION, PACKAGE_RELATIONSHIPS).as_bytes());
+
+        let document = format!(
+            "{}<w:document {}><w:body>{}<w:sectPr><w:pgSz w:w=\"11906\" w:h=\"16838\"/>\
+             <w:pgMar w:top=\"1440\" w:right=\"1440\" w:bottom=\"1440\" w:left=\"1440\" w:header=\"708\" w:footer=\"708\" w:gutter=\"0\"/>\
+             </w:sectPr></w:body></w:document>",
+            XML_DECLARATION, NAMESPACES, self.body
+        );
+        zip.add("word/document.xml", document.as_bytes());
+        zip.add("word/styles.xml", format!("{}{}", XML_DECLARATION, STYLES).as_bytes());
+        zip.add("word/numbering.xml", numbering_xml(&self.numbering).as_bytes());
+
+        let mut relationships = format!(
+            "{}<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+             <Relationship Id=\"rId1\" Type=\"{types}/styles\" Target=\"styles.xml\"/>\
+             <Relationship Id=\"rId2\" Type=\"{types}/numbering\" Target=\"numbering.xml\"/>",
+            XML_DECLARATION,
+            types = RELATIONSHIP_TYPES
+        );
+        for (id, kind, target, external) in &self.relationships {
+            relationships.push_str(&format!(
+                "<Relationship Id=\"{}\" Type=\"{}/{}\" Target=\"{}\"{}/>",
+                id,
+                RELATIONSHIP_TYPES,
+                kind,
+                escape_xml(target),
+                if *external { " TargetMode=\"External\"" } else { "" }
+            ));
+        }
+        relationships.push_str("</Relationships>");
+        zip.add("word/_rels/document.xml.rels", relationships.as_bytes());
+
+        for media in std::mem::take(&mut self.media) {
+            match std::fs::read(&media.path) {
+                Ok(bytes) => zip.add(&format!("word/media/{}", media.name), &bytes),
+                Err(e) => {
+                    log::warn!("Failed to read {}: {}", media.path.display(), e);
+                    self.simplify("images that couldn't be embedded");
+                }
+            }
+        }
+        Docx { bytes: zip.finish(), simplified: self.simplified }
+    }
+}
+
+/// Bullets and decimal numbers for each list level, and an instance per
+/// list so each ordered list counts from its own start
+fn numbering_xml(lists: &[Option<u64>]) -> String {
+    let mut xml = format!(
+        "{}<w:numbering xmlns:w=\"http://schemas.openxmlformats.org/wordprocessingml/2006/main\">",
+        XML_DECLARATION
+    );
+    for (abstract_id, ordered) in [(0, false), (1, true)] {
+        xml.push_str(&format!("<w:abstractNum w:abstractNumId=\"{}\"><w:multiLevelType w:val=\"hybridMultilevel\"/>", abstract_id));
+        for level in 0..LIST_LEVELS {
+            let (format, text) = if ordered {
+                ("decimal", format!("%{}.", level + 1))
+            } else {
+                ("bullet", ["•", "◦", "▪"][level % 3].to_string())
+            };
+            xml.push_str(&format!(
+                "<w:lvl w:ilvl=\"{}\"><w:start w:val=\"1\"/><w:numFmt w:val=\"{}\"/><w:lvlText w:val=\"{}\"/><w:lvlJc w:val=\"left\"/>\
+                 <w:pPr><w:ind w:left=\"{}\" w:hanging=\"360\"/></w:pPr></w:lvl>",
+                level,
+                format,
+                text,
+                LIST_INDENT * (level + 1)
+            ));
+        }
+        xml.push_str("</w:abstractNum>");
+    }
+    for (index, start) in lists.iter().enumerate() {
+        xml.push_str(&format!("<w:num w:numId=\"{}\"><w:abstractNumId w:val=\"{}\"/>", index + 1, u8::from(start.is_some())));
+        if let Some(start) = start {
+            for level in 0..LIST_LEVELS {
+                xml.push_str(&format!(
+                    "<w:lvlOverride w:ilvl=\"{}\"><w:startOverride w:val=\"{}\"/></w:lvlOverride>",
+                    level, start
+                ));
+            }
+        }
+        xml.push_str("</w:num>");
+    }
+    xml.push_str("</w:numbering>");
+    xml
+}
+
+/// MIME type of an image file Word can show
+fn image_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    IMAGE_TYPES.iter().find(|(e, _)| *e == extension).map(|(_, mime)| *mime)
+}
+
+/// `text` escaped for XML, without the control characters XML can't hold,
+/// which would make Word refuse the whole document
+fn escape_xml(text: &str) -> String {
+    let allowed = |c: &char| matches!(c, '\t' | '\n' | '\r' | ' '..='\u{fffd}' | '\u{10000}'..);
+    escape_html(&text.chars().filter(allowed).collect::<String>())
+}
+
+/// The text of an HTML fragment, without its tags
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::zip::read_stored;
+
+    /// Convert `markdown` and read back the document part, and the names of
+    /// all the parts
+    fn convert(markdown: &str, folder: Option<&Path>) -> (String, Vec<String>, Vec<&'static str>) {
+        let docx = markdown_to_docx(markdown, Flavor::Extended, folder);
+        let files = read_stored(&docx.bytes);
+        let names = files.iter().map(|(name, _)| name.clone()).collect();
+        let document = files.into_iter().find(|(name, _)| name == "word/document.xml").unwrap().1;
+        (String::from_utf8(document).unwrap(), names, docx.simplified)
+    }
+
+    #[test]
+    fn test_headings_and_paragraphs() {
+        let (xml, names, simplified) = convert(include_str!("fixtures/docx_blocks.md"), None);
+        assert_eq!(
+            names,
+            [
+                "[Content_Types].xml",
+                "_rels/.rels",
+                "word/document.xml",
+                "word/styles.xml",
+                "word/numbering.xml",
+                "word/_rels/document.xml.rels"
+            ]
+        );
+        assert!(xml.contains("<w:p><w:pPr><w:pStyle w:val=\"Heading1\"/></w:pPr><w:r><w:rPr></w:rPr><w:t xml:space=\"preserve\">Title</w:t></w:r></w:p>"));
+        assert!(xml.contains("<w:pStyle w:val=\"Heading3\"/>"));
+        // Front matter is left out
+        assert!(!xml.contains("flavor"));
+        assert!(xml.contains("<w:pPr><w:pStyle w:val=\"Quote\"/></w:pPr><w:r><w:rPr></w:rPr><w:t xml:space=\"preserve\">Quoted</w:t></w:r>"));
+        assert!(xml.contains("<w:pBdr><w:bottom w:val=\"single\""));
+        // Code keeps its lines and indentation, one shaded paragraph each
+        assert!(xml.contains(
+            "<w:p><w:pPr><w:pStyle w:val=\"Code\"/></w:pPr><w:r><w:t xml:space=\"preserve\">fn main() {</w:t></w:r></w:p>\
+             <w:p><w:pPr><w:pStyle w:val=\"Code\"/></w:pPr><w:r><w:t xml:space=\"preserve\">    println!(&quot;&lt;hi&gt;&quot;);</w:t></w:r></w:p>\
+             <w:p><w:pPr><w:pStyle w:val=\"Code\"/></w:pPr></w:p>"
+        ));
+        assert!(simplified.is_empty());
+    }
+
+    #[test]
+    fn test_inline_formatting_and_links() {
+        let (xml, _, simplified) = convert(include_str!("fixtures/docx_inline.md"), None);
+        assert!(xml.contains("<w:r><w:rPr><w:b/></w:rPr><w:t xml:space=\"preserve\">bold</w:t></w:r>"));
+        assert!(xml.contains("<w:r><w:rPr><w:b/><w:i/></w:rPr><w:t xml:space=\"preserve\">both</w:t></w:r>"));
+        assert!(xml.contains("<w:r><w:rPr><w:strike/></w:rPr><w:t xml:space=\"preserve\">gone</w:t></w:r>"));
+        assert!(xml.contains("<w:r><w:rPr><w:rStyle w:val=\"CodeChar\"/></w:rPr><w:t xml:space=\"preserve\">code</w:t></w:r>"));
+        assert!(xml.contains(
+            "<w:hyperlink r:id=\"rId3\"><w:r><w:rPr><w:rStyle w:val=\"Hyperlink\"/></w:rPr><w:t xml:space=\"preserve\">site</w:t></w:r></w:hyperlink>"
+        ));
+        assert!(xml.contains("<w:r><w:br/></w:r>"));
+        // An anchor link has nothing to point at
+        assert!(xml.contains("<w:r><w:rPr></w:rPr><w:t xml:space=\"preserve\">below</w:t></w:r>"));
+        assert_eq!(simplified, ["links within the document"]);
+    }
+
+    #[test]
+    fn test_lists() {
+        let markdown = include_str!("fixtures/docx_lists.md");
+        let docx = markdown_to_docx(markdown, Flavor::Gfm, None);
+        let files = read_stored(&docx.bytes);
+        let part = |name: &str| {
+            String::from_utf8(files.iter().find(|(n, _)| n == name).unwrap().1.clone()).unwrap()
+        };
+        let xml = part("word/document.xml");
+        let number = |level: usize, id: usize| format!("<w:numPr><w:ilvl w:val=\"{}\"/><w:numId w:val=\"{}\"/></w:numPr>", level, id);
+        assert_eq!(xml.matches(&number(0, 1)).count(), 3);
+        assert_eq!(xml.matches(&number(1, 2)).count(), 2);
+        assert_eq!(xml.matches(&number(0, 3)).count(), 2);
+        // The second paragraph of a loose item isn't numbered, but indented
+        assert!(xml.contains("<w:pStyle w:val=\"ListParagraph\"/><w:ind w:left=\"720\"/></w:pPr><w:r><w:rPr></w:rPr><w:t xml:space=\"preserve\">More</w:t>"));
+        assert!(xml.contains("☒ "));
+
+        let numbering = part("word/numbering.xml");
+        assert!(numbering.contains("<w:num w:numId=\"1\"><w:abstractNumId w:val=\"0\"/></w:num>"));
+        assert!(numbering.contains("<w:num w:numId=\"2\"><w:abstractNumId w:val=\"1\"/><w:lvlOverride w:ilvl=\"0\"><w:startOverride w:val=\"1\"/>"));
+        assert!(numbering.contains("<w:num w:numId=\"3\"><w:abstractNumId w:val=\"1\"/><w:lvlOverride w:ilvl=\"0\"><w:startOverride w:val=\"4\"/>"));
+    }
+
+    #[test]
+    fn test_table() {
+        let (xml, _, _) = convert(include_str!("fixtures/docx_table.md"), None);
+        assert_eq!(xml.matches("<w:tr>").count(), 2);
+        assert_eq!(xml.matches("<w:tc>").count(), 6);
+        assert!(xml.contains("<w:gridCol w:w=\"3008\"/>"));
+        assert!(xml.contains(
+            "<w:tr><w:trPr><w:tblHeader/></w:trPr><w:tc><w:p><w:pPr></w:pPr><w:r><w:rPr><w:b/></w:rPr><w:t xml:space=\"preserve\">Name</w:t></w:r></w:p></w:tc>"
+        ));
+        assert!(xml.contains("<w:tc><w:p><w:pPr><w:jc w:val=\"right\"/></w:pPr><w:r><w:rPr></w:rPr><w:t xml:space=\"preserve\">3</w:t>"));
+        // An empty cell still holds a paragraph, as Word requires
+        assert!(xml.contains("<w:tc><w:p><w:pPr><w:jc w:val=\"center\"/></w:pPr></w:p></w:tc>"));
+    }
+
+    #[test]
+    fn test_images() {
+        let folder = std::env::temp_dir().join(format!("rmd-docx-{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        image::RgbaImage::new(4, 2).save(folder.join("dot.png")).unwrap();
+        image::RgbaImage::new(2000, 100).save(folder.join("wide.png")).unwrap();
+
+        let markdown = "![A dot](dot.png) and again ![](dot.png)\n\n![](wide.png)\n\n![Logo](https://example.com/logo.png)\n";
+        let (xml, names, simplified) = convert(markdown, Some(&folder));
+        assert!(names.contains(&"word/media/image1.png".to_string()));
+        assert!(names.contains(&"word/media/image2.png".to_string()));
+        assert_eq!(names.len(), 8);
+        assert_eq!(xml.matches("<a:blip r:embed=\"rId3\"/>").count(), 2);
+        assert!(xml.contains("<wp:extent cx=\"38100\" cy=\"19050\"/>"));
+        assert!(xml.contains("descr=\"A dot\""));
+        // Shrunk to the width of the page
+        assert!(xml.contains("<wp:extent cx=\"5731510\" cy=\"286575\"/>"));
+        assert!(xml.contains("<w:t xml:space=\"preserve\">[Logo]</w:t>"));
+        assert_eq!(simplified, ["images that couldn't be embedded"]);
+
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_page_breaks() {
+        let (xml, _, simplified) = convert("One\n\n<!-- pagebreak -->\n\nTwo\n\n\\newpage\n\nThree\n", None);
+        assert_eq!(xml.matches("<w:br w:type=\"page\"/>").count(), 2);
+        assert!(!xml.contains("newpage"));
+        assert!(simplified.is_empty());
+    }
+
+    #[test]
+    fn test_unsupported_becomes_text() {
+        let (xml, _, simplified) = convert(include_str!("fixtures/docx_degraded.md"), None);
+        assert!(xml.contains("<w:vertAlign w:val=\"superscript\"/></w:rPr><w:t xml:space=\"preserve\">[1]</w:t>"));
+        assert!(xml.contains("<w:t xml:space=\"preserve\">[1] </w:t></w:r><w:r><w:rPr></w:rPr><w:t xml:space=\"preserve\">The note.</w:t>"));
+        assert!(xml.contains("<w:t xml:space=\"preserve\">Shown as text "));
+        assert!(!xml.contains("hidden"));
+        assert!(xml.contains("<w:rStyle w:val=\"CodeChar\"/></w:rPr><w:t xml:space=\"preserve\">x^2</w:t>"));
+        assert_eq!(simplified, ["footnotes", "HTML", "math"]);
+    }
+
+    #[test]
+    fn test_control_characters_dropped() {
+        let (xml, _, _) = convert("Form\u{c}feed and a bell\u{7}\n\n```\ncode\u{1b}[0m\n```\n", None);
+        assert!(xml.contains("<w:t xml:space=\"preserve\">Formfeed and a bell</w:t>"));
+        assert!(xml.contains("<w:t xml:space=\"preserve\">code[0m</w:t>"));
+        assert!(!xml.contains(['\u{7}', '\u{c}', '\u{1b}']));
+    }
+}
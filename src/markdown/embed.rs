@@ -0,0 +1,127 @@
+//! Local images written into exported pages as `data:` URIs, so a page
+//! works on its own
+
+use super::flavor::{self, Flavor};
+use pulldown_cmark::{Event, Parser, Tag};
+use std::path::{Path, PathBuf};
+
+/// Images larger than this make the page noticeably heavy, and are pointed
+/// out before embedding
+pub const LARGE_IMAGE: u64 = 1024 * 1024;
+
+/// An image in the document that lives on disk
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalImage {
+    /// The URL as written in the document
+    pub url: String,
+    pub path: PathBuf,
+    /// Size in bytes, or `None` when the file is missing
+    pub size: Option<u64>,
+}
+
+/// Local images `markdown` shows, each file once, resolved against `folder`.
+/// Without a folder only absolute paths can be found.
+pub fn local_images(markdown: &str, flavor: Flavor, folder: Option<&Path>) -> Vec<LocalImage> {
+    let flavor = flavor::front_matter_flavor(markdown).unwrap_or(flavor);
+    let mut images: Vec<LocalImage> = Vec::new();
+    for event in Parser::new_ext(markdown, flavor.options()) {
+        let Event::Start(Tag::Image { dest_url, .. }) = event else {
+            continue;
+        };
+        let Some(path) = resolve(&dest_url, folder) else {
+            continue;
+        };
+        if !images.iter().any(|image| image.path == path) {
+            let size = std::fs::metadata(&path).ok().filter(|m| m.is_file()).map(|m| m.len());
+            images.push(LocalImage { url: dest_url.to_string(), path, size });
+        }
+    }
+    images
+}
+
+/// The file an image `url` points at, or `None` for web and `data:` URLs
+pub fn resolve(url: &str, folder: Option<&Path>) -> Option<PathBuf> {
+    let path = url.split(['?', '#']).next().unwrap_or_default().replace("%20", " ");
+    if path.is_empty() {
+        return None;
+    }
+    let path = PathBuf::from(path);
+    if path.is_absolute() {
+        return Some(path);
+    }
+    // A scheme such as `https:`; a drive letter was taken as absolute above
+    if url.contains(':') {
+        return None;
+    }
+    folder.map(|folder| folder.join(path))
+}
+
+/// The contents of the image at `path` as a `data:` URI
+pub fn data_uri(path: &Path) -> std::io::Result<String> {
+    let bytes = std::fs::read(path)?;
+    Ok(format!("data:{};base64,{}", mime_type(path), base64(&bytes)))
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("bmp") => "image/bmp",
+        Some("ico") => "image/x-icon",
+        Some("avif") => "image/avif",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Standard Base64 with padding
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[0xff, 0xfe]), "//4=");
+    }
+
+    #[test]
+    fn test_local_images() {
+        let folder = std::env::temp_dir().join(format!("rmd-embed-{}", std::process::id()));
+        std::fs::create_dir_all(folder.join("img")).unwrap();
+        std::fs::write(folder.join("img/a b.png"), b"png").unwrap();
+
+        let markdown = "![a](img/a%20b.png) ![again](img/a%20b.png#x) ![web](https://example.com/x.png)\n\
+                        ![gone](missing.gif) ![inline](data:image/png;base64,AA==)\n";
+        let images = local_images(markdown, Flavor::Gfm, Some(&folder));
+        let found: Vec<(&str, Option<u64>)> = images.iter().map(|i| (i.url.as_str(), i.size)).collect();
+        assert_eq!(found, [("img/a%20b.png", Some(3)), ("missing.gif", None)]);
+        assert_eq!(data_uri(&images[0].path).unwrap(), "data:image/png;base64,cG5n");
+        assert!(local_images(markdown, Flavor::Gfm, None).is_empty());
+
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
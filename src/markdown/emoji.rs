@@ -0,0 +1,66 @@
+//! `:shortcode:` to emoji replacement for rendered text
+
+use std::borrow::Cow;
+
+/// Replace known `:shortcode:`s in a run of rendered text with their emoji.
+/// Unknown names and anything containing spaces are left alone.
+pub fn replace_shortcodes(text: &str) -> Cow<'_, str> {
+    if !text.contains(':') {
+        return Cow::Borrowed(text);
+    }
+
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find(':') {
+        let after = &rest[open + 1..];
+        let Some(close) = after.find(':') else {
+            break;
+        };
+        let name = &after[..close];
+        match shortcode_emoji(name) {
+            Some(emoji) => {
+                output.push_str(&rest[..open]);
+                output.push_str(emoji);
+                rest = &after[close + 1..];
+            }
+            None => {
+                // The closing colon may open the next shortcode
+                output.push_str(&rest[..=open]);
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+
+    if output == text {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(output)
+    }
+}
+
+fn shortcode_emoji(name: &str) -> Option<&'static str> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '+' | '-'));
+    if !valid {
+        return None;
+    }
+    emojis::get_by_shortcode(name).map(|emoji| emoji.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_shortcodes() {
+        assert_eq!(replace_shortcodes("Launch :rocket: now"), "Launch 🚀 now");
+        assert_eq!(replace_shortcodes(":+1::tada:"), "👍🎉");
+        assert_eq!(replace_shortcodes("at 10:30 :not_an_emoji: :rocket"), "at 10:30 :not_an_emoji: :rocket");
+        assert_eq!(replace_shortcodes("a: :rocket:"), "a: 🚀");
+        assert_eq!(replace_shortcodes(": rocket :"), ": rocket :");
+        assert!(matches!(replace_shortcodes("no colons"), Cow::Borrowed(_)));
+    }
+}
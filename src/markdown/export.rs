@@ -0,0 +1,462 @@
+//! Standalone HTML documents from Markdown
+
+use super::alerts;
+use super::figures::{self, Figure};
+use super::pagebreak::{BreakEvent, PageBreaks};
+use super::flavor::{self, Flavor};
+use super::numbering::HeadingNumbers;
+use super::outline::{self, SlugCounter};
+use super::toc;
+use pulldown_cmark::{html, Event, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+
+/// What a rewritten URL points at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Destination {
+    Link,
+    Image,
+}
+
+/// GitHub's alert colors, readable on light and dark pages alike
+macro_rules! alert_css {
+    () => {
+        ".markdown-alert{margin:0 0 1em;padding:.5em 1em;border-left:.25em solid}.markdown-alert>:last-child{margin-bottom:0}\
+.markdown-alert-title{margin:0 0 .5em;font-weight:600}\
+.markdown-alert-note{border-color:#4493f8}.markdown-alert-note .markdown-alert-title{color:#4493f8}\
+.markdown-alert-tip{border-color:#3fb950}.markdown-alert-tip .markdown-alert-title{color:#3fb950}\
+.markdown-alert-important{border-color:#ab7df8}.markdown-alert-important .markdown-alert-title{color:#ab7df8}\
+.markdown-alert-warning{border-color:#d29922}.markdown-alert-warning .markdown-alert-title{color:#d29922}\
+.markdown-alert-caution{border-color:#f85149}.markdown-alert-caution .markdown-alert-title{color:#f85149}"
+    };
+}
+
+/// The header with the title, reading time and tags, in muted tones that
+/// suit light and dark pages
+macro_rules! header_css {
+    () => {
+        ".doc-header{margin:0 0 2em;padding-bottom:1em;border-bottom:1px solid #8884}.doc-title{margin:0;font-size:1.6em;font-weight:600}\
+.doc-meta{margin:.3em 0 0;font-size:.9em;opacity:.75}.doc-tags{margin:.6em 0 0;padding:0;list-style:none}\
+.doc-tags li{display:inline-block;margin:0 .4em .4em 0;padding:.1em .7em;font-size:.85em;border-radius:1em;background:#8883}"
+    };
+}
+
+/// Plain black on white
+const PLAIN_CSS: &str = concat!("body{max-width:46em;margin:2em auto;padding:0 1em;font-family:system-ui,sans-serif;line-height:1.6;color:#222}\
+pre,code{font-family:Consolas,monospace;background:#f4f4f4}pre{padding:.8em;overflow:auto}\
+table{border-collapse:collapse}th,td{border:1px solid #ccc;padding:.3em .6em}\
+blockquote{margin-left:0;padding-left:1em;border-left:3px solid #ccc;color:#555}img{max-width:100%}\
+figure{margin:1.5em 0;text-align:center}figcaption{font-size:.9em;color:#555}\
+.page-break{break-after:page;border-top:1px dashed #ccc;margin:2em 0}@media print{.page-break{border:0;margin:0}}", alert_css!(), header_css!());
+
+/// Close to how GitHub shows a README
+const GITHUB_CSS: &str = concat!("body{max-width:980px;margin:0 auto;padding:45px;font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',Helvetica,Arial,sans-serif;\
+font-size:16px;line-height:1.5;color:#1f2328;background:#fff}\
+h1,h2{padding-bottom:.3em;border-bottom:1px solid #d1d9e0}h1,h2,h3,h4,h5,h6{margin:24px 0 16px;font-weight:600;line-height:1.25}\
+a{color:#0969da;text-decoration:none}a:hover{text-decoration:underline}\
+code{padding:.2em .4em;font-size:85%;background:#818b981f;border-radius:6px}\
+pre{padding:16px;overflow:auto;font-size:85%;line-height:1.45;background:#f6f8fa;border-radius:6px}pre code{padding:0;font-size:100%;background:none}\
+code,pre{font-family:ui-monospace,SFMono-Regular,Consolas,'Liberation Mono',monospace}\
+table{border-collapse:collapse}th,td{padding:6px 13px;border:1px solid #d1d9e0}tr:nth-child(2n){background:#f6f8fa}\
+blockquote{margin:0 0 16px;padding:0 1em;color:#59636e;border-left:.25em solid #d1d9e0}\
+hr{height:.25em;margin:24px 0;background:#d1d9e0;border:0}img{max-width:100%}\
+figure{margin:0 0 16px;text-align:center}figcaption{font-size:85%;color:#59636e}\
+.page-break{break-after:page;border-top:1px dashed #d1d9e0;margin:24px 0}@media print{.page-break{border:0;margin:0}}", alert_css!(), header_css!());
+
+/// Light text on a dark background
+const DARK_CSS: &str = concat!("body{max-width:46em;margin:2em auto;padding:0 1em;font-family:system-ui,sans-serif;line-height:1.6;color:#d4d4d4;background:#1e1e1e}\
+a{color:#4fa3f7}h1,h2,h3,h4,h5,h6{color:#f0f0f0}\
+pre,code{font-family:Consolas,monospace;background:#2d2d2d}pre{padding:.8em;overflow:auto}\
+table{border-collapse:collapse}th,td{border:1px solid #444;padding:.3em .6em}\
+blockquote{margin-left:0;padding-left:1em;border-left:3px solid #555;color:#a0a0a0}hr{border:0;border-top:1px solid #444}img{max-width:100%}\
+figure{margin:1.5em 0;text-align:center}figcaption{font-size:.9em;color:#a0a0a0}\
+.page-break{break-after:page;border-top:1px dashed #555;margin:2em 0}@media print{.page-break{border:0;margin:0}}", alert_css!(), header_css!());
+
+/// Look of exported pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportStyle {
+    Github,
+    #[default]
+    Plain,
+    Dark,
+    /// A stylesheet of the user's, set in the config
+    Custom,
+}
+
+impl ExportStyle {
+    pub const ALL: [ExportStyle; 4] = [ExportStyle::Github, ExportStyle::Plain, ExportStyle::Dark, ExportStyle::Custom];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ExportStyle::Github => "GitHub",
+            ExportStyle::Plain => "Plain",
+            ExportStyle::Dark => "Dark",
+            ExportStyle::Custom => "Custom CSS",
+        }
+    }
+
+    /// The stylesheet shipped for this style; `None` for `Custom`
+    pub fn css(self) -> Option<&'static str> {
+        match self {
+            ExportStyle::Github => Some(GITHUB_CSS),
+            ExportStyle::Plain => Some(PLAIN_CSS),
+            ExportStyle::Dark => Some(DARK_CSS),
+            ExportStyle::Custom => None,
+        }
+    }
+}
+
+/// Paper that exported pages print on, and that the paginated preview
+/// lays the document out on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageSize {
+    #[default]
+    A4,
+    A5,
+    Letter,
+    Legal,
+}
+
+impl PageSize {
+    pub const ALL: [PageSize; 4] = [PageSize::A4, PageSize::A5, PageSize::Letter, PageSize::Legal];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PageSize::A4 => "A4",
+            PageSize::A5 => "A5",
+            PageSize::Letter => "Letter",
+            PageSize::Legal => "Legal",
+        }
+    }
+
+    /// Width and height in millimetres
+    pub fn size_mm(self) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (210.0, 297.0),
+            PageSize::A5 => (148.0, 210.0),
+            PageSize::Letter => (215.9, 279.4),
+            PageSize::Legal => (215.9, 355.6),
+        }
+    }
+
+    /// The CSS rule printing a page on this paper with `margin` millimetres
+    /// around the text
+    pub fn page_rule(self, margin: f32) -> String {
+        let (width, height) = self.size_mm();
+        format!("@page{{size:{}mm {}mm;margin:{}mm}}", width, height, margin)
+    }
+}
+
+/// How a page gets its styles
+#[derive(Debug, Clone, Copy, Hash)]
+pub enum Stylesheet<'a> {
+    /// CSS written into the page
+    Inline(&'a str),
+    /// URL of a `.css` file the page links to
+    Link(&'a str),
+}
+
+impl Default for Stylesheet<'_> {
+    fn default() -> Self {
+        Stylesheet::Inline(PLAIN_CSS)
+    }
+}
+
+/// How Markdown is turned into HTML
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HtmlOptions {
+    /// Profile for documents that don't name one in their front matter
+    pub flavor: Flavor,
+    /// Put hierarchical numbers in front of headings
+    pub number_headings: bool,
+    /// Start the page with a table of contents of headings down to this level
+    pub toc_depth: Option<u8>,
+}
+
+/// HTML for the body of `markdown`, using the profile set in its front
+/// matter or else the one in `options`. Headings get anchor ids matching the
+/// outline's slugs, and every link and image URL goes through `rewrite`.
+/// A table of contents, when asked for, links to those ids. Captioned
+/// images are numbered figures, with ids that lists of figures link to.
+/// Manual page breaks become elements the printed page breaks after.
+pub fn markdown_to_html(
+    markdown: &str,
+    options: HtmlOptions,
+    mut rewrite: impl FnMut(Destination, &str) -> String,
+) -> String {
+    let flavor = flavor::front_matter_flavor(markdown).unwrap_or(options.flavor);
+    let mut numbers = options.number_headings.then(|| {
+        let levels = Parser::new_ext(markdown, flavor.options()).filter_map(|event| match event {
+            Event::Start(Tag::Heading { level, .. }) => Some(level as u8),
+            _ => None,
+        });
+        HeadingNumbers::for_levels(levels)
+    });
+    let mut parsed: Vec<_> = Parser::new_ext(markdown, flavor.options()).into_offset_iter().collect();
+    outline::apply_explicit_anchors(&mut parsed);
+    let mut slugs = SlugCounter::reserving(outline::explicit_ids(&parsed));
+    let mut events = Vec::new();
+    // Index of the open heading's start event, and its text so far
+    let mut heading: Option<(usize, String)> = None;
+    let figures = if flavor.figures() { figures::find_figures(markdown) } else { Vec::new() };
+    // The figure whose paragraph is being read, and whether its image is
+    let mut figure: Option<(&Figure, bool)> = None;
+    let mut page_breaks = PageBreaks::new(markdown);
+    // Whether each open block quote is an alert
+    let mut quotes: Vec<bool> = Vec::new();
+    // Bytes of an alert's marker still to be dropped from its first text
+    let mut marker: Option<usize> = None;
+
+    for (mut event, range) in parsed {
+        match page_breaks.event(&range) {
+            BreakEvent::Outside => {}
+            BreakEvent::Start => {
+                events.push(Event::Html("<div class=\"page-break\"></div>\n".into()));
+                continue;
+            }
+            BreakEvent::Inside => continue,
+        }
+        // Alerts are divs in GitHub's markup rather than block quotes
+        match &event {
+            Event::Start(Tag::BlockQuote(_)) => {
+                let alert = alerts::quote_alert(&markdown[range.clone()]);
+                quotes.push(alert.is_some());
+                if let Some((kind, len)) = alert {
+                    events.push(Event::Html(alerts::html_start(kind).into()));
+                    marker = Some(len);
+                    continue;
+                }
+            }
+            Event::End(TagEnd::BlockQuote(_)) if quotes.pop() == Some(true) => {
+                events.push(Event::Html("</div>\n".into()));
+                continue;
+            }
+            _ => {}
+        }
+        // The marker goes, with the line break after it, or its paragraph
+        // when nothing else is in it
+        if let Some(left) = marker {
+            match event {
+                Event::Start(Tag::Paragraph) => {}
+                Event::Text(ref text) => {
+                    let rest = text[left.min(text.len())..].trim_start();
+                    if rest.is_empty() {
+                        marker = Some(left.saturating_sub(text.len()));
+                        continue;
+                    }
+                    event = Event::Text(rest.to_string().into());
+                    marker = None;
+                }
+                Event::SoftBreak | Event::HardBreak => {
+                    marker = None;
+                    continue;
+                }
+                Event::End(TagEnd::Paragraph) if matches!(events.last(), Some(Event::Start(Tag::Paragraph))) => {
+                    events.pop();
+                    marker = None;
+                    continue;
+                }
+                _ => marker = None,
+            }
+        }
+        // A figure's paragraph keeps only its image, with the caption under it
+        if let Some((open, in_image)) = figure.as_mut() {
+            match &event {
+                Event::Start(Tag::Image { .. }) => *in_image = true,
+                Event::End(TagEnd::Image) => *in_image = false,
+                Event::End(TagEnd::Paragraph) => {
+                    let caption = format!("\n<figcaption>{}</figcaption>\n</figure>\n", escape_html(&open.label()));
+                    events.push(Event::Html(caption.into()));
+                    figure = None;
+                    continue;
+                }
+                _ if !*in_image => continue,
+                _ => {}
+            }
+        } else if let Event::Start(Tag::Paragraph) = event {
+            if let Some(found) = figures.iter().find(|figure| figure.block.contains(&range.start)) {
+                events.push(Event::Html(format!("<figure id=\"{}\">\n", figures::anchor(found.number)).into()));
+                figure = Some((found, false));
+                continue;
+            }
+        }
+        let event = match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                heading = Some((events.len(), String::new()));
+                if let Some(numbers) = numbers.as_mut() {
+                    // Pushed after the start, and kept out of the slug
+                    let number = numbers.next(level as u8);
+                    events.push(event);
+                    events.push(Event::Text(format!("{} ", number).into()));
+                    continue;
+                }
+                event
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((index, text)) = heading.take() {
+                    if let Event::Start(Tag::Heading { id: id @ None, .. }) = &mut events[index] {
+                        *id = Some(slugs.unique(&text).into());
+                    }
+                }
+                event
+            }
+            Event::Text(ref text) | Event::Code(ref text) => {
+                if let Some((_, heading_text)) = heading.as_mut() {
+                    heading_text.push_str(text);
+                }
+                event
+            }
+            Event::Start(Tag::Link { link_type, dest_url, title, id }) => Event::Start(Tag::Link {
+                link_type,
+                dest_url: rewrite(Destination::Link, &dest_url).into(),
+                title,
+                id,
+            }),
+            Event::Start(Tag::Image { link_type, dest_url, title, id }) => Event::Start(Tag::Image {
+                link_type,
+                dest_url: rewrite(Destination::Image, &dest_url).into(),
+                title,
+                id,
+            }),
+            event => event,
+        };
+        events.push(event);
+    }
+
+    let mut body = String::new();
+    if let Some(depth) = options.toc_depth.filter(|_| !outline::headings(markdown).is_empty()) {
+        body.push_str("<nav class=\"toc\">\n");
+        let list = toc::toc_list(markdown, depth);
+        html::push_html(&mut body, Parser::new_ext(&list, flavor.options()));
+        body.push_str("</nav>\n");
+    }
+    html::push_html(&mut body, events.into_iter());
+    body
+}
+
+/// A complete page around `body`, styled by `stylesheet`, in the language
+/// `lang` when it is known
+pub fn html_document(title: &str, lang: Option<&str>, stylesheet: Stylesheet, body: &str) -> String {
+    let style = match stylesheet {
+        Stylesheet::Inline(css) => format!("<style>{}</style>", css),
+        Stylesheet::Link(url) => format!("<link rel=\"stylesheet\" href=\"{}\">", escape_html(url)),
+    };
+    let html = match lang {
+        Some(lang) => format!("<html lang=\"{}\">", escape_html(lang)),
+        None => "<html>".to_string(),
+    };
+    format!(
+        "<!DOCTYPE html>\n{}\n<head>\n<meta charset=\"utf-8\">\n\"
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n{}\n</head>\n<body>\n{}</body>\n</html>\n",
+        html,
+        escape_html(title),
+        style,
+        body
+    )
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_to_html() {
+        let markdown = "# Intro\n\n## Intro\n\nSee [next](next.md) and ![logo](img/logo.png).\n";
+        let options = HtmlOptions { flavor: Flavor::Gfm, ..HtmlOptions::default() };
+        let html = markdown_to_html(markdown, options, |kind, url| match kind {
+            Destination::Link => url.replace(".md", ".html"),
+            Destination::Image => format!("assets/{}", url),
+        });
+        assert!(html.contains("<h1 id=\"intro\">Intro</h1>"));
+        assert!(html.contains("<h2 id=\"intro-1\">Intro</h2>"));
+        assert!(html.contains("<a href=\"next.html\">next</a>"));
+        assert!(html.contains("<img src=\"assets/img/logo.png\" alt=\"logo\" />"));
+    }
+
+    #[test]
+    fn test_numbered_headings() {
+        let options = HtmlOptions { flavor: Flavor::Gfm, number_headings: true, ..HtmlOptions::default() };
+        let html = markdown_to_html("## Intro\n\n### Setup\n\n## Usage\n", options, |_, url| url.to_string());
+        assert!(html.contains("<h2 id=\"intro\">1. Intro</h2>"));
+        assert!(html.contains("<h3 id=\"setup\">1.1 Setup</h3>"));
+        assert!(html.contains("<h2 id=\"usage\">2. Usage</h2>"));
+    }
+
+    #[test]
+    fn test_figures() {
+        let markdown = "![Flow](<flow chart.png> \"Data\")\n\n![Parts](parts.png)\n*Figure: The <parts>*\n\n*Not a caption*\n";
+        let html = markdown_to_html(markdown, HtmlOptions::default(), |_, url| format!("assets/{}", url));
+        assert!(html.starts_with(
+            "<figure id=\"figure-1\">\n<img src=\"assets/flow%20chart.png\" alt=\"Flow\" title=\"Data\" />\n\
+             <figcaption>Figure 1: Data</figcaption>\n</figure>\n"
+        ));
+        assert!(html.contains(
+            "<figure id=\"figure-2\">\n<img src=\"assets/parts.png\" alt=\"Parts\" />\n\
+             <figcaption>Figure 2: The &lt;parts&gt;</figcaption>\n</figure>\n"
+        ));
+        assert!(html.ends_with("<p><em>Not a caption</em></p>\n"));
+    }
+
+    #[test]
+    fn test_page_breaks() {
+        let html = markdown_to_html("One\n<!-- pagebreak -->\n\n\\newpage\n\n```\n\\newpage\n```\n", HtmlOptions::default(), |_, url| url.to_string());
+        assert_eq!(html.matches("<div class=\"page-break\"></div>").count(), 2);
+        assert!(html.contains("<code>\\newpage\n</code>"));
+        assert!(!html.contains("<!--"));
+    }
+
+    #[test]
+    fn test_alerts() {
+        let markdown = "> [!NOTE]\n> Read this\n\n> [!WARNING] Hot\n>\n> ```\n> code\n> ```\n\n> [!FOO]\n> Plain\n";
+        let html = markdown_to_html(markdown, HtmlOptions::default(), |_, url| url.to_string());
+        assert!(html.starts_with(
+            "<div class=\"markdown-alert markdown-alert-note\">\n<p class=\"markdown-alert-title\">Note</p>\n<p>Read this</p>\n</div>\n"
+        ));
+        assert!(html.contains("markdown-alert-title\">Warning</p>\n<p>Hot</p>\n<pre><code>code\n</code></pre>\n</div>"));
+        assert!(html.ends_with("<blockquote>\n<p>[!FOO]\nPlain</p>\n</blockquote>\n"));
+
+        let nested = markdown_to_html("> [!TIP]\n> > [!CAUTION]\n> > Inner\n", HtmlOptions::default(), |_, url| url.to_string());
+        assert_eq!(nested.matches("</div>").count(), 2);
+        assert!(nested.contains("Tip</p>\n<div class=\"markdown-alert markdown-alert-caution\">"));
+    }
+
+    #[test]
+    fn test_html_document_escapes_title() {
+        let page = html_document("A <b> & C", None, Stylesheet::default(), "<p>x</p>\n");
+        assert!(page.contains("<title>A &lt;b&gt; &amp; C</title>"));
+        assert!(page.contains("\n<html>\n"));
+        assert!(page.contains("<body>\n<p>x</p>\n</body>"));
+    }
+
+    #[test]
+    fn test_toc_and_linked_stylesheet() {
+        let options = HtmlOptions { flavor: Flavor::Gfm, toc_depth: Some(2), ..HtmlOptions::default() };
+        let html = markdown_to_html("# Intro\n\n## Setup\n\n### Details\n", options, |_, url| url.to_string());
+        let toc = &html[..html.find("</nav>").unwrap()];
+        assert!(toc.starts_with("<nav class=\"toc\">"));
+        assert!(toc.contains("<a href=\"#setup\">Setup</a>"));
+        assert!(!toc.contains("Details"));
+        assert!(html.contains("<h2 id=\"setup\">Setup</h2>"));
+
+        let markdown = "## Install *it* {#setup}\n\n## Setup\n\n## Odd {#}\n";
+        let html = markdown_to_html(markdown, options, |_, url| url.to_string());
+        assert!(html.contains("<a href=\"#setup\">Install it</a>"));
+        assert!(html.contains("<h2 id=\"setup\">Install <em>it</em></h2>"));
+        assert!(html.contains("<h2 id=\"setup-1\">Setup</h2>"));
+        assert!(html.contains("<h2 id=\"odd-\">Odd {#}</h2>"));
+
+        let page = html_document("Intro", Some("ja"), Stylesheet::Link("notes.css"), &html);
+        assert!(page.contains("<html lang=\"ja\">"));
+        assert!(page.contains("<link rel=\"stylesheet\" href=\"notes.css\">"));
+        assert!(!page.contains("<style>"));
+    }
+}
@@ -0,0 +1,289 @@
+//! Figures: an image alone in its paragraph, captioned by an italic line
+//! right below it (`*Figure: the architecture*`) or else by its title, and
+//! numbered through the document. A list of figures is kept between
+//! `<!-- figures -->` markers, as tables of contents are.
+
+use super::generated::Markers;
+use super::{fence_marker, ElementKind, RenderedElement};
+use std::ops::Range;
+
+pub const FIGURES_START: &str = "<!-- figures -->";
+pub const FIGURES_END: &str = "<!-- figuresstop -->";
+
+const MARKERS: Markers = Markers { start: FIGURES_START, end: FIGURES_END };
+
+/// Written between the markers when the document has no figures to list
+const NO_FIGURES: &str = "<!-- no figures -->";
+
+/// A captioned image found in the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Figure {
+    /// Counted from 1 in document order
+    pub number: usize,
+    pub alt: String,
+    pub url: String,
+    pub title: Option<String>,
+    pub caption: String,
+    /// Byte range of the image's line and its caption's, without the
+    /// newline that ends them
+    pub block: Range<usize>,
+}
+
+impl Figure {
+    /// "Figure 2: The architecture", as shown under the image
+    pub fn label(&self) -> String {
+        label(self.number, &self.caption)
+    }
+}
+
+pub fn label(number: usize, caption: &str) -> String {
+    format!("Figure {}: {}", number, caption)
+}
+
+/// Id of figure `number` in exported pages, for links to it
+pub fn anchor(number: usize) -> String {
+    format!("figure-{}", number)
+}
+
+/// The figure number a `#figure-N` link points at
+pub fn anchor_number(url: &str) -> Option<usize> {
+    url.strip_prefix("#figure-")?.parse().ok().filter(|&number| number > 0)
+}
+
+/// Alt text, destination, and title of a line that is nothing but an image
+fn image_line(line: &str) -> Option<(String, String, Option<String>)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let (alt, rest) = trimmed.trim_end().strip_prefix("![")?.split_once("](")?;
+    let inner = rest.strip_suffix(')')?;
+    if alt.contains(['[', ']']) || inner.contains(['(', ')']) {
+        return None;
+    }
+    // A destination in angle brackets may hold spaces
+    let inner = inner.trim();
+    let (url, title) = match inner.strip_prefix('<') {
+        Some(bracketed) => bracketed.split_once('>')?,
+        None => inner.split_once([' ', '\t']).unwrap_or((inner, "")),
+    };
+    let title = match title.trim() {
+        "" => None,
+        title => {
+            let unquoted = ['"', '\'']
+                .iter()
+                .find_map(|&quote| title.strip_prefix(quote)?.strip_suffix(quote))?;
+            Some(unquoted.to_string())
+        }
+    };
+    (!url.is_empty()).then(|| (alt.to_string(), url.to_string(), title))
+}
+
+/// Caption of a line that is a single italic span, `*...*` or `_..._`,
+/// without any "Figure:" it starts with
+fn caption_line(line: &str) -> Option<String> {
+    let trimmed = line.trim();
+    let delimiter = trimmed.chars().next().filter(|c| matches!(c, '*' | '_'))?;
+    let inner = trimmed.strip_prefix(delimiter)?.strip_suffix(delimiter)?;
+    let flanked = inner.starts_with(|c: char| !c.is_whitespace()) && inner.ends_with(|c: char| !c.is_whitespace());
+    if !flanked || inner.contains(delimiter) {
+        return None;
+    }
+    let caption = match inner.get(..7) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("figure:") => inner[7..].trim_start(),
+        _ => inner,
+    };
+    Some(if caption.is_empty() { inner } else { caption }.to_string())
+}
+
+/// Figures of `markdown`. Only an image on a line of its own, with blank
+/// lines around it and its caption, is one: an italic line elsewhere, even
+/// a paragraph away from an image, is left as it is.
+pub fn find_figures(markdown: &str) -> Vec<Figure> {
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for line in markdown.split_inclusive('\n') {
+        let text = line.strip_suffix('\n').unwrap_or(line);
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        lines.push((offset, text));
+        offset += line.len();
+    }
+    let blank = |index: usize| lines.get(index).is_none_or(|(_, text)| text.trim().is_empty());
+
+    let mut figures = Vec::new();
+    let mut fence: Option<(char, usize)> = None;
+    let mut index = 0;
+    while index < lines.len() {
+        let (start, text) = lines[index];
+        if let Some((marker, len)) = fence_marker(text) {
+            match fence {
+                None => fence = Some((marker, len)),
+                Some((open, open_len)) if open == marker && len >= open_len => fence = None,
+                Some(_) => {}
+            }
+        }
+        let starts_paragraph = index == 0 || blank(index - 1);
+        let image = image_line(text).filter(|_| fence.is_none() && starts_paragraph);
+        let Some((alt, url, title)) = image else {
+            index += 1;
+            continue;
+        };
+
+        let below = lines.get(index + 1).and_then(|(_, text)| caption_line(text));
+        let (caption, last) = match (below, title.clone()) {
+            (Some(caption), _) if blank(index + 2) => (caption, index + 1),
+            (_, Some(title)) if blank(index + 1) && !title.trim().is_empty() => (title, index),
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+        let (last_start, last_text) = lines[last];
+        figures.push(Figure {
+            number: figures.len() + 1,
+            alt,
+            url,
+            title,
+            caption,
+            block: start..last_start + last_text.len(),
+        });
+        index = last + 1;
+    }
+    figures
+}
+
+/// Put a figure among `elements`, rendered from `markdown`, in place of the
+/// image and caption each is made of, numbered from 1
+pub fn collect_figures(elements: &mut Vec<RenderedElement>, markdown: &str) {
+    for figure in find_figures(markdown) {
+        let block = figure.block;
+        let inside = |element: &RenderedElement| element.span.start >= block.start && element.span.end <= block.end;
+        let Some(at) = elements.iter().position(inside) else {
+            continue;
+        };
+        elements.retain(|element| !inside(element));
+        let kind = ElementKind::Figure(figure.alt, figure.url, figure.caption, figure.number);
+        elements.insert(at, RenderedElement::new(kind, block));
+    }
+}
+
+/// Number the figures in `elements` and the files they include in
+/// document order, so numbers run on across the parts of a document
+pub fn number_figures(elements: &mut [RenderedElement]) {
+    fn number(elements: &mut [RenderedElement], next: &mut usize) {
+        for element in elements {
+            match &mut element.kind {
+                ElementKind::Figure(_, _, _, number) => {
+                    *number = *next;
+                    *next += 1;
+                }
+                ElementKind::Include(_, Ok(body)) => number(body, next),
+                _ => {}
+            }
+        }
+    }
+    number(elements, &mut 1);
+}
+
+/// List of links to the figures
+pub fn figure_list(markdown: &str) -> String {
+    let list: String = find_figures(markdown)
+        .iter()
+        .map(|figure| format!("- [{}](#{})\n", figure.label(), anchor(figure.number)))
+        .collect();
+    if list.is_empty() {
+        format!("{}\n", NO_FIGURES)
+    } else {
+        list
+    }
+}
+
+/// A complete list, markers included, for inserting into `markdown`
+pub fn figure_list_block(markdown: &str) -> String {
+    MARKERS.block(&figure_list(markdown))
+}
+
+/// Byte range of the list between its markers
+pub fn find_figure_list(markdown: &str) -> Option<Range<usize>> {
+    MARKERS.find(markdown)
+}
+
+/// The edit that regenerates an existing list, or `None` if there is no
+/// list or it is already current
+pub fn update_figure_list(markdown: &str) -> Option<(Range<usize>, String)> {
+    MARKERS.update(markdown, || figure_list(markdown))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::MarkdownRenderer;
+
+    fn captions(markdown: &str) -> Vec<(usize, String)> {
+        find_figures(markdown).into_iter().map(|figure| (figure.number, figure.caption)).collect()
+    }
+
+    #[test]
+    fn test_find_figures() {
+        let markdown = include_str!("fixtures/figures.md");
+        let figures = find_figures(markdown);
+        assert_eq!(
+            captions(markdown),
+            [(1, "The architecture".to_string()), (2, "Data flow".to_string()), (3, "_Inner_ parts".to_string())]
+        );
+        assert_eq!(figures[0].url, "arch.png");
+        assert_eq!(figures[0].alt, "Architecture");
+        assert_eq!(&markdown[figures[0].block.clone()], "![Architecture](arch.png)\n*Figure: The architecture*");
+        assert_eq!(figures[1].title.as_deref(), Some("Data flow"));
+        assert_eq!(figures[1].url, "flow chart.svg");
+        assert_eq!(figures[1].label(), "Figure 2: Data flow");
+    }
+
+    #[test]
+    fn test_captions_are_never_taken_from_elsewhere() {
+        // An italic paragraph a blank line away from an image
+        assert!(find_figures("![a](a.png)\n\n*Not a caption*\n").is_empty());
+        // An italic line that isn't after an image
+        assert!(find_figures("Some text\n*Not a caption*\n").is_empty());
+        // An image inside a paragraph, or with text after the caption
+        assert!(find_figures("See this:\n![a](a.png)\n*Not a caption*\n").is_empty());
+        assert!(find_figures("![a](a.png)\n*Caption*\nmore text\n").is_empty());
+        // A caption line that is more than one italic span
+        assert!(find_figures("![a](a.png)\n*One* and *two*\n").is_empty());
+        assert!(find_figures("![a](a.png)\n**Bold**\n").is_empty());
+        // In a code block
+        assert!(find_figures("```\n![a](a.png \"Title\")\n```\n").is_empty());
+        // Neither a caption line nor a title
+        assert!(find_figures("![a](a.png)\n").is_empty());
+        assert_eq!(captions("![a](a.png \"Title\")\n*Caption*\n"), [(1, "Caption".to_string())]);
+    }
+
+    #[test]
+    fn test_preview_elements() {
+        let markdown = "Intro *text*\n\n![A](a.png)\n*The caption*\n\nAfter\n";
+        let elements = MarkdownRenderer::default().render(markdown);
+        let figures: Vec<_> = elements
+            .iter()
+            .filter_map(|element| match &element.kind {
+                ElementKind::Figure(alt, url, caption, number) => Some((alt.as_str(), url.as_str(), caption.as_str(), *number)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(figures, [("A", "a.png", "The caption", 1)]);
+        assert!(!elements.iter().any(|element| element.plain_text().contains("The caption") && !matches!(element.kind, ElementKind::Figure(..))));
+        assert!(elements.iter().any(|element| element.plain_text() == "After"));
+    }
+
+    #[test]
+    fn test_figure_list() {
+        let markdown = "<!-- figures -->\n<!-- figuresstop -->\n\n![A](a.png \"First\")\n\n![B](b.png)\n_Second_\n";
+        assert_eq!(figure_list(markdown), "- [Figure 1: First](#figure-1)\n- [Figure 2: Second](#figure-2)\n");
+        let (range, list) = update_figure_list(markdown).unwrap();
+        let mut updated = markdown.to_string();
+        updated.replace_range(range, &list);
+        assert!(update_figure_list(&updated).is_none());
+        assert_eq!(figure_list_block("No figures.\n"), "<!-- figures -->\n<!-- no figures -->\n<!-- figuresstop -->\n");
+        assert_eq!(anchor_number("#figure-2"), Some(2));
+        assert_eq!(anchor_number("#figures"), None);
+    }
+}
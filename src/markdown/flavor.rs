@@ -0,0 +1,218 @@
+//! Render profiles: which Markdown extensions the preview understands
+
+use pulldown_cmark::Options;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Front matter key that pins a document to a profile, e.g. `rmd_flavor: gfm`
+const FRONT_MATTER_KEY: &str = "rmd_flavor";
+
+/// A Markdown dialect the preview can render
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Flavor {
+    /// Strict CommonMark without extensions
+    #[serde(rename = "commonmark")]
+    CommonMark,
+    /// GitHub-Flavored Markdown: tables, task lists, strikethrough
+    #[default]
+    #[serde(rename = "gfm")]
+    Gfm,
+    /// GFM plus footnotes, math, and wiki links
+    #[serde(rename = "rmd")]
+    Extended,
+}
+
+impl Flavor {
+    pub const ALL: [Flavor; 3] = [Flavor::CommonMark, Flavor::Gfm, Flavor::Extended];
+
+    /// Name used in front matter and the config file
+    pub fn id(self) -> &'static str {
+        match self {
+            Flavor::CommonMark => "commonmark",
+            Flavor::Gfm => "gfm",
+            Flavor::Extended => "rmd",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Flavor> {
+        match id.trim().trim_matches(|c| c == '"' || c == '\'').to_ascii_lowercase().as_str() {
+            "commonmark" => Some(Flavor::CommonMark),
+            "gfm" | "github" => Some(Flavor::Gfm),
+            "rmd" | "extended" => Some(Flavor::Extended),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Flavor::CommonMark => "CommonMark",
+            Flavor::Gfm => "GitHub Flavored",
+            Flavor::Extended => "RMD Extended",
+        }
+    }
+
+    /// Parser extensions for this profile. Front matter is always skipped,
+    /// since that is where a document records its profile.
+    pub fn options(self) -> Options {
+        let mut options = Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+        if self != Flavor::CommonMark {
+            options |= Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS | Options::ENABLE_STRIKETHROUGH;
+        }
+        if self == Flavor::Extended {
+            options |= Options::ENABLE_FOOTNOTES | Options::ENABLE_MATH;
+        }
+        options
+    }
+
+    /// Whether `[[Note]]` links are always parsed in this profile
+    pub fn wiki_links(self) -> bool {
+        self == Flavor::Extended
+    }
+
+    /// Whether an image with a title, alone in its paragraph, becomes a
+    /// numbered figure. Strict CommonMark keeps it a plain image.
+    pub fn figures(self) -> bool {
+        self != Flavor::CommonMark
+    }
+}
+
+/// Byte range of the lines between the opening and closing `---` of the
+/// document's front matter
+pub(crate) fn front_matter(markdown: &str) -> Option<Range<usize>> {
+    let mut lines = markdown.split_inclusive('\n');
+    let first = lines.next()?;
+    if first.trim_end() != "---" {
+        return None;
+    }
+    let start = first.len();
+    let mut offset = start;
+    for line in lines {
+        if matches!(line.trim_end(), "---" | "...") {
+            return Some(start..offset);
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Byte range of the `rmd_flavor:` line within the front matter, and its value
+fn flavor_line(markdown: &str) -> Option<(Range<usize>, &str)> {
+    let range = front_matter(markdown)?;
+    let mut offset = range.start;
+    for line in markdown[range].split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        if let Some(value) = content
+            .strip_prefix(FRONT_MATTER_KEY)
+            .and_then(|rest| rest.trim_start().strip_prefix(':'))
+        {
+            return Some((offset..offset + content.len(), value));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// The profile named by `rmd_flavor:` in the document's front matter
+pub fn front_matter_flavor(markdown: &str) -> Option<Flavor> {
+    flavor_line(markdown).and_then(|(_, value)| Flavor::from_id(value))
+}
+
+/// The edit that records `flavor` in the document's front matter, as a range
+/// to replace and its replacement, or `None` if there is no front matter
+pub fn set_front_matter_flavor(markdown: &str, flavor: Flavor) -> Option<(Range<usize>, String)> {
+    let line = format!("{}: {}", FRONT_MATTER_KEY, flavor.id());
+    match flavor_line(markdown) {
+        Some((range, _)) => Some((range, line)),
+        None => {
+            let end = front_matter(markdown)?.end;
+            Some((end..end, format!("{}\n", line)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::{ElementKind, MarkdownRenderer, RenderedElement};
+
+    fn render(flavor: Flavor, markdown: &str) -> Vec<RenderedElement> {
+        let mut renderer = MarkdownRenderer::default();
+        renderer.set_flavor(flavor);
+        renderer.render(markdown)
+    }
+
+    fn has(elements: &[RenderedElement], predicate: &dyn Fn(&ElementKind) -> bool) -> bool {
+        elements.iter().any(|element| match &element.kind {
+            ElementKind::UnorderedList(items, _) | ElementKind::OrderedList(items, _) => {
+                items.iter().any(|item| has(item, predicate))
+            }
+            ElementKind::BlockQuote(items, _) => has(items, predicate),
+            kind => predicate(kind),
+        })
+    }
+
+    fn is_table(kind: &ElementKind) -> bool {
+        matches!(kind, ElementKind::Table(_))
+    }
+
+    #[test]
+    fn test_commonmark_fixture() {
+        let fixture = include_str!("fixtures/commonmark.md");
+        for flavor in Flavor::ALL {
+            let elements = render(flavor, fixture);
+            assert!(has(&elements, &|e| matches!(e, ElementKind::CodeBlock(lang, ..) if lang == "rust")));
+            assert!(has(&elements, &|e| matches!(e, ElementKind::Link(text, _) if text == "CommonMark")));
+        }
+        // The pipe table is plain text without the table extension
+        assert!(!has(&render(Flavor::CommonMark, fixture), &is_table));
+    }
+
+    #[test]
+    fn test_gfm_fixture() {
+        let fixture = include_str!("fixtures/gfm.md");
+        let strict = render(Flavor::CommonMark, fixture);
+        let gfm = render(Flavor::Gfm, fixture);
+
+        assert!(!has(&strict, &is_table));
+        assert!(has(&gfm, &|e| matches!(e, ElementKind::Table(rows) if rows.len() == 3 && rows[0] == ["Name", "Flavor"])));
+        assert!(has(&gfm, &|e| matches!(e, ElementKind::Paragraph(text) if text.starts_with("☑"))));
+        assert!(has(&gfm, &|e| matches!(e, ElementKind::Strikethrough(text) if text == "old")));
+        assert!(!has(&strict, &|e| matches!(e, ElementKind::Strikethrough(_))));
+
+        let is_autolink = |e: &ElementKind| matches!(e, ElementKind::Link(text, _) if text == "https://example.com");
+        assert!(has(&gfm, &is_autolink) && !has(&strict, &is_autolink));
+    }
+
+    #[test]
+    fn test_extended_fixture() {
+        let fixture = include_str!("fixtures/extended.md");
+        let gfm = render(Flavor::Gfm, fixture);
+        let extended = render(Flavor::Extended, fixture);
+
+        let is_math = |e: &ElementKind| matches!(e, ElementKind::CodeBlock(lang, ..) if lang == "math");
+        let is_wiki = |e: &ElementKind| matches!(e, ElementKind::Link(_, url) if url.starts_with("wiki:"));
+        assert!(has(&extended, &is_math) && !has(&gfm, &is_math));
+        assert!(has(&extended, &is_wiki) && !has(&gfm, &is_wiki));
+        assert!(has(&extended, &|e| matches!(e, ElementKind::FootnoteReference(label, Some(1), 1) if label == "1")));
+    }
+
+    #[test]
+    fn test_front_matter_flavor() {
+        let doc = "---\ntitle: Notes\nrmd_flavor: commonmark\n---\n\n| a |\n|---|\n| b |\n";
+        assert_eq!(front_matter_flavor(doc), Some(Flavor::CommonMark));
+        assert!(!has(&render(Flavor::Gfm, doc), &is_table));
+        assert_eq!(front_matter_flavor("rmd_flavor: gfm\n"), None);
+
+        let (range, text) = set_front_matter_flavor(doc, Flavor::Extended).unwrap();
+        let mut updated = doc.to_string();
+        updated.replace_range(range, &text);
+        assert_eq!(front_matter_flavor(&updated), Some(Flavor::Extended));
+
+        let doc = "---\ntitle: Notes\n---\nBody\n";
+        let (range, text) = set_front_matter_flavor(doc, Flavor::Gfm).unwrap();
+        let mut updated = doc.to_string();
+        updated.replace_range(range, &text);
+        assert_eq!(updated, "---\ntitle: Notes\nrmd_flavor: gfm\n---\nBody\n");
+        assert!(set_front_matter_flavor("# No front matter\n", Flavor::Gfm).is_none());
+    }
+}
@@ -0,0 +1,195 @@
+//! Footnote numbering and back-references, worked out over the rendered
+//! element stream
+
+use super::{ElementKind, RenderedElement};
+use std::collections::HashMap;
+
+/// Number footnotes in the order they are first referenced, count the
+/// references to each, and move the definitions to the end of the
+/// document behind a rule. The parser leaves references to labels without
+/// a definition as text; they become unnumbered references. Definitions
+/// nobody references go at the very end.
+pub fn link_footnotes(elements: &mut Vec<RenderedElement>) {
+    split_undefined(elements);
+    let mut definitions = Vec::new();
+    take_definitions(elements, &mut definitions);
+    if definitions.is_empty() && !has_references(elements) {
+        return;
+    }
+    let defined: Vec<String> = definitions.iter().filter_map(label).collect();
+
+    // Numbers by first reference, and how many references each label has
+    let mut numbers: HashMap<String, usize> = HashMap::new();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    visit_mut(elements, &mut |element| {
+        if let ElementKind::FootnoteReference(label, number, occurrence) = &mut element.kind {
+            if !defined.contains(label) {
+                return;
+            }
+            let next = numbers.len() + 1;
+            *number = Some(*numbers.entry(label.clone()).or_insert(next));
+            let count = counts.entry(label.clone()).or_insert(0);
+            *count += 1;
+            *occurrence = *count;
+        }
+    });
+
+    for definition in &mut definitions {
+        if let ElementKind::FootnoteDefinition(label, number, references, _) = &mut definition.kind {
+            *number = numbers.get(label).copied().unwrap_or(0);
+            *references = counts.get(label).copied().unwrap_or(0);
+        }
+    }
+    // Unreferenced definitions sort last, in document order
+    definitions.sort_by_key(|definition| match definition.kind {
+        ElementKind::FootnoteDefinition(_, 0, ..) => usize::MAX,
+        ElementKind::FootnoteDefinition(_, number, ..) => number,
+        _ => usize::MAX,
+    });
+    if let Some(first) = definitions.first() {
+        elements.push(RenderedElement::new(ElementKind::HorizontalRule, first.span));
+    }
+    elements.extend(definitions);
+}
+
+fn label(definition: &RenderedElement) -> Option<String> {
+    match &definition.kind {
+        ElementKind::FootnoteDefinition(label, ..) => Some(label.clone()),
+        _ => None,
+    }
+}
+
+/// Move footnote definitions found at the top level out of `elements`
+fn take_definitions(elements: &mut Vec<RenderedElement>, definitions: &mut Vec<RenderedElement>) {
+    let (found, rest): (Vec<_>, Vec<_>) = std::mem::take(elements)
+        .into_iter()
+        .partition(|element| matches!(element.kind, ElementKind::FootnoteDefinition(..)));
+    *elements = rest;
+    definitions.extend(found);
+}
+
+/// Split `[^label]` out of prose as references to an undefined footnote
+fn split_undefined(elements: &mut Vec<RenderedElement>) {
+    let mut split = Vec::with_capacity(elements.len());
+    for mut element in std::mem::take(elements) {
+        match &mut element.kind {
+            ElementKind::Paragraph(text) if text.contains("[^") => {
+                let span = element.span;
+                let mut rest = text.as_str();
+                while let Some((before, label, after)) = next_marker(rest) {
+                    if !before.is_empty() {
+                        split.push(RenderedElement::new(ElementKind::Paragraph(before.to_string()), span));
+                    }
+                    split.push(RenderedElement::new(ElementKind::FootnoteReference(label.to_string(), None, 0), span));
+                    rest = after;
+                }
+                if !rest.is_empty() {
+                    split.push(RenderedElement::new(ElementKind::Paragraph(rest.to_string()), span));
+                }
+                continue;
+            }
+            ElementKind::BlockQuote(items, _)
+            | ElementKind::Details(_, _, items)
+            | ElementKind::FootnoteDefinition(_, _, _, items) => split_undefined(items),
+            ElementKind::UnorderedList(items, _) | ElementKind::OrderedList(items, _) => items.iter_mut().for_each(split_undefined),
+            _ => {}
+        }
+        split.push(element);
+    }
+    *elements = split;
+}
+
+/// Text before the first `[^label]` in `text`, the label, and the text after
+fn next_marker(text: &str) -> Option<(&str, &str, &str)> {
+    let mut from = 0;
+    while let Some(start) = text[from..].find("[^").map(|i| from + i) {
+        let label_start = start + 2;
+        if let Some(end) = text[label_start..].find(']').map(|i| label_start + i) {
+            let label = &text[label_start..end];
+            if !label.is_empty() && !label.contains(|c: char| c.is_whitespace() || c == '[') {
+                return Some((&text[..start], label, &text[end + 1..]));
+            }
+        }
+        from = label_start;
+    }
+    None
+}
+
+fn has_references(elements: &mut [RenderedElement]) -> bool {
+    let mut found = false;
+    visit_mut(elements, &mut |element| {
+        found |= matches!(element.kind, ElementKind::FootnoteReference(..));
+    });
+    found
+}
+
+/// Call `f` on every element, including those inside containers
+fn visit_mut(elements: &mut [RenderedElement], f: &mut impl FnMut(&mut RenderedElement)) {
+    for element in elements {
+        f(element);
+        match &mut element.kind {
+            ElementKind::BlockQuote(items, _)
+            | ElementKind::Details(_, _, items)
+            | ElementKind::FootnoteDefinition(_, _, _, items) => visit_mut(items, f),
+            ElementKind::UnorderedList(items, _) | ElementKind::OrderedList(items, _) => {
+                for item in items {
+                    visit_mut(item, f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// `n` in superscript digits, for telling back-references apart
+pub fn superscript(n: usize) -> String {
+    const DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+    n.to_string()
+        .chars()
+        .filter_map(|c| c.to_digit(10).map(|d| DIGITS[d as usize]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::flavor::Flavor;
+    use crate::markdown::MarkdownRenderer;
+    use crate::theme::Theme;
+
+    #[test]
+    fn test_link_footnotes() {
+        let mut renderer = MarkdownRenderer::new(&Theme::default());
+        renderer.set_flavor(Flavor::Extended);
+        let doc = "[^b]: Second.\n\nOne[^a], two[^b], again[^a], missing[^x].\n\n[^a]: First.\n\n[^unused]: Never cited.\n";
+        let elements = renderer.render(doc);
+
+        let references: Vec<_> = elements
+            .iter()
+            .filter_map(|e| match &e.kind {
+                ElementKind::FootnoteReference(label, number, occurrence) => Some((label.as_str(), *number, *occurrence)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(references, [("a", Some(1), 1), ("b", Some(2), 1), ("a", Some(1), 2), ("x", None, 0)]);
+
+        // Definitions come last, in reference order, with their reference counts
+        let definitions: Vec<_> = elements
+            .iter()
+            .skip_while(|e| !matches!(e.kind, ElementKind::HorizontalRule))
+            .filter_map(|e| match &e.kind {
+                ElementKind::FootnoteDefinition(label, number, references, body) => {
+                    Some((label.as_str(), *number, *references, body.len()))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(definitions, [("a", 1, 2, 1), ("b", 2, 1, 1), ("unused", 0, 0, 1)]);
+    }
+
+    #[test]
+    fn test_superscript() {
+        assert_eq!(superscript(2), "²");
+        assert_eq!(superscript(10), "¹⁰");
+    }
+}
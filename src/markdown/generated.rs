@@ -0,0 +1,56 @@
+//! Blocks the editor keeps up to date between a pair of comment markers,
+//! such as tables of contents and lists of figures
+
+use std::ops::Range;
+
+/// The comment lines a generated block sits between
+pub struct Markers {
+    pub start: &'static str,
+    pub end: &'static str,
+}
+
+impl Markers {
+    /// `body` between the markers, for inserting into a document
+    pub fn block(&self, body: &str) -> String {
+        format!("{}\n{}{}\n", self.start, body, self.end)
+    }
+
+    /// Byte range between the start marker's line and the end marker
+    pub fn find(&self, markdown: &str) -> Option<Range<usize>> {
+        let mut offset = 0;
+        let mut start = None;
+        for line in markdown.split_inclusive('\n') {
+            let trimmed = line.trim();
+            if start.is_none() && trimmed == self.start {
+                start = Some(offset + line.len());
+            } else if let Some(start) = start.filter(|_| trimmed == self.end) {
+                return Some(start..offset);
+            }
+            offset += line.len();
+        }
+        None
+    }
+
+    /// The edit that puts `body` between the markers, or `None` if there
+    /// is no block or it already holds that
+    pub fn update(&self, markdown: &str, body: impl FnOnce() -> String) -> Option<(Range<usize>, String)> {
+        let range = self.find(markdown)?;
+        let body = body();
+        (markdown[range.clone()] != body).then_some((range, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let markers = Markers { start: "<!-- a -->", end: "<!-- astop -->" };
+        let doc = "Intro\n  <!-- a -->\nold\n<!-- astop -->\nrest\n";
+        assert_eq!(&doc[markers.find(doc).unwrap()], "old\n");
+        assert_eq!(markers.update(doc, || "old\n".to_string()), None);
+        assert_eq!(markers.find("<!-- a -->\nnever closed\n"), None);
+        assert_eq!(markers.block("x\n"), "<!-- a -->\nx\n<!-- astop -->\n");
+    }
+}
@@ -0,0 +1,112 @@
+//! What the header above the preview and an exported page says about the
+//! document: its title, from the front matter's `title` or the first level
+//! one heading, and the tags listed under `tags`
+
+use super::export::escape_html;
+use super::flavor;
+use super::outline;
+use super::overrides::unquote;
+
+/// Title and tags of a document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DocumentHeader {
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl DocumentHeader {
+    /// The header of `markdown`. Tags may be a `[a, b]` list, a comma
+    /// separated line, or a block of `- a` lines.
+    pub fn parse(markdown: &str) -> Self {
+        let mut header = Self::default();
+        let mut in_tags = false;
+        if let Some(range) = flavor::front_matter(markdown) {
+            for line in markdown[range].lines() {
+                if in_tags {
+                    if let Some(tag) = line.trim_start().strip_prefix("- ") {
+                        header.push_tag(tag);
+                        continue;
+                    }
+                    in_tags = line.trim().is_empty();
+                    if in_tags {
+                        continue;
+                    }
+                }
+                if let Some(value) = line.strip_prefix("title:") {
+                    header.title = Some(unquote(value).to_string()).filter(|title| !title.is_empty());
+                } else if let Some(value) = line.strip_prefix("tags:") {
+                    let value = unquote(value);
+                    let value = value.strip_prefix('[').and_then(|list| list.strip_suffix(']')).unwrap_or(value);
+                    value.split(',').for_each(|tag| header.push_tag(tag));
+                    in_tags = value.is_empty();
+                }
+            }
+        }
+        if header.title.is_none() {
+            header.title = outline::headings(markdown).into_iter().find(|heading| heading.level == 1).map(|heading| heading.text);
+        }
+        header
+    }
+
+    fn push_tag(&mut self, tag: &str) {
+        let tag = unquote(tag).trim_start_matches('#');
+        if !tag.is_empty() && !self.tags.iter().any(|known| known == tag) {
+            self.tags.push(tag.to_string());
+        }
+    }
+
+    /// The header as HTML for the top of an exported page, with `facts`
+    /// such as the reading time on one line under the title
+    pub fn to_html(&self, facts: &[String]) -> String {
+        let mut html = String::from("<header class=\"doc-header\">\n");
+        if let Some(title) = &self.title {
+            html.push_str(&format!("<p class=\"doc-title\">{}</p>\n", escape_html(title)));
+        }
+        if !facts.is_empty() {
+            let facts: Vec<String> = facts.iter().map(|fact| escape_html(fact)).collect();
+            html.push_str(&format!("<p class=\"doc-meta\">{}</p>\n", facts.join(" · ")));
+        }
+        if !self.tags.is_empty() {
+            html.push_str("<ul class=\"doc-tags\">");
+            for tag in &self.tags {
+                html.push_str(&format!("<li>{}</li>", escape_html(tag)));
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</header>\n");
+        html
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let header = DocumentHeader::parse("---\ntitle: \"Trip notes\"\ntags: [travel, 'japan', travel]\n---\n# Day one\n");
+        assert_eq!(header.title.as_deref(), Some("Trip notes"));
+        assert_eq!(header.tags, ["travel", "japan"]);
+
+        let header = DocumentHeader::parse("---\ntags:\n  - recipes\n  - \"#baking\"\nauthor: Me\n---\nIntro\n\n# Bread\n");
+        assert_eq!(header.title.as_deref(), Some("Bread"));
+        assert_eq!(header.tags, ["recipes", "baking"]);
+
+        let header = DocumentHeader::parse("---\ntags: work, q3 # planning\n---\n## Only a subheading\n");
+        assert_eq!(header.title, None);
+        assert_eq!(header.tags, ["work", "q3"]);
+
+        assert_eq!(DocumentHeader::parse("No front matter"), DocumentHeader::default());
+    }
+
+    #[test]
+    fn test_to_html() {
+        let header = DocumentHeader { title: Some("R&D".to_string()), tags: vec!["<b>".to_string()] };
+        let html = header.to_html(&["3 min read".to_string(), "2026-10-16".to_string()]);
+        assert_eq!(
+            html,
+            "<header class=\"doc-header\">\n<p class=\"doc-title\">R&amp;D</p>\n\
+             <p class=\"doc-meta\">3 min read · 2026-10-16</p>\n<ul class=\"doc-tags\"><li>&lt;b&gt;</li></ul>\n</header>\n"
+        );
+    }
+}
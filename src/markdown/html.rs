@@ -0,0 +1,422 @@
+//! Whitelisted HTML in Markdown, mapped onto preview elements
+
+use super::ElementKind;
+
+/// Tags dropped together with everything inside them
+const DROPPED_WITH_CONTENT: [&str; 9] = [
+    "script", "style", "iframe", "object", "template", "noscript", "textarea", "title", "select",
+];
+
+/// A piece of an HTML fragment
+#[derive(Debug, PartialEq, Eq)]
+enum Token<'a> {
+    Open { name: String, attrs: Vec<(String, String)> },
+    Close(String),
+    Text(&'a str),
+}
+
+/// What an HTML fragment contributes to the preview
+#[derive(Debug)]
+pub enum HtmlEvent {
+    Element(ElementKind),
+    /// `<details>`; elements up to the matching `DetailsEnd` belong inside it
+    DetailsStart { open: bool },
+    DetailsSummary(String),
+    DetailsEnd,
+}
+
+/// Tracks tags left open across the fragments the Markdown parser emits,
+/// so `<b>` in one inline HTML event styles the text events that follow
+#[derive(Default)]
+pub struct HtmlState {
+    bold: usize,
+    italic: usize,
+    code: usize,
+    strikethrough: usize,
+    /// Destination and text of the `<a>` being read
+    link: Option<(String, String)>,
+    /// Text of the `<summary>` being read
+    summary: Option<String>,
+    /// Tag whose content is being dropped
+    skipping: Option<String>,
+}
+
+impl HtmlState {
+    /// Whether text outside HTML currently needs styling or capturing
+    pub fn is_active(&self) -> bool {
+        self.bold + self.italic + self.code + self.strikethrough > 0
+            || self.link.is_some()
+            || self.summary.is_some()
+            || self.skipping.is_some()
+    }
+
+    /// Styled element for text inside open tags, or `None` when the text is
+    /// captured by a link or summary, dropped, or only whitespace
+    pub fn text(&mut self, text: &str) -> Option<ElementKind> {
+        if self.skipping.is_some() {
+            return None;
+        }
+        if let Some((_, link_text)) = self.link.as_mut() {
+            link_text.push_str(text);
+            return None;
+        }
+        if let Some(summary) = self.summary.as_mut() {
+            summary.push_str(text);
+            return None;
+        }
+        if text.trim().is_empty() {
+            return None;
+        }
+        let text = text.to_string();
+        Some(if self.code > 0 {
+            ElementKind::InlineCode(text)
+        } else if self.strikethrough > 0 {
+            ElementKind::Strikethrough(text)
+        } else if self.bold > 0 {
+            ElementKind::Strong(text)
+        } else if self.italic > 0 {
+            ElementKind::Emphasis(text)
+        } else {
+            ElementKind::Paragraph(text)
+        })
+    }
+
+    /// End of an HTML block: a dropped tag left open in it ends with the
+    /// block instead of hiding the rest of the document
+    pub fn end_block(&mut self) {
+        self.skipping = None;
+    }
+
+    /// Process an HTML fragment. Supported tags become elements or styles;
+    /// scripts and similar are dropped with their content, and any other tag
+    /// is dropped while its text is kept.
+    pub fn feed(&mut self, html: &str) -> Vec<HtmlEvent> {
+        let mut events = Vec::new();
+        for token in tokenize(html) {
+            if let Some(skipping) = &self.skipping {
+                if token == Token::Close(skipping.clone()) {
+                    self.skipping = None;
+                }
+                continue;
+            }
+            match token {
+                Token::Open { name, attrs } => {
+                    let attr = |key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+                    match name.as_str() {
+                        name if DROPPED_WITH_CONTENT.contains(&name) => self.skipping = Some(name.to_string()),
+                        "br" => events.push(HtmlEvent::Element(ElementKind::LineBreak)),
+                        "hr" => events.push(HtmlEvent::Element(ElementKind::HorizontalRule)),
+                        "img" => {
+                            if let Some(src) = attr("src").filter(|src| is_safe_url(src)) {
+                                let alt = attr("alt").unwrap_or_default().to_string();
+                                events.push(HtmlEvent::Element(ElementKind::Image(alt, src.to_string())));
+                            }
+                        }
+                        "a" => {
+                            if let Some(href) = attr("href").filter(|href| is_safe_url(href)) {
+                                self.link = Some((href.to_string(), String::new()));
+                            }
+                        }
+                        "b" | "strong" => self.bold += 1,
+                        "i" | "em" => self.italic += 1,
+                        "code" | "kbd" | "samp" | "tt" => self.code += 1,
+                        "s" | "del" | "strike" => self.strikethrough += 1,
+                        "details" => events.push(HtmlEvent::DetailsStart {
+                            open: attr("open").is_some(),
+                        }),
+                        "summary" => self.summary = Some(String::new()),
+                        _ => {}
+                    }
+                }
+                Token::Close(name) => match name.as_str() {
+                    "a" => {
+                        if let Some((href, text)) = self.link.take() {
+                            let text = if text.trim().is_empty() { href.clone() } else { text };
+                            events.push(HtmlEvent::Element(ElementKind::Link(text, href)));
+                        }
+                    }
+                    "b" | "strong" => self.bold = self.bold.saturating_sub(1),
+                    "i" | "em" => self.italic = self.italic.saturating_sub(1),
+                    "code" | "kbd" | "samp" | "tt" => self.code = self.code.saturating_sub(1),
+                    "s" | "del" | "strike" => self.strikethrough = self.strikethrough.saturating_sub(1),
+                    "summary" => {
+                        if let Some(summary) = self.summary.take() {
+                            events.push(HtmlEvent::DetailsSummary(summary.trim().to_string()));
+                        }
+                    }
+                    "details" => events.push(HtmlEvent::DetailsEnd),
+                    _ => {}
+                },
+                Token::Text(text) => {
+                    if let Some(element) = self.text(&decode_entities(text)) {
+                        events.push(HtmlEvent::Element(element));
+                    }
+                }
+            }
+        }
+        events
+    }
+}
+
+/// Links and images may not run code
+fn is_safe_url(url: &str) -> bool {
+    let url = url.trim().to_ascii_lowercase();
+    !["javascript:", "vbscript:", "data:"].iter().any(|scheme| url.starts_with(scheme))
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest[1..].find(';').filter(|&end| end <= 32).and_then(|end| Some((end + 2, entity(&rest[1..end + 1])?)));
+        match reference {
+            Some((len, c)) => {
+                decoded.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The character of an entity reference, without its `&` and `;`: a number
+/// like `#169` or `#xA9`, or one of the names in common use. A number that
+/// isn't a character stands for U+FFFD, as in a browser.
+fn entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) if (1..=6).contains(&hex.len()) => u32::from_str_radix(hex, 16).ok()?,
+            None if (1..=7).contains(&number.len()) => number.parse().ok()?,
+            _ => return None,
+        };
+        return Some(char::from_u32(code).filter(|&c| c != '\0').unwrap_or('\u{fffd}'));
+    }
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "hellip" => '…',
+        "mdash" => '—',
+        "ndash" => '–',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "laquo" => '«',
+        "raquo" => '»',
+        "bull" => '•',
+        "middot" => '·',
+        "deg" => '°',
+        "plusmn" => '±',
+        "times" => '×',
+        "divide" => '÷',
+        "para" => '¶',
+        "sect" => '§',
+        "euro" => '€',
+        "pound" => '£',
+        "yen" => '¥',
+        "cent" => '¢',
+        "larr" => '←',
+        "rarr" => '→',
+        "uarr" => '↑',
+        "darr" => '↓',
+        "harr" => '↔',
+        "le" => '≤',
+        "ge" => '≥',
+        "ne" => '≠',
+        "shy" => '\u{ad}',
+        "zwj" => '\u{200d}',
+        "zwnj" => '\u{200c}',
+        _ => return None,
+    };
+    Some(c)
+}
+
+/// Split HTML into tags and text. Comments and declarations are skipped,
+/// and a `<` that doesn't start a tag is kept as text.
+fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+
+    while let Some(offset) = html[i..].find('<') {
+        let start = i + offset;
+        let rest = &html[start..];
+        let parsed = if rest.starts_with("<!--") {
+            rest.find("-->").map(|end| (end + 3, None))
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest.find('>').map(|end| (end + 1, None))
+        } else {
+            parse_tag(rest).map(|(len, token)| (len, Some(token)))
+        };
+
+        match parsed {
+            Some((len, token)) => {
+                if text_start < start {
+                    tokens.push(Token::Text(&html[text_start..start]));
+                }
+                tokens.extend(token);
+                i = start + len;
+                text_start = i;
+            }
+            None => i = start + 1,
+        }
+    }
+    if text_start < html.len() {
+        tokens.push(Token::Text(&html[text_start..]));
+    }
+    tokens
+}
+
+/// Parse an opening or closing tag at the start of `text`, returning its length
+fn parse_tag(text: &str) -> Option<(usize, Token<'static>)> {
+    let (closing, body) = match text.strip_prefix("</") {
+        Some(body) => (true, body),
+        None => (false, &text[1..]),
+    };
+    let name_len = body.find(|c: char| !c.is_ascii_alphanumeric() && c != '-').unwrap_or(body.len());
+    if name_len == 0 || !body.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let name = body[..name_len].to_ascii_lowercase();
+
+    let mut attrs = Vec::new();
+    let mut rest = &body[name_len..];
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix("/>").or_else(|| rest.strip_prefix('>')) {
+            let len = text.len() - after.len();
+            let token = if closing {
+                Token::Close(name)
+            } else {
+                Token::Open { name, attrs }
+            };
+            return Some((len, token));
+        }
+        let key_len = rest.find(|c: char| c.is_whitespace() || "=>/".contains(c))?;
+        if key_len == 0 {
+            // A stray `/` inside the tag
+            rest = rest.get(1..)?;
+            continue;
+        }
+        let key = rest[..key_len].to_ascii_lowercase();
+        rest = rest[key_len..].trim_start();
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (raw, remaining) = match after.chars().next()? {
+                quote @ ('"' | '\'') => {
+                    let end = after[1..].find(quote)? + 1;
+                    (&after[1..end], &after[end + 1..])
+                }
+                _ => {
+                    let end = after.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            value = decode_entities(raw);
+            rest = remaining;
+        }
+        attrs.push((key, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::MarkdownRenderer;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize("a <img src=\"x.png\" alt='A &amp; B' /> b<!-- c --></P> 1 < 2");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Text("a "),
+                Token::Open {
+                    name: "img".to_string(),
+                    attrs: vec![("src".to_string(), "x.png".to_string()), ("alt".to_string(), "A & B".to_string())],
+                },
+                Token::Text(" b"),
+                Token::Close("p".to_string()),
+                Token::Text(" 1 < 2"),
+            ]
+        );
+        assert_eq!(
+            tokenize("<details open>"),
+            vec![Token::Open {
+                name: "details".to_string(),
+                attrs: vec![("open".to_string(), String::new())],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(decode_entities("&copy; 2024 &amp; &#169; &#xA9; &#X22;"), "© 2024 & © © \"");
+        // Unknown names, bad numbers and stray ampersands stay as written
+        assert_eq!(decode_entities("&bogus; &#; &#xZZ; AT&T &amp"), "&bogus; &#; &#xZZ; AT&T &amp");
+        assert_eq!(decode_entities("&#0;&#1234567;&#12345678;"), "\u{fffd}\u{fffd}&#12345678;");
+    }
+
+    #[test]
+    fn test_feed_maps_supported_tags() {
+        let mut state = HtmlState::default();
+        let events = state.feed("<p>Hi <b>there</b><br><script>alert(1)</script><a href=\"javascript:x\">no</a></p>");
+        let elements: Vec<String> = events
+            .into_iter()
+            .map(|event| match event {
+                HtmlEvent::Element(element) => format!("{:?}", element),
+                other => format!("{:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            elements,
+            vec![
+                "Paragraph(\"Hi \")".to_string(),
+                "Strong(\"there\")".to_string(),
+                "LineBreak".to_string(),
+                "Paragraph(\"no\")".to_string(),
+            ]
+        );
+        assert!(!state.is_active());
+    }
+
+    #[test]
+    fn test_unclosed_script_ends_with_its_block() {
+        let renderer = MarkdownRenderer::default();
+        let elements = renderer.render("<div><script>hidden = 1\n\nStill shown\n");
+        let rendered = format!("{:?}", elements);
+        assert!(!rendered.contains("hidden"));
+        assert!(rendered.contains("Still shown"));
+    }
+
+    #[test]
+    fn test_render_html_in_markdown() {
+        let mut renderer = MarkdownRenderer::default();
+        let markdown = "Press <kbd>Ctrl</kbd> now.\n\n<details open>\n<summary>More</summary>\n\nHidden *text*\n\n</details>\n";
+        let elements = renderer.render(markdown);
+        assert!(elements.iter().any(|e| matches!(&e.kind, ElementKind::InlineCode(code) if code == "Ctrl")));
+        let details_offset = markdown.find("<details").unwrap();
+        assert!(elements.iter().any(|e| matches!(&e.kind, ElementKind::Details(summary, true, body)
+            if summary == "More" && !body.is_empty() && e.span.start == details_offset)));
+
+        renderer.set_render_html(false);
+        let elements = renderer.render(markdown);
+        assert!(elements.iter().any(|e| matches!(e.kind, ElementKind::RawHtml(_))));
+        assert!(!elements.iter().any(|e| matches!(e.kind, ElementKind::Details(..))));
+    }
+}
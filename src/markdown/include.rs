@@ -0,0 +1,250 @@
+//! Include directives, so a big document can be split into parts: a line
+//! `<!-- include: ./part1.md -->`, or `![[part1]]` with wiki links on, shows
+//! the named file's content in its place, in the preview and in exports
+
+use super::incremental::adjust_spans;
+use super::{fence_marker, ElementKind, RenderedElement};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Deepest chain of includes followed, as a guard against runaway nesting
+pub const MAX_DEPTH: usize = 8;
+
+/// An include directive found in the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct Directive {
+    /// The file as written
+    pub target: String,
+    /// Byte range of the directive's line, with its newline
+    pub line: Range<usize>,
+}
+
+/// Why an included file can't be shown
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum IncludeError {
+    #[error("Save the document before including {0}, which is relative to it")]
+    Unsaved(String),
+    #[error("Include cycle: {0}")]
+    Cycle(String),
+    #[error("Includes are nested more than {MAX_DEPTH} deep")]
+    TooDeep,
+    #[error("Can't include {}: {}", .0.display(), .1)]
+    Unreadable(PathBuf, String),
+}
+
+/// The file a whole `line` includes, if it is an include directive
+pub fn directive_target(line: &str, wiki: bool) -> Option<&str> {
+    let line = line.trim();
+    if let Some(comment) = line.strip_prefix("<!--").and_then(|rest| rest.strip_suffix("-->")) {
+        let target = comment.trim().strip_prefix("include:")?.trim();
+        return (!target.is_empty()).then_some(target);
+    }
+    if wiki {
+        let inner = line.strip_prefix("![[")?.strip_suffix("]]")?;
+        // Any alias after `|` is only a label
+        let target = inner.split('|').next().unwrap_or_default().trim();
+        return (!target.is_empty() && !inner.contains(['[', ']'])).then_some(target);
+    }
+    None
+}
+
+/// Include directives outside fenced code blocks
+pub fn find_includes(markdown: &str, wiki: bool) -> Vec<Directive> {
+    let mut directives = Vec::new();
+    let mut fence: Option<(char, usize)> = None;
+    let mut offset = 0;
+
+    for line in markdown.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        if let Some((marker, len)) = fence_marker(line) {
+            match fence {
+                None => fence = Some((marker, len)),
+                Some((open, open_len)) if open == marker && len >= open_len => fence = None,
+                Some(_) => {}
+            }
+            continue;
+        }
+        if fence.is_none() {
+            if let Some(target) = directive_target(line, wiki) {
+                directives.push(Directive { target: target.to_string(), line: line_start..offset });
+            }
+        }
+    }
+    directives
+}
+
+/// The file `target` names, relative to `folder`. A target without an
+/// extension is a Markdown note, as wiki links name them.
+pub fn resolve(target: &str, folder: &Path) -> PathBuf {
+    let mut path = folder.join(target);
+    if path.extension().is_none() {
+        path.set_extension("md");
+    }
+    path
+}
+
+/// Read the file `target` names, unless including it would loop or nest
+/// too deep. `stack` holds the files being included, outermost first.
+fn load(target: &str, folder: Option<&Path>, stack: &[PathBuf]) -> Result<(PathBuf, String), IncludeError> {
+    let folder = folder.ok_or_else(|| IncludeError::Unsaved(target.to_string()))?;
+    if stack.len() > MAX_DEPTH {
+        return Err(IncludeError::TooDeep);
+    }
+    let path = resolve(target, folder);
+    let path = path.canonicalize().map_err(|e| IncludeError::Unreadable(path, e.to_string()))?;
+    if let Some(start) = stack.iter().position(|file| *file == path) {
+        let chain: Vec<String> = stack[start..]
+            .iter()
+            .chain(std::iter::once(&path))
+            .map(|file| file.file_name().unwrap_or_default().to_string_lossy().to_string())
+            .collect();
+        return Err(IncludeError::Cycle(chain.join(" → ")));
+    }
+    let text = std::fs::read_to_string(&path).map_err(|e| IncludeError::Unreadable(path.clone(), e.to_string()))?;
+    Ok((path, crate::utils::normalize_line_endings(&text)))
+}
+
+/// The files being included, starting with the document itself
+fn initial_stack(document: Option<&Path>) -> Vec<PathBuf> {
+    document.and_then(|path| path.canonicalize().ok()).into_iter().collect()
+}
+
+/// `markdown` with each include directive replaced by the file it names,
+/// for exporting. A file that can't be included leaves a note in a quote.
+pub fn expand(markdown: &str, document: Option<&Path>, wiki: bool) -> String {
+    if find_includes(markdown, wiki).is_empty() {
+        return markdown.to_string();
+    }
+    let mut expanded = String::with_capacity(markdown.len());
+    let mut stack = initial_stack(document);
+    expand_into(&mut expanded, markdown, document.and_then(Path::parent), wiki, &mut stack);
+    expanded
+}
+
+fn expand_into(out: &mut String, markdown: &str, folder: Option<&Path>, wiki: bool, stack: &mut Vec<PathBuf>) {
+    let mut copied = 0;
+    for directive in find_includes(markdown, wiki) {
+        out.push_str(&markdown[copied..directive.line.start]);
+        copied = directive.line.end;
+        // Blank lines around, so the part never runs into a paragraph
+        if !out.is_empty() && !out.ends_with("\n\n") {
+            out.push_str(if out.ends_with('\n') { "\n" } else { "\n\n" });
+        }
+        match load(&directive.target, folder, stack) {
+            Ok((path, text)) => {
+                stack.push(path);
+                let folder = stack.last().and_then(|path| path.parent()).map(Path::to_path_buf);
+                expand_into(out, &text, folder.as_deref(), wiki, stack);
+                stack.pop();
+            }
+            Err(e) => out.push_str(&format!("> ⚠ {}\n", e)),
+        }
+        if !out.ends_with('\n') {
+            out.push('\n');
+        }
+        if !out.ends_with("\n\n") {
+            out.push('\n');
+        }
+    }
+    out.push_str(&markdown[copied..]);
+}
+
+/// Put the content of included files among `elements`, rendered from
+/// `markdown`, in place of the directives. `render` renders a file's text
+/// and `files` collects every file read, to notice when one changes.
+/// Included elements take the directive's span, so they lead back to it.
+pub fn include_elements(
+    elements: Vec<RenderedElement>,
+    markdown: &str,
+    document: Option<&Path>,
+    wiki: bool,
+    render: &dyn Fn(&str) -> Vec<RenderedElement>,
+    files: &mut Vec<PathBuf>,
+) -> Vec<RenderedElement> {
+    let mut stack = initial_stack(document);
+    include_into(elements, markdown, document.and_then(Path::parent), wiki, render, files, &mut stack)
+}
+
+fn include_into(
+    mut elements: Vec<RenderedElement>,
+    markdown: &str,
+    folder: Option<&Path>,
+    wiki: bool,
+    render: &dyn Fn(&str) -> Vec<RenderedElement>,
+    files: &mut Vec<PathBuf>,
+    stack: &mut Vec<PathBuf>,
+) -> Vec<RenderedElement> {
+    for directive in find_includes(markdown, wiki) {
+        let line = &directive.line;
+        // Whatever the directive itself rendered as, such as a comment shown
+        // as raw HTML or a wiki embed's image
+        elements.retain(|element| !(element.span.start >= line.start && element.span.end <= line.end));
+        let at = elements.partition_point(|element| element.span.start < line.start);
+
+        let path = folder.map_or_else(|| PathBuf::from(&directive.target), |folder| resolve(&directive.target, folder));
+        let content = match load(&directive.target, folder, stack) {
+            Ok((path, text)) => {
+                files.push(path.clone());
+                stack.push(path);
+                let folder = stack.last().and_then(|path| path.parent()).map(Path::to_path_buf);
+                let mut included = include_into(render(&text), &text, folder.as_deref(), wiki, render, files, stack);
+                stack.pop();
+                for element in &mut included {
+                    adjust_spans(element, &|span| *span = line.clone().into());
+                }
+                Ok(included)
+            }
+            Err(e) => Err(e.to_string()),
+        };
+        elements.insert(at, RenderedElement::new(ElementKind::Include(path, content), line.clone()));
+    }
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_includes() {
+        let markdown = "# Book\n<!-- include: ./part1.md -->\n```\n<!-- include: code.md -->\n```\n  ![[part2]]  \n";
+        let targets: Vec<String> = find_includes(markdown, true).into_iter().map(|d| d.target).collect();
+        assert_eq!(targets, ["./part1.md", "part2"]);
+        assert_eq!(find_includes(markdown, false).len(), 1);
+        assert_eq!(find_includes(markdown, false)[0].line, 7..36);
+        assert_eq!(directive_target("<!-- a comment -->", true), None);
+        assert_eq!(directive_target("see ![[part]]", true), None);
+        assert_eq!(resolve("part2", Path::new("/notes")), Path::new("/notes/part2.md"));
+    }
+
+    #[test]
+    fn test_expand_with_cycle_and_depth() {
+        let folder = std::env::temp_dir().join(format!("rmd-include-{}", std::process::id()));
+        std::fs::create_dir_all(folder.join("parts")).unwrap();
+        let main = folder.join("main.md");
+        std::fs::write(&main, "").unwrap();
+        std::fs::write(folder.join("parts/a.md"), "A\n<!-- include: b.md -->\n").unwrap();
+        std::fs::write(folder.join("parts/b.md"), "B\n<!-- include: a.md -->").unwrap();
+        std::fs::write(folder.join("self.md"), "<!-- include: main.md -->\n").unwrap();
+
+        let expanded = expand("# Main\n<!-- include: parts/a.md -->\nEnd\n", Some(&main), false);
+        assert_eq!(expanded, "# Main\n\nA\n\nB\n\n> ⚠ Include cycle: a.md → b.md → a.md\n\nEnd\n");
+        let expanded = expand("<!-- include: self.md -->\n", Some(&main), false);
+        assert_eq!(expanded, "> ⚠ Include cycle: main.md → self.md → main.md\n\n");
+        assert!(expand("<!-- include: gone.md -->\n", Some(&main), false).starts_with("> ⚠ Can't include"));
+        assert_eq!(
+            expand("<!-- include: a.md -->\n", None, false),
+            "> ⚠ Save the document before including a.md, which is relative to it\n\n"
+        );
+
+        // A chain longer than the limit stops with a note instead of going on
+        for i in 0..=MAX_DEPTH + 1 {
+            std::fs::write(folder.join(format!("deep{}.md", i)), format!("<!-- include: deep{}.md -->\n", i + 1)).unwrap();
+        }
+        assert!(expand("<!-- include: deep0.md -->\n", Some(&main), false).contains("nested more than"));
+
+        std::fs::remove_dir_all(&folder).unwrap();
+    }
+}
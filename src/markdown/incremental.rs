@@ -0,0 +1,372 @@
+//! Re-rendering only the blocks of a document that changed since the last
+//! render
+
+use super::flavor::Flavor;
+use super::{fence_marker, ElementKind, MarkdownRenderer, RenderedElement, SourceSpan};
+use pulldown_cmark::Options;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+
+/// A top-level block as last rendered
+struct Block {
+    hash: u64,
+    /// Elements with spans relative to the start of the block
+    elements: Vec<RenderedElement>,
+}
+
+/// Rendered blocks of the last document, for reuse by the next render
+#[derive(Default)]
+pub struct IncrementalRender {
+    blocks: Vec<Block>,
+    /// Renderer settings the blocks were rendered with
+    settings: u64,
+    /// Blocks rendered by the last call, for telling how much was reused
+    rendered: usize,
+}
+
+impl IncrementalRender {
+    /// Render `markdown`, parsing only the blocks that differ from the last
+    /// call's. Falls back to rendering the whole document when it can't be
+    /// split into independent blocks.
+    pub fn render(&mut self, renderer: &MarkdownRenderer, markdown: &str) -> Vec<RenderedElement> {
+        let flavor = renderer.flavor_for(markdown);
+        let settings = renderer.settings_key(flavor);
+        let Some(Blocks { ranges, footnotes }) = split_blocks(markdown) else {
+            self.blocks.clear();
+            self.rendered = 1;
+            return renderer.render(markdown);
+        };
+        if settings != self.settings {
+            self.blocks.clear();
+            self.settings = settings;
+        }
+
+        // References depend on which footnotes the rest of the document defines
+        let footnotes_enabled = renderer.options(flavor).contains(Options::ENABLE_FOOTNOTES);
+        let footnotes = if footnotes_enabled { footnotes } else { Vec::new() };
+        let footnotes_hash = hash(&footnotes);
+        let hashes: Vec<u64> = ranges
+            .iter()
+            .map(|range| {
+                let block = &markdown[range.clone()];
+                if uses_footnotes(block, &footnotes) {
+                    hash(&(block, footnotes_hash))
+                } else {
+                    hash(block)
+                }
+            })
+            .collect();
+        // Blocks unchanged at the start and at the end keep their elements
+        let prefix = self.blocks.iter().zip(&hashes).take_while(|(block, hash)| block.hash == **hash).count();
+        let max_suffix = self.blocks.len().min(hashes.len()) - prefix;
+        let suffix = self
+            .blocks
+            .iter()
+            .rev()
+            .zip(hashes.iter().rev())
+            .take(max_suffix)
+            .take_while(|(block, hash)| block.hash == **hash)
+            .count();
+
+        let changed = prefix..hashes.len() - suffix;
+        let fresh: Vec<Block> = changed
+            .clone()
+            .map(|index| Block {
+                hash: hashes[index],
+                elements: render_block(renderer, &markdown[ranges[index].clone()], flavor, &footnotes),
+            })
+            .collect();
+        self.rendered = fresh.len();
+        let old_end = self.blocks.len() - suffix;
+        self.blocks.splice(prefix..old_end, fresh);
+
+        let mut elements = Vec::new();
+        for (block, range) in self.blocks.iter().zip(&ranges) {
+            elements.extend(block.elements.iter().cloned().map(|mut element| {
+                adjust_spans(&mut element, &|span| {
+                    span.start += range.start;
+                    span.end += range.start;
+                });
+                element
+            }));
+        }
+        renderer.finish(&mut elements, flavor);
+        elements
+    }
+
+    /// How many blocks the last render parsed
+    pub fn rendered_blocks(&self) -> usize {
+        self.rendered
+    }
+
+    /// How many blocks are kept for the next render
+    pub fn cached_blocks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Rough size of the kept elements, without what they point to
+    pub fn cached_bytes(&self) -> usize {
+        self.blocks.iter().map(|block| block.elements.capacity()).sum::<usize>() * std::mem::size_of::<RenderedElement>()
+    }
+}
+
+fn hash(value: &(impl Hash + ?Sized)) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn uses_footnotes(block: &str, footnotes: &[String]) -> bool {
+    !footnotes.is_empty() && block.contains("[^")
+}
+
+/// Elements of one block, with spans relative to its start. The parser only
+/// turns `[^label]` into a reference when the label is defined, so the
+/// document's footnote definitions are appended for it to see, and their
+/// elements dropped again.
+fn render_block(renderer: &MarkdownRenderer, block: &str, flavor: Flavor, footnotes: &[String]) -> Vec<RenderedElement> {
+    if !uses_footnotes(block, footnotes) {
+        return renderer.render_blocks(block, flavor);
+    }
+    let mut text = block.to_string();
+    if !text.ends_with('\n') {
+        text.push('\n');
+    }
+    for label in footnotes {
+        text.push_str(&format!("\n[^{}]: .\n", label));
+    }
+    let mut elements = renderer.render_blocks(&text, flavor);
+    elements.retain(|element| element.span.start < block.len());
+    for element in &mut elements {
+        adjust_spans(element, &|span| span.end = span.end.min(block.len()));
+    }
+    elements
+}
+
+/// Call `f` on the span of `element` and of everything inside it
+pub(super) fn adjust_spans(element: &mut RenderedElement, f: &impl Fn(&mut SourceSpan)) {
+    f(&mut element.span);
+    match &mut element.kind {
+        ElementKind::BlockQuote(items, _)
+        | ElementKind::Details(_, _, items)
+        | ElementKind::FootnoteDefinition(_, _, _, items)
+        | ElementKind::Include(_, Ok(items)) => items.iter_mut().for_each(|e| adjust_spans(e, f)),
+        ElementKind::UnorderedList(items, _) | ElementKind::OrderedList(items, _) => {
+            items.iter_mut().flatten().for_each(|e| adjust_spans(e, f));
+        }
+        _ => {}
+    }
+}
+
+/// Label of the footnote `line` defines, `[^label]: text`
+fn footnote_label(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let label = &trimmed.strip_prefix("[^")?[..trimmed[2..].find("]:")?];
+    (!label.is_empty() && !label.contains(|c: char| c.is_whitespace() || c == ']')).then_some(label)
+}
+
+/// Kind of list item a line starts, for keeping loose lists in one block
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ListMarker {
+    Bullet,
+    Ordered,
+}
+
+fn list_marker(line: &str) -> Option<ListMarker> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let rest = trimmed.trim_start_matches(|c: char| c.is_ascii_digit());
+    let digits = trimmed.len() - rest.len();
+    let marker = match rest.chars().next()? {
+        '.' | ')' if (1..=9).contains(&digits) => ListMarker::Ordered,
+        '-' | '*' | '+' if digits == 0 => ListMarker::Bullet,
+        _ => return None,
+    };
+    // `- - -` and `* * *` are rules, not list items
+    let is_rule = trimmed.chars().filter(|c| !c.is_whitespace()).all(|c| matches!(c, '-' | '*' | '_'))
+        && trimmed.chars().filter(|c| !c.is_whitespace()).count() >= 3;
+    let after = &rest[1..];
+    (!is_rule && (after.is_empty() || after.starts_with([' ', '\t']))).then_some(marker)
+}
+
+/// Whether `line` defines a link reference, `[label]: url`
+fn is_reference_definition(line: &str) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 || !trimmed.starts_with('[') || trimmed.starts_with("[^") {
+        return false;
+    }
+    trimmed.find("]:").is_some_and(|end| end > 1 && !trimmed[1..end].contains(']'))
+}
+
+/// A document split into blocks that render the same on their own
+pub struct Blocks {
+    pub ranges: Vec<Range<usize>>,
+    /// Labels of the footnotes the document defines
+    pub footnotes: Vec<String>,
+}
+
+/// Byte ranges of the top-level blocks of `markdown`: runs of lines ending
+/// at a blank line, each with the blank lines after it. Fenced code, HTML
+/// comments, front matter, indented continuations, and the items of a
+/// loose list stay in one block. `None` when blocks would render
+/// differently on their own: when the document defines link references,
+/// or has HTML blocks that may span blank lines.
+pub fn split_blocks(markdown: &str) -> Option<Blocks> {
+    let lower = markdown.to_ascii_lowercase();
+    if ["<details", "<pre", "<script", "<style", "<textarea"].iter().any(|tag| lower.contains(tag)) {
+        return None;
+    }
+
+    let lines: Vec<(usize, &str)> = markdown
+        .split_inclusive('\n')
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len();
+            Some((start, line.trim_end_matches(['\n', '\r'])))
+        })
+        .collect();
+
+    let mut blocks = Vec::new();
+    let mut footnotes = Vec::new();
+    let mut block_start = 0;
+    let mut block_list: Option<ListMarker> = None;
+    let mut fence: Option<(char, usize)> = None;
+    let mut in_comment = false;
+    let mut in_front_matter = markdown.starts_with("---\n") || markdown.starts_with("---\r\n");
+    let mut after_blank = false;
+
+    for (index, &(start, line)) in lines.iter().enumerate() {
+        if in_front_matter {
+            if index > 0 && (line == "---" || line == "...") {
+                in_front_matter = false;
+            }
+            continue;
+        }
+        if let Some((marker, len)) = fence {
+            let closes = fence_marker(line).is_some_and(|(m, l)| m == marker && l >= len)
+                && line.trim().trim_start_matches(marker).is_empty();
+            if closes {
+                fence = None;
+            }
+            continue;
+        }
+        if in_comment {
+            in_comment = !line.contains("-->");
+            continue;
+        }
+
+        let blank = line.trim().is_empty();
+        if blank {
+            after_blank = true;
+            continue;
+        }
+        if after_blank {
+            after_blank = false;
+            let indented = line.starts_with([' ', '\t']);
+            let same_list = block_list.is_some() && list_marker(line) == block_list;
+            if !indented && !same_list && start > block_start {
+                blocks.push(block_start..start);
+                block_start = start;
+                block_list = None;
+            }
+        }
+        if start == block_start {
+            block_list = list_marker(line);
+        }
+
+        if is_reference_definition(line) {
+            return None;
+        }
+        if let Some(label) = footnote_label(line) {
+            footnotes.push(label.to_string());
+        }
+        if let Some(open) = fence_marker(line) {
+            fence = Some(open);
+        } else if let Some(comment) = line.find("<!--") {
+            in_comment = !line[comment..].contains("-->");
+        }
+    }
+    if block_start < markdown.len() || blocks.is_empty() {
+        blocks.push(block_start..markdown.len());
+    }
+    Some(Blocks { ranges: blocks, footnotes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::flavor::Flavor;
+    use crate::theme::Theme;
+
+    fn texts(markdown: &str) -> Vec<&str> {
+        split_blocks(markdown).unwrap().ranges.into_iter().map(|range| &markdown[range]).collect()
+    }
+
+    #[test]
+    fn test_split_blocks() {
+        assert_eq!(texts("# Title\n\nOne\ntwo\n\n\nThree\n"), ["# Title\n\n", "One\ntwo\n\n\n", "Three\n"]);
+        // Fences, comments, loose lists, and indented continuations hold together
+        assert_eq!(texts("```\na\n\nb\n```\n\nx\n"), ["```\na\n\nb\n```\n\n", "x\n"]);
+        assert_eq!(texts("<!-- a\n\nb -->\n\nx\n"), ["<!-- a\n\nb -->\n\n", "x\n"]);
+        assert_eq!(texts("- a\n\n- b\n\n  more\n\nafter\n"), ["- a\n\n- b\n\n  more\n\n", "after\n"]);
+        assert_eq!(texts("1. a\n\n2. b\n\n- c\n"), ["1. a\n\n2. b\n\n", "- c\n"]);
+        assert_eq!(texts("---\ntitle: x\n\nmore: y\n---\n\nBody\n"), ["---\ntitle: x\n\nmore: y\n---\n\n", "Body\n"]);
+        assert_eq!(texts(""), [""]);
+
+        assert!(split_blocks("See [x].\n\n[x]: https://example.com\n").is_none());
+        assert!(split_blocks("<details>\n\nHidden\n\n</details>\n").is_none());
+        assert_eq!(split_blocks("Note[^1].\n\n[^1]: Text.\n").unwrap().footnotes, ["1"]);
+    }
+
+    fn debug(elements: &[RenderedElement]) -> String {
+        format!("{:?}", elements)
+    }
+
+    #[test]
+    fn test_matches_full_render() {
+        let mut renderer = MarkdownRenderer::new(&Theme::default());
+        renderer.set_flavor(Flavor::Extended);
+        renderer.set_number_headings(true);
+        let mut incremental = IncrementalRender::default();
+        let mut doc = String::from(
+            "# One\n\nText with a note[^n] and https://example.com.\n\n\
+             ```rust\nfn main() {}\n\n// more\n```\n\n\
+             - a\n\n- b\n\n> quote\n\n## Two\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n[^n]: The note.\n",
+        );
+        assert_eq!(debug(&incremental.render(&renderer, &doc)), debug(&renderer.render(&doc)));
+
+        // Editing a paragraph, removing a fence, adding a heading
+        let edits = [("Text with", "Edited text with"), ("```rust\n", ""), ("> quote", "# New\n\n> quote")];
+        for (from, to) in edits {
+            doc = doc.replacen(from, to, 1);
+            assert_eq!(debug(&incremental.render(&renderer, &doc)), debug(&renderer.render(&doc)), "{}", doc);
+        }
+    }
+
+    #[test]
+    fn test_only_the_edited_block_is_rendered() {
+        let renderer = MarkdownRenderer::new(&Theme::default());
+        let mut incremental = IncrementalRender::default();
+        let mut doc = String::new();
+        for n in 0..1000 {
+            doc.push_str(&format!("## Section {}\n\nParagraph {} with *emphasis*\nand a second line.\n\n", n, n));
+        }
+        assert_eq!(doc.lines().count(), 5000);
+        incremental.render(&renderer, &doc);
+        assert_eq!(incremental.rendered_blocks(), 2000);
+
+        let edited = doc.replacen("Paragraph 700 with", "Paragraph 700, edited, with", 1);
+        let elements = incremental.render(&renderer, &edited);
+        assert_eq!(incremental.rendered_blocks(), 1);
+        assert_eq!(debug(&elements), debug(&renderer.render(&edited)));
+
+        incremental.render(&renderer, &edited);
+        assert_eq!(incremental.rendered_blocks(), 0);
+    }
+}
@@ -0,0 +1,37 @@
+//! The language a document is written in, as a tag such as `en`, `ja` or
+//! `zh-Hant`. Exported pages carry it, and Chinese, Japanese and Korean
+//! documents are typeset and counted by character rather than by word.
+
+/// Whether `tag` is shaped like a language tag: two or three letters,
+/// then any number of `-`-separated subtags of up to eight letters or digits
+pub fn is_valid_tag(tag: &str) -> bool {
+    let mut parts = tag.split('-');
+    let primary = parts.next().unwrap_or_default();
+    (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && parts.all(|part| (1..=8).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// Whether the language of `tag` is written without spaces between words:
+/// Chinese, Japanese or Korean
+pub fn is_cjk(tag: &str) -> bool {
+    let primary = tag.split('-').next().unwrap_or_default().to_ascii_lowercase();
+    matches!(primary.as_str(), "zh" | "ja" | "ko" | "cmn" | "yue")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags() {
+        assert!(is_valid_tag("en"));
+        assert!(is_valid_tag("zh-Hant-TW"));
+        assert!(!is_valid_tag("english"));
+        assert!(!is_valid_tag("en_US"));
+        assert!(!is_valid_tag("ja-"));
+        assert!(is_cjk("ja"));
+        assert!(is_cjk("ZH-Hans"));
+        assert!(!is_cjk("en-JA"));
+    }
+}
@@ -0,0 +1,369 @@
+//! Finding broken links and images in a document
+
+use super::flavor::Flavor;
+use super::outline;
+use crate::utils;
+use crate::workspace::links::normalize;
+use pulldown_cmark::{Event, Parser, Tag};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::ops::Range;
+use std::path::{Component, Path};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long to wait for a web server to answer
+const WEB_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Web addresses checked at the same time
+const WEB_WORKERS: usize = 4;
+
+/// Whether a reference is a link or an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    Link,
+    Image,
+}
+
+/// What checking a link target found
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkStatus {
+    /// A local file that exists
+    Found,
+    /// A local file that doesn't exist
+    Missing,
+    /// An anchor matching a heading of this document or the linked one
+    AnchorFound,
+    /// An anchor no heading has
+    AnchorMissing,
+    /// A web address or other URL, not checked
+    External,
+    /// A web address that answered
+    Reachable,
+    /// A web address that failed to answer, and why
+    Unreachable(String),
+}
+
+impl LinkStatus {
+    pub fn is_problem(&self) -> bool {
+        matches!(self, LinkStatus::Missing | LinkStatus::AnchorMissing | LinkStatus::Unreachable(_))
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            LinkStatus::Found => "File exists".to_string(),
+            LinkStatus::Missing => "File not found".to_string(),
+            LinkStatus::AnchorFound => "Heading exists".to_string(),
+            LinkStatus::AnchorMissing => "No heading with this anchor".to_string(),
+            LinkStatus::External => "Not checked".to_string(),
+            LinkStatus::Reachable => "Reachable".to_string(),
+            LinkStatus::Unreachable(reason) => reason.clone(),
+        }
+    }
+}
+
+/// A link or image in the document and the state of its target
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkReference {
+    pub kind: LinkKind,
+    pub url: String,
+    /// Byte range of the whole link or image in the source
+    pub range: Range<usize>,
+    pub status: LinkStatus,
+}
+
+impl LinkReference {
+    /// A web address that can be requested
+    pub fn is_web(&self) -> bool {
+        self.url.starts_with("http://") || self.url.starts_with("https://")
+    }
+}
+
+/// Every link and image in `markdown`, with its target checked. Local paths
+/// are resolved against the folder of `document`; relative ones are left
+/// out when the document hasn't been saved. `#anchors` are checked against
+/// the heading slugs of this document, or of the file linked to when it has
+/// one of the Markdown `extensions`.
+pub fn check_links(
+    markdown: &str,
+    default_flavor: Flavor,
+    document: Option<&Path>,
+    extensions: &[String],
+) -> Vec<LinkReference> {
+    let flavor = super::flavor::front_matter_flavor(markdown).unwrap_or(default_flavor);
+    let own_slugs: HashSet<String> = outline::headings(markdown).into_iter().map(|h| h.slug).collect();
+    let base = document.and_then(Path::parent);
+    // Heading slugs of other documents, read once each
+    let mut linked_slugs: HashMap<std::path::PathBuf, HashSet<String>> = HashMap::new();
+
+    Parser::new_ext(markdown, flavor.options())
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::Link { dest_url, .. }) => Some((LinkKind::Link, dest_url, range)),
+            Event::Start(Tag::Image { dest_url, .. }) => Some((LinkKind::Image, dest_url, range)),
+            _ => None,
+        })
+        .filter_map(|(kind, url, range)| {
+            let url = url.to_string();
+            let status = if let Some(anchor) = url.strip_prefix('#') {
+                anchor_status(&own_slugs, anchor)
+            } else if has_scheme(&url) {
+                LinkStatus::External
+            } else {
+                let (path, fragment) = url.split_once('#').unwrap_or((&url, ""));
+                let path = path.split('?').next().unwrap_or_default().replace("%20", " ");
+                if path.is_empty() {
+                    return None;
+                }
+                let target = if Path::new(&path).is_absolute() {
+                    normalize(Path::new(&path))
+                } else {
+                    normalize(&base?.join(path))
+                };
+                if !target.exists() {
+                    LinkStatus::Missing
+                } else if !fragment.is_empty() && utils::is_markdown_file(&target, extensions) {
+                    let slugs = linked_slugs.entry(target.clone()).or_insert_with(|| {
+                        let text = std::fs::read_to_string(&target).unwrap_or_default();
+                        outline::headings(&text).into_iter().map(|h| h.slug).collect()
+                    });
+                    anchor_status(slugs, fragment)
+                } else {
+                    LinkStatus::Found
+                }
+            };
+            Some(LinkReference { kind, url, range, status })
+        })
+        .collect()
+}
+
+fn anchor_status(slugs: &HashSet<String>, anchor: &str) -> LinkStatus {
+    // Explicit `{#id}`s keep their case; slugs are lower case
+    let anchor = anchor.replace("%20", " ");
+    if slugs.contains(&anchor) || slugs.contains(&anchor.to_lowercase()) {
+        LinkStatus::AnchorFound
+    } else {
+        LinkStatus::AnchorMissing
+    }
+}
+
+/// Starts with a URL scheme such as `https:` or `mailto:`, unlike a Windows
+/// drive letter
+pub fn has_scheme(url: &str) -> bool {
+    url.split_once(':').is_some_and(|(scheme, _)| {
+        scheme.len() > 1
+            && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+/// Byte range of `url` in the source of the link at `range`: inside its
+/// parentheses, or on the reference definition a `[text][label]` link uses
+pub fn url_range(markdown: &str, range: &Range<usize>, url: &str) -> Option<Range<usize>> {
+    if url.is_empty() {
+        return None;
+    }
+    let source = &markdown[range.clone()];
+    if let Some(open) = source.rfind("](") {
+        if let Some(index) = source[open..].find(url) {
+            let start = range.start + open + index;
+            return Some(start..start + url.len());
+        }
+    }
+    markdown
+        .match_indices(url)
+        .find(|(index, _)| {
+            let line_start = markdown[..*index].rfind('\n').map_or(0, |i| i + 1);
+            let before = markdown[line_start..*index].trim();
+            before.starts_with('[') && before.ends_with("]:")
+        })
+        .map(|(index, _)| index..index + url.len())
+}
+
+/// Link from a document in `from_dir` to `target`, relative where they
+/// share a root, with `/` separators and spaces encoded
+pub fn relative_link(from_dir: &Path, target: &Path) -> String {
+    let from: Vec<Component> = from_dir.components().collect();
+    let to: Vec<Component> = target.components().collect();
+    relativize(&from, &to).unwrap_or_else(|| absolute_link(target))
+}
+
+/// The path components `to` as a link from the folder `from`, or `None`
+/// when they share nothing, such as paths on two Windows drives
+fn relativize<C: PartialEq + AsRef<OsStr>>(from: &[C], to: &[C]) -> Option<String> {
+    let common = from.iter().zip(to).take_while(|(a, b)| a == b).count();
+    if common == 0 {
+        return None;
+    }
+    let parts: Vec<String> = std::iter::repeat_n("..".into(), from.len() - common)
+        .chain(to[common..].iter().map(|c| c.as_ref().to_string_lossy().into_owned()))
+        .collect();
+    Some(parts.join("/").replace(' ', "%20"))
+}
+
+/// Link to `target` by its full path, with `/` separators and spaces encoded
+pub fn absolute_link(target: &Path) -> String {
+    target.to_string_lossy().replace('\\', "/").replace(' ', "%20")
+}
+
+/// Web addresses being requested on background threads
+pub struct WebCheck {
+    receiver: Receiver<(String, LinkStatus)>,
+    cancel: Arc<AtomicBool>,
+    pub done: usize,
+    pub total: usize,
+}
+
+impl WebCheck {
+    /// Send a HEAD request to each of `urls`, falling back to GET for
+    /// servers that don't allow HEAD
+    pub fn start(urls: Vec<String>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let total = urls.len();
+        let queue = Arc::new(Mutex::new(urls));
+        let agent = ureq::AgentBuilder::new().timeout(WEB_TIMEOUT).redirects(5).build();
+
+        for _ in 0..WEB_WORKERS.min(total) {
+            let (sender, cancel, queue, agent) = (sender.clone(), cancel.clone(), queue.clone(), agent.clone());
+            std::thread::spawn(move || loop {
+                if cancel.load(Ordering::Relaxed) {
+                    return;
+                }
+                let Some(url) = queue.lock().ok().and_then(|mut queue| queue.pop()) else {
+                    return;
+                };
+                let status = request(&agent, &url);
+                if sender.send((url, status)).is_err() {
+                    return;
+                }
+            });
+        }
+
+        Self {
+            receiver,
+            cancel,
+            done: 0,
+            total,
+        }
+    }
+
+    /// Results that arrived since the last call
+    pub fn poll(&mut self) -> Vec<(String, LinkStatus)> {
+        let mut results = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(result) => results.push(result),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.done = self.total;
+                    break;
+                }
+            }
+        }
+        self.done += results.len();
+        results
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.done < self.total
+    }
+}
+
+impl Drop for WebCheck {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+fn request(agent: &ureq::Agent, url: &str) -> LinkStatus {
+    let result = match agent.head(url).call() {
+        Err(ureq::Error::Status(405 | 501, _)) => agent.get(url).call(),
+        result => result,
+    };
+    match result {
+        Ok(_) => LinkStatus::Reachable,
+        Err(ureq::Error::Status(code, response)) => {
+            LinkStatus::Unreachable(format!("HTTP {} {}", code, response.status_text()))
+        }
+        Err(ureq::Error::Transport(transport)) => LinkStatus::Unreachable(transport.kind().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_links() {
+        let dir = std::env::temp_dir().join(format!("rmd-linkcheck-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("img")).unwrap();
+        std::fs::write(dir.join("img/logo.png"), "").unwrap();
+        std::fs::write(dir.join("other.md"), "# Setup Guide\n").unwrap();
+        let document = dir.join("doc.md");
+        let extensions = vec!["md".to_string()];
+
+        let markdown = "# Intro\n\n![logo](img/logo.png) ![gone](img/gone.png)\n\
+            [intro](#intro) [nowhere](#nowhere) [guide](other.md#setup-guide) [bad](other.md#install)\n\
+            [web](https://example.com) [mail](mailto:a@b.c) [ref][r]\n\n[r]: missing%20file.md\n";
+        let links = check_links(markdown, Flavor::Gfm, Some(&document), &extensions);
+        let statuses: Vec<(&str, LinkStatus)> = links.iter().map(|l| (l.url.as_str(), l.status.clone())).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("img/logo.png", LinkStatus::Found),
+                ("img/gone.png", LinkStatus::Missing),
+                ("#intro", LinkStatus::AnchorFound),
+                ("#nowhere", LinkStatus::AnchorMissing),
+                ("other.md#setup-guide", LinkStatus::AnchorFound),
+                ("other.md#install", LinkStatus::AnchorMissing),
+                ("https://example.com", LinkStatus::External),
+                ("mailto:a@b.c", LinkStatus::External),
+                ("missing%20file.md", LinkStatus::Missing),
+            ]
+        );
+        assert_eq!(links[1].kind, LinkKind::Image);
+        assert_eq!(&markdown[links[1].range.clone()], "![gone](img/gone.png)");
+
+        // Unsaved documents can't resolve relative paths
+        let unsaved = check_links(markdown, Flavor::Gfm, None, &extensions);
+        assert!(unsaved.iter().all(|l| !l.url.starts_with("img/")));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_url_range() {
+        let markdown = "See ![a](a.png \"a.png\") and [b][ref].\n\n[ref]: b.md\n";
+        let image = 4..23;
+        assert_eq!(url_range(markdown, &image, "a.png"), Some(9..14));
+        let link = 28..36;
+        assert_eq!(&markdown[url_range(markdown, &link, "b.md").unwrap()], "b.md");
+        assert_eq!(url_range(markdown, &link, "c.md"), None);
+    }
+
+    #[test]
+    fn test_relative_link() {
+        let root = std::env::temp_dir();
+        assert_eq!(relative_link(&root.join("notes"), &root.join("notes/img/a b.png")), "img/a%20b.png");
+        assert_eq!(relative_link(&root.join("notes/deep"), &root.join("pics/x.png")), "../../pics/x.png");
+    }
+
+    #[test]
+    fn test_relativize() {
+        let from = ["C:", "\\", "notes", "deep"];
+        assert_eq!(relativize(&from, &["C:", "\\", "notes", "img", "a b.png"]), Some("../img/a%20b.png".to_string()));
+        assert_eq!(relativize(&from, &["C:", "\\", "notes", "deep"]), Some(String::new()));
+        // Another drive shares no root, so the link has to stay absolute
+        assert_eq!(relativize(&from, &["D:", "\\", "My Pictures", "x.png"]), None);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_relative_link_across_drives() {
+        let link = relative_link(Path::new(r"C:\notes"), Path::new(r"D:\My Pictures\x.png"));
+        assert_eq!(link, "D:/My%20Pictures/x.png");
+    }
+}
@@ -0,0 +1,266 @@
+//! Converting between inline links and reference links
+
+use super::flavor::Flavor;
+use pulldown_cmark::{Event, LinkType, Parser, Tag, TagEnd};
+use std::ops::Range;
+
+/// A reference definition, `[label]: url "title"`
+struct Definition {
+    label: String,
+    url: String,
+    title: String,
+    /// Whole lines the definition takes up, newline included
+    lines: Range<usize>,
+}
+
+/// A link or image as written in the source
+struct SourceLink {
+    link_type: LinkType,
+    url: String,
+    title: String,
+    /// From the start of `[` or `![` to the end of the link
+    range: Range<usize>,
+    /// Where the text ends: the offset of the `]` closing it
+    text_end: usize,
+}
+
+/// Rewrite every inline link and image `[text](url "title")` as a
+/// reference `[text][n]`, numbering URLs from 1 in order of appearance and
+/// reusing an existing definition with the same URL and title. Definitions
+/// at the start of a line, old and new, are gathered in one block at the
+/// end. Code is left alone. Returns `None` when there is nothing to change.
+pub fn to_references(markdown: &str, flavor: Flavor) -> Option<String> {
+    let (links, mut definitions) = scan(markdown, flavor);
+    let inline: Vec<&SourceLink> = links.iter().filter(|link| link.link_type == LinkType::Inline).collect();
+    if inline.is_empty() {
+        return None;
+    }
+
+    let mut next_number = 1;
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    for link in inline {
+        let existing = definitions.iter().find(|d| d.url == link.url && d.title == link.title);
+        let label = match existing {
+            Some(definition) => definition.label.clone(),
+            None => {
+                while definitions.iter().any(|d| d.label.eq_ignore_ascii_case(&next_number.to_string())) {
+                    next_number += 1;
+                }
+                let label = next_number.to_string();
+                definitions.push(Definition {
+                    label: label.clone(),
+                    url: link.url.clone(),
+                    title: link.title.clone(),
+                    lines: markdown.len()..markdown.len(),
+                });
+                label
+            }
+        };
+        edits.push((link.text_end + 1..link.range.end, format!("[{}]", label)));
+    }
+    // The old definitions move into the block at the end
+    let old = definitions.iter().filter(|d| !d.lines.is_empty()).map(|d| d.lines.clone());
+    edits.extend(removals(markdown, old.collect()));
+
+    let mut text = apply(markdown, edits);
+    let body_len = text.trim_end().len();
+    text.truncate(body_len);
+    text.push_str("\n\n");
+    for definition in &definitions {
+        text.push_str(&format!("[{}]: {}", definition.label, destination(&definition.url, &definition.title)));
+        text.push('\n');
+    }
+    Some(text)
+}
+
+/// Rewrite every reference link and image, full `[text][label]`,
+/// collapsed `[text][]`, and shortcut `[text]`, as an inline one, and
+/// remove the definitions they used. Returns `None` when there is nothing
+/// to change.
+pub fn to_inline(markdown: &str, flavor: Flavor) -> Option<String> {
+    let (links, definitions) = scan(markdown, flavor);
+    let references: Vec<&SourceLink> = links
+        .iter()
+        .filter(|link| matches!(link.link_type, LinkType::Reference | LinkType::Collapsed | LinkType::Shortcut))
+        .collect();
+    if references.is_empty() {
+        return None;
+    }
+
+    let mut edits: Vec<(Range<usize>, String)> = references
+        .iter()
+        .map(|link| (link.text_end + 1..link.range.end, format!("({})", destination(&link.url, &link.title))))
+        .collect();
+    let used = |definition: &&Definition| {
+        references.iter().any(|link| link.url == definition.url && link.title == definition.title)
+    };
+    let used = definitions.iter().filter(used).map(|d| d.lines.clone());
+    edits.extend(removals(markdown, used.collect()));
+
+    let mut text = apply(markdown, edits);
+    let body_len = text.trim_end().len();
+    if markdown.ends_with('\n') {
+        text.truncate(body_len);
+        text.push('\n');
+    }
+    Some(text)
+}
+
+/// Links and images in `markdown`, and the definitions that start a line
+fn scan(markdown: &str, flavor: Flavor) -> (Vec<SourceLink>, Vec<Definition>) {
+    let parser = Parser::new_ext(markdown, flavor.options());
+    let mut definitions: Vec<Definition> = parser
+        .reference_definitions()
+        .iter()
+        .filter(|(_, def)| def.span.start == line_start(markdown, def.span.start))
+        .map(|(label, def)| {
+            let end = markdown[def.span.end..].find('\n').map_or(markdown.len(), |i| def.span.end + i + 1);
+            Definition {
+                label: label.to_string(),
+                url: def.dest.to_string(),
+                title: def.title.as_deref().unwrap_or_default().to_string(),
+                lines: def.span.start..end,
+            }
+        })
+        .collect();
+    definitions.sort_by_key(|definition| definition.lines.start);
+
+    let mut links = Vec::new();
+    // Index into `links` of each open link, and the furthest its text reaches
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    for (event, range) in parser.into_offset_iter() {
+        match event {
+            Event::Start(Tag::Link { link_type, dest_url, title, .. } | Tag::Image { link_type, dest_url, title, .. }) => {
+                let text_start = markdown[range.start..].find('[').map_or(range.start, |i| range.start + i + 1);
+                // The parser's range stops short of a collapsed link's `[]`
+                let mut range = range;
+                if link_type == LinkType::Collapsed && markdown[range.end..].starts_with("[]") {
+                    range.end += 2;
+                }
+                open.push((links.len(), text_start));
+                links.push(SourceLink {
+                    link_type,
+                    url: dest_url.to_string(),
+                    title: title.to_string(),
+                    range,
+                    text_end: 0,
+                });
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                if let Some((index, reach)) = open.pop() {
+                    let link = &mut links[index];
+                    link.text_end = markdown[reach..link.range.end].find(']').map_or(link.range.end, |i| reach + i);
+                    if let Some((_, outer_reach)) = open.last_mut() {
+                        *outer_reach = (*outer_reach).max(link.range.end);
+                    }
+                }
+            }
+            _ => {
+                if let Some((_, reach)) = open.last_mut() {
+                    *reach = (*reach).max(range.end);
+                }
+            }
+        }
+    }
+    (links, definitions)
+}
+
+fn line_start(markdown: &str, offset: usize) -> usize {
+    markdown[..offset].rfind('\n').map_or(0, |i| i + 1)
+}
+
+/// `url "title"`, with the URL in angle brackets when it has spaces
+fn destination(url: &str, title: &str) -> String {
+    let url = if url.contains([' ', '(', ')']) { format!("<{}>", url) } else { url.to_string() };
+    match title {
+        "" => url,
+        title => format!("{} \"{}\"", url, title.replace('"', "\\\"")),
+    }
+}
+
+/// Edits deleting the definition `lines`, sorted by position. Definitions
+/// next to each other go together, and so does the blank line after a
+/// group that stands on its own, so no gap is left behind.
+fn removals(markdown: &str, lines: Vec<Range<usize>>) -> Vec<(Range<usize>, String)> {
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for range in lines {
+        match merged.last_mut() {
+            Some(last) if last.end == range.start => last.end = range.end,
+            _ => merged.push(range),
+        }
+    }
+    for range in &mut merged {
+        let blank_before = range.start == 0 || markdown[..range.start].ends_with("\n\n");
+        if blank_before && markdown[range.end..].starts_with('\n') {
+            range.end += 1;
+        }
+    }
+    merged.into_iter().map(|range| (range, String::new())).collect()
+}
+
+/// Apply non-overlapping `edits` to `markdown`
+fn apply(markdown: &str, mut edits: Vec<(Range<usize>, String)>) -> String {
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    let mut text = markdown.to_string();
+    for (range, replacement) in edits {
+        text.replace_range(range, &replacement);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_references() {
+        let doc = "See [docs](https://a.example \"Docs\") and ![logo](img/logo.png).\n\n\
+                   Again [the docs](https://a.example \"Docs\"), [old][site].\n\n\
+                   [site]: https://b.example\n\n\
+                   `[code](not/a/link)`\n";
+        let converted = to_references(doc, Flavor::Gfm).unwrap();
+        assert_eq!(
+            converted,
+            "See [docs][1] and ![logo][2].\n\n\
+             Again [the docs][1], [old][site].\n\n\
+             `[code](not/a/link)`\n\n\
+             [site]: https://b.example\n\
+             [1]: https://a.example \"Docs\"\n\
+             [2]: img/logo.png\n"
+        );
+        assert_eq!(to_references(&converted, Flavor::Gfm), None);
+    }
+
+    #[test]
+    fn test_reuses_existing_definition() {
+        let doc = "[a](https://a.example) and [b][x].\n\n[x]: https://a.example\n";
+        assert_eq!(
+            to_references(doc, Flavor::Gfm).unwrap(),
+            "[a][x] and [b][x].\n\n[x]: https://a.example\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let doc = "# Links\n\nA [link](https://a.example \"Title\"), an ![image](<pics/a b.png>), \
+                   [nested ![icon](i.png)](https://n.example) and `[code](x)`.\n\n\
+                   ```\n[fenced](y)\n```\n";
+        let references = to_references(doc, Flavor::Gfm).unwrap();
+        assert!(references.contains("![image][2]"));
+        assert!(references.contains("```\n[fenced](y)\n```"));
+        let inline = to_inline(&references, Flavor::Gfm).unwrap();
+        assert_eq!(inline.split_whitespace().collect::<Vec<_>>(), doc.split_whitespace().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_to_inline_forms() {
+        let doc = "[full][a], [Collapsed][], [shortcut] and [plain](p.md).\n\n\
+                   [a]: https://a.example \"A\"\n[collapsed]: c.md\n[shortcut]: <s s.md>\n[unused]: u.md\n";
+        assert_eq!(
+            to_inline(doc, Flavor::Gfm).unwrap(),
+            "[full](https://a.example \"A\"), [Collapsed](c.md), [shortcut](<s s.md>) and [plain](p.md).\n\n\
+             [unused]: u.md\n"
+        );
+        assert_eq!(to_inline("No [links](here.md).\n", Flavor::Gfm), None);
+    }
+}
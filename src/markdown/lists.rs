@@ -0,0 +1,273 @@
+//! Renumbering ordered lists so their numbers run on from each list's first,
+//! after items were added, removed or moved. Nested lists are numbered on
+//! their own, and code and front matter are left alone.
+
+use super::{fence_marker, flavor};
+use std::ops::Range;
+
+/// A list item's marker: where it starts, how it is written, and where its
+/// content starts
+struct Marker {
+    indent: usize,
+    /// Byte range of the number within the line, `None` for a bullet
+    number: Option<Range<usize>>,
+    /// `.`, `)`, or the bullet character
+    delimiter: char,
+    content: usize,
+}
+
+fn marker(line: &str) -> Option<Marker> {
+    let trimmed = line.trim_start_matches(' ');
+    let indent = line.len() - trimmed.len();
+    let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+    let (number, delimiter) = match trimmed[digits..].chars().next()? {
+        c @ ('.' | ')') if (1..=9).contains(&digits) => (Some(indent..indent + digits), c),
+        c @ ('-' | '*' | '+') if digits == 0 => (None, c),
+        _ => return None,
+    };
+    let after = &trimmed[digits + 1..];
+    if !(after.is_empty() || after.starts_with([' ', '\t'])) {
+        return None;
+    }
+    // `- - -` and `* * *` are rules, not list items
+    let marks: Vec<char> = trimmed.chars().filter(|c| !c.is_whitespace()).collect();
+    if number.is_none() && marks.len() >= 3 && marks.iter().all(|&c| c == marks[0]) {
+        return None;
+    }
+    let spaces = after.len() - after.trim_start_matches(' ').len();
+    let content = indent + digits + 1 + if (1..=4).contains(&spaces) { spaces } else { 1 };
+    Some(Marker { indent, number, delimiter, content })
+}
+
+/// A list open at some depth while reading the lines
+struct Level {
+    indent: usize,
+    content: usize,
+    delimiter: char,
+    /// Number the next item should have, for an ordered list
+    next: Option<u64>,
+}
+
+/// A top-level list: the bytes from its first item through its last line,
+/// and the numbers in it to change
+struct List {
+    span: Range<usize>,
+    edits: Vec<(Range<usize>, String)>,
+}
+
+/// Every top-level list of `markdown`
+fn lists(markdown: &str) -> Vec<List> {
+    let mut offset = flavor::front_matter(markdown).map_or(0, |range| {
+        range.end + markdown[range.end..].find('\n').map_or(markdown.len() - range.end, |i| i + 1)
+    });
+    let mut lists: Vec<List> = Vec::new();
+    let mut levels: Vec<Level> = Vec::new();
+    let mut fence: Option<(char, usize, usize)> = None;
+    let mut after_blank = true;
+    // Whether the last line was paragraph text, which a list can only
+    // interrupt by starting at 1
+    let mut in_paragraph = false;
+
+    for line in markdown[offset..].split_inclusive('\n').collect::<Vec<_>>() {
+        let start = offset;
+        offset += line.len();
+        let text = line.trim_end_matches(['\n', '\r']);
+        let indent = text.len() - text.trim_start_matches(' ').len();
+        let container = levels.last().filter(|level| indent >= level.content).map_or(0, |level| level.content);
+
+        if let Some((marker, len, fence_indent)) = fence {
+            let closes = fence_marker(&text[fence_indent.min(indent)..])
+                .is_some_and(|(close, close_len)| close == marker && close_len >= len && text.trim().trim_start_matches(marker).is_empty());
+            if closes {
+                fence = None;
+            }
+            extend(&mut lists, &levels, offset);
+            continue;
+        }
+        if text.trim().is_empty() {
+            after_blank = true;
+            in_paragraph = false;
+            continue;
+        }
+        if let Some((marker, len)) = fence_marker(&text[container..]) {
+            if indent < container.max(1) + 3 || container == 0 {
+                if container == 0 {
+                    levels.clear();
+                }
+                fence = Some((marker, len, container));
+                extend(&mut lists, &levels, offset);
+                in_paragraph = false;
+                after_blank = false;
+                continue;
+            }
+        }
+
+        // Indented code, at the top level or inside an item
+        let code = after_blank && indent >= container + 4;
+        let item = marker(text).filter(|item| {
+            let starts_at_one = item.number.as_ref().is_none_or(|number| &text[number.clone()] == "1");
+            let interrupts = !in_paragraph || starts_at_one || levels.last().is_some_and(|level| item.indent < level.content);
+            !code && interrupts && (item.indent <= 3 || !levels.is_empty())
+        });
+
+        let Some(item) = item else {
+            // Text left of every open item ends the lists, unless it is
+            // a lazy continuation of a paragraph
+            if after_blank || !in_paragraph {
+                while levels.last().is_some_and(|level| indent < level.content) {
+                    levels.pop();
+                }
+            }
+            extend(&mut lists, &levels, offset);
+            in_paragraph = !code;
+            after_blank = false;
+            continue;
+        };
+
+        while levels.last().is_some_and(|level| item.indent < level.indent) {
+            levels.pop();
+        }
+        let sibling = levels.last().is_some_and(|level| item.indent < level.content);
+        let same_kind = levels.last().is_some_and(|level| level.delimiter == item.delimiter);
+        if sibling && !same_kind {
+            levels.pop();
+        }
+        if sibling && same_kind {
+            let level = levels.last_mut().expect("checked above");
+            if let (Some(next), Some(number)) = (level.next.as_mut(), item.number.as_ref()) {
+                let expected = next.to_string();
+                if text[number.clone()] != expected {
+                    let list = lists.last_mut().expect("a level is open");
+                    list.edits.push((start + number.start..start + number.end, expected));
+                }
+                *next += 1;
+            }
+        } else {
+            let first = item.number.as_ref().and_then(|number| text[number.clone()].parse::<u64>().ok());
+            if levels.is_empty() {
+                lists.push(List { span: start..start, edits: Vec::new() });
+            }
+            levels.push(Level {
+                indent: item.indent,
+                content: item.content,
+                delimiter: item.delimiter,
+                next: first.map(|first| first + 1),
+            });
+        }
+        extend(&mut lists, &levels, offset);
+        in_paragraph = true;
+        after_blank = false;
+    }
+    lists
+}
+
+/// Take the line ending at `end` into the open list, if there is one
+fn extend(lists: &mut [List], levels: &[Level], end: usize) {
+    if let Some(list) = lists.last_mut().filter(|_| !levels.is_empty()) {
+        list.span.end = end;
+    }
+}
+
+/// Edits that renumber every ordered list in `markdown`: the byte range of
+/// each number to change, and the number to put there
+pub fn renumber(markdown: &str) -> Vec<(Range<usize>, String)> {
+    lists(markdown).into_iter().flat_map(|list| list.edits).collect()
+}
+
+/// Edits renumbering only the lists that the text at `touched` is part of
+/// or right next to
+pub fn renumber_around(markdown: &str, touched: Range<usize>) -> Vec<(Range<usize>, String)> {
+    lists(markdown)
+        .into_iter()
+        .filter(|list| touched.start <= list.span.end && list.span.start <= touched.end)
+        .flat_map(|list| list.edits)
+        .collect()
+}
+
+/// `edits` made into one: the range from the first to the end of the last,
+/// and what to replace it with
+pub fn combine(markdown: &str, edits: &[(Range<usize>, String)]) -> Option<(Range<usize>, String)> {
+    let span = edits.first()?.0.start..edits.last()?.0.end;
+    let mut replacement = String::new();
+    let mut at = span.start;
+    for (range, number) in edits {
+        replacement.push_str(&markdown[at..range.start]);
+        replacement.push_str(number);
+        at = range.end;
+    }
+    Some((span, replacement))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn renumbered(markdown: &str) -> String {
+        match combine(markdown, &renumber(markdown)) {
+            Some((range, replacement)) => {
+                let mut text = markdown.to_string();
+                text.replace_range(range, &replacement);
+                text
+            }
+            None => markdown.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_renumber() {
+        let cases = [
+            // An item removed, and one added
+            ("1. a\n2. b\n4. c\n5. d\n", "1. a\n2. b\n3. c\n4. d\n"),
+            ("1. a\n1. new\n2. b\n", "1. a\n2. new\n3. b\n"),
+            // The list's own start is kept, and `)` lists are numbered too
+            ("3) a\n3) b\n9) c\n", "3) a\n4) b\n5) c\n"),
+            // Nested lists are numbered on their own
+            (
+                "1. a\n   1. x\n   5. y\n3. b\n   - bullet\n   7. z\n   9. w\n4. c\n",
+                "1. a\n   1. x\n   2. y\n2. b\n   - bullet\n   7. z\n   8. w\n3. c\n",
+            ),
+            // Loose lists and item paragraphs
+            ("1. a\n\n   more of a\n\n5. b\n", "1. a\n\n   more of a\n\n2. b\n"),
+            // A paragraph between ends the list, so the next one starts over
+            ("1. a\n2. b\n\nText\n\n7. c\n9. d\n", "1. a\n2. b\n\nText\n\n7. c\n8. d\n"),
+            // A different delimiter starts a new list
+            ("1. a\n2. b\n1) c\n5) d\n", "1. a\n2. b\n1) c\n2) d\n"),
+            // Wide numbers
+            ("9. a\n9. b\n", "9. a\n10. b\n"),
+        ];
+        for (before, after) in cases {
+            assert_eq!(renumbered(before), after, "renumbering {:?}", before);
+        }
+    }
+
+    #[test]
+    fn test_code_and_prose_are_left_alone() {
+        let unchanged = [
+            // Numbers in fenced and indented code
+            "1. a\n\n```\n1. x\n5. y\n```\n",
+            "1. a\n   ```\n   3. code\n   ```\n2. b\n",
+            "Text\n\n    1. code\n    5. code\n",
+            // A line of prose that only starts with a number
+            "The year was\n1984. Then a list:\n",
+            "In\n2024. And\n2025. Too\n",
+            // Front matter
+            "---\n1. a\n5. b\n---\n",
+        ];
+        for markdown in unchanged {
+            assert_eq!(renumbered(markdown), markdown, "renumbering {:?}", markdown);
+        }
+        // Code in an item doesn't end the list
+        assert_eq!(renumbered("1. a\n   ```\n   x\n   ```\n5. b\n"), "1. a\n   ```\n   x\n   ```\n2. b\n");
+    }
+
+    #[test]
+    fn test_renumber_around() {
+        let markdown = "1. a\n3. b\n\nText\n\n1. c\n3. d\n";
+        let first = renumber_around(markdown, 2..2);
+        assert_eq!(first, [(5..6, "2".to_string())]);
+        // Typing in the paragraph between touches neither list
+        assert!(renumber_around(markdown, 12..13).is_empty());
+        let second = renumber_around(markdown, markdown.len()..markdown.len());
+        assert_eq!(second, [(markdown.len() - 5..markdown.len() - 4, "2".to_string())]);
+    }
+}
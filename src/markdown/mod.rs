@@ -1,48 +1,297 @@
+pub mod alerts;
+pub mod assets;
+pub mod autolink;
+pub mod cleanup;
+pub mod colors;
+pub mod copy;
+pub mod docx;
+pub mod embed;
+pub mod emoji;
+pub mod export;
+pub mod figures;
+pub mod flavor;
+pub mod footnotes;
+pub mod generated;
+pub mod header;
+pub mod html;
+pub mod include;
+pub mod incremental;
+pub mod language;
+pub mod linkcheck;
+pub mod links;
+pub mod lists;
+pub mod numbering;
+pub mod outline;
+pub mod overrides;
+pub mod pagebreak;
+pub mod rename;
+pub mod split;
+pub mod toc;
+pub mod typography;
+pub mod wiki;
+pub mod wrap;
+
 use crate::theme::Theme;
-use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use alerts::AlertKind;
+use flavor::Flavor;
+use html::{HtmlEvent, HtmlState};
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, TagEnd};
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::path::PathBuf;
 
 /// Renders Markdown to rich text for display
 pub struct MarkdownRenderer {
     theme: Theme,
+    /// Parse `[[Note Name]]` as links
+    wiki_links: bool,
+    /// Curly quotes, dashes, and ellipses in the rendered output
+    smart_punctuation: bool,
+    /// Show `:shortcode:`s as emoji
+    emoji_shortcodes: bool,
+    /// Render whitelisted HTML tags instead of showing their source
+    render_html: bool,
+    /// Profile for documents that don't name one in their front matter
+    flavor: Flavor,
+    /// Show hierarchical numbers in front of headings
+    number_headings: bool,
+}
+
+/// Byte range of the Markdown source an element was rendered from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SourceSpan {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl SourceSpan {
+    pub fn range(self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+impl From<Range<usize>> for SourceSpan {
+    fn from(range: Range<usize>) -> Self {
+        Self {
+            start: range.start,
+            end: range.end,
+        }
+    }
+}
+
+/// A rendered element in the preview and the source it came from
+#[derive(Clone, Debug)]
+pub struct RenderedElement {
+    pub kind: ElementKind,
+    pub span: SourceSpan,
+}
+
+impl RenderedElement {
+    pub fn new(kind: ElementKind, span: impl Into<SourceSpan>) -> Self {
+        Self { kind, span: span.into() }
+    }
+
+    /// Text of the element and everything inside it, without markup
+    pub fn plain_text(&self) -> String {
+        let join = |elements: &[RenderedElement]| elements.iter().map(RenderedElement::plain_text).collect::<String>();
+        match &self.kind {
+            ElementKind::Heading(_, text)
+            | ElementKind::Paragraph(text)
+            | ElementKind::InlineCode(text)
+            | ElementKind::Strong(text)
+            | ElementKind::Emphasis(text)
+            | ElementKind::Strikethrough(text)
+            | ElementKind::RawHtml(text)
+            | ElementKind::Link(text, _)
+            | ElementKind::Image(text, _) => text.clone(),
+            ElementKind::Figure(_, _, caption, number) => figures::label(*number, caption),
+            ElementKind::CodeBlock(_, code) => code.clone(),
+            ElementKind::BlockQuote(items, _) => join(items),
+            ElementKind::UnorderedList(items, _) | ElementKind::OrderedList(items, _) => {
+                items.iter().map(|item| join(item)).collect::<Vec<_>>().join("\n")
+            }
+            ElementKind::Table(rows) => rows.iter().map(|row| row.join("\t")).collect::<Vec<_>>().join("\n"),
+            ElementKind::Details(summary, _, body) => format!("{}\n{}", summary, join(body)),
+            ElementKind::FootnoteReference(label, number, _) => match number {
+                Some(number) => number.to_string(),
+                None => format!("[^{}]", label),
+            },
+            ElementKind::FootnoteDefinition(_, _, _, body) => join(body),
+            ElementKind::Include(_, Ok(body)) => join(body),
+            ElementKind::Include(_, Err(error)) => error.clone(),
+            ElementKind::HorizontalRule | ElementKind::PageBreak | ElementKind::LineBreak => String::new(),
+        }
+    }
 }
 
-/// A rendered element in the preview
+/// What a rendered element shows
 #[derive(Clone, Debug)]
-pub enum RenderedElement {
+pub enum ElementKind {
     Heading(u8, String),
     Paragraph(String),
+    /// Language and code
     CodeBlock(String, String),
     InlineCode(String),
-    BlockQuote(Vec<RenderedElement>),
-    UnorderedList(Vec<Vec<RenderedElement>>),
-    OrderedList(Vec<Vec<RenderedElement>>),
+    /// Content, and the alert it is when it starts with a marker such as
+    /// `[!NOTE]`, which is taken off
+    BlockQuote(Vec<RenderedElement>, Option<AlertKind>),
+    /// Items, and whether the list is tight: no blank lines between its
+    /// items, so they sit close together
+    UnorderedList(Vec<Vec<RenderedElement>>, bool),
+    OrderedList(Vec<Vec<RenderedElement>>, bool),
     HorizontalRule,
+    /// `<!-- pagebreak -->` or `\newpage` on a line of its own
+    PageBreak,
     Link(String, String),
+    /// Alt text and source
     Image(String, String),
+    /// Image alone in its paragraph with a caption: alt text, source,
+    /// caption, and its number, from 1
+    Figure(String, String, String, usize),
     RawHtml(String),
     LineBreak,
     Strong(String),
     Emphasis(String),
     Strikethrough(String),
+    /// Rows of cells, header row first
+    Table(Vec<Vec<String>>),
+    /// `<details>` with its summary, whether it starts open, and its content
+    Details(String, bool, Vec<RenderedElement>),
+    /// Footnote marker: its label, its number (`None` when the footnote
+    /// isn't defined), and which reference to the footnote it is, from 1
+    FootnoteReference(String, Option<usize>, usize),
+    /// Footnote: its label, number (0 when never referenced), how many
+    /// references point at it, and its content
+    FootnoteDefinition(String, usize, usize, Vec<RenderedElement>),
+    /// Another file's content shown in place of an include directive, or
+    /// why it can't be
+    Include(PathBuf, Result<Vec<RenderedElement>, String>),
 }
 
 impl MarkdownRenderer {
     pub fn new(theme: &Theme) -> Self {
-        Self { theme: theme.clone() }
+        Self {
+            theme: theme.clone(),
+            wiki_links: false,
+            smart_punctuation: false,
+            emoji_shortcodes: false,
+            render_html: true,
+            flavor: Flavor::default(),
+            number_headings: false,
+        }
     }
 
     pub fn set_theme(&mut self, theme: Theme) {
         self.theme = theme;
     }
 
+    pub fn set_wiki_links(&mut self, enabled: bool) {
+        self.wiki_links = enabled;
+    }
+
+    pub fn set_smart_punctuation(&mut self, enabled: bool) {
+        self.smart_punctuation = enabled;
+    }
+
+    pub fn set_emoji_shortcodes(&mut self, enabled: bool) {
+        self.emoji_shortcodes = enabled;
+    }
+
+    pub fn set_render_html(&mut self, enabled: bool) {
+        self.render_html = enabled;
+    }
+
+    pub fn set_flavor(&mut self, flavor: Flavor) {
+        self.flavor = flavor;
+    }
+
+    pub fn set_number_headings(&mut self, enabled: bool) {
+        self.number_headings = enabled;
+    }
+
+    /// The profile `markdown` renders with: its front matter's, or the default
+    pub fn flavor_for(&self, markdown: &str) -> Flavor {
+        flavor::front_matter_flavor(markdown).unwrap_or(self.flavor)
+    }
+
+    /// Whether `[[Note Name]]` links are read when rendering as `flavor`
+    pub fn wiki_links_for(&self, flavor: Flavor) -> bool {
+        flavor.wiki_links() || (self.wiki_links && flavor != Flavor::CommonMark)
+    }
+
+    /// Parser extensions used for rendering with `flavor`
+    pub fn options(&self, flavor: Flavor) -> Options {
+        let mut options = flavor.options();
+        if self.smart_punctuation {
+            options.insert(Options::ENABLE_SMART_PUNCTUATION);
+        }
+        options
+    }
+
     pub fn render(&self, markdown: &str) -> Vec<RenderedElement> {
-        let parser = Parser::new(markdown);
+        let flavor = self.flavor_for(markdown);
+        let mut elements = self.render_blocks(markdown, flavor);
+        self.finish(&mut elements, flavor);
+        elements
+    }
+
+    /// Elements of `markdown` rendered as `flavor`, before the passes over
+    /// the whole document. Rendering a document in pieces and joining them
+    /// gives the same elements as rendering it whole, as long as no piece
+    /// depends on another, as reference links do on their definitions.
+    pub fn render_blocks(&self, markdown: &str, flavor: Flavor) -> Vec<RenderedElement> {
+        let wiki_links = self.wiki_links_for(flavor);
+        let (source, expansion) = if wiki_links {
+            let (expanded, map) = wiki::expand_wiki_links_mapped(markdown);
+            (Cow::Owned(expanded), Some(map))
+        } else {
+            (Cow::Borrowed(markdown), None)
+        };
+        // Spans refer to `markdown`, not the text with wiki links expanded
+        let span_of = |range: Range<usize>| -> SourceSpan {
+            match &expansion {
+                Some(map) => SourceSpan {
+                    start: map.original(range.start),
+                    end: map.original(range.end),
+                },
+                None => range.into(),
+            }
+        };
+        // Merged text runs so a shortcode is never split across events
+        let mut events = merge_text(Parser::new_ext(&source, self.options(flavor)).into_offset_iter());
+        // `{#id}` anchors aren't shown
+        outline::apply_explicit_anchors(&mut events);
         let mut elements = Vec::new();
         let mut current_element: Option<RenderedElement> = None;
-        let mut list_stack: Vec<(bool, Vec<Vec<RenderedElement>>)> = Vec::new();
-        let mut blockquote_stack: Vec<Vec<RenderedElement>> = Vec::new();
+        let mut list_stack: Vec<ListState> = Vec::new();
+        let mut blockquote_stack: Vec<(Vec<RenderedElement>, SourceSpan)> = Vec::new();
+        // Text, destination, and span of the link being read
+        let mut link: Option<(String, String, SourceSpan)> = None;
+        // Alt text, source, and span of the image being read
+        let mut image: Option<(String, String, SourceSpan)> = None;
+        // Rows and span of the table being read
+        let mut table: Option<(Vec<Vec<String>>, SourceSpan)> = None;
+        // Level, text, and span of the heading being read
+        let mut heading: Option<(u8, String, SourceSpan)> = None;
+        let mut strikethrough = false;
+        let mut in_front_matter = false;
+        // Checkbox to prefix the next text of a task list item with
+        let mut task_marker: Option<&str> = None;
+        let mut html = HtmlState::default();
+        // HTML block being read, converted once complete so tags may span lines
+        let mut html_block: Option<String> = None;
+        // Span of the HTML that `html_events` came from
+        let mut html_span = SourceSpan::default();
+        // Summary, open state, start, and enclosing elements of each open `<details>`
+        let mut details_stack: Vec<(String, bool, usize, Vec<RenderedElement>)> = Vec::new();
+        // Label, span, and enclosing elements of the footnote definition being read
+        let mut footnote: Option<(String, SourceSpan, Vec<RenderedElement>)> = None;
 
-        for event in parser {
+        for (event, range) in events {
+            let span = span_of(range);
+            let mut html_events = Vec::new();
             match event {
                 Event::Start(tag) => {
                     match tag {
@@ -50,18 +299,18 @@ impl MarkdownRenderer {
                             if let Some(elem) = current_element.take() {
                                 elements.push(elem);
                             }
-                            list_stack.push((start_num.is_some(), Vec::new()));
+                            list_stack.push(ListState { ordered: start_num.is_some(), tight: true, items: Vec::new(), span });
                         }
                         Tag::Item => {
                             if let Some(last) = list_stack.last_mut() {
-                                last.1.push(Vec::new());
+                                last.items.push(Vec::new());
                             }
                         }
                         Tag::BlockQuote(_) => {
                             if let Some(elem) = current_element.take() {
                                 elements.push(elem);
                             }
-                            blockquote_stack.push(Vec::new());
+                            blockquote_stack.push((Vec::new(), span));
                         }
                         Tag::CodeBlock(kind) => {
                             if let Some(elem) = current_element.take() {
@@ -71,7 +320,53 @@ impl MarkdownRenderer {
                                 pulldown_cmark::CodeBlockKind::Fenced(lang) => lang.to_string(),
                                 _ => String::new(),
                             };
-                            current_element = Some(RenderedElement::CodeBlock(lang, String::new()));
+                            current_element = Some(RenderedElement::new(ElementKind::CodeBlock(lang, String::new()), span));
+                        }
+                        Tag::Heading { level, .. } => {
+                            if let Some(elem) = current_element.take() {
+                                elements.push(elem);
+                            }
+                            heading = Some((level as u8, String::new(), span));
+                        }
+                        Tag::Link { dest_url, .. } => {
+                            link = Some((String::new(), dest_url.to_string(), span));
+                        }
+                        Tag::Image { dest_url, .. } => {
+                            image = Some((String::new(), dest_url.to_string(), span));
+                        }
+                        Tag::Table(_) => {
+                            if let Some(elem) = current_element.take() {
+                                elements.push(elem);
+                            }
+                            table = Some((Vec::new(), span));
+                        }
+                        Tag::TableHead | Tag::TableRow => {
+                            if let Some((rows, _)) = table.as_mut() {
+                                rows.push(Vec::new());
+                            }
+                        }
+                        Tag::TableCell => {
+                            if let Some(row) = table.as_mut().and_then(|(rows, _)| rows.last_mut()) {
+                                row.push(String::new());
+                            }
+                        }
+                        // Paragraphs are only marked inside the items of loose lists
+                        Tag::Paragraph => {
+                            if let Some(last) = list_stack.last_mut() {
+                                last.tight = false;
+                            }
+                        }
+                        Tag::Strikethrough => strikethrough = true,
+                        Tag::HtmlBlock if self.render_html => {
+                            html_block = Some(String::new());
+                            html_span = span;
+                        }
+                        Tag::MetadataBlock(_) => in_front_matter = true,
+                        Tag::FootnoteDefinition(label) => {
+                            if let Some(elem) = current_element.take() {
+                                elements.push(elem);
+                            }
+                            footnote = Some((label.to_string(), span, std::mem::take(&mut elements)));
                         }
                         _ => {}
                     }
@@ -79,95 +374,322 @@ impl MarkdownRenderer {
                 Event::End(tag) => {
                     match tag {
                         TagEnd::List(_) => {
-                            if let Some((is_ordered, items)) = list_stack.pop() {
+                            if let Some(ListState { ordered, tight, items, span }) = list_stack.pop() {
                                 if let Some(elem) = current_element.take() {
                                     elements.push(elem);
                                 }
-                                if is_ordered {
-                                    elements.push(RenderedElement::OrderedList(items));
+                                let kind = if ordered {
+                                    ElementKind::OrderedList(items, tight)
                                 } else {
-                                    elements.push(RenderedElement::UnorderedList(items));
-                                }
+                                    ElementKind::UnorderedList(items, tight)
+                                };
+                                container(&mut elements, &mut list_stack, &mut blockquote_stack)
+                                    .push(RenderedElement::new(kind, span));
                             }
                         }
                         TagEnd::BlockQuote(_) => {
-                            if let Some(items) = blockquote_stack.pop() {
+                            if let Some((mut items, span)) = blockquote_stack.pop() {
                                 if let Some(elem) = current_element.take() {
                                     elements.push(elem);
                                 }
-                                elements.push(RenderedElement::BlockQuote(items));
+                                let alert = alerts::take_marker(&mut items);
+                                container(&mut elements, &mut list_stack, &mut blockquote_stack)
+                                    .push(RenderedElement::new(ElementKind::BlockQuote(items, alert), span));
                             }
                         }
                         TagEnd::CodeBlock => {
                             if let Some(elem) = current_element.take() {
-                                elements.push(elem);
+                                container(&mut elements, &mut list_stack, &mut blockquote_stack).push(elem);
+                            }
+                        }
+                        TagEnd::Table => {
+                            if let Some((rows, span)) = table.take() {
+                                elements.push(RenderedElement::new(ElementKind::Table(rows), span));
+                            }
+                        }
+                        TagEnd::Strikethrough => strikethrough = false,
+                        TagEnd::HtmlBlock => {
+                            if let Some(block) = html_block.take() {
+                                html_events = html.feed(&block);
+                                html.end_block();
+                            }
+                        }
+                        TagEnd::MetadataBlock(_) => in_front_matter = false,
+                        TagEnd::FootnoteDefinition => {
+                            if let Some((label, span, outer)) = footnote.take() {
+                                if let Some(elem) = current_element.take() {
+                                    elements.push(elem);
+                                }
+                                let body = std::mem::replace(&mut elements, outer);
+                                elements.push(RenderedElement::new(ElementKind::FootnoteDefinition(label, 0, 0, body), span));
+                            }
+                        }
+                        TagEnd::Heading(_) => {
+                            if let Some((level, text, span)) = heading.take() {
+                                container(&mut elements, &mut list_stack, &mut blockquote_stack)
+                                    .push(RenderedElement::new(ElementKind::Heading(level, text.trim().to_string()), span));
+                            }
+                        }
+                        // A heading shows its links and images as text
+                        TagEnd::Link => {
+                            if let Some((text, url, span)) = link.take() {
+                                match heading.as_mut() {
+                                    Some((_, heading_text, _)) => heading_text.push_str(&text),
+                                    None => container(&mut elements, &mut list_stack, &mut blockquote_stack)
+                                        .push(RenderedElement::new(ElementKind::Link(text, url), span)),
+                                }
+                            }
+                        }
+                        TagEnd::Image => {
+                            if let Some((alt, url, span)) = image.take() {
+                                match heading.as_mut() {
+                                    Some((_, heading_text, _)) => heading_text.push_str(&alt),
+                                    None => container(&mut elements, &mut list_stack, &mut blockquote_stack)
+                                        .push(RenderedElement::new(ElementKind::Image(alt, url), span)),
+                                }
                             }
                         }
                         _ => {}
                     }
                 }
+                Event::Text(_) if in_front_matter => {}
                 Event::Text(text) => {
-                    if let Some(ref mut elem) = current_element {
-                        match elem {
-                            RenderedElement::CodeBlock(_, ref mut code) => {
-                                code.push_str(&text);
-                            }
-                            RenderedElement::Paragraph(ref mut p) => {
-                                p.push_str(&text);
-                            }
-                            _ => {}
+                    let in_code_block = matches!(current_element, Some(RenderedElement { kind: ElementKind::CodeBlock(..), .. }));
+                    let mut text = text;
+                    if self.emoji_shortcodes && !in_code_block {
+                        if let Cow::Owned(replaced) = emoji::replace_shortcodes(&text) {
+                            text = replaced.into();
                         }
-                    } else if let Some(last) = list_stack.last_mut() {
-                        if let Some(item) = last.1.last_mut() {
-                            item.push(RenderedElement::Paragraph(text.to_string()));
+                    }
+                    if let Some(marker) = task_marker.take() {
+                        text = format!("{} {}", marker, text).into();
+                    }
+                    if let Some((alt, _, _)) = image.as_mut() {
+                        alt.push_str(&text);
+                    } else if self.render_html && html.is_active() {
+                        if let Some(kind) = html.text(&text) {
+                            container(&mut elements, &mut list_stack, &mut blockquote_stack)
+                                .push(RenderedElement::new(kind, span));
                         }
-                    } else if let Some(items) = blockquote_stack.last_mut() {
-                        items.push(RenderedElement::Paragraph(text.to_string()));
+                    } else if let Some(cell) = table.as_mut().and_then(|(rows, _)| rows.last_mut()).and_then(|row| row.last_mut()) {
+                        cell.push_str(&text);
+                    } else if let Some((ref mut link_text, _, _)) = link {
+                        link_text.push_str(&text);
+                    } else if let Some((_, ref mut heading_text, _)) = heading {
+                        heading_text.push_str(&text);
+                    } else if let Some(RenderedElement { kind: ElementKind::CodeBlock(_, ref mut code), .. }) = current_element {
+                        code.push_str(&text);
                     } else {
-                        elements.push(RenderedElement::Paragraph(text.to_string()));
+                        let autolinks = flavor != Flavor::CommonMark;
+                        container(&mut elements, &mut list_stack, &mut blockquote_stack).extend(
+                            inline_elements(&text, strikethrough, autolinks)
+                                .into_iter()
+                                .map(|kind| RenderedElement::new(kind, span)),
+                        );
                     }
                 }
                 Event::Code(code) => {
-                    if let Some(last) = list_stack.last_mut() {
-                        if let Some(item) = last.1.last_mut() {
-                            item.push(RenderedElement::InlineCode(code.to_string()));
-                        }
+                    if let Some(cell) = table.as_mut().and_then(|(rows, _)| rows.last_mut()).and_then(|row| row.last_mut()) {
+                        cell.push_str(&code);
+                    } else if let Some((ref mut link_text, _, _)) = link {
+                        link_text.push_str(&code);
+                    } else if let Some((_, ref mut heading_text, _)) = heading {
+                        heading_text.push_str(&code);
                     } else {
-                        elements.push(RenderedElement::InlineCode(code.to_string()));
+                        container(&mut elements, &mut list_stack, &mut blockquote_stack)
+                            .push(RenderedElement::new(ElementKind::InlineCode(code.to_string()), span));
                     }
                 }
-                Event::Html(html) => {
-                    elements.push(RenderedElement::RawHtml(html.to_string()));
+                Event::Html(fragment) => match html_block.as_mut() {
+                    Some(block) => block.push_str(&fragment),
+                    None => container(&mut elements, &mut list_stack, &mut blockquote_stack)
+                        .push(RenderedElement::new(ElementKind::RawHtml(fragment.to_string()), span)),
+                },
+                Event::InlineHtml(fragment) if self.render_html => {
+                    html_span = span;
+                    html_events = html.feed(&fragment);
                 }
-                Event::SoftBreak => {
-                    // Handle soft breaks if needed
+                // Shown as written, like the HTML blocks
+                Event::InlineHtml(fragment) => {
+                    container(&mut elements, &mut list_stack, &mut blockquote_stack)
+                        .push(RenderedElement::new(ElementKind::RawHtml(fragment.to_string()), span));
                 }
                 Event::HardBreak => {
-                    if let Some(ref mut elem) = current_element {
-                        match elem {
-                            RenderedElement::Paragraph(ref mut p) => {
-                                p.push('\n');
-                            }
-                            _ => {}
-                        }
-                    } else {
-                        elements.push(RenderedElement::LineBreak);
-                    }
+                    container(&mut elements, &mut list_stack, &mut blockquote_stack)
+                        .push(RenderedElement::new(ElementKind::LineBreak, span));
                 }
                 Event::Rule => {
-                    elements.push(RenderedElement::HorizontalRule);
+                    elements.push(RenderedElement::new(ElementKind::HorizontalRule, span));
+                }
+                Event::TaskListMarker(checked) => {
+                    task_marker = Some(if checked { "☑" } else { "☐" });
+                }
+                Event::FootnoteReference(label) => {
+                    container(&mut elements, &mut list_stack, &mut blockquote_stack)
+                        .push(RenderedElement::new(ElementKind::FootnoteReference(label.to_string(), None, 0), span));
+                }
+                // Math shows as its TeX source until the preview can typeset it
+                Event::InlineMath(math) => {
+                    container(&mut elements, &mut list_stack, &mut blockquote_stack)
+                        .push(RenderedElement::new(ElementKind::InlineCode(math.to_string()), span));
+                }
+                Event::DisplayMath(math) => {
+                    if let Some(elem) = current_element.take() {
+                        elements.push(elem);
+                    }
+                    let kind = ElementKind::CodeBlock("math".to_string(), math.to_string());
+                    elements.push(RenderedElement::new(kind, span));
                 }
                 _ => {}
             }
+
+            for event in html_events {
+                match event {
+                    HtmlEvent::Element(kind) => {
+                        container(&mut elements, &mut list_stack, &mut blockquote_stack)
+                            .push(RenderedElement::new(kind, html_span));
+                    }
+                    HtmlEvent::DetailsStart { open } => {
+                        details_stack.push((String::new(), open, html_span.start, std::mem::take(&mut elements)));
+                    }
+                    HtmlEvent::DetailsSummary(summary) => match details_stack.last_mut() {
+                        Some(details) => details.0 = summary,
+                        None => elements.push(RenderedElement::new(ElementKind::Paragraph(summary), html_span)),
+                    },
+                    HtmlEvent::DetailsEnd => {
+                        if let Some((summary, open, start, outer)) = details_stack.pop() {
+                            let body = std::mem::replace(&mut elements, outer);
+                            let span = SourceSpan { start, end: html_span.end };
+                            elements.push(RenderedElement::new(ElementKind::Details(summary, open, body), span));
+                        }
+                    }
+                }
+            }
         }
 
         // Add any remaining element
         if let Some(elem) = current_element {
             elements.push(elem);
         }
+        // Close any `<details>` left open at the end of the document
+        while let Some((summary, open, start, outer)) = details_stack.pop() {
+            let body = std::mem::replace(&mut elements, outer);
+            let span = SourceSpan { start, end: markdown.len() };
+            elements.push(RenderedElement::new(ElementKind::Details(summary, open, body), span));
+        }
+        if flavor.figures() {
+            figures::collect_figures(&mut elements, markdown);
+        }
+        pagebreak::collect_page_breaks(&mut elements, markdown);
 
         elements
     }
+
+    /// Passes over the whole document's elements: numbering footnotes and
+    /// moving them to the end, and numbering headings and figures
+    pub fn finish(&self, elements: &mut Vec<RenderedElement>, flavor: Flavor) {
+        figures::number_figures(elements);
+        if self.options(flavor).contains(Options::ENABLE_FOOTNOTES) {
+            footnotes::link_footnotes(elements);
+        }
+        if self.number_headings {
+            numbering::number_elements(elements);
+        }
+    }
+
+    /// Changes whenever a setting that affects rendering does
+    pub fn settings_key(&self, flavor: Flavor) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        (flavor, self.wiki_links, self.smart_punctuation, self.emoji_shortcodes, self.render_html).hash(&mut hasher);
+        self.number_headings.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Join runs of text events into one, like `TextMergeStream`, with the
+/// source range covering the whole run. A soft line break joins the lines
+/// around it with a space, as it does in a browser.
+fn merge_text<'a>(events: impl Iterator<Item = (Event<'a>, Range<usize>)>) -> Vec<(Event<'a>, Range<usize>)> {
+    let mut merged: Vec<(Event<'a>, Range<usize>)> = Vec::new();
+    for (event, range) in events {
+        let event = match event {
+            Event::SoftBreak => Event::Text(" ".into()),
+            event => event,
+        };
+        if let (Event::Text(text), Some((Event::Text(previous), previous_range))) = (&event, merged.last_mut()) {
+            *previous = CowStr::from(format!("{}{}", previous, text));
+            previous_range.end = range.end;
+            continue;
+        }
+        merged.push((event, range));
+    }
+    merged
+}
+
+/// A list being read
+struct ListState {
+    ordered: bool,
+    /// Until a paragraph shows up directly in an item
+    tight: bool,
+    items: Vec<Vec<RenderedElement>>,
+    span: SourceSpan,
+}
+
+/// Where an element goes: the innermost open list item or block quote,
+/// which is the one that started last, or the document
+fn container<'a>(
+    elements: &'a mut Vec<RenderedElement>,
+    list_stack: &'a mut [ListState],
+    blockquote_stack: &'a mut [(Vec<RenderedElement>, SourceSpan)],
+) -> &'a mut Vec<RenderedElement> {
+    let list_start = list_stack.last().filter(|list| !list.items.is_empty()).map(|list| list.span.start);
+    let in_quote = blockquote_stack.last().is_some_and(|(_, span)| list_start.is_none_or(|start| span.start > start));
+    match blockquote_stack.last_mut() {
+        Some((items, _)) if in_quote => items,
+        _ => match list_stack.last_mut().and_then(|last| last.items.last_mut()) {
+            Some(item) => item,
+            None => elements,
+        },
+    }
+}
+
+/// Elements for a run of prose, with bare URLs and emails split out as links
+fn inline_elements(text: &str, strikethrough: bool, autolinks: bool) -> Vec<ElementKind> {
+    let prose = |text: &str| {
+        if strikethrough {
+            ElementKind::Strikethrough(text.to_string())
+        } else {
+            ElementKind::Paragraph(text.to_string())
+        }
+    };
+    if !autolinks {
+        return vec![prose(text)];
+    }
+
+    let mut elements = Vec::new();
+    let mut last = 0;
+    for link in autolink::find_autolinks(text) {
+        if link.range.start > last {
+            elements.push(prose(&text[last..link.range.start]));
+        }
+        elements.push(ElementKind::Link(text[link.range.clone()].to_string(), link.url));
+        last = link.range.end;
+    }
+    if last < text.len() || elements.is_empty() {
+        elements.push(prose(&text[last..]));
+    }
+    elements
+}
+
+/// Opening or closing code fence at the start of `line`: its character and length
+pub(crate) fn fence_marker(line: &str) -> Option<(char, usize)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let marker = trimmed.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let len = trimmed.chars().take_while(|&c| c == marker).count();
+    (len >= 3).then_some((marker, len))
 }
 
 impl Default for MarkdownRenderer {
@@ -175,3 +697,84 @@ impl Default for MarkdownRenderer {
         Self::new(&Theme::default())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(elements: &[RenderedElement]) -> Vec<String> {
+        elements.iter().map(|element| format!("{:?}", element.kind)).collect()
+    }
+
+    #[test]
+    fn test_breaks_stay_in_their_block() {
+        let renderer = MarkdownRenderer::default();
+        assert_eq!(kinds(&renderer.render("one\ntwo\n")), ["Paragraph(\"one two\")"]);
+
+        let elements = renderer.render("> one  \n> `two`\n");
+        assert_eq!(elements.len(), 1);
+        let ElementKind::BlockQuote(items, None) = &elements[0].kind else {
+            panic!("no quote in {:?}", elements);
+        };
+        assert_eq!(kinds(items), ["Paragraph(\"one\")", "LineBreak", "InlineCode(\"two\")"]);
+    }
+
+    #[test]
+    fn test_heading_anchor_hidden() {
+        let renderer = MarkdownRenderer::default();
+        assert_eq!(kinds(&renderer.render("## Use `rmd` {#usage}\n")), ["Heading(2, \"Use rmd\")"]);
+        assert_eq!(kinds(&renderer.render("## Odd {#}\n")), ["Heading(2, \"Odd {#}\")"]);
+    }
+
+    #[test]
+    fn test_numbered_headings() {
+        let mut renderer = MarkdownRenderer::default();
+        let markdown = "# A\n## B\n### C\n\nText with [a link](#a)\n";
+        assert_eq!(
+            kinds(&renderer.render(markdown)),
+            ["Heading(1, \"A\")", "Heading(2, \"B\")", "Heading(3, \"C\")", "Paragraph(\"Text with \")", "Link(\"a link\", \"#a\")"]
+        );
+        renderer.set_number_headings(true);
+        assert_eq!(
+            kinds(&renderer.render(markdown))[..3],
+            ["Heading(1, \"1. A\")", "Heading(2, \"1.1 B\")", "Heading(3, \"1.1.1 C\")"]
+        );
+    }
+
+    #[test]
+    fn test_alerts() {
+        let renderer = MarkdownRenderer::default();
+        let elements = renderer.render("> [!TIP]\n> Try `this`\n>\n> ```rust\n> fn main() {}\n> ```\n>\n> > [!WARNING]\n> > Careful\n");
+        assert_eq!(elements.len(), 1);
+        let ElementKind::BlockQuote(items, Some(AlertKind::Tip)) = &elements[0].kind else {
+            panic!("no tip in {:?}", elements);
+        };
+        assert_eq!(kinds(&items[..3]), ["Paragraph(\"Try \")", "InlineCode(\"this\")", "CodeBlock(\"rust\", \"fn main() {}\\n\")"]);
+        let ElementKind::BlockQuote(inner, Some(AlertKind::Warning)) = &items[3].kind else {
+            panic!("no nested warning in {:?}", items);
+        };
+        assert_eq!(kinds(inner), ["Paragraph(\"Careful\")"]);
+
+        let elements = renderer.render("> [!FOO]\n> Plain\n");
+        assert!(matches!(&elements[0].kind, ElementKind::BlockQuote(items, None) if items.len() == 1));
+    }
+
+    #[test]
+    fn test_list_tightness() {
+        let renderer = MarkdownRenderer::default();
+        let elements = renderer.render("- a\n- b\n\n1. c\n\n2. d\n");
+        assert!(matches!(elements[0].kind, ElementKind::UnorderedList(_, true)));
+        assert!(matches!(elements[1].kind, ElementKind::OrderedList(_, false)));
+    }
+
+    #[test]
+    fn test_inline_html_shown_as_written() {
+        let mut renderer = MarkdownRenderer::default();
+        renderer.set_render_html(false);
+        let elements = renderer.render("- press <kbd>Q</kbd>\n");
+        let ElementKind::UnorderedList(items, _) = &elements[0].kind else {
+            panic!("no list in {:?}", elements);
+        };
+        assert_eq!(kinds(&items[0]), ["Paragraph(\"press \")", "RawHtml(\"<kbd>\")", "Paragraph(\"Q\")", "RawHtml(\"</kbd>\")"]);
+    }
+}
@@ -0,0 +1,69 @@
+//! Hierarchical heading numbers (1., 1.1, 1.1.1) shown in the preview and
+//! in exports, never written into the source
+
+use super::{ElementKind, RenderedElement};
+
+/// Numbers headings in document order. Levels above the document's highest
+/// heading are left out, so a document of `##` headings starts at `1.`.
+/// A level skipped on the way down counts as 0: `#` then `###` is `1.` then
+/// `1.0.1`.
+pub struct HeadingNumbers {
+    /// Level numbered as the first component
+    top: u8,
+    counters: [usize; 6],
+}
+
+impl HeadingNumbers {
+    /// Numbering for a document whose headings have `levels`
+    pub fn for_levels(levels: impl IntoIterator<Item = u8>) -> Self {
+        let top = levels.into_iter().min().unwrap_or(1).clamp(1, 6);
+        Self { top, counters: [0; 6] }
+    }
+
+    /// Number of the next heading, which has `level`
+    pub fn next(&mut self, level: u8) -> String {
+        let index = usize::from(level.clamp(self.top, 6) - 1);
+        self.counters[index] += 1;
+        self.counters[index + 1..].fill(0);
+        let parts: Vec<String> = self.counters[usize::from(self.top - 1)..=index]
+            .iter()
+            .map(usize::to_string)
+            .collect();
+        match parts.as_slice() {
+            [only] => format!("{}.", only),
+            _ => parts.join("."),
+        }
+    }
+}
+
+/// Put the number in front of the text of each top-level heading
+pub fn number_elements(elements: &mut [RenderedElement]) {
+    let levels = elements.iter().filter_map(|element| match element.kind {
+        ElementKind::Heading(level, _) => Some(level),
+        _ => None,
+    });
+    let mut numbers = HeadingNumbers::for_levels(levels);
+    for element in elements {
+        if let ElementKind::Heading(level, text) = &mut element.kind {
+            *text = format!("{} {}", numbers.next(*level), text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heading_numbers() {
+        let levels = [1, 2, 2, 3, 1, 3, 2];
+        let mut numbers = HeadingNumbers::for_levels(levels);
+        let numbered: Vec<String> = levels.iter().map(|&level| numbers.next(level)).collect();
+        // The skipped level under the second `#` counts as 0
+        assert_eq!(numbered, ["1.", "1.1", "1.2", "1.2.1", "2.", "2.0.1", "2.1"]);
+
+        // Numbering starts at the highest level used
+        let mut numbers = HeadingNumbers::for_levels([2, 3, 2]);
+        assert_eq!([numbers.next(2), numbers.next(3), numbers.next(2)], ["1.", "1.1", "2."]);
+    }
+}
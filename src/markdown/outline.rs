@@ -0,0 +1,405 @@
+//! Document outline: headings with their source offsets and anchor slugs.
+//! A heading ending in `{#id}` has that id as its anchor instead of a slug.
+
+use pulldown_cmark::{CowStr, Event, Parser, Tag, TagEnd};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+/// A heading found in the document source
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeadingEntry {
+    /// Heading level (1-6)
+    pub level: u8,
+    /// Plain text of the heading
+    pub text: String,
+    /// Unique anchor slug, as used for `#fragment` links
+    pub slug: String,
+    /// Byte offset of the start of the heading in the source
+    pub offset: usize,
+    /// Whether the slug was written as `{#id}` rather than made from the text
+    pub explicit: bool,
+}
+
+/// Collect every heading in the document, in order
+pub fn headings(markdown: &str) -> Vec<HeadingEntry> {
+    let mut entries = Vec::new();
+    let mut events: Vec<_> = Parser::new(markdown).into_offset_iter().collect();
+    apply_explicit_anchors(&mut events);
+    let mut slugs = SlugCounter::reserving(explicit_ids(&events));
+    let mut current: Option<(u8, usize, Option<String>, String)> = None;
+
+    for (event, range) in events {
+        match event {
+            Event::Start(Tag::Heading { level, id, .. }) => {
+                current = Some((level as u8, range.start, id.map(|id| id.to_string()), String::new()));
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((level, offset, id, text)) = current.take() {
+                    let text = text.trim().to_string();
+                    entries.push(HeadingEntry {
+                        level,
+                        explicit: id.is_some(),
+                        slug: id.unwrap_or_else(|| slugs.unique(&text)),
+                        text,
+                        offset,
+                    });
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, _, _, ref mut buf)) = current {
+                    buf.push_str(&text);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    entries
+}
+
+/// Where an explicit `{#id}` anchor ending `text` starts, and the id.
+/// Only letters, digits, `-`, `_`, `:` and `.` make an id, so `{#}` and
+/// other braces stay text.
+pub fn explicit_anchor(text: &str) -> Option<(usize, &str)> {
+    let inner = text.trim_end().strip_suffix('}')?;
+    let start = inner.rfind("{#")?;
+    let id = &inner[start + 2..];
+    let valid = !id.is_empty() && id.chars().all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'));
+    let separated = inner[..start].is_empty() || inner[..start].ends_with(char::is_whitespace);
+    (valid && separated).then_some((start, id))
+}
+
+/// Give each heading of `events` the id of the `{#id}` its text ends in,
+/// taking the block off the text, and the text too when nothing else is left
+pub fn apply_explicit_anchors(events: &mut Vec<(Event<'_>, Range<usize>)>) {
+    let mut start = None;
+    let mut emptied = Vec::new();
+    for index in 0..events.len() {
+        match &events[index].0 {
+            Event::Start(Tag::Heading { .. }) => start = Some(index),
+            Event::End(TagEnd::Heading(_)) => {
+                let Some(start) = start.take().filter(|&start| start + 1 < index) else {
+                    continue;
+                };
+                let Event::Text(text) = &events[index - 1].0 else {
+                    continue;
+                };
+                let Some((at, id)) = explicit_anchor(text) else {
+                    continue;
+                };
+                let id = CowStr::from(id.to_string());
+                let text = text[..at].trim_end().to_string();
+                if text.is_empty() {
+                    emptied.push(index - 1);
+                }
+                events[index - 1].0 = Event::Text(text.into());
+                if let Event::Start(Tag::Heading { id: heading_id, .. }) = &mut events[start].0 {
+                    *heading_id = Some(id);
+                }
+            }
+            _ => {}
+        }
+    }
+    for index in emptied.into_iter().rev() {
+        events.remove(index);
+    }
+}
+
+/// The ids headings of `events` were given
+pub fn explicit_ids(events: &[(Event<'_>, Range<usize>)]) -> HashSet<String> {
+    events
+        .iter()
+        .filter_map(|(event, _)| match event {
+            Event::Start(Tag::Heading { id: Some(id), .. }) => Some(id.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Headings whose explicit id an earlier heading already has
+pub fn duplicate_anchors(headings: &[HeadingEntry]) -> Vec<&HeadingEntry> {
+    let mut seen = HashSet::new();
+    headings.iter().filter(|heading| !seen.insert(heading.slug.as_str()) && heading.explicit).collect()
+}
+
+/// Indices of the headings enclosing byte `offset`, outermost first
+pub fn heading_path(headings: &[HeadingEntry], offset: usize) -> Vec<usize> {
+    let mut path: Vec<usize> = Vec::new();
+    for (index, heading) in headings.iter().enumerate() {
+        if heading.offset > offset {
+            break;
+        }
+        while path.last().is_some_and(|&i| headings[i].level >= heading.level) {
+            path.pop();
+        }
+        path.push(index);
+    }
+    path
+}
+
+/// Indices of the headings at the same level and under the same parent as
+/// `headings[index]`, including itself
+pub fn siblings(headings: &[HeadingEntry], index: usize) -> Vec<usize> {
+    let level = headings[index].level;
+    let start = headings[..index]
+        .iter()
+        .rposition(|h| h.level < level)
+        .map_or(0, |i| i + 1);
+    let end = headings[index..]
+        .iter()
+        .position(|h| h.level < level)
+        .map_or(headings.len(), |i| index + i);
+    (start..end).filter(|&i| headings[i].level == level).collect()
+}
+
+/// Byte range of the section containing `caret_byte`: from the nearest
+/// heading before it up to the next heading of the same or a higher level,
+/// or the end of the text. `None` before the first heading.
+pub fn section_range(text: &str, caret_byte: usize) -> Option<Range<usize>> {
+    let headings = headings(text);
+    let index = headings.iter().rposition(|h| h.offset <= caret_byte)?;
+    Some(heading_section(text, &headings, index))
+}
+
+/// Byte range of the section `headings[index]` starts
+pub(crate) fn heading_section(text: &str, headings: &[HeadingEntry], index: usize) -> Range<usize> {
+    let heading = &headings[index];
+    let end = headings[index + 1..]
+        .iter()
+        .find(|h| h.level <= heading.level)
+        .map_or(text.len(), |h| h.offset);
+    heading.offset..end
+}
+
+/// A section moved elsewhere in the document, as a single edit
+#[derive(Debug, PartialEq)]
+pub struct SectionMove {
+    /// The text replaced, from the section's old place to its new one
+    pub range: Range<usize>,
+    pub replacement: String,
+    /// Where the moved heading starts afterwards
+    pub heading_offset: usize,
+}
+
+/// Whether the section of `headings[from]` can go before `headings[before]`,
+/// or at the end for `None`: not into itself, nor where it already is
+pub fn can_move_section(text: &str, headings: &[HeadingEntry], from: usize, before: Option<usize>) -> bool {
+    let section = heading_section(text, headings, from);
+    let target = before.map_or(text.len(), |i| headings[i].offset);
+    target < section.start || target > section.end
+}
+
+/// Move the section of `headings[from]`, subsections included, before
+/// `headings[before]` or to the end of the text, adding `level_change` to
+/// the levels of its ATX headings (kept within 1–6)
+pub fn move_section(
+    text: &str,
+    headings: &[HeadingEntry],
+    from: usize,
+    before: Option<usize>,
+    level_change: i8,
+) -> Option<SectionMove> {
+    if !can_move_section(text, headings, from, before) {
+        return None;
+    }
+    let section = heading_section(text, headings, from);
+    let target = before.map_or(text.len(), |i| headings[i].offset);
+
+    let mut moved = text[section.clone()].to_string();
+    let inner = headings[from..].iter().take_while(|h| h.offset < section.end);
+    for heading in inner.collect::<Vec<_>>().into_iter().rev() {
+        shift_atx_level(&mut moved, heading.offset - section.start, level_change);
+    }
+    if !moved.ends_with('\n') {
+        moved.push('\n');
+    }
+
+    let no_final_newline = !text.ends_with('\n');
+    if target < section.start {
+        // A blank line before the heading it now precedes
+        if !moved.ends_with("\n\n") {
+            moved.push('\n');
+        }
+        let mut replacement = format!("{}{}", moved, &text[target..section.start]);
+        if section.end == text.len() && no_final_newline {
+            replacement.truncate(replacement.trim_end_matches('\n').len());
+        }
+        Some(SectionMove { range: target..section.end, replacement, heading_offset: target })
+    } else {
+        let mut between = text[section.end..target].to_string();
+        while !between.ends_with("\n\n") {
+            between.push('\n');
+        }
+        let heading_offset = section.start + between.len();
+        let mut replacement = between + &moved;
+        if target == text.len() && no_final_newline {
+            replacement.truncate(replacement.trim_end_matches('\n').len());
+        }
+        Some(SectionMove { range: section.start..target, replacement, heading_offset })
+    }
+}
+
+/// Change the level of the ATX heading at `offset` of `text` by `change`
+pub(crate) fn shift_atx_level(text: &mut String, offset: usize, change: i8) {
+    let line = &text[offset..];
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let level = line[indent..].chars().take_while(|&c| c == '#').count();
+    if level == 0 || change == 0 {
+        // A setext heading, left as it is
+        return;
+    }
+    let new_level = (level as i8 + change).clamp(1, 6) as usize;
+    let start = offset + indent;
+    text.replace_range(start..start + level, &"#".repeat(new_level));
+}
+
+/// Convert heading text into a GitHub-style anchor slug
+pub fn slugify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Hands out unique slugs, suffixing repeats with `-1`, `-2`, ...
+#[derive(Default)]
+pub struct SlugCounter {
+    seen: HashMap<String, usize>,
+    /// Explicit ids, which slugs made from text keep clear of
+    reserved: HashSet<String>,
+}
+
+impl SlugCounter {
+    /// A counter whose slugs never take one of the explicit `ids`
+    pub fn reserving(ids: HashSet<String>) -> Self {
+        Self { seen: HashMap::new(), reserved: ids }
+    }
+
+    pub fn unique(&mut self, text: &str) -> String {
+        let base = slugify(text);
+        loop {
+            let count = self.seen.entry(base.clone()).or_insert(0);
+            let slug = if *count == 0 {
+                base.clone()
+            } else {
+                format!("{}-{}", base, count)
+            };
+            *count += 1;
+            if !self.reserved.contains(&slug) {
+                return slug;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello World"), "hello-world");
+        assert_eq!(slugify("What's new in v2.0?"), "whats-new-in-v20");
+        assert_eq!(slugify("snake_case and-dash"), "snake_case-and-dash");
+        assert_eq!(slugify("Überblick"), "überblick");
+    }
+
+    #[test]
+    fn test_headings_with_offsets_and_duplicates() {
+        let doc = "# Intro\n\ntext\n\n## Setup `cargo`\n\n## Intro\n";
+        let entries = headings(doc);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].slug, "intro");
+        assert_eq!(entries[1].text, "Setup cargo");
+        assert_eq!(entries[1].offset, doc.find("## Setup").unwrap());
+        assert_eq!(entries[2].slug, "intro-1");
+    }
+
+    #[test]
+    fn test_explicit_anchors() {
+        let doc = "## Install **now** {#setup}\n\n# Setup\n\n## Use `rmd` {#usage}\n\n## Other {#usage}\n\n## Odd {#}\n\n## Braces {#a b}\n";
+        let entries = headings(doc);
+        let slugs: Vec<&str> = entries.iter().map(|h| h.slug.as_str()).collect();
+        // The slug made from "Setup" keeps clear of the explicit one
+        assert_eq!(slugs, ["setup", "setup-1", "usage", "usage", "odd-", "braces-a-b"]);
+        assert_eq!(entries[0].text, "Install now");
+        assert_eq!(entries[2].text, "Use rmd");
+        assert!(entries[0].explicit && !entries[1].explicit);
+        // Malformed blocks stay in the text
+        assert_eq!(entries[4].text, "Odd {#}");
+        assert_eq!(entries[5].text, "Braces {#a b}");
+        let duplicates: Vec<usize> = duplicate_anchors(&entries).iter().map(|h| h.offset).collect();
+        assert_eq!(duplicates, [doc.find("## Other").unwrap()]);
+
+        assert_eq!(explicit_anchor("Title {#intro} "), Some((6, "intro")));
+        assert_eq!(explicit_anchor("Title{#intro}"), None);
+        assert_eq!(explicit_anchor("{#only}"), Some((0, "only")));
+    }
+
+    #[test]
+    fn test_heading_path_and_siblings() {
+        let doc = "# Architecture\n\n## Storage\n\n### Gap buffer\n\nbody\n\n### Rope\n\n## Rendering\n\n# Usage\n";
+        let entries = headings(doc);
+        let at = |needle: &str| doc.find(needle).unwrap();
+
+        assert_eq!(heading_path(&entries, 0), vec![0]);
+        assert_eq!(heading_path(&entries, at("body")), vec![0, 1, 2]);
+        assert_eq!(heading_path(&entries, at("## Rendering")), vec![0, 4]);
+        assert_eq!(heading_path(&entries, doc.len()), vec![5]);
+        assert!(heading_path(&headings("text\n\n# Later\n"), 0).is_empty());
+
+        assert_eq!(siblings(&entries, 2), vec![2, 3]);
+        assert_eq!(siblings(&entries, 1), vec![1, 4]);
+        assert_eq!(siblings(&entries, 5), vec![0, 5]);
+    }
+
+    #[test]
+    fn test_section_range() {
+        let doc = "preface\n\n# Guide\n\nintro\n\n## Install\n\nsteps\n\n### Linux\n\napt\n\n## Use\n\nrun\n\n# Appendix\n\nend\n";
+        let at = |needle: &str| doc.find(needle).unwrap();
+        let section = |caret: usize| section_range(doc, caret).map(|range| &doc[range]);
+
+        // Content before the first heading has no section
+        assert_eq!(section(0), None);
+        // A section includes its subsections
+        assert_eq!(section(at("steps")), Some("## Install\n\nsteps\n\n### Linux\n\napt\n\n"));
+        assert_eq!(section(at("intro")), Some(&doc[at("# Guide")..at("# Appendix")]));
+        assert_eq!(section(at("apt")), Some("### Linux\n\napt\n\n"));
+        // The last section runs to the end of the file
+        assert_eq!(section(at("end")), Some("# Appendix\n\nend\n"));
+        assert_eq!(section(doc.len()), Some("# Appendix\n\nend\n"));
+    }
+
+    #[test]
+    fn test_move_section() {
+        let doc = "# A\n\na\n\n## A1\n\n# B\n\nb\n\n# C\n\nc";
+        let entries = headings(doc);
+        let apply = |edit: SectionMove| {
+            let mut text = doc.to_string();
+            text.replace_range(edit.range, &edit.replacement);
+            (text, edit.heading_offset)
+        };
+
+        // Down, to the end of a text without a final newline
+        let (text, offset) = apply(move_section(doc, &entries, 0, None, 0).unwrap());
+        assert_eq!(text, "# B\n\nb\n\n# C\n\nc\n\n# A\n\na\n\n## A1");
+        assert_eq!(&text[offset..offset + 3], "# A");
+
+        // Up, demoted to sit under B
+        let (text, offset) = apply(move_section(doc, &entries, 3, Some(2), 1).unwrap());
+        assert_eq!(text, "# A\n\na\n\n## A1\n\n## C\n\nc\n\n# B\n\nb");
+        assert_eq!(offset, doc.find("# B").unwrap());
+
+        // Not into itself, nor where it already is
+        assert!(!can_move_section(doc, &entries, 0, Some(1)));
+        assert!(!can_move_section(doc, &entries, 0, Some(0)));
+        assert!(!can_move_section(doc, &entries, 0, Some(2)));
+        assert_eq!(move_section(doc, &entries, 3, None, 0), None);
+    }
+}
@@ -0,0 +1,178 @@
+//! Settings a document overrides for itself in its front matter, under
+//! `rmd.`: either `rmd.word_wrap: false` lines or an indented `rmd:` block.
+//! They apply over the global config only while the document is open.
+//! The common top-level `lang` key counts too, below `rmd.lang`.
+
+use super::flavor::{self, Flavor};
+use super::language;
+
+/// The `rmd.*` keys of a document's front matter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentSettings {
+    pub word_wrap: Option<bool>,
+    pub flavor: Option<Flavor>,
+    /// Widest the preview's text column gets, 0 for no limit
+    pub preview_width: Option<f32>,
+    /// Only ever locks: `false` leaves the document as it was
+    pub readonly: Option<bool>,
+    /// Language the document is written in, such as `ja`
+    pub lang: Option<String>,
+    /// Keys or values that weren't understood, which are otherwise ignored
+    pub warnings: Vec<String>,
+}
+
+impl DocumentSettings {
+    /// Settings from the front matter of `markdown`
+    pub fn parse(markdown: &str) -> Self {
+        let mut settings = Self::default();
+        let Some(range) = flavor::front_matter(markdown) else {
+            return settings;
+        };
+        let mut in_block = false;
+        for line in markdown[range].lines() {
+            let entry = if let Some(entry) = line.strip_prefix("rmd.") {
+                in_block = false;
+                entry
+            } else if in_block && (line.starts_with([' ', '\t']) || line.is_empty()) {
+                line.trim_start()
+            } else if let Some(value) = line.strip_prefix("lang:") {
+                // Other tools' key, so left alone when it isn't a tag
+                let value = unquote(value);
+                if settings.lang.is_none() && language::is_valid_tag(value) {
+                    settings.lang = Some(value.to_string());
+                }
+                in_block = false;
+                continue;
+            } else {
+                in_block = line.trim_end() == "rmd:";
+                continue;
+            };
+            if entry.trim().is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            match entry.split_once(':') {
+                Some((key, value)) => settings.set(key.trim(), value),
+                None => settings.warnings.push(format!("rmd.{}: expected a value after a colon", entry.trim())),
+            }
+        }
+        settings
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        let value = unquote(value);
+        let invalid = |expected: &str| format!("rmd.{}: expected {}, not \"{}\"", key, expected, value);
+        match key {
+            "word_wrap" => match boolean(value) {
+                Some(wrap) => self.word_wrap = Some(wrap),
+                None => self.warnings.push(invalid("true or false")),
+            },
+            "flavor" => match Flavor::from_id(value) {
+                Some(flavor) => self.flavor = Some(flavor),
+                None => self.warnings.push(invalid("commonmark, gfm, or rmd")),
+            },
+            "preview_width" => match value.parse::<f32>().ok().filter(|width| width.is_finite() && *width >= 0.0) {
+                Some(width) => self.preview_width = Some(width),
+                None => self.warnings.push(invalid("a width in pixels")),
+            },
+            "readonly" => match boolean(value) {
+                Some(readonly) => self.readonly = Some(readonly),
+                None => self.warnings.push(invalid("true or false")),
+            },
+            "lang" if language::is_valid_tag(value) => self.lang = Some(value.to_string()),
+            "lang" => self.warnings.push(invalid("a language tag such as en or ja")),
+            _ => self.warnings.push(format!("Unknown setting rmd.{}", key)),
+        }
+    }
+
+    /// Whether the front matter had no `rmd.*` keys at all
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The overridden settings, as `key: value` lines to show
+    pub fn overrides(&self) -> Vec<String> {
+        let mut overrides = Vec::new();
+        if let Some(wrap) = self.word_wrap {
+            overrides.push(format!("word_wrap: {}", wrap));
+        }
+        if let Some(flavor) = self.flavor {
+            overrides.push(format!("flavor: {}", flavor.id()));
+        }
+        if let Some(width) = self.preview_width {
+            overrides.push(format!("preview_width: {}", width));
+        }
+        if let Some(readonly) = self.readonly {
+            overrides.push(format!("readonly: {}", readonly));
+        }
+        if let Some(lang) = &self.lang {
+            overrides.push(format!("lang: {}", lang));
+        }
+        overrides
+    }
+}
+
+/// `value` without a trailing comment or quotes around it
+pub(crate) fn unquote(value: &str) -> &str {
+    let value = value.split(" #").next().unwrap_or_default().trim();
+    value.trim_matches(|c| c == '"' || c == '\'')
+}
+
+fn boolean(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "yes" | "on" => Some(true),
+        "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let doc = "---\ntitle: Notes\nrmd.word_wrap: false\nrmd.flavor: \"commonmark\"\nrmd:\n  preview_width: 900 # pixels\n\n  readonly: yes\nauthor: Me\n---\n# Notes\nrmd.word_wrap: true\n";
+        let settings = DocumentSettings::parse(doc);
+        assert_eq!(settings.word_wrap, Some(false));
+        assert_eq!(settings.flavor, Some(Flavor::CommonMark));
+        assert_eq!(settings.preview_width, Some(900.0));
+        assert_eq!(settings.readonly, Some(true));
+        assert!(settings.warnings.is_empty());
+        assert_eq!(settings.overrides(), ["word_wrap: false", "flavor: commonmark", "preview_width: 900", "readonly: true"]);
+
+        // Outside front matter, nothing counts
+        assert!(DocumentSettings::parse("rmd.word_wrap: false\n").is_empty());
+        assert!(DocumentSettings::parse("---\ntitle: Notes\nrmd_flavor: gfm\n---\n").is_empty());
+    }
+
+    #[test]
+    fn test_lang() {
+        let settings = DocumentSettings::parse("---\ntitle: Notes\nlang: \"ja\"\n---\n");
+        assert_eq!(settings.lang.as_deref(), Some("ja"));
+        assert_eq!(settings.overrides(), ["lang: ja"]);
+        // `rmd.lang` wins, wherever it is
+        let settings = DocumentSettings::parse("---\nrmd.lang: zh-Hans\nlang: en\n---\n");
+        assert_eq!(settings.lang.as_deref(), Some("zh-Hans"));
+        let settings = DocumentSettings::parse("---\nlang: English\nrmd.lang: Japanese\n---\n");
+        assert_eq!(settings.lang, None);
+        assert_eq!(settings.warnings, ["rmd.lang: expected a language tag such as en or ja, not \"Japanese\""]);
+    }
+
+    #[test]
+    fn test_unknown_keys_are_warnings() {
+        let doc = "---\nrmd.spell_check: false\nrmd.word_wrap: maybe\nrmd.preview_width: -5\nrmd.readonly\nrmd.flavor: gfm\n---\n";
+        let settings = DocumentSettings::parse(doc);
+        assert_eq!(settings.flavor, Some(Flavor::Gfm));
+        assert_eq!(settings.word_wrap, None);
+        assert_eq!(
+            settings.warnings,
+            [
+                "Unknown setting rmd.spell_check",
+                "rmd.word_wrap: expected true or false, not \"maybe\"",
+                "rmd.preview_width: expected a width in pixels, not \"-5\"",
+                "rmd.readonly: expected a value after a colon",
+            ]
+        );
+        assert!(!settings.is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+//! Manual page breaks: `<!-- pagebreak -->` or `\newpage` on a line of its
+//! own. The preview marks where they are, HTML export breaks the printed
+//! page there, and Word export starts a new page.
+
+use super::{fence_marker, flavor, ElementKind, RenderedElement};
+use std::ops::Range;
+
+/// Whether `line` asks for a page break
+fn is_page_break(line: &str) -> bool {
+    let line = line.trim();
+    let comment = line
+        .strip_prefix("<!--")
+        .and_then(|rest| rest.strip_suffix("-->"))
+        .is_some_and(|inner| inner.trim().eq_ignore_ascii_case("pagebreak"));
+    comment || line == "\\newpage"
+}
+
+/// Byte ranges of the page break lines of `markdown`, newline included,
+/// outside code blocks and front matter. `\newpage` needs blank lines
+/// around it, or it is part of the paragraph it's in.
+pub fn page_breaks(markdown: &str) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+    let mut offset = flavor::front_matter(markdown).map_or(0, |range| {
+        range.end + markdown[range.end..].find('\n').map_or(markdown.len() - range.end, |i| i + 1)
+    });
+    for line in markdown[offset..].split_inclusive('\n') {
+        lines.push((offset, line));
+        offset += line.len();
+    }
+    let blank = |index: Option<usize>| index.and_then(|index| lines.get(index)).is_none_or(|(_, line)| line.trim().is_empty());
+
+    let mut breaks = Vec::new();
+    let mut fence: Option<(char, usize)> = None;
+    for (index, &(start, line)) in lines.iter().enumerate() {
+        if let Some((marker, len)) = fence_marker(line) {
+            match fence {
+                None => fence = Some((marker, len)),
+                Some((open, open_len)) if open == marker && len >= open_len => fence = None,
+                Some(_) => {}
+            }
+            continue;
+        }
+        let alone = line.trim() != "\\newpage" || (blank(index.checked_sub(1)) && blank(Some(index + 1)));
+        if fence.is_none() && is_page_break(line) && alone {
+            breaks.push(start..start + line.len());
+        }
+    }
+    breaks
+}
+
+/// Where an exporter's parser event falls among the page breaks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakEvent {
+    /// Not part of a page break
+    Outside,
+    /// The first event of a page break, to put the break in place of
+    Start,
+    /// Another event of the same page break, to leave out
+    Inside,
+}
+
+/// Page breaks of a document, for telling which parser events make them
+pub struct PageBreaks {
+    breaks: Vec<Range<usize>>,
+    /// The break whose first event was seen
+    started: Option<usize>,
+}
+
+impl PageBreaks {
+    pub fn new(markdown: &str) -> Self {
+        Self { breaks: page_breaks(markdown), started: None }
+    }
+
+    /// Where the event parsed from `range` falls
+    pub fn event(&mut self, range: &Range<usize>) -> BreakEvent {
+        let Some(index) = self.breaks.iter().position(|line| line.start <= range.start && range.end <= line.end) else {
+            return BreakEvent::Outside;
+        };
+        if self.started == Some(index) {
+            return BreakEvent::Inside;
+        }
+        self.started = Some(index);
+        BreakEvent::Start
+    }
+}
+
+/// Put a page break among `elements`, rendered from `markdown`, in place
+/// of whatever each break line rendered as
+pub fn collect_page_breaks(elements: &mut Vec<RenderedElement>, markdown: &str) {
+    for line in page_breaks(markdown) {
+        let inside = |element: &RenderedElement| element.span.start >= line.start && element.span.end <= line.end;
+        elements.retain(|element| !inside(element));
+        let at = elements.iter().position(|element| element.span.start >= line.end).unwrap_or(elements.len());
+        elements.insert(at, RenderedElement::new(ElementKind::PageBreak, line));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::MarkdownRenderer;
+
+    #[test]
+    fn test_page_breaks() {
+        let doc = "---\ntitle: x\n---\nOne\n\n<!-- pagebreak -->\n# Two\n\n\\newpage\n\nThree\n\\newpage\nstill three\n\n```\n<!-- pagebreak -->\n```\n<!--PageBreak-->";
+        let lines: Vec<_> = page_breaks(doc).into_iter().map(|range| &doc[range]).collect();
+        assert_eq!(lines, ["<!-- pagebreak -->\n", "\\newpage\n", "<!--PageBreak-->"]);
+        assert!(page_breaks("<!-- page break -->\n\\newpage please\n").is_empty());
+
+        let elements = MarkdownRenderer::default().render(doc);
+        let breaks = elements.iter().filter(|element| matches!(element.kind, ElementKind::PageBreak)).count();
+        assert_eq!(breaks, 3);
+        // Only the `\newpage` inside a paragraph still shows as text
+        let texts = elements.iter().filter(|element| element.plain_text().contains("\\newpage")).count();
+        assert_eq!(texts, 1);
+    }
+
+    #[test]
+    fn test_break_events() {
+        let doc = "a\n\n\\newpage\n\nb\n";
+        let mut breaks = PageBreaks::new(doc);
+        assert_eq!(breaks.event(&(0..2)), BreakEvent::Outside);
+        assert_eq!(breaks.event(&(3..12)), BreakEvent::Start);
+        assert_eq!(breaks.event(&(3..11)), BreakEvent::Inside);
+        assert_eq!(breaks.event(&(13..15)), BreakEvent::Outside);
+    }
+}
@@ -0,0 +1,275 @@
+//! Renaming a heading along with the `#anchor` links that point at it, and
+//! at any other heading whose slug changes with it
+
+use super::outline;
+use super::wiki;
+use pulldown_cmark::{Event, Parser, Tag};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Text to put in place of a byte range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replacement {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+/// A line as it reads before and after a rename, for confirming it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineChange {
+    /// One-based line number
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+/// Where a link points, without its `#fragment`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkTarget<'a> {
+    /// The URL of a Markdown link, empty for a link within the document
+    Url(&'a str),
+    /// The note of a wiki link, empty for `[[#heading]]`
+    Wiki(&'a str),
+}
+
+/// A heading rename worked out for one document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadingRename {
+    /// Anchors that change, old to new. Besides the renamed heading's,
+    /// a repeated heading's `-1` suffix can shift.
+    pub slugs: HashMap<String, String>,
+    /// The heading's new text and the document's own links updated
+    pub edits: Vec<Replacement>,
+}
+
+/// Source range of the text of the heading starting at `offset`, without
+/// its `#` markers, closing hashes or `{#id}`. Only the first line of a
+/// setext heading.
+pub fn heading_text_range(text: &str, offset: usize) -> Option<Range<usize>> {
+    let line_end = text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+    let line = text[offset..line_end].trim_end_matches('\r');
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let rest = &line[indent..];
+    let hashes = rest.bytes().take_while(|&b| b == b'#').count();
+    let atx = (1..=6).contains(&hashes) && rest[hashes..].chars().next().is_none_or(char::is_whitespace);
+    let start = if atx {
+        let content = &rest[hashes..];
+        offset + indent + hashes + (content.len() - content.trim_start().len())
+    } else {
+        offset + indent
+    };
+    let mut content = text[start..offset + line.len()].trim_end();
+    if atx {
+        let unclosed = content.trim_end_matches('#');
+        if unclosed.is_empty() || unclosed.ends_with([' ', '\t']) {
+            content = unclosed.trim_end();
+        }
+    }
+    if let Some((at, _)) = outline::explicit_anchor(content) {
+        content = content[..at].trim_end();
+    }
+    Some(start..start + content.len())
+}
+
+/// Rename the heading starting at `offset` to `new_text`, updating the
+/// document's own links to every anchor that changes. `None` when the new
+/// text is empty or would no longer be the same heading.
+pub fn rename_heading(text: &str, offset: usize, new_text: &str) -> Option<HeadingRename> {
+    let new_text = new_text.trim();
+    if new_text.is_empty() || new_text.contains('\n') {
+        return None;
+    }
+    let range = heading_text_range(text, offset)?;
+    let heading = Replacement { range: range.clone(), text: new_text.to_string() };
+    let renamed = apply(text, std::slice::from_ref(&heading));
+
+    let before = outline::headings(text);
+    let after = outline::headings(&renamed);
+    if before.len() != after.len() {
+        return None;
+    }
+    let slugs: HashMap<String, String> = before
+        .into_iter()
+        .zip(after)
+        .filter(|(old, new)| old.slug != new.slug)
+        .map(|(old, new)| (old.slug, new.slug))
+        .collect();
+
+    let mut edits = vec![heading];
+    // Links inside the heading went with its old text
+    edits.extend(
+        link_replacements(text, &slugs, |target| matches!(target, LinkTarget::Url("") | LinkTarget::Wiki("")))
+            .into_iter()
+            .filter(|edit| edit.range.end <= range.start || edit.range.start >= range.end),
+    );
+    edits.sort_by_key(|edit| edit.range.start);
+    Some(HeadingRename { slugs, edits })
+}
+
+/// Point the links of `text` that `points_here` accepts from the old to the
+/// new anchors of `slugs`: inline links, reference definitions and wiki
+/// links
+pub fn link_replacements(
+    text: &str,
+    slugs: &HashMap<String, String>,
+    points_here: impl Fn(LinkTarget) -> bool,
+) -> Vec<Replacement> {
+    if slugs.is_empty() {
+        return Vec::new();
+    }
+    let mut edits = Vec::new();
+    let retarget = |url: &str, source: Range<usize>, edits: &mut Vec<Replacement>| {
+        let Some((path, fragment)) = url.split_once('#') else {
+            return;
+        };
+        let Some(new) = slugs.get(fragment) else {
+            return;
+        };
+        if !points_here(LinkTarget::Url(path)) {
+            return;
+        }
+        if let Some(at) = fragment_in(&text[source.clone()], fragment) {
+            let start = source.start + at + 1;
+            edits.push(Replacement { range: start..start + fragment.len(), text: new.clone() });
+        }
+    };
+
+    let mut parser = Parser::new(text).into_offset_iter();
+    for (event, range) in parser.by_ref() {
+        if let Event::Start(Tag::Link { dest_url, .. }) = event {
+            retarget(&dest_url, range, &mut edits);
+        }
+    }
+    for (_, definition) in parser.reference_definitions().iter() {
+        retarget(&definition.dest, definition.span.clone(), &mut edits);
+    }
+
+    for link in wiki::find_wiki_links(text) {
+        let Some((_, fragment)) = link.target.split_once('#') else {
+            continue;
+        };
+        let fragment = fragment.trim();
+        let Some(new) = slugs.get(fragment) else {
+            continue;
+        };
+        if !points_here(LinkTarget::Wiki(link.note())) {
+            continue;
+        }
+        if let Some(at) = fragment_in(&text[link.range.clone()], fragment) {
+            let start = link.range.start + at + 1;
+            edits.push(Replacement { range: start..start + fragment.len(), text: new.clone() });
+        }
+    }
+
+    edits.sort_by_key(|edit| edit.range.start);
+    edits.dedup_by_key(|edit| edit.range.start);
+    edits
+}
+
+/// Offset of the `#` before `fragment` in a link's source, taking the last
+/// one so a link text mentioning it is skipped
+fn fragment_in(source: &str, fragment: &str) -> Option<usize> {
+    let needle = format!("#{}", fragment);
+    source.rmatch_indices(&needle).map(|(at, _)| at).find(|&at| {
+        let after = &source[at + needle.len()..];
+        !after.starts_with(|c: char| c.is_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'))
+    })
+}
+
+/// `text` with `edits`, sorted and not overlapping, applied
+pub fn apply(text: &str, edits: &[Replacement]) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for edit in edits {
+        result.push_str(&text[last..edit.range.start]);
+        result.push_str(&edit.text);
+        last = edit.range.end;
+    }
+    result.push_str(&text[last..]);
+    result
+}
+
+/// Each line `edits`, sorted, touch, as it reads before and after them
+pub fn line_changes(text: &str, edits: &[Replacement]) -> Vec<LineChange> {
+    let line_end = |offset: usize| text[offset..].find('\n').map_or(text.len(), |i| offset + i);
+    let mut changes = Vec::new();
+    let mut rest = edits;
+    while let Some(first) = rest.first() {
+        let start = text[..first.range.start].rfind('\n').map_or(0, |i| i + 1);
+        let mut end = line_end(first.range.end);
+        let mut count = 1;
+        while let Some(edit) = rest.get(count).filter(|edit| edit.range.start <= end) {
+            end = end.max(line_end(edit.range.end));
+            count += 1;
+        }
+        let shifted: Vec<Replacement> = rest[..count]
+            .iter()
+            .map(|edit| Replacement { range: edit.range.start - start..edit.range.end - start, text: edit.text.clone() })
+            .collect();
+        let before = &text[start..end];
+        changes.push(LineChange {
+            line: text[..start].matches('\n').count() + 1,
+            before: before.to_string(),
+            after: apply(before, &shifted),
+        });
+        rest = &rest[count..];
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heading_text_range() {
+        let text = "# Title\n\n  ## Setup ##\n\n### Usage {#use}\n\nLong name\n=========\n";
+        let range_of = |heading: &str| {
+            let offset = text.find(heading).unwrap();
+            let range = heading_text_range(text, offset).unwrap();
+            text[range].to_string()
+        };
+        assert_eq!(range_of("# Title"), "Title");
+        assert_eq!(range_of("  ## Setup"), "Setup");
+        assert_eq!(range_of("### Usage"), "Usage");
+        assert_eq!(range_of("Long name"), "Long name");
+    }
+
+    #[test]
+    fn test_rename_heading() {
+        let text = "# Intro\n\nSee [intro](#intro), [again][ref] and [[#intro]].\n\n\
+                    # Intro\n\nThe [second](#intro-1) and [elsewhere](other.md#intro).\n\n\
+                    [ref]: #intro\n";
+        let rename = rename_heading(text, 0, " Overview ").unwrap();
+        // The repeated heading loses its suffix
+        assert_eq!(rename.slugs.get("intro").map(String::as_str), Some("overview"));
+        assert_eq!(rename.slugs.get("intro-1").map(String::as_str), Some("intro"));
+        assert_eq!(
+            apply(text, &rename.edits),
+            "# Overview\n\nSee [intro](#overview), [again][ref] and [[#overview]].\n\n\
+             # Intro\n\nThe [second](#intro) and [elsewhere](other.md#intro).\n\n\
+             [ref]: #overview\n"
+        );
+
+        let changes = line_changes(text, &rename.edits);
+        assert_eq!(changes.len(), 4);
+        assert_eq!(changes[1].line, 3);
+        assert_eq!(changes[1].after, "See [intro](#overview), [again][ref] and [[#overview]].");
+
+        // An explicit id stays, so only the text changes
+        let rename = rename_heading("## Setup {#install}\n\n[x](#install)\n", 0, "Installing").unwrap();
+        assert!(rename.slugs.is_empty());
+        assert_eq!(rename.edits.len(), 1);
+        assert_eq!(rename_heading("# Title\n", 0, "  "), None);
+    }
+
+    #[test]
+    fn test_link_replacements_elsewhere() {
+        let slugs: HashMap<String, String> = [("setup".to_string(), "install".to_string())].into_iter().collect();
+        let text = "[a](guide.md#setup) [b](#setup) [[Guide#setup|c]] [d](notes.md#setup)\n";
+        let edits = link_replacements(text, &slugs, |target| {
+            matches!(target, LinkTarget::Url("guide.md") | LinkTarget::Wiki("Guide"))
+        });
+        assert_eq!(apply(text, &edits), "[a](guide.md#install) [b](#setup) [[Guide#install|c]] [d](notes.md#setup)\n");
+    }
+}
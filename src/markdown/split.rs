@@ -0,0 +1,189 @@
+//! Splitting a document into one file per section, and merging files back
+//! into one. Both work on text only; writing and reading the files is left
+//! to the caller.
+
+use super::flavor;
+use super::outline;
+use crate::utils::sanitize_filename;
+use std::collections::HashSet;
+use std::ops::Range;
+
+/// What becomes of a section in the document it was split out of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Replace {
+    /// Left where it is
+    #[default]
+    Keep,
+    /// Replaced by `<!-- include: ./file.md -->`
+    Include,
+    /// Replaced by a link to its file
+    Link,
+}
+
+impl Replace {
+    pub const ALL: [Replace; 3] = [Replace::Keep, Replace::Include, Replace::Link];
+}
+
+/// A section to be written to a file of its own
+#[derive(Debug, Clone, PartialEq)]
+pub struct Part {
+    /// Text of the section's heading
+    pub title: String,
+    pub file_name: String,
+    /// Where the section is in the document
+    pub range: Range<usize>,
+}
+
+/// The files a split writes, and what the document becomes
+#[derive(Debug, PartialEq)]
+pub struct Split {
+    pub parts: Vec<Part>,
+    /// The document with the sections replaced, `None` when they are kept
+    pub remainder: Option<String>,
+}
+
+/// Split `text` at its headings of `level`, each section with its
+/// subsections becoming a part. File names come from the headings, with
+/// `-2`, `-3`... added when one is in `taken` (names in the folder written
+/// to, lowercase) or used by an earlier part. `prefix` is put before a file
+/// name to refer to it from the document, such as `./parts/`.
+pub fn split_by_headings(text: &str, level: u8, replace: Replace, taken: &HashSet<String>, prefix: &str) -> Split {
+    let headings = outline::headings(text);
+    let mut used = taken.clone();
+    let parts: Vec<Part> = headings
+        .iter()
+        .enumerate()
+        .filter(|(_, heading)| heading.level == level)
+        .map(|(index, heading)| {
+            let file_name = unique_file_name(&heading.text, &mut used);
+            Part { title: heading.text.clone(), file_name, range: outline::heading_section(text, &headings, index) }
+        })
+        .collect();
+
+    let remainder = (replace != Replace::Keep && !parts.is_empty()).then(|| {
+        let mut remainder = text.to_string();
+        for part in parts.iter().rev() {
+            let target = format!("{}{}", prefix, part.file_name);
+            let reference = match replace {
+                Replace::Link => format!("[{}]({})", part.title.replace('[', "\\[").replace(']', "\\]"), target),
+                _ => format!("<!-- include: {} -->", target),
+            };
+            let end = if part.range.end == text.len() { "\n" } else { "\n\n" };
+            remainder.replace_range(part.range.clone(), &format!("{}{}", reference, end));
+        }
+        remainder
+    });
+    Split { parts, remainder }
+}
+
+/// `title` as a file name not yet in `used`, which it is added to
+fn unique_file_name(title: &str, used: &mut HashSet<String>) -> String {
+    let stem = sanitize_filename(&outline::slugify(title));
+    let stem = if stem.is_empty() { "section".to_string() } else { stem };
+    let mut name = format!("{}.md", stem);
+    let mut count = 1;
+    while used.contains(&name.to_lowercase()) {
+        count += 1;
+        name = format!("{}-{}.md", stem, count);
+    }
+    used.insert(name.to_lowercase());
+    name
+}
+
+/// The text of a part, ending in a single newline
+pub fn part_text(text: &str, part: &Part) -> String {
+    format!("{}\n", text[part.range.clone()].trim_end())
+}
+
+/// `documents` one after another, separated by a blank line. Front matter
+/// is kept from the first only. With `top_level`, the headings of each are
+/// shifted so its highest one is at that level.
+pub fn merge(documents: &[&str], top_level: Option<u8>) -> String {
+    let mut merged = String::new();
+    for (index, document) in documents.iter().enumerate() {
+        let (front, body) = document.split_at(front_matter_end(document));
+        let mut body = body.to_string();
+        if let Some(top_level) = top_level {
+            shift_headings(&mut body, top_level);
+        }
+        let document = if index == 0 { format!("{}{}", front, body) } else { body };
+        let document = document.trim_matches('\n');
+        if document.is_empty() {
+            continue;
+        }
+        if !merged.is_empty() {
+            merged.push_str("\n\n");
+        }
+        merged.push_str(document);
+    }
+    merged.push('\n');
+    merged
+}
+
+/// Where the front matter ends with its closing line, 0 without any
+fn front_matter_end(text: &str) -> usize {
+    let Some(range) = flavor::front_matter(text) else {
+        return 0;
+    };
+    text[range.end..].find('\n').map_or(text.len(), |i| range.end + i + 1)
+}
+
+/// Shift the ATX headings of `text` so the highest is at `top_level`
+fn shift_headings(text: &mut String, top_level: u8) {
+    let headings = outline::headings(text);
+    let Some(highest) = headings.iter().map(|heading| heading.level).min() else {
+        return;
+    };
+    let change = top_level as i8 - highest as i8;
+    // From the end, so the offsets of the others stay put
+    for heading in headings.iter().rev() {
+        outline::shift_atx_level(text, heading.offset, change);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "# Guide\n\nIntro\n\n## Install\n\nSteps\n\n### Linux\n\napt\n\n## Install\n\nAgain\n";
+
+    #[test]
+    fn test_split_by_headings() {
+        let taken = HashSet::from(["install.md".to_string()]);
+        let split = split_by_headings(DOC, 2, Replace::Keep, &taken, "./");
+        let names: Vec<&str> = split.parts.iter().map(|part| part.file_name.as_str()).collect();
+        assert_eq!(names, ["install-2.md", "install-3.md"]);
+        assert_eq!(part_text(DOC, &split.parts[0]), "## Install\n\nSteps\n\n### Linux\n\napt\n");
+        assert_eq!(part_text(DOC, &split.parts[1]), "## Install\n\nAgain\n");
+        assert!(split.remainder.is_none());
+
+        let split = split_by_headings(DOC, 2, Replace::Include, &HashSet::new(), "./parts/");
+        assert_eq!(
+            split.remainder.unwrap(),
+            "# Guide\n\nIntro\n\n<!-- include: ./parts/install.md -->\n\n<!-- include: ./parts/install-2.md -->\n"
+        );
+        let split = split_by_headings(DOC, 3, Replace::Link, &HashSet::new(), "./");
+        assert_eq!(split.parts[0].file_name, "linux.md");
+        assert!(split.remainder.unwrap().contains("[Linux](./linux.md)\n\n## Install"));
+
+        assert!(split_by_headings(DOC, 4, Replace::Link, &HashSet::new(), "./").parts.is_empty());
+    }
+
+    #[test]
+    fn test_file_names() {
+        let mut used = HashSet::new();
+        assert_eq!(unique_file_name("What's new?", &mut used), "whats-new.md");
+        assert_eq!(unique_file_name("What's New", &mut used), "whats-new-2.md");
+        assert_eq!(unique_file_name("!!!", &mut used), "section.md");
+    }
+
+    #[test]
+    fn test_merge() {
+        let first = "---\ntitle: Book\n---\n# One\n\ntext\n";
+        let second = "---\ntitle: Part\n---\n\n## Two\n\n### Deeper\n";
+        assert_eq!(merge(&[first, second], None), "---\ntitle: Book\n---\n# One\n\ntext\n\n## Two\n\n### Deeper\n");
+        assert_eq!(merge(&[first, second], Some(2)), "---\ntitle: Book\n---\n## One\n\ntext\n\n## Two\n\n### Deeper\n");
+        // Code that looks like a heading is left alone
+        assert_eq!(merge(&["# A\n\n```\n# not\n```\n"], Some(3)), "### A\n\n```\n# not\n```\n");
+    }
+}
@@ -0,0 +1,86 @@
+//! Table of contents blocks kept between `<!-- toc -->` markers
+
+use super::generated::Markers;
+use super::outline;
+use std::ops::Range;
+
+pub const TOC_START: &str = "<!-- toc -->";
+pub const TOC_END: &str = "<!-- tocstop -->";
+
+const MARKERS: Markers = Markers { start: TOC_START, end: TOC_END };
+
+/// Written between the markers when the document has no headings to list
+const NO_HEADINGS: &str = "<!-- no headings -->";
+
+/// Nested list of links to headings of level `max_depth` and above
+pub fn toc_list(markdown: &str, max_depth: u8) -> String {
+    let mut list = String::new();
+    // Levels of the headings enclosing the current one
+    let mut parents: Vec<u8> = Vec::new();
+    for heading in outline::headings(markdown).into_iter().filter(|h| h.level <= max_depth) {
+        while parents.last().is_some_and(|&level| level >= heading.level) {
+            parents.pop();
+        }
+        list.push_str(&"  ".repeat(parents.len()));
+        list.push_str(&format!("- [{}](#{})\n", heading.text, heading.slug));
+        parents.push(heading.level);
+    }
+    if list.is_empty() {
+        list = format!("{}\n", NO_HEADINGS);
+    }
+    list
+}
+
+/// A complete block, markers included, for inserting into `markdown`
+pub fn toc_block(markdown: &str, max_depth: u8) -> String {
+    MARKERS.block(&toc_list(markdown, max_depth))
+}
+
+/// Byte range between the start marker's line and the end marker
+pub fn find_toc(markdown: &str) -> Option<Range<usize>> {
+    MARKERS.find(markdown)
+}
+
+/// The edit that regenerates an existing block, or `None` if there is no
+/// block or it is already current
+pub fn update_toc(markdown: &str, max_depth: u8) -> Option<(Range<usize>, String)> {
+    MARKERS.update(markdown, || toc_list(markdown, max_depth))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(markdown: &str, edit: Option<(Range<usize>, String)>) -> String {
+        let mut text = markdown.to_string();
+        if let Some((range, replacement)) = edit {
+            text.replace_range(range, &replacement);
+        }
+        text
+    }
+
+    #[test]
+    fn test_toc_block_nests_headings() {
+        let doc = include_str!("fixtures/toc_input.md");
+        let block = toc_block(doc, 3);
+        assert_eq!(block, include_str!("fixtures/toc_block.md"));
+        assert!(!block.contains("Too deep"));
+    }
+
+    #[test]
+    fn test_toc_without_headings() {
+        assert_eq!(
+            toc_block("Just text.\n", 3),
+            "<!-- toc -->\n<!-- no headings -->\n<!-- tocstop -->\n"
+        );
+    }
+
+    #[test]
+    fn test_update_toc_in_place() {
+        let doc = include_str!("fixtures/toc_stale.md");
+        let updated = apply(doc, update_toc(doc, 3));
+        assert_eq!(updated, include_str!("fixtures/toc_updated.md"));
+        assert!(update_toc(&updated, 3).is_none());
+        assert!(update_toc("# No markers\n", 3).is_none());
+    }
+}
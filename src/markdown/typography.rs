@@ -0,0 +1,240 @@
+//! Smart punctuation applied to the Markdown source itself
+
+use super::fence_marker;
+
+/// Replace straight quotes with curly ones, `--`/`---` with en/em dashes,
+/// and `...` with an ellipsis. Code blocks, code spans, URLs, link
+/// destinations, HTML, front matter, and rule/table-delimiter lines are
+/// left exactly as they are.
+pub fn smarten(markdown: &str) -> String {
+    let mut output = String::with_capacity(markdown.len());
+    let mut fence: Option<(char, usize)> = None;
+    let mut in_front_matter = false;
+    let mut in_indented_code = false;
+    let mut previous_blank = true;
+    // Inside an HTML comment that started on an earlier line
+    let mut in_comment = false;
+
+    for (index, line) in markdown.split_inclusive('\n').enumerate() {
+        let trimmed = line.trim();
+        let blank = trimmed.is_empty();
+
+        if index == 0 && trimmed == "---" {
+            in_front_matter = true;
+            output.push_str(line);
+            continue;
+        }
+        if in_front_matter {
+            in_front_matter = !(trimmed == "---" || trimmed == "...");
+            output.push_str(line);
+            continue;
+        }
+
+        if in_comment {
+            let Some(end) = line.find("-->") else {
+                output.push_str(line);
+                continue;
+            };
+            output.push_str(&line[..end + 3]);
+            in_comment = smarten_line(&line[end + 3..], &mut output);
+            continue;
+        }
+
+        if let Some((marker, len)) = fence_marker(line) {
+            match fence {
+                None => fence = Some((marker, len)),
+                Some((open, open_len)) if open == marker && len >= open_len => fence = None,
+                Some(_) => {}
+            }
+            output.push_str(line);
+            continue;
+        }
+
+        let indented = line.starts_with("    ") || line.starts_with('\t');
+        if !blank {
+            in_indented_code = indented && (previous_blank || in_indented_code);
+        }
+        previous_blank = blank;
+
+        if fence.is_some() || in_indented_code || is_structural(trimmed) {
+            output.push_str(line);
+        } else {
+            in_comment = smarten_line(line, &mut output);
+        }
+    }
+
+    output
+}
+
+/// Lines made only of rule, setext underline, or table delimiter characters
+fn is_structural(line: &str) -> bool {
+    !line.is_empty() && line.chars().all(|c| matches!(c, '-' | '=' | '*' | '_' | '|' | ':' | ' ' | '\t'))
+}
+
+/// Smarten one line into `output`, returning whether it ends inside an
+/// HTML comment that goes on to the next
+fn smarten_line(line: &str, output: &mut String) -> bool {
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+
+    // Copy `chars[from..to]` unchanged
+    let copy = |output: &mut String, from: usize, to: usize| output.extend(&chars[from..to.min(chars.len())]);
+
+    while i < chars.len() {
+        let c = chars[i];
+        let previous = i.checked_sub(1).map(|p| chars[p]);
+        match c {
+            '\\' => {
+                copy(output, i, i + 2);
+                i += 2;
+            }
+            '`' => {
+                let run = chars[i..].iter().take_while(|&&ch| ch == '`').count();
+                let end = find_run(&chars, i + run, '`', run).map_or(i + run, |close| close + run);
+                copy(output, i, end);
+                i = end;
+            }
+            '<' if chars[i..].starts_with(&['<', '!', '-', '-']) => {
+                let close = (i + 4..chars.len()).find(|&p| chars[p..].starts_with(&['-', '-', '>']));
+                let Some(close) = close else {
+                    copy(output, i, chars.len());
+                    return true;
+                };
+                copy(output, i, close + 3);
+                i = close + 3;
+            }
+            '<' => {
+                // Autolinks and inline HTML
+                let end = chars[i..].iter().position(|&ch| ch == '>').map_or(i + 1, |p| i + p + 1);
+                copy(output, i, end);
+                i = end;
+            }
+            ']' if chars.get(i + 1) == Some(&'(') => {
+                let end = closing_paren(&chars, i + 2).map_or(i + 2, |p| p + 1);
+                copy(output, i, end);
+                i = end;
+            }
+            _ if starts_url(&chars[i..]) && !previous.is_some_and(char::is_alphanumeric) => {
+                let end = chars[i..].iter().position(|ch| ch.is_whitespace()).map_or(chars.len(), |p| i + p);
+                copy(output, i, end);
+                i = end;
+            }
+            '.' if chars.get(i + 1) == Some(&'.') && chars.get(i + 2) == Some(&'.') => {
+                output.push('…');
+                i += 3;
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                if chars.get(i + 2) == Some(&'-') {
+                    output.push('—');
+                    i += 3;
+                } else {
+                    output.push('–');
+                    i += 2;
+                }
+            }
+            '"' => {
+                output.push(if opens_quote(previous) { '“' } else { '”' });
+                i += 1;
+            }
+            '\'' => {
+                output.push(if opens_quote(previous) { '‘' } else { '’' });
+                i += 1;
+            }
+            _ => {
+                output.push(c);
+                i += 1;
+            }
+        }
+    }
+    false
+}
+
+/// A quote opens when it starts a word rather than ending one
+fn opens_quote(previous: Option<char>) -> bool {
+    previous.is_none_or(|c| {
+        c.is_whitespace() || matches!(c, '(' | '[' | '{' | '-' | '–' | '—' | '/' | '“' | '‘')
+    })
+}
+
+fn starts_url(chars: &[char]) -> bool {
+    let prefix: String = chars.iter().take(8).collect();
+    prefix.starts_with("http://") || prefix.starts_with("https://") || prefix.starts_with("www.")
+}
+
+/// Index of the next run of exactly `len` `c` characters at or after `from`
+fn find_run(chars: &[char], from: usize, c: char, len: usize) -> Option<usize> {
+    let mut i = from;
+    while i < chars.len() {
+        if chars[i] == c {
+            let run = chars[i..].iter().take_while(|&&ch| ch == c).count();
+            if run == len {
+                return Some(i);
+            }
+            i += run;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Index of the `)` closing a link destination that starts at `from`
+fn closing_paren(chars: &[char], from: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, &c) in chars.iter().enumerate().skip(from) {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smarten_prose() {
+        assert_eq!(
+            smarten("\"Quote\" and 'single' -- it's 1990--2000 --- wait..."),
+            "“Quote” and ‘single’ – it’s 1990–2000 — wait…"
+        );
+        assert_eq!(smarten("(\"nested 'inner'\")"), "(“nested ‘inner’”)");
+    }
+
+    #[test]
+    fn test_smarten_skips_code_and_urls() {
+        let cases = [
+            "Run `let s = \"x\"; a--` now",
+            "See [it's](https://x.y/a--b?q=\"1\") and <https://x.y/--> or https://x.y/a...b",
+            "<span title=\"x\">it</span>",
+            "a \\\"literal\\\" quote",
+        ];
+        let expected = [
+            "Run `let s = \"x\"; a--` now",
+            "See [it’s](https://x.y/a--b?q=\"1\") and <https://x.y/--> or https://x.y/a...b",
+            "<span title=\"x\">it</span>",
+            "a \\\"literal\\\" quote",
+        ];
+        for (input, output) in cases.iter().zip(expected) {
+            assert_eq!(smarten(input), output, "input: {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_smarten_skips_code_blocks_and_structure() {
+        let doc = "---\ntitle: \"Doc\"\n---\n\n\"a\"\n\n```sh\necho \"--x\"\n```\n\n    indented \"code\"\n\n| a | b |\n| --- | --- |\n\n---\n";
+        let expected = "---\ntitle: \"Doc\"\n---\n\n“a”\n\n```sh\necho \"--x\"\n```\n\n    indented \"code\"\n\n| a | b |\n| --- | --- |\n\n---\n";
+        assert_eq!(smarten(doc), expected);
+    }
+
+    #[test]
+    fn test_smarten_skips_comments() {
+        let doc = "\"a\" <!-- it's -- a\nnote -- \"here\"\n--> and \"b\"\n\n<!-- one -- line --> 'c'\n";
+        let expected = "“a” <!-- it's -- a\nnote -- \"here\"\n--> and “b”\n\n<!-- one -- line --> ‘c’\n";
+        assert_eq!(smarten(doc), expected);
+    }
+}
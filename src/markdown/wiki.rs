@@ -0,0 +1,192 @@
+//! Wiki-style links: `[[Note Name]]` and `[[Note Name|display text]]`
+
+use super::fence_marker;
+use std::ops::Range;
+
+/// URL scheme given to wiki links when they are rewritten as regular links
+pub const WIKI_SCHEME: &str = "wiki:";
+
+/// A wiki link found in the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct WikiLink {
+    /// Note name, possibly with a `#heading` suffix
+    pub target: String,
+    /// Text to display: the alias after `|`, or the target
+    pub label: String,
+    /// Byte range of the whole `[[...]]` in the source
+    pub range: Range<usize>,
+}
+
+impl WikiLink {
+    /// Note name without any `#heading` suffix
+    pub fn note(&self) -> &str {
+        self.target.split('#').next().unwrap_or_default().trim()
+    }
+}
+
+/// Find wiki links outside of code spans and fenced code blocks
+pub fn find_wiki_links(markdown: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+    let mut fence: Option<(char, usize)> = None;
+    let mut offset = 0;
+
+    for line in markdown.split_inclusive('\n') {
+        let line_start = offset;
+        offset += line.len();
+
+        if let Some((marker, len)) = fence_marker(line) {
+            match fence {
+                None => fence = Some((marker, len)),
+                Some((open, open_len)) if open == marker && len >= open_len => fence = None,
+                Some(_) => {}
+            }
+            continue;
+        }
+        if fence.is_none() {
+            scan_line(line, line_start, &mut links);
+        }
+    }
+
+    links
+}
+
+/// Rewrite wiki links as `[label](<wiki:target>)` so the Markdown parser
+/// sees them as ordinary links
+pub fn expand_wiki_links(markdown: &str) -> String {
+    expand_wiki_links_mapped(markdown).0
+}
+
+/// Where `expand_wiki_links` replaced text, to map offsets in its output
+/// back to the original
+#[derive(Debug, Default)]
+pub struct ExpansionMap {
+    /// Range in the output and in the original of each rewritten link
+    replacements: Vec<(Range<usize>, Range<usize>)>,
+}
+
+impl ExpansionMap {
+    /// Offset in the original text of `offset` in the expanded text. An
+    /// offset inside a rewritten link maps to the start of the wiki link,
+    /// or its end when it is the end of the rewritten link.
+    pub fn original(&self, offset: usize) -> usize {
+        let mut shift = 0isize;
+        for (expanded, original) in &self.replacements {
+            if offset < expanded.start {
+                break;
+            }
+            if offset < expanded.end {
+                return original.start;
+            }
+            shift = original.end as isize - expanded.end as isize;
+            if offset == expanded.end {
+                return original.end;
+            }
+        }
+        (offset as isize + shift) as usize
+    }
+}
+
+/// `expand_wiki_links`, with the map back to the original offsets
+pub fn expand_wiki_links_mapped(markdown: &str) -> (String, ExpansionMap) {
+    let mut output = String::with_capacity(markdown.len());
+    let mut map = ExpansionMap::default();
+    let mut last = 0;
+    for link in find_wiki_links(markdown) {
+        output.push_str(&markdown[last..link.range.start]);
+        let start = output.len();
+        output.push_str(&format!("[{}](<{}{}>)", link.label, WIKI_SCHEME, link.target));
+        map.replacements.push((start..output.len(), link.range.clone()));
+        last = link.range.end;
+    }
+    output.push_str(&markdown[last..]);
+    (output, map)
+}
+
+fn scan_line(line: &str, line_start: usize, links: &mut Vec<WikiLink>) {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'`' => {
+                // Skip the whole code span, or just the backticks if unclosed
+                let run = bytes[i..].iter().take_while(|&&b| b == b'`').count();
+                let ticks = &line[i..i + run];
+                i = match line[i + run..].find(ticks) {
+                    Some(close) => i + run + close + run,
+                    None => i + run,
+                };
+            }
+            b'[' if bytes.get(i + 1) == Some(&b'[') && (i == 0 || bytes[i - 1] != b'!') => {
+                let inner_start = i + 2;
+                let Some(close) = line[inner_start..].find("]]") else {
+                    break;
+                };
+                let inner = &line[inner_start..inner_start + close];
+                let end = inner_start + close + 2;
+                if inner.trim().is_empty() || inner.contains(['[', ']', '<', '>', '\n']) {
+                    i += 1;
+                    continue;
+                }
+
+                let (target, label) = match inner.split_once('|') {
+                    Some((target, label)) => (target.trim(), label.trim()),
+                    None => (inner.trim(), inner.trim()),
+                };
+                links.push(WikiLink {
+                    target: target.to_string(),
+                    label: if label.is_empty() { target } else { label }.to_string(),
+                    range: line_start + i..line_start + end,
+                });
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_wiki_links() {
+        let text = "See [[Note Name]] and [[Other#Setup|the setup]].\n";
+        let links = find_wiki_links(text);
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].target, "Note Name");
+        assert_eq!(links[0].label, "Note Name");
+        assert_eq!(&text[links[0].range.clone()], "[[Note Name]]");
+        assert_eq!(links[1].note(), "Other");
+        assert_eq!(links[1].label, "the setup");
+    }
+
+    #[test]
+    fn test_wiki_links_skip_code_and_embeds() {
+        let text = "`[[code]]` ![[embed]] [[]]\n```\n[[fenced]]\n```\n[[after]]";
+        let targets: Vec<_> = find_wiki_links(text).into_iter().map(|l| l.target).collect();
+        assert_eq!(targets, vec!["after"]);
+    }
+
+    #[test]
+    fn test_expand_wiki_links() {
+        assert_eq!(
+            expand_wiki_links("a [[Note Name|alias]] b"),
+            "a [alias](<wiki:Note Name>) b"
+        );
+        assert_eq!(expand_wiki_links("no links"), "no links");
+    }
+
+    #[test]
+    fn test_expansion_map() {
+        let text = "a [[Note]] b [[X|y]] c";
+        let (expanded, map) = expand_wiki_links_mapped(text);
+        assert_eq!(expanded, "a [Note](<wiki:Note>) b [y](<wiki:X>) c");
+        assert_eq!(map.original(0), 0);
+        assert_eq!(map.original(expanded.find("Note").unwrap()), 2);
+        assert_eq!(map.original(expanded.find(" b").unwrap()), text.find(" b").unwrap());
+        assert_eq!(map.original(expanded.find(" c").unwrap()), text.find(" c").unwrap());
+        assert_eq!(map.original(expanded.len()), text.len());
+    }
+}
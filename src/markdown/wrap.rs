@@ -0,0 +1,303 @@
+//! Hard-wrapping paragraphs at a column, and joining them back into one line
+
+use std::ops::Range;
+
+/// A run of lines that reflows as one unit
+struct Paragraph {
+    /// Prefix of the first line: quote markers, indentation, list marker
+    first_prefix: String,
+    /// Prefix of the following lines, aligned under the item's text
+    rest_prefix: String,
+    words: Vec<String>,
+}
+
+impl Paragraph {
+    fn render(&self, column: Option<usize>, out: &mut Vec<String>) {
+        let mut line = self.first_prefix.clone();
+        let mut line_has_words = false;
+        for word in &self.words {
+            let fits = column.is_none_or(|column| line.chars().count() + 1 + word.chars().count() <= column);
+            if line_has_words && !fits {
+                out.push(std::mem::replace(&mut line, self.rest_prefix.clone()));
+                line_has_words = false;
+            }
+            if line_has_words {
+                line.push(' ');
+            }
+            line.push_str(word);
+            line_has_words = true;
+        }
+        out.push(line);
+    }
+}
+
+/// Re-wrap the paragraphs on `lines` (zero-based) of `markdown` so no line
+/// is longer than `column`, or join each onto a single line when `column`
+/// is `None`. List items keep a hanging indent and quotes keep their `>`.
+/// Code, tables, headings, HTML and front matter are left as they are, and
+/// a word longer than the column gets a line to itself. Returns the new
+/// text for those lines.
+pub fn reflow(markdown: &str, lines: Range<usize>, column: Option<usize>) -> String {
+    let all: Vec<&str> = markdown.split('\n').collect();
+    let verbatim = verbatim_lines(&all);
+    let end = lines.end.min(all.len());
+    let mut out = Vec::new();
+    let mut paragraph: Option<Paragraph> = None;
+
+    for index in lines.start.min(end)..end {
+        let line = all[index];
+        let (quote, content) = split_quote(line);
+        let standalone = verbatim[index] || is_standalone(content);
+        if standalone {
+            if let Some(paragraph) = paragraph.take() {
+                paragraph.render(column, &mut out);
+            }
+            out.push(line.to_string());
+            continue;
+        }
+
+        let indent = &content[..content.len() - content.trim_start().len()];
+        let marker = list_marker(content.trim_start());
+        let continues = paragraph.as_ref().is_some_and(|p| {
+            marker.is_none() && p.rest_prefix.starts_with(quote) && quote.len() == quote_len(&p.rest_prefix)
+        });
+        if !continues {
+            if let Some(paragraph) = paragraph.take() {
+                paragraph.render(column, &mut out);
+            }
+            let marker = marker.unwrap_or("");
+            let first_prefix = format!("{}{}{}", quote, indent, marker);
+            let rest_prefix = format!("{}{}{}", quote, indent, " ".repeat(marker.chars().count()));
+            paragraph = Some(Paragraph {
+                first_prefix,
+                rest_prefix,
+                words: Vec::new(),
+            });
+        }
+
+        let Some(current) = paragraph.as_mut() else {
+            continue;
+        };
+        let text = &content.trim_start()[marker.map_or(0, str::len)..];
+        current.words.extend(text.split_whitespace().map(str::to_string));
+        // A hard line break ends the paragraph at this line
+        if line.ends_with("  ") {
+            if let Some(last) = current.words.last_mut() {
+                last.push_str("  ");
+            }
+        }
+        if line.ends_with("  ") || line.ends_with('\\') {
+            if let Some(finished) = paragraph.take() {
+                finished.render(column, &mut out);
+            }
+        }
+    }
+    if let Some(paragraph) = paragraph {
+        paragraph.render(column, &mut out);
+    }
+    out.join("\n")
+}
+
+/// Lines of front matter, fenced code, and tables
+fn verbatim_lines(lines: &[&str]) -> Vec<bool> {
+    let mut verbatim = vec![false; lines.len()];
+    let mut index = 0;
+
+    if lines.first().is_some_and(|line| line.trim_end() == "---") {
+        if let Some(close) = lines[1..].iter().position(|line| matches!(line.trim_end(), "---" | "...")) {
+            verbatim[..close + 2].fill(true);
+            index = close + 2;
+        }
+    }
+
+    let mut fence: Option<(char, usize)> = None;
+    while index < lines.len() {
+        let (_, content) = split_quote(lines[index]);
+        let trimmed = content.trim_start();
+        let run = |c: char| trimmed.chars().take_while(|&t| t == c).count();
+        match fence {
+            Some((c, len)) => {
+                verbatim[index] = true;
+                if run(c) >= len && trimmed.trim_end().chars().all(|t| t == c) {
+                    fence = None;
+                }
+            }
+            None => {
+                for c in ['`', '~'] {
+                    if run(c) >= 3 {
+                        fence = Some((c, run(c)));
+                        verbatim[index] = true;
+                    }
+                }
+                // Indented code, unless it continues a paragraph or list
+                let previous_blank = index == 0 || lines[index - 1].trim().is_empty();
+                if (content.starts_with("    ") || content.starts_with('\t')) && previous_blank {
+                    let in_list = lines[..index]
+                        .iter()
+                        .rev()
+                        .find(|line| !line.trim().is_empty())
+                        .is_some_and(|line| list_marker(split_quote(line).1.trim_start()).is_some() || line.starts_with(' '));
+                    verbatim[index] = !in_list;
+                }
+            }
+        }
+        index += 1;
+    }
+
+    // Tables: a delimiter row and the pipe rows around it
+    for index in 0..lines.len() {
+        if is_table_delimiter(lines[index]) {
+            verbatim[index] = true;
+            let is_row = |line: &&str| line.contains('|') && !line.trim().is_empty();
+            for before in (0..index).rev().take_while(|&i| is_row(&lines[i])) {
+                verbatim[before] = true;
+            }
+            for after in (index + 1..lines.len()).take_while(|&i| is_row(&lines[i])) {
+                verbatim[after] = true;
+            }
+        }
+    }
+    verbatim
+}
+
+fn is_table_delimiter(line: &str) -> bool {
+    let line = line.trim();
+    line.contains('-')
+        && line.contains('|')
+        && line.chars().all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+/// Lines that never join with their neighbours
+fn is_standalone(content: &str) -> bool {
+    let trimmed = content.trim();
+    trimmed.is_empty()
+        || trimmed.starts_with('#')
+        || trimmed.starts_with('<')
+        || trimmed.starts_with('|')
+        || is_thematic_break(trimmed)
+        || (trimmed.starts_with('[') && trimmed.contains("]:"))
+}
+
+fn is_thematic_break(line: &str) -> bool {
+    ['-', '*', '_'].iter().any(|&c| {
+        let marks = line.chars().filter(|&t| t == c).count();
+        marks >= 3 && line.chars().all(|t| t == c || t == ' ')
+    })
+}
+
+/// Split off leading `>` quote markers, including the space after each
+fn split_quote(line: &str) -> (&str, &str) {
+    let len = quote_len(line);
+    line.split_at(len)
+}
+
+fn quote_len(line: &str) -> usize {
+    let mut len = 0;
+    let mut rest = line;
+    loop {
+        let trimmed = rest.trim_start_matches(' ');
+        let Some(after) = trimmed.strip_prefix('>') else {
+            return len;
+        };
+        let after = after.strip_prefix(' ').unwrap_or(after);
+        len += rest.len() - after.len();
+        rest = after;
+    }
+}
+
+/// List marker at the start of `text`, with its trailing space and any
+/// task box, e.g. `- `, `12. ` or `* [x] `
+fn list_marker(text: &str) -> Option<&str> {
+    let marker_len = if text.starts_with(['-', '*', '+']) {
+        1
+    } else {
+        let digits = text.chars().take_while(char::is_ascii_digit).count();
+        if !(1..=9).contains(&digits) || !text[digits..].starts_with(['.', ')']) {
+            return None;
+        }
+        digits + 1
+    };
+    let rest = &text[marker_len..];
+    if !rest.starts_with(' ') {
+        return None;
+    }
+    let mut len = marker_len + 1;
+    for task in ["[ ] ", "[x] ", "[X] "] {
+        if text[len..].starts_with(task) {
+            len += task.len();
+        }
+    }
+    Some(&text[..len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wrap(markdown: &str, column: usize) -> String {
+        let count = markdown.split('\n').count();
+        reflow(markdown, 0..count, Some(column))
+    }
+
+    fn unwrap(markdown: &str) -> String {
+        let count = markdown.split('\n').count();
+        reflow(markdown, 0..count, None)
+    }
+
+    #[test]
+    fn test_wrap_paragraph() {
+        assert_eq!(
+            wrap("The quick brown fox jumps over the lazy dog.\n", 20),
+            "The quick brown fox\njumps over the lazy\ndog.\n"
+        );
+        assert_eq!(unwrap("The quick brown fox\njumps over the lazy\ndog.\n"), "The quick brown fox jumps over the lazy dog.\n");
+    }
+
+    #[test]
+    fn test_wrap_list_items_with_hanging_indent() {
+        let markdown = "- first item that is rather long\n- [ ] task item that is also long\n  continued here\n10. numbered item goes on and on";
+        assert_eq!(
+            wrap(markdown, 20),
+            "- first item that is\n  rather long\n- [ ] task item that\n      is also long\n      continued here\n10. numbered item\n    goes on and on"
+        );
+        assert_eq!(
+            unwrap(&wrap(markdown, 20)),
+            "- first item that is rather long\n- [ ] task item that is also long continued here\n10. numbered item goes on and on"
+        );
+    }
+
+    #[test]
+    fn test_wrap_quotes() {
+        assert_eq!(
+            wrap("> A quoted line that needs wrapping\n> > nested quote text here", 16),
+            "> A quoted line\n> that needs\n> wrapping\n> > nested quote\n> > text here"
+        );
+    }
+
+    #[test]
+    fn test_long_urls_stay_whole() {
+        assert_eq!(
+            wrap("See https://example.com/a/very/long/path/that/cannot/break for details", 20),
+            "See\nhttps://example.com/a/very/long/path/that/cannot/break\nfor details"
+        );
+    }
+
+    #[test]
+    fn test_code_tables_and_front_matter_untouched() {
+        let markdown = "---\ntitle: a long title that should stay\n---\n\n```\nlong code line that must not wrap\n```\n\n| a | b |\n|---|---|\n| long cell text | more |\n\n    indented code line stays as is\n\n# A heading that is long stays\n";
+        assert_eq!(wrap(markdown, 10), markdown);
+    }
+
+    #[test]
+    fn test_reflow_only_the_given_lines() {
+        let markdown = "```\ncode\n```\none two three four\nfive six";
+        assert_eq!(reflow(markdown, 3..5, Some(9)), "one two\nthree\nfour five\nsix");
+        // A selection inside a fence started above it stays code
+        assert_eq!(reflow(markdown, 1..2, Some(2)), "code");
+    }
+
+    #[test]
+    fn test_hard_breaks_are_kept() {
+        assert_eq!(unwrap("first line  \nsecond\nthird\\\nfourth"), "first line  \nsecond third\\\nfourth");
+    }
+}
@@ -0,0 +1,202 @@
+//! History of what was copied or cut in the app, most recent first, for
+//! pasting something other than the last copy. Pinned entries stay when
+//! newer copies push the others out. Kept in memory unless the user asks
+//! for it to be kept between sessions.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Entries kept besides pinned ones
+pub const MAX_ENTRIES: usize = 20;
+
+/// Lines of an entry shown in the list
+const PREVIEW_LINES: usize = 3;
+
+/// Characters of a line shown in the list
+const PREVIEW_CHARS: usize = 80;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClipEntry {
+    pub text: String,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// Layout of `clipboard.toml`
+#[derive(Default, Serialize, Deserialize)]
+struct HistoryFile {
+    #[serde(default, rename = "entry")]
+    entries: Vec<ClipEntry>,
+}
+
+#[derive(Default)]
+pub struct ClipboardHistory {
+    entries: Vec<ClipEntry>,
+    path: Option<PathBuf>,
+    /// Whether the history is written to `path`
+    persist: bool,
+}
+
+impl ClipboardHistory {
+    /// The history kept in `path` the last time it was kept, or none
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let entries = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| toml::from_str::<HistoryFile>(&contents).ok())
+            .map(|file| file.entries)
+            .unwrap_or_default();
+        let persist = path.as_ref().is_some_and(|path| path.exists());
+        Self { entries, path, persist }
+    }
+
+    /// The history in `config_dir/rmd/clipboard.toml`
+    pub fn load_default() -> Self {
+        Self::load(crate::config::Config::config_dir().ok().map(|dir| dir.join("clipboard.toml")))
+    }
+
+    /// Most recent first
+    pub fn entries(&self) -> &[ClipEntry] {
+        &self.entries
+    }
+
+    /// Put `text` at the top, moving it there if it is already in the
+    /// history, and drop the oldest unpinned entries past the limit
+    pub fn record(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let pinned = match self.entries.iter().position(|entry| entry.text == text) {
+            Some(index) => self.entries.remove(index).pinned,
+            None => false,
+        };
+        self.entries.insert(0, ClipEntry { text: text.to_string(), pinned });
+        let mut unpinned = 0;
+        self.entries.retain(|entry| {
+            unpinned += usize::from(!entry.pinned);
+            entry.pinned || unpinned <= MAX_ENTRIES
+        });
+        self.save();
+    }
+
+    pub fn toggle_pin(&mut self, index: usize) {
+        if let Some(entry) = self.entries.get_mut(index) {
+            entry.pinned = !entry.pinned;
+            self.save();
+        }
+    }
+
+    /// Forget every entry that isn't pinned
+    pub fn clear_unpinned(&mut self) {
+        self.entries.retain(|entry| entry.pinned);
+        self.save();
+    }
+
+    /// Keep the history between sessions or stop keeping it, deleting the
+    /// file it was kept in
+    pub fn set_persist(&mut self, persist: bool) {
+        if persist == self.persist {
+            return;
+        }
+        self.persist = persist;
+        if persist {
+            self.save();
+        } else if let Some(path) = &self.path {
+            if let Err(e) = std::fs::remove_file(path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    log::warn!("Failed to delete {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+
+    fn save(&self) {
+        let Some(path) = self.path.as_deref().filter(|_| self.persist) else {
+            return;
+        };
+        if let Err(e) = write_file(path, &self.entries) {
+            log::warn!("Failed to save the clipboard history: {}", e);
+        }
+    }
+}
+
+fn write_file(path: &Path, entries: &[ClipEntry]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = HistoryFile { entries: entries.to_vec() };
+    std::fs::write(path, toml::to_string(&file)?)?;
+    Ok(())
+}
+
+/// The first lines of `text`, each cut short, for the list. What is
+/// pasted is the whole text.
+pub fn preview(text: &str) -> String {
+    let mut lines: Vec<String> = text
+        .lines()
+        .take(PREVIEW_LINES)
+        .map(|line| {
+            let line = line.trim_end();
+            match line.char_indices().nth(PREVIEW_CHARS) {
+                Some((end, _)) => format!("{}…", &line[..end]),
+                None => line.to_string(),
+            }
+        })
+        .collect();
+    if text.lines().nth(PREVIEW_LINES).is_some() {
+        lines.push("…".to_string());
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_keeps_pinned_entries() {
+        let mut history = ClipboardHistory::default();
+        history.record("first");
+        history.toggle_pin(0);
+        for i in 0..MAX_ENTRIES + 5 {
+            history.record(&format!("copy {}", i));
+        }
+        assert_eq!(history.entries().len(), MAX_ENTRIES + 1);
+        assert_eq!(history.entries()[0].text, format!("copy {}", MAX_ENTRIES + 4));
+        assert!(history.entries().iter().any(|entry| entry.text == "first" && entry.pinned));
+
+        // Copying something again moves it to the top, pin and all
+        history.record("first");
+        assert_eq!(history.entries()[0], ClipEntry { text: "first".to_string(), pinned: true });
+        assert_eq!(history.entries().len(), MAX_ENTRIES + 1);
+
+        history.clear_unpinned();
+        assert_eq!(history.entries().len(), 1);
+    }
+
+    #[test]
+    fn test_persist() {
+        let dir = std::env::temp_dir().join(format!("rmd-clipboard-{}", std::process::id()));
+        let path = dir.join("clipboard.toml");
+        let mut history = ClipboardHistory::load(Some(path.clone()));
+        history.record("kept in memory");
+        assert!(!path.exists());
+
+        history.set_persist(true);
+        history.record("line one\nline two");
+        let loaded = ClipboardHistory::load(Some(path.clone()));
+        assert_eq!(loaded.entries(), history.entries());
+
+        history.set_persist(false);
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview("one\ntwo"), "one\ntwo");
+        assert_eq!(preview("a\nb\nc\nd"), "a\nb\nc\n…");
+        let long = "x".repeat(PREVIEW_CHARS + 10);
+        assert_eq!(preview(&long), format!("{}…", "x".repeat(PREVIEW_CHARS)));
+    }
+}
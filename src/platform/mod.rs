@@ -0,0 +1,63 @@
+//! Integration with the operating system shell
+
+pub mod clipboard;
+
+use clipboard::ClipboardHistory;
+use eframe::egui;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[cfg(windows)]
+mod win32;
+
+/// Tell the shell a document was opened or saved, and show `recent`
+/// (most recent first) in the taskbar jump list. Does nothing on other
+/// platforms; failures are logged and otherwise ignored.
+pub fn note_recent_document(path: &Path, recent: &[PathBuf]) {
+    #[cfg(windows)]
+    if let Err(e) = win32::note_recent_document(path, recent) {
+        log::warn!("Failed to update recent documents: {}", e);
+    }
+    #[cfg(not(windows))]
+    let _ = (path, recent);
+}
+
+/// Kept open, since on Linux what was copied goes away with the clipboard
+/// handle that copied it
+static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+/// Put `text` on the clipboard, with `html` as the rich version for
+/// programs that paste formatting. Falls back to the text alone. Copies
+/// through egui reach `history` at the end of the frame; this one is added
+/// to it here.
+pub fn copy_with_html(ctx: &egui::Context, history: &mut ClipboardHistory, text: String, html: Option<String>) {
+    if let Some(html) = html {
+        let mut clipboard = CLIPBOARD.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if clipboard.is_none() {
+            *clipboard = arboard::Clipboard::new().map_err(|e| log::warn!("Failed to open the clipboard: {}", e)).ok();
+        }
+        match clipboard.as_mut().map(|clipboard| clipboard.set_html(&html, Some(&text))) {
+            Some(Ok(())) => {
+                history.record(&text);
+                return;
+            }
+            Some(Err(e)) => log::warn!("Failed to copy HTML: {}", e),
+            None => {}
+        }
+    }
+    ctx.copy_text(text);
+}
+
+/// Open the system file manager at the folder containing `path`, with the
+/// file selected where the file manager supports it
+pub fn show_in_folder(path: &Path) {
+    #[cfg(windows)]
+    let result = std::process::Command::new("explorer").arg(format!("/select,{}", path.display())).spawn();
+    #[cfg(target_os = "macos")]
+    let result = std::process::Command::new("open").arg("-R").arg(path).spawn();
+    #[cfg(not(any(windows, target_os = "macos")))]
+    let result = std::process::Command::new("xdg-open").arg(path.parent().unwrap_or(path)).spawn();
+    if let Err(e) = result {
+        log::warn!("Failed to show {} in its folder: {}", path.display(), e);
+    }
+}
@@ -0,0 +1,74 @@
+use std::path::{Path, PathBuf};
+use windows::core::{Interface, Result, HSTRING, PROPVARIANT};
+use windows::Win32::Storage::EnhancedStorage::PKEY_Title;
+use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED};
+use windows::Win32::UI::Shell::Common::{IObjectArray, IObjectCollection};
+use windows::Win32::UI::Shell::PropertiesSystem::IPropertyStore;
+use windows::Win32::UI::Shell::{
+    DestinationList, EnumerableObjectCollection, ICustomDestinationList, IShellLinkW, SHAddToRecentDocs, ShellLink,
+    SHARD_PATHW,
+};
+
+/// Jump list category holding the recent files
+const CATEGORY: &str = "Recent Files";
+
+pub fn note_recent_document(path: &Path, recent: &[PathBuf]) -> Result<()> {
+    let path = HSTRING::from(path.as_os_str());
+    unsafe {
+        // The windowing backend has normally initialized COM on this thread already
+        let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+        SHAddToRecentDocs(SHARD_PATHW.0 as u32, Some(path.as_ptr().cast()));
+        update_jump_list(recent)
+    }
+}
+
+/// Replace the jump list's category with links that run `rmd "<path>"`
+unsafe fn update_jump_list(recent: &[PathBuf]) -> Result<()> {
+    let Ok(exe) = std::env::current_exe() else {
+        return Ok(());
+    };
+    let exe = HSTRING::from(exe.as_os_str());
+
+    let list: ICustomDestinationList = CoCreateInstance(&DestinationList, None, CLSCTX_INPROC_SERVER)?;
+    let mut max_slots = 0;
+    let removed: IObjectArray = list.BeginList(&mut max_slots)?;
+    // Entries the user removed from the jump list must not be added back
+    let removed = removed_arguments(&removed)?;
+
+    let items: IObjectCollection = CoCreateInstance(&EnumerableObjectCollection, None, CLSCTX_INPROC_SERVER)?;
+    let entries = recent
+        .iter()
+        .map(|path| (path, format!("\"{}\"", path.display())))
+        .filter(|(_, arguments)| !removed.contains(arguments))
+        .take(max_slots as usize);
+    for (path, arguments) in entries {
+        let link: IShellLinkW = CoCreateInstance(&ShellLink, None, CLSCTX_INPROC_SERVER)?;
+        link.SetPath(&exe)?;
+        link.SetArguments(&HSTRING::from(arguments))?;
+        link.SetDescription(&HSTRING::from(path.as_os_str()))?;
+        link.SetIconLocation(&exe, 0)?;
+
+        let title = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+        let store: IPropertyStore = link.cast()?;
+        store.SetValue(&PKEY_Title, &PROPVARIANT::from(&*title))?;
+        store.Commit()?;
+        items.AddObject(&link)?;
+    }
+
+    list.AppendCategory(&HSTRING::from(CATEGORY), &items.cast::<IObjectArray>()?)?;
+    list.CommitList()
+}
+
+unsafe fn removed_arguments(removed: &IObjectArray) -> Result<Vec<String>> {
+    let mut arguments = Vec::new();
+    for index in 0..removed.GetCount()? {
+        let Ok(link) = removed.GetAt::<IShellLinkW>(index) else {
+            continue;
+        };
+        let mut buffer = [0u16; 1024];
+        link.GetArguments(&mut buffer)?;
+        let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+        arguments.push(String::from_utf16_lossy(&buffer[..len]));
+    }
+    Ok(arguments)
+}
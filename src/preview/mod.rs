@@ -1,4 +1,42 @@
-use crate::markdown::RenderedElement;
+pub mod pages;
+pub mod pinned;
+pub mod selection;
+
+use crate::markdown::export::{self, HtmlOptions, Stylesheet};
+use crate::markdown::{figures, include};
+use crate::markdown::incremental::IncrementalRender;
+use crate::markdown::{ElementKind, MarkdownRenderer, RenderedElement};
+use crate::utils::timing::{self, Cache, Phase};
+use pinned::PinnedFile;
+use selection::BlockSelection;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How long the caret's element stays tinted after the caret moves to it
+const FLASH_DURATION: Duration = Duration::from_millis(700);
+
+/// How long after the user scrolls the preview the caret leaves it alone
+const MANUAL_SCROLL_GRACE: Duration = Duration::from_secs(1);
+
+/// How much wider than the text column code blocks and tables may grow
+const WIDE_CONTENT_FACTOR: f32 = 1.4;
+
+/// Smallest and largest preview zoom
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 3.0;
+
+/// Where a clicked footnote link goes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FootnoteJump {
+    /// The footnote with this label
+    Definition(String),
+    /// A reference to the footnote with this label, counted from 1
+    Reference(String, usize),
+}
 
 /// Preview panel for rendered Markdown
 pub struct Preview {
@@ -7,6 +45,60 @@ pub struct Preview {
     pub follow_editor: bool,
     pub zoom: f32,
     pub show_toc: bool,
+    /// Show the exported HTML in place of the rendered document
+    pub show_html: bool,
+    /// Elements selected as a whole, for copying
+    pub selection: BlockSelection,
+    /// Drawing the items of a tight list, whose paragraphs go without a gap
+    pub in_tight_list: bool,
+    /// Open state of collapsible sections the user toggled, by source offset
+    sections: HashMap<usize, bool>,
+    /// File shown instead of the editor's document
+    pinned: Option<PinnedFile>,
+    /// Zoom of the targets not being shown; `None` is the editor's document
+    zooms: HashMap<Option<PathBuf>, f32>,
+    /// Height of the rendered content and of the visible part of it
+    content_height: f32,
+    viewport_height: f32,
+    /// Fraction of the way down to scroll to after a reload, and whether
+    /// the reloaded text has been laid out yet
+    restore_scroll: Option<(f32, bool)>,
+    /// Content of each footnote in the document shown, by label
+    footnotes: HashMap<String, Vec<RenderedElement>>,
+    /// Footnote link clicked, to scroll to when its target is drawn
+    footnote_jump: Option<FootnoteJump>,
+    /// Source offset of a heading to scroll to when it is drawn
+    heading_jump: Option<usize>,
+    /// Offset to scroll back to, after peeking at a heading
+    scroll_back: Option<f32>,
+    /// Source offset of the caret, whose element to bring into view
+    caret_follow: Option<usize>,
+    /// Source offset of the element flashed for the caret, and since when
+    flash: Option<(usize, Instant)>,
+    /// When the user last scrolled the preview themselves
+    manual_scroll: Option<Instant>,
+    /// Blocks of the last text rendered, reused for the parts that don't change
+    incremental: IncrementalRender,
+    /// Hash of the text and settings `elements` were rendered from
+    rendered: Option<u64>,
+    /// Files the document shown includes, checked for changes
+    included: Vec<PathBuf>,
+    /// Words from each element to the end of the document
+    words_after: Vec<usize>,
+    /// Index of the first element in view, as last drawn
+    first_visible: usize,
+    /// Source offset of the first top-level element in view, and how far
+    /// into it the view starts, as a fraction of its height
+    view_top: Option<(usize, f32)>,
+    /// Where the view started when the zoom changed, to scroll back to
+    /// once the new zoom is laid out
+    zoom_anchor: Option<(usize, f32)>,
+    /// Offset that keeps the zoom anchor in place, scrolled to next frame
+    zoom_scroll: Option<f32>,
+    /// Exported page for the HTML source view, with the hash of what it was made from
+    html: Option<(u64, String)>,
+    /// Height of each top-level element as last drawn on a page
+    page_heights: Vec<f32>,
 }
 
 impl Preview {
@@ -17,6 +109,32 @@ impl Preview {
             follow_editor: true,
             zoom: 1.0,
             show_toc: false,
+            show_html: false,
+            selection: BlockSelection::default(),
+            in_tight_list: false,
+            sections: HashMap::new(),
+            pinned: None,
+            zooms: HashMap::new(),
+            content_height: 0.0,
+            viewport_height: 0.0,
+            restore_scroll: None,
+            footnotes: HashMap::new(),
+            footnote_jump: None,
+            heading_jump: None,
+            scroll_back: None,
+            caret_follow: None,
+            flash: None,
+            manual_scroll: None,
+            incremental: IncrementalRender::default(),
+            rendered: None,
+            included: Vec::new(),
+            words_after: Vec::new(),
+            first_visible: 0,
+            view_top: None,
+            zoom_anchor: None,
+            zoom_scroll: None,
+            html: None,
+            page_heights: Vec::new(),
         }
     }
 
@@ -24,38 +142,345 @@ impl Preview {
         self.elements = elements;
     }
 
+    /// Bring `elements` up to date with `text`, the contents of `document`,
+    /// re-rendering only the blocks that changed since the last call. Also
+    /// re-renders when a file it includes changes on disk.
+    pub fn refresh(&mut self, renderer: &MarkdownRenderer, text: &str, document: Option<&Path>) {
+        let flavor = renderer.flavor_for(text);
+        let changed: Vec<_> = self
+            .included
+            .iter()
+            .map(|path| std::fs::metadata(path).and_then(|meta| meta.modified()).ok())
+            .collect();
+        let mut hasher = DefaultHasher::new();
+        (text, renderer.settings_key(flavor), document, changed).hash(&mut hasher);
+        let key = hasher.finish();
+        if self.rendered == Some(key) {
+            return;
+        }
+        self.rendered = Some(key);
+        let _span = timing::span(Phase::Parse);
+        let elements = self.incremental.render(renderer, text);
+        let rendered = self.incremental.rendered_blocks();
+        log::trace!("Preview rendered {} blocks", rendered);
+        timing::count(Cache::PreviewBlocks, self.incremental.cached_blocks().saturating_sub(rendered), rendered);
+        self.included.clear();
+        let wiki = renderer.wiki_links_for(flavor);
+        let render = |text: &str| renderer.render(text);
+        let mut elements = include::include_elements(elements, text, document, wiki, &render, &mut self.included);
+        // Figures of included files count on from the document's own
+        figures::number_figures(&mut elements);
+        self.set_footnotes(&elements);
+        let mut words = 0;
+        self.words_after = elements.iter().rev().map(|element| {
+            words += Self::count_words_in_element(element);
+            words
+        }).collect();
+        self.words_after.reverse();
+        self.elements = elements;
+        self.selection.follow(text);
+    }
+
+    /// The page the HTML exporter writes for `text`, made again only when
+    /// the text or options change
+    pub fn html_source(&mut self, text: &str, options: HtmlOptions, stylesheet: Stylesheet, title: &str, lang: Option<&str>) -> &str {
+        let mut hasher = DefaultHasher::new();
+        (text, options.flavor, options.number_headings, options.toc_depth, stylesheet, title, lang).hash(&mut hasher);
+        let key = hasher.finish();
+        if self.html.as_ref().map(|(made_from, _)| *made_from) != Some(key) {
+            let body = export::markdown_to_html(text, options, |_, url| url.to_string());
+            self.html = Some((key, export::html_document(title, lang, stylesheet, &body)));
+        }
+        self.html.as_ref().map_or("", |(_, html)| html)
+    }
+
+    /// Rough size of the elements shown and kept for the next render,
+    /// without what they point to
+    pub fn cache_bytes(&self) -> usize {
+        self.elements.capacity() * std::mem::size_of::<RenderedElement>() + self.incremental.cached_bytes()
+    }
+
+    /// Whether the document shown includes other files
+    pub fn has_includes(&self) -> bool {
+        !self.included.is_empty()
+    }
+
     pub fn set_scroll_offset(&mut self, offset: f32) {
         self.scroll_offset = offset;
     }
 
+    /// Record where the preview is scrolled and how tall its content is,
+    /// after it was drawn
+    pub fn record_scroll(&mut self, offset: f32, content_height: f32, viewport_height: f32) {
+        self.scroll_offset = offset;
+        self.content_height = content_height;
+        self.viewport_height = viewport_height;
+        // An anchor not drawn went with the text that changed
+        self.zoom_anchor = None;
+        if let Some((_, laid_out)) = &mut self.restore_scroll {
+            *laid_out = true;
+        }
+    }
+
+    /// Furthest the preview can scroll, as last laid out
+    pub fn max_scroll(&self) -> f32 {
+        (self.content_height - self.viewport_height).max(0.0)
+    }
+
+    /// How far down the document the view is, from 0 to 1
+    pub fn scroll_fraction(&self) -> f32 {
+        let range = self.max_scroll();
+        if range > 0.0 { (self.scroll_offset / range).clamp(0.0, 1.0) } else { 0.0 }
+    }
+
+    /// Scroll `fraction` of the way down once the document is laid out
+    pub fn scroll_to_fraction(&mut self, fraction: f32) {
+        self.restore_scroll = Some((fraction.clamp(0.0, 1.0), false));
+    }
+
+    /// Note the first element in view while drawing
+    pub fn set_first_visible(&mut self, index: usize) {
+        self.first_visible = index;
+    }
+
+    /// Which of `elements` go on each page of `content_height`, laid out
+    /// with their heights as last drawn
+    pub fn pages(&mut self, elements: &[RenderedElement], content_height: f32) -> Vec<Range<usize>> {
+        self.page_heights.resize(elements.len(), 0.0);
+        let breaks: Vec<bool> = elements.iter().map(|element| matches!(element.kind, ElementKind::PageBreak)).collect();
+        pages::paginate(&self.page_heights, &breaks, content_height)
+    }
+
+    /// Note the height the element at `index` was drawn on a page at,
+    /// returning whether it differs from what its page was laid out with
+    pub fn measure_on_page(&mut self, index: usize, height: f32) -> bool {
+        let Some(measured) = self.page_heights.get_mut(index) else {
+            return false;
+        };
+        let changed = (*measured - height).abs() > 0.5;
+        *measured = height;
+        changed
+    }
+
+    /// Words from the first element in view to the end
+    pub fn words_left(&self) -> usize {
+        self.words_after.get(self.first_visible).copied().unwrap_or(0)
+    }
+
+    /// Stay the same fraction of the way down the document when the text
+    /// is replaced
+    pub fn keep_scroll_fraction(&mut self) {
+        self.scroll_to_fraction(self.scroll_fraction());
+    }
+
+    /// Return to `offset`, where the preview was before peeking elsewhere
+    pub fn scroll_back_to(&mut self, offset: f32) {
+        self.heading_jump = None;
+        self.scroll_back = Some(offset);
+    }
+
+    /// Offset to scroll to this frame: back from a peek, or once the new
+    /// text has been measured
+    pub fn take_scroll_restore(&mut self) -> Option<f32> {
+        if let Some(offset) = self.scroll_back.take().or(self.zoom_scroll.take()) {
+            return Some(offset);
+        }
+        match self.restore_scroll {
+            Some((fraction, true)) => {
+                self.restore_scroll = None;
+                Some(fraction * (self.content_height - self.viewport_height).max(0.0))
+            }
+            _ => None,
+        }
+    }
+
     pub fn toggle_follow_editor(&mut self) {
         self.follow_editor = !self.follow_editor;
     }
 
     pub fn zoom_in(&mut self) {
-        self.zoom = (self.zoom * 1.1).min(3.0);
+        self.zoom_by(1.1);
     }
 
     pub fn zoom_out(&mut self) {
-        self.zoom = (self.zoom / 1.1).max(0.5);
+        self.zoom_by(1.0 / 1.1);
+    }
+
+    /// Scale the zoom by `factor`, as a pinch or Ctrl+wheel asks for
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.set_zoom(self.zoom * factor);
     }
 
     pub fn reset_zoom(&mut self) {
-        self.zoom = 1.0;
+        self.set_zoom(1.0);
+    }
+
+    /// Zoom to `zoom`, within bounds, keeping the content at the top of
+    /// the view where it is
+    fn set_zoom(&mut self, zoom: f32) {
+        let zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+        if zoom != self.zoom {
+            self.zoom = zoom;
+            self.hold_view_top();
+        }
+    }
+
+    /// Keep the content at the top of the view where it is through the
+    /// next layout, which may change the content's height
+    pub fn hold_view_top(&mut self) {
+        self.zoom_anchor = self.zoom_anchor.or(self.view_top);
+    }
+
+    /// Note while drawing that the view starts `fraction` of the way into
+    /// the top-level element at source offset `start`
+    pub fn set_view_top(&mut self, start: usize, fraction: f32) {
+        self.view_top = Some((start, fraction));
+    }
+
+    /// How far into the element at `start`, as a fraction of its height,
+    /// the view should start, when the zoom just changed while it was at
+    /// the top. Cleared once taken.
+    pub fn take_zoom_anchor(&mut self, start: usize) -> Option<f32> {
+        let (anchor, fraction) = self.zoom_anchor?;
+        (anchor == start).then(|| {
+            self.zoom_anchor = None;
+            fraction
+        })
+    }
+
+    /// Scroll to `offset` next frame, to keep the zoom anchor in place
+    pub fn scroll_for_zoom(&mut self, offset: f32) {
+        self.zoom_scroll = Some(offset.max(0.0));
     }
 
     pub fn toggle_toc(&mut self) {
         self.show_toc = !self.show_toc;
     }
 
-    pub fn generate_toc(&self) -> Vec<(u8, String)> {
-        let mut toc = Vec::new();
-        for element in &self.elements {
-            if let RenderedElement::Heading(level, text) = element {
-                toc.push((*level, text.clone()));
+    /// Whether the collapsible section at `offset` is open, or `default`
+    /// if it hasn't been toggled this session
+    pub fn is_section_open(&self, offset: usize, default: bool) -> bool {
+        self.sections.get(&offset).copied().unwrap_or(default)
+    }
+
+    pub fn set_section_open(&mut self, offset: usize, open: bool) {
+        self.sections.insert(offset, open);
+    }
+
+    /// Remember the footnotes among `elements`, for previews on hover
+    pub fn set_footnotes(&mut self, elements: &[RenderedElement]) {
+        self.footnotes.clear();
+        for element in elements {
+            if let ElementKind::FootnoteDefinition(label, _, _, body) = &element.kind {
+                self.footnotes.insert(label.clone(), body.clone());
             }
         }
-        toc
+    }
+
+    pub fn footnote(&self, label: &str) -> Option<&[RenderedElement]> {
+        self.footnotes.get(label).map(Vec::as_slice)
+    }
+
+    pub fn jump_to_footnote(&mut self, jump: FootnoteJump) {
+        self.footnote_jump = Some(jump);
+    }
+
+    /// Whether `target` is the pending jump, clearing it if so
+    pub fn take_footnote_jump(&mut self, target: &FootnoteJump) -> bool {
+        let matched = self.footnote_jump.as_ref() == Some(target);
+        if matched {
+            self.footnote_jump = None;
+        }
+        matched
+    }
+
+    /// Scroll the heading at source `offset` to the top when it is next drawn
+    pub fn jump_to_heading(&mut self, offset: usize) {
+        self.scroll_back = None;
+        self.heading_jump = Some(offset);
+    }
+
+    /// Whether the heading at `offset` is the pending jump, clearing it if so
+    pub fn take_heading_jump(&mut self, offset: usize) -> bool {
+        let matched = self.heading_jump == Some(offset);
+        if matched {
+            self.heading_jump = None;
+        }
+        matched
+    }
+
+    /// Bring the element at the caret's source `offset` into view when the
+    /// preview is next drawn, unless the user is scrolling it
+    pub fn follow_caret(&mut self, offset: usize) {
+        if self.manual_scroll.is_some_and(|at| at.elapsed() < MANUAL_SCROLL_GRACE) {
+            return;
+        }
+        self.caret_follow = Some(offset);
+    }
+
+    pub fn take_caret_follow(&mut self) -> Option<usize> {
+        self.caret_follow.take()
+    }
+
+    /// The user scrolled the preview; leave it be for a moment
+    pub fn note_manual_scroll(&mut self) {
+        self.manual_scroll = Some(Instant::now());
+        self.caret_follow = None;
+    }
+
+    /// Briefly tint the element starting at source `offset`
+    pub fn flash(&mut self, offset: usize) {
+        self.flash = Some((offset, Instant::now()));
+    }
+
+    /// How strongly the element at `offset` is tinted, from 1 fading to 0,
+    /// or `None` when it isn't
+    pub fn flash_strength(&mut self, offset: usize) -> Option<f32> {
+        let (flashed, since) = self.flash?;
+        let elapsed = since.elapsed();
+        if elapsed >= FLASH_DURATION {
+            self.flash = None;
+            return None;
+        }
+        (flashed == offset).then(|| 1.0 - elapsed.as_secs_f32() / FLASH_DURATION.as_secs_f32())
+    }
+
+    /// Forget toggled sections, e.g. when another document is opened
+    pub fn reset_sections(&mut self) {
+        self.sections.clear();
+    }
+
+    /// Show `path` instead of the editor's document until unpinned
+    pub fn pin(&mut self, path: PathBuf) {
+        self.switch_target(Some(PinnedFile::open(path)));
+    }
+
+    /// Follow the editor's document again
+    pub fn unpin(&mut self) {
+        self.switch_target(None);
+    }
+
+    /// Swap in another target, keeping the zoom of each
+    fn switch_target(&mut self, pinned: Option<PinnedFile>) {
+        self.zooms.insert(self.pinned_path().map(Path::to_path_buf), self.zoom);
+        self.pinned = pinned;
+        self.zoom = self.zooms.get(&self.pinned_path().map(Path::to_path_buf)).copied().unwrap_or(1.0);
+        self.sections.clear();
+    }
+
+    pub fn pinned(&self) -> Option<&PinnedFile> {
+        self.pinned.as_ref()
+    }
+
+    pub fn pinned_path(&self) -> Option<&Path> {
+        self.pinned.as_ref().map(PinnedFile::path)
+    }
+
+    /// Reload the pinned file if it changed on disk
+    pub fn poll_pinned(&mut self) {
+        if let Some(pinned) = self.pinned.as_mut() {
+            pinned.reload_if_changed();
+        }
     }
 
     pub fn word_count(&self) -> usize {
@@ -67,16 +492,16 @@ impl Preview {
     }
 
     fn count_words_in_element(element: &RenderedElement) -> usize {
-        match element {
-            RenderedElement::Paragraph(text) |
-            RenderedElement::Heading(_, text) => text.split_whitespace().count(),
-            RenderedElement::BlockQuote(items) => {
+        match &element.kind {
+            ElementKind::Paragraph(text) |
+            ElementKind::Heading(_, text) => text.split_whitespace().count(),
+            ElementKind::BlockQuote(items, _) => {
                 items.iter().map(|item| {
                     Self::count_words_in_element(item)
                 }).sum()
             }
-            RenderedElement::UnorderedList(items) |
-            RenderedElement::OrderedList(items) => {
+            ElementKind::UnorderedList(items, _) |
+            ElementKind::OrderedList(items, _) => {
                 items.iter().map(|item| {
                     item.iter().map(Self::count_words_in_element).sum::<usize>()
                 }).sum()
@@ -86,8 +511,114 @@ impl Preview {
     }
 }
 
+/// Size of a heading at `level` and `zoom` in a text column `width` wide.
+/// Zooming in stops growing headings at an eighth of the column, so a long
+/// title at 300% still wraps within it; they are never made smaller than
+/// unzoomed for that.
+pub fn heading_size(level: u8, zoom: f32, width: f32) -> f32 {
+    let size = match level {
+        1 => 32.0,
+        2 => 28.0,
+        3 => 24.0,
+        4 => 20.0,
+        5 => 18.0,
+        _ => 16.0,
+    };
+    (size * zoom).min((width / 8.0).max(size))
+}
+
+/// Widths of the preview's text column and of the wider column code blocks
+/// and tables may use, given `max_width` in unzoomed pixels (0 for no limit)
+/// and the `available` width. Zoom scales the column with the text, so lines
+/// break at the same words at any zoom.
+pub fn content_widths(max_width: f32, zoom: f32, available: f32) -> (f32, f32) {
+    if max_width <= 0.0 {
+        return (available, available);
+    }
+    let text = (max_width * zoom).min(available);
+    let wide = (max_width * zoom * WIDE_CONTENT_FACTOR).min(available);
+    (text, wide)
+}
+
 impl Default for Preview {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_widths() {
+        assert_eq!(content_widths(0.0, 2.0, 1000.0), (1000.0, 1000.0));
+        assert_eq!(content_widths(500.0, 1.0, 1000.0), (500.0, 700.0));
+        assert_eq!(content_widths(500.0, 1.5, 1000.0), (750.0, 1000.0));
+        assert_eq!(content_widths(500.0, 1.0, 300.0), (300.0, 300.0));
+    }
+
+    #[test]
+    fn test_heading_size() {
+        assert_eq!(heading_size(1, 1.0, 800.0), 32.0);
+        assert_eq!(heading_size(3, 2.0, 800.0), 48.0);
+        // A narrow column stops the heading growing, but doesn't shrink it
+        assert_eq!(heading_size(1, 3.0, 400.0), 50.0);
+        assert_eq!(heading_size(1, 3.0, 100.0), 32.0);
+        assert_eq!(heading_size(1, 0.5, 100.0), 16.0);
+    }
+
+    #[test]
+    fn test_heading_size_at_max_zoom() {
+        // A 700 wide column at 300% is cut to a 600 wide window, and the
+        // rendered H1 is held to an eighth of it
+        let (column, _) = content_widths(700.0, MAX_ZOOM, 600.0);
+        assert_eq!(column, 600.0);
+        assert_eq!(heading_size(1, MAX_ZOOM, column), 75.0);
+        let heading = MarkdownRenderer::default().render("# Getting started\n").remove(0);
+        let ElementKind::Heading(level, _) = heading.kind else {
+            panic!("not a heading: {:?}", heading.kind);
+        };
+        assert_eq!(heading_size(level, MAX_ZOOM, column), 75.0);
+        // Lower levels grow until they reach it too
+        assert_eq!(heading_size(3, MAX_ZOOM, column), 72.0);
+        assert_eq!(heading_size(2, MAX_ZOOM, column), 75.0);
+    }
+
+    #[test]
+    fn test_zoom_anchor() {
+        let mut preview = Preview::new();
+        preview.set_view_top(120, 0.25);
+        preview.zoom_by(10.0);
+        assert_eq!(preview.zoom, MAX_ZOOM);
+        preview.set_view_top(300, 0.0);
+        // Zooming again before the new zoom is drawn keeps the first anchor
+        preview.zoom_out();
+        assert_eq!(preview.take_zoom_anchor(300), None);
+        assert_eq!(preview.take_zoom_anchor(120), Some(0.25));
+        assert_eq!(preview.take_zoom_anchor(120), None);
+    }
+
+    #[test]
+    fn test_scroll_fraction_kept_across_reload() {
+        let mut preview = Preview::new();
+        preview.record_scroll(300.0, 1000.0, 400.0);
+        preview.keep_scroll_fraction();
+        // Not until the new text has been laid out once
+        assert_eq!(preview.take_scroll_restore(), None);
+        preview.record_scroll(300.0, 2400.0, 400.0);
+        assert_eq!(preview.take_scroll_restore(), Some(1000.0));
+        assert_eq!(preview.take_scroll_restore(), None);
+    }
+
+    #[test]
+    fn test_words_left() {
+        let mut preview = Preview::new();
+        preview.refresh(&MarkdownRenderer::default(), "# One two\n\nthree four five\n\nsix\n", None);
+        assert_eq!(preview.words_left(), 6);
+        preview.set_first_visible(1);
+        assert_eq!(preview.words_left(), 4);
+        preview.set_first_visible(9);
+        assert_eq!(preview.words_left(), 0);
+    }
+}
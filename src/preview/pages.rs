@@ -0,0 +1,90 @@
+//! The paginated preview: the document laid out on pages of the paper and
+//! margins printing uses, so page breaks can be seen before exporting.
+//! Elements are measured as they are drawn, and each frame lays out its
+//! pages with the heights measured the frame before.
+
+use crate::markdown::export::PageSize;
+use std::ops::Range;
+
+/// Points per millimetre; a point is a CSS pixel, 1/96 inch
+const POINTS_PER_MM: f32 = 96.0 / 25.4;
+
+/// Size and margins of a page, in points at the preview's zoom
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageGeometry {
+    pub width: f32,
+    pub height: f32,
+    pub margin: f32,
+}
+
+impl PageGeometry {
+    /// A page of `size` with `margin` millimetres around the text
+    pub fn new(size: PageSize, margin: f32, zoom: f32) -> Self {
+        let (width, height) = size.size_mm();
+        let scale = POINTS_PER_MM * zoom;
+        Self { width: width * scale, height: height * scale, margin: margin.max(0.0) * scale }
+    }
+
+    /// Height between the top and bottom margins
+    pub fn content_height(&self) -> f32 {
+        (self.height - 2.0 * self.margin).max(1.0)
+    }
+}
+
+/// The elements on each page, given each element's height and whether it
+/// is a manual page break. An element taller than a page gets one to
+/// itself; a break ends the page it is on and takes no space.
+pub fn paginate(heights: &[f32], breaks: &[bool], content_height: f32) -> Vec<Range<usize>> {
+    let mut pages = Vec::new();
+    let mut start = 0;
+    let mut used = 0.0;
+    for (index, &height) in heights.iter().enumerate() {
+        if breaks.get(index).copied().unwrap_or(false) {
+            pages.push(start..index + 1);
+            start = index + 1;
+            used = 0.0;
+            continue;
+        }
+        if index > start && used + height > content_height {
+            pages.push(start..index);
+            start = index;
+            used = 0.0;
+        }
+        used += height;
+    }
+    if start < heights.len() || pages.is_empty() {
+        pages.push(start..heights.len());
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_geometry() {
+        let a4 = PageGeometry::new(PageSize::A4, 20.0, 1.0);
+        assert!((a4.width - 793.7).abs() < 0.1);
+        assert!((a4.height - 1122.5).abs() < 0.1);
+        assert!((a4.content_height() - (1122.5 - 151.2)).abs() < 0.1);
+        let zoomed = PageGeometry::new(PageSize::A4, 20.0, 2.0);
+        assert!((zoomed.width - 2.0 * a4.width).abs() < 0.01);
+        assert_eq!(PageSize::Letter.page_rule(25.0), "@page{size:215.9mm 279.4mm;margin:25mm}");
+    }
+
+    #[test]
+    fn test_paginate() {
+        let heights = [40.0, 40.0, 40.0, 0.0, 40.0, 250.0, 10.0];
+        let breaks = [false, false, false, true, false, false, false];
+        // Full pages move on, a break ends its page, and a tall element
+        // takes a page of its own
+        assert_eq!(paginate(&heights, &breaks, 100.0), [0..2, 2..4, 4..5, 5..6, 6..7]);
+        // An empty document still has its page, and nothing follows a final break
+        for (heights, breaks, all) in [(&[][..], &[][..], 0..0), (&[10.0, 0.0], &[false, true], 0..2)] {
+            let pages = paginate(heights, breaks, 100.0);
+            assert_eq!(pages.len(), 1);
+            assert_eq!(pages[0], all);
+        }
+    }
+}
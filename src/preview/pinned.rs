@@ -0,0 +1,97 @@
+//! A file from disk shown in the preview in place of the editor's document
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the pinned file's modification time is checked
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The file the preview is pinned to, reloaded when it changes on disk
+pub struct PinnedFile {
+    path: PathBuf,
+    /// Contents as last read
+    text: String,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+    /// Why the file couldn't be read, if it couldn't
+    error: Option<String>,
+}
+
+impl PinnedFile {
+    pub fn open(path: PathBuf) -> Self {
+        let mut pinned = Self {
+            path,
+            text: String::new(),
+            modified: None,
+            last_check: Instant::now(),
+            error: None,
+        };
+        pinned.reload();
+        pinned
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Re-read the file if its modification time changed since it was last
+    /// read, checking at most every half second. Returns whether it did.
+    pub fn reload_if_changed(&mut self) -> bool {
+        if self.last_check.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.last_check = Instant::now();
+        let modified = std::fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        if modified == self.modified && self.error.is_none() {
+            return false;
+        }
+        self.reload();
+        true
+    }
+
+    fn reload(&mut self) {
+        self.modified = std::fs::metadata(&self.path).and_then(|m| m.modified()).ok();
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => {
+                self.text = crate::utils::normalize_line_endings(&text);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload_when_modified() {
+        let path = std::env::temp_dir().join(format!("rmd-pinned-{}.md", std::process::id()));
+        std::fs::write(&path, "# One\r\n").unwrap();
+        let mut pinned = PinnedFile::open(path.clone());
+        assert_eq!(pinned.text(), "# One\n");
+        assert!(!pinned.reload_if_changed());
+
+        std::fs::write(&path, "# Two\n").unwrap();
+        let earlier = SystemTime::now() - Duration::from_secs(60);
+        std::fs::File::options().write(true).open(&path).unwrap().set_modified(earlier).unwrap();
+        pinned.last_check -= POLL_INTERVAL;
+        assert!(pinned.reload_if_changed());
+        assert_eq!(pinned.text(), "# Two\n");
+
+        std::fs::remove_file(&path).unwrap();
+        pinned.last_check -= POLL_INTERVAL;
+        assert!(pinned.reload_if_changed());
+        assert!(pinned.error().is_some());
+        assert_eq!(pinned.text(), "# Two\n");
+    }
+}
@@ -0,0 +1,149 @@
+//! Selecting whole elements of the preview: Ctrl+A selects them all and
+//! Shift+click extends from the last one clicked, for Ctrl+C to copy. Text
+//! inside one element is selected by dragging, as labels are. The selection
+//! is kept as the source text it covers, so it is found again after edits
+//! elsewhere in the document.
+
+use crate::markdown::RenderedElement;
+use std::ops::Range;
+
+#[derive(Debug, Default)]
+pub struct BlockSelection {
+    /// Source range of the selected elements, and the source it held
+    selected: Option<(Range<usize>, String)>,
+    /// Span of the element last clicked, which Shift+click extends from
+    anchor: Option<Range<usize>>,
+}
+
+impl BlockSelection {
+    pub fn is_empty(&self) -> bool {
+        self.selected.is_none()
+    }
+
+    pub fn clear(&mut self) {
+        self.selected = None;
+    }
+
+    /// Select every element of `elements`, rendered from `text`
+    pub fn select_all(&mut self, elements: &[RenderedElement], text: &str) {
+        let (Some(first), Some(last)) = (elements.first(), elements.last()) else {
+            return;
+        };
+        self.select(first.span.start..last.span.end, text);
+    }
+
+    /// A click on the element at `span`: with `extend`, select from the
+    /// element clicked before through this one
+    pub fn click(&mut self, span: Range<usize>, extend: bool, text: &str) {
+        match self.anchor.clone().filter(|_| extend) {
+            Some(anchor) => self.select(anchor.start.min(span.start)..anchor.end.max(span.end), text),
+            None => {
+                self.selected = None;
+                self.anchor = Some(span);
+            }
+        }
+    }
+
+    fn select(&mut self, range: Range<usize>, text: &str) {
+        if let Some(source) = text.get(range.clone()) {
+            self.selected = Some((range, source.to_string()));
+        }
+    }
+
+    /// Whether the element at `span` is selected
+    pub fn contains(&self, span: Range<usize>) -> bool {
+        self.selected.as_ref().is_some_and(|(range, _)| span.start < range.end && range.start < span.end)
+    }
+
+    /// Find the selection again in `text`, the document after an edit: at
+    /// the same place if it is unchanged there, or else where the same
+    /// source is nearest to it. An edit inside the selection ends it.
+    pub fn follow(&mut self, text: &str) {
+        let Some((range, source)) = self.selected.take() else {
+            return;
+        };
+        let found = if text.get(range.clone()) == Some(source.as_str()) {
+            Some(range.start)
+        } else {
+            text.match_indices(source.as_str()).map(|(at, _)| at).min_by_key(|&at| at.abs_diff(range.start))
+        };
+        if let Some(start) = found {
+            let shift = |offset: usize| (offset + start).saturating_sub(range.start);
+            self.anchor = self.anchor.take().map(|anchor| shift(anchor.start)..shift(anchor.end));
+            self.selected = Some((start..start + source.len(), source));
+        } else {
+            self.anchor = None;
+        }
+    }
+
+    /// Text of the selected elements in document order. Elements from one
+    /// line run together, as they do in the source, and a line or blank
+    /// line between two in the source separates them the same way.
+    pub fn copy_text(&self, elements: &[RenderedElement], text: &str) -> Option<String> {
+        self.selected.as_ref()?;
+        let mut copied = String::new();
+        let mut previous: Option<usize> = None;
+        for element in elements.iter().filter(|element| self.contains(element.span.range())) {
+            if let Some(end) = previous {
+                let gap = text.get(end..element.span.start.max(end)).unwrap_or("");
+                copied.push_str(match gap.matches('\n').count() {
+                    0 => "",
+                    1 => "\n",
+                    _ => "\n\n",
+                });
+            }
+            copied.push_str(&element.plain_text());
+            // Blocks' spans may take in the blank lines after them
+            let source = text.get(element.span.range()).unwrap_or("");
+            previous = Some(element.span.start + source.trim_end().len());
+        }
+        (!copied.is_empty()).then_some(copied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::MarkdownRenderer;
+
+    const TEXT: &str = "# Title\n\nHello **world** and [a link](u) too\nnext line\n\n- one\n- two\n\nLast\n";
+
+    #[test]
+    fn test_copy_text() {
+        let elements = MarkdownRenderer::default().render(TEXT);
+        let mut selection = BlockSelection::default();
+        assert_eq!(selection.copy_text(&elements, TEXT), None);
+        selection.select_all(&elements, TEXT);
+        assert_eq!(
+            selection.copy_text(&elements, TEXT).unwrap(),
+            // The paragraph's lines are joined, as the preview shows them
+            "Title\n\nHello world and a link too next line\n\none\ntwo\n\nLast"
+        );
+
+        // Shift+click extends from the element clicked before
+        let hello = elements.iter().find(|element| element.plain_text() == "Hello ").unwrap();
+        let next = elements.iter().find(|element| element.plain_text() == " too next line").unwrap();
+        selection.click(next.span.range(), false, TEXT);
+        assert!(selection.is_empty());
+        selection.click(hello.span.range(), true, TEXT);
+        assert_eq!(selection.copy_text(&elements, TEXT).unwrap(), "Hello world and a link too next line");
+    }
+
+    #[test]
+    fn test_selection_follows_edits() {
+        let elements = MarkdownRenderer::default().render(TEXT);
+        let last = elements.last().unwrap().span.range();
+        let mut selection = BlockSelection::default();
+        selection.click(last.clone(), false, TEXT);
+        selection.click(last.clone(), true, TEXT);
+
+        // Typing above moves it along
+        let edited = format!("Intro\n\n{}", TEXT);
+        selection.follow(&edited);
+        assert!(selection.contains(last.start + 7..last.end + 7));
+        assert!(!selection.contains(last.clone()));
+        // Editing the selected text itself ends it
+        selection.follow(&edited.replace("Last", "Lost"));
+        assert!(selection.is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+//! Loading what the first frame can do without — the settings, the
+//! monospace font, the theme file, and the document named on the command line — on a
+//! background thread, so the window appears at once with the defaults
+
+use crate::config::Config;
+use crate::editor::error::{EditorError, Operation};
+use crate::editor::load;
+use crate::editor::snippets::SnippetLibrary;
+use crate::editor::typing_aids::Corrections;
+use crate::keymap::Keymap;
+use crate::theme::custom::ThemeFile;
+use crate::theme::Chrome;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Instant;
+
+/// Monospace font used for the editor and code, when it is there
+const FONT_PATH: &str = "assets/fonts/FiraCode-Regular.ttf";
+
+/// Every settings file, and the font, read together so they change the
+/// window once
+pub struct Settings {
+    pub config: Config,
+    pub keymap: Keymap,
+    pub snippets: SnippetLibrary,
+    pub corrections: Corrections,
+    pub font: Option<Vec<u8>>,
+    /// Each fallback font that could be read, by path
+    pub fallback_fonts: Vec<(String, Vec<u8>)>,
+    /// Pane styling from `theme.toml`
+    pub chrome: Chrome,
+}
+
+/// Something the startup thread finished loading
+pub enum Loaded {
+    Settings(Box<Settings>),
+    /// The document to open, and its text
+    Document(PathBuf, Result<String, EditorError>),
+}
+
+/// The startup thread, and what to do once the settings are in
+pub struct StartupLoad {
+    receiver: Receiver<Loaded>,
+    /// When the process started
+    started: Instant,
+    first_frame: bool,
+    /// Whether the app has the loaded settings yet
+    pub settings_applied: bool,
+    /// Workspace folder to open once the settings are in, from File → New
+    /// Window
+    pub workspace: Option<PathBuf>,
+    /// Lock the document once it is open
+    pub readonly: bool,
+}
+
+impl StartupLoad {
+    /// Start loading, then `document` if there is one
+    pub fn spawn(started: Instant, document: Option<PathBuf>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let config = Config::load_or_default();
+            let fallback_fonts = read_fallback_fonts(&config.font.fallback_fonts);
+            let settings = Settings {
+                config,
+                keymap: Keymap::load_or_default(),
+                snippets: SnippetLibrary::load_or_default(),
+                corrections: Corrections::load_or_default(),
+                font: std::fs::read(FONT_PATH).ok(),
+                fallback_fonts,
+                chrome: ThemeFile::load_chrome(),
+            };
+            log::debug!("Settings loaded {:?} after start", started.elapsed());
+            if sender.send(Loaded::Settings(Box::new(settings))).is_err() {
+                return;
+            }
+            if let Some(path) = document {
+                let text = load::read(&path, Operation::Open);
+                let _ = sender.send(Loaded::Document(path, text));
+            }
+        });
+        Self { receiver, started, first_frame: true, settings_applied: false, workspace: None, readonly: false }
+    }
+
+    /// What was loaded since the last call, and whether everything has been
+    pub fn poll(&mut self) -> (Vec<Loaded>, bool) {
+        if std::mem::take(&mut self.first_frame) {
+            log::debug!("First frame {:?} after start", self.started.elapsed());
+        }
+        let mut loaded = Vec::new();
+        loop {
+            match self.receiver.try_recv() {
+                Ok(item) => loaded.push(item),
+                Err(TryRecvError::Empty) => return (loaded, false),
+                Err(TryRecvError::Disconnected) => {
+                    log::debug!("Startup finished {:?} after start", self.started.elapsed());
+                    return (loaded, true);
+                }
+            }
+        }
+    }
+}
+
+/// The font files at `paths` that could be read, skipping the rest
+fn read_fallback_fonts(paths: &[String]) -> Vec<(String, Vec<u8>)> {
+    paths
+        .iter()
+        .filter_map(|path| match std::fs::read(path) {
+            Ok(data) => Some((path.clone(), data)),
+            Err(e) => {
+                log::warn!("Couldn't read fallback font {}: {}", path, e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_come_before_the_document() {
+        let path = std::env::temp_dir().join(format!("rmd-startup-{}.md", std::process::id()));
+        std::fs::write(&path, "# Hello\n").unwrap();
+        let mut startup = StartupLoad::spawn(Instant::now(), Some(path.clone()));
+        let mut loaded = Vec::new();
+        loop {
+            let (items, finished) = startup.poll();
+            loaded.extend(items);
+            if finished {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(loaded.len(), 2);
+        assert!(matches!(loaded[0], Loaded::Settings(_)));
+        assert!(matches!(&loaded[1], Loaded::Document(p, Ok(text)) if *p == path && text == "# Hello\n"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
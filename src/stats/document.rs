@@ -0,0 +1,158 @@
+//! Word counts of the open document, telling prose apart from code and markup
+
+use crate::markdown::flavor::Flavor;
+use pulldown_cmark::{Event, LinkType, Parser, Tag, TagEnd};
+
+/// Words in a document, by where they appear
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DocumentStats {
+    /// Every word in the source, markup included
+    pub total_words: usize,
+    /// Words of text a reader sees: paragraphs, headings, lists, tables,
+    /// and the text of links
+    pub prose_words: usize,
+    /// Words in code blocks and inline code
+    pub code_words: usize,
+    /// Words in the front matter
+    pub front_matter_words: usize,
+    /// Characters in the source
+    pub characters: usize,
+    /// Characters of prose other than spaces, the count that stands in for
+    /// words in Chinese, Japanese and Korean
+    pub prose_characters: usize,
+}
+
+impl DocumentStats {
+    /// Count the words of `markdown` as parsed with `flavor`. HTML, link
+    /// and image destinations, image descriptions, and bare `<url>` links
+    /// count towards the total only.
+    pub fn new(markdown: &str, flavor: Flavor) -> Self {
+        // Collect the text of each kind, so words split across events,
+        // like `un*believ*able`, are counted once
+        let mut prose = String::new();
+        let mut code = String::new();
+        let mut front_matter = String::new();
+        let mut in_front_matter = false;
+        let mut in_code_block = false;
+        // Depth of images and `<url>` links, whose text isn't prose
+        let mut hidden = 0;
+
+        for event in Parser::new_ext(markdown, flavor.options()) {
+            match event {
+                Event::Start(Tag::MetadataBlock(_)) => in_front_matter = true,
+                Event::End(TagEnd::MetadataBlock(_)) => in_front_matter = false,
+                Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+                Event::End(TagEnd::CodeBlock) => {
+                    in_code_block = false;
+                    code.push(' ');
+                }
+                Event::Start(Tag::Image { .. })
+                | Event::Start(Tag::Link { link_type: LinkType::Autolink | LinkType::Email, .. }) => hidden += 1,
+                Event::End(TagEnd::Image) => hidden -= 1,
+                Event::End(TagEnd::Link) if hidden > 0 => hidden -= 1,
+                Event::Text(text) if in_front_matter => front_matter.push_str(&text),
+                Event::Text(text) if in_code_block => code.push_str(&text),
+                Event::Text(text) if hidden == 0 => prose.push_str(&text),
+                Event::Code(text) => {
+                    code.push_str(&text);
+                    code.push(' ');
+                    prose.push(' ');
+                }
+                // Block ends and breaks separate words; inline markup doesn't
+                Event::End(
+                    TagEnd::Paragraph
+                    | TagEnd::Heading(_)
+                    | TagEnd::Item
+                    | TagEnd::TableCell
+                    | TagEnd::FootnoteDefinition
+                    | TagEnd::BlockQuote(_),
+                )
+                | Event::SoftBreak
+                | Event::HardBreak
+                | Event::Html(_)
+                | Event::InlineHtml(_)
+                | Event::InlineMath(_)
+                | Event::DisplayMath(_)
+                | Event::FootnoteReference(_)
+                | Event::TaskListMarker(_) => prose.push(' '),
+                _ => {}
+            }
+        }
+
+        Self {
+            total_words: count_words(markdown),
+            prose_words: count_words(&prose),
+            code_words: count_words(&code),
+            front_matter_words: count_words(&front_matter),
+            characters: markdown.chars().count(),
+            prose_characters: prose.chars().filter(|c| !c.is_whitespace()).count(),
+        }
+    }
+
+    /// Words of the total that are none of the others: HTML, URLs, and such
+    pub fn markup_words(&self) -> usize {
+        self.total_words
+            .saturating_sub(self.prose_words + self.code_words + self.front_matter_words)
+    }
+}
+
+/// Ideographs and syllables of Chinese, Japanese and Korean, which are
+/// written without spaces and counted one character per word
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}'     // Hiragana, Katakana
+        | '\u{3400}'..='\u{4DBF}'   // CJK Extension A
+        | '\u{4E00}'..='\u{9FFF}'   // CJK Unified Ideographs
+        | '\u{AC00}'..='\u{D7AF}'   // Hangul syllables
+        | '\u{F900}'..='\u{FAFF}'   // CJK Compatibility Ideographs
+        | '\u{20000}'..='\u{2FA1F}' // CJK Extensions B and later
+    )
+}
+
+/// Words in `text`: runs of characters between spaces that have a letter
+/// or digit in them, so a lone `-` or `|` isn't one, and each CJK character
+pub fn count_words(text: &str) -> usize {
+    let mut words = 0;
+    for token in text.split_whitespace() {
+        let mut in_word = false;
+        for c in token.chars() {
+            if is_cjk(c) {
+                words += 1 + usize::from(in_word);
+                in_word = false;
+            } else if c.is_alphanumeric() {
+                in_word = true;
+            }
+        }
+        words += usize::from(in_word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_words() {
+        assert_eq!(count_words("Hello, world — it's **bold**"), 4);
+        assert_eq!(count_words("| - | -- |"), 0);
+        assert_eq!(count_words("日本語のテキスト"), 8);
+        assert_eq!(count_words("RMD是编辑器"), 5);
+    }
+
+    #[test]
+    fn test_fixture() {
+        let stats = DocumentStats::new(include_str!("fixtures/word_count.md"), Flavor::Gfm);
+        assert_eq!(
+            stats,
+            DocumentStats {
+                total_words: 54,
+                prose_words: 32,
+                code_words: 6,
+                front_matter_words: 6,
+                characters: 462,
+                prose_characters: 123,
+            }
+        );
+    }
+}
@@ -0,0 +1,216 @@
+//! Local writing statistics: characters typed, words added, and time spent
+//! actively editing, per day
+
+pub mod document;
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Editing counts as active while keystrokes are at most this far apart
+pub const ACTIVE_WINDOW: Duration = Duration::from_secs(30);
+
+/// Days of history kept in the file
+const MAX_DAYS: usize = 365;
+
+/// How often changed statistics are written while editing
+const SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Totals for one day
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct DayStats {
+    /// Days since the Unix epoch (UTC)
+    pub day: u64,
+    #[serde(default)]
+    pub chars_typed: u64,
+    /// Words added minus words removed
+    #[serde(default)]
+    pub word_delta: i64,
+    #[serde(default)]
+    pub active_seconds: u64,
+}
+
+impl DayStats {
+    pub fn active_time(&self) -> Duration {
+        Duration::from_secs(self.active_seconds)
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct StatsFile {
+    #[serde(default, rename = "day")]
+    days: Vec<DayStats>,
+}
+
+/// Statistics being recorded, with the file they are kept in
+pub struct WritingStats {
+    /// Oldest first
+    days: Vec<DayStats>,
+    path: Option<PathBuf>,
+    /// When the last keystroke was recorded
+    last_activity: Option<Instant>,
+    /// Sub-second active time not yet added to a day
+    pending_active: Duration,
+    dirty: bool,
+    last_save: Instant,
+}
+
+impl WritingStats {
+    /// Statistics kept in `path`, starting empty if it doesn't exist
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let days = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|contents| toml::from_str::<StatsFile>(&contents).ok())
+            .map(|file| file.days)
+            .unwrap_or_default();
+        Self {
+            days,
+            path,
+            last_activity: None,
+            pending_active: Duration::ZERO,
+            dirty: false,
+            last_save: Instant::now(),
+        }
+    }
+
+    /// Statistics in `config_dir/rmd/stats.toml`
+    pub fn load_default() -> Self {
+        Self::load(crate::config::Config::config_dir().ok().map(|dir| dir.join("stats.toml")))
+    }
+
+    /// Record an edit that typed `chars` characters and changed the word
+    /// count by `word_delta`
+    pub fn record_edit(&mut self, chars: usize, word_delta: i64) {
+        self.record_edit_at(today(), Instant::now(), chars, word_delta);
+    }
+
+    fn record_edit_at(&mut self, day: u64, now: Instant, chars: usize, word_delta: i64) {
+        if let Some(last) = self.last_activity {
+            let gap = now.saturating_duration_since(last);
+            if gap <= ACTIVE_WINDOW {
+                self.pending_active += gap;
+            }
+        }
+        self.last_activity = Some(now);
+
+        let whole_seconds = self.pending_active.as_secs();
+        self.pending_active -= Duration::from_secs(whole_seconds);
+        let stats = self.day_mut(day);
+        stats.chars_typed += chars as u64;
+        stats.word_delta += word_delta;
+        stats.active_seconds += whole_seconds;
+        self.dirty = true;
+    }
+
+    /// Totals for today
+    pub fn today(&self) -> DayStats {
+        self.day(today())
+    }
+
+    /// Totals for `day`, zero if nothing was recorded
+    pub fn day(&self, day: u64) -> DayStats {
+        self.days
+            .iter()
+            .find(|stats| stats.day == day)
+            .copied()
+            .unwrap_or(DayStats { day, ..DayStats::default() })
+    }
+
+    /// The last `count` days up to today, oldest first, including empty ones
+    pub fn recent_days(&self, count: u64) -> Vec<DayStats> {
+        let today = today();
+        (0..count).rev().map(|ago| self.day(today.saturating_sub(ago))).collect()
+    }
+
+    fn day_mut(&mut self, day: u64) -> &mut DayStats {
+        let index = match self.days.iter().position(|stats| stats.day == day) {
+            Some(index) => index,
+            None => {
+                self.days.push(DayStats { day, ..DayStats::default() });
+                self.days.sort_by_key(|stats| stats.day);
+                if self.days.len() > MAX_DAYS {
+                    self.days.drain(..self.days.len() - MAX_DAYS);
+                }
+                self.days.iter().position(|stats| stats.day == day).unwrap_or(self.days.len() - 1)
+            }
+        };
+        &mut self.days[index]
+    }
+
+    /// Write the statistics if they changed and weren't written recently
+    pub fn save_if_due(&mut self) {
+        if self.dirty && self.last_save.elapsed() >= SAVE_INTERVAL {
+            self.save();
+        }
+    }
+
+    /// Write the statistics if they changed
+    pub fn save(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        self.last_save = Instant::now();
+        let Some(path) = &self.path else {
+            return;
+        };
+        let file = StatsFile { days: self.days.clone() };
+        let result = toml::to_string(&file)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                Ok(std::fs::write(path, contents)?)
+            });
+        if let Err(e) = result {
+            log::warn!("Failed to save writing statistics: {}", e);
+        }
+    }
+}
+
+/// Days since the Unix epoch, by the local calendar, so a day starts at
+/// the user's midnight
+pub fn today() -> u64 {
+    // 1970-01-01
+    let epoch = chrono::NaiveDate::default();
+    let days = chrono::Local::now().date_naive().signed_duration_since(epoch).num_days();
+    days.max(0) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_active_time_counts_only_short_gaps() {
+        let mut stats = WritingStats::load(None);
+        let start = Instant::now();
+        stats.record_edit_at(100, start, 1, 0);
+        stats.record_edit_at(100, start + Duration::from_secs(10), 3, 1);
+        // A long pause isn't active time
+        stats.record_edit_at(100, start + Duration::from_secs(300), 2, 1);
+        stats.record_edit_at(100, start + Duration::from_millis(305_500), 1, -1);
+
+        let day = stats.day(100);
+        assert_eq!(day.chars_typed, 7);
+        assert_eq!(day.word_delta, 1);
+        assert_eq!(day.active_seconds, 15);
+        assert_eq!(stats.day(99), DayStats { day: 99, ..DayStats::default() });
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("rmd-stats-{}.toml", std::process::id()));
+        let mut stats = WritingStats::load(Some(path.clone()));
+        stats.record_edit_at(5, Instant::now(), 4, 2);
+        stats.record_edit_at(3, Instant::now(), 1, 1);
+        stats.save();
+
+        let loaded = WritingStats::load(Some(path.clone()));
+        assert_eq!(loaded.days.iter().map(|d| d.day).collect::<Vec<_>>(), vec![3, 5]);
+        assert_eq!(loaded.day(5).chars_typed, 4);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,155 @@
+//! `theme.toml` in the settings folder: a background behind the editor and
+//! preview, and card styling for them. Everything is optional; without the
+//! file the panes look as they always have.
+//!
+//! ```toml
+//! [background]
+//! image = "paper.png"           # relative to the settings folder
+//! gradient = ["#1e1e2e", "#11111b"]
+//! opacity = 0.15
+//!
+//! [cards]
+//! enabled = true
+//! rounding = 8.0
+//! margin = 8.0
+//! shadow = true
+//! ```
+
+use super::{Backdrop, Card, Chrome};
+use anyhow::Result;
+use eframe::egui;
+use serde::Deserialize;
+use std::path::Path;
+
+/// The file, as written
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ThemeFile {
+    background: BackgroundSection,
+    cards: CardSection,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct BackgroundSection {
+    /// Image covering each pane; wins over the gradient
+    image: Option<String>,
+    /// One color, or two for top to bottom
+    gradient: Vec<String>,
+    /// How strongly it shows over the theme's background, 0 to 1
+    opacity: f32,
+}
+
+impl Default for BackgroundSection {
+    fn default() -> Self {
+        Self { image: None, gradient: Vec::new(), opacity: 0.15 }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct CardSection {
+    enabled: bool,
+    rounding: f32,
+    margin: f32,
+    shadow: bool,
+}
+
+impl Default for CardSection {
+    fn default() -> Self {
+        Self { enabled: false, rounding: 8.0, margin: 8.0, shadow: true }
+    }
+}
+
+impl ThemeFile {
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(toml::from_str(text)?)
+    }
+
+    /// The file in the settings folder, or nothing when it is missing or
+    /// broken
+    pub fn load_chrome() -> Chrome {
+        let dir = match crate::config::Config::config_dir() {
+            Ok(dir) => dir,
+            Err(e) => {
+                log::warn!("Failed to locate theme.toml: {}", e);
+                return Chrome::default();
+            }
+        };
+        let path = dir.join("theme.toml");
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Chrome::default(),
+            Err(e) => {
+                log::warn!("Failed to read {}: {}", path.display(), e);
+                return Chrome::default();
+            }
+        };
+        match Self::parse(&text) {
+            Ok(file) => file.chrome(&dir),
+            Err(e) => {
+                log::warn!("Ignoring {}: {}", path.display(), e);
+                Chrome::default()
+            }
+        }
+    }
+
+    /// The pane styling, with relative image paths taken from `dir`. Colors
+    /// that don't parse are left out.
+    pub fn chrome(&self, dir: &Path) -> Chrome {
+        let background = &self.background;
+        let colors: Vec<egui::Color32> = background
+            .gradient
+            .iter()
+            .filter_map(|hex| match egui::Color32::from_hex(hex.trim()) {
+                Ok(color) => Some(color),
+                Err(e) => {
+                    log::warn!("Ignoring the color {:?} in theme.toml: {:?}", hex, e);
+                    None
+                }
+            })
+            .collect();
+        let backdrop = match (&background.image, colors.as_slice()) {
+            (Some(image), _) => Some(Backdrop::Image(dir.join(image))),
+            (None, [color]) => Some(Backdrop::Gradient(*color, *color)),
+            (None, [top, bottom, ..]) => Some(Backdrop::Gradient(*top, *bottom)),
+            (None, []) => None,
+        };
+        let cards = &self.cards;
+        Chrome {
+            backdrop,
+            opacity: background.opacity.clamp(0.0, 1.0),
+            card: cards.enabled.then_some(Card {
+                rounding: cards.rounding.max(0.0),
+                margin: cards.margin.max(0.0),
+                shadow: cards.shadow,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_chrome() {
+        let dir = Path::new("settings");
+        assert_eq!(ThemeFile::parse("").unwrap().chrome(dir), Chrome { opacity: 0.15, ..Chrome::default() });
+
+        let file = ThemeFile::parse("[background]\nimage = \"paper.png\"\ngradient = [\"#000000\"]\nopacity = 2.0\n").unwrap();
+        let chrome = file.chrome(dir);
+        assert_eq!(chrome.backdrop, Some(Backdrop::Image(PathBuf::from("settings").join("paper.png"))));
+        assert_eq!(chrome.opacity, 1.0);
+        assert_eq!(chrome.card, None);
+
+        let file = ThemeFile::parse("[background]\ngradient = [\"#ff0000\", \"nope\", \"#0000ff80\"]\n[cards]\nenabled = true\nrounding = 4.0\n").unwrap();
+        let chrome = file.chrome(dir);
+        let blue = egui::Color32::from_rgba_unmultiplied(0, 0, 255, 128);
+        assert_eq!(chrome.backdrop, Some(Backdrop::Gradient(egui::Color32::RED, blue)));
+        assert_eq!(chrome.card, Some(Card { rounding: 4.0, margin: 8.0, shadow: true }));
+
+        assert!(ThemeFile::parse("[cards]\nenabled = \"yes\"\n").is_err());
+    }
+}
@@ -1,4 +1,7 @@
+pub mod custom;
+
 use eframe::egui;
+use std::path::PathBuf;
 
 /// Application theme
 #[derive(Clone, Debug)]
@@ -27,6 +30,37 @@ pub struct Theme {
     pub warning: egui::Color32,
     /// Success color
     pub success: egui::Color32,
+    /// Background and card styling of the editor and preview, from
+    /// `theme.toml`
+    pub chrome: Chrome,
+}
+
+/// Extra styling of the editor and preview panes. The default is the flat
+/// theme background.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Chrome {
+    pub backdrop: Option<Backdrop>,
+    /// How strongly the backdrop shows over the background, 0 to 1
+    pub opacity: f32,
+    /// Draw each pane as a card inset from the window
+    pub card: Option<Card>,
+}
+
+/// What is drawn behind a pane
+#[derive(Clone, Debug, PartialEq)]
+pub enum Backdrop {
+    /// An image covering the pane
+    Image(PathBuf),
+    /// A gradient from the top color to the bottom one
+    Gradient(egui::Color32, egui::Color32),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Card {
+    pub rounding: f32,
+    /// Space around the card
+    pub margin: f32,
+    pub shadow: bool,
 }
 
 impl Theme {
@@ -45,6 +79,7 @@ impl Theme {
             error: egui::Color32::from_rgb(244, 67, 54),
             warning: egui::Color32::from_rgb(255, 152, 0),
             success: egui::Color32::from_rgb(76, 175, 80),
+            chrome: Chrome::default(),
         }
     }
 
@@ -63,12 +98,45 @@ impl Theme {
             error: egui::Color32::from_rgb(211, 47, 47),
             warning: egui::Color32::from_rgb(245, 124, 0),
             success: egui::Color32::from_rgb(56, 142, 60),
+            chrome: Chrome::default(),
+        }
+    }
+
+    /// Whether this is a dark theme, judged by its background
+    pub fn is_dark(&self) -> bool {
+        egui::Rgba::from(self.background).intensity() < 0.5
+    }
+
+    /// This theme blended `t` (0 to 1) of the way towards `other`
+    pub fn lerp(&self, other: &Theme, t: f32) -> Theme {
+        let mix = |from: egui::Color32, to: egui::Color32| from.lerp_to_gamma(to, t);
+        Theme {
+            accent: mix(self.accent, other.accent),
+            background: mix(self.background, other.background),
+            surface: mix(self.surface, other.surface),
+            text: mix(self.text, other.text),
+            text_muted: mix(self.text_muted, other.text_muted),
+            border: mix(self.border, other.border),
+            selection: mix(self.selection, other.selection),
+            code_bg: mix(self.code_bg, other.code_bg),
+            link: mix(self.link, other.link),
+            error: mix(self.error, other.error),
+            warning: mix(self.warning, other.warning),
+            success: mix(self.success, other.success),
+            chrome: other.chrome.clone(),
         }
     }
 
     /// Apply this theme to egui context
     pub fn apply(&self, ctx: &egui::Context) {
-        let mut visuals = egui::Visuals::dark();
+        // Pin egui to one style so it doesn't swap in its own when the
+        // system setting changes
+        let (mut visuals, egui_theme) = if self.is_dark() {
+            (egui::Visuals::dark(), egui::Theme::Dark)
+        } else {
+            (egui::Visuals::light(), egui::Theme::Light)
+        };
+        ctx.set_theme(egui_theme);
 
         visuals.override_text_color = Some(self.text);
         visuals.widgets.inactive.bg_fill = self.surface;
@@ -91,16 +159,21 @@ impl Theme {
         ctx.set_visuals(visuals);
     }
 
-    /// Create a theme from ThemeMode
-    pub fn from_mode(mode: crate::config::ThemeMode) -> Self {
+    /// This theme with the pane styling `chrome`
+    pub fn with_chrome(self, chrome: Chrome) -> Self {
+        Self { chrome, ..self }
+    }
+
+    /// Create a theme from ThemeMode; `system` is the OS setting, when known
+    pub fn from_mode(mode: crate::config::ThemeMode, system: Option<egui::Theme>) -> Self {
         match mode {
             crate::config::ThemeMode::Light => Self::light(),
             crate::config::ThemeMode::Dark => Self::dark(),
-            crate::config::ThemeMode::System => {
-                // Check system preference
-                // For now, default to dark
-                Self::dark()
-            }
+            crate::config::ThemeMode::System => match system {
+                Some(egui::Theme::Light) => Self::light(),
+                // Dark when the system doesn't say
+                Some(egui::Theme::Dark) | None => Self::dark(),
+            },
         }
     }
 }
@@ -110,4 +183,3 @@ impl Default for Theme {
         Self::dark()
     }
 }
-
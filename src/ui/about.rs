@@ -0,0 +1,84 @@
+//! Help > About
+
+use crate::app::RmdApp;
+use crate::config::Config;
+use crate::i18n::t;
+use eframe::egui;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const REPOSITORY: &str = env!("CARGO_PKG_REPOSITORY");
+const LICENSE: &str = env!("CARGO_PKG_LICENSE");
+/// Set by the build script
+const BUILD_DATE: &str = env!("RMD_BUILD_DATE");
+
+impl RmdApp {
+    /// Render the About dialog
+    pub fn ui_about(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_about;
+        let config_path = Config::config_path().ok();
+        let mut failure = None;
+        egui::Window::new(t!("about.title"))
+            .id(egui::Id::new("about"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.heading("RMD");
+                    ui.label(env!("CARGO_PKG_DESCRIPTION"));
+                    ui.label(egui::RichText::new(t!("about.version", version = VERSION, date = BUILD_DATE)).color(self.theme.text_muted));
+                });
+                ui.separator();
+
+                egui::Grid::new("about").num_columns(2).spacing([16.0, 4.0]).show(ui, |ui| {
+                    ui.label(t!("about.source"));
+                    if ui.link(REPOSITORY).clicked() {
+                        if let Err(e) = webbrowser::open(REPOSITORY) {
+                            failure = Some((t!("error.open_failed", path = REPOSITORY), e.to_string()));
+                        }
+                    }
+                    ui.end_row();
+                    ui.label(t!("about.license"));
+                    let license_url = format!("https://spdx.org/licenses/{}.html", LICENSE);
+                    if ui.link(LICENSE).on_hover_text(&license_url).clicked() {
+                        if let Err(e) = webbrowser::open(&license_url) {
+                            failure = Some((t!("error.open_failed", path = license_url), e.to_string()));
+                        }
+                    }
+                    ui.end_row();
+                    ui.label(t!("about.settings"));
+                    match &config_path {
+                        Some(path) => {
+                            ui.horizontal(|ui| {
+                                ui.monospace(path.display().to_string());
+                                if ui.small_button(t!("about.open_config_folder")).clicked() {
+                                    crate::platform::show_in_folder(path);
+                                }
+                            });
+                        }
+                        None => {
+                            ui.label(egui::RichText::new(t!("about.not_saved")).color(self.theme.text_muted));
+                        }
+                    }
+                    ui.end_row();
+                });
+            });
+        if let Some((context, e)) = failure {
+            self.notify_error(context, e);
+        }
+        self.show_about = open;
+    }
+
+    /// Close the topmost Help window, if any, when Escape is pressed
+    pub fn close_help_on_escape(&mut self, ctx: &egui::Context) {
+        if !(self.show_about || self.show_shortcuts) || !ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            return;
+        }
+        if self.show_about {
+            self.show_about = false;
+        } else {
+            self.show_shortcuts = false;
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use crate::app::RmdApp;
+use crate::keymap::{Action, Scope};
+use crate::markdown::flavor::Flavor;
+use crate::markdown::{figures, links, toc};
+use crate::ui::focus::Pane;
+use crate::ui::layouts::LayoutMode;
+use crate::ui::unsaved::Replacement;
+use crate::utils::{timing, transform};
+use eframe::egui;
+
+impl RmdApp {
+    /// Run an action from a shortcut, a menu, or the command palette
+    pub fn run_action(&mut self, ctx: &egui::Context, action: Action) {
+        if self.readonly && action.edits_text() {
+            self.show_readonly_hint();
+            return;
+        }
+
+        match action {
+            Action::NewFile => self.replace_document(Replacement::New),
+            Action::NewWindow => self.open_new_window(),
+            Action::OpenFile => self.open_file_dialog(),
+            Action::OpenFolder => self.open_folder_dialog(),
+            Action::Save => self.save_file(),
+            Action::SaveAs => self.save_file_as(),
+            Action::ToggleReadonly => self.toggle_readonly(),
+            Action::FileHistory => self.open_file_history(),
+            Action::ExportHtml => self.export_html_dialog(),
+            Action::ExportFolderHtml => self.export_folder_dialog(),
+            Action::Find => self.open_find(),
+            Action::PasteFromHistory => self.open_clipboard_picker(ctx),
+            Action::FindInFolder => self.toggle_search_panel(ctx),
+            Action::Preferences => self.show_preferences = true,
+            Action::ToggleSplit => {
+                let mode = if self.layout.mode == LayoutMode::Split {
+                    LayoutMode::EditorOnly
+                } else {
+                    LayoutMode::Split
+                };
+                self.layout.set_mode(mode);
+            }
+            Action::ToggleSidebar => self.show_sidebar = !self.show_sidebar,
+            Action::ToggleChanges => self.show_changes = !self.show_changes,
+            Action::ToggleScratchpad => self.toggle_scratchpad(),
+            Action::ToggleDiagnostics => timing::set_enabled(!timing::is_enabled()),
+            Action::ShowShortcuts => self.show_shortcuts = true,
+            Action::CommandPalette => self.palette.open(),
+            Action::QuickOpen => self.switcher.open(),
+            Action::GoToHeading => self.heading_picker.open(self.preview.scroll_offset),
+            Action::CheckLinks => self.check_links(),
+            Action::SplitDocument => self.open_split_dialog(),
+            Action::MergeFiles => self.open_merge_dialog(),
+            Action::ConsolidateImages => self.open_consolidate_dialog(),
+            Action::CheckForUpdates => self.check_for_updates(true),
+            Action::PinPreview => self.pin_preview_dialog(),
+            Action::OpenInBrowser => self.open_in_browser(),
+            Action::FocusEditor => self.focus_pane(ctx, Pane::Editor),
+            Action::FocusPreview => self.focus_pane(ctx, Pane::Preview),
+            Action::FocusFiles => self.focus_pane(ctx, Pane::Files),
+            Action::FocusOutline => self.focus_pane(ctx, Pane::Outline),
+            Action::FocusBookmarks => self.focus_pane(ctx, Pane::Bookmarks),
+            Action::FocusNotifications => self.focus_pane(ctx, Pane::Notifications),
+            Action::FocusSearch => self.focus_pane(ctx, Pane::Search),
+            Action::FocusProblems => self.focus_pane(ctx, Pane::Problems),
+            Action::FocusScratchpad => self.focus_pane(ctx, Pane::Scratchpad),
+            Action::FocusNextPane => self.cycle_pane_focus(ctx, false),
+            Action::FocusPreviousPane => self.cycle_pane_focus(ctx, true),
+            Action::ToggleTheme => self.toggle_theme(),
+            Action::ExportSelectionHtml => self.export_selection_dialog(),
+            Action::ExportDocx => self.export_docx_dialog(),
+            Action::CopySection => self.copy_section(ctx),
+            Action::InsertImage => self.insert_image_dialog(),
+            Action::ToggleBookmark => self.toggle_bookmark(),
+            Action::NextBookmark => {
+                self.editor.next_bookmark(false);
+            }
+            Action::PreviousBookmark => {
+                self.editor.next_bookmark(true);
+            }
+            Action::SelectWordOrNext => {
+                self.editor.select_word_or_next();
+            }
+            Action::SelectLine => {
+                self.editor.select_line();
+            }
+            Action::ExpandSelection => {
+                self.editor.expand_selection();
+            }
+            _ => self.run_edit_action(action),
+        }
+    }
+
+    /// Actions that change the document text
+    fn run_edit_action(&mut self, action: Action) {
+        let changed = match action {
+            Action::Undo => {
+                let can_undo = self.editor.can_undo();
+                self.editor.undo();
+                can_undo
+            }
+            Action::Redo => {
+                let can_redo = self.editor.can_redo();
+                self.editor.redo();
+                can_redo
+            }
+            Action::Bold => {
+                self.editor.toggle_inline_marker("**");
+                true
+            }
+            Action::Italic => {
+                self.editor.toggle_inline_marker("*");
+                true
+            }
+            Action::MoveLineUp => self.editor.move_lines(true),
+            Action::MoveLineDown => self.editor.move_lines(false),
+            Action::DuplicateLineUp => {
+                self.editor.duplicate_lines(true);
+                true
+            }
+            Action::DuplicateLineDown => {
+                self.editor.duplicate_lines(false);
+                true
+            }
+            Action::DeleteLines => self.editor.delete_lines(),
+            Action::PromoteHeading => self.editor.shift_headings(true),
+            Action::DemoteHeading => self.editor.shift_headings(false),
+            Action::InsertToc => self.insert_toc(),
+            Action::UpdateToc => self.update_toc(),
+            Action::RenameHeading => {
+                self.rename_heading_at_caret();
+                false
+            }
+            Action::InsertFigureList => self.insert_figure_list(),
+            Action::UpdateFigureList => self.update_figure_list(),
+            Action::TransformUpper => self.editor.transform_selection(transform::to_upper, false),
+            Action::TransformLower => self.editor.transform_selection(transform::to_lower, false),
+            Action::TransformTitleCase => self.editor.transform_selection(transform::to_title_case, false),
+            Action::SortLinesAscending => self.editor.transform_selection(transform::sort_lines_ascending, true),
+            Action::SortLinesDescending => self.editor.transform_selection(transform::sort_lines_descending, true),
+            Action::RemoveDuplicateLines => self.editor.transform_selection(transform::remove_duplicate_lines, true),
+            Action::JoinLines => self.editor.transform_selection(transform::join_lines, true),
+            Action::ToggleComment => self.editor.transform_selection(transform::toggle_comment, false),
+            Action::LinksToReferences => self.rewrite_links(links::to_references),
+            Action::InlineReferenceLinks => self.rewrite_links(links::to_inline),
+            Action::HardWrap => self.editor.reflow_selection(Some(self.wrap_column())),
+            Action::UnwrapParagraph => self.editor.reflow_selection(None),
+            Action::FormatTable => self.editor.format_table(),
+            Action::RenumberLists => self.editor.renumber_lists(),
+            _ => false,
+        };
+        if changed {
+            self.has_unsaved_changes = true;
+            self.autocomplete.close();
+        }
+    }
+
+    /// Insert a table of contents at the start of the caret's line, or
+    /// refresh the existing one rather than adding a second
+    fn insert_toc(&mut self) -> bool {
+        let text = self.editor.text();
+        if toc::find_toc(&text).is_some() {
+            return self.update_toc();
+        }
+        let cursor = self.editor.cursor().min(text.len());
+        let line_start = text[..cursor].rfind('\n').map_or(0, |i| i + 1);
+        let mut block = toc::toc_block(&text, self.config.writing.toc_depth);
+        if !text[line_start..].is_empty() {
+            block.push('\n');
+        }
+        self.editor.replace_range(line_start..line_start, &block);
+        true
+    }
+
+    /// Insert a list of figures at the start of the caret's line, or
+    /// refresh the existing one rather than adding a second
+    fn insert_figure_list(&mut self) -> bool {
+        let text = self.editor.text();
+        if figures::find_figure_list(&text).is_some() {
+            return self.update_figure_list();
+        }
+        let cursor = self.editor.cursor().min(text.len());
+        let line_start = text[..cursor].rfind('\n').map_or(0, |i| i + 1);
+        let mut block = figures::figure_list_block(&text);
+        if !text[line_start..].is_empty() {
+            block.push('\n');
+        }
+        self.editor.replace_range(line_start..line_start, &block);
+        true
+    }
+
+    /// Rewrite the document's links with `rewrite` as one undoable edit
+    fn rewrite_links(&mut self, rewrite: fn(&str, Flavor) -> Option<String>) -> bool {
+        let text = self.editor.text();
+        match rewrite(&text, self.markdown_renderer.flavor_for(&text)) {
+            Some(new_text) => {
+                self.editor.replace_text(&new_text);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Column to hard-wrap at: the guide's, or 80 while the guide is off
+    fn wrap_column(&self) -> usize {
+        match self.config.editor.ruler_column {
+            0 => 80,
+            column => column,
+        }
+    }
+
+    /// Regenerate the table of contents between its markers as one undoable edit
+    pub fn update_toc(&mut self) -> bool {
+        let text = self.editor.text();
+        match toc::update_toc(&text, self.config.writing.toc_depth) {
+            Some((range, list)) => {
+                self.editor.replace_range_in_place(range, &list);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Regenerate the list of figures between its markers as one undoable edit
+    pub fn update_figure_list(&mut self) -> bool {
+        let text = self.editor.text();
+        match figures::update_figure_list(&text) {
+            Some((range, list)) => {
+                self.editor.replace_range_in_place(range, &list);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Handle shortcuts that work regardless of which widget has focus
+    pub fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        // Let the Preferences dialog capture the chord being rebound
+        if self.rebinding.is_some() {
+            return;
+        }
+        if let Some(action) = ctx.input_mut(|i| self.keymap.consume(i, Scope::Global)) {
+            self.run_action(ctx, action);
+        }
+    }
+
+    /// Handle editing shortcuts before the text widget sees the keys
+    pub fn handle_editor_shortcuts(&mut self, ui: &mut egui::Ui) {
+        if self.rebinding.is_some() {
+            return;
+        }
+        if let Some(action) = ui.input_mut(|i| self.keymap.consume(i, Scope::Editor)) {
+            self.run_action(ui.ctx(), action);
+        }
+    }
+
+    /// Menu entry for `action` showing its current shortcut. Returns whether it was clicked.
+    pub fn action_button(&mut self, ui: &mut egui::Ui, action: Action, enabled: bool) -> bool {
+        let button = egui::Button::new(action.label()).shortcut_text(self.keymap.shortcut_text(action));
+        let clicked = ui.add_enabled(enabled, button).clicked();
+        if clicked {
+            ui.close_menu();
+            let ctx = ui.ctx().clone();
+            self.run_action(&ctx, action);
+        }
+        clicked
+    }
+}
@@ -0,0 +1,47 @@
+//! GitHub alerts in the preview: a card tinted with the alert's color, with
+//! a bar down its left edge and the alert's icon and title above its content
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::alerts::AlertKind;
+use crate::markdown::RenderedElement;
+use eframe::egui;
+
+impl RmdApp {
+    fn alert_color(&self, kind: AlertKind) -> egui::Color32 {
+        match kind {
+            AlertKind::Note => self.theme.link,
+            AlertKind::Tip => self.theme.success,
+            AlertKind::Important => self.theme.accent,
+            AlertKind::Warning => self.theme.warning,
+            AlertKind::Caution => self.theme.error,
+        }
+    }
+
+    pub(super) fn render_alert(&mut self, ui: &mut egui::Ui, kind: AlertKind, items: &[RenderedElement], source: &str) {
+        let color = self.alert_color(kind);
+        let title = match kind {
+            AlertKind::Note => t!("alert.note"),
+            AlertKind::Tip => t!("alert.tip"),
+            AlertKind::Important => t!("alert.important"),
+            AlertKind::Warning => t!("alert.warning"),
+            AlertKind::Caution => t!("alert.caution"),
+        };
+        let zoom = self.preview.zoom;
+        ui.add_space(8.0 * zoom);
+        let frame = egui::Frame::none()
+            .fill(color.gamma_multiply(0.12))
+            .inner_margin(egui::Margin { left: 16.0, right: 12.0, top: 10.0, bottom: 10.0 } * zoom)
+            .rounding(4.0 * zoom)
+            .show(ui, |ui| {
+                ui.label(egui::RichText::new(format!("{} {}", kind.icon(), title)).size(14.0 * zoom).strong().color(color));
+                for item in items {
+                    self.render_element(ui, item, source);
+                }
+            });
+        let rect = frame.response.rect;
+        let bar = egui::Rect::from_min_max(rect.left_top(), egui::pos2(rect.left() + 4.0 * zoom, rect.bottom()));
+        ui.painter().rect_filled(bar, egui::Rounding { nw: 4.0 * zoom, sw: 4.0 * zoom, ..Default::default() }, color);
+        ui.add_space(8.0 * zoom);
+    }
+}
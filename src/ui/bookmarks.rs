@@ -0,0 +1,100 @@
+//! Bookmark markers in the editor gutter, the sidebar list, and keeping
+//! bookmarks with the document between sessions
+
+use crate::app::RmdApp;
+use crate::history::meta::{DocumentMeta, MetaStore};
+use crate::i18n::t;
+use crate::ui::focus::Pane;
+use crate::utils;
+use eframe::egui;
+use std::sync::Arc;
+
+/// Space left of the text for bookmark markers
+pub const GUTTER_WIDTH: f32 = 14.0;
+
+/// Line text shown per bookmark in the sidebar
+const MAX_LABEL_CHARS: usize = 40;
+
+impl RmdApp {
+    /// Bookmark (or un-bookmark) the caret line and remember it for the file
+    pub fn toggle_bookmark(&mut self) {
+        self.editor.toggle_bookmark();
+        self.save_bookmarks();
+    }
+
+    /// Write the bookmarks to the current file's sidecar
+    pub fn save_bookmarks(&mut self) {
+        let Some(path) = &self.current_file else {
+            return;
+        };
+        let bookmarks = self.editor.bookmarks().to_vec();
+        let saved = MetaStore::open_default().and_then(|store| {
+            let meta = DocumentMeta { bookmarks, ..store.load(path) };
+            store.save(path, &meta)
+        });
+        if let Err(e) = saved {
+            log::warn!("Failed to save bookmarks for {}: {}", path.display(), e);
+        }
+    }
+
+    /// Draw a marker beside each bookmarked line of the laid-out text
+    pub fn paint_bookmarks(&self, ui: &egui::Ui, text: &str, galley: &Arc<egui::Galley>, galley_pos: egui::Pos2) {
+        let mut bookmarks = self.editor.bookmarks().iter().peekable();
+        if bookmarks.peek().is_none() {
+            return;
+        }
+        let line_starts = std::iter::once(0).chain(text.match_indices('\n').map(|(i, _)| i + 1));
+        for (line, start) in line_starts.enumerate() {
+            if bookmarks.next_if(|&&b| b == line).is_none() {
+                continue;
+            }
+            let cursor = egui::text::CCursor::new(utils::byte_to_char(text, start));
+            let row = galley.pos_from_ccursor(cursor).translate(galley_pos.to_vec2());
+            let center = egui::pos2(galley_pos.x - GUTTER_WIDTH / 2.0, row.center().y);
+            ui.painter().circle_filled(center, 3.5, self.theme.accent);
+            if bookmarks.peek().is_none() {
+                break;
+            }
+        }
+    }
+
+    /// List the bookmarks of the open document, returning the line clicked
+    pub fn ui_bookmarks(&mut self, ui: &mut egui::Ui) -> Option<usize> {
+        // Ctrl+5 opens the section at its first bookmark
+        let mut focus = self.pane_focus.take(Pane::Bookmarks);
+        let bookmarks = self.editor.bookmarks();
+        let text = if bookmarks.is_empty() { String::new() } else { self.editor.text() };
+        let lines: Vec<&str> = text.lines().collect();
+
+        let mut jump = None;
+        let section = egui::CollapsingHeader::new(t!("sidebar.bookmarks", count = bookmarks.len()))
+            .default_open(true)
+            .open((focus && !bookmarks.is_empty()).then_some(true))
+            .show(ui, |ui| {
+                if bookmarks.is_empty() {
+                    ui.label(egui::RichText::new(t!("sidebar.no_bookmarks")).color(self.theme.text_muted));
+                }
+                for &line in bookmarks {
+                    let text = lines.get(line).map_or("", |text| text.trim());
+                    let mut label: String = text.chars().take(MAX_LABEL_CHARS).collect();
+                    if label.len() < text.len() {
+                        label.push('…');
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label(egui::RichText::new(format!("{:>4}", line + 1)).color(self.theme.text_muted));
+                        let link = ui.link(label);
+                        if std::mem::take(&mut focus) {
+                            link.request_focus();
+                        }
+                        if link.clicked() {
+                            jump = Some(line);
+                        }
+                    });
+                }
+            });
+        if focus {
+            section.header_response.request_focus();
+        }
+        jump
+    }
+}
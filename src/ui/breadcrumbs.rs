@@ -0,0 +1,81 @@
+use crate::app::RmdApp;
+use crate::editor::Editor;
+use crate::i18n::t;
+use crate::markdown::outline;
+use crate::ui::layouts::LayoutMode;
+use eframe::egui;
+
+/// Heading path of the caret's location, e.g. "Architecture ▸ Storage"
+#[derive(Default)]
+pub struct Breadcrumbs {
+    /// Caret line and text revision the path was computed for
+    computed_for: Option<(usize, u64)>,
+    /// Indices into the editor's headings, outermost first
+    path: Vec<usize>,
+}
+
+impl Breadcrumbs {
+    /// Recompute the path if the caret moved to another line or the text changed
+    pub fn update(&mut self, editor: &mut Editor) -> &[usize] {
+        let key = (editor.cursor_position().0, editor.revision());
+        if self.computed_for != Some(key) {
+            self.computed_for = Some(key);
+            let cursor = editor.cursor();
+            self.path = outline::heading_path(editor.headings(), cursor);
+        }
+        &self.path
+    }
+}
+
+impl RmdApp {
+    /// Render the breadcrumb strip between the toolbar and the editor
+    pub fn ui_breadcrumbs(&mut self, ctx: &egui::Context) {
+        if self.layout.mode == LayoutMode::PreviewOnly {
+            return;
+        }
+        let path = self.breadcrumbs.update(&mut self.editor).to_vec();
+        let headings = self.editor.headings();
+        if headings.is_empty() {
+            return;
+        }
+
+        let muted = self.theme.text_muted;
+        let mut jump_to = None;
+        egui::TopBottomPanel::top("breadcrumbs")
+            .exact_height(22.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if path.is_empty() {
+                        ui.label(egui::RichText::new(t!("outline.top_of_document")).color(muted));
+                    }
+                    for (depth, &index) in path.iter().enumerate() {
+                        if depth > 0 {
+                            ui.label(egui::RichText::new("▸").color(muted));
+                        }
+                        let heading = &headings[index];
+                        if depth + 1 < path.len() {
+                            if ui.link(&heading.text).clicked() {
+                                jump_to = Some(heading.offset);
+                            }
+                            continue;
+                        }
+
+                        // The last crumb lists its siblings for quick navigation
+                        ui.menu_button(&heading.text, |ui| {
+                            for sibling in outline::siblings(headings, index) {
+                                let entry = &headings[sibling];
+                                if ui.selectable_label(sibling == index, &entry.text).clicked() {
+                                    jump_to = Some(entry.offset);
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    }
+                });
+            });
+
+        if let Some(offset) = jump_to {
+            self.editor.set_cursor(offset);
+        }
+    }
+}
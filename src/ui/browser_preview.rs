@@ -0,0 +1,211 @@
+//! View → Open Preview in Browser: the document exported as a page in a
+//! temporary folder and opened in the default browser, to check what the
+//! HTML export will look like. The page is rewritten on every save, or as
+//! the document changes with live reload on.
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::embed;
+use crate::markdown::export::{self, HtmlOptions, Stylesheet};
+use crate::markdown::include;
+use crate::ui::toasts::Severity;
+use eframe::egui;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long typing pauses before a live page is rewritten
+const LIVE_DELAY: Duration = Duration::from_millis(500);
+
+/// Checks a script file for the time the page was last written, and
+/// reloads when it is newer than the time in the page's meta tag. A script
+/// tag, unlike `fetch`, may load a `file:` URL.
+const LIVE_RELOAD_SCRIPT: &str = "<script>(function () {\
+var written = document.querySelector('meta[name=\"rmd-updated\"]').content;\
+window.rmdUpdated = function (latest) { if (latest !== written) { location.reload(); } };\
+setInterval(function () {\
+var check = document.createElement('script');\
+check.src = 'STAMP_FILE?' + Date.now();\
+check.onload = check.onerror = function () { check.remove(); };\
+document.head.appendChild(check);\
+}, 1000);\
+})();</script>";
+
+/// The page written for the document, while it stays open
+struct Page {
+    document: Option<PathBuf>,
+    path: PathBuf,
+    /// Editor revision the page was written from
+    revision: u64,
+    /// When the editor first differed from the page, for live reload
+    stale_since: Option<Instant>,
+}
+
+/// Pages written this session, in a folder removed on exit
+#[derive(Default)]
+pub struct BrowserPreview {
+    page: Option<Page>,
+}
+
+impl BrowserPreview {
+    /// Folder of this session's pages
+    fn folder() -> PathBuf {
+        std::env::temp_dir().join(format!("rmd-preview-{}", std::process::id()))
+    }
+
+    /// Remove the pages written this session
+    pub fn clean_up(&mut self) {
+        self.page = None;
+        let folder = Self::folder();
+        if folder.exists() {
+            if let Err(e) = std::fs::remove_dir_all(&folder) {
+                log::warn!("Failed to remove {}: {}", folder.display(), e);
+            }
+        }
+    }
+}
+
+/// `page` with a meta tag holding `stamp` and the script that reloads it
+/// once `stamp_file` holds a different one
+fn with_live_reload(page: &str, stamp: &str, stamp_file: &str) -> String {
+    let head = format!(
+        "<meta name=\"rmd-updated\" content=\"{}\">\n{}\n</head>",
+        stamp,
+        LIVE_RELOAD_SCRIPT.replace("STAMP_FILE", &stamp_file.replace(' ', "%20"))
+    );
+    page.replacen("</head>", &head, 1)
+}
+
+/// A `file:` URL for `path`, so the page finds images next to the document
+fn file_url(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/").replace(' ', "%20");
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        format!("file:///{}", path)
+    }
+}
+
+impl RmdApp {
+    /// Write the document's page and open it in the browser
+    pub fn open_in_browser(&mut self) {
+        let path = BrowserPreview::folder().join(format!("{}.html", crate::utils::sanitize_filename(&self.page_title())));
+        self.browser_preview.page = Some(Page {
+            document: self.current_file.clone(),
+            path: path.clone(),
+            revision: self.editor.revision(),
+            stale_since: None,
+        });
+        if let Err(e) = self.write_browser_page() {
+            self.browser_preview.page = None;
+            self.notify_error(t!("error.write_preview_page"), format!("{:#}", e));
+            return;
+        }
+        if let Err(e) = webbrowser::open(&path.to_string_lossy()) {
+            self.notify_error(t!("error.open_browser"), e);
+        }
+    }
+
+    /// Rewrite the page after the document was saved
+    pub(crate) fn refresh_browser_page(&mut self) {
+        if !self.has_browser_page() {
+            return;
+        }
+        if let Err(e) = self.write_browser_page() {
+            self.notify(Severity::Warning, t!("toast.preview_page_not_updated", error = format!("{:#}", e)));
+        }
+    }
+
+    /// With live reload on, rewrite the page once typing pauses
+    pub fn update_browser_preview(&mut self, ctx: &egui::Context) {
+        if !self.config.preview.browser_live_reload || !self.has_browser_page() {
+            return;
+        }
+        let revision = self.editor.revision();
+        let Some(page) = self.browser_preview.page.as_mut() else {
+            return;
+        };
+        if page.revision == revision {
+            page.stale_since = None;
+            return;
+        }
+        let since = *page.stale_since.get_or_insert_with(Instant::now);
+        if since.elapsed() < LIVE_DELAY {
+            ctx.request_repaint_after(LIVE_DELAY);
+            return;
+        }
+        if let Err(e) = self.write_browser_page() {
+            log::warn!("Failed to update the preview page: {:#}", e);
+        }
+    }
+
+    /// Whether a page was opened for the document that is open now
+    fn has_browser_page(&self) -> bool {
+        self.browser_preview.page.as_ref().is_some_and(|page| page.document == self.current_file)
+    }
+
+    /// Title for the page: the first heading, or else the file name
+    fn page_title(&self) -> String {
+        let text = self.editor.text();
+        crate::markdown::outline::headings(&text)
+            .first()
+            .map(|heading| heading.text.clone())
+            .or_else(|| Some(self.current_file.as_deref()?.file_stem()?.to_string_lossy().to_string()))
+            .unwrap_or_else(|| "Untitled".to_string())
+    }
+
+    /// Export the document into its page as the HTML export would, but
+    /// with relative URLs made absolute, since the page is elsewhere
+    fn write_browser_page(&mut self) -> anyhow::Result<()> {
+        let css = self.export_css()?;
+        let text = self.editor.text();
+        let flavor = self.markdown_renderer.flavor_for(&text);
+        let markdown = include::expand(&text, self.current_file.as_deref(), self.markdown_renderer.wiki_links_for(flavor));
+        let folder = self.current_file.as_deref().and_then(Path::parent);
+        let options = HtmlOptions {
+            flavor,
+            number_headings: self.config.preview.number_headings,
+            toc_depth: self.config.export.include_toc.then_some(self.config.writing.toc_depth),
+        };
+        let body = export::markdown_to_html(&markdown, options, |_, url| match embed::resolve(url, folder) {
+            Some(path) => format!("{}{}", file_url(&path), url.find('#').map_or("", |at| &url[at..])),
+            None => url.to_string(),
+        });
+        let title = self.page_title();
+        let mut page = export::html_document(&title, self.document_language(), Stylesheet::Inline(&css), &body);
+
+        let Some(target) = self.browser_preview.page.as_mut() else {
+            return Ok(());
+        };
+        std::fs::create_dir_all(BrowserPreview::folder())?;
+        if self.config.preview.browser_live_reload {
+            let stamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis().to_string();
+            let stamp_path = target.path.with_extension("js");
+            let stamp_file = stamp_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            page = with_live_reload(&page, &stamp, &stamp_file);
+            std::fs::write(&target.path, page)?;
+            std::fs::write(&stamp_path, format!("rmdUpdated(\"{}\");\n", stamp))?;
+        } else {
+            std::fs::write(&target.path, page)?;
+        }
+        target.revision = self.editor.revision();
+        target.stale_since = None;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_live_reload_and_urls() {
+        let page = export::html_document("Notes", None, Stylesheet::default(), "<p>Hi</p>\n");
+        let live = with_live_reload(&page, "42", "My Notes.js");
+        assert!(live.contains("<meta name=\"rmd-updated\" content=\"42\">\n<script>"));
+        assert!(live.contains("check.src = 'My%20Notes.js?' + Date.now();"));
+        assert!(live.find("</script>\n</head>").is_some_and(|at| at < live.find("<body>").unwrap()));
+
+        assert_eq!(file_url(Path::new("/home/me/my notes/a.png")), "file:///home/me/my%20notes/a.png");
+        assert_eq!(file_url(Path::new(r"C:\notes\a.png")), "file:///C:/notes/a.png");
+    }
+}
@@ -0,0 +1,326 @@
+use crate::app::RmdApp;
+use crate::editor::diff::{self, ChangeKind, DiffRow, Hunk};
+use crate::i18n::t;
+use crate::ui::bookmarks;
+use crate::ui::widgets::ClickAway;
+use crate::utils;
+use eframe::egui;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Wait this long after the last edit before recomputing the diff
+const DIFF_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Unchanged runs longer than this are collapsed into a single row
+const MAX_VISIBLE_UNCHANGED: usize = 3;
+
+/// Width of the change bars at the left edge of the editor gutter
+const MARKER_WIDTH: f32 = 3.0;
+
+/// Diff between the saved text and the buffer, recomputed lazily
+#[derive(Default)]
+pub struct ChangesView {
+    rows: Vec<DiffRow>,
+    /// Editor revision the rows were computed for
+    computed_for: Option<u64>,
+    /// When a newer revision was first seen
+    stale_since: Option<Instant>,
+    /// New-text line numbers of collapsed runs the user expanded
+    expanded: HashSet<usize>,
+    /// Hunk whose gutter marker was clicked, with where to show it
+    open_hunk: Option<OpenHunk>,
+}
+
+struct OpenHunk {
+    hunk: Hunk,
+    pos: egui::Pos2,
+    /// Editor revision it was opened at; any edit closes it
+    revision: u64,
+    click_away: ClickAway,
+}
+
+impl ChangesView {
+    /// Recompute the diff if the buffer changed and has been idle long enough.
+    /// Returns true while a recompute is still waiting on the debounce.
+    pub fn update(&mut self, revision: u64, saved: &str, text: impl FnOnce() -> String) -> bool {
+        if self.computed_for == Some(revision) {
+            self.stale_since = None;
+            return false;
+        }
+        let since = *self.stale_since.get_or_insert_with(Instant::now);
+        if self.computed_for.is_some() && since.elapsed() < DIFF_DEBOUNCE {
+            return true;
+        }
+
+        // A document that was never saved is all additions
+        self.rows = diff::diff_lines(saved, &text());
+        self.computed_for = Some(revision);
+        self.stale_since = None;
+        false
+    }
+
+    /// Force a recompute on the next update, e.g. after saving
+    pub fn invalidate(&mut self) {
+        self.computed_for = None;
+        self.open_hunk = None;
+    }
+
+    /// Whether the rows are of the buffer at `revision`, rather than of
+    /// text from before the last few keystrokes
+    fn is_current(&self, revision: u64) -> bool {
+        self.computed_for == Some(revision)
+    }
+
+    fn hunks(&self) -> impl Iterator<Item = &Hunk> {
+        self.rows.iter().filter_map(|row| match row {
+            DiffRow::Changed(hunk) => Some(hunk),
+            DiffRow::Unchanged { .. } => None,
+        })
+    }
+}
+
+impl RmdApp {
+    /// Bring the diff up to date with the buffer, once typing pauses
+    fn update_changes(&mut self, ctx: &egui::Context) {
+        let revision = self.editor.revision();
+        let editor = &self.editor;
+        if self.changes.update(revision, editor.saved_text(), || editor.text()) {
+            ctx.request_repaint_after(DIFF_DEBOUNCE);
+        }
+    }
+
+    /// Render the changes view: removed lines left, added lines right
+    pub fn render_changes(&mut self, ui: &mut egui::Ui) {
+        self.update_changes(ui.ctx());
+
+        let removed_fill = self.theme.error.gamma_multiply(0.15);
+        let added_fill = self.theme.success.gamma_multiply(0.15);
+        let muted = self.theme.text_muted;
+        let mut jump_to = None;
+
+        egui::CentralPanel::default()
+            .frame(egui::Frame::central_panel(ui.style()).inner_margin(8.0))
+            .show_inside(ui, |ui| {
+                let changes = &mut self.changes;
+                if changes.rows.iter().all(|row| matches!(row, DiffRow::Unchanged { .. })) {
+                    ui.label(egui::RichText::new(t!("changes.none")).color(muted));
+                    return;
+                }
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for row in &changes.rows {
+                            match row {
+                                DiffRow::Unchanged { old_start, new_start, lines } => {
+                                    let collapsed = lines.len() > MAX_VISIBLE_UNCHANGED
+                                        && !changes.expanded.contains(new_start);
+                                    if collapsed {
+                                        let label = t!("changes.unchanged_lines", count = lines.len());
+                                        if ui.link(egui::RichText::new(label).color(muted)).clicked() {
+                                            changes.expanded.insert(*new_start);
+                                        }
+                                        continue;
+                                    }
+                                    for (i, line) in lines.iter().enumerate() {
+                                        ui.columns(2, |columns| {
+                                            diff_line(&mut columns[0], old_start + i, line, None);
+                                            diff_line(&mut columns[1], new_start + i, line, None);
+                                        });
+                                    }
+                                }
+                                DiffRow::Changed(hunk) => {
+                                    let response = ui
+                                        .scope(|ui| show_hunk(ui, hunk, removed_fill, added_fill))
+                                        .response
+                                        .interact(egui::Sense::click())
+                                        .on_hover_cursor(egui::CursorIcon::PointingHand);
+                                    if response.clicked() {
+                                        jump_to = Some(hunk.new_start);
+                                    }
+                                }
+                            }
+                        }
+                    });
+            });
+
+        if let Some(line) = jump_to {
+            self.editor.go_to_line(line);
+        }
+    }
+
+    /// Mark changed lines in the editor gutter: a bar beside added and
+    /// modified lines, a triangle where lines were deleted. Clicking a
+    /// marker shows what the lines were. Nothing is shown while the diff
+    /// waits to catch up with typing, as its line numbers are out of date.
+    pub fn paint_change_markers(&mut self, ui: &egui::Ui, text: &str, galley: &Arc<egui::Galley>, galley_pos: egui::Pos2) {
+        self.update_changes(ui.ctx());
+        let revision = self.editor.revision();
+        if !self.changes.is_current(revision) {
+            return;
+        }
+        let clip = ui.clip_rect();
+        let x = galley_pos.x - bookmarks::GUTTER_WIDTH - MARKER_WIDTH;
+        let line_rect = |line: usize| {
+            let range = diff::line_byte_range(text, line..line + 1);
+            let end = text[range.clone()].strip_suffix('\n').map_or(range.end, |line| range.start + line.len());
+            let pos = |byte: usize| {
+                let cursor = egui::text::CCursor::new(utils::byte_to_char(text, byte));
+                galley.pos_from_ccursor(cursor).translate(galley_pos.to_vec2())
+            };
+            pos(range.start).union(pos(end))
+        };
+
+        let mut clicked = None;
+        for (index, hunk) in self.changes.hunks().enumerate() {
+            let kind = hunk.kind();
+            let lines = hunk.new_lines();
+            let rect = if kind == ChangeKind::Deleted {
+                let top = line_rect(lines.start).top();
+                egui::Rect::from_min_max(egui::pos2(x, top - 4.0), egui::pos2(x + 2.0 * MARKER_WIDTH, top + 4.0))
+            } else {
+                let y = line_rect(lines.start).top()..=line_rect(lines.end - 1).bottom();
+                egui::Rect::from_x_y_ranges(x..=x + MARKER_WIDTH, y)
+            };
+            if !rect.intersects(clip) {
+                continue;
+            }
+            let shape = match kind {
+                ChangeKind::Added => egui::Shape::rect_filled(rect, 0.0, self.theme.success),
+                ChangeKind::Modified => egui::Shape::rect_filled(rect, 0.0, self.theme.accent),
+                ChangeKind::Deleted => {
+                    let points = vec![rect.left_top(), rect.right_center(), rect.left_bottom()];
+                    egui::Shape::convex_polygon(points, self.theme.error, egui::Stroke::NONE)
+                }
+            };
+            ui.painter().add(shape);
+            // A little wider than drawn, to be easy to hit
+            let target = rect.expand2(egui::vec2(2.0, 0.0));
+            let response = ui
+                .interact(target, ui.id().with(("change_marker", index)), egui::Sense::click())
+                .on_hover_cursor(egui::CursorIcon::PointingHand);
+            if response.clicked() {
+                clicked = Some((hunk.clone(), egui::pos2(rect.right() + 4.0, rect.top())));
+            }
+        }
+        if let Some((hunk, pos)) = clicked {
+            self.changes.open_hunk = Some(OpenHunk { hunk, pos, revision, click_away: ClickAway::opened() });
+        }
+    }
+
+    /// What the lines of a clicked change marker were, with buttons to put
+    /// them back or copy them
+    pub fn ui_change_popup(&mut self, ctx: &egui::Context) {
+        let Some(open) = &mut self.changes.open_hunk else {
+            return;
+        };
+        if open.revision != self.editor.revision() {
+            self.changes.open_hunk = None;
+            return;
+        }
+        let mut click_away = std::mem::take(&mut open.click_away);
+        let hunk = open.hunk.clone();
+        let removed_fill = self.theme.error.gamma_multiply(0.3);
+        let muted = self.theme.text_muted;
+
+        let mut revert = false;
+        let mut copy = false;
+        let area = egui::Area::new(egui::Id::new("change_popup"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(open.pos)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_max_width(480.0);
+                    if hunk.kind() == ChangeKind::Added {
+                        ui.label(egui::RichText::new(t!("changes.added")).color(muted));
+                    } else {
+                        // Words of the old lines that are gone are highlighted
+                        let original = hunk.original_text();
+                        let mut job = egui::text::LayoutJob::default();
+                        let font = egui::TextStyle::Monospace.resolve(ui.style());
+                        let color = ui.visuals().text_color();
+                        for (text, removed) in diff::removed_words(&original, &hunk.added.join("\n")) {
+                            let format = egui::TextFormat {
+                                font_id: font.clone(),
+                                color,
+                                background: if removed { removed_fill } else { egui::Color32::TRANSPARENT },
+                                ..Default::default()
+                            };
+                            job.append(&text, 0.0, format);
+                        }
+                        egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| ui.label(job));
+                    }
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        revert = ui.button(t!("changes.revert")).clicked();
+                        if hunk.kind() != ChangeKind::Added {
+                            copy = ui.button(t!("changes.copy_original")).clicked();
+                        }
+                    });
+                });
+            });
+
+        if copy {
+            ctx.copy_text(hunk.original_text());
+        }
+        if revert {
+            self.revert_hunk(&hunk);
+        }
+        if revert || copy || click_away.clicked(&area.response) {
+            self.changes.open_hunk = None;
+        }
+    }
+
+    /// Put back the saved lines of `hunk` as one undoable edit
+    fn revert_hunk(&mut self, hunk: &Hunk) {
+        if self.readonly {
+            self.show_readonly_hint();
+            return;
+        }
+        // Line numbers of an older diff would replace the wrong lines
+        if !self.changes.is_current(self.editor.revision()) {
+            return;
+        }
+        let text = self.editor.text();
+        let (range, original) = diff::revert_hunk(self.editor.saved_text(), &text, hunk);
+        self.editor.replace_range_in_place(range, &original);
+        self.has_unsaved_changes = self.editor.text() != self.editor.saved_text();
+    }
+}
+
+fn show_hunk(ui: &mut egui::Ui, hunk: &Hunk, removed_fill: egui::Color32, added_fill: egui::Color32) {
+    ui.columns(2, |columns| {
+        for (i, line) in hunk.removed.iter().enumerate() {
+            diff_line(&mut columns[0], hunk.old_start + i, line, Some(removed_fill));
+        }
+        for (i, line) in hunk.added.iter().enumerate() {
+            diff_line(&mut columns[1], hunk.new_start + i, line, Some(added_fill));
+        }
+    });
+}
+
+fn diff_line(ui: &mut egui::Ui, line: usize, text: &str, fill: Option<egui::Color32>) {
+    egui::Frame::none()
+        .fill(fill.unwrap_or(egui::Color32::TRANSPARENT))
+        .show(ui, |ui| {
+            ui.set_width(ui.available_width());
+            ui.label(egui::RichText::new(format!("{:>4}  {}", line + 1, text)).monospace());
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stale_until_recomputed() {
+        let mut changes = ChangesView::default();
+        assert!(!changes.update(1, "a\n", || "a\nb\n".to_string()));
+        assert!(changes.is_current(1));
+        assert_eq!(changes.hunks().count(), 1);
+        // Typing again waits on the debounce, and the old hunks are not current
+        assert!(changes.update(2, "a\n", || "x\na\nb\n".to_string()));
+        assert!(!changes.is_current(2));
+    }
+}
@@ -0,0 +1,116 @@
+//! The backdrop and card styling of the editor and preview panes, from
+//! `theme.toml`
+
+use crate::app::RmdApp;
+use crate::theme::Backdrop;
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+/// The backdrop image, loaded once per path. A path that fails to load is
+/// remembered too, so the panes stay flat without trying again every frame.
+#[derive(Default)]
+pub struct PaneBackdrop {
+    loaded: Option<(PathBuf, Option<egui::TextureHandle>)>,
+}
+
+impl PaneBackdrop {
+    fn texture(&mut self, ctx: &egui::Context, path: &Path) -> Option<&egui::TextureHandle> {
+        if self.loaded.as_ref().map(|(loaded, _)| loaded.as_path()) != Some(path) {
+            let texture = match image::open(path) {
+                Ok(image) => {
+                    let image = image.to_rgba8();
+                    let size = [image.width() as usize, image.height() as usize];
+                    let pixels = egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+                    Some(ctx.load_texture("pane_backdrop", pixels, egui::TextureOptions::LINEAR))
+                }
+                Err(e) => {
+                    log::warn!("Failed to load the background image {}: {}", path.display(), e);
+                    None
+                }
+            };
+            self.loaded = Some((path.to_path_buf(), texture));
+        }
+        self.loaded.as_ref().and_then(|(_, texture)| texture.as_ref())
+    }
+}
+
+/// Part of an image of `size` that covers `rect` without stretching it
+fn cover_uv(size: egui::Vec2, rect: egui::Rect) -> egui::Rect {
+    let scale = (rect.width() / size.x).max(rect.height() / size.y);
+    let shown = rect.size() / (size * scale);
+    egui::Rect::from_center_size(egui::pos2(0.5, 0.5), shown)
+}
+
+impl RmdApp {
+    /// The frame of the editor or preview pane with `inner_margin`, after
+    /// painting its backdrop into the space it will take
+    pub fn pane_frame(&mut self, ui: &egui::Ui, inner_margin: f32) -> egui::Frame {
+        let frame = egui::Frame::central_panel(ui.style()).inner_margin(inner_margin);
+        let chrome = &self.theme.chrome;
+        if chrome.backdrop.is_none() && chrome.card.is_none() {
+            return frame;
+        }
+        let rect = ui.max_rect();
+        let painter = ui.painter();
+        if chrome.card.is_some() {
+            // Cards stand out against the window background
+            painter.rect_filled(rect, 0.0, self.theme.background);
+        }
+        let tint = |color: egui::Color32| color.gamma_multiply(chrome.opacity);
+        match &chrome.backdrop {
+            Some(Backdrop::Image(path)) => {
+                if let Some(texture) = self.pane_backdrop.texture(ui.ctx(), path) {
+                    let size = texture.size_vec2();
+                    if size.x > 0.0 && size.y > 0.0 {
+                        painter.image(texture.id(), rect, cover_uv(size, rect), tint(egui::Color32::WHITE));
+                    }
+                }
+            }
+            Some(Backdrop::Gradient(top, bottom)) => {
+                let mut mesh = egui::Mesh::default();
+                mesh.colored_vertex(rect.left_top(), tint(*top));
+                mesh.colored_vertex(rect.right_top(), tint(*top));
+                mesh.colored_vertex(rect.left_bottom(), tint(*bottom));
+                mesh.colored_vertex(rect.right_bottom(), tint(*bottom));
+                mesh.add_triangle(0, 1, 2);
+                mesh.add_triangle(1, 3, 2);
+                painter.add(mesh);
+            }
+            None => {}
+        }
+
+        match chrome.card {
+            Some(card) => egui::Frame {
+                outer_margin: card.margin.into(),
+                rounding: card.rounding.into(),
+                fill: self.theme.surface,
+                stroke: egui::Stroke::new(1.0, self.theme.border),
+                shadow: if card.shadow {
+                    egui::Shadow {
+                        offset: egui::vec2(0.0, 2.0),
+                        blur: 12.0,
+                        spread: 0.0,
+                        color: egui::Color32::from_black_alpha(if self.theme.is_dark() { 96 } else { 40 }),
+                    }
+                } else {
+                    egui::Shadow::NONE
+                },
+                ..frame
+            },
+            // The backdrop shows through the pane
+            None => egui::Frame { fill: egui::Color32::TRANSPARENT, ..frame },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cover_uv() {
+        // A wide image in a square pane shows its middle
+        let uv = cover_uv(egui::vec2(200.0, 100.0), egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(50.0, 50.0)));
+        assert_eq!(uv, egui::Rect::from_min_max(egui::pos2(0.25, 0.0), egui::pos2(0.75, 1.0)));
+    }
+}
@@ -0,0 +1,128 @@
+//! Ctrl+Shift+V: the clipboard history at the caret, to paste something
+//! copied before the last copy, and to pin entries so they stay
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::platform::clipboard;
+use crate::ui::widgets::ClickAway;
+use eframe::egui;
+
+/// State of the Paste from History list
+#[derive(Default)]
+pub struct ClipboardPicker {
+    open: bool,
+    click_away: ClickAway,
+    selected: usize,
+}
+
+impl RmdApp {
+    /// Show the clipboard history at the caret, taking the keys from the
+    /// editor while it is open
+    pub fn open_clipboard_picker(&mut self, ctx: &egui::Context) {
+        if self.readonly {
+            self.show_readonly_hint();
+            return;
+        }
+        let picker = &mut self.clipboard_picker;
+        picker.open = true;
+        picker.click_away = ClickAway::opened();
+        picker.selected = 0;
+        // Ctrl+Shift+V is a plain paste to the text widget as well
+        ctx.input_mut(|i| i.events.retain(|event| !matches!(event, egui::Event::Paste(_))));
+        ctx.memory_mut(|m| m.surrender_focus(self.editor.widget_id()));
+    }
+
+    /// Render the clipboard history. Enter or a click pastes the selected
+    /// entry at the caret as one undoable edit; Escape closes the list.
+    pub fn ui_clipboard_picker(&mut self, ctx: &egui::Context) {
+        if !self.clipboard_picker.open {
+            return;
+        }
+        let count = self.clipboard.entries().len();
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        let picker = &mut self.clipboard_picker;
+        if up {
+            picker.selected = picker.selected.saturating_sub(1);
+        }
+        if down && picker.selected + 1 < count {
+            picker.selected += 1;
+        }
+        picker.selected = picker.selected.min(count.saturating_sub(1));
+
+        let mut chosen = (enter && count > 0).then_some(picker.selected);
+        let mut pin = None;
+        let mut clear = false;
+        let muted = self.theme.text_muted;
+        let accent = self.theme.accent;
+        let pos = self.editor.caret_rect().map_or(egui::pos2(120.0, 120.0), |caret| caret.left_bottom() + egui::vec2(0.0, 2.0));
+        let entries = self.clipboard.entries();
+        let area = egui::Area::new(egui::Id::new("clipboard_picker"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(pos)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_width(360.0);
+                    if entries.is_empty() {
+                        ui.label(egui::RichText::new(t!("clipboard.empty")).color(muted));
+                        return;
+                    }
+                    egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                        for (index, entry) in entries.iter().enumerate() {
+                            let selected = index == picker.selected;
+                            let response = ui
+                                .horizontal(|ui| {
+                                    let (icon, hint) = if entry.pinned {
+                                        (egui::RichText::new("📌").color(accent), t!("clipboard.unpin"))
+                                    } else {
+                                        (egui::RichText::new("📌").color(muted), t!("clipboard.pin"))
+                                    };
+                                    if ui.small_button(icon).on_hover_text(hint).clicked() {
+                                        pin = Some(index);
+                                    }
+                                    let label = egui::SelectableLabel::new(selected, clipboard::preview(&entry.text));
+                                    ui.add_sized([ui.available_width(), 0.0], label)
+                                })
+                                .inner;
+                            if selected && (up || down) {
+                                response.scroll_to_me(None);
+                            }
+                            if response.clicked() {
+                                chosen = Some(index);
+                            }
+                        }
+                    });
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        ui.label(egui::RichText::new(t!("clipboard.hint")).small().color(muted));
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            clear = ui.small_button(t!("clipboard.clear")).clicked();
+                        });
+                    });
+                });
+            });
+
+        let dismissed = self.clipboard_picker.click_away.clicked(&area.response) || escape;
+        if let Some(index) = pin {
+            self.clipboard.toggle_pin(index);
+        }
+        if clear {
+            self.clipboard.clear_unpinned();
+        }
+        let text = chosen.and_then(|index| self.clipboard.entries().get(index)).map(|entry| entry.text.clone());
+        if let Some(text) = &text {
+            self.editor.insert_text(text);
+            self.has_unsaved_changes = true;
+        }
+        if text.is_some() || dismissed {
+            self.clipboard_picker.open = false;
+            ctx.memory_mut(|m| m.request_focus(self.editor.widget_id()));
+        }
+    }
+}
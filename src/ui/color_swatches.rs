@@ -0,0 +1,195 @@
+//! Swatches beside color literals: inline in the preview, and in the
+//! editor gutter, where clicking one opens a color picker that rewrites the
+//! literal the way it was written
+
+use crate::app::RmdApp;
+use crate::editor::typing_aids::in_prose;
+use crate::markdown::colors::{self, ColorLiteral, ColorRules};
+use crate::ui::bookmarks;
+use crate::ui::widgets::ClickAway;
+use crate::utils;
+use eframe::egui;
+use std::sync::Arc;
+
+/// Width of the swatch column left of the gutter markers, when shown
+pub const SWATCH_WIDTH: f32 = 12.0;
+
+/// Colors of the editor text, and the picker open on one of them
+#[derive(Default)]
+pub struct ColorSwatches {
+    /// Colors found, and the editor revision they were found at
+    found: Option<(u64, Vec<ColorLiteral>)>,
+    picker: Option<OpenPicker>,
+}
+
+struct OpenPicker {
+    literal: ColorLiteral,
+    pos: egui::Pos2,
+    /// Editor revision after the picker's last change; any other edit
+    /// closes it
+    revision: u64,
+    /// Whether the picker changed the text yet, so its later changes join
+    /// the same undo entry
+    edited: bool,
+    click_away: ClickAway,
+}
+
+/// Colors of the Markdown source `text`, held to the prose rules outside
+/// code
+pub fn source_colors(text: &str, rules: &ColorRules) -> Vec<ColorLiteral> {
+    colors::find_colors(text, rules, true)
+        .into_iter()
+        .filter(|literal| rules.allows_in_prose(literal) || !in_prose(text, literal.range.start))
+        .collect()
+}
+
+fn color32(rgba: [u8; 4]) -> egui::Color32 {
+    egui::Color32::from_rgba_unmultiplied(rgba[0], rgba[1], rgba[2], rgba[3])
+}
+
+/// `text` laid out in `format`, with a swatch before each of `colors`
+fn swatch_job(text: &str, colors: &[ColorLiteral], format: egui::TextFormat) -> egui::text::LayoutJob {
+    let mut job = egui::text::LayoutJob::default();
+    let mut at = 0;
+    for literal in colors {
+        job.append(&text[at..literal.range.start], 0.0, format.clone());
+        // Spaces on the color make the swatch, so it needs no glyph
+        let swatch = egui::TextFormat { background: color32(literal.rgba), ..format.clone() };
+        job.append("  ", 0.0, swatch);
+        job.append(" ", 0.0, format.clone());
+        at = literal.range.start;
+    }
+    job.append(&text[at..], 0.0, format);
+    job
+}
+
+impl RmdApp {
+    /// `rich`, the preview text `text` styled, with swatches before the
+    /// colors in it. `code` for text of inline code.
+    pub fn with_color_swatches(&self, ui: &egui::Ui, rich: egui::RichText, text: &str, code: bool) -> egui::WidgetText {
+        let colors = if self.config.colors.preview {
+            colors::find_colors(text, &self.config.colors.rules, code)
+        } else {
+            Vec::new()
+        };
+        if colors.is_empty() {
+            return rich.into();
+        }
+        let job = egui::WidgetText::from(rich).into_layout_job(ui.style(), egui::FontSelection::Default, egui::Align::Center);
+        let format = job.sections.first().map(|section| section.format.clone()).unwrap_or_default();
+        swatch_job(text, &colors, format).into()
+    }
+
+    /// Width the swatch column takes left of the editor text
+    pub fn swatch_gutter_width(&self) -> f32 {
+        if self.config.colors.editor {
+            SWATCH_WIDTH
+        } else {
+            0.0
+        }
+    }
+
+    /// Draw a swatch in the gutter beside each line with a color, for its
+    /// first color. Clicking one opens a picker for it.
+    pub fn paint_color_swatches(&mut self, ui: &egui::Ui, text: &str, galley: &Arc<egui::Galley>, galley_pos: egui::Pos2) {
+        if !self.config.colors.editor {
+            return;
+        }
+        let revision = self.editor.revision();
+        let swatches = &mut self.color_swatches;
+        if swatches.found.as_ref().map(|(at, _)| *at) != Some(revision) {
+            swatches.found = Some((revision, source_colors(text, &self.config.colors.rules)));
+        }
+        let Some((_, found)) = &swatches.found else {
+            return;
+        };
+
+        let clip = ui.clip_rect();
+        let x = galley_pos.x - bookmarks::GUTTER_WIDTH - SWATCH_WIDTH - 3.0;
+        let mut last_row = None;
+        let mut clicked = None;
+        for (index, literal) in found.iter().enumerate() {
+            let cursor = egui::text::CCursor::new(utils::byte_to_char(text, literal.range.start));
+            let row = galley.pos_from_ccursor(cursor).translate(galley_pos.to_vec2());
+            // One swatch per row, for its first color
+            if last_row.replace(row.top()) == Some(row.top()) || !clip.intersects(row) {
+                continue;
+            }
+            let side = (SWATCH_WIDTH - 2.0).min(row.height() - 2.0);
+            let rect = egui::Rect::from_center_size(egui::pos2(x + SWATCH_WIDTH / 2.0, row.center().y), egui::vec2(side, side));
+            ui.painter().rect(rect, 2.0, color32(literal.rgba), egui::Stroke::new(1.0, self.theme.text_muted));
+            let response = ui
+                .interact(rect, ui.id().with(("color_swatch", index)), egui::Sense::click())
+                .on_hover_cursor(egui::CursorIcon::PointingHand)
+                .on_hover_text(&literal.text);
+            if response.clicked() && !self.readonly {
+                clicked = Some((literal.clone(), egui::pos2(rect.right() + 4.0, rect.top())));
+            }
+        }
+        if let Some((literal, pos)) = clicked {
+            self.color_swatches.picker = Some(OpenPicker { literal, pos, revision, edited: false, click_away: ClickAway::opened() });
+        }
+    }
+
+    /// The color picker of a clicked swatch. Each change rewrites the
+    /// literal, all of them as one undoable edit.
+    pub fn ui_color_picker(&mut self, ctx: &egui::Context) {
+        // Taken out while shown, and put back unless it closes
+        let Some(mut picker) = self.color_swatches.picker.take() else {
+            return;
+        };
+        if picker.revision != self.editor.revision() {
+            return;
+        }
+        let mut click_away = std::mem::take(&mut picker.click_away);
+        let format = picker.literal.format;
+        let mut color = color32(picker.literal.rgba);
+        let alpha = if format.has_alpha() {
+            egui::color_picker::Alpha::OnlyBlend
+        } else {
+            egui::color_picker::Alpha::Opaque
+        };
+        let mut changed = false;
+        let area = egui::Area::new(egui::Id::new("color_picker_popup"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(picker.pos)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    changed = egui::color_picker::color_picker_color32(ui, &mut color, alpha);
+                });
+            });
+
+        if changed {
+            let rgba = color.to_srgba_unmultiplied();
+            let written = format.write(rgba);
+            if written != picker.literal.text {
+                let range = picker.literal.range.clone();
+                if picker.edited {
+                    self.editor.amend_last_edit(range.clone(), &written);
+                } else {
+                    self.editor.replace_range_in_place(range.clone(), &written);
+                }
+                self.has_unsaved_changes = true;
+                picker.literal = ColorLiteral { range: range.start..range.start + written.len(), text: written, rgba, format };
+                picker.revision = self.editor.revision();
+                picker.edited = true;
+            }
+        }
+        let escape = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+        if !escape && !click_away.clicked(&area.response) {
+            self.color_swatches.picker = Some(picker);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_colors() {
+        let text = "Issue #123 is `#123`, and #ff8800.\n\n```css\na { color: #fad }\n```\n";
+        let found: Vec<String> = source_colors(text, &ColorRules::default()).into_iter().map(|c| c.text).collect();
+        assert_eq!(found, ["#123", "#ff8800", "#fad"]);
+    }
+}
@@ -0,0 +1,201 @@
+//! Tools → Consolidate Images: the document's images with where they are
+//! now, gathered into the assets folder next to it once confirmed, and a
+//! summary of what was moved, skipped, and what failed
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::assets::{self, DocumentImage, ImageLocation, Outcome, ASSETS_FOLDER};
+use crate::ui::toasts::Severity;
+use eframe::egui;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+pub struct ConsolidateDialog {
+    /// Folder of the document
+    folder: PathBuf,
+    images: Vec<DocumentImage>,
+    /// Download web images too
+    download: bool,
+    /// Gathering on a background thread, until it sends what it did
+    running: Option<Receiver<Vec<(String, Outcome)>>>,
+    /// What was done with each image, once finished
+    outcomes: Option<Vec<(String, Outcome)>>,
+}
+
+impl ConsolidateDialog {
+    /// Whether confirming would copy or download anything
+    fn has_work(&self) -> bool {
+        self.images.iter().any(|image| match image.location {
+            ImageLocation::Local(_) => true,
+            ImageLocation::Remote => self.download,
+            _ => false,
+        })
+    }
+
+    fn start(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+        let (images, folder, download) = (self.images.clone(), self.folder.clone(), self.download);
+        std::thread::spawn(move || {
+            let _ = sender.send(assets::gather(&images, &folder, download));
+        });
+        self.running = Some(receiver);
+    }
+}
+
+impl RmdApp {
+    pub fn open_consolidate_dialog(&mut self) {
+        if self.readonly {
+            self.show_readonly_hint();
+            return;
+        }
+        let Some(folder) = self.current_file.as_deref().and_then(Path::parent).map(Path::to_path_buf) else {
+            self.notify(Severity::Warning, t!("consolidate.unsaved"));
+            return;
+        };
+        let text = self.editor.text();
+        let images = assets::document_images(&text, self.markdown_renderer.flavor_for(&text), &folder);
+        self.consolidate_dialog = Some(ConsolidateDialog { folder, images, download: false, running: None, outcomes: None });
+    }
+
+    pub fn ui_consolidate_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.consolidate_dialog else {
+            return;
+        };
+        let mut finished = None;
+        if let Some(receiver) = &dialog.running {
+            match receiver.try_recv() {
+                Ok(outcomes) => finished = Some(outcomes),
+                Err(TryRecvError::Disconnected) => finished = Some(Vec::new()),
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+        if let Some(outcomes) = finished {
+            dialog.running = None;
+            let moved: HashMap<String, String> = outcomes
+                .iter()
+                .filter_map(|(url, outcome)| match outcome {
+                    Outcome::Moved(new_url) => Some((url.clone(), new_url.clone())),
+                    _ => None,
+                })
+                .collect();
+            dialog.outcomes = Some(outcomes);
+            let text = self.editor.text();
+            if let Some(rewritten) = assets::rewrite_images(&text, self.markdown_renderer.flavor_for(&text), &moved) {
+                self.editor.replace_text(&rewritten);
+                self.has_unsaved_changes = true;
+            }
+        }
+        let Some(dialog) = &mut self.consolidate_dialog else {
+            return;
+        };
+
+        let muted = self.theme.text_muted;
+        let warning = self.theme.warning;
+        let error = self.theme.error;
+        let mut open = true;
+        let mut confirm = false;
+        let mut close = false;
+        egui::Window::new(t!("consolidate.title"))
+            .id(egui::Id::new("consolidate_images"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                if dialog.running.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(t!("consolidate.working"));
+                    });
+                    return;
+                }
+                if let Some(outcomes) = &dialog.outcomes {
+                    ui_outcomes(ui, outcomes, muted, warning, error);
+                    ui.separator();
+                    close = ui.button(t!("dialog.close")).clicked();
+                    return;
+                }
+
+                ui.label(t!("consolidate.into", folder = ASSETS_FOLDER));
+                egui::ScrollArea::vertical().max_height(260.0).show(ui, |ui| {
+                    if dialog.images.is_empty() {
+                        ui.label(egui::RichText::new(t!("consolidate.none")).color(muted));
+                    }
+                    egui::Grid::new("consolidate_images_list").num_columns(2).spacing([16.0, 4.0]).show(ui, |ui| {
+                        for image in &dialog.images {
+                            let (status, color) = match &image.location {
+                                ImageLocation::Local(_) => (t!("consolidate.local"), None),
+                                ImageLocation::InAssets(_) => (t!("consolidate.in_assets"), Some(muted)),
+                                ImageLocation::Missing(_) => (t!("consolidate.missing"), Some(warning)),
+                                ImageLocation::Remote => (t!("consolidate.remote"), Some(muted)),
+                            };
+                            let url = ui.monospace(&image.url);
+                            if let ImageLocation::Local(path) | ImageLocation::InAssets(path) | ImageLocation::Missing(path) = &image.location {
+                                url.on_hover_text(path.display().to_string());
+                            }
+                            let status = egui::RichText::new(status);
+                            ui.label(match color {
+                                Some(color) => status.color(color),
+                                None => status,
+                            });
+                            ui.end_row();
+                        }
+                    });
+                });
+                let remote = dialog.images.iter().any(|image| image.location == ImageLocation::Remote);
+                ui.add_enabled(remote, egui::Checkbox::new(&mut dialog.download, t!("consolidate.download")));
+                ui.separator();
+                ui.horizontal(|ui| {
+                    confirm = ui.add_enabled(dialog.has_work(), egui::Button::new(t!("consolidate.consolidate"))).clicked();
+                    close = ui.button(t!("dialog.cancel")).clicked();
+                });
+            });
+
+        if confirm {
+            dialog.start();
+        } else if (close || !open) && dialog.running.is_none() {
+            self.consolidate_dialog = None;
+        }
+    }
+}
+
+/// What was moved where, what was skipped, and what failed and why
+fn ui_outcomes(ui: &mut egui::Ui, outcomes: &[(String, Outcome)], muted: egui::Color32, warning: egui::Color32, error: egui::Color32) {
+    let moved: Vec<(&str, &str)> = outcomes
+        .iter()
+        .filter_map(|(url, outcome)| match outcome {
+            Outcome::Moved(new_url) => Some((url.as_str(), new_url.as_str())),
+            _ => None,
+        })
+        .collect();
+    let skipped: Vec<&str> = outcomes.iter().filter(|(_, outcome)| *outcome == Outcome::Skipped).map(|(url, _)| url.as_str()).collect();
+    let failed: Vec<(&str, &str)> = outcomes
+        .iter()
+        .filter_map(|(url, outcome)| match outcome {
+            Outcome::Failed(reason) => Some((url.as_str(), reason.as_str())),
+            _ => None,
+        })
+        .collect();
+
+    egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+        ui.strong(t!("consolidate.moved", count = moved.len()));
+        for (url, new_url) in moved {
+            ui.label(egui::RichText::new(format!("{} → {}", url, new_url)).monospace().color(muted));
+        }
+        if !skipped.is_empty() {
+            ui.add_space(4.0);
+            ui.colored_label(warning, t!("consolidate.skipped", count = skipped.len()));
+            for url in skipped {
+                ui.label(egui::RichText::new(url).monospace().color(muted));
+            }
+        }
+        if !failed.is_empty() {
+            ui.add_space(4.0);
+            ui.colored_label(error, t!("consolidate.failed", count = failed.len()));
+            for (url, reason) in failed {
+                ui.label(egui::RichText::new(format!("{}: {}", url, reason)).monospace().color(muted));
+            }
+        }
+    });
+}
@@ -0,0 +1,109 @@
+//! The performance overlay: how long the last frames spent parsing,
+//! highlighting, laying out and saving, how much memory the text and the
+//! preview hold, and how often the caches were reused. Toggled with its
+//! action or started with `--debug-hud`.
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::utils;
+use crate::utils::timing::{self, Cache, CacheCounts, Phase};
+use eframe::egui;
+use std::time::Duration;
+
+/// Frame time above which the overlay marks a frame as slow, at 60 Hz
+const FRAME_BUDGET: Duration = Duration::from_micros(16_667);
+
+fn phase_label(phase: Phase) -> &'static str {
+    match phase {
+        Phase::Frame => t!("diagnostics.frame"),
+        Phase::Parse => t!("diagnostics.parse"),
+        Phase::PreviewLayout => t!("diagnostics.preview_layout"),
+        Phase::Highlight => t!("diagnostics.highlight"),
+        Phase::Save => t!("diagnostics.save"),
+    }
+}
+
+fn cache_label(cache: Cache) -> &'static str {
+    match cache {
+        Cache::HighlightLines => t!("diagnostics.highlight_lines"),
+        Cache::PreviewBlocks => t!("diagnostics.preview_blocks"),
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// Reused of all uses, e.g. `98%`, or a dash before any
+fn hit_rate(counts: &CacheCounts) -> String {
+    match counts.hits + counts.misses {
+        0 => "–".to_string(),
+        total => format!("{:.0}%", counts.hits as f64 * 100.0 / total as f64),
+    }
+}
+
+impl RmdApp {
+    /// The performance overlay in the top right corner, when turned on
+    pub fn ui_diagnostics(&mut self, ctx: &egui::Context) {
+        if !timing::is_enabled() {
+            return;
+        }
+        let memory = [
+            (t!("diagnostics.text_buffer"), self.editor.allocated_bytes()),
+            (t!("diagnostics.preview_cache"), self.preview.cache_bytes()),
+        ];
+        egui::Area::new(egui::Id::new("diagnostics_hud"))
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-12.0, 40.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(egui::RichText::new(t!("diagnostics.title")).strong());
+                    egui::Grid::new("diagnostics_timings").num_columns(3).show(ui, |ui| {
+                        ui.label("");
+                        ui.label(egui::RichText::new(t!("diagnostics.latest")).small());
+                        ui.label(egui::RichText::new(t!("diagnostics.worst")).small());
+                        ui.end_row();
+                        for phase in Phase::ALL {
+                            ui.label(phase_label(phase));
+                            match timing::latest_and_worst(phase) {
+                                Some((latest, worst)) => {
+                                    let over = phase == Phase::Frame && latest > FRAME_BUDGET;
+                                    let color = if over { self.theme.error } else { self.theme.text };
+                                    ui.monospace(egui::RichText::new(millis(latest)).color(color));
+                                    ui.monospace(millis(worst));
+                                }
+                                None => {
+                                    ui.monospace("–");
+                                    ui.monospace("–");
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    ui.separator();
+                    egui::Grid::new("diagnostics_memory").num_columns(2).show(ui, |ui| {
+                        for (label, bytes) in memory {
+                            ui.label(label);
+                            ui.monospace(utils::format_file_size(bytes as u64));
+                            ui.end_row();
+                        }
+                    });
+                    ui.separator();
+                    egui::Grid::new("diagnostics_caches").num_columns(3).show(ui, |ui| {
+                        ui.label("");
+                        ui.label(egui::RichText::new(t!("diagnostics.reused_redone")).small());
+                        ui.label(egui::RichText::new(t!("diagnostics.hit_rate")).small());
+                        ui.end_row();
+                        for cache in Cache::ALL {
+                            let counts = timing::cache_counts(cache);
+                            ui.label(cache_label(cache));
+                            ui.monospace(format!("{} / {}", counts.last_hits, counts.last_misses));
+                            ui.monospace(hit_rate(&counts));
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+    }
+}
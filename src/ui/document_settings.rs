@@ -0,0 +1,116 @@
+//! Front matter overrides for the open document: parsed again whenever the
+//! text changes, applied over the global config, and shown as a chip in the
+//! status bar that lists them and can set them aside
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::overrides::DocumentSettings;
+use crate::ui::readonly;
+use eframe::egui;
+use std::path::PathBuf;
+
+/// The open document's settings, and which document and revision they
+/// were read from
+#[derive(Default)]
+pub struct DocumentSettingsState {
+    settings: DocumentSettings,
+    /// The user chose to ignore them for this document
+    ignored: bool,
+    /// Whether `rmd.readonly` locked the document
+    locked: bool,
+    file: Option<PathBuf>,
+    revision: Option<u64>,
+}
+
+impl RmdApp {
+    /// Read the document's settings again if it changed, and apply them.
+    /// Opening another document starts over with its own.
+    pub fn update_document_settings(&mut self) {
+        let state = &mut self.document_settings;
+        if state.file != self.current_file {
+            *state = DocumentSettingsState { file: self.current_file.clone(), ..Default::default() };
+        }
+        let revision = self.editor.revision();
+        if state.revision == Some(revision) {
+            return;
+        }
+        state.revision = Some(revision);
+        let settings = DocumentSettings::parse(&self.editor.text());
+        if settings != state.settings {
+            state.settings = settings;
+            self.apply_document_settings();
+        }
+    }
+
+    /// The document's settings, unless there are none or they are ignored
+    pub fn active_document_settings(&self) -> Option<&DocumentSettings> {
+        let state = &self.document_settings;
+        (!state.ignored && !state.settings.is_empty()).then_some(&state.settings)
+    }
+
+    /// Whether the editor wraps long lines
+    pub fn word_wrap(&self) -> bool {
+        self.active_document_settings()
+            .and_then(|settings| settings.word_wrap)
+            .unwrap_or(self.config.editor.word_wrap)
+    }
+
+    /// Widest the preview's text column gets
+    pub fn preview_width(&self) -> f32 {
+        self.active_document_settings()
+            .and_then(|settings| settings.preview_width)
+            .unwrap_or(self.config.preview.max_content_width)
+    }
+
+    /// Use the settings, or the globals when they are ignored or gone
+    fn apply_document_settings(&mut self) {
+        self.apply_markdown_settings();
+        let lock = self.active_document_settings().and_then(|settings| settings.readonly) == Some(true);
+        if lock == self.document_settings.locked {
+            return;
+        }
+        self.document_settings.locked = lock;
+        let on_disk = self.current_file.as_deref().is_some_and(readonly::is_readonly_on_disk);
+        if self.readonly != (lock || on_disk) {
+            self.toggle_readonly();
+        }
+    }
+
+    /// Status bar chip shown while the document has settings of its own,
+    /// listing them with a button to ignore them or use them again
+    pub fn ui_document_settings_chip(&mut self, ui: &mut egui::Ui) {
+        let state = &self.document_settings;
+        if state.settings.is_empty() {
+            return;
+        }
+        let ignored = state.ignored;
+        let label = if ignored { t!("status.document_settings_ignored") } else { t!("status.document_settings_active") };
+        let text = egui::RichText::new(label).small();
+        let text = if ignored { text.color(self.theme.text_muted) } else { text.color(self.theme.accent) };
+
+        let mut toggle = false;
+        ui.menu_button(text, |ui| {
+            let settings = &self.document_settings.settings;
+            ui.label(egui::RichText::new(t!("status.document_settings_source")).small().color(self.theme.text_muted));
+            for line in settings.overrides() {
+                ui.monospace(format!("rmd.{}", line));
+            }
+            for warning in &settings.warnings {
+                ui.label(egui::RichText::new(format!("⚠ {}", warning)).color(ui.visuals().warn_fg_color));
+            }
+            ui.separator();
+            let button = if ignored { t!("status.use_document_settings") } else { t!("status.ignore_document_settings") };
+            if ui.button(button).clicked() {
+                toggle = true;
+                ui.close_menu();
+            }
+        })
+        .response
+        .on_hover_text(t!("status.document_settings_hint"));
+
+        if toggle {
+            self.document_settings.ignored = !ignored;
+            self.apply_document_settings();
+        }
+    }
+}
@@ -0,0 +1,414 @@
+//! Exporting a document or part of one as an HTML page or a Word document,
+//! or the workspace folder as a static HTML site
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::embed::{self, LocalImage};
+use crate::markdown::export::{self, Destination, ExportStyle, HtmlOptions, PageSize, Stylesheet};
+use crate::markdown::header::DocumentHeader;
+use crate::markdown::{docx, include, outline};
+use crate::stats::document::DocumentStats;
+use crate::ui::toasts::Severity;
+use crate::workspace::export::FolderExport;
+use anyhow::Context;
+use eframe::egui;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Markdown waiting in the export options window to be saved as a page
+pub struct ExportDialog {
+    markdown: String,
+    title: String,
+    /// Local images it shows, to warn about before embedding them
+    images: Vec<LocalImage>,
+}
+
+impl RmdApp {
+    /// Ask where to write the site and start exporting the workspace, or a
+    /// folder picked here when none is open
+    pub fn export_folder_dialog(&mut self) {
+        if self.folder_export.as_ref().is_some_and(FolderExport::is_running) {
+            return;
+        }
+        let root = match self.workspace_root.clone() {
+            Some(root) => root,
+            None => match rfd::FileDialog::new().set_title("Folder to export").pick_folder() {
+                Some(root) => root,
+                None => return,
+            },
+        };
+        let css = match self.export_css() {
+            Ok(css) => css.into_owned(),
+            Err(e) => {
+                self.notify_error(t!("error.export_failed"), format!("{:#}", e));
+                return;
+            }
+        };
+        let Some(output) = rfd::FileDialog::new()
+            .set_title(t!("export.site_to"))
+            .set_directory(root.parent().unwrap_or(&root))
+            .pick_folder()
+        else {
+            return;
+        };
+        let options = HtmlOptions {
+            flavor: self.config.preview.flavor,
+            number_headings: self.config.preview.number_headings,
+            toc_depth: self.config.export.include_toc.then_some(self.config.writing.toc_depth),
+        };
+        let extensions = &self.config.markdown_extensions;
+        self.folder_export = Some(FolderExport::start(&root, &output, options, css, extensions));
+    }
+
+    /// Choose how to export the document, then where to save the page
+    pub fn export_html_dialog(&mut self) {
+        let text = self.editor.text();
+        self.open_export_dialog(text);
+    }
+
+    /// Choose how to export the selection, or else the section around the
+    /// caret, then where to save the page
+    pub fn export_selection_dialog(&mut self) {
+        let text = self.editor.text();
+        let Some(range) = self.editor.selection().or_else(|| self.current_section(&text)) else {
+            return;
+        };
+        self.open_export_dialog(text[range].to_string());
+    }
+
+    fn open_export_dialog(&mut self, markdown: String) {
+        // Included parts are written into the page
+        let wiki = self.markdown_renderer.wiki_links_for(self.markdown_renderer.flavor_for(&markdown));
+        let markdown = include::expand(&markdown, self.current_file.as_deref(), wiki);
+        let title = outline::headings(&markdown)
+            .first()
+            .map(|heading| heading.text.clone())
+            .or_else(|| {
+                let stem = self.current_file.as_deref()?.file_stem()?;
+                Some(stem.to_string_lossy().to_string())
+            })
+            .unwrap_or_else(|| t!("status.untitled").to_string());
+        let folder = self.current_file.as_deref().and_then(Path::parent);
+        let images = embed::local_images(&markdown, self.markdown_renderer.flavor_for(&markdown), folder);
+        self.export_dialog = Some(ExportDialog { markdown, title, images });
+    }
+
+    /// Ask where to save the document as a Word file, then write it. What
+    /// Word can't show as it is gets listed in a warning.
+    pub fn export_docx_dialog(&mut self) {
+        let text = self.editor.text();
+        let flavor = self.markdown_renderer.flavor_for(&text);
+        let markdown = include::expand(&text, self.current_file.as_deref(), self.markdown_renderer.wiki_links_for(flavor));
+        let name = self
+            .current_file
+            .as_deref()
+            .and_then(Path::file_stem)
+            .map_or_else(|| t!("status.untitled").to_string(), |stem| stem.to_string_lossy().to_string());
+        let mut save = rfd::FileDialog::new()
+            .set_title(t!("export.docx_title"))
+            .set_file_name(format!("{}.docx", name))
+            .add_filter(t!("export.word_document"), &["docx"]);
+        let folder = self.current_file.as_deref().and_then(Path::parent).map(Path::to_path_buf);
+        if let Some(folder) = &folder {
+            save = save.set_directory(folder);
+        }
+        let Some(output) = save.save_file() else {
+            return;
+        };
+
+        let document = docx::markdown_to_docx(&markdown, flavor, folder.as_deref());
+        if let Err(e) = std::fs::write(&output, &document.bytes) {
+            self.notify_error(t!("error.export_failed_path", path = output.display()), e);
+            return;
+        }
+        self.notify(Severity::Success, t!("toast.exported", path = output.display()));
+        if !document.simplified.is_empty() {
+            self.notify(
+                Severity::Warning,
+                t!("toast.docx_simplified", items = document.simplified.join(", ")),
+            );
+        }
+    }
+
+    /// Stylesheet of the configured export style: a built-in one, or the
+    /// custom file read from disk, followed by the page size and margins
+    pub(crate) fn export_css(&self) -> anyhow::Result<Cow<'static, str>> {
+        let export = &self.config.export;
+        let css = match export.style.css() {
+            Some(css) => Cow::Borrowed(css),
+            None => {
+                let path = export.custom_css.as_deref().context("No custom stylesheet is chosen")?;
+                Cow::Owned(std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?)
+            }
+        };
+        Ok(Cow::Owned(format!("{}\n{}", css, export.page_size.page_rule(export.page_margin))))
+    }
+
+    /// Export options, and the page's save dialog once confirmed
+    pub fn ui_export_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &self.export_dialog else {
+            return;
+        };
+        let mut open = true;
+        let mut export = false;
+        let mut cancel = false;
+        let export_config = &mut self.config.export;
+        egui::Window::new(t!("export.title"))
+            .id(egui::Id::new("export_html"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(t!("export.style"));
+                    egui::ComboBox::from_id_salt("export_style")
+                        .selected_text(export_config.style.label())
+                        .show_ui(ui, |ui| {
+                            for style in ExportStyle::ALL {
+                                ui.selectable_value(&mut export_config.style, style, style.label());
+                            }
+                        });
+                });
+                if export_config.style == ExportStyle::Custom {
+                    ui.horizontal(|ui| {
+                        match &export_config.custom_css {
+                            Some(path) => ui.label(path.display().to_string()),
+                            None => ui.weak(t!("export.no_stylesheet")),
+                        };
+                        if ui.button(t!("export.choose")).clicked() {
+                            if let Some(path) = rfd::FileDialog::new().add_filter("CSS", &["css"]).pick_file() {
+                                export_config.custom_css = Some(path);
+                            }
+                        }
+                    });
+                }
+                ui.checkbox(&mut export_config.link_css, t!("export.link_css"))
+                    .on_hover_text(t!("export.link_css_hint"));
+                ui.horizontal(|ui| {
+                    ui.label(t!("export.page_size"));
+                    egui::ComboBox::from_id_salt("export_page_size")
+                        .selected_text(export_config.page_size.label())
+                        .show_ui(ui, |ui| {
+                            for size in PageSize::ALL {
+                                ui.selectable_value(&mut export_config.page_size, size, size.label());
+                            }
+                        });
+                    ui.label(t!("export.page_margin"));
+                    ui.add(egui::DragValue::new(&mut export_config.page_margin).range(0.0..=50.0).suffix(" mm"));
+                });
+                ui.separator();
+                ui.checkbox(&mut export_config.include_toc, t!("export.include_toc"))
+                    .on_hover_text(t!("export.include_toc_hint", depth = self.config.writing.toc_depth));
+                ui.checkbox(&mut export_config.include_header, t!("export.include_header"))
+                    .on_hover_text(t!("export.include_header_hint"));
+                ui.checkbox(&mut self.config.preview.number_headings, t!("preferences.number_headings"))
+                    .on_hover_text(t!("export.number_headings_hint"));
+                ui.checkbox(&mut export_config.embed_images, t!("export.embed_images"))
+                    .on_hover_text(t!("export.embed_images_hint"));
+                if export_config.embed_images {
+                    ui_image_warnings(ui, &dialog.images, &self.theme);
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let ready = export_config.style != ExportStyle::Custom || export_config.custom_css.is_some();
+                    export = ui.add_enabled(ready, egui::Button::new(t!("export.export"))).clicked();
+                    cancel = ui.button(t!("dialog.cancel")).clicked();
+                });
+            });
+
+        if export {
+            if let Some(dialog) = self.export_dialog.take() {
+                self.export_page(dialog);
+            }
+        } else if cancel || !open {
+            self.export_dialog = None;
+        }
+    }
+
+    /// Ask where to save the page for `dialog`, then write it, and its
+    /// stylesheet when that is linked
+    fn export_page(&mut self, dialog: ExportDialog) {
+        let css = match self.export_css() {
+            Ok(css) => css,
+            Err(e) => {
+                self.notify_error(t!("error.export_failed"), format!("{:#}", e));
+                return;
+            }
+        };
+        let mut save = rfd::FileDialog::new()
+            .set_title(t!("export.title"))
+            .set_file_name(format!("{}.html", crate::utils::sanitize_filename(&dialog.title)))
+            .add_filter("HTML", &["html", "htm"]);
+        let folder = self.current_file.as_deref().and_then(Path::parent).map(Path::to_path_buf);
+        if let Some(folder) = &folder {
+            save = save.set_directory(folder);
+        }
+        let Some(output) = save.save_file() else {
+            return;
+        };
+
+        let options = HtmlOptions {
+            flavor: self.markdown_renderer.flavor_for(&dialog.markdown),
+            number_headings: self.config.preview.number_headings,
+            toc_depth: self.config.export.include_toc.then_some(self.config.writing.toc_depth),
+        };
+        let embed_images = self.config.export.embed_images;
+        let mut embedded: HashMap<PathBuf, String> = HashMap::new();
+        let mut not_embedded = Vec::new();
+        let body = export::markdown_to_html(&dialog.markdown, options, |kind, url| {
+            let path = match kind {
+                Destination::Image if embed_images => embed::resolve(url, folder.as_deref()),
+                _ => None,
+            };
+            let Some(path) = path else {
+                return url.to_string();
+            };
+            if let Some(uri) = embedded.get(&path) {
+                return uri.clone();
+            }
+            match embed::data_uri(&path) {
+                Ok(uri) => embedded.entry(path).or_insert(uri).clone(),
+                Err(e) => {
+                    not_embedded.push(format!("{}: {}", url, e));
+                    url.to_string()
+                }
+            }
+        });
+        let body = if self.config.export.include_header {
+            let stats = DocumentStats::new(&dialog.markdown, options.flavor);
+            let facts = self.header_facts(&stats, self.file_modified());
+            DocumentHeader::parse(&dialog.markdown).to_html(&facts) + &body
+        } else {
+            body
+        };
+
+        let css_file = output.with_extension("css");
+        let page = if self.config.export.link_css {
+            if let Err(e) = std::fs::write(&css_file, css.as_ref()) {
+                self.notify_error(t!("error.export_failed_path", path = css_file.display()), e);
+                return;
+            }
+            let href = css_file.file_name().unwrap_or_default().to_string_lossy().replace(' ', "%20");
+            export::html_document(&dialog.title, self.document_language(), Stylesheet::Link(&href), &body)
+        } else {
+            export::html_document(&dialog.title, self.document_language(), Stylesheet::Inline(&css), &body)
+        };
+        match std::fs::write(&output, page) {
+            Ok(()) => self.notify(Severity::Success, t!("toast.exported", path = output.display())),
+            Err(e) => {
+                self.notify_error(t!("error.export_failed_path", path = output.display()), e);
+                return;
+            }
+        }
+        if !not_embedded.is_empty() {
+            self.notify(
+                Severity::Warning,
+                t!("toast.not_embedded", images = not_embedded.join("\n")),
+            );
+        }
+    }
+
+    /// Put the Markdown of the section around the caret on the clipboard
+    pub fn copy_section(&mut self, ctx: &egui::Context) {
+        let text = self.editor.text();
+        if let Some(range) = self.current_section(&text) {
+            ctx.copy_text(text[range].to_string());
+        }
+    }
+
+    /// Section of `text` around the caret, as `outline::section_range` finds it
+    fn current_section(&self, text: &str) -> Option<Range<usize>> {
+        outline::section_range(text, self.editor.cursor())
+    }
+
+    /// Progress of a folder export, then its summary
+    pub fn ui_folder_export(&mut self, ctx: &egui::Context) {
+        let Some(export) = self.folder_export.as_mut() else {
+            return;
+        };
+        export.poll();
+
+        let mut close = false;
+        let mut failure = None;
+        egui::Window::new(t!("export.folder_title"))
+            .id(egui::Id::new("export_folder"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| match &export.summary {
+                None => {
+                    let fraction = if export.total == 0 {
+                        0.0
+                    } else {
+                        export.done as f32 / export.total as f32
+                    };
+                    ui.add(
+                        egui::ProgressBar::new(fraction)
+                            .desired_width(320.0)
+                            .text(t!("export.progress", done = export.done, total = export.total)),
+                    );
+                    if ui.button(t!("dialog.cancel")).clicked() {
+                        export.cancel();
+                    }
+                }
+                Some(summary) => {
+                    if summary.cancelled {
+                        ui.label(t!("export.cancelled", count = summary.exported));
+                    } else {
+                        ui.label(t!("export.finished", count = summary.exported, path = summary.output.display()));
+                    }
+                    if !summary.failed.is_empty() {
+                        ui.colored_label(self.theme.error, t!("export.failed", count = summary.failed.len()));
+                        egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                            for (path, error) in &summary.failed {
+                                ui.label(format!("{}: {}", path.display(), error));
+                            }
+                        });
+                    }
+                    ui.horizontal(|ui| {
+                        let index = summary.output.join("index.html");
+                        if !summary.cancelled && index.is_file() && ui.button(t!("export.open_in_browser")).clicked() {
+                            if let Err(e) = webbrowser::open(&index.to_string_lossy()) {
+                                failure = Some((t!("error.open_failed", path = index.display()), e));
+                            }
+                        }
+                        close = ui.button(t!("dialog.close")).clicked();
+                    });
+                }
+            });
+
+        if let Some((context, e)) = failure {
+            self.notify_error(context, e);
+        }
+        if close {
+            self.folder_export = None;
+        }
+    }
+}
+
+/// Images too large to embed comfortably, and missing ones
+fn ui_image_warnings(ui: &mut egui::Ui, images: &[LocalImage], theme: &crate::theme::Theme) {
+    let megabytes = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    let large: Vec<(&LocalImage, u64)> = images
+        .iter()
+        .filter_map(|image| Some((image, image.size?)))
+        .filter(|&(_, size)| size > embed::LARGE_IMAGE)
+        .collect();
+    if !large.is_empty() {
+        let total: u64 = images.iter().filter_map(|image| image.size).sum();
+        // Base64 takes four bytes for every three
+        ui.colored_label(
+            theme.warning,
+            format!("⚠ Embedding makes the page about {:.1} MB larger. Largest images:", megabytes(total * 4 / 3)),
+        );
+        for (image, size) in large {
+            ui.label(format!("    {} ({:.1} MB)", image.url, megabytes(size)));
+        }
+    }
+    for image in images.iter().filter(|image| image.size.is_none()) {
+        ui.colored_label(theme.text_muted, format!("{} is missing and stays a link", image.url));
+    }
+}
@@ -0,0 +1,31 @@
+//! List of figures in the sidebar, like the outline but for captioned images
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use eframe::egui;
+
+impl RmdApp {
+    /// List the figures of the open document, returning the offset of the
+    /// figure clicked
+    pub fn ui_figures(&mut self, ui: &mut egui::Ui) -> Option<usize> {
+        let muted = self.theme.text_muted;
+        let figures = self.editor.figures();
+        let mut jump = None;
+        egui::CollapsingHeader::new(t!("sidebar.figures", count = figures.len()))
+            .default_open(true)
+            .show(ui, |ui| {
+                if figures.is_empty() {
+                    ui.label(egui::RichText::new(t!("sidebar.no_figures")).color(muted))
+                        .on_hover_text(t!("sidebar.no_figures_hint"));
+                }
+                egui::ScrollArea::vertical().auto_shrink([false, true]).show(ui, |ui| {
+                    for figure in figures {
+                        if ui.link(figure.label()).on_hover_text(&figure.url).clicked() {
+                            jump = Some(figure.block.start);
+                        }
+                    }
+                });
+            });
+        jump
+    }
+}
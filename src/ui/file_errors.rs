@@ -0,0 +1,89 @@
+//! Telling the user why a document couldn't be opened, reloaded, or saved,
+//! with a button for what can be done about it
+
+use crate::app::RmdApp;
+use crate::editor::error::{EditorError, Operation};
+use crate::editor::load;
+use crate::i18n::t;
+use crate::ui::toasts::{Severity, ToastAction};
+use std::path::Path;
+
+/// The notification for `error`, and the button it offers
+fn describe(error: &EditorError) -> (String, Option<ToastAction>) {
+    let path = error.path().display();
+    match error {
+        EditorError::NotFound { .. } => (t!("file_error.not_found", path = path), None),
+        EditorError::PermissionDenied { operation: Operation::Save, path: file } | EditorError::ReadOnly { path: file, .. } => {
+            (t!("file_error.not_writable", path = path), Some(ToastAction::LockDocument(file.clone())))
+        }
+        EditorError::PermissionDenied { .. } => (t!("file_error.not_readable", path = path), None),
+        EditorError::InvalidUtf8 { lossy_available, path: file, .. } => {
+            let action = (*lossy_available && error.operation() != Operation::Save).then(|| ToastAction::OpenLossy(file.clone()));
+            (t!("file_error.invalid_utf8", path = path), action)
+        }
+        EditorError::TooLarge { size, limit, .. } => (
+            t!("file_error.too_large", path = path, size = megabytes(*size), limit = megabytes(*limit)),
+            None,
+        ),
+        EditorError::DiskFull { .. } => (t!("file_error.disk_full", path = path), None),
+        EditorError::Io { .. } => {
+            let message = match error.operation() {
+                Operation::Open => t!("error.open_failed", path = path),
+                Operation::Reload => t!("error.reload_failed", path = path),
+                Operation::Save => t!("error.save_failed", path = path),
+            };
+            (message, None)
+        }
+    }
+}
+
+fn megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+impl RmdApp {
+    /// Tell the user what went wrong with a file, offering a way on where
+    /// there is one
+    pub fn notify_file_error(&mut self, error: EditorError) {
+        log::error!("{}", error);
+        let (message, action) = describe(&error);
+        // The message says it all unless the system's own words add to it
+        let details = matches!(error, EditorError::Io { .. }).then(|| error.to_string());
+        self.toasts.push_action(Severity::Error, message, details, action);
+    }
+
+    /// Open `path` with anything that isn't UTF-8 replaced, locked so the
+    /// replacements aren't saved over the original by accident
+    pub fn open_lossy(&mut self, path: &Path) {
+        if self.finish_open(path, load::read_lossy(path, Operation::Open)) && !self.readonly {
+            self.toggle_readonly();
+        }
+    }
+
+    /// Lock the document that couldn't be saved to `path`, if it is still
+    /// the open one
+    pub fn lock_document(&mut self, path: &Path) {
+        if self.current_file.as_deref() == Some(path) && !self.readonly {
+            self.toggle_readonly();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_actions_offered() {
+        let path = PathBuf::from("notes.md");
+        let action = |error| describe(&error).1;
+        let denied = |operation| EditorError::PermissionDenied { path: path.clone(), operation };
+        assert_eq!(action(denied(Operation::Save)), Some(ToastAction::LockDocument(path.clone())));
+        assert_eq!(action(denied(Operation::Open)), None);
+        let utf8 = |lossy_available| EditorError::InvalidUtf8 { path: path.clone(), operation: Operation::Open, lossy_available };
+        assert_eq!(action(utf8(true)), Some(ToastAction::OpenLossy(path.clone())));
+        assert_eq!(action(utf8(false)), None);
+        assert_eq!(action(EditorError::NotFound { path: path.clone(), operation: Operation::Open }), None);
+    }
+}
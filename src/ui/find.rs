@@ -0,0 +1,430 @@
+//! Ctrl+F: a find bar searching whichever pane is showing. The editor's
+//! matches are painted over its text; the preview's are marked in the
+//! rendered elements' text as it is laid out.
+
+use crate::app::RmdApp;
+use crate::editor::search::{Matcher, SearchOptions};
+use crate::i18n::t;
+use crate::ui::layouts::LayoutMode;
+use eframe::egui;
+use egui::text::{CCursor, LayoutJob};
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Pane the find bar searches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindTarget {
+    Editor,
+    Preview,
+}
+
+/// State of the find bar
+#[derive(Default)]
+pub struct FindBar {
+    open: bool,
+    /// Focus the query field when next drawn
+    focus: bool,
+    query: String,
+    options: SearchOptions,
+    /// `None` when the query is empty or not a valid expression
+    matcher: Option<Matcher>,
+    error: Option<String>,
+    /// Pane searched this frame, while the bar is open
+    target: Option<FindTarget>,
+    /// Where each match is, as the source offset of its element and the
+    /// offset in the element's text, in the order they were found
+    found: Vec<(usize, usize)>,
+    /// Matches met while drawing the preview this frame
+    drawing: Vec<(usize, usize)>,
+    current: usize,
+    /// Bring the current match into view when it is next drawn
+    reveal: bool,
+    /// The current match was just drawn and wants bringing into view
+    revealed: bool,
+    /// Byte ranges of the matches in the editor's text, by its revision
+    editor_matches: Option<(u64, Vec<Range<usize>>)>,
+}
+
+impl FindBar {
+    /// Open the bar with its field focused, searching for `selected` when given
+    pub fn show(&mut self, selected: Option<String>) {
+        self.open = true;
+        self.focus = true;
+        if let Some(selected) = selected {
+            self.query = selected;
+            self.query_changed();
+        }
+        self.reveal = true;
+    }
+
+    fn close(&mut self) {
+        self.open = false;
+        self.target = None;
+    }
+
+    fn query_changed(&mut self) {
+        self.editor_matches = None;
+        self.reveal = true;
+        if self.query.is_empty() {
+            self.matcher = None;
+            self.error = None;
+            return;
+        }
+        match Matcher::new(&self.query, self.options) {
+            Ok(matcher) => {
+                self.matcher = Some(matcher);
+                self.error = None;
+            }
+            Err(e) => {
+                self.matcher = None;
+                self.error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Move to the next match, or the previous one, wrapping around
+    fn step(&mut self, backwards: bool) {
+        let count = self.found.len();
+        if count == 0 {
+            return;
+        }
+        self.current = if backwards { (self.current + count - 1) % count } else { (self.current + 1) % count };
+        self.reveal = true;
+    }
+
+    /// Take the matches found this frame. When they differ from the last
+    /// ones, e.g. after the text was rendered again, the current match is
+    /// the first one at or after where the current one was.
+    fn settle(&mut self, found: Vec<(usize, usize)>) {
+        if found == self.found {
+            return;
+        }
+        let anchor = self.found.get(self.current).copied();
+        self.current = anchor.and_then(|anchor| found.iter().position(|&at| at >= anchor)).unwrap_or(0);
+        self.found = found;
+    }
+
+    /// Whether the preview's text is being searched
+    fn in_preview(&self) -> bool {
+        self.target == Some(FindTarget::Preview) && self.matcher.is_some()
+    }
+
+    /// Start counting the matches drawn in the preview
+    pub fn begin_preview(&mut self) {
+        self.drawing.clear();
+    }
+
+    /// Done drawing the preview; its matches are the ones found
+    pub fn end_preview(&mut self) {
+        if self.target == Some(FindTarget::Preview) {
+            let drawing = std::mem::take(&mut self.drawing);
+            self.settle(drawing);
+        }
+    }
+
+    /// Matches in `text`, an element's text starting at `offset` in the
+    /// element at source offset `element`, each with whether it is the
+    /// current one
+    fn preview_matches(&mut self, text: &str, element: usize, offset: usize) -> Vec<(Range<usize>, bool)> {
+        let Some(matcher) = self.matcher.as_ref().filter(|_| self.in_preview()) else {
+            return Vec::new();
+        };
+        let mut matches = Vec::new();
+        for range in matcher.find_all(text) {
+            let current = self.drawing.len() == self.current;
+            if current && self.reveal {
+                self.reveal = false;
+                self.revealed = true;
+            }
+            self.drawing.push((element, offset + range.start));
+            matches.push((range, current));
+        }
+        matches
+    }
+
+    /// Whether the current match was just drawn and should be scrolled to
+    pub fn take_reveal(&mut self) -> bool {
+        std::mem::take(&mut self.revealed)
+    }
+
+    /// "3 of 17", or why there is nothing to count
+    fn status(&self) -> String {
+        if let Some(error) = &self.error {
+            return error.clone();
+        }
+        match self.found.len() {
+            _ if self.matcher.is_none() => String::new(),
+            0 => t!("find.no_results").to_string(),
+            total => t!("find.count", current = self.current.min(total - 1) + 1, total = total),
+        }
+    }
+}
+
+/// `job` with the background of `ranges` of its text set
+fn mark_ranges(mut job: LayoutJob, ranges: &[(Range<usize>, egui::Color32)]) -> LayoutJob {
+    for (range, color) in ranges {
+        let mut sections = Vec::with_capacity(job.sections.len() + 2);
+        for section in job.sections {
+            let bytes = section.byte_range.clone();
+            let start = range.start.clamp(bytes.start, bytes.end);
+            let end = range.end.clamp(bytes.start, bytes.end);
+            if start == end {
+                sections.push(section);
+                continue;
+            }
+            for (part, marked) in [(bytes.start..start, false), (start..end, true), (end..bytes.end, false)] {
+                if part.is_empty() {
+                    continue;
+                }
+                let mut piece = section.clone();
+                // Only the first piece keeps the space before the section
+                if part.start != bytes.start {
+                    piece.leading_space = 0.0;
+                }
+                piece.byte_range = part;
+                if marked {
+                    piece.format.background = *color;
+                }
+                sections.push(piece);
+            }
+        }
+        job.sections = sections;
+    }
+    job
+}
+
+/// Rectangles covering the characters `chars` of `galley`, one per row
+fn range_rects(galley: &egui::Galley, chars: Range<usize>) -> Vec<egui::Rect> {
+    let start = galley.pos_from_ccursor(CCursor::new(chars.start));
+    let end = galley.pos_from_ccursor(CCursor::new(chars.end));
+    if (start.top() - end.top()).abs() < 0.5 {
+        return vec![egui::Rect::from_min_max(start.left_top(), end.right_bottom())];
+    }
+    galley
+        .rows
+        .iter()
+        .map(|row| row.rect)
+        .filter(|row| row.bottom() > start.top() + 0.5 && row.top() < end.bottom() - 0.5)
+        .map(|row| {
+            let left = if row.top() <= start.top() + 0.5 { start.left() } else { row.left() };
+            let right = if row.bottom() >= end.bottom() - 0.5 { end.left() } else { row.right() };
+            egui::Rect::from_x_y_ranges(left..=right.max(left), row.y_range())
+        })
+        .collect()
+}
+
+fn query_id() -> egui::Id {
+    egui::Id::new("find_query")
+}
+
+impl RmdApp {
+    /// Ctrl+F: open the find bar, searching for the selected text when the
+    /// editor has one on a single line
+    pub fn open_find(&mut self) {
+        let selected = self
+            .editor
+            .selection()
+            .filter(|_| self.find_target() == FindTarget::Editor)
+            .map(|range| self.editor.text()[range].to_string())
+            .filter(|selected| !selected.contains('\n'));
+        self.find.show(selected);
+    }
+
+    /// The preview when it is the only pane showing, otherwise the editor
+    fn find_target(&self) -> FindTarget {
+        if self.layout.mode == LayoutMode::PreviewOnly && !self.show_changes {
+            FindTarget::Preview
+        } else {
+            FindTarget::Editor
+        }
+    }
+
+    /// The find bar above the panes: the query, its options, the count,
+    /// and buttons to step through the matches
+    pub fn ui_find_bar(&mut self, ctx: &egui::Context) {
+        if !self.find.open {
+            return;
+        }
+        let target = self.find_target();
+        if self.find.target != Some(target) {
+            // The query carries over to the pane now showing
+            self.find.target = Some(target);
+            self.find.reveal = true;
+        }
+
+        let focused = ctx.memory(|m| m.has_focus(query_id()));
+        let (next, previous, close) = ctx.input_mut(|i| {
+            if !focused {
+                return (false, false, false);
+            }
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::SHIFT, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        let mut step = if next { Some(false) } else if previous { Some(true) } else { None };
+        let mut close = close;
+
+        egui::TopBottomPanel::top("find_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let find = &mut self.find;
+                let field = ui.add(
+                    egui::TextEdit::singleline(&mut find.query)
+                        .id(query_id())
+                        .hint_text(t!("find.hint"))
+                        .desired_width(240.0),
+                );
+                if std::mem::take(&mut find.focus) {
+                    field.request_focus();
+                }
+                let mut changed = field.changed();
+                let options = &mut find.options;
+                changed |= ui.toggle_value(&mut options.case_sensitive, "Aa").on_hover_text(t!("find.match_case")).changed();
+                changed |= ui.toggle_value(&mut options.whole_word, "ab").on_hover_text(t!("find.whole_word")).changed();
+                changed |= ui.toggle_value(&mut options.regex, ".*").on_hover_text(t!("find.regex")).changed();
+                if changed {
+                    find.query_changed();
+                }
+                let color = if find.error.is_some() { self.theme.error } else { self.theme.text_muted };
+                ui.label(egui::RichText::new(find.status()).color(color));
+                if ui.small_button("⏶").on_hover_text(t!("find.previous")).clicked() {
+                    step = Some(true);
+                }
+                if ui.small_button("⏷").on_hover_text(t!("find.next")).clicked() {
+                    step = Some(false);
+                }
+                close |= ui.small_button("✕").on_hover_text(t!("find.close")).clicked();
+            });
+        });
+
+        if let Some(backwards) = step {
+            self.find.step(backwards);
+            ctx.memory_mut(|m| m.request_focus(query_id()));
+        }
+        if close {
+            // Leave the current match selected in the editor
+            let selected = match &self.find.editor_matches {
+                Some((_, ranges)) if target == FindTarget::Editor => ranges.get(self.find.current).cloned(),
+                _ => None,
+            };
+            if let Some(range) = selected {
+                self.editor.set_selection(range.start, range.end);
+            }
+            self.find.close();
+        }
+    }
+
+    /// Mark the find bar's matches in `text`, shown by the preview for the
+    /// element at source offset `element`. `offset` is where `text` starts
+    /// among the element's texts, for elements showing more than one.
+    pub(crate) fn find_highlight(
+        &mut self,
+        ui: &egui::Ui,
+        text: impl Into<egui::WidgetText>,
+        element: usize,
+        offset: usize,
+    ) -> egui::WidgetText {
+        let text = text.into();
+        if !self.find.in_preview() {
+            return text;
+        }
+        let job = text.into_layout_job(ui.style(), egui::FontSelection::Default, egui::Align::Center);
+        let matches = self.find.preview_matches(&job.text, element, offset);
+        if matches.is_empty() {
+            return job.into();
+        }
+        let selection = ui.visuals().selection.bg_fill;
+        let ranges: Vec<_> = matches
+            .into_iter()
+            .map(|(range, current)| (range, if current { selection } else { selection.gamma_multiply(0.35) }))
+            .collect();
+        mark_ranges(job, &ranges).into()
+    }
+
+    /// Paint the find bar's matches over the editor's text, scrolling to
+    /// the current one when it changed
+    pub fn paint_find_matches(&mut self, ui: &egui::Ui, text: &str, galley: &Arc<egui::Galley>, galley_pos: egui::Pos2) {
+        if self.find.target != Some(FindTarget::Editor) {
+            return;
+        }
+        let Some(matcher) = &self.find.matcher else {
+            return;
+        };
+        let revision = self.editor.revision();
+        if self.find.editor_matches.as_ref().map(|(at, _)| *at) != Some(revision) {
+            self.find.editor_matches = Some((revision, matcher.find_all(text)));
+        }
+        let ranges = self.find.editor_matches.as_ref().map(|(_, ranges)| ranges.clone()).unwrap_or_default();
+        self.find.settle(ranges.iter().map(|range| (range.start, 0)).collect());
+
+        let selection = ui.visuals().selection.bg_fill;
+        let clip = ui.clip_rect();
+        let painter = ui.painter();
+        // Character offsets, counted on from the previous match
+        let (mut chars, mut bytes) = (0, 0);
+        for (index, range) in ranges.iter().enumerate() {
+            chars += text[bytes..range.start].chars().count();
+            let start = chars;
+            chars += text[range.clone()].chars().count();
+            bytes = range.end;
+            let current = index == self.find.current;
+            let rects: Vec<egui::Rect> = range_rects(galley, start..chars)
+                .into_iter()
+                .map(|rect| rect.translate(galley_pos.to_vec2()))
+                .collect();
+            if current && self.find.reveal {
+                self.find.reveal = false;
+                if let Some(first) = rects.first() {
+                    ui.scroll_to_rect(*first, Some(egui::Align::Center));
+                }
+            }
+            let color = if current { selection.gamma_multiply(0.8) } else { selection.gamma_multiply(0.35) };
+            for rect in rects.into_iter().filter(|rect| rect.intersects(clip)) {
+                painter.rect_filled(rect, 2.0, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mark_ranges() {
+        let mut job = LayoutJob::default();
+        job.append("one two", 0.0, egui::TextFormat::default());
+        job.append(" three", 0.0, egui::TextFormat::default());
+        let red = egui::Color32::RED;
+        let job = mark_ranges(job, &[(2..5, red), (8..10, red)]);
+        let pieces: Vec<(&str, bool)> = job
+            .sections
+            .iter()
+            .map(|section| (&job.text[section.byte_range.clone()], section.format.background == red))
+            .collect();
+        assert_eq!(
+            pieces,
+            [("on", false), ("e t", true), ("wo", false), (" ", false), ("th", true), ("ree", false)]
+        );
+    }
+
+    #[test]
+    fn test_current_match_kept_across_renders() {
+        let mut find = FindBar::default();
+        find.settle(vec![(0, 3), (10, 0), (20, 5)]);
+        find.step(false);
+        find.step(false);
+        assert_eq!(find.current, 2);
+        // Text added before the current match moves it along
+        find.settle(vec![(0, 3), (10, 0), (12, 1), (24, 5)]);
+        assert_eq!(find.current, 3);
+        find.step(false);
+        assert_eq!(find.current, 0);
+        find.step(true);
+        assert_eq!(find.current, 3);
+        // Gone, and nothing after it: back to the first
+        find.settle(vec![(0, 3)]);
+        assert_eq!(find.current, 0);
+    }
+}
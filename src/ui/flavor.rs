@@ -0,0 +1,48 @@
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::flavor::{self, Flavor};
+use eframe::egui;
+
+impl RmdApp {
+    /// Status bar menu choosing the render profile. Documents with front
+    /// matter record the choice there; others change the global default.
+    pub fn ui_flavor_selector(&mut self, ui: &mut egui::Ui) {
+        let text = self.editor.text();
+        let current = self.markdown_renderer.flavor_for(&text);
+        let pinned = flavor::front_matter_flavor(&text).is_some();
+
+        let mut chosen = None;
+        ui.menu_button(current.label(), |ui| {
+            for flavor in Flavor::ALL {
+                if ui.radio(flavor == current, flavor.label()).clicked() {
+                    chosen = Some(flavor);
+                    ui.close_menu();
+                }
+            }
+            ui.separator();
+            let note = if pinned {
+                t!("status.flavor_from_front_matter")
+            } else {
+                t!("status.flavor_default")
+            };
+            ui.label(egui::RichText::new(note).small().color(self.theme.text_muted));
+        })
+        .response
+        .on_hover_text(t!("status.flavor_hint"));
+
+        let Some(flavor) = chosen.filter(|&flavor| flavor != current) else {
+            return;
+        };
+        match flavor::set_front_matter_flavor(&text, flavor) {
+            Some(_) if self.readonly => self.show_readonly_hint(),
+            Some((range, replacement)) => {
+                self.editor.replace_range_in_place(range, &replacement);
+                self.has_unsaved_changes = true;
+            }
+            None => {
+                self.config.preview.flavor = flavor;
+                self.apply_markdown_settings();
+            }
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Moving keyboard focus between the parts of the window without the
+//! mouse: F6 and Shift+F6 cycle through the editor, preview and sidebar,
+//! and Ctrl+1..9 go straight to one of them, opening it if need be
+
+use crate::app::RmdApp;
+use crate::ui::layouts::LayoutMode;
+use eframe::egui;
+
+/// Focusable stand-in for the preview, which has no widget of its own to
+/// take focus; arrow and page keys scroll it while focused
+pub const PREVIEW_FOCUS_ID: &str = "preview_focus";
+
+/// How far an arrow key scrolls the focused preview, in points
+const ARROW_SCROLL: f32 = 40.0;
+
+/// A part of the window that can take keyboard focus
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pane {
+    Editor,
+    Preview,
+    Files,
+    Outline,
+    Bookmarks,
+    Notifications,
+    Search,
+    Problems,
+    Scratchpad,
+}
+
+/// Panes F6 goes through, in order
+const CYCLE: [Pane; 3] = [Pane::Editor, Pane::Preview, Pane::Files];
+
+/// The pane F6 moves to from `current`, or Shift+F6 when `backwards`,
+/// among those `shown`. Sidebar sections count as the sidebar.
+fn cycle(current: Option<Pane>, backwards: bool, shown: impl Fn(Pane) -> bool) -> Option<Pane> {
+    let current = current.map(|pane| match pane {
+        Pane::Outline | Pane::Bookmarks => Pane::Files,
+        pane => pane,
+    });
+    let start = current.and_then(|pane| CYCLE.iter().position(|&p| p == pane));
+    let len = CYCLE.len();
+    (1..=len)
+        .map(|step| match (start, backwards) {
+            (Some(start), false) => (start + step) % len,
+            (Some(start), true) => (start + len - step) % len,
+            (None, false) => step - 1,
+            (None, true) => len - step,
+        })
+        .map(|index| CYCLE[index])
+        .find(|&pane| shown(pane))
+}
+
+/// Where each pane was drawn, to tell which holds the focused widget, and
+/// a pane waiting to move focus into itself when next drawn
+#[derive(Default)]
+pub struct PaneFocus {
+    /// Each pane's rectangle, with the pass it was drawn in
+    rects: Vec<(Pane, egui::Rect, u64)>,
+    requested: Option<Pane>,
+}
+
+impl PaneFocus {
+    /// Record that `pane` was drawn in `rect` this pass
+    pub fn note(&mut self, ctx: &egui::Context, pane: Pane, rect: egui::Rect) {
+        let pass = ctx.cumulative_pass_nr();
+        self.rects.retain(|(p, _, _)| *p != pane);
+        self.rects.push((pane, rect, pass));
+    }
+
+    /// Whether `pane` was drawn this pass or the last
+    fn shown(&self, ctx: &egui::Context, pane: Pane) -> bool {
+        let pass = ctx.cumulative_pass_nr();
+        self.rects.iter().any(|&(p, _, drawn)| p == pane && drawn + 1 >= pass)
+    }
+
+    /// The pane holding the focused widget. Sidebar sections are drawn
+    /// inside the sidebar, so the smallest matching rectangle wins.
+    fn current(&self, ctx: &egui::Context) -> Option<Pane> {
+        let focused = ctx.memory(|m| m.focused())?;
+        let center = ctx.read_response(focused)?.rect.center();
+        self.rects
+            .iter()
+            .filter(|(pane, rect, _)| rect.contains(center) && self.shown(ctx, *pane))
+            .min_by(|a, b| a.1.area().total_cmp(&b.1.area()))
+            .map(|(pane, _, _)| *pane)
+    }
+
+    /// Whether `pane` should focus its first widget now; true once per request
+    pub fn take(&mut self, pane: Pane) -> bool {
+        if self.requested == Some(pane) {
+            self.requested = None;
+            return true;
+        }
+        false
+    }
+}
+
+impl RmdApp {
+    /// Move keyboard focus into `pane`, showing it first if it is hidden
+    pub fn focus_pane(&mut self, ctx: &egui::Context, pane: Pane) {
+        match pane {
+            Pane::Editor => {
+                if !self.layout.has_editor() {
+                    self.layout.set_mode(LayoutMode::Split);
+                }
+                ctx.memory_mut(|m| m.request_focus(self.editor.widget_id()));
+            }
+            Pane::Preview => {
+                if !self.layout.has_preview() {
+                    self.layout.set_mode(LayoutMode::Split);
+                }
+                ctx.memory_mut(|m| m.request_focus(egui::Id::new(PREVIEW_FOCUS_ID)));
+            }
+            Pane::Files | Pane::Outline | Pane::Bookmarks => {
+                // The sidebar only shows with a folder open
+                if self.workspace_root.is_none() {
+                    return;
+                }
+                self.show_sidebar = true;
+                self.pane_focus.requested = Some(pane);
+            }
+            Pane::Notifications => {
+                if !self.toasts.is_empty() {
+                    self.pane_focus.requested = Some(pane);
+                }
+            }
+            Pane::Search => self.focus_search_panel(ctx),
+            Pane::Problems => {
+                self.show_problems = true;
+                self.pane_focus.requested = Some(pane);
+            }
+            Pane::Scratchpad => self.focus_scratchpad(ctx),
+        }
+    }
+
+    /// F6: focus the next of the editor, preview and sidebar that is shown
+    pub fn cycle_pane_focus(&mut self, ctx: &egui::Context, backwards: bool) {
+        let current = self.pane_focus.current(ctx);
+        if let Some(pane) = cycle(current, backwards, |pane| self.pane_focus.shown(ctx, pane)) {
+            self.focus_pane(ctx, pane);
+        }
+    }
+
+    /// Make the preview focusable, announced by name, and scroll it with
+    /// the arrow, page, Space and Home/End keys while it has focus, or in
+    /// Preview Only while nothing else does. Returns the offset to scroll
+    /// to, if a key moved it.
+    pub(crate) fn preview_keyboard_scroll(&mut self, ui: &mut egui::Ui) -> Option<f32> {
+        let id = egui::Id::new(PREVIEW_FOCUS_ID);
+        let rect = ui.available_rect_before_wrap();
+        self.pane_focus.note(ui.ctx(), Pane::Preview, rect);
+        let response = ui.interact(rect, id, egui::Sense::focusable_noninteractive());
+        response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Other, true, "Preview"));
+        // With only the preview shown, its keys work before anything is clicked
+        let alone = self.layout.mode == LayoutMode::PreviewOnly && ui.memory(|m| m.focused().is_none());
+        if response.has_focus() {
+            // Up and down scroll instead of moving focus to a widget inside
+            let filter = egui::EventFilter { vertical_arrows: true, ..Default::default() };
+            ui.memory_mut(|m| m.set_focus_lock_filter(id, filter));
+            ui.painter().rect_stroke(rect.shrink(1.0), 2.0, ui.visuals().selection.stroke);
+        } else if !alone {
+            return None;
+        }
+
+        let page = rect.height() * 0.9;
+        let offset = self.preview.scroll_offset;
+        ui.input_mut(|i| {
+            // Shift+Space comes before Space, which would match it too
+            let keys = [
+                (egui::Modifiers::NONE, egui::Key::ArrowUp, -ARROW_SCROLL),
+                (egui::Modifiers::NONE, egui::Key::ArrowDown, ARROW_SCROLL),
+                (egui::Modifiers::NONE, egui::Key::PageUp, -page),
+                (egui::Modifiers::NONE, egui::Key::PageDown, page),
+                (egui::Modifiers::SHIFT, egui::Key::Space, -page),
+                (egui::Modifiers::NONE, egui::Key::Space, page),
+                (egui::Modifiers::NONE, egui::Key::Home, f32::NEG_INFINITY),
+                (egui::Modifiers::NONE, egui::Key::End, f32::INFINITY),
+            ];
+            let delta: f32 = keys
+                .iter()
+                .filter(|(modifiers, key, _)| i.consume_key(*modifiers, *key))
+                .map(|(_, _, delta)| delta)
+                .sum();
+            (delta != 0.0).then(|| (offset + delta).clamp(0.0, self.preview.max_scroll()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle() {
+        let all = |_: Pane| true;
+        assert_eq!(cycle(None, false, all), Some(Pane::Editor));
+        assert_eq!(cycle(Some(Pane::Editor), false, all), Some(Pane::Preview));
+        assert_eq!(cycle(Some(Pane::Files), false, all), Some(Pane::Editor));
+        assert_eq!(cycle(Some(Pane::Editor), true, all), Some(Pane::Files));
+        // From a sidebar section, on past the sidebar
+        assert_eq!(cycle(Some(Pane::Outline), false, all), Some(Pane::Editor));
+        assert_eq!(cycle(Some(Pane::Notifications), true, all), Some(Pane::Files));
+
+        let no_preview = |pane: Pane| pane != Pane::Preview;
+        assert_eq!(cycle(Some(Pane::Editor), false, no_preview), Some(Pane::Files));
+        assert_eq!(cycle(Some(Pane::Files), true, no_preview), Some(Pane::Editor));
+        assert_eq!(cycle(Some(Pane::Editor), false, |pane| pane == Pane::Editor), Some(Pane::Editor));
+        assert_eq!(cycle(None, false, |_| false), None);
+    }
+}
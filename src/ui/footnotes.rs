@@ -0,0 +1,85 @@
+//! Footnote markers and the footnote section at the end of the preview
+
+use crate::app::RmdApp;
+use crate::markdown::footnotes::superscript;
+use crate::markdown::RenderedElement;
+use crate::preview::FootnoteJump;
+use eframe::egui;
+
+impl RmdApp {
+    /// Superscript marker showing the footnote on hover and jumping to it
+    /// on click, or an error-colored one for an undefined footnote
+    pub(super) fn render_footnote_reference(
+        &mut self,
+        ui: &mut egui::Ui,
+        label: &str,
+        number: Option<usize>,
+        occurrence: usize,
+        source: &str,
+    ) -> egui::Response {
+        let small = egui::TextStyle::Small.resolve(ui.style()).size * self.preview.zoom;
+        let Some(number) = number else {
+            let marker = egui::RichText::new(format!("[^{}]", label)).small_raised().size(small).color(self.theme.error);
+            return ui
+                .add(egui::Label::new(marker).sense(egui::Sense::click()))
+                .on_hover_text("footnote not defined");
+        };
+
+        let response = ui.link(egui::RichText::new(number.to_string()).small_raised().size(small));
+        let target = FootnoteJump::Reference(label.to_string(), occurrence);
+        if self.preview.take_footnote_jump(&target) {
+            response.scroll_to_me(Some(egui::Align::Center));
+        }
+        if response.clicked() {
+            self.preview.jump_to_footnote(FootnoteJump::Definition(label.to_string()));
+        }
+        let body = self.preview.footnote(label).map(<[RenderedElement]>::to_vec).unwrap_or_default();
+        response.on_hover_ui(|ui| {
+            ui.set_max_width(360.0);
+            for element in &body {
+                self.render_element(ui, element, source);
+            }
+        })
+    }
+
+    /// A footnote in the section at the end, with a back-link to each
+    /// reference to it
+    pub(super) fn render_footnote_definition(
+        &mut self,
+        ui: &mut egui::Ui,
+        label: &str,
+        number: usize,
+        references: usize,
+        body: &[RenderedElement],
+        source: &str,
+    ) {
+        let muted = self.theme.text_muted;
+        let body_size = egui::TextStyle::Body.resolve(ui.style()).size * self.preview.zoom;
+        let response = ui
+            .horizontal_top(|ui| {
+                let name = if number > 0 { format!("{}.", number) } else { format!("[^{}]", label) };
+                ui.label(egui::RichText::new(name).size(body_size).color(muted));
+                ui.vertical(|ui| {
+                    for element in body {
+                        self.render_element(ui, element, source);
+                    }
+                    ui.horizontal(|ui| {
+                        for occurrence in 1..=references {
+                            let text = match references {
+                                1 => "↩".to_string(),
+                                _ => format!("↩{}", superscript(occurrence)),
+                            };
+                            if ui.link(egui::RichText::new(text).size(body_size)).on_hover_text("Back to the reference").clicked() {
+                                let target = FootnoteJump::Reference(label.to_string(), occurrence);
+                                self.preview.jump_to_footnote(target);
+                            }
+                        }
+                    });
+                });
+            })
+            .response;
+        if self.preview.take_footnote_jump(&FootnoteJump::Definition(label.to_string())) {
+            response.scroll_to_me(Some(egui::Align::TOP));
+        }
+    }
+}
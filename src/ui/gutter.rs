@@ -0,0 +1,87 @@
+//! Selecting whole lines from the editor gutter: a click selects the line
+//! beside it, dragging extends the selection line by line, and Shift+click
+//! extends it from its anchor
+
+use crate::app::RmdApp;
+use crate::ui::bookmarks;
+use crate::utils;
+use eframe::egui;
+use std::sync::Arc;
+
+/// A selection being dragged out in the gutter
+#[derive(Default)]
+pub struct GutterDrag {
+    /// Line the drag started on, while the button is held
+    anchor_line: Option<usize>,
+    /// Lines last selected, so an unchanged drag doesn't select again
+    selected: Option<(usize, usize)>,
+}
+
+impl GutterDrag {
+    /// The lines to select with the pointer on `line`: the first press
+    /// anchors on `line`, or on `shift_anchor` with Shift held. `None`
+    /// when they are already selected.
+    fn drag_to(&mut self, line: usize, shift_anchor: Option<usize>) -> Option<(usize, usize)> {
+        let anchor = *self.anchor_line.get_or_insert(shift_anchor.unwrap_or(line));
+        let lines = (anchor, line);
+        (self.selected != Some(lines)).then(|| {
+            self.selected = Some(lines);
+            lines
+        })
+    }
+
+    fn release(&mut self) {
+        self.anchor_line = None;
+        self.selected = None;
+    }
+}
+
+/// Zero-based line of `text` laid out at the height of `pos`
+fn line_at(text: &str, galley: &egui::Galley, galley_pos: egui::Pos2, pos: egui::Pos2) -> usize {
+    let cursor = galley.cursor_from_pos(egui::vec2(0.0, pos.y - galley_pos.y));
+    let byte = utils::char_to_byte(text, cursor.ccursor.index);
+    text[..byte].matches('\n').count()
+}
+
+impl RmdApp {
+    /// Select lines by clicking and dragging in the gutter left of the text
+    /// in `rect`, the editor's area. Color swatches further left keep their
+    /// own clicks.
+    pub fn handle_gutter(&mut self, ui: &egui::Ui, rect: egui::Rect, text: &str, galley: &Arc<egui::Galley>, galley_pos: egui::Pos2) {
+        let left = (galley_pos.x - bookmarks::GUTTER_WIDTH - 3.0).max(rect.left());
+        let gutter = egui::Rect::from_min_max(egui::pos2(left, rect.top()), egui::pos2(galley_pos.x, rect.bottom()));
+        let response = ui
+            .interact(gutter, ui.id().with("editor_gutter"), egui::Sense::click_and_drag())
+            .on_hover_cursor(egui::CursorIcon::Default);
+        if !response.is_pointer_button_down_on() {
+            self.gutter.release();
+            return;
+        }
+        let Some(pointer) = ui.input(|i| i.pointer.interact_pos()) else {
+            return;
+        };
+        let line = line_at(text, galley, galley_pos, pointer);
+        let shift_anchor = ui.input(|i| i.modifiers.shift).then(|| self.editor.anchor_line());
+        if let Some((anchor, cursor)) = self.gutter.drag_to(line, shift_anchor) {
+            self.editor.select_lines(anchor, cursor);
+            ui.ctx().request_repaint();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drag_to() {
+        let mut drag = GutterDrag::default();
+        assert_eq!(drag.drag_to(4, None), Some((4, 4)));
+        // Held still: nothing new to select
+        assert_eq!(drag.drag_to(4, None), None);
+        assert_eq!(drag.drag_to(6, None), Some((4, 6)));
+        assert_eq!(drag.drag_to(2, Some(9)), Some((4, 2)));
+        drag.release();
+        assert_eq!(drag.drag_to(7, Some(1)), Some((1, 7)));
+    }
+}
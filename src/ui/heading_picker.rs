@@ -0,0 +1,215 @@
+//! Ctrl+Shift+O: jump to a heading of the open document, with the preview
+//! following the selection so each candidate can be looked at first
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::numbering::HeadingNumbers;
+use crate::markdown::outline::HeadingEntry;
+use crate::ui::widgets::ClickAway;
+use crate::utils;
+use eframe::egui;
+
+/// Indentation per heading level in the list
+const LEVEL_INDENT: &str = "  ";
+
+/// State of the Go to Heading picker
+#[derive(Default)]
+pub struct HeadingPicker {
+    open: bool,
+    click_away: ClickAway,
+    query: String,
+    selected: usize,
+    /// Where the preview was scrolled when the picker opened
+    scroll_before: f32,
+    /// Heading the preview was last scrolled to while picking
+    peeked: Option<usize>,
+}
+
+struct HeadingRow {
+    offset: usize,
+    label: String,
+    level: u8,
+}
+
+impl HeadingPicker {
+    pub fn open(&mut self, preview_scroll: f32) {
+        self.open = true;
+        self.click_away = ClickAway::opened();
+        self.query.clear();
+        self.selected = 0;
+        self.scroll_before = preview_scroll;
+        self.peeked = None;
+    }
+}
+
+/// Headings matching `query`, best first, labelled with their number when
+/// headings are numbered and indented by level while unfiltered
+fn rows(headings: &[HeadingEntry], query: &str, numbered: bool) -> Vec<HeadingRow> {
+    let mut numbers = numbered.then(|| HeadingNumbers::for_levels(headings.iter().map(|h| h.level)));
+    let mut matches: Vec<(i32, HeadingRow)> = headings
+        .iter()
+        .filter_map(|heading| {
+            // Numbered before filtering, so a heading keeps its number
+            let number = numbers.as_mut().map(|numbers| numbers.next(heading.level));
+            let score = utils::fuzzy_score(&heading.text, query)?;
+            let label = match number {
+                Some(number) => format!("{} {}", number, heading.text),
+                None => heading.text.clone(),
+            };
+            Some((score, HeadingRow { offset: heading.offset, label, level: heading.level }))
+        })
+        .collect();
+    let filtered = !query.trim().is_empty();
+    if filtered {
+        matches.sort_by_key(|(score, _)| -score);
+    }
+    matches
+        .into_iter()
+        .map(|(_, mut row)| {
+            if !filtered {
+                row.label = format!("{}{}", LEVEL_INDENT.repeat(usize::from(row.level) - 1), row.label);
+            }
+            row
+        })
+        .collect()
+}
+
+impl RmdApp {
+    /// Render the Go to Heading picker. Moving through the list scrolls the
+    /// preview to each heading; Enter moves the caret there and Escape puts
+    /// the preview back where it was.
+    pub fn ui_heading_picker(&mut self, ctx: &egui::Context) {
+        if !self.heading_picker.open {
+            return;
+        }
+
+        let headings = self.editor.headings().to_vec();
+        let rows = rows(&headings, &self.heading_picker.query, self.config.preview.number_headings);
+
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        let picker = &mut self.heading_picker;
+        if up {
+            picker.selected = picker.selected.saturating_sub(1);
+        }
+        if down && picker.selected + 1 < rows.len() {
+            picker.selected += 1;
+        }
+        picker.selected = picker.selected.min(rows.len().saturating_sub(1));
+
+        let mut chosen = enter.then_some(picker.selected);
+        let mut moved = up || down;
+        let muted = self.theme.text_muted;
+        let area = egui::Area::new(egui::Id::new("heading_picker"))
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 80.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_width(480.0);
+                    let input = ui.add(
+                        egui::TextEdit::singleline(&mut picker.query)
+                            .hint_text(t!("picker.go_to_heading"))
+                            .desired_width(f32::INFINITY),
+                    );
+                    input.request_focus();
+                    if input.changed() {
+                        picker.selected = 0;
+                        moved = true;
+                    }
+
+                    ui.separator();
+                    egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                        if rows.is_empty() {
+                            let text = if headings.is_empty() { t!("picker.no_headings") } else { t!("picker.no_matches") };
+                            ui.label(egui::RichText::new(text).color(muted));
+                        }
+                        for (index, row) in rows.iter().enumerate() {
+                            let selected = index == picker.selected;
+                            let response = ui
+                                .horizontal(|ui| {
+                                    let response = ui.selectable_label(selected, &row.label);
+                                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                        ui.label(egui::RichText::new(format!("H{}", row.level)).small().color(muted));
+                                    });
+                                    response
+                                })
+                                .inner;
+                            if selected && (up || down) {
+                                response.scroll_to_me(None);
+                            }
+                            if response.clicked() {
+                                chosen = Some(index);
+                            }
+                        }
+                    });
+                });
+            });
+
+        // The preview shows another file while pinned, so there is nothing
+        // of this document to scroll to
+        let can_peek = self.layout.has_preview() && self.preview.pinned().is_none();
+        let dismissed = self.heading_picker.click_away.clicked(&area.response) || escape;
+        let offset = chosen.and_then(|index| rows.get(index)).map(|row| row.offset);
+
+        if let Some(offset) = offset {
+            self.heading_picker.open = false;
+            ctx.memory_mut(|m| m.request_focus(self.editor.widget_id()));
+            self.editor.set_cursor(offset);
+            if can_peek {
+                self.preview.jump_to_heading(offset);
+            }
+        } else if dismissed {
+            self.heading_picker.open = false;
+            ctx.memory_mut(|m| m.request_focus(self.editor.widget_id()));
+            if self.heading_picker.peeked.is_some() {
+                self.preview.scroll_back_to(self.heading_picker.scroll_before);
+            }
+        } else if moved && can_peek {
+            if let Some(row) = rows.get(self.heading_picker.selected) {
+                if self.heading_picker.peeked != Some(row.offset) {
+                    self.heading_picker.peeked = Some(row.offset);
+                    self.preview.jump_to_heading(row.offset);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::outline;
+    use crate::markdown::{ElementKind, MarkdownRenderer};
+
+    #[test]
+    fn test_rows() {
+        let headings = outline::headings("# Intro\n## Setup\n## Usage\n### Setup again\n");
+        let labels = |query: &str, numbered: bool| -> Vec<String> {
+            rows(&headings, query, numbered).into_iter().map(|row| row.label).collect()
+        };
+        assert_eq!(labels("", false), ["Intro", "  Setup", "  Usage", "    Setup again"]);
+        assert_eq!(labels("", true), ["1. Intro", "  1.1 Setup", "  1.2 Usage", "    1.2.1 Setup again"]);
+        // Filtered rows are flat and keep their numbers
+        assert_eq!(labels("setup", true), ["1.1 Setup", "1.2.1 Setup again"]);
+        assert!(labels("zzz", false).is_empty());
+    }
+
+    #[test]
+    fn test_rows_jump_to_rendered_headings() {
+        let text = "# Intro\n\nText\n\n  ## Setup ##\n\nLong name\n---------\n";
+        let spans: Vec<usize> = MarkdownRenderer::default()
+            .render(text)
+            .iter()
+            .filter(|element| matches!(element.kind, ElementKind::Heading(..)))
+            .map(|element| element.span.start)
+            .collect();
+        let offsets: Vec<usize> = rows(&outline::headings(text), "", true).iter().map(|row| row.offset).collect();
+        assert_eq!(offsets, spans);
+    }
+}
@@ -0,0 +1,114 @@
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::history::{Snapshot, SnapshotStore};
+use crate::utils;
+use eframe::egui;
+use std::time::SystemTime;
+
+/// State of the File History dialog
+pub struct FileHistoryDialog {
+    snapshots: Vec<Snapshot>,
+    selected: Option<usize>,
+    /// Contents of the selected snapshot
+    preview: String,
+}
+
+impl RmdApp {
+    /// Open the File History dialog for the current document
+    pub fn open_file_history(&mut self) {
+        let Some(path) = &self.current_file else {
+            return;
+        };
+        let snapshots = match SnapshotStore::open_default(self.config.history.clone()) {
+            Ok(store) => store.list(path),
+            Err(e) => {
+                self.notify_error(t!("error.open_file_history"), e);
+                return;
+            }
+        };
+        self.file_history = Some(FileHistoryDialog {
+            snapshots,
+            selected: None,
+            preview: String::new(),
+        });
+    }
+
+    /// Render the File History dialog
+    pub fn ui_file_history(&mut self, ctx: &egui::Context) {
+        let Some(mut dialog) = self.file_history.take() else {
+            return;
+        };
+
+        let mut open = true;
+        let mut restore = false;
+        let readonly = self.readonly;
+        egui::Window::new(t!("history.title"))
+            .id(egui::Id::new("file_history"))
+            .open(&mut open)
+            .default_size([720.0, 420.0])
+            .show(ctx, |ui| {
+                if dialog.snapshots.is_empty() {
+                    ui.label(t!("history.empty"));
+                    return;
+                }
+
+                egui::SidePanel::left("file_history_list")
+                    .resizable(false)
+                    .exact_width(220.0)
+                    .show_inside(ui, |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            for (index, snapshot) in dialog.snapshots.iter().enumerate() {
+                                let label = format!(
+                                    "{}  ·  {}",
+                                    time_ago(snapshot.timestamp),
+                                    utils::format_file_size(snapshot.size)
+                                );
+                                if ui.selectable_label(dialog.selected == Some(index), label).clicked() {
+                                    dialog.selected = Some(index);
+                                    dialog.preview = std::fs::read_to_string(&snapshot.path)
+                                        .unwrap_or_else(|e| t!("history.read_failed", error = e));
+                                }
+                            }
+                        });
+                    });
+
+                egui::TopBottomPanel::bottom("file_history_actions")
+                    .show_inside(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            restore = ui
+                                .add_enabled(dialog.selected.is_some() && !readonly, egui::Button::new(t!("history.restore")))
+                                .on_hover_text(t!("history.restore_hint"))
+                                .clicked();
+                        });
+                    });
+
+                egui::ScrollArea::both()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        if dialog.selected.is_some() {
+                            ui.label(egui::RichText::new(&dialog.preview).monospace());
+                        } else {
+                            ui.label(egui::RichText::new(t!("history.select")).color(self.theme.text_muted));
+                        }
+                    });
+            });
+
+        if restore {
+            self.editor.replace_text(&dialog.preview);
+            self.has_unsaved_changes = true;
+            open = false;
+        }
+        if open {
+            self.file_history = Some(dialog);
+        }
+    }
+}
+
+/// "5m 3s ago" style age of a snapshot
+fn time_ago(timestamp: SystemTime) -> String {
+    match SystemTime::now().duration_since(timestamp) {
+        Ok(age) if age.as_secs() < 60 * 60 * 24 => t!("history.ago", age = utils::format_duration(age)),
+        Ok(age) => t!("history.days_ago", days = age.as_secs() / (60 * 60 * 24)),
+        Err(_) => t!("history.just_now").to_string(),
+    }
+}
@@ -0,0 +1,216 @@
+//! The preview's HTML source view: the page the exporter would write for
+//! the document, highlighted and read-only
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::export::{HtmlOptions, Stylesheet};
+use crate::markdown::include;
+use crate::theme::Theme;
+use eframe::egui;
+use std::ops::Range;
+
+/// Kinds of HTML source text, colored apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HtmlToken {
+    /// `<`, the tag name, `=`, and `>`
+    Tag,
+    Attribute,
+    /// A quoted attribute value
+    Value,
+    Comment,
+    Text,
+}
+
+/// Consecutive spans of `html` by kind
+#[derive(Default)]
+struct Spans(Vec<(Range<usize>, HtmlToken)>);
+
+impl Spans {
+    fn push(&mut self, range: Range<usize>, kind: HtmlToken) {
+        if range.is_empty() {
+            return;
+        }
+        match self.0.last_mut() {
+            Some((last, last_kind)) if *last_kind == kind && last.end == range.start => last.end = range.end,
+            _ => self.0.push((range, kind)),
+        }
+    }
+}
+
+/// Split `html` into spans to color. Tags that never close run to the end.
+fn tokens(html: &str) -> Vec<(Range<usize>, HtmlToken)> {
+    let mut spans = Spans::default();
+    let find_from = |pos: usize, pattern: &str| html[pos..].find(pattern).map(|i| pos + i);
+    let mut pos = 0;
+    while pos < html.len() {
+        if html[pos..].starts_with("<!--") {
+            let end = find_from(pos, "-->").map_or(html.len(), |i| i + 3);
+            spans.push(pos..end, HtmlToken::Comment);
+            pos = end;
+        } else if html[pos..].starts_with('<') {
+            pos = tag(html, pos, &mut spans);
+        } else {
+            let end = find_from(pos, "<").unwrap_or(html.len());
+            spans.push(pos..end, HtmlToken::Text);
+            pos = end;
+        }
+    }
+    spans.0
+}
+
+/// Spans of the tag starting at `start`; returns where it ends
+fn tag(html: &str, start: usize, spans: &mut Spans) -> usize {
+    let name_end = |from: usize| {
+        html[from..]
+            .find(|c: char| c.is_whitespace() || matches!(c, '>' | '/' | '=' | '"' | '\''))
+            .map_or(html.len(), |i| from + i)
+    };
+    let mut pos = start + 1;
+    if html[pos..].starts_with(['/', '!', '?']) {
+        pos += 1;
+    }
+    pos = name_end(pos).max(pos);
+    spans.push(start..pos, HtmlToken::Tag);
+
+    while let Some(c) = html[pos..].chars().next() {
+        let next = pos + c.len_utf8();
+        match c {
+            '>' => {
+                spans.push(pos..next, HtmlToken::Tag);
+                return next;
+            }
+            '/' | '=' => spans.push(pos..next, HtmlToken::Tag),
+            '"' | '\'' => {
+                let end = html[next..].find(c).map_or(html.len(), |i| next + i + 1);
+                spans.push(pos..end, HtmlToken::Value);
+                pos = end;
+                continue;
+            }
+            c if c.is_whitespace() => spans.push(pos..next, HtmlToken::Text),
+            _ => {
+                let end = name_end(pos).max(next);
+                spans.push(pos..end, HtmlToken::Attribute);
+                pos = end;
+                continue;
+            }
+        }
+        pos = next;
+    }
+    pos
+}
+
+/// `html` laid out in `font`, colored by `theme`
+fn layout_job(html: &str, font: egui::FontId, theme: &Theme) -> egui::text::LayoutJob {
+    let mut job = egui::text::LayoutJob::default();
+    for (range, kind) in tokens(html) {
+        let color = match kind {
+            HtmlToken::Tag => theme.accent,
+            HtmlToken::Attribute => theme.warning,
+            HtmlToken::Value => theme.success,
+            HtmlToken::Comment => theme.text_muted,
+            HtmlToken::Text => theme.text,
+        };
+        job.append(&html[range], 0.0, egui::TextFormat::simple(font.clone(), color));
+    }
+    job
+}
+
+impl RmdApp {
+    /// Toggles above the preview: the rendered view or its HTML, pages,
+    /// and following the caret
+    pub(crate) fn ui_preview_toolbar(&mut self, ui: &mut egui::Ui) {
+        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+            ui.toggle_value(&mut self.preview.show_html, "</> HTML")
+                .on_hover_text("View HTML source: the page Export would write");
+            let pages = ui
+                .toggle_value(&mut self.config.preview.paginated, t!("preview.pages"))
+                .on_hover_text(t!("preview.pages_hint"));
+            if pages.changed() {
+                // The same content stays at the top of the view, laid out anew
+                self.preview.hold_view_top();
+            }
+            ui.toggle_value(&mut self.config.preview.follow_caret, "⌖ Follow")
+                .on_hover_text("Follow the caret: scroll to where you click or type, and click here to move the caret");
+        });
+    }
+
+    /// The exported page for the previewed document, read-only but
+    /// selectable. Its scroll position is kept apart from the rendered
+    /// view's, which is where it was when toggled back.
+    pub(crate) fn render_html_source(&mut self, ui: &mut egui::Ui, text: &str) {
+        let document = match self.preview.pinned() {
+            Some(pinned) => Some(pinned.path().to_path_buf()),
+            None => self.current_file.clone(),
+        };
+        let title = document
+            .as_deref()
+            .and_then(|path| path.file_stem())
+            .map_or("Untitled".to_string(), |stem| stem.to_string_lossy().to_string());
+        let options = HtmlOptions {
+            flavor: self.markdown_renderer.flavor_for(text),
+            number_headings: self.config.preview.number_headings,
+            toc_depth: self.config.export.include_toc.then_some(self.config.writing.toc_depth),
+        };
+        // Images stay links here even when the export embeds them
+        let css_file = format!("{}.css", title.replace(' ', "%20"));
+        let css = match self.export_css() {
+            Ok(css) => css,
+            Err(e) => format!("/* {:#} */", e).into(),
+        };
+        let stylesheet = if self.config.export.link_css {
+            Stylesheet::Link(&css_file)
+        } else {
+            Stylesheet::Inline(&css)
+        };
+        let font = egui::FontId::monospace(self.config.font.editor_font_size * self.preview.zoom);
+        let wiki = self.markdown_renderer.wiki_links_for(options.flavor);
+        let text = include::expand(text, document.as_deref(), wiki);
+        let lang = self.document_language().map(str::to_string);
+        let mut html = self.preview.html_source(&text, options, stylesheet, &title, lang.as_deref());
+        let theme = &self.theme;
+        let mut layouter = |ui: &egui::Ui, html: &str, wrap_width: f32| {
+            let mut job = layout_job(html, font.clone(), theme);
+            job.wrap.max_width = wrap_width;
+            ui.fonts(|f| f.layout_job(job))
+        };
+        egui::ScrollArea::vertical()
+            .id_salt(("preview_html", document))
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut html)
+                        .code_editor()
+                        .desired_width(f32::INFINITY)
+                        .frame(false)
+                        .layouter(&mut layouter),
+                );
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokens() {
+        let html = "<!-- note --><a href=\"x.html\" hidden>Go</a>";
+        let kinds: Vec<(&str, HtmlToken)> = tokens(html).into_iter().map(|(range, kind)| (&html[range], kind)).collect();
+        assert_eq!(
+            kinds,
+            [
+                ("<!-- note -->", HtmlToken::Comment),
+                ("<a", HtmlToken::Tag),
+                (" ", HtmlToken::Text),
+                ("href", HtmlToken::Attribute),
+                ("=", HtmlToken::Tag),
+                ("\"x.html\"", HtmlToken::Value),
+                (" ", HtmlToken::Text),
+                ("hidden", HtmlToken::Attribute),
+                (">", HtmlToken::Tag),
+                ("Go", HtmlToken::Text),
+                ("</a>", HtmlToken::Tag),
+            ]
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! The open document's indentation: detected from the file when it opens,
+//! changed from the status bar, and used by Tab, by tab stops as the text
+//! is laid out, and by the indentation guides
+
+use crate::app::RmdApp;
+use crate::editor::indent::{self, Indentation};
+use crate::i18n::t;
+use eframe::egui::{self, text::LayoutJob, text::LayoutSection};
+use std::path::PathBuf;
+
+/// Sizes offered in the status bar popup
+const SIZES: [usize; 4] = [2, 3, 4, 8];
+
+/// Indentation chosen or detected for the open document, and which document
+/// that was
+#[derive(Default)]
+pub struct IndentationState {
+    /// Overrides the config while this document is open
+    document: Option<Indentation>,
+    file: Option<PathBuf>,
+    /// Whether the document was looked at since it opened
+    detected: bool,
+    /// Converting leaves fenced code blocks as they are
+    skip_code: bool,
+}
+
+impl RmdApp {
+    /// Detect the indentation of a document once it has opened, unless
+    /// that's turned off. Opening another document starts over.
+    pub fn update_indentation(&mut self) {
+        let state = &mut self.indentation;
+        if state.file != self.current_file {
+            *state = IndentationState { file: self.current_file.clone(), skip_code: state.skip_code, ..Default::default() };
+        }
+        if state.detected || self.loading_document.is_some() {
+            return;
+        }
+        state.detected = true;
+        if self.config.editor.detect_indentation {
+            state.document = indent::detect(&self.editor.text(), self.config.editor.tab_size.max(1));
+        }
+    }
+
+    /// How the open document is indented
+    pub fn indentation(&self) -> Indentation {
+        self.indentation.document.unwrap_or(Indentation {
+            spaces: self.config.editor.use_spaces_for_tabs,
+            size: self.config.editor.tab_size.max(1),
+        })
+    }
+
+    /// Status bar segment showing the indentation, with a popup to change
+    /// it for this document and to convert the document to it
+    pub fn ui_indentation_selector(&mut self, ui: &mut egui::Ui) {
+        let current = self.indentation();
+        let label = if current.spaces {
+            t!("status.spaces", size = current.size)
+        } else {
+            t!("status.tab_size", size = current.size)
+        };
+        let mut chosen = current;
+        let mut convert = None;
+        let skip_code = &mut self.indentation.skip_code;
+        ui.menu_button(label, |ui| {
+            ui.radio_value(&mut chosen.spaces, true, t!("indentation.spaces"));
+            ui.radio_value(&mut chosen.spaces, false, t!("indentation.tabs"));
+            ui.separator();
+            ui.label(t!("indentation.size"));
+            ui.horizontal(|ui| {
+                for size in SIZES {
+                    ui.selectable_value(&mut chosen.size, size, size.to_string());
+                }
+            });
+            ui.separator();
+            if ui.button(t!("indentation.convert_to_spaces")).clicked() {
+                convert = Some(Indentation { spaces: true, ..current });
+                ui.close_menu();
+            }
+            if ui.button(t!("indentation.convert_to_tabs")).clicked() {
+                convert = Some(Indentation { spaces: false, ..current });
+                ui.close_menu();
+            }
+            ui.checkbox(skip_code, t!("indentation.skip_code"));
+        })
+        .response
+        .on_hover_text(t!("status.indentation_hint"));
+
+        if chosen != current {
+            self.indentation.document = Some(chosen);
+        }
+        let Some(to) = convert else {
+            return;
+        };
+        if self.readonly {
+            self.show_readonly_hint();
+            return;
+        }
+        self.indentation.document = Some(to);
+        let text = self.editor.text();
+        let converted = indent::convert(&text, to, self.indentation.skip_code);
+        if converted != text {
+            self.editor.replace_text(&converted);
+            self.has_unsaved_changes = true;
+        }
+    }
+}
+
+/// `job` with each tab in a section of its own, sized to reach the next
+/// stop every `tab_size` columns. A tab is laid out as wide as four spaces
+/// of its font, so its font is scaled to the columns it spans, and what
+/// rounding leaves over is made up with leading space.
+pub fn with_tab_stops(job: LayoutJob, tab_size: usize, fonts: &egui::text::Fonts) -> LayoutJob {
+    if !job.text.contains('\t') {
+        return job;
+    }
+    let tab_size = tab_size.max(1);
+    let mut sections = Vec::with_capacity(job.sections.len());
+    let mut column = 0;
+    for section in &job.sections {
+        let format = &section.format;
+        let space = fonts.glyph_width(&format.font_id, ' ');
+        let mut start = section.byte_range.start;
+        for (offset, c) in job.text[section.byte_range.clone()].char_indices() {
+            let at = section.byte_range.start + offset;
+            match c {
+                '\n' => column = 0,
+                '\t' => {
+                    if start < at {
+                        sections.push(LayoutSection { leading_space: 0.0, byte_range: start..at, format: format.clone() });
+                    }
+                    let columns = tab_size - column % tab_size;
+                    let mut tab_format = format.clone();
+                    tab_format.font_id.size = format.font_id.size * columns as f32 / egui::text::TAB_SIZE as f32;
+                    tab_format.line_height = Some(format.line_height.unwrap_or_else(|| fonts.row_height(&format.font_id)));
+                    let width = fonts.glyph_width(&tab_format.font_id, '\t');
+                    sections.push(LayoutSection {
+                        leading_space: space * columns as f32 - width,
+                        byte_range: at..at + 1,
+                        format: tab_format,
+                    });
+                    start = at + 1;
+                    column += columns;
+                }
+                _ => column += 1,
+            }
+        }
+        if start < section.byte_range.end {
+            sections.push(LayoutSection { leading_space: section.leading_space, byte_range: start..section.byte_range.end, format: format.clone() });
+        }
+    }
+    LayoutJob { sections, ..job }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tab_stops() {
+        let fonts = egui::text::Fonts::new(1.0, 1024, egui::FontDefinitions::default());
+        let font = egui::FontId::monospace(14.0);
+        let space = fonts.glyph_width(&font, ' ');
+        let text = "a\tb\n\tc\t";
+        let job = LayoutJob::simple(text.to_string(), font, egui::Color32::WHITE, f32::INFINITY);
+        let galley = fonts.layout_job(with_tab_stops(job, 3, &fonts));
+        // Each glyph lands on its column, a tab reaching the next multiple of three
+        let columns: Vec<(char, f32)> = galley.rows.iter().flat_map(|row| &row.glyphs).map(|glyph| (glyph.chr, glyph.pos.x / space)).collect();
+        let expected = [('a', 0.0), ('\t', 1.0), ('b', 3.0), ('\t', 0.0), ('c', 3.0), ('\t', 4.0)];
+        for ((chr, column), (expected_chr, expected_column)) in columns.iter().zip(expected) {
+            assert_eq!(*chr, expected_chr);
+            assert!((column - expected_column).abs() < 0.2, "{} at column {}, not {}", chr, column, expected_column);
+        }
+        assert!((galley.rows[1].rect.width() / space - 6.0).abs() < 0.2);
+    }
+}
@@ -0,0 +1,228 @@
+//! Insert > Image: pick an image, describe it, and link or copy it in
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::assets::ASSETS_FOLDER;
+use crate::markdown::linkcheck;
+use crate::workspace::files::unique_path;
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp"];
+
+/// Longest side of the thumbnail, in pixels
+const THUMBNAIL_SIZE: u32 = 160;
+
+/// State of the Insert Image dialog
+pub struct InsertImageDialog {
+    source: PathBuf,
+    alt: String,
+    title: String,
+    /// Copy the image into the assets folder rather than linking to it in place
+    copy: bool,
+    /// Link with an absolute path rather than one relative to the document
+    absolute: bool,
+    /// `None` until loaded; `Some(None)` when the image couldn't be decoded
+    thumbnail: Option<Option<egui::TextureHandle>>,
+    error: Option<String>,
+}
+
+impl InsertImageDialog {
+    fn new(source: PathBuf) -> Self {
+        let alt = source.file_stem().unwrap_or_default().to_string_lossy().replace(['-', '_'], " ");
+        Self {
+            source,
+            alt,
+            title: String::new(),
+            copy: false,
+            absolute: false,
+            thumbnail: None,
+            error: None,
+        }
+    }
+}
+
+/// Ask for an image file, starting in `folder`
+fn pick_image(folder: Option<&Path>) -> Option<PathBuf> {
+    let mut dialog = rfd::FileDialog::new()
+        .set_title(t!("insert_image.title"))
+        .add_filter(t!("dialog.images"), &IMAGE_EXTENSIONS)
+        .add_filter(t!("dialog.all_files"), &["*"]);
+    if let Some(folder) = folder {
+        dialog = dialog.set_directory(folder);
+    }
+    dialog.pick_file()
+}
+
+/// `![alt](url "title")`, leaving the title out when empty
+pub fn image_markdown(alt: &str, url: &str, title: &str) -> String {
+    let alt = alt.replace('[', "\\[").replace(']', "\\]");
+    let url = if url.contains([' ', '(', ')']) { format!("<{}>", url) } else { url.to_string() };
+    match title.trim() {
+        "" => format!("![{}]({})", alt, url),
+        title => format!("![{}]({} \"{}\")", alt, url, title.replace('"', "\\\"")),
+    }
+}
+
+/// Small texture of the image at `path`, or `None` if it can't be decoded
+fn load_thumbnail(ctx: &egui::Context, path: &Path) -> Option<egui::TextureHandle> {
+    let image = image::open(path).ok()?.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgba8();
+    let size = [image.width() as usize, image.height() as usize];
+    let pixels = egui::ColorImage::from_rgba_unmultiplied(size, image.as_raw());
+    Some(ctx.load_texture("insert_image_thumbnail", pixels, egui::TextureOptions::LINEAR))
+}
+
+impl RmdApp {
+    /// Pick an image and open the dialog describing how to insert it
+    pub fn insert_image_dialog(&mut self) {
+        if self.readonly {
+            self.show_readonly_hint();
+            return;
+        }
+        let folder = self.current_file.as_deref().and_then(Path::parent);
+        if let Some(source) = pick_image(folder) {
+            self.insert_image = Some(InsertImageDialog::new(source));
+        }
+    }
+
+    /// Render the Insert Image dialog and insert the image when confirmed
+    pub fn ui_insert_image(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = self.insert_image.as_mut() else {
+            return;
+        };
+        let doc_dir = self.current_file.as_deref().and_then(Path::parent).map(Path::to_path_buf);
+        if dialog.thumbnail.is_none() {
+            dialog.thumbnail = Some(load_thumbnail(ctx, &dialog.source));
+        }
+
+        let mut open = true;
+        let mut insert = false;
+        let mut cancel = false;
+        let muted = self.theme.text_muted;
+        let error_color = self.theme.error;
+        egui::Window::new(t!("insert_image.title"))
+            .id(egui::Id::new("insert_image"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal_top(|ui| {
+                    match dialog.thumbnail.as_ref().and_then(Option::as_ref) {
+                        Some(texture) => {
+                            ui.image((texture.id(), texture.size_vec2()));
+                        }
+                        None => {
+                            ui.label(egui::RichText::new(t!("insert_image.no_preview")).color(muted));
+                        }
+                    }
+                    ui.vertical(|ui| {
+                        let name = dialog.source.file_name().unwrap_or_default().to_string_lossy();
+                        ui.strong(name).on_hover_text(dialog.source.display().to_string());
+                        if ui.button(t!("dialog.browse")).clicked() {
+                            if let Some(source) = pick_image(dialog.source.parent()) {
+                                *dialog = InsertImageDialog::new(source);
+                            }
+                        }
+                    });
+                });
+                ui.separator();
+
+                egui::Grid::new("insert_image_fields").num_columns(2).show(ui, |ui| {
+                    ui.label(t!("insert_image.alt"));
+                    ui.add(egui::TextEdit::singleline(&mut dialog.alt).hint_text(t!("insert_image.alt_hint")));
+                    ui.end_row();
+                    ui.label(t!("insert_image.image_title"));
+                    ui.add(egui::TextEdit::singleline(&mut dialog.title).hint_text(t!("insert_image.image_title_hint")));
+                    ui.end_row();
+                });
+                ui.add_space(4.0);
+
+                match &doc_dir {
+                    Some(_) => {
+                        ui.radio_value(&mut dialog.copy, true, t!("insert_image.copy", folder = ASSETS_FOLDER))
+                            .on_hover_text(t!("insert_image.copy_hint"));
+                        ui.radio_value(&mut dialog.copy, false, t!("insert_image.link"));
+                        ui.add_enabled(
+                            !dialog.copy,
+                            egui::Checkbox::new(&mut dialog.absolute, t!("insert_image.absolute")),
+                        );
+                    }
+                    None => {
+                        ui.label(
+                            egui::RichText::new(t!("insert_image.unsaved"))
+                                .small()
+                                .color(muted),
+                        );
+                    }
+                }
+                if let Some(error) = &dialog.error {
+                    ui.colored_label(error_color, error);
+                }
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    insert = ui.button(t!("insert_image.insert")).clicked();
+                    cancel = ui.button(t!("dialog.cancel")).clicked();
+                });
+            });
+
+        if !open || cancel {
+            self.insert_image = None;
+            return;
+        }
+        if !insert {
+            return;
+        }
+        match self.link_for_image(doc_dir.as_deref()) {
+            Ok(url) => {
+                if let Some(dialog) = self.insert_image.take() {
+                    self.editor.insert_text(&image_markdown(&dialog.alt, &url, &dialog.title));
+                    self.has_unsaved_changes = true;
+                    ctx.memory_mut(|m| m.request_focus(self.editor.widget_id()));
+                }
+            }
+            Err(e) => {
+                if let Some(dialog) = self.insert_image.as_mut() {
+                    dialog.error = Some(e.to_string());
+                }
+            }
+        }
+    }
+
+    /// Copy the image into the assets folder if asked to, and work out the
+    /// URL the document should use for it
+    fn link_for_image(&self, doc_dir: Option<&Path>) -> std::io::Result<String> {
+        let Some(dialog) = &self.insert_image else {
+            return Ok(String::new());
+        };
+        let Some(doc_dir) = doc_dir else {
+            return Ok(linkcheck::absolute_link(&dialog.source));
+        };
+        let target = if dialog.copy {
+            let assets = doc_dir.join(ASSETS_FOLDER);
+            std::fs::create_dir_all(&assets)?;
+            let name = dialog.source.file_name().unwrap_or_default().to_string_lossy();
+            let target = unique_path(&assets, &name);
+            std::fs::copy(&dialog.source, &target)?;
+            target
+        } else {
+            dialog.source.clone()
+        };
+        Ok(if dialog.absolute && !dialog.copy {
+            linkcheck::absolute_link(&target)
+        } else {
+            linkcheck::relative_link(doc_dir, &target)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_markdown() {
+        assert_eq!(image_markdown("A cat", "img/cat.png", ""), "![A cat](img/cat.png)");
+        assert_eq!(image_markdown("[x]", "a b.png", "Say \"hi\""), "![\\[x\\]](<a b.png> \"Say \\\"hi\\\"\")");
+    }
+}
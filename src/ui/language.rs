@@ -0,0 +1,69 @@
+//! The open document's language: from its front matter or the config, and
+//! how the preview sets its text. Chinese, Japanese and Korean put no
+//! spaces between words, so their lines break between any two characters
+//! and sit further apart.
+
+use crate::app::RmdApp;
+use crate::markdown::language;
+use eframe::egui::{self, text::LayoutJob};
+
+/// Line height of CJK text, as a multiple of its font size
+const CJK_LINE_HEIGHT: f32 = 1.7;
+
+impl RmdApp {
+    /// Language tag of the open document, if it has one
+    pub fn document_language(&self) -> Option<&str> {
+        self.active_document_settings()
+            .and_then(|settings| settings.lang.as_deref())
+            .or_else(|| Some(self.config.preview.document_language.trim()).filter(|lang| language::is_valid_tag(lang)))
+    }
+
+    /// Whether the open document is in Chinese, Japanese or Korean
+    pub fn is_cjk_document(&self) -> bool {
+        self.document_language().is_some_and(language::is_cjk)
+    }
+
+    /// `text` set for the document's language: for CJK, lines may break
+    /// anywhere and, unless `tight`, are spaced further apart
+    pub fn typeset(&self, ui: &egui::Ui, text: egui::WidgetText, tight: bool) -> egui::WidgetText {
+        if !self.is_cjk_document() {
+            return text;
+        }
+        let job = text.into_layout_job(ui.style(), egui::FontSelection::Default, egui::Align::Center);
+        cjk_job(job, tight).into()
+    }
+}
+
+/// `job` breaking lines between any two characters, with taller rows
+/// unless `tight`
+fn cjk_job(mut job: LayoutJob, tight: bool) -> LayoutJob {
+    job.wrap.break_anywhere = true;
+    if !tight {
+        for section in &mut job.sections {
+            let format = &mut section.format;
+            format.line_height = Some(format.line_height.unwrap_or(0.0).max(format.font_id.size * CJK_LINE_HEIGHT));
+        }
+    }
+    job
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cjk_wraps_without_spaces() {
+        let fonts = egui::text::Fonts::new(1.0, 1024, egui::FontDefinitions::default());
+        let font = egui::FontId::proportional(16.0);
+        // Latin letters stand in for CJK: the point is there are no spaces
+        let text = "a".repeat(200);
+        let mut job = LayoutJob::simple(text, font, egui::Color32::WHITE, 100.0);
+        job.wrap.break_anywhere = false;
+        let plain = fonts.layout_job(job.clone());
+        let cjk = fonts.layout_job(cjk_job(job, false));
+        assert!(cjk.rows.len() > 1);
+        assert!(cjk.rect.width() <= 100.0 + 0.5);
+        assert!(cjk.rows[0].rect.height() >= 16.0 * CJK_LINE_HEIGHT - 0.5);
+        assert!(cjk.rows[0].rect.height() > plain.rows[0].rect.height());
+    }
+}
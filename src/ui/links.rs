@@ -0,0 +1,175 @@
+//! Opening links clicked in the preview. Web and mail links open at once,
+//! local paths open here or with the system, schemes set in the config open
+//! too, and anything else asks first. `data:` and `javascript:` never open.
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::linkcheck::has_scheme;
+use crate::markdown::outline;
+use crate::ui::toasts::Severity;
+use crate::utils;
+use crate::workspace::links::normalize;
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+/// Schemes that open without asking
+const SAFE_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// Schemes that are never opened: they run or carry content rather than
+/// point somewhere
+const REFUSED_SCHEMES: [&str; 3] = ["data", "javascript", "vbscript"];
+
+/// What clicking a link does
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    /// Open in the browser or mail client
+    Open,
+    /// Scroll to the heading with this slug
+    Anchor(String),
+    /// A file on disk
+    Local(PathBuf),
+    /// An unusual scheme, opened once the user agrees
+    Ask,
+    /// Never opened
+    Refused,
+}
+
+/// What clicking `url` should do. Relative paths resolve against `folder`,
+/// and schemes in `allowed` open like web links.
+pub fn classify(url: &str, folder: Option<&Path>, allowed: &[String]) -> LinkTarget {
+    let url = url.trim();
+    if let Some(anchor) = url.strip_prefix('#') {
+        return LinkTarget::Anchor(anchor.to_string());
+    }
+    if let Some(path) = url.strip_prefix("file://") {
+        // `file:///C:/notes` names a Windows drive after the third slash
+        let path = match path.strip_prefix('/') {
+            Some(rest) if rest.get(1..2) == Some(":") => rest,
+            _ => path,
+        };
+        return local(path, None);
+    }
+    if !has_scheme(url) {
+        return local(url, folder);
+    }
+    let scheme = url.split(':').next().unwrap_or_default().to_ascii_lowercase();
+    if REFUSED_SCHEMES.contains(&scheme.as_str()) {
+        LinkTarget::Refused
+    } else if SAFE_SCHEMES.contains(&scheme.as_str()) || allowed.iter().any(|allowed| allowed.eq_ignore_ascii_case(&scheme)) {
+        LinkTarget::Open
+    } else {
+        LinkTarget::Ask
+    }
+}
+
+/// The file a local link points at, without its query or fragment
+fn local(url: &str, folder: Option<&Path>) -> LinkTarget {
+    let path = url.split(['?', '#']).next().unwrap_or_default().replace("%20", " ");
+    let path = match folder {
+        Some(folder) if Path::new(&path).is_relative() => folder.join(path),
+        _ => PathBuf::from(path),
+    };
+    LinkTarget::Local(normalize(&path))
+}
+
+/// Offset of the heading in `source` whose anchor, generated or set with
+/// `{#id}`, is `slug`
+fn anchor_offset(source: &str, slug: &str) -> Option<usize> {
+    outline::headings(source).into_iter().find(|heading| heading.slug == slug).map(|heading| heading.offset)
+}
+
+impl RmdApp {
+    /// Follow a link clicked in the preview of `source`
+    pub fn open_link(&mut self, url: &str, source: &str) {
+        let folder = self.current_file.as_deref().and_then(Path::parent);
+        match classify(url, folder, &self.config.preview.open_link_schemes) {
+            LinkTarget::Open => self.open_in_system(url),
+            LinkTarget::Anchor(slug) => {
+                if let Some(offset) = anchor_offset(source, &slug) {
+                    self.preview.jump_to_heading(offset);
+                }
+            }
+            LinkTarget::Local(path) if !path.exists() => {
+                self.notify(Severity::Error, t!("links.missing", path = path.display()));
+            }
+            LinkTarget::Local(path) if utils::is_markdown_file(&path, &self.config.markdown_extensions) => {
+                self.open_path(&path);
+            }
+            LinkTarget::Local(path) => self.open_in_system(&path.to_string_lossy()),
+            LinkTarget::Ask => self.link_prompt = Some(url.to_string()),
+            LinkTarget::Refused => self.notify(Severity::Warning, t!("links.refused", url = url)),
+        }
+    }
+
+    /// Hand `url` to the system to open
+    fn open_in_system(&mut self, url: &str) {
+        if let Err(e) = webbrowser::open(url) {
+            self.notify_error(t!("error.open_failed", path = url), e);
+        }
+    }
+
+    /// Ask before opening a link with an unusual scheme, showing all of it
+    pub fn ui_link_prompt(&mut self, ctx: &egui::Context) {
+        let Some(url) = self.link_prompt.clone() else {
+            return;
+        };
+        let mut open = false;
+        let mut cancel = false;
+        egui::Window::new(t!("links.prompt_title"))
+            .id(egui::Id::new("open_link"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(t!("links.prompt"));
+                ui.add(egui::Label::new(egui::RichText::new(&url).monospace()).wrap());
+                ui.horizontal(|ui| {
+                    open = ui.button(t!("links.open")).clicked();
+                    cancel = ui.button(t!("dialog.cancel")).clicked();
+                });
+            });
+
+        if cancel {
+            self.link_prompt = None;
+        }
+        if open {
+            self.link_prompt = None;
+            self.open_in_system(&url);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markdown::{ElementKind, MarkdownRenderer};
+
+    #[test]
+    fn test_classify() {
+        let folder = Path::new("/docs/notes");
+        let allowed = vec!["zotero".to_string()];
+        let classify = |url| classify(url, Some(folder), &allowed);
+        assert_eq!(classify("https://example.com/a"), LinkTarget::Open);
+        assert_eq!(classify("MailTo:me@example.com"), LinkTarget::Open);
+        assert_eq!(classify("zotero://select/items/1"), LinkTarget::Open);
+        assert_eq!(classify("#setup"), LinkTarget::Anchor("setup".to_string()));
+        assert_eq!(classify("../todo%20list.md#today"), LinkTarget::Local(PathBuf::from("/docs/todo list.md")));
+        assert_eq!(classify("file:///etc/hosts"), LinkTarget::Local(PathBuf::from("/etc/hosts")));
+        assert_eq!(classify("ftp://example.com/file"), LinkTarget::Ask);
+        assert_eq!(classify("ms-settings:privacy"), LinkTarget::Ask);
+        assert_eq!(classify("javascript:alert(1)"), LinkTarget::Refused);
+        assert_eq!(classify(" DATA:text/html,<b>x</b>"), LinkTarget::Refused);
+    }
+
+    #[test]
+    fn test_explicit_anchor_jumps_to_heading() {
+        let source = "# Intro\n\nSee [usage](#usage).\n\n## Use `rmd` {#usage}\n\nText\n";
+        let rendered = MarkdownRenderer::default().render(source);
+        let heading = rendered.iter().find(|element| matches!(element.kind, ElementKind::Heading(2, _))).unwrap();
+        let LinkTarget::Anchor(slug) = classify("#usage", None, &[]) else {
+            panic!("not an anchor");
+        };
+        assert_eq!(anchor_offset(source, &slug), Some(heading.span.start));
+        assert_eq!(anchor_offset(source, "use-rmd"), None);
+    }
+}
@@ -1,129 +1,289 @@
+pub mod about;
+pub mod alerts;
+pub mod actions;
+pub mod bookmarks;
+pub mod breadcrumbs;
+pub mod browser_preview;
+pub mod changes;
+pub mod chrome;
+pub mod clipboard;
+pub mod color_swatches;
+pub mod consolidate;
+pub mod diagnostics;
+pub mod document_settings;
+pub mod export;
+pub mod file_errors;
+pub mod figures;
+pub mod find;
+pub mod flavor;
+pub mod focus;
+pub mod footnotes;
+pub mod gutter;
+pub mod heading_picker;
+pub mod history;
+pub mod html_source;
+pub mod indentation;
+pub mod insert_image;
+pub mod language;
 pub mod layouts;
+pub mod links;
+pub mod outline;
+pub mod pages;
+pub mod palette;
+pub mod pinned;
+pub mod positions;
+pub mod preferences;
+pub mod preview_header;
+pub mod preview_menu;
+pub mod preview_selection;
+pub mod problems;
+pub mod reading;
+pub mod readonly;
+pub mod rename_heading;
+pub mod scale;
+pub mod scratchpad;
+pub mod search_panel;
+pub mod settings_bundle;
+pub mod shortcuts;
+pub mod theme_switch;
+pub mod title;
+pub mod toasts;
+pub mod typing_aids;
+pub mod unsaved;
+pub mod updates;
+pub mod watch;
+pub mod whitespace;
+pub mod sidebar;
+pub mod split;
+pub mod stats;
+pub mod switcher;
+pub mod wiki;
 pub mod widgets;
+pub mod windows;
+pub mod workspaces;
+pub mod zoom;
 
 use crate::app::RmdApp;
+use crate::editor::autocomplete::CompletionContext;
+use crate::editor::diff_region;
+use crate::editor::error::{EditorError, Operation};
+use crate::editor::load;
+use crate::editor::snippets;
+use crate::editor::typing_aids::typed_char;
+use crate::editor::words;
+use crate::history::SnapshotStore;
+use crate::i18n::t;
+use crate::keymap::Action;
+use crate::markdown::cleanup::Cleanup;
+use crate::ui::focus::Pane;
 use crate::ui::layouts::LayoutMode;
+use crate::ui::scratchpad::ScratchFragment;
+use crate::ui::toasts::Severity;
+use crate::ui::unsaved::Replacement;
+use crate::utils;
+use crate::utils::timing::{self, Phase};
 use eframe::egui;
 
+/// How often to look for changes to files the preview includes
+const INCLUDE_POLL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// What drawing the preview's top-level elements looks for and notes
+pub(crate) struct PreviewPass {
+    /// Index of the element with the caret, to scroll to
+    follow: Option<usize>,
+    flash_color: egui::Color32,
+    selection_color: egui::Color32,
+    /// Top of the visible part of the preview
+    view_top: f32,
+    /// Index of the first element reaching below `view_top`
+    first_visible: Option<usize>,
+}
+
 /// UI components for RMD
 impl RmdApp {
     /// Render the menu bar
     pub fn ui_menu_bar(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
         egui::menu::bar(ui, |ui| {
-            ui.menu_button("File", |ui| {
-                if ui.button("New (Ctrl+N)").clicked() {
-                    self.new_file();
-                    ui.close_menu();
-                }
-                if ui.button("Open (Ctrl+O)").clicked() {
-                    self.open_file_dialog();
-                    ui.close_menu();
-                }
+            ui.menu_button(t!("menu.file"), |ui| {
+                self.action_button(ui, Action::NewFile, true);
+                self.action_button(ui, Action::NewWindow, true);
+                self.action_button(ui, Action::OpenFile, true);
+                self.action_button(ui, Action::OpenFolder, true);
+                ui.menu_button(t!("menu.open_recent"), |ui| self.ui_open_recent_menu(ui));
+                self.action_button(ui, Action::QuickOpen, true);
                 ui.separator();
-                if ui.button("Save (Ctrl+S)").clicked() {
-                    self.save_file();
-                    ui.close_menu();
-                }
-                if ui.button("Save As (Ctrl+Shift+S)").clicked() {
-                    self.save_file_as();
+                self.action_button(ui, Action::Save, true);
+                self.action_button(ui, Action::SaveAs, true);
+                self.action_button(ui, Action::FileHistory, self.current_file.is_some());
+                ui.menu_button(t!("menu.export"), |ui| {
+                    let exporting = self.folder_export.as_ref().is_some_and(|export| export.is_running());
+                    self.action_button(ui, Action::ExportHtml, true);
+                    self.action_button(ui, Action::ExportSelectionHtml, true);
+                    self.action_button(ui, Action::ExportDocx, true);
+                    self.action_button(ui, Action::ExportFolderHtml, !exporting);
+                });
+                ui.separator();
+                let mut readonly = self.readonly;
+                if ui.checkbox(&mut readonly, self.shortcut_label(t!("menu.read_only"), Action::ToggleReadonly)).clicked() {
+                    self.toggle_readonly();
                     ui.close_menu();
                 }
                 ui.separator();
-                if ui.button("Exit (Alt+F4)").clicked() {
+                if ui.button(format!("{} (Alt+F4)", t!("menu.exit"))).clicked() {
                     ctx.send_viewport_cmd(egui::ViewportCommand::Close);
                     ui.close_menu();
                 }
             });
 
-            ui.menu_button("Edit", |ui| {
-                let can_undo = self.editor.can_undo();
-                let can_redo = self.editor.can_redo();
+            ui.menu_button(t!("menu.edit"), |ui| {
+                let can_undo = !self.readonly && self.editor.can_undo();
+                let can_redo = !self.readonly && self.editor.can_redo();
 
-                if ui.add_enabled(can_undo, egui::Button::new("Undo (Ctrl+Z)")).clicked() {
-                    self.editor.undo();
-                    ui.close_menu();
-                }
-                if ui.add_enabled(can_redo, egui::Button::new("Redo (Ctrl+Y)")).clicked() {
-                    self.editor.redo();
-                    ui.close_menu();
-                }
+                self.action_button(ui, Action::Undo, can_undo);
+                self.action_button(ui, Action::Redo, can_redo);
                 ui.separator();
-                if ui.button("Cut (Ctrl+X)").clicked() {
+                if ui.button(format!("{} (Ctrl+X)", t!("menu.cut"))).clicked() {
                     // self.cut();
                     ui.close_menu();
                 }
-                if ui.button("Copy (Ctrl+C)").clicked() {
+                if ui.button(format!("{} (Ctrl+C)", t!("menu.copy"))).clicked() {
                     // self.copy();
                     ui.close_menu();
                 }
-                if ui.button("Paste (Ctrl+V)").clicked() {
+                if ui.button(format!("{} (Ctrl+V)", t!("menu.paste"))).clicked() {
                     // self.paste();
                     ui.close_menu();
                 }
+                self.action_button(ui, Action::PasteFromHistory, !self.readonly);
                 ui.separator();
-                if ui.button("Find (Ctrl+F)").clicked() {
-                    // self.open_find();
-                    ui.close_menu();
-                }
-                if ui.button("Replace (Ctrl+H)").clicked() {
+                self.action_button(ui, Action::Bold, !self.readonly);
+                self.action_button(ui, Action::Italic, !self.readonly);
+                self.action_button(ui, Action::HardWrap, !self.readonly);
+                self.action_button(ui, Action::UnwrapParagraph, !self.readonly);
+                self.action_button(ui, Action::FormatTable, !self.readonly);
+                self.action_button(ui, Action::RenumberLists, !self.readonly);
+                ui.add_enabled_ui(!self.readonly, |ui| {
+                    ui.menu_button(t!("category.structure"), |ui| {
+                        for action in Action::ALL.into_iter().filter(|a| a.category() == "Structure") {
+                            self.action_button(ui, action, true);
+                        }
+                    });
+                    ui.menu_button(t!("category.transform"), |ui| {
+                        for action in Action::ALL.into_iter().filter(|a| a.category() == "Transform") {
+                            self.action_button(ui, action, true);
+                        }
+                    });
+                });
+                ui.separator();
+                self.action_button(ui, Action::Find, true);
+                if ui.button(format!("{} (Ctrl+H)", t!("menu.replace"))).clicked() {
                     // self.open_replace();
                     ui.close_menu();
                 }
+                self.action_button(ui, Action::FindInFolder, true);
+                ui.separator();
+                self.action_button(ui, Action::CopySection, true);
+                self.action_button(ui, Action::ExportSelectionHtml, true);
+                ui.separator();
+                let has_bookmarks = !self.editor.bookmarks().is_empty();
+                self.action_button(ui, Action::ToggleBookmark, true);
+                self.action_button(ui, Action::NextBookmark, has_bookmarks);
+                self.action_button(ui, Action::PreviousBookmark, has_bookmarks);
+                ui.separator();
+                self.action_button(ui, Action::Preferences, true);
             });
 
-            ui.menu_button("View", |ui| {
-                ui.menu_button("Layout", |ui| {
-                    if ui.radio(self.layout.mode == LayoutMode::EditorOnly, "Editor Only").clicked() {
+            ui.menu_button(t!("category.insert"), |ui| {
+                for action in Action::ALL.into_iter().filter(|a| a.category() == "Insert") {
+                    self.action_button(ui, action, !self.readonly);
+                }
+            });
+
+            ui.menu_button(t!("category.view"), |ui| {
+                self.action_button(ui, Action::CommandPalette, true);
+                ui.separator();
+                ui.menu_button(t!("menu.layout"), |ui| {
+                    if ui.radio(self.layout.mode == LayoutMode::EditorOnly, t!("menu.editor_only")).clicked() {
                         self.layout.set_mode(LayoutMode::EditorOnly);
                         ui.close_menu();
                     }
-                    if ui.radio(self.layout.mode == LayoutMode::PreviewOnly, "Preview Only").clicked() {
+                    if ui.radio(self.layout.mode == LayoutMode::PreviewOnly, t!("menu.preview_only")).clicked() {
                         self.layout.set_mode(LayoutMode::PreviewOnly);
                         ui.close_menu();
                     }
-                    if ui.radio(self.layout.mode == LayoutMode::Split, "Split View").clicked() {
+                    if ui.radio(self.layout.mode == LayoutMode::Split, t!("menu.split_view")).clicked() {
                         self.layout.set_mode(LayoutMode::Split);
                         ui.close_menu();
                     }
                 });
                 ui.separator();
-                if ui.checkbox(&mut self.show_sidebar, "Show Sidebar").clicked() {
+                let sidebar_label = self.shortcut_label(t!("menu.show_sidebar"), Action::ToggleSidebar);
+                if ui.checkbox(&mut self.show_sidebar, sidebar_label).clicked() {
                     // Toggle handled by checkbox
                 }
-                if ui.checkbox(&mut self.show_toolbar, "Show Toolbar").clicked() {
+                if ui.checkbox(&mut self.show_toolbar, t!("menu.show_toolbar")).clicked() {
                     // Toggle handled by checkbox
                 }
-                if ui.checkbox(&mut self.show_status_bar, "Show Status Bar").clicked() {
+                if ui.checkbox(&mut self.show_status_bar, t!("menu.show_status_bar")).clicked() {
                     // Toggle handled by checkbox
                 }
+                ui.checkbox(&mut self.config.show_breadcrumbs, t!("menu.show_breadcrumbs"));
+                ui.checkbox(&mut self.config.editor.show_indent_guides, t!("menu.show_indent_guides"));
+                ui.checkbox(&mut self.config.editor.show_whitespace, t!("menu.show_whitespace"));
+                ui.add_enabled_ui(self.config.editor.show_whitespace, |ui| {
+                    ui.checkbox(&mut self.config.editor.whitespace_everywhere, t!("menu.whitespace_everywhere"))
+                        .on_hover_text(t!("menu.whitespace_everywhere_hint"));
+                });
+                let changes_label = self.shortcut_label(t!("menu.show_changes"), Action::ToggleChanges);
+                ui.checkbox(&mut self.show_changes, changes_label)
+                    .on_hover_text(t!("menu.show_changes_hint"));
+                let scratchpad_label = self.shortcut_label(t!("menu.show_scratchpad"), Action::ToggleScratchpad);
+                ui.checkbox(&mut self.config.window.scratchpad_open, scratchpad_label)
+                    .on_hover_text(t!("menu.show_scratchpad_hint"));
+                self.action_button(ui, Action::ToggleTheme, true);
+                ui.separator();
+                self.action_button(ui, Action::PinPreview, true);
+                if ui.add_enabled(self.preview.pinned().is_some(), egui::Button::new(t!("menu.unpin_preview"))).clicked() {
+                    self.preview.unpin();
+                    ui.close_menu();
+                }
+                self.action_button(ui, Action::OpenInBrowser, true);
+                ui.checkbox(&mut self.config.preview.browser_live_reload, t!("menu.live_reload"))
+                    .on_hover_text(t!("menu.live_reload_hint"));
                 ui.separator();
-                if ui.button("Zoom In (Ctrl++)").clicked() {
-                    // self.zoom_in();
+                if ui.button(format!("{} (Ctrl++)", t!("menu.zoom_in"))).clicked() {
+                    self.preview.zoom_in();
                     ui.close_menu();
                 }
-                if ui.button("Zoom Out (Ctrl+-)").clicked() {
-                    // self.zoom_out();
+                if ui.button(format!("{} (Ctrl+-)", t!("menu.zoom_out"))).clicked() {
+                    self.preview.zoom_out();
                     ui.close_menu();
                 }
-                if ui.button("Reset Zoom (Ctrl+0)").clicked() {
-                    // self.reset_zoom();
+                if ui.button(format!("{} (Ctrl+0)", t!("menu.reset_zoom"))).clicked() {
+                    self.preview.reset_zoom();
                     ui.close_menu();
                 }
             });
 
-            ui.menu_button("Help", |ui| {
-                if ui.button("Documentation").clicked() {
+            ui.menu_button(t!("category.tools"), |ui| {
+                self.action_button(ui, Action::CheckLinks, true);
+                ui.separator();
+                self.action_button(ui, Action::SplitDocument, true);
+                self.action_button(ui, Action::MergeFiles, !self.readonly);
+                self.action_button(ui, Action::ConsolidateImages, !self.readonly);
+            });
+
+            ui.menu_button(t!("menu.help"), |ui| {
+                if ui.button(t!("menu.documentation")).clicked() {
                     // Open documentation
                     ui.close_menu();
                 }
-                if ui.button("Keyboard Shortcuts").clicked() {
-                    // Show shortcuts
-                    ui.close_menu();
-                }
+                self.action_button(ui, Action::ShowShortcuts, true);
+                self.action_button(ui, Action::CheckForUpdates, self.updates.check.is_none());
                 ui.separator();
-                if ui.button("About").clicked() {
-                    // Show about dialog
+                if ui.button(t!("menu.about")).clicked() {
+                    self.show_about = true;
                     ui.close_menu();
                 }
             });
@@ -144,47 +304,47 @@ impl RmdApp {
             .show(ctx, |ui| {
                 ui.horizontal(|ui| {
                     // File operations
-                    if ui.button("New").clicked() {
-                        self.new_file();
+                    if ui.button(t!("toolbar.new")).clicked() {
+                        self.replace_document(Replacement::New);
                     }
-                    if ui.button("Open").clicked() {
+                    if ui.button(t!("toolbar.open")).clicked() {
                         self.open_file_dialog();
                     }
-                    if ui.button("Save").clicked() {
+                    if ui.button(t!("toolbar.save")).clicked() {
                         self.save_file();
                     }
 
                     ui.separator();
 
                     // Edit operations
-                    let can_undo = self.editor.can_undo();
-                    let can_redo = self.editor.can_redo();
+                    let can_undo = !self.readonly && self.editor.can_undo();
+                    let can_redo = !self.readonly && self.editor.can_redo();
 
-                    if ui.add_enabled(can_undo, egui::Button::new("Undo")).clicked() {
+                    if ui.add_enabled(can_undo, egui::Button::new(t!("toolbar.undo"))).clicked() {
                         self.editor.undo();
                     }
-                    if ui.add_enabled(can_redo, egui::Button::new("Redo")).clicked() {
+                    if ui.add_enabled(can_redo, egui::Button::new(t!("toolbar.redo"))).clicked() {
                         self.editor.redo();
                     }
 
                     ui.separator();
 
                     // View modes
-                    ui.label("View:");
-                    if ui.selectable_label(self.layout.mode == LayoutMode::EditorOnly, "Editor").clicked() {
+                    ui.label(t!("toolbar.view"));
+                    if ui.selectable_label(self.layout.mode == LayoutMode::EditorOnly, t!("toolbar.editor")).clicked() {
                         self.layout.set_mode(LayoutMode::EditorOnly);
                     }
-                    if ui.selectable_label(self.layout.mode == LayoutMode::Split, "Split").clicked() {
+                    if ui.selectable_label(self.layout.mode == LayoutMode::Split, t!("toolbar.split")).clicked() {
                         self.layout.set_mode(LayoutMode::Split);
                     }
-                    if ui.selectable_label(self.layout.mode == LayoutMode::PreviewOnly, "Preview").clicked() {
+                    if ui.selectable_label(self.layout.mode == LayoutMode::PreviewOnly, t!("toolbar.preview")).clicked() {
                         self.layout.set_mode(LayoutMode::PreviewOnly);
                     }
 
                     ui.separator();
 
                     // Theme toggle
-                    if ui.button("Theme").clicked() {
+                    if ui.button(t!("toolbar.theme")).clicked() {
                         // Toggle theme
                     }
                 });
@@ -201,33 +361,45 @@ impl RmdApp {
                     if let Some(ref path) = self.current_file {
                         let file_name = path.file_name()
                             .and_then(|n| n.to_str())
-                            .unwrap_or("Untitled");
-                        ui.label(file_name);
+                            .unwrap_or(t!("status.untitled"));
+                        ui.label(file_name).on_hover_text(path.display().to_string());
                     } else {
-                        ui.label("Untitled");
+                        ui.label(t!("status.untitled"));
                     }
 
                     if self.has_unsaved_changes {
-                        ui.label(egui::RichText::new("(modified)").color(ui.visuals().warn_fg_color));
+                        ui.label(egui::RichText::new(t!("status.modified")).color(ui.visuals().warn_fg_color));
+                    }
+                    if self.readonly
+                        && ui
+                            .add(egui::Label::new("🔒").sense(egui::Sense::click()))
+                            .on_hover_text(self.unlock_hint())
+                            .clicked()
+                    {
+                        self.toggle_readonly();
                     }
 
                     ui.separator();
 
                     // Cursor position
                     let (line, col) = self.editor.cursor_position();
-                    ui.label(format!("Ln {}, Col {}", line + 1, col + 1));
+                    ui.label(t!("status.position", line = line + 1, column = col + 1));
 
                     ui.separator();
 
                     // Document statistics
-                    let text = self.editor.text();
-                    let char_count = text.chars().count();
-                    let word_count = text.split_whitespace().count();
-                    ui.label(format!("{} words, {} chars", word_count, char_count));
+                    self.ui_document_stats(ui);
+
+                    if self.config.stats.enabled {
+                        ui.separator();
+                        self.ui_writing_stats(ui);
+                    }
 
                     // Right-aligned info
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        ui.label("Markdown");
+                        self.ui_flavor_selector(ui);
+                        self.ui_indentation_selector(ui);
+                        self.ui_document_settings_chip(ui);
                     });
                 });
             });
@@ -237,6 +409,11 @@ impl RmdApp {
     pub fn ui_main_content(&mut self, ui: &mut egui::Ui) {
         let layout_mode = self.layout.mode;
 
+        if self.show_changes {
+            self.render_changes_split(ui);
+            return;
+        }
+
         match layout_mode {
             LayoutMode::EditorOnly => {
                 self.render_editor(ui);
@@ -252,300 +429,1166 @@ impl RmdApp {
 
     /// Render the editor panel
     fn render_editor(&mut self, ui: &mut egui::Ui) {
+        let frame = self.pane_frame(ui, 0.0);
         egui::CentralPanel::default()
-            .frame(egui::Frame::central_panel(ui.style()).inner_margin(0.0))
+            .frame(frame)
             .show_inside(ui, |ui| {
+                self.pane_focus.note(ui.ctx(), Pane::Editor, ui.max_rect());
+                if let Some(path) = &self.loading_document {
+                    let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().to_string());
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(ui.available_height() / 3.0);
+                        ui.spinner();
+                        ui.label(egui::RichText::new(t!("status.opening", name = name)).color(self.theme.text_muted));
+                    });
+                    return;
+                }
+                self.zoom_editor(ui);
                 let available_size = ui.available_size();
 
-                egui::ScrollArea::both()
-                    .auto_shrink([false, false])
-                    .show(ui, |ui| {
-                        let text = self.editor.text();
-
-                        // Create a text edit for the editor
-                        let mut text_clone = text.clone();
-                        let text_edit = egui::TextEdit::multiline(&mut text_clone)
-                            .font(egui::TextStyle::Monospace)
-                            .code_editor()
-                            .desired_width(available_size.x)
-                            .desired_rows(100);
-
-                        let response = ui.add(text_edit);
-                        if response.changed() {
-                            // Update editor content
-                            self.editor.set_text(text_clone);
-                            self.has_unsaved_changes = true;
+                // The completion popup gets first pick of navigation keys
+                if self.readonly {
+                    if self.editor.has_focus() && readonly::attempted_edit(ui) {
+                        self.show_readonly_hint();
+                    }
+                } else {
+                    if self.autocomplete.is_open() && self.editor.has_focus() {
+                        self.handle_autocomplete_keys(ui);
+                    }
+                    if self.editor.has_focus() {
+                        self.handle_snippet_keys(ui);
+                        self.handle_table_keys(ui);
+                        self.handle_fence_keys(ui);
+                        self.handle_indent_keys(ui);
+                        self.handle_editor_shortcuts(ui);
+                    }
+                }
+                let word_step = if self.editor.has_focus() { take_word_step(ui) } else { None };
+                let mut dropped = None;
+
+                let mut scroll_area = egui::ScrollArea::both()
+                    .id_salt(("editor_scroll", self.editor.widget_id()))
+                    .auto_shrink([false, false]);
+                if let Some(offset) = self.editor.take_pending_scroll() {
+                    scroll_area = scroll_area.vertical_scroll_offset(offset);
+                }
+                let scroll = scroll_area.show(ui, |ui| {
+                    let text = self.editor.text();
+                    let editor_id = self.editor.widget_id();
+
+                    // Apply a selection requested by an editing command
+                    let pending_selection = self.editor.take_pending_selection();
+                    if let Some((anchor, cursor)) = pending_selection {
+                        let mut state = egui::TextEdit::load_state(ui.ctx(), editor_id).unwrap_or_default();
+                        state.cursor.set_char_range(Some(egui::text::CCursorRange::two(
+                            egui::text::CCursor::new(utils::byte_to_char(&text, anchor)),
+                            egui::text::CCursor::new(utils::byte_to_char(&text, cursor)),
+                        )));
+                        state.store(ui.ctx(), editor_id);
+                        ui.memory_mut(|m| m.request_focus(editor_id));
+                    }
+
+                    // Ctrl+arrow moves by Markdown-aware words instead of
+                    // the widget's own
+                    if let Some((forward, extend)) = word_step {
+                        let (anchor, cursor) = self.editor.step_word(forward, extend);
+                        store_selection(ui.ctx(), editor_id, &text, anchor, cursor);
+                    }
+
+                    // Highlight Markdown syntax as the text is laid out
+                    let font_id = self.editor_font();
+                    let word_wrap = self.word_wrap();
+                    let swatch_width = self.swatch_gutter_width();
+                    let tab_size = self.indentation().size;
+                    let highlighter = &mut self.highlighter;
+                    let mut layouter = |ui: &egui::Ui, text: &str, wrap_width: f32| {
+                        let mut job = highlighter.layout_job(text, font_id.clone());
+                        job.wrap.max_width = if word_wrap { wrap_width } else { f32::INFINITY };
+                        ui.fonts(|f| f.layout_job(indentation::with_tab_stops(job, tab_size, f)))
+                    };
+
+                    // Create a text edit for the editor; a `&str` buffer keeps
+                    // a read-only document selectable but not editable
+                    let mut text_clone = text.clone();
+                    let mut locked_text = text.as_str();
+                    let buffer: &mut dyn egui::TextBuffer = if self.readonly {
+                        &mut locked_text
+                    } else {
+                        &mut text_clone
+                    };
+                    // Without a card around it, a backdrop shows through the text
+                    let chrome = &self.theme.chrome;
+                    let see_through = chrome.backdrop.is_some() && chrome.card.is_none();
+                    let text_edit = egui::TextEdit::multiline(buffer)
+                        .id(editor_id)
+                        .font(font_id.clone())
+                        .code_editor()
+                        .frame(!see_through)
+                        .desired_width(available_size.x)
+                        .desired_rows(100)
+                        .margin(egui::Margin {
+                            left: 4.0 + bookmarks::GUTTER_WIDTH + swatch_width,
+                            right: 4.0,
+                            top: 2.0,
+                            bottom: 2.0,
+                        })
+                        .layouter(&mut layouter);
+
+                    let output = text_edit.show(ui);
+                    // Text dragged from the scratchpad goes where it is let go
+                    if let Some(fragment) = output.response.dnd_release_payload::<ScratchFragment>() {
+                        if let Some(pointer) = ui.input(|i| i.pointer.interact_pos()) {
+                            let at = output.galley.cursor_from_pos(pointer - output.galley_pos);
+                            dropped = Some((fragment, utils::char_to_byte(&text_clone, at.ccursor.index)));
                         }
+                    }
+                    output.response.context_menu(|ui| {
+                        self.action_button(ui, Action::CopySection, true);
+                        self.action_button(ui, Action::ExportSelectionHtml, true);
                     });
+                    let changed = output.response.changed();
+                    if changed {
+                        // Update editor content
+                        self.editor.apply_widget_text(&text_clone);
+                        self.has_unsaved_changes = true;
+                        self.record_typing(&text, &text_clone);
+                    }
+                    self.paint_bookmarks(ui, &output.galley.job.text, &output.galley, output.galley_pos);
+                    self.paint_change_markers(ui, &output.galley.job.text, &output.galley, output.galley_pos);
+                    self.paint_color_swatches(ui, &output.galley.job.text, &output.galley, output.galley_pos);
+                    self.paint_ruler(ui, output.galley_pos, output.response.rect);
+                    self.paint_whitespace(ui, &output.galley, output.galley_pos);
+                    self.paint_find_matches(ui, &output.galley.job.text, &output.galley, output.galley_pos);
+                    self.paint_recent_substitution(ui, &output.galley, output.galley_pos);
+                    self.handle_gutter(ui, output.response.rect, &output.galley.job.text, &output.galley, output.galley_pos);
+
+                    // Double-clicking selects a Markdown-aware word instead
+                    // of the widget's own
+                    let mut cursor_range = output.cursor_range;
+                    let double_clicked = output.response.double_clicked().then(|| output.response.interact_pointer_pos()).flatten();
+                    if let Some(pointer) = double_clicked {
+                        let clicked = output.galley.cursor_from_pos(pointer - output.galley_pos);
+                        let pos = utils::char_to_byte(&text_clone, clicked.ccursor.index);
+                        if let Some(word) = words::word_at(&text_clone, pos) {
+                            let range = egui::text::CursorRange::two(
+                                output.galley.from_ccursor(egui::text::CCursor::new(utils::byte_to_char(&text_clone, word.start))),
+                                output.galley.from_ccursor(egui::text::CCursor::new(utils::byte_to_char(&text_clone, word.end))),
+                            );
+                            let mut state = output.state.clone();
+                            state.cursor.set_range(Some(range));
+                            state.store(ui.ctx(), editor_id);
+                            cursor_range = Some(range);
+                        }
+                    }
+
+                    self.editor.set_focus(output.response.has_focus());
+                    if let Some(range) = cursor_range {
+                        let previous_cursor = self.editor.cursor();
+                        self.editor.sync_selection(
+                            utils::char_to_byte(&text_clone, range.secondary.ccursor.index),
+                            utils::char_to_byte(&text_clone, range.primary.ccursor.index),
+                        );
+                        let caret = output.galley.pos_from_cursor(&range.primary);
+                        let caret = caret.translate(output.galley_pos.to_vec2());
+                        self.editor.set_caret_rect(Some(caret));
+                        if pending_selection.is_some() {
+                            ui.scroll_to_rect(caret, Some(egui::Align::Center));
+                        } else if word_step.is_some() {
+                            ui.scroll_to_rect(caret, None);
+                        }
+
+                        // A typing aid's substitution shows from the next frame,
+                        // with the caret kept after what was typed
+                        let typed = typed_char(&text, &text_clone, self.editor.cursor());
+                        if changed && self.apply_typing_aids(&text, &text_clone) {
+                            text_clone = self.editor.text();
+                            if let Some((anchor, cursor)) = self.editor.take_pending_selection() {
+                                store_selection(ui.ctx(), editor_id, &text_clone, anchor, cursor);
+                            }
+                        }
+                        // Likewise the closing fence of a code block just opened
+                        if matches!(typed, Some('`' | '~')) && self.config.writing.auto_pair && self.editor.close_fence() {
+                            text_clone = self.editor.text();
+                            if let Some((anchor, cursor)) = self.editor.take_pending_selection() {
+                                store_selection(ui.ctx(), editor_id, &text_clone, anchor, cursor);
+                            }
+                        }
+                        // Likewise renumbering the lists the edit touched
+                        if changed && self.config.writing.renumber_lists {
+                            let touched = diff_region(&text, &text_clone).map(|(range, new)| range.start..range.start + new.len());
+                            if touched.is_some_and(|touched| self.editor.renumber_lists_around(touched)) {
+                                text_clone = self.editor.text();
+                                if let Some((anchor, cursor)) = self.editor.take_pending_selection() {
+                                    store_selection(ui.ctx(), editor_id, &text_clone, anchor, cursor);
+                                }
+                            }
+                        }
+
+                        let moved = previous_cursor != self.editor.cursor();
+                        if (changed || moved) && self.config.preview.follow_caret && self.preview.pinned().is_none() {
+                            self.preview.follow_caret(self.editor.cursor());
+                        }
+
+                        if changed {
+                            let doc_dir = self.current_file.as_deref().and_then(std::path::Path::parent);
+                            self.autocomplete.update(&CompletionContext {
+                                text: &text_clone,
+                                cursor: self.editor.cursor(),
+                                doc_dir,
+                                used_languages: &self.config.writing.code_languages,
+                            });
+                        } else if moved {
+                            self.autocomplete.close();
+                        }
+                    }
+                });
+                self.editor.set_scroll_y(scroll.state.offset.y);
+                if let Some((fragment, pos)) = dropped {
+                    self.drop_scratch_fragment(&fragment, pos);
+                }
+            });
+
+        if self.autocomplete.is_open() {
+            self.render_autocomplete_popup(ui.ctx());
+        }
+    }
+
+    /// Faint vertical line at the guide column of the monospace text
+    fn paint_ruler(&self, ui: &egui::Ui, galley_pos: egui::Pos2, rect: egui::Rect) {
+        let column = self.config.editor.ruler_column;
+        if column == 0 {
+            return;
+        }
+        let glyph_width = ui.fonts(|f| f.glyph_width(&self.editor_font(), '0'));
+        let x = galley_pos.x + column as f32 * glyph_width;
+        let stroke = egui::Stroke::new(1.0, self.theme.border.gamma_multiply(0.6));
+        ui.painter().vline(x.round() + 0.5, rect.y_range(), stroke);
+    }
+
+    /// Handle arrow keys, Tab/Enter, and Escape while the completion popup is open
+    fn handle_autocomplete_keys(&mut self, ui: &mut egui::Ui) {
+        let (up, down, accept, dismiss) = ui.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Tab)
+                    || i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+
+        if up {
+            self.autocomplete.select_previous();
+        }
+        if down {
+            self.autocomplete.select_next();
+        }
+        if dismiss {
+            self.autocomplete.dismiss();
+        }
+        if accept {
+            self.accept_completion();
+        }
+    }
+
+    /// Tab expands a snippet trigger or moves to the next tab stop,
+    /// Shift+Tab goes back a stop and Escape leaves snippet mode
+    fn handle_snippet_keys(&mut self, ui: &mut egui::Ui) {
+        if self.editor.in_snippet() {
+            let (next, previous, exit) = ui.input_mut(|i| {
+                (
+                    i.consume_key(egui::Modifiers::NONE, egui::Key::Tab),
+                    i.consume_key(egui::Modifiers::SHIFT, egui::Key::Tab),
+                    i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+                )
             });
+            if next || previous {
+                self.editor.jump_tab_stop(previous);
+            }
+            if exit {
+                self.editor.exit_snippet();
+            }
+            return;
+        }
+
+        let Some((range, trigger)) = self.editor.snippet_trigger() else {
+            return;
+        };
+        let Some(snippet) = self.snippets.get(&trigger) else {
+            return;
+        };
+        if ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Tab)) {
+            self.editor.expand_snippet(range, &snippets::expand(&snippet.body));
+            self.has_unsaved_changes = true;
+        }
+    }
+
+    /// Tab and Shift+Tab move between table cells and Enter adds a row
+    /// while the caret is in a table; elsewhere the keys are left alone
+    fn handle_table_keys(&mut self, ui: &mut egui::Ui) {
+        let (tab, enter, modifiers) = ui.input(|i| (i.key_pressed(egui::Key::Tab), i.key_pressed(egui::Key::Enter), i.modifiers));
+        let revision = self.editor.revision();
+        if tab && (modifiers.is_none() || modifiers == egui::Modifiers::SHIFT) && self.editor.table_tab(modifiers.shift) {
+            ui.input_mut(|i| i.consume_key(modifiers, egui::Key::Tab));
+        } else if enter && modifiers.is_none() && self.editor.table_new_row() {
+            ui.input_mut(|i| i.consume_key(modifiers, egui::Key::Enter));
+        }
+        if self.editor.revision() != revision {
+            self.has_unsaved_changes = true;
+        }
+    }
+
+    /// Enter where the language of an empty code block goes moves into the
+    /// block, and a multi-line paste there goes inside it
+    fn handle_fence_keys(&mut self, ui: &mut egui::Ui) {
+        if !self.config.writing.auto_pair {
+            return;
+        }
+        if ui.input(|i| i.key_pressed(egui::Key::Enter) && i.modifiers.is_none()) {
+            if let Some(language) = self.editor.enter_fence() {
+                ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Enter));
+                if !language.is_empty() {
+                    *self.config.writing.code_languages.entry(language).or_default() += 1;
+                }
+                self.has_unsaved_changes = true;
+            }
+        }
+        let pasted = ui.input(|i| {
+            i.events.iter().find_map(|event| match event {
+                egui::Event::Paste(text) => Some(text.clone()),
+                _ => None,
+            })
+        });
+        if let Some(pasted) = pasted {
+            if self.editor.paste_into_fence(&pasted) {
+                ui.input_mut(|i| i.events.retain(|event| !matches!(event, egui::Event::Paste(_))));
+                self.has_unsaved_changes = true;
+            }
+        }
+    }
+
+    /// Tab indents as the document does, and Shift+Tab takes a level off
+    /// the selected lines or the caret line
+    fn handle_indent_keys(&mut self, ui: &mut egui::Ui) {
+        let (tab, outdent) = ui.input_mut(|i| {
+            (i.consume_key(egui::Modifiers::NONE, egui::Key::Tab), i.consume_key(egui::Modifiers::SHIFT, egui::Key::Tab))
+        });
+        let revision = self.editor.revision();
+        if tab {
+            self.editor.tab(self.indentation());
+        } else if outdent {
+            self.editor.shift_lines(self.indentation(), true);
+        }
+        if self.editor.revision() != revision {
+            self.has_unsaved_changes = true;
+        }
+    }
+
+    /// `label` followed by the shortcut for `action` in parentheses, if it has one
+    fn shortcut_label(&self, label: &str, action: Action) -> String {
+        match self.keymap.chord(action) {
+            Some(chord) => format!("{} ({})", label, chord),
+            None => label.to_string(),
+        }
+    }
+
+    /// Insert the selected completion into the document
+    fn accept_completion(&mut self) {
+        if let Some(suggestion) = self.autocomplete.accept() {
+            self.editor.replace_range(suggestion.replace, &suggestion.insert);
+            self.has_unsaved_changes = true;
+        }
+    }
+
+    /// Render the completion popup anchored below the caret
+    fn render_autocomplete_popup(&mut self, ctx: &egui::Context) {
+        let Some(caret) = self.editor.caret_rect() else {
+            return;
+        };
+
+        let mut clicked = None;
+        egui::Area::new(egui::Id::new("autocomplete_popup"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(caret.left_bottom() + egui::vec2(0.0, 2.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_min_width(220.0);
+                    egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                        for (i, item) in self.autocomplete.items().iter().enumerate() {
+                            let selected = i == self.autocomplete.selected();
+                            let response = ui.horizontal(|ui| {
+                                let response = ui.selectable_label(selected, &item.label);
+                                if let Some(ref detail) = item.detail {
+                                    ui.label(egui::RichText::new(detail).small().color(self.theme.text_muted));
+                                }
+                                response
+                            }).inner;
+                            if selected {
+                                response.scroll_to_me(None);
+                            }
+                            if response.clicked() {
+                                clicked = Some(i);
+                            }
+                        }
+                    });
+                });
+            });
+
+        if let Some(i) = clicked {
+            self.autocomplete.select(i);
+            self.accept_completion();
+        }
     }
 
     /// Render the preview panel
     fn render_preview(&mut self, ui: &mut egui::Ui) {
+        let frame = self.pane_frame(ui, 16.0);
         egui::CentralPanel::default()
-            .frame(egui::Frame::central_panel(ui.style()).inner_margin(16.0))
+            .frame(frame)
             .show_inside(ui, |ui| {
-                egui::ScrollArea::vertical()
-                    .auto_shrink([false, false])
-                    .show(ui, |ui| {
-                        let text = self.editor.text();
-                        let elements = self.markdown_renderer.render(&text);
+                self.zoom_preview(ui);
+                self.preview.poll_pinned();
+                self.ui_pinned_header(ui);
+                self.ui_preview_toolbar(ui);
+                if self.preview.show_html {
+                    let text = match self.preview.pinned() {
+                        Some(pinned) => pinned.text().to_string(),
+                        None => self.editor.text(),
+                    };
+                    self.render_html_source(ui, &text);
+                    return;
+                }
+                self.ui_reading_progress(ui);
+                self.ui_preview_header(ui);
+                let keyboard_scroll = self.preview_keyboard_scroll(ui);
+                if keyboard_scroll.is_some() {
+                    self.preview.note_manual_scroll();
+                }
+                // Each target keeps its own scroll position
+                let mut scroll_area = egui::ScrollArea::vertical()
+                    .id_salt(("preview", self.preview.pinned_path().map(|path| path.to_path_buf())))
+                    .auto_shrink([false, false]);
+                if let Some(offset) = self.preview.take_scroll_restore().or(keyboard_scroll) {
+                    scroll_area = scroll_area.vertical_scroll_offset(offset);
+                }
+                let output = scroll_area.show(ui, |ui| {
+                    let text = match self.preview.pinned() {
+                        Some(pinned) => pinned.text().to_string(),
+                        None => self.editor.text(),
+                    };
+                    let document = self.preview.pinned_path().map(std::path::Path::to_path_buf).or_else(|| self.current_file.clone());
+                    self.preview.refresh(&self.markdown_renderer, &text, document.as_deref());
+                    let _layout = timing::span(Phase::PreviewLayout);
+                    if self.preview.has_includes() {
+                        // Included files aren't watched, so look at them now and then
+                        ui.ctx().request_repaint_after(INCLUDE_POLL);
+                    }
+                    // Taken out while drawing, which needs `self` mutably
+                    let elements = std::mem::take(&mut self.preview.elements);
+                    self.preview_selection_keys(ui, &elements, &text);
 
-                        for element in elements {
-                            self.render_element(ui, &element);
-                        }
-                    });
+                    // A centered column, so long lines stay readable in wide windows
+                    let available = ui.available_width();
+                    let (text_width, wide_width) = crate::preview::content_widths(
+                        self.preview_width(),
+                        self.preview.zoom,
+                        available,
+                    );
+                    // The block the caret is in, when it moved
+                    let follow = self
+                        .preview
+                        .take_caret_follow()
+                        .filter(|_| self.preview.pinned().is_none())
+                        .and_then(|caret| elements.iter().rposition(|element| element.span.start <= caret));
+                    let mut pass = PreviewPass {
+                        follow,
+                        flash_color: self.theme.accent.gamma_multiply(0.2),
+                        selection_color: ui.visuals().selection.bg_fill,
+                        view_top: ui.clip_rect().top(),
+                        first_visible: None,
+                    };
+                    self.find.begin_preview();
+                    if self.config.preview.paginated {
+                        self.render_pages(ui, &elements, &text, &mut pass);
+                    } else {
+                        let centered = egui::Layout::top_down(egui::Align::Center);
+                        ui.allocate_ui_with_layout(egui::vec2(available, 0.0), centered, |ui| {
+                            for (index, element) in elements.iter().enumerate() {
+                                use crate::markdown::ElementKind::{CodeBlock, Table};
+                                let wide = matches!(element.kind, CodeBlock(..) | Table(..));
+                                let width = if wide { wide_width } else { text_width };
+                                self.render_top_level(ui, index, element, &text, width, &mut pass);
+                            }
+                        });
+                    }
+                    self.find.end_preview();
+                    self.preview.set_first_visible(pass.first_visible.unwrap_or(elements.len()));
+                    self.preview.elements = elements;
+                });
+                // Scrolled by the wheel or the scroll bar, not by following the caret
+                let scrolled = (output.state.offset.y - self.preview.scroll_offset).abs() > 0.5;
+                let by_user = ui.rect_contains_pointer(ui.max_rect())
+                    && ui.input(|i| i.pointer.any_down() || i.raw_scroll_delta.y != 0.0 || i.smooth_scroll_delta.y != 0.0);
+                if scrolled && by_user {
+                    self.preview.note_manual_scroll();
+                }
+                self.preview.record_scroll(output.state.offset.y, output.content_size.y, output.inner_rect.height());
             });
     }
 
+    /// Render a top-level element of the preview `width` wide, noting
+    /// whether it is the first in view and scrolling to it when asked.
+    /// Returns where it was drawn.
+    pub(crate) fn render_top_level(
+        &mut self,
+        ui: &mut egui::Ui,
+        index: usize,
+        element: &crate::markdown::RenderedElement,
+        text: &str,
+        width: f32,
+        pass: &mut PreviewPass,
+    ) -> egui::Rect {
+        let left = egui::Layout::top_down(egui::Align::Min);
+        // Filled in under the element once its size is known
+        let tint = ui.painter().add(egui::Shape::Noop);
+        let rect = ui
+            .allocate_ui_with_layout(egui::vec2(width, 0.0), left, |ui| {
+                ui.set_width(width);
+                self.render_element(ui, element, text);
+            })
+            .response
+            .rect;
+        if pass.first_visible.is_none() && rect.bottom() > pass.view_top {
+            pass.first_visible = Some(index);
+            let fraction = ((pass.view_top - rect.top()) / rect.height().max(1.0)).clamp(0.0, 1.0);
+            self.preview.set_view_top(element.span.start, fraction);
+        }
+        if let Some(fraction) = self.preview.take_zoom_anchor(element.span.start) {
+            // The same content stays at the top of the view at the new zoom
+            let offset = self.preview.scroll_offset + rect.top() + fraction * rect.height() - pass.view_top;
+            self.preview.scroll_for_zoom(offset);
+        }
+        if self.find.take_reveal() {
+            // The match was in an element without a widget of its own
+            ui.scroll_to_rect(rect, Some(egui::Align::Center));
+        }
+        if pass.follow == Some(index) {
+            ui.scroll_to_rect(rect, None);
+            self.preview.flash(element.span.start);
+        }
+        if self.preview.selection.contains(element.span.range()) {
+            ui.painter().set(tint, egui::Shape::rect_filled(rect.expand(2.0), 2.0, pass.selection_color));
+        } else if let Some(strength) = self.preview.flash_strength(element.span.start) {
+            let shape = egui::Shape::rect_filled(rect.expand(4.0), 4.0, pass.flash_color.gamma_multiply(strength));
+            ui.painter().set(tint, shape);
+            ui.ctx().request_repaint();
+        }
+        rect
+    }
+
     /// Render the split view with editor and preview side by side
     fn render_split_view(&mut self, ui: &mut egui::Ui) {
         let split_ratio = self.config.window.editor_ratio;
+        let total_width = ui.available_width();
 
         // Use a splitter to divide the space
-        egui::SidePanel::left("editor_panel")
+        let panel = egui::SidePanel::left("editor_panel")
             .resizable(true)
-            .default_width(ui.available_width() * split_ratio)
+            .default_width(total_width * split_ratio)
             .show_inside(ui, |ui| {
                 self.render_editor(ui);
             });
+        self.remember_split_ratio(panel.response.rect.width(), total_width);
 
         // Preview panel takes remaining space
         self.render_preview(ui);
     }
 
+    /// Render the editor next to the diff against the saved file
+    fn render_changes_split(&mut self, ui: &mut egui::Ui) {
+        let split_ratio = self.config.window.editor_ratio;
+        let total_width = ui.available_width();
+
+        let panel = egui::SidePanel::left("editor_panel")
+            .resizable(true)
+            .default_width(total_width * split_ratio)
+            .show_inside(ui, |ui| {
+                self.render_editor(ui);
+            });
+        self.remember_split_ratio(panel.response.rect.width(), total_width);
+
+        self.render_changes(ui);
+    }
+
+    /// Keep the editor's share of a split in the config, so it is restored
+    /// next time. Rounded so a slightly different window size isn't a change.
+    fn remember_split_ratio(&mut self, editor_width: f32, total_width: f32) {
+        if total_width <= 0.0 {
+            return;
+        }
+        let ratio = ((editor_width / total_width) * 100.0).round() / 100.0;
+        if (ratio - self.config.window.editor_ratio).abs() >= 0.01 {
+            self.config.window.editor_ratio = ratio.clamp(0.1, 0.9);
+        }
+    }
+
+    /// Render an image, for now as a placeholder naming it
+    fn render_image(&self, ui: &mut egui::Ui, alt: &str, url: &str) -> egui::Response {
+        let zoom = self.preview.zoom;
+        let frame = egui::Frame::none()
+            .fill(self.theme.surface)
+            .rounding(6.0 * zoom)
+            .inner_margin(16.0 * zoom)
+            .show(ui, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.label(egui::RichText::new("🖼").size(48.0 * zoom));
+                    ui.add_space(4.0 * zoom);
+                    ui.label(egui::RichText::new(alt).size(12.0 * zoom).color(self.theme.text_muted));
+                    ui.label(egui::RichText::new(url).size(10.0 * zoom).color(self.theme.text_muted).monospace());
+                });
+            });
+        let response = frame.response.interact(egui::Sense::click());
+        response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Other, true, t!("preview.image", alt = alt)));
+        response
+    }
+
     /// Render a single element
-    fn render_element(&self, ui: &mut egui::Ui, element: &crate::markdown::RenderedElement) {
-        use crate::markdown::RenderedElement::*;
+    fn render_element(&mut self, ui: &mut egui::Ui, element: &crate::markdown::RenderedElement, source: &str) {
+        use crate::markdown::ElementKind::*;
+        let zoom = self.preview.zoom;
+        // Text the style sizes, such as emphasis and table cells, zoomed too
+        let body_size = egui::TextStyle::Body.resolve(ui.style()).size * zoom;
 
-        match element {
+        // Leaf elements hand back the widget their right-click menu belongs to
+        let response = match &element.kind {
             Heading(level, text) => {
-                let text_size = match level {
-                    1 => 32.0,
-                    2 => 28.0,
-                    3 => 24.0,
-                    4 => 20.0,
-                    5 => 18.0,
-                    _ => 16.0,
-                };
-                ui.add_space(16.0);
-                ui.label(
-                    egui::RichText::new(text)
-                        .size(text_size)
-                        .strong()
-                        .color(self.theme.text),
+                let text_size = crate::preview::heading_size(*level, zoom, ui.available_width());
+                ui.add_space(16.0 * zoom);
+                let rich = egui::RichText::new(text).size(text_size).strong().color(self.theme.text);
+                let text = self.find_highlight(ui, rich, element.span.start, 0);
+                let response = ui.add(
+                    egui::Label::new(self.typeset(ui, text, true))
+                    .selectable(true)
+                    .sense(egui::Sense::click()),
                 );
-                ui.add_space(8.0);
+                // Screen readers list headings to jump between them
+                ui.ctx().accesskit_node_builder(response.id, |node| {
+                    node.set_role(egui::accesskit::Role::Heading);
+                    node.set_level(*level as usize);
+                });
+                if self.preview.take_heading_jump(element.span.start) {
+                    response.scroll_to_me(Some(egui::Align::TOP));
+                }
+                ui.add_space(8.0 * zoom);
+                Some(response)
             }
             Paragraph(text) => {
-                ui.label(
-                    egui::RichText::new(text)
-                        .size(16.0)
-                        .color(self.theme.text),
+                let rich = egui::RichText::new(text).size(16.0 * zoom).color(self.theme.text);
+                let rich = self.with_color_swatches(ui, rich, text, false);
+                let text = self.find_highlight(ui, rich, element.span.start, 0);
+                let response = ui.add(
+                    egui::Label::new(self.typeset(ui, text, false))
+                    .selectable(true)
+                    .sense(egui::Sense::click()),
                 );
-                ui.add_space(12.0);
+                ui.add_space(if self.preview.in_tight_list { 2.0 } else { 12.0 } * zoom);
+                Some(response)
             }
             CodeBlock(lang, code) => {
-                ui.add_space(8.0);
-                egui::Frame::none()
+                let offset = &element.span.start;
+                let line_count = code.lines().count();
+                let max_lines = self.config.preview.max_code_lines;
+                let collapsible = max_lines > 0 && line_count > max_lines;
+                let expanded = !collapsible || self.preview.is_section_open(*offset, false);
+                let shown = if expanded {
+                    code.as_str()
+                } else {
+                    // Byte offset just past the last shown line
+                    let end = code.match_indices('\n').nth(max_lines - 1).map_or(code.len(), |(i, _)| i);
+                    &code[..end]
+                };
+
+                ui.add_space(8.0 * zoom);
+                let response = egui::Frame::none()
                     .fill(self.theme.code_bg)
-                    .rounding(6.0)
-                    .inner_margin(12.0)
+                    .rounding(6.0 * zoom)
+                    .inner_margin(12.0 * zoom)
                     .show(ui, |ui| {
                         if !lang.is_empty() {
                             ui.label(
                                 egui::RichText::new(lang)
-                                    .size(12.0)
+                                    .size(12.0 * zoom)
                                     .color(self.theme.text_muted)
                                     .monospace(),
                             );
-                            ui.add_space(4.0);
+                            ui.add_space(4.0 * zoom);
                         }
-                        ui.label(
-                            egui::RichText::new(code)
-                                .monospace()
-                                .size(14.0)
-                                .color(self.theme.text),
+                        let rich = egui::RichText::new(shown).monospace().size(14.0 * zoom).color(self.theme.text);
+                        let response = ui.add(
+                            egui::Label::new(self.find_highlight(ui, rich, element.span.start, 0))
+                            .selectable(true)
+                            .sense(egui::Sense::click()),
                         );
-                    });
-                ui.add_space(8.0);
+                        if collapsible {
+                            let text = if expanded {
+                                t!("preview.show_less").to_string()
+                            } else {
+                                t!("preview.show_all_lines", count = line_count)
+                            };
+                            if ui.link(egui::RichText::new(text).size(12.0 * zoom)).clicked() {
+                                self.preview.set_section_open(*offset, !expanded);
+                            }
+                        }
+                        response
+                    })
+                    .inner;
+                ui.add_space(8.0 * zoom);
+                Some(response)
             }
             InlineCode(code) => {
-                ui.colored_label(
-                    self.theme.code_bg,
-                    egui::RichText::new(code).monospace().size(14.0),
-                );
+                let rich = egui::RichText::new(code).monospace().size(14.0 * zoom).color(self.theme.code_bg);
+                let text = self.with_color_swatches(ui, rich, code, true);
+                let text = self.find_highlight(ui, text, element.span.start, 0);
+                Some(ui.add(egui::Label::new(text).selectable(true).sense(egui::Sense::click())))
             }
-            BlockQuote(items) => {
-                ui.add_space(8.0);
+            BlockQuote(items, Some(kind)) => {
+                self.render_alert(ui, *kind, items, source);
+                None
+            }
+            BlockQuote(items, None) => {
+                ui.add_space(8.0 * zoom);
                 egui::Frame::none()
                     .fill(self.theme.surface)
-                    .inner_margin(12.0)
-                    .rounding(4.0)
+                    .inner_margin(12.0 * zoom)
+                    .rounding(4.0 * zoom)
                     .show(ui, |ui| {
                         for item in items {
-                            self.render_element(ui, item);
+                            self.render_element(ui, item, source);
                         }
                     });
-                ui.add_space(8.0);
+                ui.add_space(8.0 * zoom);
+                None
             }
-            UnorderedList(items) => {
-                ui.add_space(4.0);
+            UnorderedList(items, tight) => {
+                ui.add_space(4.0 * zoom);
+                let outer = std::mem::replace(&mut self.preview.in_tight_list, *tight);
                 for item in items {
                     ui.horizontal(|ui| {
-                        ui.label("•");
+                        ui.label(egui::RichText::new("•").size(16.0 * zoom));
                         ui.vertical(|ui| {
                             for elem in item {
-                                self.render_element(ui, elem);
+                                self.render_element(ui, elem, source);
                             }
                         });
                     });
                 }
-                ui.add_space(4.0);
+                self.preview.in_tight_list = outer;
+                ui.add_space(4.0 * zoom);
+                None
             }
-            OrderedList(items) => {
-                ui.add_space(4.0);
+            OrderedList(items, tight) => {
+                ui.add_space(4.0 * zoom);
+                let outer = std::mem::replace(&mut self.preview.in_tight_list, *tight);
                 for (i, item) in items.iter().enumerate() {
                     ui.horizontal(|ui| {
-                        ui.label(format!("{}.", i + 1));
+                        ui.label(egui::RichText::new(format!("{}.", i + 1)).size(16.0 * zoom));
                         ui.vertical(|ui| {
                             for elem in item {
-                                self.render_element(ui, elem);
+                                self.render_element(ui, elem, source);
                             }
                         });
                     });
                 }
-                ui.add_space(4.0);
+                self.preview.in_tight_list = outer;
+                ui.add_space(4.0 * zoom);
+                None
             }
             HorizontalRule => {
-                ui.add_space(8.0);
+                ui.add_space(8.0 * zoom);
                 ui.add(egui::Separator::default().horizontal());
-                ui.add_space(8.0);
+                ui.add_space(8.0 * zoom);
+                None
+            }
+            PageBreak => {
+                ui.add_space(8.0 * zoom);
+                let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 14.0 * zoom), egui::Sense::hover());
+                let color = ui.visuals().weak_text_color();
+                let painter = ui.painter();
+                let label = painter.layout_no_wrap(t!("preview.page_break").to_string(), egui::FontId::proportional(11.0 * zoom), color);
+                let gap = egui::Rect::from_center_size(rect.center(), label.size() + egui::vec2(12.0 * zoom, 0.0));
+                let stroke = egui::Stroke::new(1.0, color);
+                for (from, to) in [(rect.left(), gap.left()), (gap.right(), rect.right())] {
+                    let line = [egui::pos2(from, rect.center().y), egui::pos2(to, rect.center().y)];
+                    painter.extend(egui::Shape::dashed_line(&line, stroke, 6.0 * zoom, 4.0 * zoom));
+                }
+                painter.galley(rect.center() - label.size() / 2.0, label, color);
+                ui.add_space(8.0 * zoom);
+                None
             }
             Link(text, url) => {
-                if ui.link(text).clicked() {
-                    if let Err(e) = webbrowser::open(url) {
-                        eprintln!("Failed to open link: {}", e);
+                let response = ui
+                    .link(self.find_highlight(ui, egui::RichText::new(text).size(body_size), element.span.start, 0))
+                    .on_hover_text(url);
+                if response.clicked() {
+                    if let Some(target) = url.strip_prefix(crate::markdown::wiki::WIKI_SCHEME) {
+                        self.follow_wiki_link(target);
+                    } else if let Some(number) = crate::markdown::figures::anchor_number(url) {
+                        let figures = crate::markdown::figures::find_figures(source);
+                        if let Some(figure) = figures.iter().find(|figure| figure.number == number) {
+                            self.preview.jump_to_heading(figure.block.start);
+                        }
+                    } else {
+                        self.open_link(url, source);
                     }
                 }
+                Some(response)
             }
             Image(alt, url) => {
-                // For now, just show a placeholder for images
-                ui.add_space(8.0);
-                egui::Frame::none()
-                    .fill(self.theme.surface)
-                    .rounding(6.0)
-                    .inner_margin(16.0)
-                    .show(ui, |ui| {
-                        ui.vertical_centered(|ui| {
-                            ui.label(egui::RichText::new("🖼").size(48.0));
-                            ui.add_space(4.0);
-                            ui.label(egui::RichText::new(alt).size(12.0).color(self.theme.text_muted));
-                            ui.label(egui::RichText::new(url).size(10.0).color(self.theme.text_muted).monospace());
-                        });
-                    });
-                ui.add_space(8.0);
+                ui.add_space(8.0 * zoom);
+                let response = self.render_image(ui, alt, url);
+                ui.add_space(8.0 * zoom);
+                Some(response)
+            }
+            Figure(alt, url, caption, number) => {
+                ui.add_space(8.0 * zoom);
+                let response = ui
+                    .vertical_centered(|ui| {
+                        let image = self.render_image(ui, alt, url);
+                        ui.add_space(4.0 * zoom);
+                        let label = crate::markdown::figures::label(*number, caption);
+                        let caption = egui::RichText::new(label).size(12.0 * zoom).color(self.theme.text_muted);
+                        ui.add(egui::Label::new(self.find_highlight(ui, caption, element.span.start, 0)).selectable(true));
+                        image
+                    })
+                    .inner;
+                // Links to `#figure-N` scroll here
+                if self.preview.take_heading_jump(element.span.start) {
+                    response.scroll_to_me(Some(egui::Align::TOP));
+                }
+                ui.add_space(8.0 * zoom);
+                Some(response)
             }
             RawHtml(html) => {
                 // Show HTML as code block for now
-                ui.add_space(4.0);
+                ui.add_space(4.0 * zoom);
                 egui::Frame::none()
                     .fill(self.theme.code_bg)
-                    .rounding(4.0)
-                    .inner_margin(8.0)
+                    .rounding(4.0 * zoom)
+                    .inner_margin(8.0 * zoom)
                     .show(ui, |ui| {
-                        ui.label(
-                            egui::RichText::new(html)
-                                .monospace()
-                                .size(12.0)
-                                .color(self.theme.text_muted),
-                        );
+                        let rich = egui::RichText::new(html).monospace().size(12.0 * zoom).color(self.theme.text_muted);
+                        ui.label(self.find_highlight(ui, rich, element.span.start, 0));
                     });
-                ui.add_space(4.0);
+                ui.add_space(4.0 * zoom);
+                None
             }
             LineBreak => {
-                ui.add_space(8.0);
+                ui.add_space(8.0 * zoom);
+                None
             }
             Strong(text) => {
-                ui.label(
-                    egui::RichText::new(text)
-                        .strong()
-                        .color(self.theme.text),
-                );
+                let text = egui::RichText::new(text).size(body_size).strong().color(self.theme.text);
+                let text = self.find_highlight(ui, text, element.span.start, 0);
+                Some(ui.add(egui::Label::new(text).selectable(true).sense(egui::Sense::click())))
             }
             Emphasis(text) => {
-                ui.label(
-                    egui::RichText::new(text)
-                        .italics()
-                        .color(self.theme.text),
-                );
+                let text = egui::RichText::new(text).size(body_size).italics().color(self.theme.text);
+                let text = self.find_highlight(ui, text, element.span.start, 0);
+                Some(ui.add(egui::Label::new(text).selectable(true).sense(egui::Sense::click())))
             }
             Strikethrough(text) => {
-                ui.label(
-                    egui::RichText::new(text)
-                        .strikethrough()
-                        .color(self.theme.text_muted),
-                );
+                let text = egui::RichText::new(text).size(body_size).strikethrough().color(self.theme.text_muted);
+                let text = self.find_highlight(ui, text, element.span.start, 0);
+                Some(ui.add(egui::Label::new(text).selectable(true).sense(egui::Sense::click())))
+            }
+            Details(summary, open, body) => {
+                let offset = &element.span.start;
+                let title = if summary.is_empty() { t!("preview.details") } else { summary.as_str() };
+                let is_open = self.preview.is_section_open(*offset, *open);
+                let title = egui::RichText::new(title).size(body_size).color(self.theme.text);
+                let title = self.find_highlight(ui, title, element.span.start, 0);
+                let response = egui::CollapsingHeader::new(title)
+                    .id_salt(("details", *offset))
+                    .open(Some(is_open))
+                    .show(ui, |ui| {
+                        for elem in body {
+                            self.render_element(ui, elem, source);
+                        }
+                    });
+                if response.header_response.clicked() {
+                    self.preview.set_section_open(*offset, !is_open);
+                }
+                None
+            }
+            Table(rows) => {
+                ui.add_space(8.0 * zoom);
+                let grid = egui::Grid::new(ui.next_auto_id())
+                    .striped(true)
+                    .spacing([16.0 * zoom, 4.0 * zoom])
+                    .min_row_height(ui.fonts(|f| f.row_height(&egui::FontId::proportional(body_size))))
+                    .show(ui, |ui| {
+                        // Matches in cells count on through the table
+                        let mut offset = 0;
+                        for (i, row) in rows.iter().enumerate() {
+                            for cell in row {
+                                let text = egui::RichText::new(cell).size(body_size).color(self.theme.text);
+                                let text = self.find_highlight(ui, if i == 0 { text.strong() } else { text }, element.span.start, offset);
+                                ui.add(egui::Label::new(self.typeset(ui, text, true)).selectable(true));
+                                offset += cell.len() + 1;
+                            }
+                            ui.end_row();
+                        }
+                    });
+                ui.add_space(8.0 * zoom);
+                Some(grid.response.interact(egui::Sense::click()))
+            }
+            FootnoteReference(label, number, occurrence) => {
+                Some(self.render_footnote_reference(ui, label, *number, *occurrence, source))
+            }
+            FootnoteDefinition(label, number, references, body) => {
+                self.render_footnote_definition(ui, label, *number, *references, body, source);
+                None
+            }
+            Include(path, content) => {
+                let name = path
+                    .file_name()
+                    .map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().to_string());
+                let mut open = false;
+                ui.add_space(4.0 * zoom);
+                egui::Frame::none()
+                    .stroke(egui::Stroke::new(1.0, self.theme.border))
+                    .rounding(6.0 * zoom)
+                    .inner_margin(8.0 * zoom)
+                    .show(ui, |ui| {
+                        let chip = egui::Button::new(
+                            egui::RichText::new(format!("📄 {}", name))
+                                .size(12.0 * zoom)
+                                .color(self.theme.text_muted),
+                        )
+                        .fill(self.theme.surface)
+                        .rounding(8.0 * zoom);
+                        open = ui.add(chip).on_hover_text(t!("preview.open_include", path = path.display())).clicked();
+                        match content {
+                            Ok(body) => {
+                                for elem in body {
+                                    self.render_element(ui, elem, source);
+                                }
+                            }
+                            Err(error) => {
+                                ui.label(egui::RichText::new(format!("⚠ {}", error)).size(body_size).color(self.theme.error));
+                            }
+                        }
+                    });
+                ui.add_space(4.0 * zoom);
+                if open {
+                    self.open_path(path);
+                }
+                None
+            }
+        };
+
+        if let Some(response) = response {
+            if self.find.take_reveal() {
+                response.scroll_to_me(Some(egui::Align::Center));
             }
+            // Links and footnote references go where they point instead
+            let moves_caret = !matches!(element.kind, Link(..) | FootnoteReference(..));
+            if response.clicked() && moves_caret {
+                self.preview_selection_click(ui, element, source);
+                if self.config.preview.follow_caret && self.preview.pinned().is_none() {
+                    self.editor.set_cursor(element.span.start);
+                }
+            }
+            self.preview_context_menu(&response, element, source);
         }
     }
 }
 
 // Stub implementations for actions
 impl RmdApp {
-    fn new_file(&mut self) {
-        if self.has_unsaved_changes {
-            // Show save dialog
-        }
+    /// Replace the document with an empty one, whatever it had unsaved
+    pub(crate) fn new_file(&mut self) {
+        self.save_reading_position();
+        self.save_position();
         self.editor.set_text("");
         self.current_file = None;
         self.has_unsaved_changes = false;
+        self.readonly = false;
+        self.preview.reset_sections();
     }
 
     fn open_file_dialog(&mut self) {
         if let Some(path) = rfd::FileDialog::new()
-            .add_filter("Markdown", &["md", "markdown", "mdown", "mkd"])
-            .add_filter("Text", &["txt"])
-            .add_filter("All files", &["*"])
+            .add_filter(t!("dialog.markdown_files"), &self.config.markdown_extensions)
+            .add_filter(t!("dialog.text_files"), &["txt"])
+            .add_filter(t!("dialog.all_files"), &["*"])
             .pick_file()
         {
-            if let Err(e) = self.editor.open_file(&path) {
-                eprintln!("Failed to open file: {}", e);
-            } else {
-                self.current_file = Some(path);
-                self.has_unsaved_changes = false;
+            self.replace_document(Replacement::Open(path));
+        }
+    }
+
+    /// Load a file into the editor, returning whether it succeeded
+    pub fn open_path(&mut self, path: &std::path::Path) -> bool {
+        self.finish_open(path, load::read(path, Operation::Open))
+    }
+
+    /// Show `text`, read from `path`, as the document, or tell why it
+    /// couldn't be read
+    pub fn finish_open(&mut self, path: &std::path::Path, text: Result<String, EditorError>) -> bool {
+        if text.is_ok() {
+            self.save_reading_position();
+            self.save_position();
+        }
+        match text {
+            Ok(text) => self.editor.open_text(text),
+            Err(e) => {
+                self.notify_file_error(e);
+                return false;
             }
         }
+        self.current_file = Some(path.to_path_buf());
+        self.has_unsaved_changes = false;
+        self.readonly = readonly::is_readonly_on_disk(path);
+        self.disk_conflict = false;
+        self.file_watch = None;
+        self.preview.reset_sections();
+        self.restore_position();
+        self.offer_resume_reading();
+        self.note_recent_file();
+        true
+    }
+
+    fn open_folder_dialog(&mut self) {
+        if let Some(path) = rfd::FileDialog::new().pick_folder() {
+            self.open_workspace(path);
+        }
     }
 
     fn save_file(&mut self) {
-        if let Some(ref path) = self.current_file {
-            if let Err(e) = self.editor.save_file(path) {
-                eprintln!("Failed to save file: {}", e);
-            } else {
-                self.has_unsaved_changes = false;
-            }
-        } else {
+        if self.readonly {
+            self.show_readonly_hint();
+            return;
+        }
+        let Some(path) = self.current_file.clone() else {
             self.save_file_as();
+            return;
+        };
+        let cleaned = self.prepare_for_save();
+        if let Err(e) = self.editor.save_file(&path) {
+            self.notify_file_error(e);
+        } else {
+            self.after_save(cleaned);
         }
     }
 
     fn save_file_as(&mut self) {
+        if self.readonly {
+            self.show_readonly_hint();
+            return;
+        }
         if let Some(path) = rfd::FileDialog::new()
-            .add_filter("Markdown", &["md"])
-            .set_file_name("untitled.md")
+            .add_filter(t!("dialog.markdown_files"), &self.config.markdown_extensions)
+            .set_file_name(format!("untitled.{}", self.config.markdown_extensions.first().map_or("md", String::as_str)))
             .save_file()
         {
+            // Only once there is somewhere to save, so cancelling leaves the text alone
+            let cleaned = self.prepare_for_save();
             if let Err(e) = self.editor.save_file(&path) {
-                eprintln!("Failed to save file: {}", e);
+                self.notify_file_error(e);
             } else {
                 self.current_file = Some(path);
-                self.has_unsaved_changes = false;
+                self.after_save(cleaned);
             }
         }
     }
 
+    /// Bookkeeping after the buffer was written to `current_file`, with the
+    /// whitespace cleanups that changed it
+    fn after_save(&mut self, cleaned: Cleanup) {
+        self.has_unsaved_changes = false;
+        if cleaned.any() {
+            self.notify(Severity::Info, t!("toast.saved_cleaned", changes = cleaned.describe()));
+        }
+        self.disk_conflict = false;
+        if let Some(watch) = &mut self.file_watch {
+            watch.sync();
+        }
+        self.changes.invalidate();
+        self.refresh_backlinks();
+        self.note_recent_file();
+        self.save_bookmarks();
+        self.refresh_browser_page();
+        if let Some(path) = self.current_file.clone() {
+            let store = SnapshotStore::open_default(self.config.history.clone());
+            if let Err(e) = store.and_then(|store| store.record(&path, &self.editor.text())) {
+                log::warn!("Failed to snapshot {}: {}", path.display(), e);
+                self.notify(Severity::Warning, t!("toast.saved_without_history", error = e));
+            }
+        }
+    }
+
+    /// Remember the current file as recent, here and in the OS shell
+    fn note_recent_file(&mut self) {
+        let Some(path) = self.current_file.clone() else {
+            return;
+        };
+        self.config.add_recent_file(&path);
+        crate::platform::note_recent_document(&path, &self.config.recent_files);
+    }
+
+    /// Rewrite the buffer before it is written, as undoable edits. Returns
+    /// the whitespace cleanups that changed it.
+    fn prepare_for_save(&mut self) -> Cleanup {
+        if self.config.writing.smart_punctuation_on_save {
+            let text = self.editor.text();
+            self.editor.replace_text(&crate::markdown::typography::smarten(&text));
+        }
+        if self.config.writing.update_toc_on_save {
+            self.update_toc();
+            self.update_figure_list();
+        }
+        // Last, so the cleanups see what the others wrote, as one edit
+        let cleanup = self.config.editor.save_cleanup();
+        if !cleanup.any() {
+            return cleanup;
+        }
+        let (text, cleaned) = crate::markdown::cleanup::clean(&self.editor.text(), cleanup);
+        self.editor.replace_text(&text);
+        cleaned
+    }
+
     fn check_auto_save(&mut self) {
         // Implement auto-save logic
     }
 }
+
+/// Put the selection from `anchor` to `cursor`, in bytes of `text`, into
+/// the text widget `id` without waiting for the next frame
+fn store_selection(ctx: &egui::Context, id: egui::Id, text: &str, anchor: usize, cursor: usize) {
+    let mut state = egui::TextEdit::load_state(ctx, id).unwrap_or_default();
+    state.cursor.set_char_range(Some(egui::text::CCursorRange::two(
+        egui::text::CCursor::new(utils::byte_to_char(text, anchor)),
+        egui::text::CCursor::new(utils::byte_to_char(text, cursor)),
+    )));
+    state.store(ctx, id);
+}
+
+/// Take a Ctrl+Left/Right press, or Alt on macOS, before the editor widget
+/// sees it. Returns whether it goes forward and whether Shift extends the
+/// selection.
+fn take_word_step(ui: &mut egui::Ui) -> Option<(bool, bool)> {
+    ui.input_mut(|i| {
+        let mut step = None;
+        i.events.retain(|event| match event {
+            egui::Event::Key { key: key @ (egui::Key::ArrowLeft | egui::Key::ArrowRight), pressed: true, modifiers, .. }
+                if (modifiers.ctrl || modifiers.alt) && !modifiers.mac_cmd =>
+            {
+                step = Some((*key == egui::Key::ArrowRight, modifiers.shift));
+                false
+            }
+            _ => true,
+        });
+        step
+    })
+}
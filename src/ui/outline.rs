@@ -0,0 +1,202 @@
+//! Outline of the open document in the sidebar: filtering its headings and
+//! dragging a heading to move its whole section, and renaming one from its
+//! context menu
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::keymap::Action;
+use crate::markdown::numbering::HeadingNumbers;
+use crate::markdown::outline::{self, HeadingEntry};
+use crate::ui::focus::Pane;
+use eframe::egui;
+
+/// Indentation per heading level in the outline
+const LEVEL_INDENT: f32 = 12.0;
+
+/// A section dropped among headings of another level, waiting for the user
+/// to choose whether its levels change to fit
+pub struct PendingSectionMove {
+    /// Index of the heading whose section moves
+    from: usize,
+    /// Heading it goes before, or `None` for the end of the document
+    before: Option<usize>,
+    /// Levels to add to its headings to fit in
+    level_change: i8,
+}
+
+impl RmdApp {
+    /// List the headings of the open document, narrowed by the filter box.
+    /// Returns the offset of the heading clicked; a heading dragged onto
+    /// another moves its section before that one.
+    pub fn ui_outline(&mut self, ui: &mut egui::Ui) -> Option<usize> {
+        let headings = self.editor.headings().to_vec();
+        let mut jump = None;
+        let mut dropped = None;
+        let mut rename = None;
+        // Ctrl+4 opens the section at its filter box
+        let focus = self.pane_focus.take(Pane::Outline);
+        egui::CollapsingHeader::new(t!("outline.title", count = headings.len()))
+            .default_open(true)
+            .open(focus.then_some(true))
+            .show(ui, |ui| {
+                let filter_box = ui.add(
+                    egui::TextEdit::singleline(&mut self.outline_filter)
+                        .hint_text(t!("outline.filter"))
+                        .desired_width(f32::INFINITY),
+                );
+                if focus {
+                    filter_box.request_focus();
+                }
+                let filter = self.outline_filter.trim().to_lowercase();
+                // Numbered like the preview, before filtering so a heading keeps its number
+                let mut numbers = self
+                    .config
+                    .preview
+                    .number_headings
+                    .then(|| HeadingNumbers::for_levels(headings.iter().map(|heading| heading.level)));
+                let labels: Vec<String> = headings
+                    .iter()
+                    .map(|heading| match numbers.as_mut() {
+                        Some(numbers) => format!("{} {}", numbers.next(heading.level), heading.text),
+                        None => heading.text.clone(),
+                    })
+                    .collect();
+                let text = self.editor.text();
+                let accent = self.theme.accent;
+                egui::ScrollArea::vertical().auto_shrink([false, true]).show(ui, |ui| {
+                    for (index, heading) in headings.iter().enumerate() {
+                        if !filter.is_empty() && !heading.text.to_lowercase().contains(&filter) {
+                            continue;
+                        }
+                        let row = ui.horizontal(|ui| {
+                            // A filtered list is flat
+                            if filter.is_empty() {
+                                ui.add_space(f32::from(heading.level - 1) * LEVEL_INDENT);
+                            }
+                            let drag = ui.dnd_drag_source(ui.id().with(("outline", index)), index, |ui| {
+                                ui.selectable_label(false, &labels[index])
+                            });
+                            if drag.inner.clicked() {
+                                jump = Some(heading.offset);
+                            }
+                            drag.inner.context_menu(|ui| {
+                                if ui.button(Action::RenameHeading.label()).clicked() {
+                                    rename = Some(heading.offset);
+                                    ui.close_menu();
+                                }
+                            });
+                            drag.response
+                        });
+                        if let Some(from) = drop_target(ui, &row.response, &text, &headings, Some(index), accent) {
+                            dropped = Some((from, Some(index)));
+                        }
+                    }
+
+                    // Below the last heading: move to the end of the document
+                    let (_, end) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 16.0), egui::Sense::hover());
+                    if let Some(from) = drop_target(ui, &end, &text, &headings, None, accent) {
+                        dropped = Some((from, None));
+                    }
+                });
+            });
+
+        if let Some((from, before)) = dropped {
+            self.drop_section(&headings, from, before);
+        }
+        if let Some(offset) = rename {
+            self.open_rename_heading(offset);
+        }
+        jump
+    }
+
+    /// Move the section of `headings[from]` before `headings[before]`,
+    /// first asking whether to change its levels when they don't match
+    /// the heading it lands next to
+    fn drop_section(&mut self, headings: &[HeadingEntry], from: usize, before: Option<usize>) {
+        if self.readonly {
+            self.show_readonly_hint();
+            return;
+        }
+        let level_change = before.map_or(0, |i| headings[i].level as i8 - headings[from].level as i8);
+        if level_change == 0 {
+            self.move_section(from, before, 0);
+        } else {
+            self.section_move = Some(PendingSectionMove { from, before, level_change });
+        }
+    }
+
+    /// Move a section as one undoable edit, leaving the caret on its heading
+    fn move_section(&mut self, from: usize, before: Option<usize>, level_change: i8) {
+        let text = self.editor.text();
+        let headings = outline::headings(&text);
+        if from >= headings.len() || before.is_some_and(|i| i >= headings.len()) {
+            return;
+        }
+        if let Some(edit) = outline::move_section(&text, &headings, from, before, level_change) {
+            self.editor.replace_range_in_place(edit.range, &edit.replacement);
+            self.editor.set_cursor(edit.heading_offset);
+            self.has_unsaved_changes = true;
+        }
+    }
+
+    /// Ask whether a section dropped among headings of another level
+    /// should change its levels to fit
+    pub fn ui_section_move_prompt(&mut self, ctx: &egui::Context) {
+        let Some(pending) = &self.section_move else {
+            return;
+        };
+        let (from, before, change) = (pending.from, pending.before, pending.level_change);
+        let name = self.editor.headings().get(from).map(|h| h.text.clone()).unwrap_or_default();
+        let fit = if change > 0 { t!("outline.demote") } else { t!("outline.promote") };
+
+        let mut choice = None;
+        egui::Window::new(t!("outline.move_title"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                let key = match (change > 0, change.abs() == 1) {
+                    (true, true) => "outline.move_deeper_one",
+                    (true, false) => "outline.move_deeper",
+                    (false, true) => "outline.move_higher_one",
+                    (false, false) => "outline.move_higher",
+                };
+                ui.label(t!(key, name = name, count = change.abs()));
+                ui.horizontal(|ui| {
+                    if ui.button(fit).clicked() {
+                        choice = Some(change);
+                    }
+                    if ui.button(t!("outline.keep_levels")).clicked() {
+                        choice = Some(0);
+                    }
+                    if ui.button(t!("dialog.cancel")).clicked() {
+                        self.section_move = None;
+                    }
+                });
+            });
+
+        if let Some(level_change) = choice {
+            self.section_move = None;
+            self.move_section(from, before, level_change);
+        }
+    }
+}
+
+/// Mark where a dragged heading would land when it hovers `response`,
+/// and return the heading dropped there, if it may be
+fn drop_target(
+    ui: &egui::Ui,
+    response: &egui::Response,
+    text: &str,
+    headings: &[HeadingEntry],
+    before: Option<usize>,
+    accent: egui::Color32,
+) -> Option<usize> {
+    let from = *response.dnd_hover_payload::<usize>()?;
+    if !outline::can_move_section(text, headings, from, before) {
+        return None;
+    }
+    let y = response.rect.top();
+    ui.painter().hline(response.rect.x_range(), y, egui::Stroke::new(2.0, accent));
+    response.dnd_release_payload::<usize>().map(|from| *from)
+}
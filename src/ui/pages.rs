@@ -0,0 +1,72 @@
+//! The preview laid out on pages of the export's paper size and margins,
+//! with the document's name above the text and the page number below it
+
+use super::PreviewPass;
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::{ElementKind, RenderedElement};
+use crate::preview::pages::PageGeometry;
+use eframe::egui;
+use std::path::Path;
+
+/// Space around and between pages
+const PAGE_GAP: f32 = 16.0;
+
+impl RmdApp {
+    /// Draw `elements` on pages. A page wider than the panel is cut to fit
+    /// it, so its lines wrap sooner than printed ones.
+    pub(crate) fn render_pages(&mut self, ui: &mut egui::Ui, elements: &[RenderedElement], text: &str, pass: &mut PreviewPass) {
+        let zoom = self.preview.zoom;
+        let export = &self.config.export;
+        let geometry = PageGeometry::new(export.page_size, export.page_margin, zoom);
+        let available = ui.available_width();
+        let width = geometry.width.min(available - 2.0 * PAGE_GAP).max(2.0 * geometry.margin + 1.0);
+        let content_size = egui::vec2(width - 2.0 * geometry.margin, geometry.content_height());
+        let pages = self.preview.pages(elements, content_size.y);
+        let title = self
+            .preview
+            .pinned_path()
+            .or(self.current_file.as_deref())
+            .and_then(Path::file_name)
+            .map_or_else(|| t!("status.untitled").to_string(), |name| name.to_string_lossy().to_string());
+        let font = egui::FontId::proportional(10.0 * zoom);
+        let muted = self.theme.text_muted;
+        let paper = self.theme.background;
+        let border = self.theme.border;
+
+        let mut remeasure = false;
+        let centered = egui::Layout::top_down(egui::Align::Center);
+        ui.allocate_ui_with_layout(egui::vec2(available, 0.0), centered, |ui| {
+            for (number, page) in pages.iter().enumerate() {
+                ui.add_space(PAGE_GAP);
+                let (rect, _) = ui.allocate_exact_size(egui::vec2(width, geometry.height), egui::Sense::hover());
+                let painter = ui.painter();
+                painter.rect_filled(rect.translate(egui::vec2(2.0, 3.0)), 2.0, egui::Color32::from_black_alpha(40));
+                painter.rect(rect, 2.0, paper, egui::Stroke::new(1.0, border));
+                let header = egui::pos2(rect.left() + geometry.margin, rect.top() + geometry.margin / 2.0);
+                painter.text(header, egui::Align2::LEFT_CENTER, &title, font.clone(), muted);
+                let footer = egui::pos2(rect.center().x, rect.bottom() - geometry.margin / 2.0);
+                let page_number = t!("preview.page_number", page = number + 1, pages = pages.len());
+                painter.text(footer, egui::Align2::CENTER_CENTER, page_number, font.clone(), muted);
+
+                let content = egui::Rect::from_min_size(rect.min + egui::vec2(geometry.margin, geometry.margin), content_size);
+                let layout = egui::Layout::top_down(egui::Align::Min);
+                let mut page_ui = ui.new_child(egui::UiBuilder::new().max_rect(content).layout(layout));
+                // Until its elements are measured, a page may hold more than fits
+                page_ui.set_clip_rect(rect.intersect(ui.clip_rect()));
+                for index in page.clone() {
+                    let element = &elements[index];
+                    let top = page_ui.cursor().top();
+                    if !matches!(element.kind, ElementKind::PageBreak) {
+                        self.render_top_level(&mut page_ui, index, element, text, content_size.x, pass);
+                    }
+                    remeasure |= self.preview.measure_on_page(index, page_ui.cursor().top() - top);
+                }
+            }
+            ui.add_space(PAGE_GAP);
+        });
+        if remeasure {
+            ui.ctx().request_repaint();
+        }
+    }
+}
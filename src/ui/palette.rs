@@ -0,0 +1,208 @@
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::keymap::{Action, Keymap};
+use crate::markdown::outline::HeadingEntry;
+use crate::ui::widgets::ClickAway;
+use crate::utils;
+use eframe::egui;
+
+/// Commands remembered for ranking, most recent first
+const MAX_RECENT: usize = 5;
+
+/// Score bonus for recently run commands
+const RECENT_BONUS: i32 = 6;
+
+/// State of the Ctrl+Shift+P command palette
+#[derive(Default)]
+pub struct CommandPalette {
+    open: bool,
+    click_away: ClickAway,
+    query: String,
+    selected: usize,
+    recent: Vec<Action>,
+}
+
+/// What choosing a palette row does
+#[derive(Clone, Copy)]
+enum PaletteItem {
+    Run(Action),
+    GoToLine(usize),
+    GoToOffset(usize),
+}
+
+struct PaletteRow {
+    /// `None` for hint rows that can't be chosen
+    item: Option<PaletteItem>,
+    label: String,
+    detail: String,
+}
+
+impl CommandPalette {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.click_away = ClickAway::opened();
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    fn remember(&mut self, action: Action) {
+        self.recent.retain(|&a| a != action);
+        self.recent.insert(0, action);
+        self.recent.truncate(MAX_RECENT);
+    }
+
+    /// Rows for the current query: `:42` goes to a line, `@name` to a
+    /// heading, anything else filters the commands
+    fn rows(&self, keymap: &Keymap, headings: &[HeadingEntry], line_count: usize) -> Vec<PaletteRow> {
+        if let Some(line) = self.query.strip_prefix(':') {
+            let row = match line.trim().parse::<usize>() {
+                Ok(line) if line >= 1 => PaletteRow {
+                    item: Some(PaletteItem::GoToLine(line.min(line_count) - 1)),
+                    label: format!("Go to line {}", line.min(line_count)),
+                    detail: format!("of {}", line_count),
+                },
+                _ => PaletteRow {
+                    item: None,
+                    label: format!("Type a line number between 1 and {}", line_count),
+                    detail: String::new(),
+                },
+            };
+            return vec![row];
+        }
+
+        if let Some(query) = self.query.strip_prefix('@') {
+            let mut matches: Vec<(i32, &HeadingEntry)> = headings
+                .iter()
+                .filter_map(|heading| Some((utils::fuzzy_score(&heading.text, query)?, heading)))
+                .collect();
+            if !query.trim().is_empty() {
+                matches.sort_by_key(|(score, _)| -score);
+            }
+            return matches
+                .into_iter()
+                .map(|(_, heading)| PaletteRow {
+                    item: Some(PaletteItem::GoToOffset(heading.offset)),
+                    label: format!("{}{}", "  ".repeat(heading.level as usize - 1), heading.text),
+                    detail: format!("H{}", heading.level),
+                })
+                .collect();
+        }
+
+        let mut matches: Vec<(i32, Action)> = Action::ALL
+            .into_iter()
+            .filter(|&action| action != Action::CommandPalette)
+            .filter_map(|action| {
+                let score = utils::fuzzy_score(action.label(), &self.query)?;
+                let bonus = match self.recent.iter().position(|&a| a == action) {
+                    Some(rank) => RECENT_BONUS + (MAX_RECENT - rank) as i32,
+                    None => 0,
+                };
+                Some((score + bonus, action))
+            })
+            .collect();
+        // Stable sort keeps menu order among equal scores
+        matches.sort_by_key(|(score, _)| -score);
+        matches
+            .into_iter()
+            .map(|(_, action)| PaletteRow {
+                item: Some(PaletteItem::Run(action)),
+                label: action.label().to_string(),
+                detail: keymap.shortcut_text(action),
+            })
+            .collect()
+    }
+}
+
+impl RmdApp {
+    /// Render the command palette and run the chosen entry
+    pub fn ui_command_palette(&mut self, ctx: &egui::Context) {
+        if !self.palette.open {
+            return;
+        }
+
+        let line_count = self.editor.text().lines().count().max(1);
+        let headings = self.editor.headings().to_vec();
+        let rows = self.palette.rows(&self.keymap, &headings, line_count);
+
+        let (up, down, enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        let palette = &mut self.palette;
+        if up {
+            palette.selected = palette.selected.saturating_sub(1);
+        }
+        if down && palette.selected + 1 < rows.len() {
+            palette.selected += 1;
+        }
+        palette.selected = palette.selected.min(rows.len().saturating_sub(1));
+
+        let mut chosen = enter.then_some(palette.selected);
+        let muted = self.theme.text_muted;
+        let area = egui::Area::new(egui::Id::new("command_palette"))
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 80.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_width(480.0);
+                    let input = ui.add(
+                        egui::TextEdit::singleline(&mut palette.query)
+                            .hint_text(t!("picker.command"))
+                            .desired_width(f32::INFINITY),
+                    );
+                    input.request_focus();
+                    if input.changed() {
+                        palette.selected = 0;
+                    }
+
+                    ui.separator();
+                    egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                        if rows.is_empty() {
+                            ui.label(egui::RichText::new(t!("picker.no_matches")).color(muted));
+                        }
+                        for (index, row) in rows.iter().enumerate() {
+                            let selected = index == palette.selected;
+                            let response = ui
+                                .horizontal(|ui| {
+                                    let response = ui.selectable_label(selected, &row.label);
+                                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                        ui.label(egui::RichText::new(&row.detail).small().color(muted));
+                                    });
+                                    response
+                                })
+                                .inner;
+                            if selected && (up || down) {
+                                response.scroll_to_me(None);
+                            }
+                            if response.clicked() {
+                                chosen = Some(index);
+                            }
+                        }
+                    });
+                });
+            });
+
+        let dismissed = self.palette.click_away.clicked(&area.response) || escape;
+        let item = chosen.and_then(|index| rows.get(index)).and_then(|row| row.item);
+        if item.is_none() && !dismissed {
+            return;
+        }
+
+        // Hand the keyboard back to the editor before running the command
+        self.palette.open = false;
+        ctx.memory_mut(|m| m.request_focus(self.editor.widget_id()));
+        match item {
+            Some(PaletteItem::Run(action)) => {
+                self.palette.remember(action);
+                self.run_action(ctx, action);
+            }
+            Some(PaletteItem::GoToLine(line)) => self.editor.go_to_line(line),
+            Some(PaletteItem::GoToOffset(offset)) => self.editor.set_cursor(offset),
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Pinning the preview to a file other than the one being edited
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::ui::layouts::LayoutMode;
+use eframe::egui;
+
+impl RmdApp {
+    /// Ask for a Markdown file and show it in the preview instead of the
+    /// editor's document
+    pub fn pin_preview_dialog(&mut self) {
+        let folder = self
+            .current_file
+            .as_deref()
+            .and_then(|path| path.parent())
+            .or(self.workspace_root.as_deref())
+            .map(|folder| folder.to_path_buf());
+        let mut dialog = rfd::FileDialog::new()
+            .set_title(t!("pinned.title"))
+            .add_filter(t!("dialog.markdown_files"), &self.config.markdown_extensions)
+            .add_filter(t!("dialog.all_files"), &["*"]);
+        if let Some(folder) = folder {
+            dialog = dialog.set_directory(folder);
+        }
+        let Some(path) = dialog.pick_file() else {
+            return;
+        };
+        self.preview.pin(path);
+        if self.layout.mode == LayoutMode::EditorOnly {
+            self.layout.set_mode(LayoutMode::Split);
+        }
+    }
+
+    /// Bar above a pinned preview naming the file, with a button to unpin
+    pub fn ui_pinned_header(&mut self, ui: &mut egui::Ui) {
+        let Some(pinned) = self.preview.pinned() else {
+            return;
+        };
+        let path = pinned.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let error = pinned.error().map(str::to_string);
+        let mut unpin = false;
+        ui.horizontal(|ui| {
+            ui.label("📌").on_hover_text(t!("pinned.pinned_here"));
+            ui.label(egui::RichText::new(name).strong()).on_hover_text(path.display().to_string());
+            if let Some(error) = error {
+                ui.colored_label(self.theme.error, error);
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                unpin = ui
+                    .small_button(t!("workspace.unpin"))
+                    .on_hover_text(t!("pinned.unpin_hint"))
+                    .clicked();
+            });
+        });
+        ui.separator();
+        if unpin {
+            self.preview.unpin();
+        }
+    }
+}
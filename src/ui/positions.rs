@@ -0,0 +1,59 @@
+//! Putting a document back where it was left: the selection, the editor's
+//! scroll position, and the preview's, kept in the document's sidecar with
+//! its bookmarks
+
+use crate::app::RmdApp;
+use crate::editor::view_state::ViewState;
+use crate::history::meta::{DocumentMeta, MetaStore, Position};
+
+impl RmdApp {
+    /// Remember where the current file is, before it is switched away
+    /// from or the app exits
+    pub fn save_position(&mut self) {
+        let Some(path) = &self.current_file else {
+            return;
+        };
+        let view = self.editor.view_state();
+        let position = Position {
+            anchor: view.anchor,
+            cursor: view.cursor,
+            editor_scroll: view.scroll_y,
+            preview_scroll: self.preview.scroll_fraction(),
+        };
+        // The top of the document is where it opens anyway
+        let position = (position != Position::default()).then_some(position);
+        let saved = MetaStore::open_default().and_then(|store| {
+            let meta = store.load(path);
+            if meta.position.is_none() && position.is_none() {
+                return Ok(());
+            }
+            store.save(path, &DocumentMeta { position, ..meta })
+        });
+        if let Err(e) = saved {
+            log::warn!("Failed to save the position in {}: {}", path.display(), e);
+        }
+    }
+
+    /// Put the caret, scroll positions, and bookmarks of the file just
+    /// opened back as they were, as far as its text allows. Preview Only
+    /// leaves the preview to the offer to resume reading.
+    pub fn restore_position(&mut self) {
+        let Some(path) = &self.current_file else {
+            return;
+        };
+        let Ok(store) = MetaStore::open_default() else {
+            return;
+        };
+        let meta = store.load(path);
+        let position = meta.position.unwrap_or_default();
+        self.editor.restore_view_state(&ViewState {
+            anchor: position.anchor,
+            cursor: position.cursor,
+            scroll_y: position.editor_scroll,
+            bookmarks: meta.bookmarks,
+        });
+        if position.preview_scroll > 0.0 && !self.is_reading() {
+            self.preview.scroll_to_fraction(position.preview_scroll);
+        }
+    }
+}
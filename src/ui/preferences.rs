@@ -0,0 +1,295 @@
+use crate::app::RmdApp;
+use crate::i18n::{self, t};
+use crate::utils;
+use eframe::egui;
+
+impl RmdApp {
+    /// Render the Preferences window
+    pub fn ui_preferences(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_preferences;
+        let mut changed = false;
+        let mut extensions_changed = false;
+
+        egui::Window::new(t!("preferences.title"))
+            .id(egui::Id::new("preferences"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(t!("preferences.language"));
+                    let system = t!("preferences.system_language");
+                    let selected = i18n::available()
+                        .find(|(id, _)| *id == self.config.language)
+                        .map_or(system, |(_, name)| name);
+                    egui::ComboBox::from_id_salt("language").selected_text(selected).show_ui(ui, |ui| {
+                        let language = &mut self.config.language;
+                        let mut picked = ui.selectable_value(language, String::new(), system).changed();
+                        for (id, name) in i18n::available() {
+                            picked |= ui.selectable_value(language, id.to_string(), name).changed();
+                        }
+                        if picked {
+                            i18n::set_language(language);
+                        }
+                    });
+                });
+
+                ui.add_space(8.0);
+                ui.heading(t!("preferences.editor"));
+                ui.horizontal(|ui| {
+                    ui.label(t!("preferences.ruler_column"));
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut self.config.editor.ruler_column).range(0..=400))
+                        .on_hover_text(t!("preferences.ruler_column_hint"))
+                        .changed();
+                });
+                ui.horizontal(|ui| {
+                    ui.label(t!("preferences.tab_size"));
+                    changed |= ui.add(egui::DragValue::new(&mut self.config.editor.tab_size).range(1..=16)).changed();
+                });
+                changed |= ui.checkbox(&mut self.config.editor.use_spaces_for_tabs, t!("preferences.use_spaces_for_tabs")).changed();
+                changed |= ui
+                    .checkbox(&mut self.config.editor.detect_indentation, t!("preferences.detect_indentation"))
+                    .on_hover_text(t!("preferences.detect_indentation_hint"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut self.config.editor.keep_clipboard_history, t!("preferences.keep_clipboard_history"))
+                    .on_hover_text(t!("preferences.keep_clipboard_history_hint"))
+                    .changed();
+                ui.label(t!("preferences.when_saving"));
+                let editor = &mut self.config.editor;
+                changed |= ui
+                    .checkbox(&mut editor.trim_trailing_whitespace, t!("preferences.trim_trailing_whitespace"))
+                    .on_hover_text(t!("preferences.trim_trailing_whitespace_hint"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut editor.ensure_final_newline, t!("preferences.final_newline"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut editor.collapse_blank_lines, t!("preferences.collapse_blank_lines"))
+                    .changed();
+
+                ui.add_space(8.0);
+                ui.heading(t!("preferences.markdown"));
+                changed |= ui
+                    .checkbox(&mut self.config.wiki_links, t!("preferences.wiki_links"))
+                    .changed();
+                extensions_changed = self.ui_markdown_extensions(ui);
+
+                ui.add_space(8.0);
+                ui.heading(t!("preferences.preview"));
+                changed |= ui
+                    .checkbox(&mut self.config.preview.emoji_shortcodes, t!("preferences.emoji_shortcodes"))
+                    .on_hover_text(t!("preferences.emoji_shortcodes_hint"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut self.config.preview.render_html, t!("preferences.render_html"))
+                    .on_hover_text(t!("preferences.render_html_hint"))
+                    .changed();
+                ui.horizontal(|ui| {
+                    ui.label(t!("preferences.max_code_lines"));
+                    changed |= ui
+                        .add(egui::DragValue::new(&mut self.config.preview.max_code_lines).range(0..=10_000))
+                        .on_hover_text(t!("preferences.max_code_lines_hint"))
+                        .changed();
+                    ui.label(t!("preferences.lines"));
+                });
+                changed |= ui
+                    .checkbox(&mut self.config.preview.number_headings, t!("preferences.number_headings"))
+                    .on_hover_text(t!("preferences.number_headings_hint"))
+                    .changed();
+                ui.horizontal(|ui| {
+                    ui.label(t!("preferences.content_width"));
+                    changed |= ui
+                        .add(
+                            egui::DragValue::new(&mut self.config.preview.max_content_width)
+                                .range(0.0..=4000.0)
+                                .speed(4.0)
+                                .suffix(" px"),
+                        )
+                        .on_hover_text(t!("preferences.content_width_hint"))
+                        .changed();
+                });
+                changed |= ui
+                    .checkbox(&mut self.config.preview.reading_progress, t!("preferences.reading_progress"))
+                    .on_hover_text(t!("preferences.reading_progress_hint"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut self.config.preview.show_header, t!("preferences.show_header"))
+                    .on_hover_text(t!("preferences.show_header_hint"))
+                    .changed();
+                ui.horizontal(|ui| {
+                    ui.label(t!("preferences.document_language"));
+                    changed |= ui
+                        .add(egui::TextEdit::singleline(&mut self.config.preview.document_language).desired_width(60.0))
+                        .on_hover_text(t!("preferences.document_language_hint"))
+                        .changed();
+                });
+
+                ui.add_space(8.0);
+                ui.heading(t!("preferences.colors"));
+                let colors = &mut self.config.colors;
+                changed |= ui
+                    .checkbox(&mut colors.preview, t!("preferences.preview_swatches"))
+                    .on_hover_text(t!("preferences.preview_swatches_hint"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut colors.editor, t!("preferences.editor_swatches"))
+                    .on_hover_text(t!("preferences.editor_swatches_hint"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut colors.rules.functions, t!("preferences.color_functions"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut colors.rules.short_hex_in_prose, t!("preferences.short_hex"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut colors.rules.numeric_hex_in_prose, t!("preferences.numeric_hex"))
+                    .on_hover_text(t!("preferences.numeric_hex_hint"))
+                    .changed();
+
+                ui.add_space(8.0);
+                ui.heading(t!("preferences.writing"));
+                let writing = &mut self.config.writing;
+                changed |= ui
+                    .checkbox(&mut writing.smart_punctuation, t!("preferences.smart_punctuation"))
+                    .on_hover_text(t!("preferences.smart_punctuation_hint"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut writing.smart_punctuation_on_save, t!("preferences.smart_punctuation_on_save"))
+                    .on_hover_text(t!("preferences.smart_punctuation_on_save_hint"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut writing.update_toc_on_save, t!("preferences.update_toc_on_save"))
+                    .on_hover_text(t!("preferences.update_toc_on_save_hint"))
+                    .changed();
+                ui.horizontal(|ui| {
+                    ui.label(t!("preferences.toc_depth"));
+                    changed |= ui
+                        .add(egui::Slider::new(&mut writing.toc_depth, 1..=6))
+                        .changed();
+                });
+                changed |= ui
+                    .checkbox(&mut writing.auto_capitalize, t!("preferences.auto_capitalize"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut writing.auto_correct, t!("preferences.auto_correct"))
+                    .on_hover_text(t!("preferences.auto_correct_hint"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut writing.auto_dashes, t!("preferences.auto_dashes"))
+                    .on_hover_text(t!("preferences.auto_dashes_hint"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut writing.renumber_lists, t!("preferences.renumber_lists"))
+                    .on_hover_text(t!("preferences.renumber_lists_hint"))
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut writing.auto_pair, t!("preferences.auto_pair"))
+                    .on_hover_text(t!("preferences.auto_pair_hint"))
+                    .changed();
+
+                ui.add_space(8.0);
+                ui.heading(t!("preferences.statistics"));
+                let stats = &mut self.config.stats;
+                changed |= ui
+                    .checkbox(&mut stats.enabled, t!("preferences.track_stats"))
+                    .on_hover_text(t!("preferences.track_stats_hint"))
+                    .changed();
+                ui.add_enabled_ui(stats.enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(t!("preferences.word_goal"));
+                        changed |= ui
+                            .add(egui::DragValue::new(&mut stats.daily_word_goal).range(0..=100_000))
+                            .on_hover_text(t!("preferences.word_goal_hint"))
+                            .changed();
+                    });
+                });
+
+                ui.add_space(8.0);
+                ui.heading(t!("preferences.updates"));
+                changed |= ui
+                    .checkbox(&mut self.config.updates.check_weekly, t!("preferences.check_weekly"))
+                    .on_hover_text(t!("preferences.check_weekly_hint"))
+                    .changed();
+
+                ui.add_space(8.0);
+                egui::CollapsingHeader::new(t!("preferences.keyboard_shortcuts"))
+                    .id_salt("preferences_keybindings")
+                    .show(ui, |ui| self.ui_keybindings(ui));
+
+                ui.add_space(8.0);
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(t!("preferences.export_settings"))
+                        .on_hover_text(t!("preferences.export_settings_hint"))
+                        .clicked()
+                    {
+                        self.export_settings();
+                    }
+                    if ui.button(t!("preferences.import_settings")).clicked() {
+                        self.import_settings();
+                    }
+                });
+            });
+
+        self.show_preferences = open;
+        if !open {
+            self.rebinding = None;
+        }
+        if extensions_changed {
+            if let Some(root) = self.workspace_root.clone() {
+                self.sidebar.refresh(&root, &self.config.markdown_extensions);
+            }
+        }
+        if changed || extensions_changed {
+            self.apply_markdown_settings();
+            self.refresh_backlinks();
+        }
+    }
+
+    /// The editable list of Markdown file extensions, returning whether it changed
+    fn ui_markdown_extensions(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut extensions = self.config.markdown_extensions.clone();
+        let mut remove = None;
+        ui.horizontal_wrapped(|ui| {
+            ui.label(t!("preferences.extensions"))
+                .on_hover_text(t!("preferences.extensions_hint"));
+            for (index, extension) in extensions.iter().enumerate() {
+                let last = extensions.len() == 1;
+                let button = ui
+                    .add_enabled(!last, egui::Button::new(format!(".{} ✕", extension)).small())
+                    .on_hover_text(t!("preferences.remove_extension"))
+                    .on_disabled_hover_text(t!("preferences.last_extension"));
+                if button.clicked() {
+                    remove = Some(index);
+                }
+            }
+        });
+        let mut add = false;
+        let new = utils::normalize_extension(&self.new_extension).filter(|e| !extensions.contains(e));
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.new_extension)
+                    .hint_text("mdx")
+                    .desired_width(80.0),
+            );
+            add |= response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            add |= ui
+                .add_enabled(new.is_some(), egui::Button::new(t!("preferences.add_extension")))
+                .on_disabled_hover_text(t!("preferences.add_extension_hint"))
+                .clicked();
+        });
+
+        if let Some(index) = remove {
+            extensions.remove(index);
+        } else if let Some(extension) = new.filter(|_| add) {
+            extensions.push(extension);
+            self.new_extension.clear();
+        } else {
+            return false;
+        }
+        self.config.set_markdown_extensions(extensions);
+        true
+    }
+}
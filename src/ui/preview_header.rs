@@ -0,0 +1,145 @@
+//! The header above the preview: the document's title, reading time, word
+//! count, date modified, and tags, which search for themselves when
+//! clicked. It folds away once the preview is scrolled down and comes back
+//! at the top.
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::header::DocumentHeader;
+use crate::stats::document::DocumentStats;
+use crate::utils;
+use eframe::egui;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Scrolled further down than this, the header folds away
+const COLLAPSE_AFTER: f32 = 48.0;
+
+/// The header as last worked out, and whether it is folded away
+#[derive(Default)]
+pub struct PreviewHeader {
+    /// The editor revision, file and dirty state it was worked out for
+    key: Option<(u64, Option<PathBuf>, bool)>,
+    header: DocumentHeader,
+    /// Reading time, word count and date, ready to show
+    facts: Vec<String>,
+    collapsed: bool,
+}
+
+impl PreviewHeader {
+    /// Fold away below `COLLAPSE_AFTER`, and only come back right at the
+    /// top, so the change in height can't set it flickering
+    fn follow_scroll(&mut self, offset: f32) {
+        if offset > COLLAPSE_AFTER {
+            self.collapsed = true;
+        } else if offset <= 1.0 {
+            self.collapsed = false;
+        }
+    }
+}
+
+impl RmdApp {
+    /// Reading time, words, and the date `modified`, as the header shows them
+    pub(crate) fn header_facts(&self, stats: &DocumentStats, modified: Option<SystemTime>) -> Vec<String> {
+        let mut facts = vec![t!("preview_header.reading_time", minutes = utils::estimate_reading_time(stats.prose_words))];
+        if self.is_cjk_document() {
+            facts.push(t!("stats.characters", chars = stats.prose_characters));
+        } else {
+            facts.push(t!("preview_header.words", words = stats.prose_words));
+        }
+        if let Some(modified) = modified {
+            let date = chrono::DateTime::<chrono::Local>::from(modified).format("%Y-%m-%d");
+            facts.push(t!("preview_header.modified", date = date));
+        }
+        facts
+    }
+
+    /// When the open file was last written
+    pub(crate) fn file_modified(&self) -> Option<SystemTime> {
+        let path = self.current_file.as_deref()?;
+        std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+    }
+
+    /// Work the header out again when the text, file, or saved state changed
+    fn refresh_preview_header(&mut self) {
+        let key = (self.editor.revision(), self.current_file.clone(), self.has_unsaved_changes);
+        if self.preview_header.key.as_ref() == Some(&key) {
+            return;
+        }
+        let text = self.editor.text();
+        let stats = DocumentStats::new(&text, self.markdown_renderer.flavor_for(&text));
+        self.preview_header.facts = self.header_facts(&stats, self.file_modified());
+        self.preview_header.header = DocumentHeader::parse(&text);
+        self.preview_header.key = Some(key);
+    }
+
+    /// The header at the top of the preview, when it is turned on
+    pub fn ui_preview_header(&mut self, ui: &mut egui::Ui) {
+        if !self.config.preview.show_header || self.preview.pinned().is_some() {
+            return;
+        }
+        self.refresh_preview_header();
+        self.preview_header.follow_scroll(self.preview.scroll_offset);
+
+        let id = ui.make_persistent_id("preview_header");
+        let mut state = egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(), id, true);
+        state.set_open(!self.preview_header.collapsed);
+        let header = &self.preview_header.header;
+        let mut search = None;
+        state.show_body_unindented(ui, |ui| {
+            if let Some(title) = &header.title {
+                ui.label(egui::RichText::new(title).heading().strong());
+            }
+            ui.label(egui::RichText::new(self.preview_header.facts.join(" · ")).small().color(self.theme.text_muted));
+            if !header.tags.is_empty() {
+                ui.horizontal_wrapped(|ui| {
+                    for tag in &header.tags {
+                        let chip = egui::Button::new(egui::RichText::new(tag).small())
+                            .rounding(10.0)
+                            .fill(self.theme.accent.gamma_multiply(0.15));
+                        if ui.add(chip).on_hover_text(t!("preview_header.tag_hint")).clicked() {
+                            search = Some(tag.clone());
+                        }
+                    }
+                });
+            }
+            ui.separator();
+        });
+        if let Some(tag) = search {
+            self.search_tag(ui.ctx(), &tag);
+        }
+    }
+
+    /// Look for `tag` across the open folder, or in the document when no
+    /// folder is open
+    fn search_tag(&mut self, ctx: &egui::Context, tag: &str) {
+        let Some(root) = self.workspace_root.clone() else {
+            self.find.show(Some(tag.to_string()));
+            return;
+        };
+        if !self.show_search_panel {
+            self.toggle_search_panel(ctx);
+        }
+        self.workspace_search.query = tag.to_string();
+        self.workspace_search.start(&root, &self.config.markdown_extensions);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_follow_scroll() {
+        let mut header = PreviewHeader::default();
+        header.follow_scroll(20.0);
+        assert!(!header.collapsed);
+        header.follow_scroll(200.0);
+        assert!(header.collapsed);
+        // Still folded on the way back up, until the very top
+        header.follow_scroll(20.0);
+        assert!(header.collapsed);
+        header.follow_scroll(0.0);
+        assert!(!header.collapsed);
+    }
+}
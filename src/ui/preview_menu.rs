@@ -0,0 +1,97 @@
+//! Right-click menu on elements of the rendered preview
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::export::{self, HtmlOptions};
+use crate::markdown::{copy, ElementKind, RenderedElement};
+use crate::ui::layouts::LayoutMode;
+use eframe::egui;
+use std::path::PathBuf;
+
+impl RmdApp {
+    /// Attach the preview context menu for `element` to `response`.
+    /// `source` is the Markdown the element was rendered from.
+    pub(crate) fn preview_context_menu(&mut self, response: &egui::Response, element: &RenderedElement, source: &str) {
+        response.context_menu(|ui| {
+            let markdown = source.get(element.span.range()).unwrap_or_default();
+            if ui.button(t!("preview_menu.copy_text")).clicked() {
+                match &element.kind {
+                    // Bullets and numbers kept, and the list itself for
+                    // programs that paste formatting
+                    ElementKind::UnorderedList(..) | ElementKind::OrderedList(..) | ElementKind::BlockQuote(..) => {
+                        let html = export::markdown_to_html(markdown, HtmlOptions::default(), |_, url| url.to_string());
+                        crate::platform::copy_with_html(ui.ctx(), &mut self.clipboard, copy::block_text(markdown), Some(html));
+                    }
+                    _ => ui.ctx().copy_text(element.plain_text()),
+                }
+                ui.close_menu();
+            }
+            if ui.button(t!("preview_menu.copy_markdown")).clicked() {
+                ui.ctx().copy_text(markdown.to_string());
+                ui.close_menu();
+            }
+
+            match &element.kind {
+                ElementKind::CodeBlock(_, code) if ui.button(t!("preview_menu.copy_code")).clicked() => {
+                    ui.ctx().copy_text(code.clone());
+                    ui.close_menu();
+                }
+                ElementKind::Table(rows) => {
+                    if ui.button(t!("preview_menu.copy_tsv")).on_hover_text(t!("preview_menu.copy_tsv_hint")).clicked() {
+                        crate::platform::copy_with_html(ui.ctx(), &mut self.clipboard, copy::tsv(rows), Some(copy::table_html(rows)));
+                        ui.close_menu();
+                    }
+                    if ui.button(t!("preview_menu.copy_csv")).clicked() {
+                        ui.ctx().copy_text(copy::csv(rows));
+                        ui.close_menu();
+                    }
+                }
+                ElementKind::Image(_, url) => {
+                    let path = self.resolve_preview_path(url);
+                    if ui.add_enabled(path.is_some(), egui::Button::new(t!("preview_menu.copy_image_path"))).clicked() {
+                        if let Some(path) = &path {
+                            ui.ctx().copy_text(path.display().to_string());
+                        }
+                        ui.close_menu();
+                    }
+                    let exists = path.as_ref().is_some_and(|path| path.exists());
+                    if ui.add_enabled(exists, egui::Button::new(t!("preview_menu.open_folder"))).clicked() {
+                        if let Some(path) = &path {
+                            crate::platform::show_in_folder(path);
+                        }
+                        ui.close_menu();
+                    }
+                }
+                _ => {}
+            }
+
+            ui.separator();
+            // A pinned preview shows another file, so its offsets mean nothing in the editor
+            let jump = ui
+                .add_enabled(self.preview.pinned().is_none(), egui::Button::new(t!("preview_menu.jump_to_source")))
+                .on_disabled_hover_text(t!("preview_menu.pinned_elsewhere"));
+            if jump.clicked() {
+                if self.layout.mode == LayoutMode::PreviewOnly {
+                    self.layout.set_mode(LayoutMode::Split);
+                }
+                self.editor.set_cursor(element.span.start);
+                ui.ctx().memory_mut(|m| m.request_focus(self.editor.widget_id()));
+                ui.close_menu();
+            }
+        });
+    }
+
+    /// Local file an image in the preview points at, relative to the
+    /// previewed document. `None` for web images and unsaved documents.
+    fn resolve_preview_path(&self, url: &str) -> Option<PathBuf> {
+        if crate::markdown::linkcheck::has_scheme(url) {
+            return None;
+        }
+        let document = match self.preview.pinned() {
+            Some(pinned) => Some(pinned.path()),
+            None => self.current_file.as_deref(),
+        };
+        let url = url.split(['#', '?']).next().unwrap_or(url);
+        Some(document?.parent()?.join(url))
+    }
+}
@@ -0,0 +1,62 @@
+//! Selecting and copying in the preview: text is selected by dragging
+//! across it, and whole elements with Ctrl+A or Shift+click, copied with
+//! Ctrl+C in document order
+
+use crate::app::RmdApp;
+use crate::markdown::RenderedElement;
+use crate::ui::focus::PREVIEW_FOCUS_ID;
+use eframe::egui;
+use egui::text_selection::LabelSelectionState;
+
+impl RmdApp {
+    /// Ctrl+A and Ctrl+C while the preview is focused, or hovered with
+    /// nothing else focused. A click without Shift or Escape ends the
+    /// selection.
+    pub(crate) fn preview_selection_keys(&mut self, ui: &mut egui::Ui, elements: &[RenderedElement], text: &str) {
+        let hovered = ui.rect_contains_pointer(ui.clip_rect());
+        let (focused, nothing_focused) =
+            ui.memory(|m| (m.has_focus(egui::Id::new(PREVIEW_FOCUS_ID)), m.focused().is_none()));
+        let (pressed, shift) = ui.input(|i| (i.pointer.any_pressed(), i.modifiers.shift));
+        let ended = (pressed && hovered && !shift) || ui.input(|i| i.key_pressed(egui::Key::Escape));
+        if ended && !self.preview.selection.is_empty() {
+            self.preview.selection.clear();
+        }
+        let active = focused || (hovered && nothing_focused);
+        if !active {
+            return;
+        }
+        if ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::A)) {
+            self.preview.selection.select_all(elements, text);
+            clear_text_selection(ui.ctx());
+        }
+        let Some(copied) = self.preview.selection.copy_text(elements, text) else {
+            return;
+        };
+        let copy = ui.input_mut(|i| {
+            let before = i.events.len();
+            i.events.retain(|event| !matches!(event, egui::Event::Copy));
+            i.events.len() != before
+        });
+        if copy {
+            ui.ctx().copy_text(copied);
+        }
+    }
+
+    /// A click on an element: Shift+click selects from the element
+    /// clicked before through this one
+    pub(crate) fn preview_selection_click(&mut self, ui: &egui::Ui, element: &RenderedElement, source: &str) {
+        let extend = ui.input(|i| i.modifiers.shift);
+        self.preview.selection.click(element.span.range(), extend, source);
+        if extend {
+            clear_text_selection(ui.ctx());
+        }
+    }
+}
+
+/// End a selection made by dragging across text, which a selection of
+/// whole elements replaces
+fn clear_text_selection(ctx: &egui::Context) {
+    let mut state = LabelSelectionState::load(ctx);
+    state.clear_selection();
+    state.store(ctx);
+}
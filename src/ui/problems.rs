@@ -0,0 +1,223 @@
+//! Problems panel listing the broken links and images found by Check Links,
+//! and headings that repeat another's explicit `{#id}`
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::linkcheck::{self, LinkKind, LinkReference, LinkStatus, WebCheck};
+use crate::markdown::outline;
+use crate::ui::focus::Pane;
+use crate::ui::widgets::name_icon_button;
+use eframe::egui;
+use std::collections::HashMap;
+use std::ops::Range;
+
+const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp"];
+
+/// Links found in the document, kept up to date while the panel is open
+#[derive(Default)]
+pub struct Problems {
+    links: Vec<LinkReference>,
+    /// Explicit heading ids used before, and where the repeat starts
+    duplicate_anchors: Vec<(String, usize)>,
+    /// Editor revision the links were found in; `None` to scan again
+    revision: Option<u64>,
+    /// Answers from web addresses, kept across scans
+    web_status: HashMap<String, LinkStatus>,
+    web_check: Option<WebCheck>,
+    /// List every link rather than only the broken ones
+    show_all: bool,
+}
+
+/// What clicking in the panel asks for
+enum ProblemAction {
+    Select(Range<usize>),
+    BrowseImage(Range<usize>, String),
+    CheckWeb,
+    Refresh,
+}
+
+impl RmdApp {
+    /// Show the Problems panel with the links of the current document
+    pub fn check_links(&mut self) {
+        self.show_problems = true;
+        self.problems.revision = None;
+    }
+
+    fn scan_links(&mut self) {
+        let text = self.editor.text();
+        let flavor = self.markdown_renderer.flavor_for(&text);
+        let problems = &mut self.problems;
+        problems.links = linkcheck::check_links(&text, flavor, self.current_file.as_deref(), &self.config.markdown_extensions);
+        for link in &mut problems.links {
+            if let Some(status) = problems.web_status.get(&link.url) {
+                link.status = status.clone();
+            }
+        }
+        let headings = outline::headings(&text);
+        problems.duplicate_anchors =
+            outline::duplicate_anchors(&headings).into_iter().map(|heading| (heading.slug.clone(), heading.offset)).collect();
+        problems.revision = Some(self.editor.revision());
+    }
+
+    /// Render the Problems panel at the bottom of the window
+    pub fn ui_problems_panel(&mut self, ctx: &egui::Context) {
+        if let Some(check) = self.problems.web_check.as_mut() {
+            let results = check.poll();
+            if !check.is_running() {
+                self.problems.web_check = None;
+            }
+            if !results.is_empty() {
+                self.problems.web_status.extend(results);
+                self.problems.revision = None;
+            }
+        }
+        if self.problems.revision != Some(self.editor.revision()) {
+            self.scan_links();
+        }
+
+        let text = self.editor.text();
+        let mut action = None;
+        let mut open = true;
+        // Ctrl+8 focuses Check Again, from where Tab goes down the list
+        let focus = self.pane_focus.take(Pane::Problems);
+        egui::TopBottomPanel::bottom("problems")
+            .resizable(true)
+            .default_height(160.0)
+            .show(ctx, |ui| {
+                let problems = &mut self.problems;
+                let problem_count = problems.links.iter().filter(|l| l.status.is_problem()).count();
+                let unchecked: Vec<String> = problems
+                    .links
+                    .iter()
+                    .filter(|l| l.is_web() && l.status == LinkStatus::External)
+                    .map(|l| l.url.clone())
+                    .collect();
+
+                ui.horizontal(|ui| {
+                    ui.strong(t!("problems.title"));
+                    ui.label(
+                        egui::RichText::new(t!("problems.summary", links = problems.links.len(), broken = problem_count))
+                            .color(self.theme.text_muted),
+                    );
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if name_icon_button(ui.small_button("✕"), t!("dialog.close")).clicked() {
+                            open = false;
+                        }
+                        let check_again = name_icon_button(ui.small_button("⟳"), t!("problems.check_again"));
+                        if focus {
+                            check_again.request_focus();
+                        }
+                        if check_again.clicked() {
+                            action = Some(ProblemAction::Refresh);
+                        }
+                        ui.checkbox(&mut problems.show_all, t!("problems.show_all"));
+                        match &problems.web_check {
+                            Some(check) => {
+                                ui.spinner();
+                                ui.label(t!("problems.checking", done = check.done, total = check.total));
+                            }
+                            None => {
+                                let button = ui.add_enabled(!unchecked.is_empty(), egui::Button::new(t!("problems.check_web")));
+                                if button.on_hover_text(t!("problems.check_web_hint")).clicked() {
+                                    action = Some(ProblemAction::CheckWeb);
+                                }
+                            }
+                        }
+                    });
+                });
+                ui.separator();
+
+                if self.current_file.is_none() {
+                    ui.label(
+                        egui::RichText::new(t!("problems.save_first"))
+                            .small()
+                            .color(self.theme.text_muted),
+                    );
+                }
+                egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                    let shown = problems.links.iter().filter(|l| problems.show_all || l.status.is_problem());
+                    let mut empty = true;
+                    for (id, offset) in &problems.duplicate_anchors {
+                        empty = false;
+                        ui.horizontal(|ui| {
+                            let line = text[..*offset].matches('\n').count() + 1;
+                            let end = text[*offset..].find('\n').map_or(text.len(), |i| offset + i);
+                            if ui.selectable_label(false, t!("problems.duplicate_anchor", id = id, line = line)).clicked() {
+                                action = Some(ProblemAction::Select(*offset..end));
+                            }
+                            ui.label(egui::RichText::new(t!("problems.duplicate_anchor_hint")).color(self.theme.warning));
+                        });
+                    }
+                    for link in shown {
+                        empty = false;
+                        ui.horizontal(|ui| {
+                            let line = text[..link.range.start].matches('\n').count() + 1;
+                            let icon = match link.kind {
+                                LinkKind::Link => "🔗",
+                                LinkKind::Image => "🖼",
+                            };
+                            let label = t!("problems.link", icon = icon, url = link.url, line = line);
+                            if ui.selectable_label(false, label).clicked() {
+                                action = Some(ProblemAction::Select(link.range.clone()));
+                            }
+                            let color = if link.status.is_problem() { self.theme.error } else { self.theme.text_muted };
+                            ui.label(egui::RichText::new(link.status.description()).color(color));
+                            let fixable = link.kind == LinkKind::Image && link.status == LinkStatus::Missing;
+                            if fixable
+                                && !self.readonly
+                                && ui.small_button(t!("dialog.browse")).on_hover_text(t!("problems.browse_hint")).clicked()
+                            {
+                                action = Some(ProblemAction::BrowseImage(link.range.clone(), link.url.clone()));
+                            }
+                        });
+                    }
+                    if empty {
+                        ui.label(egui::RichText::new(t!("problems.none")).color(self.theme.text_muted));
+                    }
+                });
+
+                if matches!(action, Some(ProblemAction::CheckWeb)) {
+                    problems.web_check = Some(WebCheck::start(unchecked));
+                }
+            });
+
+        if !open {
+            self.show_problems = false;
+            self.problems.web_check = None;
+        }
+        match action {
+            Some(ProblemAction::Select(range)) => {
+                self.editor.set_selection(range.start, range.end);
+                ctx.memory_mut(|m| m.request_focus(self.editor.widget_id()));
+            }
+            Some(ProblemAction::BrowseImage(range, url)) => self.browse_for_image(&text, range, &url),
+            Some(ProblemAction::Refresh) => {
+                self.problems.web_status.clear();
+                self.problems.revision = None;
+            }
+            Some(ProblemAction::CheckWeb) | None => {}
+        }
+    }
+
+    /// Ask for the image a broken reference meant and point it there,
+    /// relative to the document
+    fn browse_for_image(&mut self, text: &str, range: Range<usize>, url: &str) {
+        let Some(folder) = self.current_file.as_deref().and_then(|path| path.parent()) else {
+            return;
+        };
+        let Some(url_range) = linkcheck::url_range(text, &range, url) else {
+            return;
+        };
+        let Some(image) = rfd::FileDialog::new()
+            .set_title(t!("problems.find_image", url = url))
+            .set_directory(folder)
+            .add_filter(t!("dialog.images"), &IMAGE_EXTENSIONS)
+            .pick_file()
+        else {
+            return;
+        };
+        let link = linkcheck::relative_link(folder, &image);
+        self.editor.replace_range_in_place(url_range, &link);
+        self.has_unsaved_changes = true;
+    }
+}
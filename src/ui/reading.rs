@@ -0,0 +1,106 @@
+//! Reading long documents in Preview Only: how far through the view is,
+//! the reading time left, and picking up where the last visit stopped
+
+use crate::app::RmdApp;
+use crate::history::meta::{DocumentMeta, MetaStore};
+use crate::i18n::t;
+use crate::ui::layouts::LayoutMode;
+use crate::ui::toasts::{Severity, ToastAction};
+use crate::utils;
+use eframe::egui;
+
+/// Positions this close to the top or the bottom aren't worth resuming at
+const RESUME_MARGIN: f32 = 0.02;
+
+/// The position worth offering to resume at, from how far down the
+/// document the view was when it was left
+fn resume_position(fraction: f32) -> Option<f32> {
+    (RESUME_MARGIN..=1.0 - RESUME_MARGIN).contains(&fraction).then_some(fraction)
+}
+
+impl RmdApp {
+    /// Whether the preview is being read on its own, with progress shown
+    pub(crate) fn is_reading(&self) -> bool {
+        self.config.preview.reading_progress && self.layout.mode == LayoutMode::PreviewOnly && self.preview.pinned().is_none()
+    }
+
+    /// A thin bar across the preview showing how far through the document
+    /// the view is, and the reading time left, for documents too long to
+    /// fit
+    pub(crate) fn ui_reading_progress(&mut self, ui: &mut egui::Ui) {
+        if !self.is_reading() || self.preview.max_scroll() <= 0.0 {
+            return;
+        }
+        let fraction = self.preview.scroll_fraction();
+        let words = self.preview.words_left();
+        let left = if words == 0 {
+            t!("reading.done").to_string()
+        } else {
+            t!("reading.minutes_left", minutes = utils::estimate_reading_time(words))
+        };
+        ui.horizontal(|ui| {
+            ui.label(
+                egui::RichText::new(format!("{:.0}% · {}", fraction * 100.0, left))
+                    .small()
+                    .color(self.theme.text_muted),
+            );
+        });
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 3.0), egui::Sense::hover());
+        let painter = ui.painter();
+        painter.rect_filled(rect, 1.5, self.theme.border);
+        let done = egui::Rect::from_min_size(rect.min, egui::vec2(rect.width() * fraction, rect.height()));
+        painter.rect_filled(done, 1.5, self.theme.accent);
+        ui.add_space(4.0);
+    }
+
+    /// Remember how far the current file was read, before it is closed
+    pub fn save_reading_position(&mut self) {
+        if !self.is_reading() {
+            return;
+        }
+        let Some(path) = &self.current_file else {
+            return;
+        };
+        let reading_position = resume_position(self.preview.scroll_fraction());
+        let saved = MetaStore::open_default().and_then(|store| {
+            let meta = store.load(path);
+            if meta.reading_position == reading_position {
+                return Ok(());
+            }
+            store.save(path, &DocumentMeta { reading_position, ..meta })
+        });
+        if let Err(e) = saved {
+            log::warn!("Failed to save the reading position for {}: {}", path.display(), e);
+        }
+    }
+
+    /// Offer to scroll back to where the file just opened was left
+    pub fn offer_resume_reading(&mut self) {
+        if !self.is_reading() {
+            return;
+        }
+        let Some(path) = &self.current_file else {
+            return;
+        };
+        let Ok(store) = MetaStore::open_default() else {
+            return;
+        };
+        if let Some(fraction) = store.load(path).reading_position.and_then(resume_position) {
+            let message = t!("reading.resume", percent = format!("{:.0}", fraction * 100.0));
+            self.toasts.push_action(Severity::Info, message, None, Some(ToastAction::ResumeReading(fraction)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resume_position() {
+        assert_eq!(resume_position(0.0), None);
+        assert_eq!(resume_position(0.4), Some(0.4));
+        // Finished documents start over
+        assert_eq!(resume_position(0.995), None);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::app::RmdApp;
+use crate::keymap::Action;
+use eframe::egui;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How long the "document is read-only" hint stays up
+const HINT_DURATION: Duration = Duration::from_secs(3);
+
+/// Whether the file can't be written, judged from its metadata
+pub fn is_readonly_on_disk(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .map(|metadata| metadata.permissions().readonly())
+        .unwrap_or(false)
+}
+
+/// Whether this frame's input would have edited an unlocked document
+pub fn attempted_edit(ui: &egui::Ui) -> bool {
+    ui.input(|i| {
+        i.events.iter().any(|event| match event {
+            egui::Event::Text(_) | egui::Event::Paste(_) | egui::Event::Cut => true,
+            egui::Event::Key { key, pressed: true, modifiers, .. } => {
+                matches!(key, egui::Key::Backspace | egui::Key::Delete | egui::Key::Enter | egui::Key::Tab)
+                    || (modifiers.command && matches!(key, egui::Key::Z | egui::Key::Y))
+            }
+            _ => false,
+        })
+    })
+}
+
+impl RmdApp {
+    /// Lock or unlock the current document
+    pub fn toggle_readonly(&mut self) {
+        self.readonly = !self.readonly;
+        self.readonly_hint = None;
+        if self.readonly {
+            self.autocomplete.close();
+            self.editor.exit_snippet();
+        }
+    }
+
+    /// How to unlock, naming the current Toggle Read-only shortcut
+    pub fn unlock_hint(&self) -> String {
+        match self.keymap.chord(Action::ToggleReadonly) {
+            Some(chord) => format!("document is read-only — press {} to unlock", chord),
+            None => "document is read-only — use File > Read-only to unlock".to_string(),
+        }
+    }
+
+    /// Tell the user why their edit or save did nothing
+    pub fn show_readonly_hint(&mut self) {
+        self.readonly_hint = Some(Instant::now());
+    }
+
+    /// Render the transient read-only hint above the status bar
+    pub fn ui_readonly_hint(&mut self, ctx: &egui::Context) {
+        let Some(shown_at) = self.readonly_hint else {
+            return;
+        };
+        if shown_at.elapsed() >= HINT_DURATION {
+            self.readonly_hint = None;
+            return;
+        }
+
+        egui::Area::new(egui::Id::new("readonly_hint"))
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_BOTTOM, egui::vec2(0.0, -36.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(
+                        egui::RichText::new(format!("🔒 {}", self.unlock_hint()))
+                            .color(self.theme.warning),
+                    );
+                });
+            });
+    }
+}
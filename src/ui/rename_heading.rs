@@ -0,0 +1,224 @@
+//! Renaming a heading from the outline or with the caret on it: the links
+//! to its anchor in the document, and in other workspace files the backlink
+//! index knows to link here, are updated with it after a look at the edits
+
+use crate::app::RmdApp;
+use crate::editor::error::Operation;
+use crate::editor::{load, save};
+use crate::i18n::t;
+use crate::markdown::rename::{self, HeadingRename, LineChange};
+use crate::ui::toasts::Severity;
+use crate::workspace::links;
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+/// The rename being typed, then confirmed
+pub struct RenameHeadingDialog {
+    /// Offset of the heading in the document
+    offset: usize,
+    new_text: String,
+    /// The edits worked out for the new text, once asked for
+    plan: Option<RenamePlan>,
+    /// Focus the text box when the dialog opens
+    focus: bool,
+}
+
+/// Every edit a rename makes
+struct RenamePlan {
+    /// The document with the heading renamed, as one edit
+    document: String,
+    changes: Vec<LineChange>,
+    files: Vec<FileRename>,
+}
+
+/// Another file's links to update
+struct FileRename {
+    path: PathBuf,
+    /// Its contents when the rename was worked out, so a change since is noticed
+    original: String,
+    updated: String,
+    changes: Vec<LineChange>,
+}
+
+impl RmdApp {
+    /// Rename the heading the caret is on
+    pub fn rename_heading_at_caret(&mut self) {
+        let text = self.editor.text();
+        let caret = self.editor.cursor();
+        let heading = self.editor.headings().iter().rev().find(|heading| heading.offset <= caret).map(|heading| heading.offset);
+        let on_heading = heading.filter(|&offset| !text[offset..caret].contains('\n'));
+        match on_heading {
+            Some(offset) => self.open_rename_heading(offset),
+            None => self.notify(Severity::Info, t!("rename_heading.no_heading")),
+        }
+    }
+
+    /// Ask for the new text of the heading starting at `offset`
+    pub fn open_rename_heading(&mut self, offset: usize) {
+        if self.readonly {
+            self.show_readonly_hint();
+            return;
+        }
+        let text = self.editor.text();
+        let Some(range) = rename::heading_text_range(&text, offset) else {
+            return;
+        };
+        self.rename_heading = Some(RenameHeadingDialog { offset, new_text: text[range].to_string(), plan: None, focus: true });
+    }
+
+    /// The edits renaming the heading at `offset` to `new_text` makes here
+    /// and in the files that link to this one
+    fn plan_rename(&self, offset: usize, new_text: &str) -> Option<RenamePlan> {
+        let text = self.editor.text();
+        let HeadingRename { slugs, edits } = rename::rename_heading(&text, offset, new_text)?;
+        let mut files = Vec::new();
+        if let Some(current) = &self.current_file {
+            for source in self.backlinks.backlinks_to(current) {
+                let original = match load::read(source, Operation::Open) {
+                    Ok(original) => original,
+                    Err(e) => {
+                        log::warn!("Not updating links in {}: {}", source.display(), e);
+                        continue;
+                    }
+                };
+                let edits = rename::link_replacements(&original, &slugs, |link| links::points_to(source, current, link));
+                if edits.is_empty() {
+                    continue;
+                }
+                files.push(FileRename {
+                    path: source.clone(),
+                    updated: rename::apply(&original, &edits),
+                    changes: rename::line_changes(&original, &edits),
+                    original,
+                });
+            }
+        }
+        Some(RenamePlan { document: rename::apply(&text, &edits), changes: rename::line_changes(&text, &edits), files })
+    }
+
+    /// The new text, then the edits it makes, applied once confirmed
+    pub fn ui_rename_heading(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.rename_heading else {
+            return;
+        };
+        let muted = self.theme.text_muted;
+        let document_name = self
+            .current_file
+            .as_deref()
+            .and_then(Path::file_name)
+            .map_or_else(|| t!("rename_heading.this_document").to_string(), |name| name.to_string_lossy().to_string());
+        let indexing = self.backlinks.is_building();
+        let root = self.workspace_root.as_deref();
+
+        let mut open = true;
+        let mut preview = false;
+        let mut back = false;
+        let mut confirm = false;
+        let mut cancel = false;
+        egui::Window::new(t!("rename_heading.title"))
+            .id(egui::Id::new("rename_heading"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                let Some(plan) = &dialog.plan else {
+                    ui.label(t!("rename_heading.new_text"));
+                    let field = ui.add(egui::TextEdit::singleline(&mut dialog.new_text).desired_width(320.0));
+                    if std::mem::take(&mut dialog.focus) {
+                        field.request_focus();
+                    }
+                    let entered = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    ui.horizontal(|ui| {
+                        let ready = !dialog.new_text.trim().is_empty();
+                        preview = ui.add_enabled(ready, egui::Button::new(t!("rename_heading.preview"))).clicked() || (ready && entered);
+                        cancel = ui.button(t!("dialog.cancel")).clicked();
+                    });
+                    return;
+                };
+                let lines = plan.changes.len() - 1 + plan.files.iter().map(|file| file.changes.len()).sum::<usize>();
+                ui.label(t!("rename_heading.summary", lines = lines, files = plan.files.len()));
+                if indexing {
+                    ui.label(egui::RichText::new(t!("rename_heading.indexing")).small().color(muted));
+                }
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    let groups = std::iter::once((document_name.clone(), &plan.changes))
+                        .chain(plan.files.iter().map(|file| {
+                            let name = root.and_then(|root| file.path.strip_prefix(root).ok()).unwrap_or(&file.path);
+                            (name.display().to_string(), &file.changes)
+                        }));
+                    for (name, changes) in groups {
+                        ui.label(egui::RichText::new(name).strong());
+                        for change in changes {
+                            ui.horizontal(|ui| {
+                                ui.label(egui::RichText::new(format!("{:>4}", change.line)).monospace().color(muted));
+                                ui.vertical(|ui| {
+                                    ui.label(egui::RichText::new(format!("- {}", change.before)).monospace().color(muted));
+                                    ui.label(egui::RichText::new(format!("+ {}", change.after)).monospace());
+                                });
+                            });
+                        }
+                    }
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    confirm = ui.button(t!("rename_heading.rename")).clicked();
+                    back = ui.button(t!("rename_heading.back")).clicked();
+                    cancel = ui.button(t!("dialog.cancel")).clicked();
+                });
+            });
+
+        if preview {
+            let (offset, new_text) = (dialog.offset, dialog.new_text.clone());
+            match self.plan_rename(offset, &new_text) {
+                Some(plan) => {
+                    if let Some(dialog) = &mut self.rename_heading {
+                        dialog.plan = Some(plan);
+                    }
+                }
+                None => self.notify(Severity::Warning, t!("rename_heading.invalid")),
+            }
+        } else if back {
+            dialog.plan = None;
+            dialog.focus = true;
+        } else if confirm {
+            if let Some(plan) = self.rename_heading.take().and_then(|dialog| dialog.plan) {
+                self.apply_rename(plan);
+            }
+        } else if cancel || !open {
+            self.rename_heading = None;
+        }
+    }
+
+    /// Write the other files, each replaced whole, then rename in the
+    /// document as one undoable edit
+    fn apply_rename(&mut self, plan: RenamePlan) {
+        let mut written = 0;
+        let mut updated_lines = 0;
+        for file in &plan.files {
+            // Changed since the edits were worked out: leave it alone
+            let unchanged = std::fs::read_to_string(&file.path).is_ok_and(|text| text == file.original);
+            if !unchanged {
+                self.notify(Severity::Warning, t!("rename_heading.changed", path = file.path.display()));
+                continue;
+            }
+            match save::write_atomic(&file.path, file.updated.as_bytes()) {
+                Ok(()) => {
+                    written += 1;
+                    updated_lines += file.changes.len();
+                }
+                Err(e) => self.notify_file_error(e),
+            }
+        }
+        self.editor.replace_text(&plan.document);
+        self.has_unsaved_changes = true;
+        if plan.files.is_empty() {
+            self.notify(Severity::Success, t!("rename_heading.renamed", lines = plan.changes.len() - 1));
+        } else {
+            self.notify(Severity::Success, t!("rename_heading.renamed_files", lines = updated_lines, files = written));
+        }
+        if written > 0 {
+            self.refresh_backlinks();
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Following the display: the window opens at its saved size in logical
+//! points, fitted to the monitor it opens on, and the preview keeps its
+//! place when the window moves to a monitor with another scale factor
+
+use crate::app::RmdApp;
+use eframe::egui;
+
+/// Size the window opens at before the monitor is known, in points
+pub const INITIAL_SIZE: egui::Vec2 = egui::vec2(1024.0, 700.0);
+
+/// Smallest window, in points; fits a 1366×768 laptop at 150%
+pub const MIN_SIZE: egui::Vec2 = egui::vec2(640.0, 480.0);
+
+/// Size a window with no saved size gets at most
+const DEFAULT_SIZE: egui::Vec2 = egui::vec2(1400.0, 900.0);
+
+/// Share of the monitor a window with no saved size takes
+const DEFAULT_SHARE: f32 = 0.8;
+
+/// Share of the monitor a saved size is cut down to
+const MAX_SHARE: f32 = 0.95;
+
+/// The scale factor last drawn at, and whether the window was fitted to
+/// its monitor yet
+#[derive(Default)]
+pub struct WindowScale {
+    pixels_per_point: Option<f32>,
+    sized: bool,
+}
+
+/// Size to open at on a `monitor` of that size: the `saved` size when
+/// there is one, or most of the monitor, never more than fits on it
+fn fitted_size(saved: egui::Vec2, monitor: egui::Vec2) -> egui::Vec2 {
+    let size = if saved.x > 0.0 && saved.y > 0.0 {
+        saved
+    } else {
+        DEFAULT_SIZE.min(monitor * DEFAULT_SHARE)
+    };
+    size.min(monitor * MAX_SHARE).max(MIN_SIZE.min(monitor))
+}
+
+impl RmdApp {
+    /// Fit the window to its monitor once the settings are in, remember
+    /// its size as it changes, and hold the preview in place when the
+    /// scale factor changes
+    pub fn follow_window_scale(&mut self, ctx: &egui::Context) {
+        let pixels_per_point = ctx.pixels_per_point();
+        let previous = self.window_scale.pixels_per_point.replace(pixels_per_point);
+        if previous.is_some_and(|previous| previous != pixels_per_point) {
+            log::debug!("Scale factor changed from {:?} to {}", previous, pixels_per_point);
+            // Text wraps a little differently at another scale
+            self.preview.hold_view_top();
+            ctx.request_repaint();
+        }
+
+        let settings_applied = self.startup.as_ref().is_none_or(|startup| startup.settings_applied);
+        let viewport = ctx.input(|i| i.viewport().clone());
+        if !self.window_scale.sized {
+            let Some(monitor) = viewport.monitor_size.filter(|_| settings_applied) else {
+                return;
+            };
+            self.window_scale.sized = true;
+            if self.config.window.maximized {
+                ctx.send_viewport_cmd(egui::ViewportCommand::Maximized(true));
+            } else {
+                let saved = egui::vec2(self.config.window.width, self.config.window.height);
+                ctx.send_viewport_cmd(egui::ViewportCommand::InnerSize(fitted_size(saved, monitor)));
+            }
+            return;
+        }
+
+        let maximized = viewport.maximized.unwrap_or(false);
+        self.config.window.maximized = maximized;
+        let Some(inner) = viewport.inner_rect.filter(|_| !maximized && viewport.minimized != Some(true)) else {
+            return;
+        };
+        let window = &mut self.config.window;
+        if (inner.width() - window.width).abs() >= 1.0 || (inner.height() - window.height).abs() >= 1.0 {
+            window.width = inner.width().round();
+            window.height = inner.height().round();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fitted_size() {
+        let close = |size: egui::Vec2, expected: egui::Vec2| (size - expected).length() < 0.01;
+        let full_hd = egui::vec2(1920.0, 1080.0);
+        // Nothing saved: most of the monitor, up to the default
+        assert!(close(fitted_size(egui::Vec2::ZERO, full_hd), egui::vec2(1400.0, 864.0)));
+        // A 1920×1080 laptop screen at 150%
+        assert!(close(fitted_size(egui::Vec2::ZERO, egui::vec2(1280.0, 720.0)), egui::vec2(1024.0, 576.0)));
+        // A size saved on a larger screen is cut down to fit
+        assert!(close(fitted_size(egui::vec2(2400.0, 1500.0), full_hd), egui::vec2(1824.0, 1026.0)));
+        assert!(close(fitted_size(egui::vec2(1000.0, 700.0), full_hd), egui::vec2(1000.0, 700.0)));
+        // Never smaller than the minimum, unless the monitor is
+        assert!(close(fitted_size(egui::vec2(300.0, 200.0), full_hd), MIN_SIZE));
+        assert!(close(fitted_size(egui::Vec2::ZERO, egui::vec2(600.0, 400.0)), egui::vec2(600.0, 400.0)));
+    }
+}
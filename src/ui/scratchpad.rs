@@ -0,0 +1,226 @@
+//! A scratchpad for quick notes below the editor: one small Markdown
+//! buffer kept in the config folder, whatever document is open, and saved
+//! on its own as it changes. Selected text can be dragged out of it into
+//! the document.
+
+use crate::app::RmdApp;
+use crate::config::manager::SAVE_DELAY;
+use crate::config::Config;
+use crate::i18n::t;
+use crate::ui::widgets::name_icon_button;
+use crate::utils;
+use eframe::egui;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// The scratchpad's text and whether it is on disk yet
+#[derive(Default)]
+pub struct Scratchpad {
+    /// File the text is kept in, once it has been read
+    path: Option<PathBuf>,
+    text: String,
+    /// When the text last changed, if it hasn't been written since
+    changed_at: Option<Instant>,
+    /// Byte range of the selection, to drag out
+    selection: Option<Range<usize>>,
+}
+
+/// Text dragged from the scratchpad, and where it was taken from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScratchFragment {
+    pub text: String,
+    range: Range<usize>,
+}
+
+impl Scratchpad {
+    /// The scratchpad kept in `path`, empty when there is no such file
+    fn open(path: PathBuf) -> Self {
+        let text = std::fs::read_to_string(&path).unwrap_or_default();
+        Self { path: Some(path), text, ..Default::default() }
+    }
+
+    /// Read the scratchpad from `config_dir/rmd/scratchpad.md` the first
+    /// time it is shown
+    fn ensure_loaded(&mut self) -> anyhow::Result<()> {
+        if self.path.is_none() {
+            *self = Self::open(Config::config_dir()?.join("scratchpad.md"));
+        }
+        Ok(())
+    }
+
+    /// The text changed; write it once it has been left alone a moment
+    fn note_change(&mut self) {
+        self.changed_at = Some(Instant::now());
+    }
+
+    /// Write the text once it has stopped changing for as long as the
+    /// config waits, or at once with `now`
+    pub fn save_if_due(&mut self, now: bool) -> anyhow::Result<()> {
+        let Some(changed_at) = self.changed_at else {
+            return Ok(());
+        };
+        if !now && changed_at.elapsed() < SAVE_DELAY {
+            return Ok(());
+        }
+        self.changed_at = None;
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, &self.text)?;
+        Ok(())
+    }
+
+    /// The selected text, to drag into the document
+    fn fragment(&self) -> Option<ScratchFragment> {
+        let range = self.selection.clone().filter(|range| !range.is_empty())?;
+        let text = self.text.get(range.clone())?.to_string();
+        Some(ScratchFragment { text, range })
+    }
+
+    /// Take `fragment` out, once it was dropped in the document, if the
+    /// text is still there
+    fn remove(&mut self, fragment: &ScratchFragment) {
+        if self.text.get(fragment.range.clone()) != Some(fragment.text.as_str()) {
+            return;
+        }
+        self.text.replace_range(fragment.range.clone(), "");
+        self.selection = None;
+        self.note_change();
+    }
+}
+
+/// Id of the text field, so Ctrl+9 can focus it
+fn text_id() -> egui::Id {
+    egui::Id::new("scratchpad_text")
+}
+
+impl RmdApp {
+    /// Show or hide the scratchpad
+    pub fn toggle_scratchpad(&mut self) {
+        self.config.window.scratchpad_open = !self.config.window.scratchpad_open;
+    }
+
+    /// Show the scratchpad with its text focused
+    pub fn focus_scratchpad(&mut self, ctx: &egui::Context) {
+        self.config.window.scratchpad_open = true;
+        ctx.memory_mut(|m| m.request_focus(text_id()));
+    }
+
+    /// Write the scratchpad when it is due, or now when `now`
+    pub fn save_scratchpad(&mut self, now: bool) {
+        if let Err(e) = self.scratchpad.save_if_due(now) {
+            self.notify_error(t!("scratchpad.save_failed"), e);
+        }
+    }
+
+    /// The scratchpad panel along the bottom of the window, keeping the
+    /// height it was dragged to
+    pub fn ui_scratchpad(&mut self, ctx: &egui::Context) {
+        if let Err(e) = self.scratchpad.ensure_loaded() {
+            self.notify_error(t!("scratchpad.load_failed"), e);
+            self.config.window.scratchpad_open = false;
+            return;
+        }
+        let mut open = true;
+        let font = egui::FontId::monospace(self.config.font.editor_font_size);
+        let panel = egui::TopBottomPanel::bottom("scratchpad")
+            .resizable(true)
+            .default_height(self.config.window.scratchpad_height)
+            .height_range(80.0..=600.0)
+            .show(ctx, |ui| {
+                let fragment = self.scratchpad.fragment();
+                ui.horizontal(|ui| {
+                    ui.strong(t!("scratchpad.title"));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if name_icon_button(ui.small_button("✕"), t!("scratchpad.close")).clicked() {
+                            open = false;
+                        }
+                        if let Some(fragment) = fragment {
+                            let id = ui.id().with("scratch_fragment");
+                            ui.dnd_drag_source(id, fragment, |ui| {
+                                ui.label(egui::RichText::new(t!("scratchpad.drag")).small().color(self.theme.text_muted));
+                            })
+                            .response
+                            .on_hover_text(t!("scratchpad.drag_hint"));
+                        }
+                    });
+                });
+                egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                    let output = egui::TextEdit::multiline(&mut self.scratchpad.text)
+                        .id(text_id())
+                        .font(font)
+                        .hint_text(t!("scratchpad.hint"))
+                        .desired_width(f32::INFINITY)
+                        .desired_rows(4)
+                        .show(ui);
+                    if output.response.changed() {
+                        self.scratchpad.note_change();
+                    }
+                    if let Some(range) = output.cursor_range {
+                        let text = &self.scratchpad.text;
+                        let [start, end] = range.sorted_cursors().map(|cursor| utils::char_to_byte(text, cursor.ccursor.index));
+                        self.scratchpad.selection = Some(start..end);
+                    }
+                });
+            });
+        let height = panel.response.rect.height();
+        if (height - self.config.window.scratchpad_height).abs() >= 1.0 {
+            self.config.window.scratchpad_height = height;
+        }
+        if !open {
+            self.config.window.scratchpad_open = false;
+        }
+    }
+
+    /// Put text dragged from the scratchpad into the document at byte
+    /// `pos`, moving it out of the scratchpad
+    pub fn drop_scratch_fragment(&mut self, fragment: &ScratchFragment, pos: usize) {
+        if self.readonly {
+            self.show_readonly_hint();
+            return;
+        }
+        self.editor.set_cursor(pos);
+        self.editor.insert_text(&fragment.text);
+        self.has_unsaved_changes = true;
+        self.scratchpad.remove(fragment);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_move_out() {
+        let path = std::env::temp_dir().join(format!("rmd-scratchpad-{}.md", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut pad = Scratchpad::open(path.clone());
+        assert_eq!(pad.text, "");
+        pad.text = "call Ana\n- buy milk\n".to_string();
+        pad.note_change();
+        // Not yet: the text only just changed
+        pad.save_if_due(false).unwrap();
+        assert!(!path.exists());
+        pad.save_if_due(true).unwrap();
+        assert_eq!(Scratchpad::open(path.clone()).text, "call Ana\n- buy milk\n");
+
+        pad.selection = Some(9..20);
+        let fragment = pad.fragment().unwrap();
+        assert_eq!(fragment.text, "- buy milk\n");
+        pad.remove(&fragment);
+        assert_eq!(pad.text, "call Ana\n");
+        // Gone already, so dropping it again takes nothing
+        pad.remove(&fragment);
+        assert_eq!(pad.text, "call Ana\n");
+        pad.selection = Some(4..4);
+        assert_eq!(pad.fragment(), None);
+
+        pad.save_if_due(true).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "call Ana\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,137 @@
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::theme::Theme;
+use crate::workspace::search::LineMatch;
+use eframe::egui;
+use std::path::PathBuf;
+
+/// Id of the query field, so the shortcut can focus it
+fn query_id() -> egui::Id {
+    egui::Id::new("workspace_search_query")
+}
+
+impl RmdApp {
+    /// Show or hide the workspace search panel, focusing the query when shown
+    pub fn toggle_search_panel(&mut self, ctx: &egui::Context) {
+        if self.show_search_panel {
+            self.show_search_panel = false;
+            self.workspace_search.cancel();
+        } else {
+            self.focus_search_panel(ctx);
+        }
+    }
+
+    /// Show the workspace search panel with its query focused
+    pub fn focus_search_panel(&mut self, ctx: &egui::Context) {
+        self.show_search_panel = true;
+        ctx.memory_mut(|m| m.request_focus(query_id()));
+    }
+
+    /// Render the Find in Folder panel
+    pub fn ui_search_panel(&mut self, ctx: &egui::Context) {
+        self.workspace_search.poll();
+
+        let mut open: Option<(PathBuf, usize)> = None;
+        egui::SidePanel::left("workspace_search")
+            .resizable(true)
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                ui.heading(t!("search_panel.title"));
+
+                let Some(root) = self.workspace_root.clone() else {
+                    ui.label(t!("search_panel.no_folder"));
+                    if ui.button(t!("action.open_folder")).clicked() {
+                        self.open_folder_dialog();
+                    }
+                    return;
+                };
+
+                let search = &mut self.workspace_search;
+                let mut changed = ui
+                    .add(
+                        egui::TextEdit::singleline(&mut search.query)
+                            .id(query_id())
+                            .hint_text(t!("search_panel.hint"))
+                            .desired_width(f32::INFINITY),
+                    )
+                    .changed();
+                ui.horizontal(|ui| {
+                    let options = &mut search.options;
+                    changed |= ui
+                        .toggle_value(&mut options.case_sensitive, "Aa")
+                        .on_hover_text(t!("search_panel.match_case"))
+                        .changed();
+                    changed |= ui
+                        .toggle_value(&mut options.whole_word, "ab")
+                        .on_hover_text(t!("search_panel.whole_word"))
+                        .changed();
+                    changed |= ui
+                        .toggle_value(&mut options.regex, ".*")
+                        .on_hover_text(t!("search_panel.regex"))
+                        .changed();
+                });
+                if changed {
+                    search.start(&root, &self.config.markdown_extensions);
+                }
+
+                if let Some(error) = search.error() {
+                    ui.colored_label(self.theme.error, error);
+                } else if !search.query.is_empty() {
+                    let key = if search.is_running() { "search_panel.searching" } else { "search_panel.status" };
+                    let status = t!(key, lines = search.line_count(), files = search.results().len());
+                    ui.label(egui::RichText::new(status).color(self.theme.text_muted));
+                }
+                ui.separator();
+
+                let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for file in search.results() {
+                            let name = file.path.strip_prefix(&root).unwrap_or(&file.path);
+                            egui::CollapsingHeader::new(name.display().to_string())
+                                .id_salt(&file.path)
+                                .default_open(true)
+                                .show(ui, |ui| {
+                                    for line in &file.lines {
+                                        let job = highlighted_line(line, &self.theme, font_id.clone());
+                                        if ui.selectable_label(false, job).clicked() {
+                                            open = Some((file.path.clone(), line.line));
+                                        }
+                                    }
+                                });
+                        }
+                    });
+            });
+
+        if let Some((path, line)) = open {
+            if self.current_file.as_deref() == Some(path.as_path()) || self.open_path(&path) {
+                self.editor.go_to_line(line);
+            }
+        }
+    }
+}
+
+/// Lay out a result line with its matches highlighted
+fn highlighted_line(line: &LineMatch, theme: &Theme, font_id: egui::FontId) -> egui::text::LayoutJob {
+    let plain = egui::TextFormat::simple(font_id.clone(), theme.text);
+    let highlight = egui::TextFormat {
+        background: theme.selection,
+        color: theme.accent,
+        ..egui::TextFormat::simple(font_id, theme.text)
+    };
+
+    let mut job = egui::text::LayoutJob::default();
+    job.append(&format!("{:>4}  ", line.line + 1), 0.0, egui::TextFormat {
+        color: theme.text_muted,
+        ..plain.clone()
+    });
+    let mut pos = 0;
+    for range in &line.ranges {
+        job.append(&line.text[pos..range.start], 0.0, plain.clone());
+        job.append(&line.text[range.clone()], 0.0, highlight.clone());
+        pos = range.end;
+    }
+    job.append(&line.text[pos..], 0.0, plain);
+    job
+}
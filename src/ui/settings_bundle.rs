@@ -0,0 +1,150 @@
+//! Preferences → Export Settings… and Import Settings…: all settings in one
+//! file, and a dialog showing what an import replaces before doing it, and
+//! undoing it after
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::config::bundle::{self, Bundle, Change};
+use crate::config::Config;
+use crate::editor::snippets::SnippetLibrary;
+use crate::keymap::Keymap;
+use crate::ui::toasts::Severity;
+use crate::ui::typing_aids::TypingAidsState;
+use eframe::egui;
+use std::path::PathBuf;
+
+/// The Import Settings dialog
+#[derive(Default)]
+pub struct SettingsImport {
+    open: bool,
+    /// Bundle picked and checked, waiting for the user to go ahead
+    pending: Option<(PathBuf, Bundle)>,
+    /// Whether this dialog imported settings it can still undo
+    imported: bool,
+}
+
+impl RmdApp {
+    /// Write all settings to a bundle the user picks
+    pub fn export_settings(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title(t!("settings_bundle.export_title"))
+            .add_filter(t!("settings_bundle.bundle"), &["zip"])
+            .set_file_name("rmd-settings.zip")
+            .save_file()
+        else {
+            return;
+        };
+        let written = Config::config_dir()
+            .and_then(|folder| bundle::export(&folder, &self.config))
+            .and_then(|archive| Ok(std::fs::write(&path, archive)?));
+        match written {
+            Ok(()) => self.notify(Severity::Success, t!("toast.settings_exported", path = path.display())),
+            Err(e) => self.notify_error(t!("error.export_settings"), format!("{:#}", e)),
+        }
+    }
+
+    /// Pick a bundle and check it, then ask before importing it
+    pub fn import_settings(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .set_title(t!("settings_bundle.import_title"))
+            .add_filter(t!("settings_bundle.bundle"), &["zip"])
+            .pick_file()
+        else {
+            return;
+        };
+        let read = std::fs::read(&path).map_err(anyhow::Error::from).and_then(|archive| Bundle::read(&archive));
+        match read {
+            Ok(bundle) => {
+                self.settings_import = SettingsImport { open: true, pending: Some((path, bundle)), imported: false };
+            }
+            Err(e) => self.notify_error(t!("error.read_settings_bundle"), format!("{:#}", e)),
+        }
+    }
+
+    /// What an import would replace, with buttons to go ahead, and after
+    /// importing one to put the previous settings back
+    pub fn ui_settings_import(&mut self, ctx: &egui::Context) {
+        if !self.settings_import.open {
+            return;
+        }
+        let Ok(folder) = Config::config_dir() else {
+            self.settings_import = SettingsImport::default();
+            return;
+        };
+        let mut open = true;
+        let mut import = false;
+        let mut restore = false;
+        let mut cancel = false;
+        egui::Window::new(t!("settings_bundle.title"))
+            .id(egui::Id::new("import_settings"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                if let Some((path, bundle)) = &self.settings_import.pending {
+                    ui.label(t!("settings_bundle.source", path = path.display(), version = bundle.app_version));
+                    ui.add_space(4.0);
+                    for (name, change) in bundle.changes(&folder) {
+                        let change = match change {
+                            Change::Replaces => "settings_bundle.replaces",
+                            Change::Adds => "settings_bundle.adds",
+                            Change::Unchanged => "settings_bundle.unchanged",
+                        };
+                        ui.label(t!(change, name = name));
+                    }
+                    ui.add_space(4.0);
+                    ui.label(egui::RichText::new(t!("settings_bundle.backed_up")).color(self.theme.text_muted));
+                    ui.horizontal(|ui| {
+                        import = ui.button(t!("settings_bundle.import")).clicked();
+                        cancel = ui.button(t!("dialog.cancel")).clicked();
+                    });
+                } else if self.settings_import.imported && bundle::has_backup(&folder) {
+                    ui.label(t!("settings_bundle.imported"));
+                    ui.horizontal(|ui| {
+                        restore = ui.button(t!("settings_bundle.restore")).clicked();
+                        cancel = ui.button(t!("dialog.close")).clicked();
+                    });
+                } else {
+                    cancel = true;
+                }
+            });
+
+        if import {
+            if let Some((_, bundle)) = self.settings_import.pending.take() {
+                match bundle.import(&folder) {
+                    Ok(()) => {
+                        self.reload_settings();
+                        self.settings_import.imported = true;
+                    }
+                    Err(e) => {
+                        self.notify_error(t!("error.import_settings"), format!("{:#}", e));
+                        cancel = true;
+                    }
+                }
+            }
+        }
+        if restore {
+            match bundle::restore_backup(&folder) {
+                Ok(()) => {
+                    self.reload_settings();
+                    self.notify(Severity::Info, t!("toast.settings_restored"));
+                }
+                Err(e) => self.notify_error(t!("error.restore_settings"), format!("{:#}", e)),
+            }
+            cancel = true;
+        }
+        if cancel || !open {
+            self.settings_import = SettingsImport::default();
+        }
+    }
+
+    /// Read every settings file again after they were replaced
+    fn reload_settings(&mut self) {
+        self.config = Config::load_or_default();
+        self.keymap = Keymap::load_or_default();
+        self.snippets = SnippetLibrary::load_or_default();
+        self.typing_aids = TypingAidsState::load();
+        self.apply_markdown_settings();
+    }
+}
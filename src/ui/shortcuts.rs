@@ -0,0 +1,181 @@
+use crate::app::RmdApp;
+use crate::i18n::{self, t};
+use crate::keymap::{Action, KeyChord};
+use eframe::egui;
+
+/// Help dialog and Preferences sections list actions in this order
+const CATEGORIES: [&str; 7] = ["File", "Edit", "Structure", "Transform", "Insert", "View", "Tools"];
+
+impl RmdApp {
+    /// Render the Keyboard Shortcuts help dialog, generated from the keymap
+    pub fn ui_shortcuts_help(&mut self, ctx: &egui::Context) {
+        let mut open = self.show_shortcuts;
+        egui::Window::new(t!("shortcuts.title"))
+            .id(egui::Id::new("shortcuts_help"))
+            .open(&mut open)
+            .collapsible(false)
+            .default_height(420.0)
+            .show(ctx, |ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.shortcuts_filter)
+                        .hint_text(t!("shortcuts.search"))
+                        .desired_width(f32::INFINITY),
+                );
+                ui.add_space(4.0);
+                let filter = self.shortcuts_filter.trim().to_lowercase();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let mut any = false;
+                    for category in CATEGORIES {
+                        let rows: Vec<(Action, Option<String>)> = actions_in(category)
+                            .map(|action| (action, self.keymap.chord(action).map(|chord| chord.to_string())))
+                            .filter(|(action, chord)| matches_filter(action.label(), chord.as_deref(), &filter))
+                            .collect();
+                        if rows.is_empty() {
+                            continue;
+                        }
+                        any = true;
+                        ui.heading(category_label(category));
+                        egui::Grid::new(("shortcuts_help", category))
+                            .num_columns(2)
+                            .spacing([24.0, 4.0])
+                            .show(ui, |ui| {
+                                for (action, chord) in rows {
+                                    ui.label(action.label());
+                                    match chord {
+                                        Some(chord) => ui.monospace(chord),
+                                        None => ui.label(egui::RichText::new("—").color(self.theme.text_muted)),
+                                    };
+                                    ui.end_row();
+                                }
+                            });
+                        ui.add_space(8.0);
+                    }
+                    if !any {
+                        ui.label(egui::RichText::new(t!("shortcuts.no_match")).color(self.theme.text_muted));
+                    }
+                    ui.label(
+                        egui::RichText::new(t!("shortcuts.change_hint"))
+                            .small()
+                            .color(self.theme.text_muted),
+                    );
+                });
+            });
+        self.show_shortcuts = open;
+    }
+
+    /// Preferences section listing every action; click a shortcut to record a new one
+    pub fn ui_keybindings(&mut self, ui: &mut egui::Ui) {
+        let mut changed = self.capture_rebinding(ui);
+        let conflicts = self.keymap.conflicts();
+
+        egui::ScrollArea::vertical().max_height(260.0).show(ui, |ui| {
+            egui::Grid::new("keybindings")
+                .num_columns(3)
+                .spacing([16.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    for action in CATEGORIES.into_iter().flat_map(actions_in) {
+                        ui.label(action.label());
+
+                        let chord = self.keymap.chord(action);
+                        let text = if self.rebinding == Some(action) {
+                            t!("shortcuts.press").to_string()
+                        } else {
+                            chord.map_or(t!("shortcuts.unbound").to_string(), |chord| chord.to_string())
+                        };
+                        let button = egui::Button::new(text).selected(self.rebinding == Some(action));
+                        if ui.add(button).on_hover_text(t!("shortcuts.rebind_hint")).clicked() {
+                            self.rebinding = Some(action);
+                        }
+
+                        ui.horizontal(|ui| {
+                            let clashes = conflicts
+                                .iter()
+                                .find(|(c, _)| Some(*c) == chord)
+                                .map(|(_, actions)| actions.iter().filter(|&&a| a != action));
+                            if let Some(clashes) = clashes {
+                                let others: Vec<&str> = clashes.map(|a| a.label()).collect();
+                                ui.label(egui::RichText::new("⚠").color(self.theme.error))
+                                    .on_hover_text(t!("shortcuts.also_bound", actions = others.join(", ")));
+                            }
+                            if chord.is_some() && ui.small_button(t!("shortcuts.clear")).clicked() {
+                                self.keymap.set(action, None);
+                                changed = true;
+                            }
+                            if !self.keymap.is_default(action) && ui.small_button(t!("shortcuts.reset")).clicked() {
+                                self.keymap.reset(action);
+                                changed = true;
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+        });
+
+        if changed {
+            if let Err(e) = self.keymap.save() {
+                self.notify_error(t!("error.save_keybindings"), e);
+            }
+        }
+    }
+
+    /// Record the next chord pressed for the action being rebound;
+    /// Escape cancels. Returns whether a binding changed.
+    fn capture_rebinding(&mut self, ui: &mut egui::Ui) -> bool {
+        let Some(action) = self.rebinding else {
+            return false;
+        };
+        let pressed = ui.input_mut(|i| {
+            let index = i
+                .events
+                .iter()
+                .position(|event| matches!(event, egui::Event::Key { pressed: true, .. }))?;
+            match i.events.remove(index) {
+                egui::Event::Key { key, modifiers, .. } => Some(KeyChord::new(modifiers, key)),
+                _ => None,
+            }
+        });
+
+        match pressed {
+            Some(chord) if chord.key == egui::Key::Escape && chord.modifiers.is_none() => {
+                self.rebinding = None;
+                false
+            }
+            Some(chord) => {
+                self.keymap.set(action, Some(chord));
+                self.rebinding = None;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Whether an action's label or shortcut contains `filter`, which is lowercase
+fn matches_filter(label: &str, chord: Option<&str>, filter: &str) -> bool {
+    filter.is_empty()
+        || label.to_lowercase().contains(filter)
+        || chord.is_some_and(|chord| chord.to_lowercase().contains(filter))
+}
+
+/// A category's heading in the interface language
+pub fn category_label(category: &'static str) -> &'static str {
+    i18n::lookup(&format!("category.{}", category.to_lowercase())).unwrap_or(category)
+}
+
+fn actions_in(category: &str) -> impl Iterator<Item = Action> + '_ {
+    Action::ALL.into_iter().filter(move |action| action.category() == category)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_filter() {
+        assert!(matches_filter("Save As...", Some("Ctrl+Shift+S"), ""));
+        assert!(matches_filter("Save As...", Some("Ctrl+Shift+S"), "save"));
+        assert!(matches_filter("Save As...", Some("Ctrl+Shift+S"), "shift+s"));
+        assert!(!matches_filter("Check Links", None, "ctrl"));
+    }
+}
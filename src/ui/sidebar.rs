@@ -0,0 +1,421 @@
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::keymap::Action;
+use crate::ui::focus::Pane;
+use crate::ui::unsaved::Replacement;
+use crate::ui::widgets::name_icon_button;
+use crate::workspace::files::{self, FileNode};
+use eframe::egui;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// State of the sidebar file tree
+#[derive(Default)]
+pub struct Sidebar {
+    tree: Vec<FileNode>,
+    /// File or folder being renamed inline, with the name typed so far
+    renaming: Option<(PathBuf, String)>,
+    /// Focus the rename field on the next frame
+    focus_rename: bool,
+    /// File or folder waiting for delete confirmation
+    confirm_delete: Option<PathBuf>,
+    /// Message from the last failed file operation
+    error: Option<String>,
+    /// Every file in `tree`, flattened when first asked for
+    files: Option<Vec<PathBuf>>,
+    /// Folders expanded in the tree
+    expanded: HashSet<PathBuf>,
+}
+
+impl Sidebar {
+    /// Re-read the tree from disk, listing files with the Markdown `extensions`
+    pub fn refresh(&mut self, root: &Path, extensions: &[String]) {
+        self.tree = files::file_tree(root, extensions);
+        self.files = None;
+    }
+
+    /// Markdown files in the tree, in tree order, without touching the disk
+    pub fn files(&mut self) -> &[PathBuf] {
+        fn collect(nodes: &[FileNode], files: &mut Vec<PathBuf>) {
+            for node in nodes {
+                match &node.children {
+                    Some(children) => collect(children, files),
+                    None => files.push(node.path.clone()),
+                }
+            }
+        }
+        let tree = &self.tree;
+        self.files.get_or_insert_with(|| {
+            let mut files = Vec::new();
+            collect(tree, &mut files);
+            files
+        })
+    }
+
+    pub fn expanded(&self) -> &HashSet<PathBuf> {
+        &self.expanded
+    }
+
+    pub fn set_expanded(&mut self, expanded: HashSet<PathBuf>) {
+        self.expanded = expanded;
+    }
+
+    fn start_rename(&mut self, path: PathBuf) {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.renaming = Some((path, name));
+        self.focus_rename = true;
+    }
+}
+
+/// Something the user asked for in the tree, applied after it is drawn
+enum SidebarAction {
+    Open(PathBuf),
+    /// Switch to another workspace folder
+    OpenWorkspace(PathBuf),
+    /// Jump to a zero-based line of the open document
+    GoToLine(usize),
+    /// Move the caret to a byte offset of the open document
+    GoToOffset(usize),
+    NewFile(PathBuf),
+    NewFolder(PathBuf),
+    Rename(PathBuf),
+    CommitRename,
+    CancelRename,
+    Delete(PathBuf),
+    ConfirmDelete,
+    CancelDelete,
+}
+
+/// What the tree needs while drawing nodes
+struct TreeContext<'a> {
+    renaming: &'a mut Option<(PathBuf, String)>,
+    focus_rename: &'a mut bool,
+    expanded: &'a mut HashSet<PathBuf>,
+    current_file: Option<&'a Path>,
+    actions: &'a mut Vec<SidebarAction>,
+    /// Focus the first row drawn, after Ctrl+3
+    focus_first: bool,
+}
+
+impl RmdApp {
+    /// Render the workspace file tree
+    pub fn ui_sidebar(&mut self, ctx: &egui::Context) {
+        let Some(root) = self.workspace_root.clone() else {
+            return;
+        };
+
+        let mut actions = Vec::new();
+        let focus_files = self.pane_focus.take(Pane::Files);
+        let panel = egui::SidePanel::left("sidebar")
+            .resizable(true)
+            .default_width(220.0)
+            .show(ctx, |ui| {
+                let title = root
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| root.display().to_string());
+                ui.horizontal(|ui| {
+                    ui.strong(title)
+                        .context_menu(|ui| folder_menu(ui, &root, &mut actions));
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let menu = ui.menu_button("⏷", |ui| {
+                            if let Some(folder) = self.ui_workspace_folders(ui) {
+                                actions.push(SidebarAction::OpenWorkspace(folder));
+                            }
+                            ui.separator();
+                            self.action_button(ui, Action::OpenFolder, true);
+                        });
+                        name_icon_button(menu.response, t!("sidebar.switch_workspace"));
+                    });
+                });
+                if let Some(error) = &self.sidebar.error {
+                    ui.colored_label(self.theme.error, error);
+                }
+                ui.separator();
+
+                let section = egui::TopBottomPanel::bottom("bookmarks")
+                    .resizable(true)
+                    .show_inside(ui, |ui| {
+                        if let Some(line) = self.ui_bookmarks(ui) {
+                            actions.push(SidebarAction::GoToLine(line));
+                        }
+                    });
+                self.pane_focus.note(ui.ctx(), Pane::Bookmarks, section.response.rect);
+
+                egui::TopBottomPanel::bottom("figures")
+                    .resizable(true)
+                    .show_inside(ui, |ui| {
+                        if let Some(offset) = self.ui_figures(ui) {
+                            actions.push(SidebarAction::GoToOffset(offset));
+                        }
+                    });
+
+                let section = egui::TopBottomPanel::bottom("outline")
+                    .resizable(true)
+                    .show_inside(ui, |ui| {
+                        if let Some(offset) = self.ui_outline(ui) {
+                            actions.push(SidebarAction::GoToOffset(offset));
+                        }
+                    });
+                self.pane_focus.note(ui.ctx(), Pane::Outline, section.response.rect);
+
+                if self.config.wiki_links {
+                    egui::TopBottomPanel::bottom("backlinks")
+                        .resizable(true)
+                        .show_inside(ui, |ui| {
+                            if let Some(path) = self.ui_backlinks(ui) {
+                                actions.push(SidebarAction::Open(path));
+                            }
+                        });
+                }
+
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        let sidebar = &mut self.sidebar;
+                        let mut tree = TreeContext {
+                            renaming: &mut sidebar.renaming,
+                            focus_rename: &mut sidebar.focus_rename,
+                            expanded: &mut sidebar.expanded,
+                            current_file: self.current_file.as_deref(),
+                            actions: &mut actions,
+                            focus_first: focus_files,
+                        };
+                        show_nodes(ui, &sidebar.tree, &mut tree);
+
+                        // Right-clicking the empty space below the tree targets the root
+                        ui.allocate_response(ui.available_size(), egui::Sense::click())
+                            .context_menu(|ui| folder_menu(ui, &root, &mut actions));
+                    });
+            });
+        self.pane_focus.note(ctx, Pane::Files, panel.response.rect);
+
+        if let Some(path) = &self.sidebar.confirm_delete {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            egui::Window::new(t!("sidebar.delete"))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+                .show(ctx, |ui| {
+                    ui.label(t!("sidebar.delete_prompt", name = name));
+                    ui.horizontal(|ui| {
+                        if ui.button(t!("sidebar.delete")).clicked() {
+                            actions.push(SidebarAction::ConfirmDelete);
+                        }
+                        if ui.button(t!("dialog.cancel")).clicked() {
+                            actions.push(SidebarAction::CancelDelete);
+                        }
+                    });
+                });
+        }
+
+        for action in actions {
+            self.apply_sidebar_action(action, &root);
+        }
+    }
+
+    fn apply_sidebar_action(&mut self, action: SidebarAction, root: &Path) {
+        let result = match action {
+            SidebarAction::Open(path) => {
+                if self.current_file.as_deref() != Some(path.as_path()) {
+                    self.replace_document(Replacement::Open(path));
+                }
+                return;
+            }
+            SidebarAction::OpenWorkspace(folder) => {
+                self.open_workspace(folder);
+                return;
+            }
+            SidebarAction::GoToLine(line) => {
+                self.editor.go_to_line(line);
+                return;
+            }
+            SidebarAction::GoToOffset(offset) => {
+                self.editor.set_cursor(offset);
+                return;
+            }
+            SidebarAction::NewFile(dir) => {
+                self.replace_document(Replacement::CreateIn(dir));
+                return;
+            }
+            SidebarAction::NewFolder(dir) => files::create_folder(&dir).map(|path| {
+                self.sidebar.start_rename(path);
+            }),
+            SidebarAction::Rename(path) => {
+                self.sidebar.start_rename(path);
+                return;
+            }
+            SidebarAction::CommitRename => {
+                let Some((path, name)) = self.sidebar.renaming.take() else {
+                    return;
+                };
+                files::rename(&path, &name).map(|new_path| {
+                    // The buffer is untouched, so unsaved edits carry over
+                    if let Some(current) = &self.current_file {
+                        if let Some(moved) = files::renamed_path(current, &path, &new_path) {
+                            self.current_file = Some(moved);
+                        }
+                    }
+                })
+            }
+            SidebarAction::CancelRename => {
+                self.sidebar.renaming = None;
+                return;
+            }
+            SidebarAction::Delete(path) => {
+                self.sidebar.confirm_delete = Some(path);
+                return;
+            }
+            SidebarAction::ConfirmDelete => {
+                let Some(path) = self.sidebar.confirm_delete.take() else {
+                    return;
+                };
+                files::delete(&path).map(|()| {
+                    // Keep the open document's text, but it no longer has a file
+                    let open_deleted = self
+                        .current_file
+                        .as_deref()
+                        .is_some_and(|current| current.starts_with(&path));
+                    if open_deleted {
+                        self.current_file = None;
+                        self.has_unsaved_changes = true;
+                    }
+                })
+            }
+            SidebarAction::CancelDelete => {
+                self.sidebar.confirm_delete = None;
+                return;
+            }
+        };
+
+        self.sidebar.error = result.err().map(|e| e.to_string());
+        self.sidebar.refresh(root, &self.config.markdown_extensions);
+    }
+
+    /// Create a file in `dir` and open it, with its name ready to change.
+    /// Returns whether it was opened.
+    pub fn create_file_in(&mut self, dir: &Path) -> bool {
+        let result = files::create_file(dir).map(|path| {
+            let opened = self.open_path(&path);
+            self.sidebar.start_rename(path);
+            opened
+        });
+        if let Some(root) = self.workspace_root.clone() {
+            self.sidebar.refresh(&root, &self.config.markdown_extensions);
+        }
+        match result {
+            Ok(opened) => opened,
+            Err(e) => {
+                self.sidebar.error = Some(e.to_string());
+                false
+            }
+        }
+    }
+}
+
+fn show_nodes(ui: &mut egui::Ui, nodes: &[FileNode], tree: &mut TreeContext) {
+    for node in nodes {
+        let is_renaming = tree
+            .renaming
+            .as_ref()
+            .is_some_and(|(path, _)| *path == node.path);
+        if is_renaming {
+            show_rename_field(ui, tree);
+            continue;
+        }
+
+        match &node.children {
+            Some(children) => {
+                let expanded = tree.expanded.contains(&node.path);
+                let focus = std::mem::take(&mut tree.focus_first);
+                let response = egui::CollapsingHeader::new(&node.name)
+                    .id_salt(&node.path)
+                    .open(Some(expanded))
+                    .show(ui, |ui| show_nodes(ui, children, tree));
+                let header = &response.header_response;
+                if focus {
+                    header.request_focus();
+                }
+                // Right opens a focused folder and Left closes it, as in a tree view
+                if header.has_focus() {
+                    let filter = egui::EventFilter { horizontal_arrows: true, ..Default::default() };
+                    ui.memory_mut(|m| m.set_focus_lock_filter(header.id, filter));
+                }
+                let key = |key| header.has_focus() && ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, key));
+                let toggle = if expanded { key(egui::Key::ArrowLeft) } else { key(egui::Key::ArrowRight) };
+                if header.clicked() || toggle {
+                    if expanded {
+                        tree.expanded.remove(&node.path);
+                    } else {
+                        tree.expanded.insert(node.path.clone());
+                    }
+                }
+                response.header_response.context_menu(|ui| {
+                    folder_menu(ui, &node.path, tree.actions);
+                    ui.separator();
+                    item_menu(ui, &node.path, tree.actions);
+                });
+            }
+            None => {
+                let is_open = tree.current_file == Some(node.path.as_path());
+                let response = ui.selectable_label(is_open, &node.name);
+                if std::mem::take(&mut tree.focus_first) {
+                    response.request_focus();
+                }
+                if response.clicked() {
+                    tree.actions.push(SidebarAction::Open(node.path.clone()));
+                }
+                response.context_menu(|ui| {
+                    if let Some(dir) = node.path.parent() {
+                        folder_menu(ui, dir, tree.actions);
+                        ui.separator();
+                    }
+                    item_menu(ui, &node.path, tree.actions);
+                });
+            }
+        }
+    }
+}
+
+/// Inline name editor: Enter or clicking away commits, Escape cancels
+fn show_rename_field(ui: &mut egui::Ui, tree: &mut TreeContext) {
+    let Some((_, name)) = tree.renaming.as_mut() else {
+        return;
+    };
+    let response = ui.add(egui::TextEdit::singleline(name).desired_width(f32::INFINITY));
+    if std::mem::take(tree.focus_rename) {
+        response.request_focus();
+    }
+    if response.lost_focus() {
+        if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            tree.actions.push(SidebarAction::CancelRename);
+        } else {
+            tree.actions.push(SidebarAction::CommitRename);
+        }
+    }
+}
+
+fn folder_menu(ui: &mut egui::Ui, dir: &Path, actions: &mut Vec<SidebarAction>) {
+    if ui.button(t!("sidebar.new_file")).clicked() {
+        actions.push(SidebarAction::NewFile(dir.to_path_buf()));
+        ui.close_menu();
+    }
+    if ui.button(t!("sidebar.new_folder")).clicked() {
+        actions.push(SidebarAction::NewFolder(dir.to_path_buf()));
+        ui.close_menu();
+    }
+}
+
+fn item_menu(ui: &mut egui::Ui, path: &Path, actions: &mut Vec<SidebarAction>) {
+    if ui.button(t!("sidebar.rename")).clicked() {
+        actions.push(SidebarAction::Rename(path.to_path_buf()));
+        ui.close_menu();
+    }
+    if ui.button(t!("sidebar.delete")).clicked() {
+        actions.push(SidebarAction::Delete(path.to_path_buf()));
+        ui.close_menu();
+    }
+}
@@ -0,0 +1,293 @@
+//! Tools → Split Document by Headings and Merge Files. The dialogs list
+//! what will be written before anything is; the splitting and merging
+//! themselves are in `markdown::split`.
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::markdown::split::{self, Replace, Split};
+use crate::ui::toasts::Severity;
+use eframe::egui;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Choices for splitting the document, shown with the files they make
+pub struct SplitDialog {
+    level: u8,
+    replace: Replace,
+    folder: Option<PathBuf>,
+    /// Names in `folder`, lowercase, which the new files don't take
+    taken: HashSet<String>,
+}
+
+/// Files to merge into the document at the caret, in order
+pub struct MergeDialog {
+    files: Vec<PathBuf>,
+    shift: bool,
+    top_level: u8,
+}
+
+impl Replace {
+    fn label(self) -> &'static str {
+        match self {
+            Replace::Keep => t!("split.keep"),
+            Replace::Include => t!("split.include"),
+            Replace::Link => t!("split.link"),
+        }
+    }
+}
+
+/// Lowercase names of the entries of `folder`
+fn names_in(folder: &Path) -> HashSet<String> {
+    std::fs::read_dir(folder)
+        .map(|entries| entries.filter_map(Result::ok).map(|entry| entry.file_name().to_string_lossy().to_lowercase()).collect())
+        .unwrap_or_default()
+}
+
+/// How the document refers to a file in `folder`: relative to the
+/// document's own folder where it can
+fn reference_prefix(folder: &Path, doc_dir: Option<&Path>) -> String {
+    match doc_dir.and_then(|dir| folder.strip_prefix(dir).ok()) {
+        Some(relative) if relative.as_os_str().is_empty() => "./".to_string(),
+        Some(relative) => format!("./{}/", relative.to_string_lossy().replace('\\', "/")),
+        None => format!("{}/", folder.to_string_lossy().replace('\\', "/")),
+    }
+}
+
+impl RmdApp {
+    pub fn open_split_dialog(&mut self) {
+        let folder = self.current_file.as_deref().and_then(Path::parent).map(Path::to_path_buf);
+        let taken = folder.as_deref().map(names_in).unwrap_or_default();
+        // The level most sections are at below the title
+        let headings = self.editor.headings();
+        let level = (2..=6).find(|&level| headings.iter().any(|heading| heading.level == level)).unwrap_or(1);
+        self.split_dialog = Some(SplitDialog { level, replace: Replace::Keep, folder, taken });
+    }
+
+    pub fn open_merge_dialog(&mut self) {
+        if self.readonly {
+            self.show_readonly_hint();
+            return;
+        }
+        self.merge_dialog = Some(MergeDialog { files: Vec::new(), shift: false, top_level: 2 });
+    }
+
+    /// The split options with the files they would create, written once
+    /// confirmed
+    pub fn ui_split_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.split_dialog else {
+            return;
+        };
+        let text = self.editor.text();
+        let doc_dir = self.current_file.as_deref().and_then(Path::parent);
+        let prefix = dialog.folder.as_deref().map_or_else(|| "./".to_string(), |folder| reference_prefix(folder, doc_dir));
+        let plan = split::split_by_headings(&text, dialog.level, dialog.replace, &dialog.taken, &prefix);
+        let muted = self.theme.text_muted;
+
+        let mut open = true;
+        let mut confirm = false;
+        let mut cancel = false;
+        egui::Window::new(t!("split.split_title"))
+            .id(egui::Id::new("split_document"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(t!("split.level"));
+                    egui::ComboBox::from_id_salt("split_level")
+                        .selected_text(format!("H{}", dialog.level))
+                        .show_ui(ui, |ui| {
+                            for level in 1..=6 {
+                                ui.selectable_value(&mut dialog.level, level, format!("H{}", level));
+                            }
+                        });
+                });
+                ui.label(t!("split.in_document"));
+                for replace in Replace::ALL {
+                    ui.radio_value(&mut dialog.replace, replace, replace.label());
+                }
+                ui.horizontal(|ui| {
+                    ui.label(t!("split.folder"));
+                    match &dialog.folder {
+                        Some(folder) => ui.label(folder.display().to_string()),
+                        None => ui.weak(t!("split.no_folder")),
+                    };
+                    if ui.button(t!("export.choose")).clicked() {
+                        let mut pick = rfd::FileDialog::new().set_title(t!("split.folder"));
+                        if let Some(folder) = dialog.folder.as_deref().or(doc_dir) {
+                            pick = pick.set_directory(folder);
+                        }
+                        if let Some(folder) = pick.pick_folder() {
+                            dialog.taken = names_in(&folder);
+                            dialog.folder = Some(folder);
+                        }
+                    }
+                });
+                ui.separator();
+                ui.label(t!("split.files", count = plan.parts.len()));
+                egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                    if plan.parts.is_empty() {
+                        ui.label(egui::RichText::new(t!("split.no_sections")).color(muted));
+                    }
+                    for part in &plan.parts {
+                        ui.horizontal(|ui| {
+                            ui.monospace(&part.file_name);
+                            ui.label(egui::RichText::new(&part.title).color(muted));
+                        });
+                    }
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let ready = !plan.parts.is_empty() && dialog.folder.is_some();
+                    confirm = ui.add_enabled(ready, egui::Button::new(t!("split.split"))).clicked();
+                    cancel = ui.button(t!("dialog.cancel")).clicked();
+                });
+            });
+
+        if confirm {
+            if let Some(dialog) = self.split_dialog.take() {
+                self.write_split(&text, plan, dialog.folder.as_deref().unwrap_or(Path::new(".")));
+            }
+        } else if cancel || !open {
+            self.split_dialog = None;
+        }
+    }
+
+    /// Write the parts of `plan` to `folder`, then put the remainder in
+    /// place of the document as one undoable edit
+    fn write_split(&mut self, text: &str, plan: Split, folder: &Path) {
+        if let Err(e) = std::fs::create_dir_all(folder) {
+            self.notify_error(t!("error.create_failed", path = folder.display()), e);
+            return;
+        }
+        for part in &plan.parts {
+            let path = folder.join(&part.file_name);
+            if let Err(e) = std::fs::write(&path, split::part_text(text, part)) {
+                self.notify_error(t!("error.create_failed", path = path.display()), e);
+                return;
+            }
+        }
+        if let Some(remainder) = plan.remainder {
+            if self.readonly {
+                self.show_readonly_hint();
+            } else {
+                self.editor.replace_text(&remainder);
+                self.has_unsaved_changes = true;
+            }
+        }
+        self.notify(Severity::Success, t!("toast.split", count = plan.parts.len(), folder = folder.display()));
+    }
+
+    /// The files to merge, in an order that can be changed, merged into
+    /// the document at the caret once confirmed
+    pub fn ui_merge_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.merge_dialog else {
+            return;
+        };
+        let folder = self.current_file.as_deref().and_then(Path::parent).or(self.workspace_root.as_deref());
+        let extensions = &self.config.markdown_extensions;
+        let muted = self.theme.text_muted;
+
+        let mut open = true;
+        let mut confirm = false;
+        let mut cancel = false;
+        egui::Window::new(t!("split.merge_title"))
+            .id(egui::Id::new("merge_files"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                let mut swap = None;
+                let mut remove = None;
+                let count = dialog.files.len();
+                egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                    if dialog.files.is_empty() {
+                        ui.label(egui::RichText::new(t!("split.no_files")).color(muted));
+                    }
+                    for (index, path) in dialog.files.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(index > 0, egui::Button::new("⏶").small()).on_hover_text(t!("split.move_up")).clicked() {
+                                swap = Some(index - 1);
+                            }
+                            if ui.add_enabled(index + 1 < count, egui::Button::new("⏷").small()).on_hover_text(t!("split.move_down")).clicked() {
+                                swap = Some(index);
+                            }
+                            if ui.small_button("✖").on_hover_text(t!("split.remove")).clicked() {
+                                remove = Some(index);
+                            }
+                            let name = path.file_name().unwrap_or_default().to_string_lossy();
+                            ui.label(name.to_string()).on_hover_text(path.display().to_string());
+                        });
+                    }
+                });
+                if let Some(index) = swap {
+                    dialog.files.swap(index, index + 1);
+                }
+                if let Some(index) = remove {
+                    dialog.files.remove(index);
+                }
+                if ui.button(t!("split.add_files")).clicked() {
+                    let mut pick = rfd::FileDialog::new()
+                        .add_filter(t!("dialog.markdown_files"), extensions)
+                        .add_filter(t!("dialog.all_files"), &["*"]);
+                    if let Some(folder) = folder {
+                        pick = pick.set_directory(folder);
+                    }
+                    dialog.files.extend(pick.pick_files().unwrap_or_default());
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut dialog.shift, t!("split.shift_headings"));
+                    ui.add_enabled(dialog.shift, egui::DragValue::new(&mut dialog.top_level).range(1..=6).prefix("H"));
+                });
+                ui.separator();
+                ui.horizontal(|ui| {
+                    confirm = ui.add_enabled(!dialog.files.is_empty(), egui::Button::new(t!("split.merge"))).clicked();
+                    cancel = ui.button(t!("dialog.cancel")).clicked();
+                });
+            });
+
+        if confirm {
+            if let Some(dialog) = self.merge_dialog.take() {
+                self.merge_files(&dialog);
+            }
+        } else if cancel || !open {
+            self.merge_dialog = None;
+        }
+    }
+
+    /// Read the files of `dialog` and insert them merged at the caret, as
+    /// one undoable edit
+    fn merge_files(&mut self, dialog: &MergeDialog) {
+        let mut documents = Vec::with_capacity(dialog.files.len());
+        for path in &dialog.files {
+            match std::fs::read_to_string(path) {
+                Ok(text) => documents.push(text),
+                Err(e) => {
+                    self.notify_error(t!("error.open_failed", path = path.display()), e);
+                    return;
+                }
+            }
+        }
+        let documents: Vec<&str> = documents.iter().map(String::as_str).collect();
+        let merged = split::merge(&documents, dialog.shift.then_some(dialog.top_level));
+        self.editor.insert_text(&merged);
+        self.has_unsaved_changes = true;
+        self.notify(Severity::Success, t!("toast.merged", count = documents.len()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reference_prefix() {
+        let doc_dir = Path::new("/notes");
+        assert_eq!(reference_prefix(Path::new("/notes"), Some(doc_dir)), "./");
+        assert_eq!(reference_prefix(Path::new("/notes/parts"), Some(doc_dir)), "./parts/");
+        assert_eq!(reference_prefix(Path::new("/elsewhere"), Some(doc_dir)), "/elsewhere/");
+    }
+}
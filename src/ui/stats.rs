@@ -0,0 +1,184 @@
+//! Document and writing statistics in the status bar, with the word count
+//! breakdown and the 14-day chart behind them
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::stats::document::DocumentStats;
+use crate::utils;
+use eframe::egui;
+
+/// Days shown in the chart
+const CHART_DAYS: u64 = 14;
+
+const BAR_WIDTH: f32 = 16.0;
+const BAR_GAP: f32 = 4.0;
+const CHART_HEIGHT: f32 = 100.0;
+
+impl RmdApp {
+    /// Count an edit the user typed, if statistics are enabled
+    pub fn record_typing(&mut self, old_text: &str, new_text: &str) {
+        if !self.config.stats.enabled {
+            return;
+        }
+        let typed = new_text.chars().count().saturating_sub(old_text.chars().count());
+        let word_delta = utils::count_words(new_text) as i64 - utils::count_words(old_text) as i64;
+        self.writing_stats.record_edit(typed, word_delta);
+    }
+
+    /// Word counts of the document, recounted when the text changes
+    fn document_stats(&mut self) -> DocumentStats {
+        let revision = self.editor.revision();
+        match self.document_stats {
+            Some((counted, stats)) if counted == revision => stats,
+            _ => {
+                let text = self.editor.text();
+                let stats = DocumentStats::new(&text, self.markdown_renderer.flavor_for(&text));
+                self.document_stats = Some((revision, stats));
+                stats
+            }
+        }
+    }
+
+    /// Prose words and characters, or only characters in a CJK document,
+    /// with a popup breaking the words down
+    pub fn ui_document_stats(&mut self, ui: &mut egui::Ui) {
+        let stats = self.document_stats();
+        let label = if self.is_cjk_document() {
+            t!("stats.characters", chars = stats.prose_characters)
+        } else {
+            t!("stats.words_chars", words = stats.prose_words, chars = stats.characters)
+        };
+        let response = ui
+            .add(egui::Label::new(label).sense(egui::Sense::click()))
+            .on_hover_text(t!("stats.words_hint"));
+
+        let popup_id = ui.make_persistent_id("document_stats_popup");
+        if response.clicked() {
+            ui.memory_mut(|m| m.toggle_popup(popup_id));
+        }
+        egui::popup::popup_above_or_below_widget(
+            ui,
+            popup_id,
+            &response,
+            egui::AboveOrBelow::Above,
+            egui::PopupCloseBehavior::CloseOnClickOutside,
+            |ui| {
+                ui.set_min_width(200.0);
+                egui::Grid::new("document_stats").num_columns(2).spacing([16.0, 2.0]).show(ui, |ui| {
+                    let rows = [
+                        (t!("stats.prose"), stats.prose_words),
+                        (t!("stats.code"), stats.code_words),
+                        (t!("stats.front_matter"), stats.front_matter_words),
+                        (t!("stats.markup"), stats.markup_words()),
+                        (t!("stats.total"), stats.total_words),
+                    ];
+                    for (label, words) in rows {
+                        ui.label(label);
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            ui.label(words.to_string());
+                        });
+                        ui.end_row();
+                    }
+                });
+                ui.separator();
+                ui.label(
+                    egui::RichText::new(t!(
+                        "stats.reading_time",
+                        chars = stats.characters,
+                        minutes = utils::estimate_reading_time(stats.prose_words)
+                    ))
+                    .color(self.theme.text_muted),
+                );
+            },
+        );
+    }
+
+    /// Today's words and active time, with a popup chart of recent days
+    pub fn ui_writing_stats(&mut self, ui: &mut egui::Ui) {
+        let today = self.writing_stats.today();
+        let response = ui
+            .add(
+                egui::Label::new(t!(
+                    "stats.today",
+                    words = format!("{:+}", today.word_delta),
+                    time = utils::format_duration(today.active_time())
+                ))
+                .sense(egui::Sense::click()),
+            )
+            .on_hover_text(t!("stats.today_hint"));
+
+        let goal = self.config.stats.daily_word_goal;
+        if goal > 0 {
+            let progress = (today.word_delta.max(0) as f32 / goal as f32).min(1.0);
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(40.0, 4.0), egui::Sense::hover());
+            let color = if progress >= 1.0 { self.theme.success } else { self.theme.accent };
+            ui.painter().rect_filled(rect, 2.0, self.theme.border);
+            let filled = egui::Rect::from_min_size(rect.min, egui::vec2(rect.width() * progress, rect.height()));
+            ui.painter().rect_filled(filled, 2.0, color);
+        }
+
+        let popup_id = ui.make_persistent_id("writing_stats_popup");
+        if response.clicked() {
+            ui.memory_mut(|m| m.toggle_popup(popup_id));
+        }
+        egui::popup::popup_above_or_below_widget(
+            ui,
+            popup_id,
+            &response,
+            egui::AboveOrBelow::Above,
+            egui::PopupCloseBehavior::CloseOnClickOutside,
+            |ui| self.ui_stats_chart(ui),
+        );
+    }
+
+    /// Bar chart of words added over the last two weeks
+    fn ui_stats_chart(&self, ui: &mut egui::Ui) {
+        let days = self.writing_stats.recent_days(CHART_DAYS);
+        let goal = self.config.stats.daily_word_goal as i64;
+        let max = days.iter().map(|d| d.word_delta).chain([goal, 1]).max().unwrap_or(1) as f32;
+
+        ui.label(egui::RichText::new(t!("stats.chart_title", days = CHART_DAYS)).strong());
+        let width = CHART_DAYS as f32 * (BAR_WIDTH + BAR_GAP);
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(width, CHART_HEIGHT + 14.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let baseline = rect.top() + CHART_HEIGHT;
+        let font = egui::FontId::proportional(10.0);
+
+        for (i, day) in days.iter().enumerate() {
+            let left = rect.left() + i as f32 * (BAR_WIDTH + BAR_GAP);
+            let height = (day.word_delta.max(0) as f32 / max * CHART_HEIGHT).max(1.0);
+            let bar = egui::Rect::from_min_max(
+                egui::pos2(left, baseline - height),
+                egui::pos2(left + BAR_WIDTH, baseline),
+            );
+            let color = if goal > 0 && day.word_delta >= goal { self.theme.success } else { self.theme.accent };
+            painter.rect_filled(bar, 2.0, color);
+            painter.text(
+                egui::pos2(bar.center().x, baseline + 2.0),
+                egui::Align2::CENTER_TOP,
+                weekday_letter(day.day),
+                font.clone(),
+                self.theme.text_muted,
+            );
+
+            let column = egui::Rect::from_min_max(egui::pos2(left, rect.top()), egui::pos2(left + BAR_WIDTH, baseline));
+            ui.interact(column, ui.id().with(("stats_day", i)), egui::Sense::hover())
+                .on_hover_text(format!(
+                    "{:+} words, {} chars typed, {} active",
+                    day.word_delta,
+                    day.chars_typed,
+                    utils::format_duration(day.active_time())
+                ));
+        }
+
+        if goal > 0 {
+            let y = baseline - goal as f32 / max * CHART_HEIGHT;
+            painter.hline(rect.x_range(), y, egui::Stroke::new(1.0, self.theme.text_muted));
+        }
+    }
+}
+
+/// Initial of the weekday of a day counted from the Unix epoch, a Thursday
+fn weekday_letter(day: u64) -> &'static str {
+    ["T", "F", "S", "S", "M", "T", "W"][(day % 7) as usize]
+}
@@ -0,0 +1,219 @@
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::ui::widgets::ClickAway;
+use crate::utils;
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+/// Rows shown at most; the rest are reached by typing more
+const MAX_ROWS: usize = 200;
+
+/// Score bonus for recently opened files
+const RECENT_BONUS: i32 = 8;
+
+/// Extra weight for matches in the file name over the folder
+const NAME_BONUS: i32 = 4;
+
+/// State of the Ctrl+P quick file switcher
+#[derive(Default)]
+pub struct QuickSwitcher {
+    open: bool,
+    click_away: ClickAway,
+    query: String,
+    selected: usize,
+    /// Ranked files for the query they were computed for
+    matches: Option<(String, Vec<PathBuf>)>,
+}
+
+impl QuickSwitcher {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.click_away = ClickAway::opened();
+        self.query.clear();
+        self.selected = 0;
+        self.matches = None;
+    }
+}
+
+/// Files matching `query`: recent files first in the order they were used,
+/// then workspace files, each fuzzily matched on the file name and its
+/// folder. `current` is left out, since switching to it does nothing.
+pub fn rank_files(
+    query: &str,
+    recent: &[PathBuf],
+    files: &[PathBuf],
+    root: Option<&Path>,
+    current: Option<&Path>,
+) -> Vec<PathBuf> {
+    let recent_files = recent.iter().filter(|path| path.is_absolute());
+    let mut seen = std::collections::HashSet::new();
+    let candidates = recent_files
+        .enumerate()
+        .map(|(rank, path)| (path, RECENT_BONUS + (recent.len() - rank) as i32))
+        .chain(files.iter().map(|path| (path, 0)))
+        .filter(|(path, _)| Some(path.as_path()) != current && seen.insert(path.as_path()));
+
+    let mut matches: Vec<(i32, &PathBuf)> = candidates
+        .filter_map(|(path, bonus)| {
+            if query.trim().is_empty() {
+                return Some((bonus, path));
+            }
+            let name = path.file_name()?.to_string_lossy();
+            let display = display_path(path, root);
+            let score = utils::fuzzy_score(&name, query)
+                .map(|score| score + NAME_BONUS)
+                .max(utils::fuzzy_score(&display, query))?;
+            Some((score + bonus, path))
+        })
+        .collect();
+    // Stable sort keeps recent order, then tree order, among equal scores
+    matches.sort_by_key(|(score, _)| -score);
+    matches.into_iter().take(MAX_ROWS).map(|(_, path)| path.clone()).collect()
+}
+
+/// Path relative to the workspace when inside it, with `/` separators
+fn display_path(path: &Path, root: Option<&Path>) -> String {
+    let relative = root.and_then(|root| path.strip_prefix(root).ok()).unwrap_or(path);
+    relative.to_string_lossy().replace('\\', "/")
+}
+
+impl RmdApp {
+    /// Render the quick switcher and open the chosen file
+    pub fn ui_quick_switcher(&mut self, ctx: &egui::Context) {
+        if !self.switcher.open {
+            return;
+        }
+
+        let stale = self.switcher.matches.as_ref().is_none_or(|(query, _)| *query != self.switcher.query);
+        if stale {
+            let files = if self.workspace_root.is_some() { self.sidebar.files() } else { &[] };
+            let ranked = rank_files(
+                &self.switcher.query,
+                &self.config.recent_files,
+                files,
+                self.workspace_root.as_deref(),
+                self.current_file.as_deref(),
+            );
+            self.switcher.matches = Some((self.switcher.query.clone(), ranked));
+        }
+        let rows = self.switcher.matches.as_ref().map(|(_, rows)| rows.clone()).unwrap_or_default();
+
+        let (up, down, enter, ctrl_enter, escape) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::COMMAND, egui::Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        let switcher = &mut self.switcher;
+        if up {
+            switcher.selected = switcher.selected.saturating_sub(1);
+        }
+        if down && switcher.selected + 1 < rows.len() {
+            switcher.selected += 1;
+        }
+        switcher.selected = switcher.selected.min(rows.len().saturating_sub(1));
+
+        let mut chosen = (enter || ctrl_enter).then_some(switcher.selected);
+        let muted = self.theme.text_muted;
+        let root = self.workspace_root.as_deref();
+        let area = egui::Area::new(egui::Id::new("quick_switcher"))
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 80.0))
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_width(480.0);
+                    let input = ui.add(
+                        egui::TextEdit::singleline(&mut switcher.query)
+                            .hint_text(t!("picker.go_to_file"))
+                            .desired_width(f32::INFINITY),
+                    );
+                    input.request_focus();
+                    if input.changed() {
+                        switcher.selected = 0;
+                    }
+
+                    ui.separator();
+                    egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                        if rows.is_empty() {
+                            let hint = if root.is_some() { t!("picker.no_matching_files") } else { t!("picker.no_recent_files") };
+                            ui.label(egui::RichText::new(hint).color(muted));
+                        }
+                        for (index, path) in rows.iter().enumerate() {
+                            let selected = index == switcher.selected;
+                            let name = path.file_name().unwrap_or_default().to_string_lossy();
+                            let folder = path.parent().map(|parent| display_path(parent, root)).unwrap_or_default();
+                            let response = ui
+                                .horizontal(|ui| {
+                                    let response = ui.selectable_label(selected, name);
+                                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                        ui.label(egui::RichText::new(folder).small().color(muted));
+                                    });
+                                    response
+                                })
+                                .inner;
+                            if selected && (up || down) {
+                                response.scroll_to_me(None);
+                            }
+                            if response.clicked() {
+                                chosen = Some(index);
+                            }
+                        }
+                    });
+                });
+            });
+
+        let dismissed = self.switcher.click_away.clicked(&area.response) || escape;
+        let path = chosen.and_then(|index| rows.get(index));
+        if path.is_none() && !dismissed {
+            return;
+        }
+
+        self.switcher.open = false;
+        ctx.memory_mut(|m| m.request_focus(self.editor.widget_id()));
+        match path {
+            Some(path) if ctrl_enter => {
+                if let Err(e) = open_in_new_window(path) {
+                    self.notify_error(t!("error.new_window"), e);
+                }
+            }
+            Some(path) => {
+                self.open_path(path);
+            }
+            None => {}
+        }
+    }
+}
+
+/// Start another RMD window showing `path`
+fn open_in_new_window(path: &Path) -> std::io::Result<()> {
+    let exe = std::env::current_exe()?;
+    std::process::Command::new(exe).arg(path).spawn()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rank_files() {
+        let root = std::env::temp_dir().join("notes");
+        let files: Vec<PathBuf> = ["a/todo.md", "journal/2024.md", "projects/rmd/todo.md", "readme.md"]
+            .iter()
+            .map(|path| root.join(path))
+            .collect();
+        let recent = vec![files[3].clone(), files[1].clone()];
+
+        // Recent files first, most recent first, without the current file
+        let all = rank_files("", &recent, &files, Some(&root), Some(&files[1]));
+        assert_eq!(all, vec![files[3].clone(), files[0].clone(), files[2].clone()]);
+
+        // The folder counts too
+        assert_eq!(rank_files("rmdtodo", &recent, &files, Some(&root), None), vec![files[2].clone()]);
+        assert_eq!(rank_files("todo", &recent, &files, Some(&root), None).len(), 2);
+        assert!(rank_files("xyz", &recent, &files, Some(&root), None).is_empty());
+    }
+}
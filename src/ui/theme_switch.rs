@@ -0,0 +1,118 @@
+//! Following the system light/dark setting, and Toggle Theme
+
+use crate::app::RmdApp;
+use crate::config::ThemeMode;
+use crate::theme::Theme;
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+/// How long the colors take to blend into the new theme
+const FADE_DURATION: Duration = Duration::from_millis(200);
+
+/// A switch between themes in progress
+pub struct ThemeFade {
+    from: Theme,
+    to: Theme,
+    started: Instant,
+}
+
+impl ThemeFade {
+    /// The blended theme `elapsed` into the fade, or `None` once it is over
+    fn at(&self, elapsed: Duration) -> Option<Theme> {
+        let t = elapsed.as_secs_f32() / FADE_DURATION.as_secs_f32();
+        (t < 1.0).then(|| self.from.lerp(&self.to, t))
+    }
+}
+
+impl RmdApp {
+    /// The mode in effect: Toggle Theme's choice this session, else the config's
+    fn theme_mode(&self) -> ThemeMode {
+        self.theme_override.unwrap_or(self.config.theme_mode)
+    }
+
+    /// Switch to light or dark when the mode in effect calls for it, say
+    /// when the system flips under `ThemeMode::System`, and advance a fade
+    /// in progress. Called every frame.
+    pub fn update_theme(&mut self, ctx: &egui::Context) {
+        let target = Theme::from_mode(self.theme_mode(), ctx.system_theme());
+        let heading_dark = self.theme_fade.as_ref().map_or(self.theme.is_dark(), |fade| fade.to.is_dark());
+        if target.is_dark() != heading_dark {
+            if ctx.cumulative_pass_nr() == 0 {
+                // The system setting wasn't known at startup; no fade on the first frame
+                self.set_theme(ctx, target);
+                return;
+            }
+            log::debug!("Switching to the {} theme", if target.is_dark() { "dark" } else { "light" });
+            self.theme_fade = Some(ThemeFade {
+                from: self.theme.clone(),
+                to: target,
+                started: Instant::now(),
+            });
+        }
+
+        let Some(fade) = &self.theme_fade else {
+            return;
+        };
+        let theme = match fade.at(fade.started.elapsed()) {
+            Some(blended) => {
+                ctx.request_repaint();
+                blended
+            }
+            None => {
+                let theme = fade.to.clone();
+                self.theme_fade = None;
+                theme
+            }
+        };
+        self.set_theme(ctx, theme);
+    }
+
+    /// Switch between light and dark for the rest of the session,
+    /// overriding the configured mode
+    pub fn toggle_theme(&mut self) {
+        let dark = match &self.theme_fade {
+            Some(fade) => fade.to.is_dark(),
+            None => self.theme.is_dark(),
+        };
+        self.theme_override = Some(if dark { ThemeMode::Light } else { ThemeMode::Dark });
+    }
+
+    /// Switch to the theme of the mode in effect at once, without fading,
+    /// for settings that were just loaded
+    pub(crate) fn apply_theme_mode(&mut self, ctx: &egui::Context) {
+        let theme = Theme::from_mode(self.theme_mode(), ctx.system_theme());
+        self.theme_fade = None;
+        self.set_theme(ctx, theme);
+    }
+
+    /// Use `theme` in the UI, the editor highlighting, and the preview.
+    /// The editor's layouts are cached by their colors, so highlighted
+    /// text is laid out again in the new ones.
+    fn set_theme(&mut self, ctx: &egui::Context, theme: Theme) {
+        let theme = theme.with_chrome(self.theme_chrome.clone());
+        theme.apply(ctx);
+        self.markdown_renderer.set_theme(theme.clone());
+        self.highlighter.set_theme(theme.clone());
+        self.theme = theme;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fade() {
+        let fade = ThemeFade {
+            from: Theme::dark(),
+            to: Theme::light(),
+            started: Instant::now(),
+        };
+        assert_eq!(fade.at(Duration::ZERO).unwrap().background, Theme::dark().background);
+        let halfway = fade.at(FADE_DURATION / 2).unwrap().background;
+        assert_ne!(halfway, Theme::dark().background);
+        assert_ne!(halfway, Theme::light().background);
+        assert!(fade.at(FADE_DURATION).is_none());
+        assert!(Theme::dark().is_dark() && !Theme::light().is_dark());
+    }
+}
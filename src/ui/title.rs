@@ -0,0 +1,52 @@
+use crate::app::RmdApp;
+use eframe::egui;
+use std::path::Path;
+
+/// Longest file name shown in the title bar before it is shortened
+const MAX_TITLE_NAME: usize = 60;
+
+/// Window title for a document: `• notes.md — RMD` while unsaved
+pub fn window_title(path: Option<&Path>, dirty: bool) -> String {
+    let name = path
+        .and_then(|path| path.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Untitled".to_string());
+    let name = if name.chars().count() > MAX_TITLE_NAME {
+        let head: String = name.chars().take(MAX_TITLE_NAME - 1).collect();
+        format!("{}…", head)
+    } else {
+        name
+    };
+    let marker = if dirty { "• " } else { "" };
+    format!("{}{} — RMD", marker, name)
+}
+
+impl RmdApp {
+    /// Keep the window title in step with the document name and dirty state
+    pub fn update_window_title(&mut self, ctx: &egui::Context) {
+        let title = window_title(self.current_file.as_deref(), self.has_unsaved_changes);
+        if title != self.window_title {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.clone()));
+            self.window_title = title;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_window_title() {
+        assert_eq!(window_title(None, false), "Untitled — RMD");
+        let path = PathBuf::from("/home/me/notes/todo.md");
+        assert_eq!(window_title(Some(&path), false), "todo.md — RMD");
+        assert_eq!(window_title(Some(&path), true), "• todo.md — RMD");
+
+        let long = PathBuf::from(format!("/deep/{}.md", "é".repeat(100)));
+        let title = window_title(Some(&long), false);
+        assert_eq!(title.chars().count(), MAX_TITLE_NAME + " — RMD".chars().count());
+        assert!(title.starts_with('é') && title.contains("…"));
+    }
+}
@@ -0,0 +1,265 @@
+//! Notifications stacked in the bottom-right corner
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::ui::focus::Pane;
+use crate::ui::widgets::name_icon_button;
+use eframe::egui;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// How long a notification other than an error stays up
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+/// Most notifications shown at once; older ones give way
+const MAX_TOASTS: usize = 5;
+
+const TOAST_WIDTH: f32 = 320.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn icon(self) -> &'static str {
+        match self {
+            Severity::Info => "ℹ",
+            Severity::Success => "✔",
+            Severity::Warning => "⚠",
+            Severity::Error => "✖",
+        }
+    }
+
+    /// What the icon stands for, for screen readers
+    fn name(self) -> &'static str {
+        match self {
+            Severity::Info => "Information",
+            Severity::Success => "Success",
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
+        }
+    }
+}
+
+/// What a button on a notification does
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToastAction {
+    /// Scroll the preview this fraction of the way down
+    ResumeReading(f32),
+    /// Open a file that isn't UTF-8 with the bad bytes replaced
+    OpenLossy(PathBuf),
+    /// Lock the document that couldn't be saved to this file
+    LockDocument(PathBuf),
+}
+
+impl ToastAction {
+    fn label(&self) -> &'static str {
+        match self {
+            ToastAction::ResumeReading(_) => t!("toast.resume"),
+            ToastAction::OpenLossy(_) => t!("toast.open_lossy"),
+            ToastAction::LockDocument(_) => t!("toast.lock_document"),
+        }
+    }
+}
+
+pub struct Toast {
+    id: u64,
+    pub severity: Severity,
+    pub message: String,
+    /// The underlying error, shown under a "Details" expander
+    pub details: Option<String>,
+    /// Button offered beside the message
+    pub action: Option<ToastAction>,
+    shown_at: Instant,
+}
+
+impl Toast {
+    /// Errors stay until dismissed
+    fn expired(&self, now: Instant) -> bool {
+        self.severity != Severity::Error && now.duration_since(self.shown_at) >= TOAST_DURATION
+    }
+}
+
+/// Notifications waiting to be read, oldest first
+#[derive(Default)]
+pub struct Toasts {
+    toasts: Vec<Toast>,
+    next_id: u64,
+}
+
+impl Toasts {
+    pub fn push(&mut self, severity: Severity, message: impl Into<String>, details: Option<String>) {
+        self.push_at(severity, message.into(), details, Instant::now());
+    }
+
+    /// A notification with a button that does `action`, if there is one
+    pub fn push_action(&mut self, severity: Severity, message: impl Into<String>, details: Option<String>, action: Option<ToastAction>) {
+        self.push_at(severity, message.into(), details, Instant::now());
+        if let Some(toast) = self.toasts.last_mut() {
+            toast.action = action;
+        }
+    }
+
+    fn push_at(&mut self, severity: Severity, message: String, details: Option<String>, now: Instant) {
+        // The same message again, say a save retried, refreshes the one shown
+        self.toasts.retain(|t| !(t.severity == severity && t.message == message && t.details == details));
+        self.toasts.push(Toast { id: self.next_id, severity, message, details, action: None, shown_at: now });
+        self.next_id += 1;
+        if self.toasts.len() > MAX_TOASTS {
+            // Drop the oldest, errors last
+            let oldest = self
+                .toasts
+                .iter()
+                .position(|t| t.severity != Severity::Error)
+                .unwrap_or(0);
+            self.toasts.remove(oldest);
+        }
+    }
+
+    pub fn dismiss(&mut self, id: u64) {
+        self.toasts.retain(|t| t.id != id);
+    }
+
+    /// Drop notifications that have been up long enough
+    fn expire(&mut self, now: Instant) {
+        self.toasts.retain(|t| !t.expired(now));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.toasts.is_empty()
+    }
+}
+
+impl RmdApp {
+    pub fn notify(&mut self, severity: Severity, message: impl Into<String>) {
+        self.toasts.push(severity, message, None);
+    }
+
+    /// Tell the user that `context` failed, with `err` under "Details"
+    pub fn notify_error(&mut self, context: impl Into<String>, err: impl Display) {
+        let context = context.into();
+        log::error!("{}: {}", context, err);
+        self.toasts.push(Severity::Error, context, Some(err.to_string()));
+    }
+
+    fn run_toast_action(&mut self, action: ToastAction) {
+        match action {
+            ToastAction::ResumeReading(fraction) => self.preview.scroll_to_fraction(fraction),
+            ToastAction::OpenLossy(path) => self.open_lossy(&path),
+            ToastAction::LockDocument(path) => self.lock_document(&path),
+        }
+    }
+
+    /// Render the notifications, newest at the bottom
+    pub fn ui_toasts(&mut self, ctx: &egui::Context) {
+        if let Some(error) = self.config_manager.take_error() {
+            self.notify_error(t!("error.save_settings"), error);
+        }
+        self.toasts.expire(Instant::now());
+        if self.toasts.is_empty() {
+            return;
+        }
+
+        let mut dismissed = None;
+        let mut chosen = None;
+        // Ctrl+6 focuses the newest notification's dismiss button
+        let focus = self.pane_focus.take(Pane::Notifications);
+        let newest = self.toasts.toasts.last().map(|toast| toast.id);
+        let area = egui::Area::new(egui::Id::new("toasts"))
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-16.0, -36.0))
+            .show(ctx, |ui| {
+                ui.set_width(TOAST_WIDTH);
+                for toast in &self.toasts.toasts {
+                    let color = match toast.severity {
+                        Severity::Info => self.theme.text_muted,
+                        Severity::Success => self.theme.success,
+                        Severity::Warning => self.theme.warning,
+                        Severity::Error => self.theme.error,
+                    };
+                    egui::Frame::popup(ui.style()).stroke(egui::Stroke::new(1.0, color)).show(ui, |ui| {
+                        ui.horizontal_top(|ui| {
+                            let severity = toast.severity;
+                            ui.colored_label(color, severity.icon())
+                                .widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Label, true, severity.name()));
+                            ui.vertical(|ui| {
+                                ui.add(egui::Label::new(&toast.message).wrap());
+                                if let Some(action) = &toast.action {
+                                    if ui.button(action.label()).clicked() {
+                                        chosen = Some(action.clone());
+                                        dismissed = Some(toast.id);
+                                    }
+                                }
+                                if let Some(details) = &toast.details {
+                                    egui::CollapsingHeader::new(t!("toast.details")).id_salt(("toast", toast.id)).show(ui, |ui| {
+                                        ui.add(
+                                            egui::Label::new(egui::RichText::new(details).monospace().small()).wrap(),
+                                        );
+                                    });
+                                }
+                            });
+                            ui.with_layout(egui::Layout::right_to_left(egui::Align::TOP), |ui| {
+                                let dismiss = name_icon_button(ui.small_button("✕"), t!("toast.dismiss"));
+                                if focus && newest == Some(toast.id) {
+                                    dismiss.request_focus();
+                                }
+                                if dismiss.clicked() {
+                                    dismissed = Some(toast.id);
+                                }
+                            });
+                        });
+                    });
+                    ui.add_space(4.0);
+                }
+            });
+        self.pane_focus.note(ctx, Pane::Notifications, area.response.rect);
+        if let Some(id) = dismissed {
+            self.toasts.dismiss(id);
+        }
+        if let Some(action) = chosen {
+            self.run_toast_action(action);
+        }
+        // Wake up to take expired notifications down
+        ctx.request_repaint_after(Duration::from_millis(250));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_stay_until_dismissed() {
+        let start = Instant::now();
+        let mut toasts = Toasts::default();
+        toasts.push_at(Severity::Success, "Saved".into(), None, start);
+        toasts.push_at(Severity::Error, "Failed to open a.md".into(), Some("not found".into()), start);
+        toasts.expire(start + TOAST_DURATION);
+        assert_eq!(toasts.toasts.len(), 1);
+        assert_eq!(toasts.toasts[0].severity, Severity::Error);
+        toasts.dismiss(toasts.toasts[0].id);
+        assert!(toasts.is_empty());
+    }
+
+    #[test]
+    fn test_repeats_and_overflow() {
+        let start = Instant::now();
+        let mut toasts = Toasts::default();
+        toasts.push_at(Severity::Error, "Failed to save".into(), Some("disk full".into()), start);
+        toasts.push_at(Severity::Error, "Failed to save".into(), Some("disk full".into()), start);
+        assert_eq!(toasts.toasts.len(), 1);
+
+        for n in 0..MAX_TOASTS {
+            toasts.push_at(Severity::Info, format!("Note {}", n), None, start);
+        }
+        // The oldest info note gave way; the error is kept
+        assert_eq!(toasts.toasts.len(), MAX_TOASTS);
+        assert_eq!(toasts.toasts[0].severity, Severity::Error);
+        assert_eq!(toasts.toasts[1].message, "Note 1");
+    }
+}
@@ -0,0 +1,184 @@
+//! Typing aids in the editor. A substitution made while typing is
+//! underlined for a few seconds; clicking the underline offers to put back
+//! what was typed, and to turn off the rule that changed it.
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::editor::typing_aids::{self, Corrections, Rule, TypingAids};
+use crate::ui::widgets::ClickAway;
+use crate::utils;
+use eframe::egui;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How long a substitution stays underlined
+const UNDERLINE_DURATION: Duration = Duration::from_secs(4);
+
+/// The last substitution made, while it is underlined
+struct RecentSubstitution {
+    /// Where the replacement is in the text
+    range: Range<usize>,
+    replacement: String,
+    /// What was typed
+    original: String,
+    rule: Rule,
+    made: Instant,
+}
+
+/// The replacement table, and the substitution just made
+#[derive(Default)]
+pub struct TypingAidsState {
+    pub corrections: Corrections,
+    recent: Option<RecentSubstitution>,
+    /// Where the popup for the recent substitution is, while it is open
+    popup: Option<egui::Pos2>,
+    click_away: ClickAway,
+}
+
+impl TypingAidsState {
+    pub fn load() -> Self {
+        Self { corrections: Corrections::load_or_default(), ..Default::default() }
+    }
+}
+
+impl RmdApp {
+    fn typing_aids(&self) -> TypingAids {
+        let writing = &self.config.writing;
+        TypingAids { capitalize: writing.auto_capitalize, correct: writing.auto_correct, dashes: writing.auto_dashes }
+    }
+
+    /// After the user typed `old_text` into `new_text`, make the substitution
+    /// the aids that are on call for, if any, as an edit of its own. Returns
+    /// whether one was made.
+    pub(crate) fn apply_typing_aids(&mut self, old_text: &str, new_text: &str) -> bool {
+        let aids = self.typing_aids();
+        if !(aids.capitalize || aids.correct || aids.dashes) || self.editor.selection().is_some() {
+            return false;
+        }
+        let caret = self.editor.cursor();
+        let Some(typed) = typing_aids::typed_char(old_text, new_text, caret) else {
+            return false;
+        };
+        if aids.correct {
+            self.typing_aids.corrections.reload_if_changed();
+        }
+        let Some(substitution) = typing_aids::substitution(new_text, caret, typed, aids, &self.typing_aids.corrections) else {
+            return false;
+        };
+        let original = new_text[substitution.range.clone()].to_string();
+        self.editor.replace_range_in_place(substitution.range.clone(), &substitution.replacement);
+        let start = substitution.range.start;
+        self.typing_aids.recent = Some(RecentSubstitution {
+            range: start..start + substitution.replacement.len(),
+            replacement: substitution.replacement,
+            original,
+            rule: substitution.rule,
+            made: Instant::now(),
+        });
+        self.typing_aids.popup = None;
+        true
+    }
+
+    /// Underline the substitution just made, until it times out or the text
+    /// around it changes. Clicking it opens the popup.
+    pub fn paint_recent_substitution(&mut self, ui: &egui::Ui, galley: &Arc<egui::Galley>, galley_pos: egui::Pos2) {
+        let state = &mut self.typing_aids;
+        let Some(recent) = &state.recent else {
+            return;
+        };
+        let text = &galley.job.text;
+        let elapsed = recent.made.elapsed();
+        let expired = elapsed >= UNDERLINE_DURATION && state.popup.is_none();
+        if expired || text.get(recent.range.clone()) != Some(recent.replacement.as_str()) {
+            state.recent = None;
+            state.popup = None;
+            return;
+        }
+        ui.ctx().request_repaint_after(UNDERLINE_DURATION.saturating_sub(elapsed));
+
+        let pos = |byte: usize| {
+            let cursor = egui::text::CCursor::new(utils::byte_to_char(text, byte));
+            galley.pos_from_ccursor(cursor).translate(galley_pos.to_vec2())
+        };
+        let (start, end) = (pos(recent.range.start), pos(recent.range.end));
+        // A replacement wrapped onto the next row is underlined on its first
+        let right = if end.top() == start.top() { end.right() } else { start.right() + 4.0 };
+        let rect = egui::Rect::from_min_max(start.left_top(), egui::pos2(right.max(start.left() + 4.0), start.bottom()));
+        let stroke = egui::Stroke::new(1.0, self.theme.accent.gamma_multiply(0.6));
+        ui.painter().hline(rect.x_range(), rect.bottom() - 1.0, stroke);
+        let response = ui
+            .interact(rect, ui.id().with("recent_substitution"), egui::Sense::click())
+            .on_hover_cursor(egui::CursorIcon::PointingHand)
+            .on_hover_text(t!("typing_aids.changed_from", text = recent.original));
+        if response.clicked() {
+            state.popup = Some(rect.left_bottom() + egui::vec2(0.0, 4.0));
+            state.click_away = ClickAway::opened();
+        }
+    }
+
+    /// Choices for the substitution whose underline was clicked
+    pub fn ui_substitution_popup(&mut self, ctx: &egui::Context) {
+        let state = &mut self.typing_aids;
+        let (Some(pos), Some(recent)) = (state.popup, &state.recent) else {
+            return;
+        };
+        let mut click_away = std::mem::take(&mut state.click_away);
+        let muted = self.theme.text_muted;
+
+        let mut put_back = false;
+        let mut turn_off = false;
+        let area = egui::Area::new(egui::Id::new("substitution_popup"))
+            .order(egui::Order::Foreground)
+            .fixed_pos(pos)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    let changed = t!("typing_aids.changed", from = recent.original, to = recent.replacement);
+                    ui.label(egui::RichText::new(changed).color(muted));
+                    put_back = ui.button(t!("typing_aids.put_back", text = recent.original)).clicked();
+                    let off = match &recent.rule {
+                        Rule::Capitalize => t!("typing_aids.stop_capitalizing").to_string(),
+                        Rule::Correction(from) => t!("typing_aids.stop_correcting", text = from),
+                        Rule::Dash => t!("typing_aids.stop_dashes").to_string(),
+                    };
+                    turn_off = ui.button(off).clicked();
+                });
+            });
+
+        if put_back || turn_off {
+            self.put_back_substitution(turn_off);
+        }
+        if put_back || turn_off || click_away.clicked(&area.response) {
+            self.typing_aids.popup = None;
+        }
+    }
+
+    /// Replace the recent substitution with what was typed, as an undoable
+    /// edit, and turn off its rule when `turn_off`
+    fn put_back_substitution(&mut self, turn_off: bool) {
+        let Some(recent) = self.typing_aids.recent.take() else {
+            return;
+        };
+        if self.readonly {
+            self.show_readonly_hint();
+            return;
+        }
+        let text = self.editor.text();
+        if text.get(recent.range.clone()) == Some(recent.replacement.as_str()) {
+            self.editor.replace_range_in_place(recent.range, &recent.original);
+            self.has_unsaved_changes = true;
+        }
+        if !turn_off {
+            return;
+        }
+        match recent.rule {
+            Rule::Capitalize => self.config.writing.auto_capitalize = false,
+            Rule::Dash => self.config.writing.auto_dashes = false,
+            Rule::Correction(from) => {
+                if let Err(e) = self.typing_aids.corrections.remove(&from) {
+                    self.notify_error(t!("error.save_corrections"), format!("{:#}", e));
+                }
+            }
+        }
+    }
+}
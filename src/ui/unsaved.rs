@@ -0,0 +1,97 @@
+//! Asking what to do with unsaved changes before another document takes
+//! the editor: File → New and Open, and opening or creating a file in the
+//! sidebar
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+/// What takes the editor once its unsaved changes are dealt with
+#[derive(Debug, Clone, PartialEq)]
+pub enum Replacement {
+    /// An untitled document
+    New,
+    Open(PathBuf),
+    /// A new file in this folder, named in the sidebar
+    CreateIn(PathBuf),
+}
+
+impl RmdApp {
+    /// Put `replacement` in the editor, first asking what to do with
+    /// unsaved changes
+    pub fn replace_document(&mut self, replacement: Replacement) {
+        if self.has_unsaved_changes {
+            self.pending_replacement = Some(replacement);
+        } else {
+            self.apply_replacement(replacement);
+        }
+    }
+
+    /// Put `replacement` in the editor, returning whether it took it
+    fn apply_replacement(&mut self, replacement: Replacement) -> bool {
+        match replacement {
+            Replacement::New => {
+                self.new_file();
+                true
+            }
+            Replacement::Open(path) => self.open_path(&path),
+            Replacement::CreateIn(dir) => self.create_file_in(&dir),
+        }
+    }
+
+    /// Ask what to do with unsaved changes before replacing the document
+    pub fn ui_replace_prompt(&mut self, ctx: &egui::Context) {
+        let Some(replacement) = self.pending_replacement.clone() else {
+            return;
+        };
+        let name = self
+            .current_file
+            .as_deref()
+            .and_then(Path::file_name)
+            .map_or_else(|| t!("status.untitled").to_string(), |name| name.to_string_lossy().to_string());
+        let question = match &replacement {
+            Replacement::Open(path) => {
+                let file = path.file_name().unwrap_or_default().to_string_lossy();
+                t!("unsaved.save_before_opening", name = name, file = file)
+            }
+            Replacement::New | Replacement::CreateIn(_) => t!("unsaved.save_before_new", name = name),
+        };
+
+        let mut save = false;
+        let mut discard = false;
+        let mut cancel = false;
+        egui::Window::new(t!("unsaved.title"))
+            .id(egui::Id::new("replace_document"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(question);
+                ui.horizontal(|ui| {
+                    save = ui.button(t!("dialog.save")).clicked();
+                    discard = ui.button(t!("dialog.dont_save")).clicked();
+                    cancel = ui.button(t!("dialog.cancel")).clicked();
+                });
+            });
+
+        if save {
+            self.save_file();
+            // Keep the document if saving failed or was cancelled
+            if self.has_unsaved_changes {
+                self.pending_replacement = None;
+                return;
+            }
+        }
+        if save || discard {
+            self.pending_replacement = None;
+            let unsaved = std::mem::replace(&mut self.has_unsaved_changes, false);
+            // Changes not saved stay unsaved if the other document can't be opened
+            if !self.apply_replacement(replacement) {
+                self.has_unsaved_changes = unsaved;
+            }
+        } else if cancel {
+            self.pending_replacement = None;
+        }
+    }
+}
@@ -0,0 +1,103 @@
+//! Help → Check for Updates, the weekly check, and the dialog telling of a
+//! new release
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::ui::toasts::Severity;
+use crate::update::{self, Release, UpdateCheck, UreqClient};
+use eframe::egui;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The check running, and the release it found
+#[derive(Default)]
+pub struct Updates {
+    pub check: Option<UpdateCheck>,
+    found: Option<Release>,
+}
+
+impl RmdApp {
+    /// Ask GitHub for the latest release. `manual` when the user asked, so
+    /// the result is always shown; the weekly check only speaks up when there
+    /// is a new version.
+    pub fn check_for_updates(&mut self, manual: bool) {
+        if self.updates.check.is_none() {
+            self.updates.check = Some(UpdateCheck::start(UreqClient::default(), manual));
+        }
+    }
+
+    /// Start the weekly check if it is on and due
+    pub fn check_for_updates_if_due(&mut self) {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let updates = &mut self.config.updates;
+        if updates.check_weekly && update::is_due(updates.last_check, now) {
+            updates.last_check = Some(now);
+            self.check_for_updates(false);
+        }
+    }
+
+    /// Pick up the result of a running check
+    pub fn poll_update_check(&mut self, ctx: &egui::Context) {
+        let Some(check) = &self.updates.check else {
+            return;
+        };
+        let Some(result) = check.poll() else {
+            ctx.request_repaint_after(Duration::from_millis(200));
+            return;
+        };
+        let manual = check.manual;
+        self.updates.check = None;
+        match result {
+            Ok(Some(release)) => self.updates.found = Some(release),
+            Ok(None) if manual => {
+                let message = t!("update.latest", version = env!("CARGO_PKG_VERSION"));
+                self.notify(Severity::Info, message);
+            }
+            Ok(None) => {}
+            Err(e) if manual => self.notify_error(t!("update.failed"), e),
+            Err(e) => log::warn!("Weekly update check failed: {}", e),
+        }
+    }
+
+    /// Render the dialog about a new release
+    pub fn ui_update_dialog(&mut self, ctx: &egui::Context) {
+        let Some(release) = &self.updates.found else {
+            return;
+        };
+        let mut open = true;
+        let mut close = false;
+        let mut failure = None;
+        egui::Window::new(t!("update.title"))
+            .id(egui::Id::new("update_available"))
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(t!("update.available", latest = release.version, current = env!("CARGO_PKG_VERSION")));
+                if !release.notes.is_empty() {
+                    ui.add_space(4.0);
+                    egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                        ui.label(egui::RichText::new(&release.notes).color(self.theme.text_muted));
+                    });
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button(t!("update.open_release_page")).clicked() {
+                        if let Err(e) = webbrowser::open(&release.url) {
+                            failure = Some((t!("error.open_failed", path = release.url), e.to_string()));
+                        }
+                        close = true;
+                    }
+                    if ui.button(t!("update.later")).clicked() {
+                        close = true;
+                    }
+                });
+            });
+        if let Some((context, err)) = failure {
+            self.notify_error(context, err);
+        }
+        if !open || close {
+            self.updates.found = None;
+        }
+    }
+}
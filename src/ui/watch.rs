@@ -0,0 +1,195 @@
+//! Watch mode: reload the document when another program changes it
+
+use crate::app::RmdApp;
+use crate::editor::error::Operation;
+use crate::editor::load;
+use crate::i18n::t;
+use crate::ui::toasts::Severity;
+use crate::ui::layouts::LayoutMode;
+use eframe::egui;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the file's metadata is checked
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Quiet time after the last change before reloading, so a burst of writes
+/// is read once it is finished
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Modification time and size, enough to tell that a file was written
+type Stamp = (Option<SystemTime>, u64);
+
+fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok(), metadata.len()))
+}
+
+/// Polls a file for changes made by other programs. The path is looked up
+/// each time rather than held open, so editors that save by writing a new
+/// file and renaming it over the old one are noticed too.
+pub struct FileWatch {
+    path: PathBuf,
+    /// Stamp of the version the editor has
+    known: Option<Stamp>,
+    last_check: Instant,
+    /// When the file last changed, while waiting for it to settle
+    changed_at: Option<Instant>,
+}
+
+impl FileWatch {
+    pub fn new(path: PathBuf) -> Self {
+        let known = stamp(&path);
+        Self { path, known, last_check: Instant::now(), changed_at: None }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Take the file as it is now as the editor's version, after opening
+    /// or saving it
+    pub fn sync(&mut self) {
+        self.known = stamp(&self.path);
+        self.changed_at = None;
+    }
+
+    /// Whether the file changed and has been quiet since. Returns true once
+    /// per change.
+    pub fn poll(&mut self) -> bool {
+        self.poll_at(Instant::now())
+    }
+
+    fn poll_at(&mut self, now: Instant) -> bool {
+        if now.duration_since(self.last_check) < POLL_INTERVAL {
+            return false;
+        }
+        self.last_check = now;
+        // Missing for a moment while being replaced; wait for the new file
+        let Some(current) = stamp(&self.path) else {
+            return false;
+        };
+        if Some(current) != self.known {
+            self.known = Some(current);
+            self.changed_at = Some(now);
+            return false;
+        }
+        match self.changed_at {
+            Some(changed_at) if now.duration_since(changed_at) >= DEBOUNCE => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl RmdApp {
+    /// Whether the open file is being watched: in watch mode, or while only
+    /// the preview is shown
+    fn watching(&self) -> bool {
+        self.watch_mode || self.layout.mode == LayoutMode::PreviewOnly
+    }
+
+    /// Reload the document if it changed on disk, or raise the conflict
+    /// banner if it has unsaved edits
+    pub fn poll_file_watch(&mut self) {
+        let Some(path) = self.current_file.clone().filter(|_| self.watching()) else {
+            self.file_watch = None;
+            return;
+        };
+        let watch = match &mut self.file_watch {
+            Some(watch) if watch.path() == path => watch,
+            watch => watch.insert(FileWatch::new(path)),
+        };
+        if !watch.poll() {
+            return;
+        }
+        if self.has_unsaved_changes {
+            self.disk_conflict = true;
+        } else {
+            self.reload_from_disk();
+        }
+    }
+
+    /// Replace the buffer with the file's contents, as an undoable edit, and
+    /// keep the preview at the same place in the document
+    pub fn reload_from_disk(&mut self) {
+        let Some(path) = self.current_file.clone() else {
+            return;
+        };
+        let text = match load::read(&path, Operation::Reload) {
+            Ok(text) => crate::utils::normalize_line_endings(&text),
+            Err(e) => {
+                self.notify_file_error(e);
+                return;
+            }
+        };
+        self.preview.keep_scroll_fraction();
+        self.editor.replace_text(&text);
+        self.editor.mark_saved();
+        self.has_unsaved_changes = false;
+        self.disk_conflict = false;
+        self.changes.invalidate();
+        if let Some(watch) = &mut self.file_watch {
+            watch.sync();
+        }
+        let time = chrono::Local::now().format("%H:%M:%S");
+        self.notify(Severity::Info, t!("toast.reloaded", time = time));
+    }
+
+    /// Banner offering to reload a file that changed under unsaved edits
+    pub fn ui_disk_conflict(&mut self, ctx: &egui::Context) {
+        if !self.disk_conflict {
+            return;
+        }
+        let name = self
+            .current_file
+            .as_deref()
+            .and_then(Path::file_name)
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let mut reload = false;
+        let mut keep = false;
+        egui::TopBottomPanel::top("disk_conflict").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.colored_label(self.theme.warning, "⚠");
+                ui.label(t!("watch.conflict", name = name));
+                reload = ui.button(t!("watch.reload")).on_hover_text(t!("watch.reload_hint")).clicked();
+                keep = ui.button(t!("watch.keep")).on_hover_text(t!("watch.keep_hint")).clicked();
+            });
+        });
+        if reload {
+            self.reload_from_disk();
+        } else if keep {
+            self.disk_conflict = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_change_once_settled() {
+        let path = std::env::temp_dir().join(format!("rmd-watch-{}.md", std::process::id()));
+        std::fs::write(&path, "one").unwrap();
+        let mut watch = FileWatch::new(path.clone());
+        let start = Instant::now();
+        assert!(!watch.poll_at(start + POLL_INTERVAL));
+
+        // Noticed, then reported once nothing changed for the debounce time
+        std::fs::write(&path, "two, longer").unwrap();
+        assert!(!watch.poll_at(start + POLL_INTERVAL * 2));
+        assert!(!watch.poll_at(start + POLL_INTERVAL * 2 + Duration::from_millis(100)));
+        assert!(watch.poll_at(start + POLL_INTERVAL * 3 + DEBOUNCE));
+        assert!(!watch.poll_at(start + POLL_INTERVAL * 5 + DEBOUNCE));
+
+        // Gone while being replaced isn't a change
+        std::fs::remove_file(&path).unwrap();
+        assert!(!watch.poll_at(start + POLL_INTERVAL * 7 + DEBOUNCE));
+        assert!(!watch.poll_at(start + POLL_INTERVAL * 9 + DEBOUNCE * 2));
+    }
+}
@@ -0,0 +1,146 @@
+//! Indentation guides and visible whitespace, painted over the editor's
+//! laid-out text so they follow the glyphs wherever they land
+
+use crate::app::RmdApp;
+use eframe::egui::{self, Galley};
+use std::ops::Range;
+
+const SPACE_MARK: &str = "·";
+const TAB_MARK: &str = "→";
+const LINE_END_MARK: &str = "¶";
+
+/// A line of the document as laid out, over one or more rows when wrapped
+struct Line {
+    rows: Range<usize>,
+    /// Where its first non-whitespace glyph starts, or `None` when blank
+    indent: Option<f32>,
+}
+
+/// The galley's rows grouped into document lines
+fn lines(galley: &Galley) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (index, row) in galley.rows.iter().enumerate() {
+        if row.ends_with_newline || index + 1 == galley.rows.len() {
+            let indent = galley.rows[start..=index]
+                .iter()
+                .flat_map(|row| &row.glyphs)
+                .find(|glyph| !glyph.chr.is_whitespace())
+                .map(|glyph| glyph.pos.x);
+            lines.push(Line { rows: start..index + 1, indent });
+            start = index + 1;
+        }
+    }
+    lines
+}
+
+/// Give blank lines the smaller indentation of the lines around them, so
+/// guides run on through blank lines inside an indented block
+fn fill_blank_indents(indents: &mut [Option<f32>]) {
+    let mut previous = None;
+    let mut index = 0;
+    while index < indents.len() {
+        if indents[index].is_some() {
+            previous = indents[index];
+            index += 1;
+            continue;
+        }
+        let blank_end = indents[index..].iter().position(Option::is_some).map_or(indents.len(), |i| index + i);
+        let next = indents.get(blank_end).copied().flatten();
+        let fill = match (previous, next) {
+            (Some(previous), Some(next)) => Some(previous.min(next)),
+            _ => None,
+        };
+        indents[index..blank_end].fill(fill);
+        index = blank_end;
+    }
+}
+
+/// Characters of `line` between its leading and trailing whitespace; those
+/// outside get marks. Empty for a line of nothing but whitespace.
+fn between_whitespace(line: &[char]) -> Range<usize> {
+    let Some(start) = line.iter().position(|c| !c.is_whitespace()) else {
+        return 0..0;
+    };
+    let end = line.iter().rposition(|c| !c.is_whitespace()).map_or(start, |i| i + 1);
+    start..end
+}
+
+impl RmdApp {
+    /// Indentation guides at each tab stop within leading whitespace, and
+    /// marks for spaces, tabs and line ends, as set in the config. Only
+    /// rows in view are painted.
+    pub(crate) fn paint_whitespace(&self, ui: &egui::Ui, galley: &Galley, galley_pos: egui::Pos2) {
+        let config = &self.config.editor;
+        if !config.show_indent_guides && !config.show_whitespace {
+            return;
+        }
+        let font = self.editor_font();
+        let glyph_width = ui.fonts(|f| f.glyph_width(&font, ' '));
+        let tab_width = glyph_width * self.indentation().size as f32;
+        let guide = egui::Stroke::new(1.0, self.theme.text_muted.gamma_multiply(0.25));
+        let mark_color = self.theme.text_muted.gamma_multiply(0.6);
+        let painter = ui.painter();
+        let clip = ui.clip_rect();
+
+        let lines = lines(galley);
+        let mut indents: Vec<Option<f32>> = lines.iter().map(|line| line.indent).collect();
+        fill_blank_indents(&mut indents);
+
+        for (line, indent) in lines.iter().zip(indents) {
+            let rows = &galley.rows[line.rows.clone()];
+            let top = galley_pos.y + rows[0].rect.min.y;
+            let bottom = galley_pos.y + rows[rows.len() - 1].rect.max.y;
+            if bottom < clip.min.y || top > clip.max.y {
+                continue;
+            }
+
+            if config.show_indent_guides {
+                let indent = indent.unwrap_or(0.0);
+                let mut x = 0.0;
+                // Half a glyph of slack for rounding in the layout
+                while x + glyph_width / 2.0 < indent {
+                    painter.vline((galley_pos.x + x).round() + 0.5, top..=bottom, guide);
+                    x += tab_width;
+                }
+            }
+
+            if config.show_whitespace {
+                let chars: Vec<char> = rows.iter().flat_map(|row| &row.glyphs).map(|glyph| glyph.chr).collect();
+                let unmarked = if config.whitespace_everywhere { 0..0 } else { between_whitespace(&chars) };
+                let glyphs = rows.iter().flat_map(|row| &row.glyphs).enumerate();
+                for (_, glyph) in glyphs.filter(|(index, _)| !unmarked.contains(index)) {
+                    let mark = match glyph.chr {
+                        ' ' => SPACE_MARK,
+                        '\t' => TAB_MARK,
+                        _ => continue,
+                    };
+                    let center = glyph.logical_rect().center() + galley_pos.to_vec2();
+                    painter.text(center, egui::Align2::CENTER_CENTER, mark, font.clone(), mark_color);
+                }
+                let last = &rows[rows.len() - 1];
+                if last.ends_with_newline {
+                    let pos = egui::pos2(galley_pos.x + last.rect.max.x, galley_pos.y + last.rect.center().y);
+                    painter.text(pos, egui::Align2::LEFT_CENTER, LINE_END_MARK, font.clone(), mark_color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blank_lines_and_marked_whitespace() {
+        let mut indents = [Some(8.0), None, None, Some(16.0), None, Some(0.0), None];
+        fill_blank_indents(&mut indents);
+        assert_eq!(indents, [Some(8.0), Some(8.0), Some(8.0), Some(16.0), Some(0.0), Some(0.0), None]);
+
+        let line: Vec<char> = "  a b \t".chars().collect();
+        assert_eq!(between_whitespace(&line), 2..5);
+        assert_eq!(between_whitespace(&[' ', '\t']), 0..0);
+        assert_eq!(between_whitespace(&['x']), 0..1);
+    }
+}
@@ -88,45 +88,32 @@ impl SplitPanel {
     }
 }
 
-/// A toolbar button with icon and tooltip
-pub struct ToolbarButton {
-    icon: &'static str,
-    tooltip: Option<&'static str>,
-    enabled: bool,
+/// Give an icon-only button the name a screen reader announces instead of
+/// its glyph, and show the same name as its tooltip
+pub fn name_icon_button(response: egui::Response, name: &str) -> egui::Response {
+    let enabled = response.enabled();
+    response.widget_info(|| egui::WidgetInfo::labeled(egui::WidgetType::Button, enabled, name));
+    response.on_hover_text(name)
 }
 
-impl ToolbarButton {
-    pub fn new(icon: &'static str) -> Self {
-        Self {
-            icon,
-            tooltip: None,
-            enabled: true,
-        }
-    }
+/// Tells clicking away from a popup, which closes it, from the click that
+/// opened it: egui reports that one as outside the popup on the frame the
+/// popup first appears
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ClickAway {
+    just_opened: bool,
+}
 
-    pub fn tooltip(mut self, tooltip: &'static str) -> Self {
-        self.tooltip = Some(tooltip);
-        self
+impl ClickAway {
+    /// For a popup opened this frame
+    pub fn opened() -> Self {
+        Self { just_opened: true }
     }
 
-    pub fn enabled(mut self, enabled: bool) -> Self {
-        self.enabled = enabled;
-        self
-    }
-
-    pub fn show(self, ui: &mut egui::Ui) -> egui::Response {
-        let mut button = egui::Button::new(self.icon);
-        if !self.enabled {
-            button = button.sense(egui::Sense::hover());
-        }
-
-        let response = ui.add_sized(egui::vec2(32.0, 32.0), button);
-
-        if let Some(tooltip) = self.tooltip {
-            return response.on_hover_text(tooltip);
-        }
-
-        response
+    /// Whether the popup drawn as `response` was clicked away from. Call
+    /// once a frame while it is shown.
+    pub fn clicked(&mut self, response: &egui::Response) -> bool {
+        !std::mem::take(&mut self.just_opened) && response.clicked_elsewhere()
     }
 }
 
@@ -0,0 +1,105 @@
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::workspace::links;
+use eframe::egui;
+use std::path::PathBuf;
+
+impl RmdApp {
+    /// Open the note a wiki link points to, asking before creating a missing one
+    pub fn follow_wiki_link(&mut self, target: &str) {
+        let root = self
+            .workspace_root
+            .clone()
+            .or_else(|| self.current_file.as_deref()?.parent().map(PathBuf::from));
+        let Some(root) = root else {
+            return;
+        };
+
+        let names = links::note_names(&root, &self.config.markdown_extensions);
+        let path = links::resolve_wiki_target(&root, &names, target);
+        if path.exists() {
+            self.open_path(&path);
+        } else {
+            self.create_note_prompt = Some(path);
+        }
+    }
+
+    /// Rebuild the backlink index if wiki links are enabled and a folder is open
+    pub fn refresh_backlinks(&mut self) {
+        if !self.config.wiki_links {
+            return;
+        }
+        if let Some(root) = &self.workspace_root {
+            self.backlinks.rebuild(root, &self.config.markdown_extensions);
+        }
+    }
+
+    /// Ask whether to create the note a wiki link pointed to
+    pub fn ui_create_note_prompt(&mut self, ctx: &egui::Context) {
+        let Some(path) = self.create_note_prompt.clone() else {
+            return;
+        };
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+
+        let mut create = false;
+        let mut cancel = false;
+        egui::Window::new(t!("wiki.create_title"))
+            .id(egui::Id::new("create_note"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(t!("wiki.create_prompt", name = name));
+                ui.horizontal(|ui| {
+                    create = ui.button(t!("wiki.create")).clicked();
+                    cancel = ui.button(t!("dialog.cancel")).clicked();
+                });
+            });
+
+        if cancel {
+            self.create_note_prompt = None;
+        }
+        if create {
+            self.create_note_prompt = None;
+            if let Err(e) = std::fs::write(&path, format!("# {}\n", name)) {
+                self.notify_error(t!("error.create_failed", path = path.display()), e);
+                return;
+            }
+            self.open_path(&path);
+            if let Some(root) = self.workspace_root.clone() {
+                self.sidebar.refresh(&root, &self.config.markdown_extensions);
+            }
+            self.refresh_backlinks();
+        }
+    }
+
+    /// List the documents that link to the open one
+    pub fn ui_backlinks(&mut self, ui: &mut egui::Ui) -> Option<PathBuf> {
+        self.backlinks.poll();
+        let (Some(root), Some(current)) = (&self.workspace_root, &self.current_file) else {
+            return None;
+        };
+
+        let mut open = None;
+        let sources = self.backlinks.backlinks_to(current);
+        egui::CollapsingHeader::new(t!("wiki.backlinks", count = sources.len()))
+            .default_open(true)
+            .show(ui, |ui| {
+                if sources.is_empty() {
+                    let text = if self.backlinks.is_building() {
+                        t!("wiki.indexing")
+                    } else {
+                        t!("wiki.no_backlinks")
+                    };
+                    ui.label(egui::RichText::new(text).color(self.theme.text_muted));
+                }
+                for source in sources {
+                    let name = source.strip_prefix(root).unwrap_or(source);
+                    if ui.link(name.display().to_string()).clicked() {
+                        open = Some(source.clone());
+                    }
+                }
+            });
+        open
+    }
+}
@@ -0,0 +1,76 @@
+//! More than one window: File → New Window starts another copy of the app
+//! with an empty document, each keeping its own file and unsaved changes.
+//! Closing a window with unsaved changes asks first.
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use eframe::egui;
+use std::path::Path;
+
+impl RmdApp {
+    /// Open another window by starting the app again, in this window's folder
+    pub fn open_new_window(&mut self) {
+        let spawned = std::env::current_exe().and_then(|exe| {
+            let mut command = std::process::Command::new(exe);
+            command.arg("--new").args(self.workspace_root.iter());
+            command.spawn()
+        });
+        if let Err(e) = spawned {
+            self.notify_error(t!("error.new_window"), e);
+        }
+    }
+
+    /// Hold the window open while the document has unsaved changes, to ask
+    /// what to do with them
+    pub fn intercept_close(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.viewport().close_requested()) && self.has_unsaved_changes && !self.close_confirmed {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.close_prompt = true;
+        }
+    }
+
+    /// Ask what to do with unsaved changes before closing the window
+    pub fn ui_close_prompt(&mut self, ctx: &egui::Context) {
+        if !self.close_prompt {
+            return;
+        }
+        let name = self
+            .current_file
+            .as_deref()
+            .and_then(Path::file_name)
+            .map_or_else(|| t!("status.untitled").to_string(), |name| name.to_string_lossy().to_string());
+
+        let mut save = false;
+        let mut discard = false;
+        let mut cancel = false;
+        egui::Window::new(t!("windows.close_title"))
+            .id(egui::Id::new("close_window"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(t!("windows.save_before_closing", name = name));
+                ui.horizontal(|ui| {
+                    save = ui.button(t!("dialog.save")).clicked();
+                    discard = ui.button(t!("dialog.dont_save")).clicked();
+                    cancel = ui.button(t!("dialog.cancel")).clicked();
+                });
+            });
+
+        if save {
+            self.save_file();
+            // Stay open if saving failed or was cancelled
+            if self.has_unsaved_changes {
+                self.close_prompt = false;
+                return;
+            }
+        }
+        if save || discard {
+            self.close_prompt = false;
+            self.close_confirmed = true;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        } else if cancel {
+            self.close_prompt = false;
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Recent and pinned workspace folders, and switching between them
+
+use crate::app::RmdApp;
+use crate::i18n::t;
+use crate::ui::sidebar::Sidebar;
+use crate::ui::toasts::Severity;
+use crate::workspace::state::{WorkspaceState, WorkspaceStateStore};
+use eframe::egui;
+use std::path::{Path, PathBuf};
+
+fn folder_name(folder: &Path) -> String {
+    folder
+        .file_name()
+        .map_or_else(|| folder.display().to_string(), |name| name.to_string_lossy().to_string())
+}
+
+impl RmdApp {
+    /// Open `root` in the sidebar, first asking what to do with unsaved
+    /// changes to a document of the workspace being left
+    pub fn open_workspace(&mut self, root: PathBuf) {
+        if self.workspace_root.as_ref() == Some(&root) {
+            return;
+        }
+        if !root.is_dir() {
+            self.notify(Severity::Warning, t!("toast.folder_gone", path = root.display()));
+            return;
+        }
+        if self.has_unsaved_changes && self.document_in_workspace() {
+            self.workspace_switch = Some(root);
+            return;
+        }
+        self.switch_workspace(root);
+    }
+
+    /// Whether the open document belongs to the open workspace
+    fn document_in_workspace(&self) -> bool {
+        match (&self.current_file, &self.workspace_root) {
+            (Some(file), Some(root)) => file.starts_with(root),
+            _ => false,
+        }
+    }
+
+    /// Close the old workspace's tree and document and open `root`, with
+    /// the folders that were expanded when it was last open
+    fn switch_workspace(&mut self, root: PathBuf) {
+        self.save_workspace_state();
+        if self.document_in_workspace() {
+            self.new_file();
+        }
+        self.workspace_search.cancel();
+        self.sidebar = Sidebar::default();
+        if let Ok(store) = WorkspaceStateStore::open_default() {
+            self.sidebar.set_expanded(store.load(&root).expanded_paths());
+        }
+        self.sidebar.refresh(&root, &self.config.markdown_extensions);
+        self.config.add_recent_folder(&root);
+        self.workspace_root = Some(root);
+        self.show_sidebar = true;
+        self.refresh_backlinks();
+    }
+
+    /// Remember which folders of the open workspace's tree are expanded
+    pub fn save_workspace_state(&self) {
+        let Some(root) = &self.workspace_root else {
+            return;
+        };
+        let state = WorkspaceState::new(root, self.sidebar.expanded());
+        if let Err(e) = WorkspaceStateStore::open_default().and_then(|store| store.save(&state)) {
+            log::warn!("Failed to save the state of {}: {}", root.display(), e);
+        }
+    }
+
+    /// Pinned, then recent workspace folders, each with a pin toggle.
+    /// Returns the folder clicked.
+    pub(crate) fn ui_workspace_folders(&mut self, ui: &mut egui::Ui) -> Option<PathBuf> {
+        let folders: Vec<PathBuf> = self.config.workspace_folders().into_iter().map(Path::to_path_buf).collect();
+        if folders.is_empty() {
+            ui.weak(t!("workspace.no_recent_folders"));
+            return None;
+        }
+        let mut chosen = None;
+        let mut toggle_pin = None;
+        for folder in folders {
+            let pinned = self.config.is_pinned_folder(&folder);
+            let current = self.workspace_root.as_ref() == Some(&folder);
+            ui.horizontal(|ui| {
+                let pin = ui.selectable_label(pinned, "📌");
+                pin.widget_info(|| egui::WidgetInfo::selected(egui::WidgetType::SelectableLabel, true, pinned, t!("workspace.pinned")));
+                if pin.on_hover_text(if pinned { t!("workspace.unpin") } else { t!("workspace.pin") }).clicked() {
+                    toggle_pin = Some(folder.clone());
+                }
+                if ui
+                    .selectable_label(current, folder_name(&folder))
+                    .on_hover_text(folder.display().to_string())
+                    .clicked()
+                {
+                    ui.close_menu();
+                    chosen = Some(folder);
+                }
+            });
+        }
+        if let Some(folder) = toggle_pin {
+            self.config.toggle_pinned_folder(&folder);
+        }
+        chosen
+    }
+
+    /// File → Open Recent: recent documents, then workspace folders
+    pub(crate) fn ui_open_recent_menu(&mut self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new(t!("workspace.files")).weak());
+        if self.config.recent_files.is_empty() {
+            ui.weak(t!("workspace.no_recent_files"));
+        }
+        let mut open = None;
+        for path in &self.config.recent_files {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if ui.button(name).on_hover_text(path.display().to_string()).clicked() {
+                ui.close_menu();
+                open = Some(path.clone());
+            }
+        }
+        ui.separator();
+        ui.label(egui::RichText::new(t!("workspace.folders")).weak());
+        if let Some(folder) = self.ui_workspace_folders(ui) {
+            self.open_workspace(folder);
+        }
+        if let Some(path) = open {
+            self.open_path(&path);
+        }
+    }
+
+    /// Ask what to do with unsaved changes before leaving their workspace
+    pub fn ui_workspace_switch_prompt(&mut self, ctx: &egui::Context) {
+        let Some(root) = self.workspace_switch.clone() else {
+            return;
+        };
+        let name = self
+            .current_file
+            .as_deref()
+            .and_then(Path::file_name)
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let mut save = false;
+        let mut discard = false;
+        let mut cancel = false;
+        egui::Window::new(t!("workspace.switch_title"))
+            .id(egui::Id::new("switch_workspace"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.label(t!("workspace.save_before_switching", name = name, folder = folder_name(&root)));
+                ui.horizontal(|ui| {
+                    save = ui.button(t!("dialog.save")).clicked();
+                    discard = ui.button(t!("dialog.dont_save")).clicked();
+                    cancel = ui.button(t!("dialog.cancel")).clicked();
+                });
+            });
+
+        if save {
+            self.save_file();
+            // Stay put if saving failed
+            if self.has_unsaved_changes {
+                self.workspace_switch = None;
+                return;
+            }
+        }
+        if save || discard {
+            self.workspace_switch = None;
+            self.has_unsaved_changes = false;
+            self.switch_workspace(root);
+        } else if cancel {
+            self.workspace_switch = None;
+        }
+    }
+}
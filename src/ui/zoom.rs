@@ -0,0 +1,82 @@
+//! Ctrl+wheel and pinch zoom for the editor and the preview
+
+use crate::app::RmdApp;
+use eframe::egui;
+use std::time::{Duration, Instant};
+
+/// How long the zoom level stays on screen after the last change
+const INDICATOR_DURATION: Duration = Duration::from_millis(900);
+
+/// Smallest and largest editor font size reachable by zooming
+const EDITOR_FONT_RANGE: (f32, f32) = (8.0, 48.0);
+
+/// The zoom level shown briefly next to the pointer
+pub struct ZoomIndicator {
+    label: String,
+    pos: egui::Pos2,
+    shown_at: Instant,
+}
+
+/// Zoom factor of a Ctrl+wheel or pinch over `ui` this frame. egui turns
+/// Ctrl+wheel into zoom rather than scroll, so the content doesn't move too.
+fn zoom_gesture(ui: &egui::Ui) -> Option<f32> {
+    let factor = ui.input(|i| i.zoom_delta());
+    (factor != 1.0 && ui.ui_contains_pointer()).then_some(factor)
+}
+
+impl RmdApp {
+    /// Editor font, sized by the config and editor zoom
+    pub(crate) fn editor_font(&self) -> egui::FontId {
+        egui::FontId::monospace(self.config.font.editor_font_size)
+    }
+
+    /// Change the editor font size on a zoom gesture over the editor
+    pub(crate) fn zoom_editor(&mut self, ui: &egui::Ui) {
+        let Some(factor) = zoom_gesture(ui) else {
+            return;
+        };
+        let (min, max) = EDITOR_FONT_RANGE;
+        let size = (self.config.font.editor_font_size * factor).clamp(min, max);
+        self.config.font.editor_font_size = size;
+        self.show_zoom_indicator(ui, format!("{:.0} pt", size));
+    }
+
+    /// Zoom the preview on a zoom gesture over it
+    pub(crate) fn zoom_preview(&mut self, ui: &egui::Ui) {
+        let Some(factor) = zoom_gesture(ui) else {
+            return;
+        };
+        self.preview.zoom_by(factor);
+        self.show_zoom_indicator(ui, format!("{:.0}%", self.preview.zoom * 100.0));
+    }
+
+    fn show_zoom_indicator(&mut self, ui: &egui::Ui, label: String) {
+        let Some(pos) = ui.input(|i| i.pointer.hover_pos()) else {
+            return;
+        };
+        self.zoom_indicator = Some(ZoomIndicator { label, pos, shown_at: Instant::now() });
+    }
+
+    /// Draw the zoom level near the pointer, fading out
+    pub fn ui_zoom_indicator(&mut self, ctx: &egui::Context) {
+        let Some(indicator) = &self.zoom_indicator else {
+            return;
+        };
+        let elapsed = indicator.shown_at.elapsed();
+        if elapsed >= INDICATOR_DURATION {
+            self.zoom_indicator = None;
+            return;
+        }
+        let fade = 1.0 - elapsed.as_secs_f32() / INDICATOR_DURATION.as_secs_f32();
+        egui::Area::new(egui::Id::new("zoom_indicator"))
+            .order(egui::Order::Tooltip)
+            .fixed_pos(indicator.pos + egui::vec2(16.0, 16.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                ui.set_opacity(fade.min(1.0));
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.label(egui::RichText::new(&indicator.label).strong());
+                });
+            });
+    }
+}
@@ -0,0 +1,175 @@
+//! Asking GitHub whether a newer release is out. Nothing but the request
+//! for the latest release is sent, and only when the user asks or has
+//! turned on the weekly check.
+
+use serde::Deserialize;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::Duration;
+
+/// The latest release of the project, as the GitHub API has it
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/tszlznl/md-edit/releases/latest";
+
+/// How long to wait for GitHub to answer
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Time between automatic checks
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Lines of the release notes shown before "…"
+const NOTES_LINES: usize = 12;
+
+/// Fetching a URL's body, so tests can answer without the network
+pub trait HttpClient: Send + 'static {
+    fn get(&self, url: &str) -> anyhow::Result<String>;
+}
+
+/// The real client
+pub struct UreqClient {
+    agent: ureq::Agent,
+}
+
+impl Default for UreqClient {
+    fn default() -> Self {
+        let user_agent = concat!("rmd/", env!("CARGO_PKG_VERSION"));
+        Self { agent: ureq::AgentBuilder::new().timeout(TIMEOUT).user_agent(user_agent).build() }
+    }
+}
+
+impl HttpClient for UreqClient {
+    fn get(&self, url: &str) -> anyhow::Result<String> {
+        let response = self.agent.get(url).set("Accept", "application/vnd.github+json").call()?;
+        Ok(response.into_string()?)
+    }
+}
+
+#[derive(Deserialize)]
+struct ReleaseJson {
+    tag_name: String,
+    html_url: String,
+    #[serde(default)]
+    body: Option<String>,
+}
+
+/// A release newer than the running version
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    pub version: semver::Version,
+    /// Page of the release on GitHub
+    pub url: String,
+    /// The start of its release notes
+    pub notes: String,
+}
+
+/// The latest release, if it is newer than `current`
+pub fn check(client: &dyn HttpClient, current: &str) -> anyhow::Result<Option<Release>> {
+    let current = semver::Version::parse(current)?;
+    let json: ReleaseJson = serde_json::from_str(&client.get(LATEST_RELEASE_URL)?)?;
+    let tag = json.tag_name.trim();
+    let version = semver::Version::parse(tag.strip_prefix('v').unwrap_or(tag))
+        .map_err(|e| anyhow::anyhow!("The latest release has a tag that isn't a version ({}): {}", tag, e))?;
+    if version <= current {
+        return Ok(None);
+    }
+    Ok(Some(Release { version, url: json.html_url, notes: excerpt(json.body.as_deref().unwrap_or("")) }))
+}
+
+/// The first lines of release notes
+fn excerpt(notes: &str) -> String {
+    let lines: Vec<&str> = notes.trim().lines().map(str::trim_end).collect();
+    let mut excerpt = lines[..lines.len().min(NOTES_LINES)].join("\n");
+    if lines.len() > NOTES_LINES {
+        excerpt.push_str("\n…");
+    }
+    excerpt
+}
+
+/// Whether the weekly check is due, `last` and `now` in seconds since
+/// the Unix epoch
+pub fn is_due(last: Option<u64>, now: u64) -> bool {
+    last.is_none_or(|last| now.saturating_sub(last) >= CHECK_INTERVAL.as_secs())
+}
+
+/// A check running on a background thread
+pub struct UpdateCheck {
+    receiver: Receiver<anyhow::Result<Option<Release>>>,
+    /// Asked for by the user, rather than the weekly check
+    pub manual: bool,
+}
+
+impl UpdateCheck {
+    pub fn start(client: impl HttpClient, manual: bool) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(check(&client, env!("CARGO_PKG_VERSION")));
+        });
+        Self { receiver, manual }
+    }
+
+    /// The result, once the check is done
+    pub fn poll(&self) -> Option<anyhow::Result<Option<Release>>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(anyhow::anyhow!("The update check stopped"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers every request with the same body, or fails
+    struct Stub(Option<String>);
+
+    impl HttpClient for Stub {
+        fn get(&self, url: &str) -> anyhow::Result<String> {
+            assert_eq!(url, LATEST_RELEASE_URL);
+            self.0.clone().ok_or_else(|| anyhow::anyhow!("offline"))
+        }
+    }
+
+    fn release(tag: &str) -> Stub {
+        Stub(Some(format!(
+            r#"{{"tag_name": "{}", "html_url": "https://example.com/r", "body": "Fixes\r\n\r\n- one\r\n- two", "assets": []}}"#,
+            tag
+        )))
+    }
+
+    #[test]
+    fn test_check() {
+        let found = check(&release("v0.2.0"), "0.1.0").unwrap().unwrap();
+        assert_eq!(found.version, semver::Version::new(0, 2, 0));
+        assert_eq!(found.url, "https://example.com/r");
+        assert_eq!(found.notes, "Fixes\n\n- one\n- two");
+
+        // Older, the same, or a pre-release of the running version
+        for tag in ["0.0.9", "v0.1.0", "0.1.0-beta.1"] {
+            assert_eq!(check(&release(tag), "0.1.0").unwrap(), None, "tag {}", tag);
+        }
+        // Semver order, not string order
+        assert!(check(&release("0.10.0"), "0.9.1").unwrap().is_some());
+
+        assert!(check(&Stub(None), "0.1.0").is_err());
+        assert!(check(&Stub(Some("<html>".to_string())), "0.1.0").is_err());
+        assert!(check(&release("nightly"), "0.1.0").unwrap_err().to_string().contains("(nightly)"));
+    }
+
+    #[test]
+    fn test_is_due() {
+        let week = CHECK_INTERVAL.as_secs();
+        assert!(is_due(None, 1_000));
+        assert!(!is_due(Some(1_000), 1_000 + week - 1));
+        assert!(is_due(Some(1_000), 1_000 + week));
+        // A clock set back doesn't check every start
+        assert!(!is_due(Some(1_000), 500));
+    }
+
+    #[test]
+    fn test_excerpt() {
+        let notes: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        let excerpt = excerpt(&notes);
+        assert_eq!(excerpt.lines().count(), NOTES_LINES + 1);
+        assert!(excerpt.ends_with("line 12\n…"));
+    }
+}
@@ -1,17 +1,33 @@
 //! Utility functions for RMD
 
+pub mod timing;
+pub mod transform;
+pub mod zip;
+
 use std::path::Path;
 
-/// Check if a file is a supported Markdown file
-pub fn is_markdown_file(path: &Path) -> bool {
+/// Extensions recognized as Markdown until the user changes the list
+pub const DEFAULT_MARKDOWN_EXTENSIONS: [&str; 6] = ["md", "markdown", "mdown", "mkd", "mkdn", "mdwn"];
+
+/// Check if a file has one of `extensions`, which are lowercase without dots
+pub fn is_markdown_file(path: &Path, extensions: &[String]) -> bool {
     if let Some(ext) = path.extension() {
         let ext = ext.to_string_lossy().to_lowercase();
-        matches!(ext.as_str(), "md" | "markdown" | "mdown" | "mkd" | "mkdn" | "mdwn")
+        extensions.contains(&ext)
     } else {
         false
     }
 }
 
+/// An extension as typed, `.MDX` or `mdx`, in the stored form `mdx`. Empty
+/// entries and ones that couldn't be a single extension are rejected.
+pub fn normalize_extension(extension: &str) -> Option<String> {
+    let extension = extension.trim().trim_start_matches('.').to_lowercase();
+    let valid = !extension.is_empty()
+        && !extension.contains(|c: char| c.is_whitespace() || matches!(c, '.' | '/' | '\\' | '*' | '?'));
+    valid.then_some(extension)
+}
+
 /// Check if a file is a text file
 pub fn is_text_file(path: &Path) -> bool {
     if let Some(ext) = path.extension() {
@@ -68,6 +84,16 @@ pub fn normalize_line_endings(text: &str) -> String {
     text.replace("\r\n", "\n").replace('\r', "\n")
 }
 
+/// Convert a char index into a byte offset, clamped to the end of the text
+pub fn char_to_byte(text: &str, char_index: usize) -> usize {
+    text.char_indices().nth(char_index).map_or(text.len(), |(i, _)| i)
+}
+
+/// Convert a byte offset into a char index
+pub fn byte_to_char(text: &str, byte_index: usize) -> usize {
+    text.char_indices().take_while(|(i, _)| *i < byte_index).count()
+}
+
 /// Count words in text
 pub fn count_words(text: &str) -> usize {
     text.split_whitespace().count()
@@ -108,6 +134,54 @@ pub fn sanitize_filename(name: &str) -> String {
         .collect()
 }
 
+/// How well `query` fuzzily matches `candidate`, ignoring case, or `None`
+/// when its characters don't all appear in order. Matches at the start of
+/// the candidate or of a word, and consecutive runs, score higher.
+pub fn fuzzy_score(candidate: &str, query: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+
+    for q in query.chars().filter(|c| !c.is_whitespace()).flat_map(char::to_lowercase) {
+        let is_boundary = |i: usize| i == 0 || !candidate[i - 1].is_alphanumeric();
+        let remaining = next..candidate.len();
+        // Prefer continuing a run, then the start of a word, then anything
+        let index = if candidate.get(next) == Some(&q) && previous.is_some() {
+            next
+        } else {
+            remaining
+                .clone()
+                .find(|&i| candidate[i] == q && is_boundary(i))
+                .or_else(|| remaining.clone().find(|&i| candidate[i] == q))?
+        };
+
+        score += 1;
+        if index == 0 {
+            score += 8;
+        } else if is_boundary(index) {
+            score += 6;
+        }
+        if previous.is_some_and(|p| p + 1 == index) {
+            score += 4;
+        }
+        score -= (index - next).min(4) as i32;
+        previous = Some(index);
+        next = index + 1;
+    }
+    Some(score)
+}
+
+/// A new empty directory for a test, named `name` and the process so
+/// concurrent runs don't share it
+#[cfg(test)]
+pub fn test_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("rmd-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -141,6 +215,45 @@ mod tests {
         assert_eq!(count_words(""), 0);
     }
 
+    #[test]
+    fn test_char_byte_conversion() {
+        let text = "aé🚀b";
+        assert_eq!(char_to_byte(text, 2), 3);
+        assert_eq!(char_to_byte(text, 3), 7);
+        assert_eq!(char_to_byte(text, 10), text.len());
+        assert_eq!(byte_to_char(text, 7), 3);
+        assert_eq!(byte_to_char(text, text.len()), 4);
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("Save", ""), Some(0));
+        assert!(fuzzy_score("Open", "po").is_none());
+
+        // Prefix beats word start beats the middle of a word
+        let prefix = fuzzy_score("Save", "s").unwrap();
+        let word_start = fuzzy_score("Toggle Sidebar", "s").unwrap();
+        let middle = fuzzy_score("Redo Last", "s").unwrap();
+        assert!(prefix > word_start && word_start > middle);
+
+        // Consecutive runs and word initials rank well
+        assert!(fuzzy_score("Split", "sp").unwrap() > fuzzy_score("Save Preview", "sp").unwrap());
+        assert!(fuzzy_score("Toggle Split View", "tsv").unwrap() > fuzzy_score("Tests Visible", "tsv").unwrap());
+    }
+
+    #[test]
+    fn test_markdown_extensions() {
+        let extensions = vec!["md".to_string(), "qmd".to_string()];
+        assert!(is_markdown_file(Path::new("notes/Report.QMD"), &extensions));
+        assert!(!is_markdown_file(Path::new("notes/page.mdx"), &extensions));
+        assert!(!is_markdown_file(Path::new("README"), &extensions));
+
+        assert_eq!(normalize_extension(" .MDX "), Some("mdx".to_string()));
+        assert_eq!(normalize_extension("."), None);
+        assert_eq!(normalize_extension("tar.gz"), None);
+        assert_eq!(normalize_extension("*.md"), None);
+    }
+
     #[test]
     fn test_sanitize_filename() {
         assert_eq!(sanitize_filename("hello/world"), "hello_world");
@@ -0,0 +1,167 @@
+//! Timing spans and cache counts for the diagnostics overlay. Nothing is
+//! recorded until the overlay is turned on, so while it is off a span costs
+//! one atomic load.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Samples kept per phase
+const SAMPLES: usize = 120;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static RECORDER: Mutex<Recorder> = Mutex::new(Recorder::new());
+
+/// Part of a frame a span measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// The whole frame
+    Frame,
+    /// Parsing Markdown into preview elements
+    Parse,
+    /// Laying out and drawing the preview
+    PreviewLayout,
+    /// Highlighting the editor's text
+    Highlight,
+    /// Writing the document to disk
+    Save,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 5] = [Phase::Frame, Phase::Parse, Phase::PreviewLayout, Phase::Highlight, Phase::Save];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A cache whose reuse is counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cache {
+    /// Highlighted lines of the editor
+    HighlightLines,
+    /// Rendered top-level blocks of the preview
+    PreviewBlocks,
+}
+
+impl Cache {
+    pub const ALL: [Cache; 2] = [Cache::HighlightLines, Cache::PreviewBlocks];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Hits and misses of a cache, at its last use and in total
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheCounts {
+    pub last_hits: usize,
+    pub last_misses: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// The last samples of each phase, oldest first
+struct Recorder {
+    samples: [VecDeque<Duration>; Phase::ALL.len()],
+    caches: [CacheCounts; Cache::ALL.len()],
+}
+
+impl Recorder {
+    const fn new() -> Self {
+        const EMPTY: VecDeque<Duration> = VecDeque::new();
+        const ZERO: CacheCounts = CacheCounts { last_hits: 0, last_misses: 0, hits: 0, misses: 0 };
+        Self { samples: [EMPTY; Phase::ALL.len()], caches: [ZERO; Cache::ALL.len()] }
+    }
+
+    fn record(&mut self, phase: Phase, duration: Duration) {
+        let samples = &mut self.samples[phase.index()];
+        if samples.len() == SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(duration);
+    }
+
+    fn count(&mut self, cache: Cache, hits: usize, misses: usize) {
+        let counts = &mut self.caches[cache.index()];
+        counts.last_hits = hits;
+        counts.last_misses = misses;
+        counts.hits += hits as u64;
+        counts.misses += misses as u64;
+    }
+}
+
+/// Start or stop recording
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Times `phase` until it is dropped, when recording
+#[must_use = "the span measures until it is dropped"]
+pub struct Span {
+    phase: Phase,
+    started: Instant,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Ok(mut recorder) = RECORDER.lock() {
+            recorder.record(self.phase, self.started.elapsed());
+        }
+    }
+}
+
+/// Time `phase` from now until the span is dropped, or nothing when not
+/// recording
+pub fn span(phase: Phase) -> Option<Span> {
+    is_enabled().then(|| Span { phase, started: Instant::now() })
+}
+
+/// Count `hits` and `misses` of `cache`, when recording
+pub fn count(cache: Cache, hits: usize, misses: usize) {
+    if !is_enabled() {
+        return;
+    }
+    if let Ok(mut recorder) = RECORDER.lock() {
+        recorder.count(cache, hits, misses);
+    }
+}
+
+/// The latest sample of `phase` and the slowest of those kept
+pub fn latest_and_worst(phase: Phase) -> Option<(Duration, Duration)> {
+    let recorder = RECORDER.lock().ok()?;
+    let samples = &recorder.samples[phase.index()];
+    Some((*samples.back()?, *samples.iter().max()?))
+}
+
+pub fn cache_counts(cache: Cache) -> CacheCounts {
+    RECORDER.lock().map(|recorder| recorder.caches[cache.index()]).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recorder_keeps_the_last_samples() {
+        let mut recorder = Recorder::new();
+        for ms in 0..SAMPLES as u64 + 10 {
+            recorder.record(Phase::Parse, Duration::from_millis(ms));
+        }
+        let samples = &recorder.samples[Phase::Parse.index()];
+        assert_eq!(samples.len(), SAMPLES);
+        assert_eq!(samples.front(), Some(&Duration::from_millis(10)));
+        assert!(recorder.samples[Phase::Frame.index()].is_empty());
+
+        recorder.count(Cache::PreviewBlocks, 8, 2);
+        recorder.count(Cache::PreviewBlocks, 9, 1);
+        let counts = recorder.caches[Cache::PreviewBlocks.index()];
+        assert_eq!(counts, CacheCounts { last_hits: 9, last_misses: 1, hits: 17, misses: 3 });
+    }
+}
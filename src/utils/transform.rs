@@ -0,0 +1,152 @@
+//! Text transforms for the Edit → Transform menu. Each maps the selected
+//! text to its replacement; line-based ones treat it as `\n`-separated lines.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+pub fn to_upper(text: &str) -> String {
+    text.to_uppercase()
+}
+
+pub fn to_lower(text: &str) -> String {
+    text.to_lowercase()
+}
+
+/// Capitalize the first letter of each word and lowercase the rest
+pub fn to_title_case(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut word_start = true;
+    for c in text.chars() {
+        if word_start {
+            result.extend(c.to_uppercase());
+        } else {
+            result.extend(c.to_lowercase());
+        }
+        word_start = !(c.is_alphanumeric() || c == '\'' || c == '’');
+    }
+    result
+}
+
+/// Sort lines in natural order, so `item2` comes before `item10`. Equal
+/// lines keep their order.
+fn sort_lines(text: &str, descending: bool) -> String {
+    let mut lines: Vec<&str> = text.split('\n').collect();
+    lines.sort_by(|a, b| {
+        let order = natural_cmp(a, b);
+        if descending {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+    lines.join("\n")
+}
+
+pub fn sort_lines_ascending(text: &str) -> String {
+    sort_lines(text, false)
+}
+
+pub fn sort_lines_descending(text: &str) -> String {
+    sort_lines(text, true)
+}
+
+/// Drop lines that repeat an earlier one
+pub fn remove_duplicate_lines(text: &str) -> String {
+    let mut seen = HashSet::new();
+    text.split('\n').filter(|line| seen.insert(*line)).collect::<Vec<_>>().join("\n")
+}
+
+/// Join lines with single spaces, dropping the indentation of all but the first
+pub fn join_lines(text: &str) -> String {
+    let mut lines = text.split('\n');
+    let mut result = lines.next().unwrap_or_default().trim_end().to_string();
+    for line in lines.map(str::trim).filter(|line| !line.is_empty()) {
+        if !result.is_empty() {
+            result.push(' ');
+        }
+        result.push_str(line);
+    }
+    result
+}
+
+/// Wrap the text in an HTML comment, or unwrap it if it already is one
+pub fn toggle_comment(text: &str) -> String {
+    let trimmed = text.trim();
+    if let Some(inner) = trimmed.strip_prefix("<!--").and_then(|t| t.strip_suffix("-->")) {
+        let start = text.len() - text.trim_start().len();
+        let end = start + trimmed.len();
+        let inner = inner.strip_prefix(' ').unwrap_or(inner);
+        let inner = inner.strip_suffix(' ').unwrap_or(inner);
+        return format!("{}{}{}", &text[..start], inner, &text[end..]);
+    }
+    format!("<!-- {} -->", text)
+}
+
+/// Compare runs of digits by value and everything else case-insensitively,
+/// falling back to a plain comparison to break ties
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_rest, mut b_rest) = (a, b);
+    loop {
+        match (a_rest.chars().next(), b_rest.chars().next()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (a_num, a_tail) = split_digits(a_rest);
+                let (b_num, b_tail) = split_digits(b_rest);
+                let (a_value, b_value) = (a_num.trim_start_matches('0'), b_num.trim_start_matches('0'));
+                let order = a_value.len().cmp(&b_value.len()).then_with(|| a_value.cmp(b_value));
+                if order != Ordering::Equal {
+                    return order;
+                }
+                (a_rest, b_rest) = (a_tail, b_tail);
+            }
+            (Some(x), Some(y)) => {
+                let order = x.to_lowercase().cmp(y.to_lowercase());
+                if order != Ordering::Equal {
+                    return order;
+                }
+                (a_rest, b_rest) = (&a_rest[x.len_utf8()..], &b_rest[y.len_utf8()..]);
+            }
+        }
+    }
+}
+
+fn split_digits(text: &str) -> (&str, &str) {
+    let end = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    text.split_at(end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_transforms() {
+        assert_eq!(to_upper("Straße"), "STRASSE");
+        assert_eq!(to_lower("HeLLo"), "hello");
+        assert_eq!(to_title_case("the QUICK brown-fox's den"), "The Quick Brown-Fox's Den");
+    }
+
+    #[test]
+    fn test_sort_lines_naturally() {
+        assert_eq!(sort_lines_ascending("item10\nItem2\nitem1"), "item1\nItem2\nitem10");
+        assert_eq!(sort_lines_descending("item10\nitem2\nitem1"), "item10\nitem2\nitem1");
+        assert_eq!(sort_lines_ascending("b\na 007\na 7\na 10"), "a 007\na 7\na 10\nb");
+    }
+
+    #[test]
+    fn test_line_transforms() {
+        assert_eq!(remove_duplicate_lines("a\nb\na\nc\nb"), "a\nb\nc");
+        assert_eq!(join_lines("  first\n    second\n\nthird  "), "  first second third");
+        assert_eq!(join_lines("single"), "single");
+    }
+
+    #[test]
+    fn test_toggle_comment() {
+        assert_eq!(toggle_comment("note"), "<!-- note -->");
+        assert_eq!(toggle_comment("<!-- note -->"), "note");
+        assert_eq!(toggle_comment("  <!--a\nb-->"), "  a\nb");
+        assert_eq!(toggle_comment(&toggle_comment("x\ny")), "x\ny");
+    }
+}
@@ -0,0 +1,156 @@
+//! A minimal zip archive writer, enough for the Office formats: entries are
+//! stored uncompressed, which every reader accepts. Archives written this
+//! way can be read back.
+
+/// "DOS" date of 1980-01-01, the earliest a zip entry can have
+const EPOCH_DATE: u16 = (1 << 5) | 1;
+/// Names are UTF-8
+const FLAG_UTF8: u16 = 1 << 11;
+const VERSION: u16 = 20;
+
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Files added one after another, then `finish`ed into the archive's bytes
+#[derive(Default)]
+pub struct ZipWriter {
+    data: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+impl ZipWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a file at `name`, a path with `/` separators
+    pub fn add(&mut self, name: &str, contents: &[u8]) {
+        let entry = Entry {
+            name: name.to_string(),
+            crc: crc32fast::hash(contents),
+            size: contents.len() as u32,
+            offset: self.data.len() as u32,
+        };
+        let data = &mut self.data;
+        put32(data, 0x0403_4b50);
+        put16(data, VERSION);
+        put16(data, FLAG_UTF8);
+        put16(data, 0); // Stored
+        put16(data, 0);
+        put16(data, EPOCH_DATE);
+        put32(data, entry.crc);
+        put32(data, entry.size);
+        put32(data, entry.size);
+        put16(data, name.len() as u16);
+        put16(data, 0);
+        data.extend_from_slice(name.as_bytes());
+        data.extend_from_slice(contents);
+        self.entries.push(entry);
+    }
+
+    /// The archive, with its central directory after the files
+    pub fn finish(mut self) -> Vec<u8> {
+        let directory_start = self.data.len() as u32;
+        let data = &mut self.data;
+        for entry in &self.entries {
+            put32(data, 0x0201_4b50);
+            put16(data, VERSION);
+            put16(data, VERSION);
+            put16(data, FLAG_UTF8);
+            put16(data, 0);
+            put16(data, 0);
+            put16(data, EPOCH_DATE);
+            put32(data, entry.crc);
+            put32(data, entry.size);
+            put32(data, entry.size);
+            put16(data, entry.name.len() as u16);
+            put16(data, 0); // Extra field
+            put16(data, 0); // Comment
+            put16(data, 0); // Disk
+            put16(data, 0); // Internal attributes
+            put32(data, 0); // External attributes
+            put32(data, entry.offset);
+            data.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = data.len() as u32 - directory_start;
+        put32(data, 0x0605_4b50);
+        put16(data, 0);
+        put16(data, 0);
+        put16(data, self.entries.len() as u16);
+        put16(data, self.entries.len() as u16);
+        put32(data, directory_size);
+        put32(data, directory_start);
+        put16(data, 0);
+        self.data
+    }
+}
+
+fn put16(data: &mut Vec<u8>, value: u16) {
+    data.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put32(data: &mut Vec<u8>, value: u32) {
+    data.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Files of an archive whose entries are stored uncompressed, as
+/// `ZipWriter` writes them, read through the central directory with each
+/// checksum verified
+pub fn read(archive: &[u8]) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    let bytes = |at: usize, len: usize| archive.get(at..at + len).ok_or_else(|| anyhow::anyhow!("The archive is cut short"));
+    let u16_at = |at: usize| Ok::<_, anyhow::Error>(u16::from_le_bytes(bytes(at, 2)?.try_into()?) as usize);
+    let u32_at = |at: usize| Ok::<_, anyhow::Error>(u32::from_le_bytes(bytes(at, 4)?.try_into()?) as usize);
+    let end = archive.len().checked_sub(22).ok_or_else(|| anyhow::anyhow!("Not a zip archive"))?;
+    anyhow::ensure!(u32_at(end)? == 0x0605_4b50, "Not a zip archive, or one with a comment");
+    let mut at = u32_at(end + 16)?;
+    let mut files = Vec::new();
+    for _ in 0..u16_at(end + 10)? {
+        anyhow::ensure!(u32_at(at)? == 0x0201_4b50, "The archive's directory is damaged");
+        let (method, crc, size, name_len) = (u16_at(at + 10)?, u32_at(at + 16)?, u32_at(at + 24)?, u16_at(at + 28)?);
+        let (extra_len, comment_len, offset) = (u16_at(at + 30)?, u16_at(at + 32)?, u32_at(at + 42)?);
+        let name = String::from_utf8_lossy(bytes(at + 46, name_len)?).to_string();
+        anyhow::ensure!(method == 0, "{} is compressed, and only stored files can be read", name);
+        let start = offset + 30 + u16_at(offset + 26)? + u16_at(offset + 28)?;
+        let contents = bytes(start, size)?.to_vec();
+        anyhow::ensure!(crc32fast::hash(&contents) as usize == crc, "{} is damaged", name);
+        files.push((name, contents));
+        at += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(files)
+}
+
+/// Files of an archive `ZipWriter` made
+#[cfg(test)]
+pub fn read_stored(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+    read(archive).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut zip = ZipWriter::new();
+        zip.add("[Content_Types].xml", b"<Types/>");
+        zip.add("word/média.txt", b"");
+        let files = read_stored(&zip.finish());
+        assert_eq!(
+            files,
+            [("[Content_Types].xml".to_string(), b"<Types/>".to_vec()), ("word/média.txt".to_string(), Vec::new())]
+        );
+
+        let mut archive = {
+            let mut zip = ZipWriter::new();
+            zip.add("a.txt", b"contents");
+            zip.finish()
+        };
+        assert!(read(&archive[..archive.len() - 1]).is_err());
+        archive[38] ^= 1;
+        assert!(read(&archive).unwrap_err().to_string().contains("a.txt is damaged"));
+    }
+}
@@ -0,0 +1,411 @@
+//! Exporting a folder of Markdown documents as a static HTML site
+
+use super::links::normalize;
+use crate::markdown::export::{self, Destination, HtmlOptions, Stylesheet};
+use crate::markdown::overrides::DocumentSettings;
+use crate::markdown::{flavor, include, outline};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+/// Folder in the output for images that live outside the exported folder
+const OUTSIDE_ASSETS: &str = "_assets";
+
+/// Name of the page listing every document, unless the folder has its own
+/// `index.md`, in which case the listing becomes `CONTENTS_PAGE`
+const INDEX_PAGE: &str = "index.html";
+const CONTENTS_PAGE: &str = "contents.html";
+
+/// Outcome of an export
+#[derive(Debug, Default)]
+pub struct ExportSummary {
+    pub output: PathBuf,
+    pub exported: usize,
+    /// Documents that could not be exported, with the reason
+    pub failed: Vec<(PathBuf, String)>,
+    pub cancelled: bool,
+}
+
+/// Point a relative link to a file with one of the Markdown `extensions` at
+/// the exported page instead, keeping any `#fragment` or `?query`
+pub fn rewrite_link(url: &str, extensions: &[String]) -> String {
+    if !is_relative(url) {
+        return url.to_string();
+    }
+    let (path, suffix) = split_url(url);
+    if !crate::utils::is_markdown_file(Path::new(path), extensions) {
+        return url.to_string();
+    }
+    let stem = path.rfind('.').map_or(path, |dot| &path[..dot]);
+    format!("{}.html{}", stem, suffix)
+}
+
+/// Local images referenced by the exported documents, and where each is
+/// copied in the output
+#[derive(Default)]
+pub struct Assets {
+    /// Source file to its path relative to the output folder
+    targets: HashMap<PathBuf, PathBuf>,
+    taken: HashSet<PathBuf>,
+}
+
+impl Assets {
+    /// Resolve an image `url` in `document` (relative to `root`) to a local
+    /// file. Returns the file and the URL the page should use, or `None`
+    /// for remote or missing images. Images inside `root` keep their
+    /// relative place; others are gathered in `_assets`.
+    pub fn resolve(&mut self, root: &Path, document: &Path, url: &str) -> Option<(PathBuf, String)> {
+        if !is_relative(url) {
+            return None;
+        }
+        let (path, _) = split_url(url);
+        let doc_dir = document.parent().unwrap_or(Path::new(""));
+        let source = normalize(&root.join(doc_dir).join(path.replace("%20", " ")));
+        if !source.is_file() {
+            return None;
+        }
+
+        if let Some(target) = self.targets.get(&source) {
+            return Some((source.clone(), relative_url(doc_dir, target)));
+        }
+        let target = match source.strip_prefix(root) {
+            Ok(inside) => inside.to_path_buf(),
+            Err(_) => self.outside_target(&source),
+        };
+        let new_url = if target.starts_with(OUTSIDE_ASSETS) {
+            relative_url(doc_dir, &target)
+        } else {
+            url.to_string()
+        };
+        self.taken.insert(target.clone());
+        self.targets.insert(source.clone(), target);
+        Some((source, new_url))
+    }
+
+    /// Files to copy, as (source, path relative to the output folder)
+    pub fn files(&self) -> impl Iterator<Item = (&PathBuf, &PathBuf)> {
+        self.targets.iter()
+    }
+
+    /// A free `_assets/<name>` path, numbered when another image took the name
+    fn outside_target(&self, source: &Path) -> PathBuf {
+        let name = source.file_name().map(Path::new).unwrap_or(Path::new("image"));
+        let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+        let mut target = Path::new(OUTSIDE_ASSETS).join(name);
+        let mut n = 1;
+        while self.taken.contains(&target) {
+            let numbered = match name.extension() {
+                Some(ext) => format!("{}-{}.{}", stem, n, ext.to_string_lossy()),
+                None => format!("{}-{}", stem, n),
+            };
+            target = Path::new(OUTSIDE_ASSETS).join(numbered);
+            n += 1;
+        }
+        target
+    }
+}
+
+/// Export every file with one of the Markdown `extensions` under `root` into
+/// `output`, mirroring the folder structure, followed by a page listing all
+/// documents. Every page has `css` in it. `progress` is told how many
+/// documents are done out of the total.
+pub fn export_folder(
+    root: &Path,
+    output: &Path,
+    options: HtmlOptions,
+    css: &str,
+    extensions: &[String],
+    cancel: &AtomicBool,
+    mut progress: impl FnMut(usize, usize),
+) -> ExportSummary {
+    let documents: Vec<PathBuf> = super::markdown_files(root, extensions)
+        .filter(|path| !path.starts_with(output))
+        .collect();
+    let mut summary = ExportSummary {
+        output: output.to_path_buf(),
+        ..ExportSummary::default()
+    };
+    let mut assets = Assets::default();
+    let mut index = Vec::new();
+    progress(0, documents.len());
+
+    for (done, path) in documents.iter().enumerate() {
+        if cancel.load(Ordering::Relaxed) {
+            summary.cancelled = true;
+            return summary;
+        }
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        match export_document(root, output, relative, options, css, extensions, &mut assets) {
+            Ok(title) => {
+                summary.exported += 1;
+                index.push((relative.with_extension("html"), title));
+            }
+            Err(e) => summary.failed.push((path.clone(), e.to_string())),
+        }
+        progress(done + 1, documents.len());
+    }
+
+    for (source, target) in assets.files() {
+        let target = output.join(target);
+        let copied = target
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::copy(source, &target));
+        if let Err(e) = copied {
+            summary.failed.push((source.clone(), e.to_string()));
+        }
+    }
+
+    let title = root.file_name().map_or("Documents".into(), |n| n.to_string_lossy());
+    let listing = if index.iter().any(|(page, _)| page == Path::new(INDEX_PAGE)) {
+        output.join(CONTENTS_PAGE)
+    } else {
+        output.join(INDEX_PAGE)
+    };
+    if let Err(e) = std::fs::write(&listing, index_page(&title, css, &index)) {
+        summary.failed.push((listing, e.to_string()));
+    }
+    summary
+}
+
+/// Write the page for `relative` (a path under `root`), returning its title
+fn export_document(
+    root: &Path,
+    output: &Path,
+    relative: &Path,
+    options: HtmlOptions,
+    css: &str,
+    extensions: &[String],
+    assets: &mut Assets,
+) -> anyhow::Result<String> {
+    let document = root.join(relative);
+    let markdown = std::fs::read_to_string(&document)?;
+    let lang = DocumentSettings::parse(&markdown).lang;
+    let wiki = flavor::front_matter_flavor(&markdown).unwrap_or(options.flavor).wiki_links();
+    let markdown = include::expand(&markdown, Some(&document), wiki);
+    let body = export::markdown_to_html(&markdown, options, |kind, url| match kind {
+        Destination::Link => rewrite_link(url, extensions),
+        Destination::Image => assets
+            .resolve(root, relative, url)
+            .map_or_else(|| url.to_string(), |(_, url)| url),
+    });
+    let title = outline::headings(&markdown)
+        .into_iter()
+        .next()
+        .map(|heading| heading.text)
+        .unwrap_or_else(|| relative.file_stem().unwrap_or_default().to_string_lossy().to_string());
+
+    let target = output.join(relative).with_extension("html");
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(target, export::html_document(&title, lang.as_deref(), Stylesheet::Inline(css), &body))?;
+    Ok(title)
+}
+
+/// Page listing the documents, grouped by the folder they are in
+fn index_page(title: &str, css: &str, documents: &[(PathBuf, String)]) -> String {
+    let mut folders: BTreeMap<PathBuf, Vec<(&PathBuf, &String)>> = BTreeMap::new();
+    for (page, page_title) in documents {
+        let folder = page.parent().unwrap_or(Path::new("")).to_path_buf();
+        folders.entry(folder).or_default().push((page, page_title));
+    }
+
+    let mut body = format!("<h1>{}</h1>\n", export::escape_html(title));
+    for (folder, pages) in folders {
+        if !folder.as_os_str().is_empty() {
+            body.push_str(&format!("<h2>{}</h2>\n", export::escape_html(&url_path(&folder))));
+        }
+        body.push_str("<ul>\n");
+        for (page, page_title) in pages {
+            body.push_str(&format!(
+                "<li><a href=\"{}\">{}</a></li>\n",
+                export::escape_html(&url_path(page).replace(' ', "%20")),
+                export::escape_html(page_title)
+            ));
+        }
+        body.push_str("</ul>\n");
+    }
+    export::html_document(title, None, Stylesheet::Inline(css), &body)
+}
+
+/// URL from a page in `from_dir` to `target`, both relative to the output
+fn relative_url(from_dir: &Path, target: &Path) -> String {
+    let depth = from_dir.components().count();
+    format!("{}{}", "../".repeat(depth), url_path(target).replace(' ', "%20"))
+}
+
+/// A relative path with `/` separators
+fn url_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Not a web address, absolute path, or in-page anchor
+fn is_relative(url: &str) -> bool {
+    !url.is_empty() && !url.contains(':') && !url.starts_with(['/', '\\', '#'])
+}
+
+/// Split a URL into its path and its `?query#fragment`
+fn split_url(url: &str) -> (&str, &str) {
+    url.split_at(url.find(['#', '?']).unwrap_or(url.len()))
+}
+
+/// Progress reported by the export thread
+enum ExportMessage {
+    Progress { done: usize, total: usize },
+    Finished(ExportSummary),
+}
+
+/// An export running on a background thread
+pub struct FolderExport {
+    receiver: Receiver<ExportMessage>,
+    cancel: Arc<AtomicBool>,
+    pub done: usize,
+    pub total: usize,
+    /// Set once the export has stopped
+    pub summary: Option<ExportSummary>,
+}
+
+impl FolderExport {
+    pub fn start(root: &Path, output: &Path, options: HtmlOptions, css: String, extensions: &[String]) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancelled = Arc::clone(&cancel);
+        let (root, output) = (root.to_path_buf(), output.to_path_buf());
+        let extensions = extensions.to_vec();
+        std::thread::spawn(move || {
+            let summary = export_folder(&root, &output, options, &css, &extensions, &cancelled, |done, total| {
+                let _ = sender.send(ExportMessage::Progress { done, total });
+            });
+            let _ = sender.send(ExportMessage::Finished(summary));
+        });
+        Self {
+            receiver,
+            cancel,
+            done: 0,
+            total: 0,
+            summary: None,
+        }
+    }
+
+    /// Stop after the document being exported
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Collect progress sent by the export thread since the last frame
+    pub fn poll(&mut self) {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(ExportMessage::Progress { done, total }) => (self.done, self.total) = (done, total),
+                Ok(ExportMessage::Finished(summary)) => self.summary = Some(summary),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if self.summary.is_none() {
+                        self.summary = Some(ExportSummary {
+                            failed: vec![(PathBuf::new(), "Export stopped unexpectedly".to_string())],
+                            ..ExportSummary::default()
+                        });
+                    }
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.summary.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::markdown::flavor::Flavor;
+    use crate::utils::test_dir;
+
+    #[test]
+    fn test_rewrite_link() {
+        let extensions = Config::default().markdown_extensions;
+        assert_eq!(rewrite_link("notes.md", &extensions), "notes.html");
+        assert_eq!(rewrite_link("../a/b.markdown#setup", &extensions), "../a/b.html#setup");
+        assert_eq!(rewrite_link("my%20note.md?x=1", &extensions), "my%20note.html?x=1");
+        assert_eq!(rewrite_link("https://example.com/readme.md", &extensions), "https://example.com/readme.md");
+        assert_eq!(rewrite_link("#intro", &extensions), "#intro");
+        assert_eq!(rewrite_link("image.png", &extensions), "image.png");
+        assert_eq!(rewrite_link("slides.qmd", &["qmd".to_string()]), "slides.html");
+    }
+
+    #[test]
+    fn test_resolve_assets() {
+        let dir = test_dir("export-assets");
+        let root = dir.join("notes");
+        std::fs::create_dir_all(root.join("img")).unwrap();
+        std::fs::create_dir_all(root.join("deep/er")).unwrap();
+        std::fs::create_dir_all(dir.join("shared")).unwrap();
+        std::fs::write(root.join("img/a.png"), "a").unwrap();
+        std::fs::write(dir.join("shared/a.png"), "outside").unwrap();
+
+        let mut assets = Assets::default();
+        let doc = Path::new("deep/er/page.md");
+        // Inside the folder: the URL is unchanged and the image keeps its place
+        let (source, url) = assets.resolve(&root, doc, "../../img/a.png").unwrap();
+        assert_eq!(source, root.join("img/a.png"));
+        assert_eq!(url, "../../img/a.png");
+        // Outside the folder: gathered under _assets and linked relative to the page
+        let (source, url) = assets.resolve(&root, doc, "../../../shared/a.png").unwrap();
+        assert_eq!(source, dir.join("shared/a.png"));
+        assert_eq!(url, "../../_assets/a.png");
+        let (_, url) = assets.resolve(&root, Path::new("top.md"), "../shared/a.png").unwrap();
+        assert_eq!(url, "_assets/a.png");
+
+        assert!(assets.resolve(&root, doc, "missing.png").is_none());
+        assert!(assets.resolve(&root, doc, "https://example.com/a.png").is_none());
+        assert_eq!(assets.files().count(), 2);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_folder() {
+        let dir = test_dir("export-folder");
+        let root = dir.join("notes");
+        let output = dir.join("site");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("home.md"), "# Home\n\n[Child](sub/child.md#part)\n").unwrap();
+        std::fs::write(root.join("sub/child.md"), "Back [home](../home.md) ![pic](../pic.png)\n").unwrap();
+        std::fs::write(root.join("pic.png"), "png").unwrap();
+
+        let options = HtmlOptions { flavor: Flavor::Gfm, ..HtmlOptions::default() };
+        let extensions = Config::default().markdown_extensions;
+        let mut reports = Vec::new();
+        let summary = export_folder(&root, &output, options, "body{}", &extensions, &AtomicBool::new(false), |done, total| {
+            reports.push((done, total))
+        });
+        assert_eq!(summary.exported, 2);
+        assert!(summary.failed.is_empty(), "{:?}", summary.failed);
+        assert_eq!(reports, vec![(0, 2), (1, 2), (2, 2)]);
+
+        let index = std::fs::read_to_string(output.join("index.html")).unwrap();
+        assert!(index.contains("<a href=\"home.html\">Home</a>"));
+        assert!(index.contains("<h2>sub</h2>\n<ul>\n<li><a href=\"sub/child.html\">child</a></li>"));
+        let home = std::fs::read_to_string(output.join("home.html")).unwrap();
+        assert!(home.contains("href=\"sub/child.html#part\""));
+        assert!(home.contains("<style>body{}</style>"));
+        let child = std::fs::read_to_string(output.join("sub/child.html")).unwrap();
+        assert!(child.contains("href=\"../home.html\""));
+        assert!(child.contains("src=\"../pic.png\""));
+        assert!(output.join("pic.png").is_file());
+
+        let cancelled = export_folder(&root, &output, options, "", &extensions, &AtomicBool::new(true), |_, _| {});
+        assert!(cancelled.cancelled);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,191 @@
+//! File management for the sidebar tree: listing, create, rename, delete
+
+use crate::utils;
+use std::path::{Path, PathBuf};
+
+/// A file or folder shown in the sidebar tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileNode {
+    pub path: PathBuf,
+    pub name: String,
+    /// Children of a folder, folders first; `None` for files
+    pub children: Option<Vec<FileNode>>,
+}
+
+impl FileNode {
+    pub fn is_dir(&self) -> bool {
+        self.children.is_some()
+    }
+}
+
+/// List the files with one of the Markdown `extensions` and the non-hidden
+/// folders under `dir`
+pub fn file_tree(dir: &Path, extensions: &[String]) -> Vec<FileNode> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut nodes: Vec<FileNode> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| !utils::is_hidden_file(path))
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().to_string();
+            if path.is_dir() {
+                let children = Some(file_tree(&path, extensions));
+                Some(FileNode { path, name, children })
+            } else if utils::is_markdown_file(&path, extensions) {
+                Some(FileNode { path, name, children: None })
+            } else {
+                None
+            }
+        })
+        .collect();
+    nodes.sort_by(|a, b| {
+        b.is_dir()
+            .cmp(&a.is_dir())
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    nodes
+}
+
+/// First of `name`, `stem-1.ext`, `stem-2.ext`, ... that does not exist in `dir`
+pub fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let candidate = dir.join(name);
+    if !candidate.exists() {
+        return candidate;
+    }
+
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (name, String::new()),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{}-{}{}", stem, n, ext)))
+        .find(|path| !path.exists())
+        .unwrap_or(candidate)
+}
+
+/// Create an empty `untitled.md` (or `untitled-N.md`) in `dir`
+pub fn create_file(dir: &Path) -> anyhow::Result<PathBuf> {
+    let path = unique_path(dir, "untitled.md");
+    std::fs::write(&path, "")?;
+    Ok(path)
+}
+
+/// Create a `New Folder` (or `New Folder-N`) directory in `dir`
+pub fn create_folder(dir: &Path) -> anyhow::Result<PathBuf> {
+    let path = unique_path(dir, "New Folder");
+    std::fs::create_dir(&path)?;
+    Ok(path)
+}
+
+/// Rename a file or folder in place, returning its new path
+pub fn rename(path: &Path, new_name: &str) -> anyhow::Result<PathBuf> {
+    let name = utils::sanitize_filename(new_name.trim());
+    if name.is_empty() || name == "." || name == ".." {
+        anyhow::bail!("\"{}\" is not a valid name", new_name.trim());
+    }
+
+    let parent = path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Cannot rename {}", path.display()))?;
+    let target = parent.join(&name);
+    if target == path {
+        return Ok(target);
+    }
+    // Changing only the case of a name finds the file itself on
+    // case-insensitive file systems; anything else there is another file
+    if target.exists() && std::fs::canonicalize(&target)? != std::fs::canonicalize(path)? {
+        anyhow::bail!("\"{}\" already exists", name);
+    }
+    std::fs::rename(path, &target)?;
+    Ok(target)
+}
+
+/// Move a file or folder to the OS recycle bin
+pub fn delete(path: &Path) -> anyhow::Result<()> {
+    trash::delete(path)?;
+    Ok(())
+}
+
+/// Where `path` ends up after `from` is renamed to `to`, if it is affected
+pub fn renamed_path(path: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    let rest = path.strip_prefix(from).ok()?;
+    Some(if rest.as_os_str().is_empty() {
+        to.to_path_buf()
+    } else {
+        to.join(rest)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_dir;
+
+    #[test]
+    fn test_unique_path() {
+        let dir = test_dir("files-unique");
+        assert_eq!(unique_path(&dir, "cat.png"), dir.join("cat.png"));
+        std::fs::write(dir.join("cat.png"), "").unwrap();
+        std::fs::write(dir.join("cat-1.png"), "").unwrap();
+        assert_eq!(unique_path(&dir, "cat.png"), dir.join("cat-2.png"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_create_and_rename() {
+        let dir = test_dir("files-rename");
+        let first = create_file(&dir).unwrap();
+        let second = create_file(&dir).unwrap();
+        assert_eq!(first, dir.join("untitled.md"));
+        assert_eq!(second, dir.join("untitled-1.md"));
+
+        // Taken names are rejected and invalid characters sanitized
+        assert!(rename(&second, "untitled.md").is_err());
+        assert!(rename(&second, "  ").is_err());
+        let renamed = rename(&second, "a:b.md").unwrap();
+        assert_eq!(renamed, dir.join("a_b.md"));
+        assert!(renamed.exists() && !second.exists());
+
+        let folder = create_folder(&dir).unwrap();
+        assert_eq!(folder.file_name().unwrap(), "New Folder");
+        let extensions = vec!["md".to_string()];
+        let tree = file_tree(&dir, &extensions);
+        let names: Vec<_> = tree.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, vec!["New Folder", "a_b.md", "untitled.md"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rename_case_only() {
+        let dir = test_dir("files-rename-case");
+        let readme = dir.join("Readme.md");
+        std::fs::write(&readme, "readme").unwrap();
+        assert_eq!(rename(&readme, "README.md").unwrap(), dir.join("README.md"));
+        assert_eq!(std::fs::read_to_string(dir.join("README.md")).unwrap(), "readme");
+
+        // Two files told apart only by case are not renamed over each other
+        let upper = dir.join("Notes.md");
+        let lower = dir.join("notes.md");
+        std::fs::write(&upper, "upper").unwrap();
+        std::fs::write(&lower, "lower").unwrap();
+        if std::fs::read_to_string(&upper).unwrap() == "upper" {
+            assert!(rename(&upper, "notes.md").is_err());
+            assert_eq!(std::fs::read_to_string(&lower).unwrap(), "lower");
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_renamed_path() {
+        let from = Path::new("/notes/old");
+        let to = Path::new("/notes/new");
+        assert_eq!(renamed_path(Path::new("/notes/old/a.md"), from, to), Some(to.join("a.md")));
+        assert_eq!(renamed_path(from, from, to), Some(to.to_path_buf()));
+        assert_eq!(renamed_path(Path::new("/notes/older.md"), from, to), None);
+    }
+}
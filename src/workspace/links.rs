@@ -0,0 +1,206 @@
+//! Links between workspace documents and the backlink index built from them
+
+use crate::markdown::rename::LinkTarget;
+use crate::markdown::wiki::{self, WIKI_SCHEME};
+use crate::utils;
+use pulldown_cmark::{Event, Parser, Tag};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+/// Files larger than this are not indexed
+const MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+
+/// Lowercased file stem of every Markdown file, for resolving wiki links
+pub type NoteNames = HashMap<String, PathBuf>;
+
+/// Map each file with one of the Markdown `extensions` under `root` by its
+/// lowercased file stem
+pub fn note_names(root: &Path, extensions: &[String]) -> NoteNames {
+    let mut names = NoteNames::new();
+    for path in super::markdown_files(root, extensions) {
+        if let Some(stem) = path.file_stem() {
+            names.entry(stem.to_string_lossy().to_lowercase()).or_insert(path);
+        }
+    }
+    names
+}
+
+/// File a wiki link target refers to: a Markdown file of that name anywhere
+/// in the workspace, or `<root>/<target>.md` if there is none yet
+pub fn resolve_wiki_target(root: &Path, names: &NoteNames, target: &str) -> PathBuf {
+    let note = target.split('#').next().unwrap_or_default().trim();
+    names
+        .get(&note.to_lowercase())
+        .cloned()
+        .unwrap_or_else(|| root.join(format!("{}.md", utils::sanitize_filename(note))))
+}
+
+/// Documents that `text` (the contents of `source`) links to, through wiki
+/// links or relative links to files with one of the Markdown `extensions`
+pub fn link_targets(text: &str, source: &Path, names: &NoteNames, extensions: &[String]) -> Vec<PathBuf> {
+    let base = source.parent().unwrap_or(Path::new(""));
+    let expanded = wiki::expand_wiki_links(text);
+    let mut targets: Vec<PathBuf> = Parser::new(&expanded)
+        .filter_map(|event| match event {
+            Event::Start(Tag::Link { dest_url, .. }) => Some(dest_url.to_string()),
+            _ => None,
+        })
+        .filter_map(|url| {
+            if let Some(target) = url.strip_prefix(WIKI_SCHEME) {
+                let note = target.split('#').next().unwrap_or_default().trim();
+                return names.get(&note.to_lowercase()).cloned();
+            }
+            if url.contains(':') || url.starts_with('#') {
+                return None;
+            }
+            let path = url.split(['#', '?']).next().unwrap_or_default().replace("%20", " ");
+            let path = normalize(&base.join(path));
+            utils::is_markdown_file(&path, extensions).then_some(path)
+        })
+        .filter(|target| target != source)
+        .collect();
+    targets.sort();
+    targets.dedup();
+    targets
+}
+
+/// Whether a link in `source` to `link` points at the document `target`
+pub fn points_to(source: &Path, target: &Path, link: LinkTarget) -> bool {
+    match link {
+        LinkTarget::Url(url) => {
+            if url.is_empty() || url.contains(':') {
+                return url.is_empty() && source == target;
+            }
+            let base = source.parent().unwrap_or(Path::new(""));
+            let path = url.split('?').next().unwrap_or_default().replace("%20", " ");
+            normalize(&base.join(path)) == target
+        }
+        LinkTarget::Wiki("") => source == target,
+        LinkTarget::Wiki(note) => target
+            .file_stem()
+            .is_some_and(|stem| stem.to_string_lossy().to_lowercase() == note.trim().to_lowercase()),
+    }
+}
+
+/// Resolve `.` and `..` components without touching the file system
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Which workspace documents link to which, rebuilt in the background
+#[derive(Default)]
+pub struct BacklinkIndex {
+    backlinks: HashMap<PathBuf, Vec<PathBuf>>,
+    pending: Option<Receiver<HashMap<PathBuf, Vec<PathBuf>>>>,
+}
+
+impl BacklinkIndex {
+    /// Re-scan every file with one of the Markdown `extensions` under
+    /// `root` on a background thread
+    pub fn rebuild(&mut self, root: &Path, extensions: &[String]) {
+        let (sender, receiver) = mpsc::channel();
+        let root = root.to_path_buf();
+        let extensions = extensions.to_vec();
+        std::thread::spawn(move || {
+            let names = note_names(&root, &extensions);
+            let mut backlinks: HashMap<PathBuf, Vec<PathBuf>> = HashMap::new();
+            for source in names.values() {
+                let too_large = std::fs::metadata(source).map_or(true, |m| m.len() > MAX_FILE_SIZE);
+                if too_large {
+                    continue;
+                }
+                let Ok(text) = std::fs::read_to_string(source) else {
+                    continue;
+                };
+                for target in link_targets(&text, source, &names, &extensions) {
+                    backlinks.entry(target).or_default().push(source.clone());
+                }
+            }
+            for sources in backlinks.values_mut() {
+                sources.sort();
+            }
+            let _ = sender.send(backlinks);
+        });
+        self.pending = Some(receiver);
+    }
+
+    /// Pick up the result of a finished rebuild
+    pub fn poll(&mut self) {
+        let Some(receiver) = &self.pending else {
+            return;
+        };
+        match receiver.try_recv() {
+            Ok(backlinks) => {
+                self.backlinks = backlinks;
+                self.pending = None;
+            }
+            Err(TryRecvError::Disconnected) => self.pending = None,
+            Err(TryRecvError::Empty) => {}
+        }
+    }
+
+    pub fn is_building(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Documents that link to `path`
+    pub fn backlinks_to(&self, path: &Path) -> &[PathBuf] {
+        self.backlinks.get(path).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_targets() {
+        let root = Path::new("/notes");
+        let names: NoteNames = [
+            ("note name".to_string(), root.join("Note Name.md")),
+            ("setup".to_string(), root.join("guides/Setup.md")),
+        ]
+        .into_iter()
+        .collect();
+
+        let text = "[[Note Name]] [[setup#install|Setup]] [[Missing]]\n\
+                    [up](../other.md#top) [web](https://x.y/a.md) [self](./journal.md)\n\
+                    `[[Note Name]]` [img](pic.png)";
+        let extensions = vec!["md".to_string()];
+        let targets = link_targets(text, &root.join("daily/journal.md"), &names, &extensions);
+        assert_eq!(
+            targets,
+            vec![root.join("Note Name.md"), root.join("guides/Setup.md"), root.join("other.md")]
+        );
+    }
+
+    #[test]
+    fn test_points_to() {
+        let target = Path::new("/notes/guides/Setup.md");
+        let source = Path::new("/notes/daily/journal.md");
+        assert!(points_to(source, target, LinkTarget::Url("../guides/Setup.md")));
+        assert!(points_to(source, target, LinkTarget::Wiki("setup")));
+        assert!(!points_to(source, target, LinkTarget::Url("")));
+        assert!(!points_to(source, target, LinkTarget::Url("https://x.y/guides/Setup.md")));
+        assert!(points_to(target, target, LinkTarget::Wiki("")));
+    }
+
+    #[test]
+    fn test_resolve_wiki_target() {
+        let root = Path::new("/notes");
+        let names: NoteNames = [("setup".to_string(), root.join("guides/Setup.md"))].into_iter().collect();
+        assert_eq!(resolve_wiki_target(root, &names, "SETUP#x"), root.join("guides/Setup.md"));
+        assert_eq!(resolve_wiki_target(root, &names, "New Note"), root.join("New Note.md"));
+    }
+}
@@ -0,0 +1,23 @@
+//! A folder of Markdown documents opened in the sidebar
+
+pub mod export;
+pub mod files;
+pub mod links;
+pub mod search;
+pub mod state;
+
+use crate::utils;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Files under `root` with one of the Markdown `extensions`, skipping
+/// hidden files and folders
+pub fn markdown_files<'a>(root: &Path, extensions: &'a [String]) -> impl Iterator<Item = PathBuf> + 'a {
+    WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !utils::is_hidden_file(entry.path()))
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && utils::is_markdown_file(entry.path(), extensions))
+        .map(walkdir::DirEntry::into_path)
+}
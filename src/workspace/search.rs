@@ -0,0 +1,206 @@
+//! Background search across every Markdown file in the workspace
+
+use crate::editor::search::{Matcher, SearchOptions};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+/// Files larger than this are skipped
+const MAX_FILE_SIZE: u64 = 2 * 1024 * 1024;
+
+/// Stop collecting matches in a file after this many lines
+const MAX_LINES_PER_FILE: usize = 200;
+
+/// A line containing at least one match
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineMatch {
+    /// Zero-based line number
+    pub line: usize,
+    pub text: String,
+    /// Byte ranges of the matches within `text`
+    pub ranges: Vec<Range<usize>>,
+}
+
+/// All matching lines of one file
+#[derive(Debug, Clone)]
+pub struct FileMatches {
+    pub path: PathBuf,
+    pub lines: Vec<LineMatch>,
+}
+
+/// Find the matching lines of a document
+pub fn search_text(text: &str, matcher: &Matcher) -> Vec<LineMatch> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(line, content)| {
+            let ranges = matcher.find_all(content);
+            (!ranges.is_empty()).then(|| LineMatch {
+                line,
+                text: content.to_string(),
+                ranges,
+            })
+        })
+        .take(MAX_LINES_PER_FILE)
+        .collect()
+}
+
+struct RunningSearch {
+    receiver: Receiver<FileMatches>,
+    cancel: Arc<AtomicBool>,
+}
+
+/// State of the workspace search panel: the query and streamed results
+#[derive(Default)]
+pub struct WorkspaceSearch {
+    pub query: String,
+    pub options: SearchOptions,
+    results: Vec<FileMatches>,
+    error: Option<String>,
+    running: Option<RunningSearch>,
+}
+
+impl WorkspaceSearch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Search every file with one of the Markdown `extensions` under `root`
+    /// on a background thread, cancelling any search still in progress
+    pub fn start(&mut self, root: &Path, extensions: &[String]) {
+        self.cancel();
+        self.results.clear();
+        self.error = None;
+        if self.query.is_empty() {
+            return;
+        }
+
+        let matcher = match Matcher::new(&self.query, self.options) {
+            Ok(matcher) => matcher,
+            Err(e) => {
+                self.error = Some(e.to_string());
+                return;
+            }
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let cancelled = Arc::clone(&cancel);
+        let root = root.to_path_buf();
+        let extensions = extensions.to_vec();
+        std::thread::spawn(move || {
+            for path in super::markdown_files(&root, &extensions) {
+                if cancelled.load(Ordering::Relaxed) {
+                    return;
+                }
+                let too_large = std::fs::metadata(&path).map_or(true, |m| m.len() > MAX_FILE_SIZE);
+                if too_large {
+                    continue;
+                }
+                // Unreadable or non-UTF-8 files are skipped
+                let Ok(text) = std::fs::read_to_string(&path) else {
+                    continue;
+                };
+                let lines = search_text(&text, &matcher);
+                if !lines.is_empty() && sender.send(FileMatches { path, lines }).is_err() {
+                    return;
+                }
+            }
+        });
+
+        self.running = Some(RunningSearch { receiver, cancel });
+    }
+
+    /// Stop the running search, keeping the results found so far
+    pub fn cancel(&mut self) {
+        if let Some(running) = self.running.take() {
+            running.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Collect results sent by the search thread since the last frame
+    pub fn poll(&mut self) {
+        let Some(running) = &self.running else {
+            return;
+        };
+        loop {
+            match running.receiver.try_recv() {
+                Ok(file) => self.results.push(file),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.running = None;
+                    break;
+                }
+            }
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    pub fn results(&self) -> &[FileMatches] {
+        &self.results
+    }
+
+    /// Error from compiling the query, e.g. an invalid regex
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Total number of matching lines found so far
+    pub fn line_count(&self) -> usize {
+        self.results.iter().map(|file| file.lines.len()).sum()
+    }
+}
+
+impl Drop for WorkspaceSearch {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_text_reports_lines_and_ranges() {
+        let matcher = Matcher::new("gap", SearchOptions::default()).unwrap();
+        let lines = search_text("# Storage\n\nThe Gap buffer keeps a gap.\nNothing here\n", &matcher);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].line, 2);
+        assert_eq!(lines[0].ranges, vec![4..7, 23..26]);
+    }
+
+    #[test]
+    fn test_workspace_search_streams_results() {
+        let root = std::env::temp_dir().join(format!("rmd-search-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("notes")).unwrap();
+        std::fs::create_dir_all(root.join(".hidden")).unwrap();
+        std::fs::write(root.join("a.md"), "alpha\nneedle one\n").unwrap();
+        std::fs::write(root.join("notes/b.md"), "needle two\n").unwrap();
+        std::fs::write(root.join("notes/c.txt"), "needle in text file\n").unwrap();
+        std::fs::write(root.join(".hidden/d.md"), "needle hidden\n").unwrap();
+
+        let mut search = WorkspaceSearch::new();
+        search.query = "needle".to_string();
+        search.start(&root, &["md".to_string()]);
+        while search.is_running() {
+            search.poll();
+            std::thread::yield_now();
+        }
+
+        let mut found: Vec<_> = search
+            .results()
+            .iter()
+            .map(|file| file.path.strip_prefix(&root).unwrap().to_path_buf())
+            .collect();
+        found.sort();
+        assert_eq!(found, vec![PathBuf::from("a.md"), PathBuf::from("notes/b.md")]);
+        assert_eq!(search.line_count(), 2);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,97 @@
+//! Per-workspace state, such as which folders of the tree are expanded,
+//! kept in the config folder rather than in the workspace
+
+use crate::history::fnv1a;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// What is remembered about a workspace between visits
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceState {
+    /// Workspace the state belongs to, for finding it by hand
+    #[serde(default)]
+    pub root: PathBuf,
+    /// Folders expanded in the sidebar tree, relative to the root
+    #[serde(default)]
+    pub expanded: Vec<PathBuf>,
+}
+
+impl WorkspaceState {
+    /// State of the workspace at `root` with `expanded` folders open, given
+    /// as absolute paths
+    pub fn new(root: &Path, expanded: &HashSet<PathBuf>) -> Self {
+        let mut expanded: Vec<PathBuf> = expanded
+            .iter()
+            .filter_map(|path| path.strip_prefix(root).ok())
+            .map(Path::to_path_buf)
+            .collect();
+        expanded.sort();
+        Self { root: root.to_path_buf(), expanded }
+    }
+
+    /// The expanded folders as absolute paths under the root
+    pub fn expanded_paths(&self) -> HashSet<PathBuf> {
+        self.expanded.iter().map(|path| self.root.join(path)).collect()
+    }
+}
+
+/// One `.toml` file per workspace, named by a hash of its path
+pub struct WorkspaceStateStore {
+    dir: PathBuf,
+}
+
+impl WorkspaceStateStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Store under `config_dir/rmd/workspaces`
+    pub fn open_default() -> anyhow::Result<Self> {
+        Ok(Self::new(crate::config::Config::config_dir()?.join("workspaces")))
+    }
+
+    fn path_for(&self, root: &Path) -> PathBuf {
+        self.dir
+            .join(format!("{:016x}.toml", fnv1a(root.to_string_lossy().as_bytes())))
+    }
+
+    /// State of the workspace at `root`, or a fresh one if none was saved
+    /// or it can't be read
+    pub fn load(&self, root: &Path) -> WorkspaceState {
+        let state: WorkspaceState = std::fs::read_to_string(self.path_for(root))
+            .ok()
+            .and_then(|contents| toml::from_str(&contents).ok())
+            .unwrap_or_default();
+        WorkspaceState { root: root.to_path_buf(), ..state }
+    }
+
+    pub fn save(&self, state: &WorkspaceState) -> anyhow::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        std::fs::write(self.path_for(&state.root), toml::to_string(state)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("rmd-workspaces-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let store = WorkspaceStateStore::new(&dir);
+        let root = Path::new("/notes");
+
+        assert!(store.load(root).expanded.is_empty());
+        let expanded = HashSet::from([root.join("b"), root.join("a/c"), PathBuf::from("/elsewhere")]);
+        store.save(&WorkspaceState::new(root, &expanded)).unwrap();
+        let state = store.load(root);
+        assert_eq!(state.expanded, [Path::new("a/c"), Path::new("b")]);
+        assert_eq!(state.expanded_paths(), HashSet::from([root.join("b"), root.join("a/c")]));
+        assert!(store.load(Path::new("/other")).expanded.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
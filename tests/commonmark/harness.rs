@@ -0,0 +1,128 @@
+//! The examples of the CommonMark spec (0.31.2, in `spec.json`) run through
+//! the HTML exporter with the CommonMark profile.
+//!
+//! The test fails when fewer examples pass than `BASELINE`. Raise it when a
+//! fix makes more of them pass. With `RMD_SPEC_REPORT` set, the examples
+//! that still fail are written to `report.md`, grouped by spec section:
+//!
+//! ```text
+//! RMD_SPEC_REPORT=1 cargo test commonmark
+//! ```
+
+use crate::markdown::export::{markdown_to_html, HtmlOptions};
+use crate::markdown::flavor::Flavor;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+const SPEC: &str = include_str!("spec.json");
+
+/// Examples known to pass. Example 96 is left failing on purpose: a
+/// document starting with `---` opens front matter in every profile.
+const BASELINE: usize = 651;
+
+#[derive(Deserialize)]
+struct Example {
+    markdown: String,
+    html: String,
+    example: usize,
+    section: String,
+}
+
+/// `html` without differences that don't change what it shows: line
+/// breaks between tags, and the anchor ids the exporter gives headings
+fn normalize(html: &str) -> String {
+    let mut normalized = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find("<h") {
+        let (before, tag) = rest.split_at(start);
+        normalized.push_str(before);
+        let is_heading = tag.as_bytes().get(2).is_some_and(|level| (b'1'..=b'6').contains(level));
+        match tag.find('>').filter(|_| is_heading) {
+            Some(end) => {
+                normalized.push_str(&tag[..3]);
+                let attributes = &tag[3..end];
+                if !attributes.trim_start().starts_with("id=") {
+                    normalized.push_str(attributes);
+                }
+                normalized.push('>');
+                rest = &tag[end + 1..];
+            }
+            None => {
+                normalized.push_str(&tag[..2]);
+                rest = &tag[2..];
+            }
+        }
+    }
+    normalized.push_str(rest);
+    normalized.replace(">\n<", "><").trim().to_string()
+}
+
+/// An example with the exporter's HTML, and whether it matches the spec
+type Outcome = (Example, String, bool);
+
+fn run() -> Vec<Outcome> {
+    let examples: Vec<Example> = serde_json::from_str(SPEC).expect("spec.json is valid");
+    let options = HtmlOptions { flavor: Flavor::CommonMark, ..HtmlOptions::default() };
+    examples
+        .into_iter()
+        .map(|example| {
+            let html = markdown_to_html(&example.markdown, options, |_, url| url.to_string());
+            let passed = normalize(&html) == normalize(&example.html);
+            (example, html, passed)
+        })
+        .collect()
+}
+
+/// The failing examples as Markdown, by section in spec order
+fn report(results: &[Outcome]) -> String {
+    let passed = results.iter().filter(|(_, _, passed)| *passed).count();
+    let mut sections: BTreeMap<usize, (&str, usize, Vec<&Outcome>)> = BTreeMap::new();
+    for result in results {
+        let example = &result.0;
+        // Keyed by the section's first example, to keep the spec's order
+        let first = results.iter().find(|(other, _, _)| other.section == example.section).map_or(0, |(other, _, _)| other.example);
+        let entry = sections.entry(first).or_insert((&example.section, 0, Vec::new()));
+        entry.1 += 1;
+        if !result.2 {
+            entry.2.push(result);
+        }
+    }
+
+    let mut report = String::new();
+    let _ = writeln!(report, "# CommonMark spec failures\n");
+    let _ = writeln!(report, "{} of {} examples pass. Generated by `tests/commonmark/harness.rs`.\n", passed, results.len());
+    for (section, total, failures) in sections.values().filter(|(_, _, failures)| !failures.is_empty()) {
+        let _ = writeln!(report, "## {} ({} of {} failing)\n", section, failures.len(), total);
+        for (example, html, _) in failures {
+            let _ = writeln!(report, "### Example {}\n", example.example);
+            let _ = writeln!(report, "````markdown\n{}````\n", example.markdown);
+            let _ = writeln!(report, "Expected:\n\n````html\n{}````\n", example.html);
+            let _ = writeln!(report, "Exported:\n\n````html\n{}````\n", html);
+        }
+    }
+    report
+}
+
+#[test]
+fn test_commonmark_spec() {
+    let results = run();
+    let passed = results.iter().filter(|(_, _, passed)| *passed).count();
+    if std::env::var_os("RMD_SPEC_REPORT").is_some() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/commonmark/report.md");
+        std::fs::write(path, report(&results)).expect("report.md is writable");
+    }
+    assert!(
+        passed >= BASELINE,
+        "{} of {} CommonMark examples pass, fewer than the {} that did; run with RMD_SPEC_REPORT=1 to see which fail",
+        passed,
+        results.len(),
+        BASELINE
+    );
+}
+
+#[test]
+fn test_normalize() {
+    assert_eq!(normalize("<h2 id=\"intro\">Intro</h2>\n<hr />\n"), "<h2>Intro</h2><hr />");
+    assert_eq!(normalize("<html>\n<h1>x</h1>"), "<html><h1>x</h1>");
+}